@@ -33,17 +33,27 @@ pub fn generate_keys_file<P: AsRef<Path>, W: AsRef<[u8]>>(
     pass_phrase: W,
 ) -> Result<PublicKey, Error> {
     let (pk, sk) = gen_keypair();
-    let keys = EncryptedKeys::encrypt(pk, &sk, pass_phrase)?;
+    write_keys_file(path, pk, &sk, pass_phrase)?;
+    Ok(pk)
+}
+
+/// Writes the given keypair into a TOML file encrypting `SecretKey` with the passphrase.
+/// An existing file is overwritten.
+pub fn write_keys_file<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    public_key: PublicKey,
+    secret_key: &SecretKey,
+    pass_phrase: W,
+) -> Result<(), Error> {
+    let keys = EncryptedKeys::encrypt(public_key, secret_key, pass_phrase)?;
     let file_content =
         toml::to_string_pretty(&keys).map_err(|e| Error::new(ErrorKind::Other, e))?;
     let mut open_options = OpenOptions::new();
-    open_options.create(true).write(true);
+    open_options.create(true).write(true).truncate(true);
     #[cfg(unix)]
     open_options.mode(0o_600);
     let mut file = open_options.open(path.as_ref())?;
-    file.write_all(file_content.as_bytes())?;
-
-    Ok(pk)
+    file.write_all(file_content.as_bytes())
 }
 
 /// Reads and returns `PublicKey` and `SecretKey` from encrypted file located by path and returns its.
@@ -153,6 +163,20 @@ mod tests {
         assert_eq!(pk1, pk2);
     }
 
+    #[test]
+    fn test_rewrite_keys_file() {
+        let dir = TempDir::new("test_utils").expect("Couldn't create TempDir");
+        let file_path = dir.path().join("private_key.toml");
+        let pk = generate_keys_file(file_path.as_path(), b"passphrase").unwrap();
+        let (_, sk) = read_keys_from_file(file_path.as_path(), b"passphrase").unwrap();
+
+        write_keys_file(file_path.as_path(), pk, &sk, b"new passphrase").unwrap();
+        assert!(read_keys_from_file(file_path.as_path(), b"passphrase").is_err());
+        let (new_pk, new_sk) = read_keys_from_file(file_path.as_path(), b"new passphrase").unwrap();
+        assert_eq!(pk, new_pk);
+        assert_eq!(sk, new_sk);
+    }
+
     #[test]
     fn test_encrypt_decrypt() {
         let pass_phrase = b"passphrase";
@@ -20,7 +20,9 @@ pub use rocksdb::{BlockBasedOptions as RocksBlockOptions, WriteOptions as RocksD
 
 use std::{fmt, iter::Peekable, mem, path::Path, sync::Arc};
 
-use rocksdb::{self, ColumnFamily, DBIterator, Options as RocksDbOptions, WriteBatch};
+use rocksdb::{
+    self, checkpoint::Checkpoint, ColumnFamily, DBIterator, Options as RocksDbOptions, WriteBatch,
+};
 
 use crate::{
     db::{check_database, Change},
@@ -152,6 +154,15 @@ impl Database for RocksDB {
         w_opts.set_sync(true);
         self.do_merge(patch, &w_opts)
     }
+
+    /// Creates a `RocksDB` checkpoint. The checkpoint is made of hard links to the immutable
+    /// table files of the database if it is located on the same file system, so it is cheap
+    /// to create and takes almost no additional disk space.
+    fn create_checkpoint(&self, path: &Path) -> crate::Result<()> {
+        Checkpoint::new(&self.db)?
+            .create_checkpoint(path)
+            .map_err(Into::into)
+    }
 }
 
 impl Snapshot for RocksDBSnapshot {
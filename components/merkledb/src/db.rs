@@ -25,8 +25,12 @@ use std::{
     iter::{Iterator as StdIterator, Peekable},
     mem,
     ops::{Deref, DerefMut},
+    path::Path,
 };
 
+use byteorder::{ByteOrder, LittleEndian};
+use exonum_crypto::{Hash, HashStream};
+
 use crate::{
     views::{IndexAccess, IndexAddress, View},
     Error, Result,
@@ -223,6 +227,23 @@ impl WorkingPatch {
         }
     }
 
+    /// Returns the addresses of the views changed in the patch, including the views
+    /// that have been cleared.
+    pub(crate) fn changed_addresses(&self) -> Vec<IndexAddress> {
+        self.changes
+            .borrow()
+            .iter()
+            .filter_map(|(address, changes)| {
+                let changes = changes.as_ref()?;
+                if changes.data.is_empty() && !changes.is_empty() {
+                    None
+                } else {
+                    Some(address.clone())
+                }
+            })
+            .collect()
+    }
+
     pub fn clear(&self, address: &IndexAddress) {
         let mut changes = self.changes.borrow_mut();
         let change = changes.entry(address.clone());
@@ -231,36 +252,63 @@ impl WorkingPatch {
     }
 
     // TODO: verify that this method updates `Change`s already in the `Patch` [ECR-2834]
-    fn merge_into(self, patch: &mut Patch) {
+    fn merge_into(self, patch: &mut Patch, mut recorded: Option<&mut HashMap<String, Changes>>) {
         for (address, changes) in self.changes.into_inner() {
             let changes = changes.unwrap_or_else(|| {
                 panic!("changes are still borrowed at address {:?}", address);
             });
+            if let Some(ref mut recorded) = recorded {
+                merge_view_changes(recorded, &address, changes.clone());
+            }
+            merge_view_changes(&mut patch.changes, &address, changes);
+        }
+    }
+}
 
-            let patch_changes = patch
-                .changes
-                .entry(address.name().to_owned())
-                .or_insert_with(Changes::new);
+fn merge_view_changes(
+    target: &mut HashMap<String, Changes>,
+    address: &IndexAddress,
+    changes: ViewChanges,
+) {
+    let patch_changes = target
+        .entry(address.name().to_owned())
+        .or_insert_with(Changes::new);
 
-            if changes.is_empty() {
-                let prefix = address.bytes().map_or(vec![], |bytes| bytes.to_vec());
-                remove_keys_with_prefix(&mut patch_changes.data, &prefix);
+    if changes.is_empty() {
+        let prefix = address.bytes().map_or(vec![], |bytes| bytes.to_vec());
+        remove_keys_with_prefix(&mut patch_changes.data, &prefix);
 
-                // Remember the prefix to be dropped from the database
-                patch_changes.prefixes_to_remove.push(prefix);
-            }
+        // Remember the prefix to be dropped from the database
+        patch_changes.prefixes_to_remove.push(prefix);
+    }
 
-            if address.bytes().is_none() {
-                patch_changes.data.extend(changes.data);
-            } else {
-                patch_changes.data.extend(
-                    changes
-                        .data
-                        .into_iter()
-                        .map(|(key, value)| (address.keyed(&key).1.into_owned(), value)),
-                );
-            }
+    if address.bytes().is_none() {
+        patch_changes.data.extend(changes.data);
+    } else {
+        patch_changes.data.extend(
+            changes
+                .data
+                .into_iter()
+                .map(|(key, value)| (address.keyed(&key).1.into_owned(), value)),
+        );
+    }
+}
+
+/// Applies the changes to the target, as if they were flushed after the changes
+/// already present in the target.
+fn apply_changes(target: &mut HashMap<String, Changes>, changes: &HashMap<String, Changes>) {
+    for (name, changes) in changes {
+        let target_changes = target.entry(name.to_owned()).or_insert_with(Changes::new);
+        for prefix in &changes.prefixes_to_remove {
+            remove_keys_with_prefix(&mut target_changes.data, prefix);
+            target_changes.prefixes_to_remove.push(prefix.clone());
         }
+        target_changes.data.extend(
+            changes
+                .data
+                .iter()
+                .map(|(key, change)| (key.clone(), change.clone())),
+        );
     }
 }
 
@@ -354,6 +402,35 @@ pub enum Change {
 pub struct Fork {
     patch: Patch,
     working_patch: WorkingPatch,
+    recorded: Option<HashMap<String, Changes>>,
+}
+
+/// Changes flushed into a fork while recording. See [`Fork::start_recording`].
+///
+/// [`Fork::start_recording`]: struct.Fork.html#method.start_recording
+#[derive(Debug, Clone, Default)]
+pub struct Changeset {
+    changes: HashMap<String, Changes>,
+}
+
+impl Changeset {
+    /// Returns an iterator over the changes keyed by the index name.
+    pub fn iter(&self) -> HmIter<String, Changes> {
+        self.changes.iter()
+    }
+
+    /// Returns `true` if the changeset contains no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the hash of the changes in the changeset. The hash is calculated
+    /// in the same way as [`Patch::changes_hash`].
+    ///
+    /// [`Patch::changes_hash`]: struct.Patch.html#method.changes_hash
+    pub fn changes_hash(&self) -> Hash {
+        hash_changes(&self.changes)
+    }
 }
 
 /// A set of serial changes that should be applied to a storage atomically.
@@ -427,6 +504,7 @@ pub trait Database: Send + Sync + 'static {
                 changes: HashMap::new(),
             },
             working_patch: WorkingPatch::new(),
+            recorded: None,
         }
     }
 
@@ -453,6 +531,21 @@ pub trait Database: Send + Sync + 'static {
     /// will be returned. In case of an error, the method guarantees no changes are applied to
     /// the database.
     fn merge_sync(&self, patch: Patch) -> Result<()>;
+
+    /// Writes a consistent copy of the current database state to the specified path,
+    /// which should not exist. The copy can be opened by other processes while the database
+    /// is in use, and changes to the copy do not affect the database.
+    ///
+    /// # Errors
+    ///
+    /// The default implementation returns an error, since not every backend can create
+    /// copies of its data.
+    fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        Err(Error::new(format!(
+            "Database does not support checkpoints, cannot create one at {}",
+            path.display()
+        )))
+    }
 }
 
 /// A read-only snapshot of a storage backend.
@@ -501,6 +594,50 @@ impl Patch {
     pub fn iter(&self) -> HmIter<String, Changes> {
         self.changes.iter()
     }
+
+    /// Returns the hash of the changes in the patch.
+    ///
+    /// The changes are hashed ordered by the index names and keys, so the hash
+    /// does not depend on the order in which the changes were made. Unlike state
+    /// hashes, the hash covers all the changed keys, including the ones in the indexes
+    /// not aggregated into the state hash.
+    pub fn changes_hash(&self) -> Hash {
+        hash_changes(&self.changes)
+    }
+}
+
+fn hash_changes(changes: &HashMap<String, Changes>) -> Hash {
+    let mut names: Vec<_> = changes.keys().collect();
+    names.sort();
+
+    let mut stream = HashStream::new();
+    for name in names {
+        let changes = &changes[name];
+        stream = hash_chunk(stream, name.as_bytes());
+        stream = hash_len(stream, changes.prefixes_to_remove.len());
+        for prefix in &changes.prefixes_to_remove {
+            stream = hash_chunk(stream, prefix);
+        }
+        stream = hash_len(stream, changes.data.len());
+        for (key, change) in &changes.data {
+            stream = hash_chunk(stream, key);
+            stream = match change {
+                Change::Put(value) => hash_chunk(stream.update(&[1]), value),
+                Change::Delete => stream.update(&[0]),
+            };
+        }
+    }
+    stream.hash()
+}
+
+fn hash_len(stream: HashStream, len: usize) -> HashStream {
+    let mut len_bytes = [0; 8];
+    LittleEndian::write_u64(&mut len_bytes, len as u64);
+    stream.update(&len_bytes)
+}
+
+fn hash_chunk(stream: HashStream, chunk: &[u8]) -> HashStream {
+    hash_len(stream, chunk.len()).update(chunk)
 }
 
 impl Snapshot for Patch {
@@ -543,12 +680,68 @@ impl Snapshot for Patch {
 }
 
 impl Fork {
+    /// Creates an empty fork on top of the provided snapshot.
+    ///
+    /// The fork reads the data absent in its changes from the snapshot, which makes it
+    /// possible to execute changes against a custom view of the storage, e.g., a patch
+    /// which has not been merged into the database yet or a snapshot wrapper tracking
+    /// the accessed data. The patch of the fork contains only the changes made in it.
+    pub fn from_snapshot(snapshot: Box<dyn Snapshot>) -> Self {
+        Self {
+            patch: Patch {
+                snapshot,
+                changes: HashMap::new(),
+            },
+            working_patch: WorkingPatch::new(),
+            recorded: None,
+        }
+    }
+
     /// Finalizes all changes that were made after previous execution of the `flush` method.
     /// If no `flush` method had been called before, finalizes all changes that were
     /// made after creation of `Fork`.
     pub fn flush(&mut self) {
         let working_patch = mem::replace(&mut self.working_patch, WorkingPatch::new());
-        working_patch.merge_into(&mut self.patch);
+        working_patch.merge_into(&mut self.patch, self.recorded.as_mut());
+    }
+
+    /// Starts recording of the changes flushed into the fork. The changes are recorded
+    /// until [`stop_recording`] is called; the changes rolled back are not recorded.
+    ///
+    /// The recorded changes can be applied to another fork with [`apply`], which leaves
+    /// it in the same state as if the changes were made in it, provided that the state
+    /// of the fork matches the state of this fork at the start of the recording.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fork contains unflushed changes.
+    ///
+    /// [`stop_recording`]: #method.stop_recording
+    /// [`apply`]: #method.apply
+    pub fn start_recording(&mut self) {
+        assert!(!self.is_dirty(), "cannot start recording of a dirty fork");
+        self.recorded = Some(HashMap::new());
+    }
+
+    /// Stops recording of the changes and returns the changes flushed since the call
+    /// to [`start_recording`], or `None` if the recording has not been started.
+    ///
+    /// [`start_recording`]: #method.start_recording
+    pub fn stop_recording(&mut self) -> Option<Changeset> {
+        self.recorded.take().map(|changes| Changeset { changes })
+    }
+
+    /// Applies the changes recorded in another fork to this fork.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fork contains unflushed changes.
+    pub fn apply(&mut self, changeset: &Changeset) {
+        assert!(!self.is_dirty(), "cannot apply changes to a dirty fork");
+        apply_changes(&mut self.patch.changes, &changeset.changes);
+        if let Some(ref mut recorded) = self.recorded {
+            apply_changes(recorded, &changeset.changes);
+        }
     }
 
     /// Rolls back all changes that were made after the latest execution
@@ -607,6 +800,7 @@ impl From<Patch> for Fork {
         Self {
             patch,
             working_patch: WorkingPatch::new(),
+            recorded: None,
         }
     }
 }
@@ -0,0 +1,260 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Raw access to the indexes stored in the database.
+//!
+//! This module is intended for debugging, e.g., for diagnosing the divergence of
+//! the state on different nodes. It exposes the keys and values of the indexes
+//! as they are stored, bypassing the typed index interfaces; the layout of the stored
+//! data is not a part of the public API and may change between releases.
+
+use std::{collections::HashSet, fmt};
+
+use crate::views::{registered_index, registered_indexes, IndexAddress, IndexType, View};
+use crate::{Fork, Snapshot};
+
+/// Separator between the name and the family identifier in fully qualified index names.
+const FAMILY_SEPARATOR: u8 = 0;
+
+/// Index created in the database.
+#[derive(Clone, PartialEq)]
+pub struct IndexInfo {
+    name: String,
+    family_id: Option<Vec<u8>>,
+    index_type: IndexType,
+    address: IndexAddress,
+}
+
+impl IndexInfo {
+    fn new(qualified_name: &[u8], index_type: IndexType, address: IndexAddress) -> Self {
+        // Index names consist of ASCII characters, so the first zero byte separates
+        // the name from the family identifier.
+        let (name, family_id) = match qualified_name
+            .iter()
+            .position(|&byte| byte == FAMILY_SEPARATOR)
+        {
+            Some(pos) => (
+                &qualified_name[..pos],
+                Some(qualified_name[pos + 1..].to_vec()),
+            ),
+            None => (qualified_name, None),
+        };
+        Self {
+            name: String::from_utf8_lossy(name).into_owned(),
+            family_id,
+            index_type,
+            address,
+        }
+    }
+
+    /// Returns the name of the index.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the serialized family identifier of the index, or `None` if the index
+    /// does not belong to a family.
+    pub fn family_id(&self) -> Option<&[u8]> {
+        self.family_id.as_ref().map(Vec::as_slice)
+    }
+
+    /// Returns the type of the index.
+    pub fn index_type(&self) -> IndexType {
+        self.index_type
+    }
+
+    /// Returns the number of the entries stored by the index. The entries are counted
+    /// one by one, so the call takes time proportional to the size of the index.
+    ///
+    /// Merkelized indexes store the nodes of their trees along with the values,
+    /// so the number of the entries exceeds the number of the elements of such indexes.
+    pub fn entries_count(&self, snapshot: &dyn Snapshot) -> u64 {
+        self.view(snapshot).iter::<_, Vec<u8>, Vec<u8>>(&()).count() as u64
+    }
+
+    /// Returns at most `limit` entries of the index, starting from the key `from`.
+    pub fn entries(
+        &self,
+        snapshot: &dyn Snapshot,
+        from: &[u8],
+        limit: usize,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.view(snapshot)
+            .iter_from::<_, _, Vec<u8>, Vec<u8>>(&(), from)
+            .take(limit)
+            .collect()
+    }
+
+    /// Returns the value stored by the index for the key.
+    pub fn get(&self, snapshot: &dyn Snapshot, key: &[u8]) -> Option<Vec<u8>> {
+        self.view(snapshot).get(key)
+    }
+
+    fn view<'a>(&self, snapshot: &'a dyn Snapshot) -> View<&'a dyn Snapshot> {
+        View::new(snapshot, self.address.clone())
+    }
+}
+
+impl fmt::Debug for IndexInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IndexInfo")
+            .field("name", &self.name)
+            .field("family_id", &self.family_id)
+            .field("index_type", &self.index_type)
+            .finish()
+    }
+}
+
+/// Returns all indexes created in the database, in the lexicographic order
+/// of their names.
+pub fn indexes(snapshot: &dyn Snapshot) -> Vec<IndexInfo> {
+    registered_indexes(snapshot)
+        .into_iter()
+        .map(|(qualified_name, index_type, address)| {
+            IndexInfo::new(&qualified_name, index_type, address)
+        })
+        .collect()
+}
+
+/// Returns the index with the given name and family identifier, or `None` if
+/// the index has not been created.
+pub fn find_index(
+    snapshot: &dyn Snapshot,
+    name: &str,
+    family_id: Option<&[u8]>,
+) -> Option<IndexInfo> {
+    let address = match family_id {
+        Some(family_id) => IndexAddress::with_root(name).append_bytes(family_id),
+        None => IndexAddress::with_root(name),
+    };
+    let qualified_name = address.fully_qualified_name();
+    registered_index(snapshot, &qualified_name)
+        .map(|(index_type, address)| IndexInfo::new(&qualified_name, index_type, address))
+}
+
+/// Returns the indexes whose contents have been changed in the fork since the last flush,
+/// in the lexicographic order of their names. Creating an index or reading it through
+/// the fork does not count as a change.
+pub fn modified_indexes(fork: &Fork) -> Vec<IndexInfo> {
+    let data_addresses = fork
+        .working_patch()
+        .changed_addresses()
+        .into_iter()
+        .collect::<HashSet<_>>();
+    if data_addresses.is_empty() {
+        return Vec::new();
+    }
+
+    registered_indexes(fork)
+        .into_iter()
+        .filter(|(_, _, address)| data_addresses.contains(address))
+        .map(|(qualified_name, index_type, address)| {
+            IndexInfo::new(&qualified_name, index_type, address)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BinaryValue, Database, Entry, ListIndex, MapIndex, TemporaryDB};
+
+    #[test]
+    fn raw_entries_of_indexes() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        {
+            let mut map: MapIndex<_, u8, String> = MapIndex::new("map", &fork);
+            map.put(&1, "one".to_owned());
+            map.put(&2, "two".to_owned());
+            let mut list: ListIndex<_, u64> = ListIndex::new_in_family("list", &3_u8, &fork);
+            list.push(10);
+        }
+        db.merge(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+
+        let all_indexes = indexes(snapshot.as_ref());
+        let names = all_indexes
+            .iter()
+            .map(|index| (index.name(), index.family_id(), index.index_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("list", Some(&[3_u8][..]), IndexType::List),
+                ("map", None, IndexType::Map),
+            ]
+        );
+
+        let map = find_index(snapshot.as_ref(), "map", None).unwrap();
+        assert_eq!(map.entries_count(snapshot.as_ref()), 2);
+        assert_eq!(
+            map.entries(snapshot.as_ref(), &[], 10),
+            vec![(vec![1], b"one".to_vec()), (vec![2], b"two".to_vec()),]
+        );
+        assert_eq!(
+            map.entries(snapshot.as_ref(), &[2], 10),
+            vec![(vec![2], b"two".to_vec())]
+        );
+        assert_eq!(map.entries(snapshot.as_ref(), &[], 1).len(), 1);
+        assert_eq!(map.get(snapshot.as_ref(), &[2]), Some(b"two".to_vec()));
+        assert_eq!(map.get(snapshot.as_ref(), &[3]), None);
+
+        let list = find_index(snapshot.as_ref(), "list", Some(&[3])).unwrap();
+        assert_eq!(list.entries_count(snapshot.as_ref()), 1);
+        assert_eq!(
+            list.get(snapshot.as_ref(), &[0; 8]),
+            Some(10_u64.to_bytes())
+        );
+
+        assert!(find_index(snapshot.as_ref(), "list", None).is_none());
+        assert!(find_index(snapshot.as_ref(), "unknown", None).is_none());
+    }
+
+    #[test]
+    fn modified_indexes_of_fork() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        MapIndex::new("map", &fork).put(&1_u8, "one".to_owned());
+        ListIndex::new("list", &fork).push(1_u64);
+        db.merge(fork.into_patch()).unwrap();
+
+        let mut fork = db.fork();
+        assert!(modified_indexes(&fork).is_empty());
+        {
+            let map: MapIndex<_, u8, String> = MapIndex::new("map", &fork);
+            assert_eq!(map.get(&1), Some("one".to_owned()));
+            let mut list: ListIndex<_, u64> = ListIndex::new("list", &fork);
+            list.push(2);
+            Entry::new("entry", &fork).set(3_u64);
+        }
+        let names = modified_indexes(&fork)
+            .iter()
+            .map(|index| index.name().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["entry".to_owned(), "list".to_owned()]);
+        // Reading an index that does not exist creates it, but does not change it.
+        let _ = MapIndex::<_, u8, String>::new("unknown", &fork).get(&1);
+        assert_eq!(modified_indexes(&fork).len(), 2);
+
+        fork.flush();
+        assert!(modified_indexes(&fork).is_empty());
+        MapIndex::<_, u8, String>::new("map", &fork).clear();
+        let names = modified_indexes(&fork)
+            .iter()
+            .map(|index| index.name().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["map".to_owned()]);
+    }
+}
@@ -131,8 +131,8 @@ pub use self::proof_map_index::{MapProof, ProofMapIndex};
 pub use self::{
     backends::{rocksdb::RocksDB, temporarydb::TemporaryDB},
     db::{
-        Change, Changes, ChangesIterator, Database, Fork, Iter, Iterator, Patch, PatchIterator,
-        Snapshot,
+        Change, Changes, ChangesIterator, Changeset, Database, Fork, Iter, Iterator, Patch,
+        PatchIterator, Snapshot,
     },
     entry::Entry,
     error::Error,
@@ -146,7 +146,7 @@ pub use self::{
     sparse_list_index::SparseListIndex,
     value_set_index::ValueSetIndex,
     values::BinaryValue,
-    views::{IndexAccess, IndexAddress, IndexBuilder, ObjectAccess, Ref, RefMut, View},
+    views::{IndexAccess, IndexAddress, IndexBuilder, IndexType, ObjectAccess, Ref, RefMut, View},
 };
 
 #[macro_use]
@@ -167,6 +167,7 @@ mod options;
 mod values;
 mod views;
 
+pub mod inspect;
 pub mod key_set_index;
 pub mod list_index;
 pub mod map_index;
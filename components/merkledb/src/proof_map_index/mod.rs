@@ -428,6 +428,67 @@ where
         )
     }
 
+    /// Returns paths and hashes of the children of the tree node with the given path,
+    /// or the path and hash of the root node if `path` is `None`. The hash of a leaf
+    /// node is [`HashTag::hash_leaf`] of its serialized value.
+    ///
+    /// Leaf nodes and nodes absent from the tree have no children. Descending the trees
+    /// of two maps and following only the nodes with different hashes locates
+    /// the differing entries of the maps without reading the equal subtrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{TemporaryDB, Database, ProofMapIndex};
+    /// use exonum_crypto::hash;
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = ProofMapIndex::new("index", &fork);
+    /// index.put(&hash(&[1]), 1_u8);
+    /// index.put(&hash(&[2]), 2_u8);
+    ///
+    /// let root = index.child_nodes(None);
+    /// assert_eq!(root.len(), 1);
+    /// let leaves = index.child_nodes(Some(&root[0].0));
+    /// assert!(leaves.iter().all(|(path, _)| path.is_leaf()));
+    /// assert_eq!(leaves.len(), 2);
+    /// ```
+    ///
+    /// [`HashTag::hash_leaf`]: ../enum.HashTag.html#method.hash_leaf
+    pub fn child_nodes(&self, path: Option<&ProofPath>) -> Vec<(ProofPath, Hash)> {
+        let path = match path {
+            Some(path) => path,
+            None => {
+                return self
+                    .get_root_node()
+                    .map(|(path, node)| match node {
+                        Node::Leaf(hash) => vec![(path, hash)],
+                        Node::Branch(branch) => vec![(path, branch.object_hash())],
+                    })
+                    .unwrap_or_default();
+            }
+        };
+        if path.is_leaf() {
+            return Vec::new();
+        }
+        self.base
+            .get::<_, BranchNode>(path)
+            .map(|branch| {
+                vec![
+                    (
+                        branch.child_path(ChildKind::Left),
+                        branch.child_hash(ChildKind::Left),
+                    ),
+                    (
+                        branch.child_path(ChildKind::Right),
+                        branch.child_hash(ChildKind::Right),
+                    ),
+                ]
+            })
+            .unwrap_or_default()
+    }
+
     /// Returns an iterator over the entries of the map in ascending order. The iterator element
     /// type is `(K::Output, V)`.
     ///
@@ -1475,3 +1475,37 @@ fn test_tree_with_hashed_key() {
         hash_isolated_node(&ProofPath::new(&other_key), &HashTag::hash_leaf(&[1, 2, 3]))
     );
 }
+
+#[test]
+fn test_child_nodes() {
+    let db = TemporaryDB::default();
+    let fork = db.fork();
+    let mut index = ProofMapIndex::new(IDX_NAME, &fork);
+    assert!(index.child_nodes(None).is_empty());
+
+    let data = generate_random_data(50);
+    for (key, value) in &data {
+        index.put(key, value.clone());
+    }
+    let root = index.child_nodes(None);
+    assert_eq!(root.len(), 1);
+    assert_eq!(HashTag::hash_map_node(root[0].1), index.object_hash());
+
+    // Descending the whole tree reveals every entry exactly once.
+    let mut leaves = Vec::new();
+    let mut nodes = root;
+    while let Some((path, hash)) = nodes.pop() {
+        if path.is_leaf() {
+            assert!(index.child_nodes(Some(&path)).is_empty());
+            leaves.push((path, hash));
+        } else {
+            let children = index.child_nodes(Some(&path));
+            assert_eq!(children.len(), 2);
+            nodes.extend(children);
+        }
+    }
+    assert_eq!(leaves.len(), data.len());
+    for (key, value) in &data {
+        assert!(leaves.contains(&(ProofPath::new(key), HashTag::hash_leaf(value))));
+    }
+}
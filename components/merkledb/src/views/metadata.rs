@@ -190,6 +190,34 @@ where
     (index_address, index_state)
 }
 
+/// Returns the fully qualified names of all indexes created in the database along with
+/// their types and the addresses of their data, in the lexicographic order of the names.
+pub(crate) fn registered_indexes<T: IndexAccess>(
+    index_access: T,
+) -> Vec<(Vec<u8>, IndexType, IndexAddress)> {
+    let pool = IndexesPool::new(index_access);
+    pool.0
+        .iter::<_, Vec<u8>, Vec<u8>>(&())
+        // The empty key holds the size of the pool.
+        .filter(|(index_name, _)| !index_name.is_empty())
+        .filter_map(|(index_name, bytes)| {
+            let metadata = IndexMetadata::<()>::from_bytes(bytes.into()).ok()?;
+            Some((index_name, metadata.index_type, metadata.index_address()))
+        })
+        .collect()
+}
+
+/// Returns the type of the index with the given fully qualified name and the address
+/// of its data, or `None` if the index has not been created.
+pub(crate) fn registered_index<T: IndexAccess>(
+    index_access: T,
+    index_name: &[u8],
+) -> Option<(IndexType, IndexAddress)> {
+    IndexesPool::new(index_access)
+        .index_metadata::<()>(index_name)
+        .map(|metadata| (metadata.index_type, metadata.index_address()))
+}
+
 /// Persistent pool used to store indexes metadata in the database.
 /// Pool size is used as an identifier of newly created indexes.
 struct IndexesPool<T: IndexAccess>(View<T>);
@@ -14,6 +14,7 @@
 
 #![warn(missing_docs)]
 
+pub(crate) use self::metadata::{registered_index, registered_indexes};
 pub use self::{
     metadata::{BinaryAttribute, IndexState, IndexType},
     refs::{AnyObject, ObjectAccess, Ref, RefMut},
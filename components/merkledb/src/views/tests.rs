@@ -374,6 +374,40 @@ fn test_database_check_incorrect_version() {
     RocksDB::open(&dir, &opts).unwrap();
 }
 
+#[test]
+fn test_rocksdb_checkpoint() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let checkpoint_path = dir.path().join("checkpoint");
+    let db = RocksDB::open(dir.path().join("db"), &DbOptions::default()).unwrap();
+
+    let fork = db.fork();
+    View::new(&fork, IDX_NAME).put(&vec![1], vec![1]);
+    db.merge(fork.into_patch()).unwrap();
+    db.create_checkpoint(&checkpoint_path).unwrap();
+
+    // Changes made after the checkpoint are not visible in it and vice versa.
+    let fork = db.fork();
+    View::new(&fork, IDX_NAME).put(&vec![2], vec![2]);
+    db.merge(fork.into_patch()).unwrap();
+
+    let checkpoint = RocksDB::open(&checkpoint_path, &DbOptions::default()).unwrap();
+    let fork = checkpoint.fork();
+    View::new(&fork, IDX_NAME).put(&vec![3], vec![3]);
+    checkpoint.merge(fork.into_patch()).unwrap();
+
+    let snapshot = checkpoint.snapshot();
+    let view = View::new(&snapshot, IDX_NAME);
+    assert_eq!(view.get_bytes(&[1]), Some(vec![1]));
+    assert_eq!(view.get_bytes(&[2]), None);
+    let snapshot = db.snapshot();
+    assert_eq!(View::new(&snapshot, IDX_NAME).get_bytes(&[3]), None);
+
+    // Checkpoints are not written over existing paths.
+    assert!(db.create_checkpoint(&checkpoint_path).is_err());
+    let tmp_path = dir.path().join("tmp");
+    assert!(TemporaryDB::new().create_checkpoint(&tmp_path).is_err());
+}
+
 #[test]
 fn fork_iter() {
     _fork_iter(&TemporaryDB::new(), IDX_NAME);
@@ -986,3 +1020,120 @@ fn fork_from_patch() {
     db.merge(fork.into_patch())
         .expect("Fork created from patch should be merged successfully");
 }
+
+#[test]
+fn patch_changes_hash() {
+    let db = TemporaryDB::new();
+    let changes_hash = |values: &[(&str, u8)]| {
+        let fork = db.fork();
+        for (name, value) in values {
+            let mut index = ListIndex::new(*name, &fork);
+            index.push(*value);
+        }
+        fork.into_patch().changes_hash()
+    };
+
+    let hash = changes_hash(&[("first", 1), ("second", 2)]);
+    assert_eq!(hash, changes_hash(&[("second", 2), ("first", 1)]));
+    assert_ne!(hash, changes_hash(&[("first", 1), ("second", 3)]));
+    assert_ne!(hash, changes_hash(&[("first", 1)]));
+    assert_eq!(
+        db.fork().into_patch().changes_hash(),
+        db.fork().into_patch().changes_hash()
+    );
+}
+
+#[test]
+fn recorded_changes_are_applied() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    ListIndex::new("cleared", &fork).extend(vec![1_u8, 2, 3]);
+    db.merge(fork.into_patch()).unwrap();
+
+    let prepare = || {
+        let mut fork = db.fork();
+        ListIndex::new("list", &fork).push(1_u8);
+        fork.flush();
+        fork
+    };
+
+    let mut fork = prepare();
+    fork.start_recording();
+    ListIndex::new("list", &fork).push(2_u8);
+    MapIndex::new("map", &fork).put(&1_u8, 10_u8);
+    fork.flush();
+    ListIndex::new("list", &fork).push(3_u8);
+    fork.rollback();
+    {
+        let mut cleared = ListIndex::new("cleared", &fork);
+        cleared.clear();
+        cleared.push(5_u8);
+    }
+    fork.flush();
+    let changeset = fork.stop_recording().unwrap();
+    assert!(fork.stop_recording().is_none());
+
+    let mut other_fork = prepare();
+    other_fork.apply(&changeset);
+    let patch = fork.into_patch();
+    let other_patch = other_fork.into_patch();
+    assert_eq!(patch.changes_hash(), other_patch.changes_hash());
+
+    db.merge(other_patch).unwrap();
+    let snapshot = db.snapshot();
+    let list: ListIndex<_, u8> = ListIndex::new("list", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2]);
+    let map: MapIndex<_, u8, u8> = MapIndex::new("map", &snapshot);
+    assert_eq!(map.get(&1), Some(10));
+    let cleared: ListIndex<_, u8> = ListIndex::new("cleared", &snapshot);
+    assert_eq!(cleared.iter().collect::<Vec<_>>(), vec![5]);
+}
+
+#[test]
+fn changeset_changes_hash() {
+    let db = TemporaryDB::new();
+    let changes_hash = |value: u8| {
+        let mut fork = db.fork();
+        ListIndex::new("list", &fork).push(1_u8);
+        fork.flush();
+        fork.start_recording();
+        ListIndex::new("list", &fork).push(value);
+        fork.flush();
+        fork.stop_recording().unwrap().changes_hash()
+    };
+
+    // Only the recorded changes are hashed.
+    assert_eq!(changes_hash(2), changes_hash(2));
+    assert_ne!(changes_hash(2), changes_hash(3));
+    let fork = db.fork();
+    ListIndex::new("list", &fork).extend(vec![1_u8, 2]);
+    assert_ne!(changes_hash(2), fork.into_patch().changes_hash());
+}
+
+#[test]
+fn fork_from_snapshot() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    ListIndex::new("committed", &fork).push(1_u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let fork = db.fork();
+    ListIndex::new("pending", &fork).push(2_u8);
+    let fork = Fork::from_snapshot(Box::new(fork.into_patch()));
+    ListIndex::new("pending", &fork).push(3_u8);
+    MapIndex::new("map", &fork).put(&1_u8, 10_u8);
+
+    let committed: ListIndex<_, u8> = ListIndex::new("committed", &fork);
+    assert_eq!(committed.iter().collect::<Vec<_>>(), vec![1]);
+    let pending: ListIndex<_, u8> = ListIndex::new("pending", &fork);
+    assert_eq!(pending.iter().collect::<Vec<_>>(), vec![2, 3]);
+
+    let patch = fork.into_patch();
+    let changed = patch
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert!(changed.contains(&"pending"));
+    assert!(changed.contains(&"map"));
+    assert!(!changed.contains(&"committed"));
+}
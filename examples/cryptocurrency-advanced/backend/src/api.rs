@@ -24,7 +24,7 @@ use exonum::{
     helpers::Height,
 };
 
-use crate::{wallet::Wallet, Schema, CRYPTOCURRENCY_SERVICE_ID};
+use crate::{schema::WALLETS, wallet::Wallet, Schema};
 
 /// Describes the query parameters for the `get_wallet` endpoint.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -79,8 +79,7 @@ impl PublicApi {
             .block_and_precommits(Height(max_height))
             .unwrap();
 
-        let to_table: MapProof<Hash, Hash> =
-            general_schema.get_proof_to_service_table(CRYPTOCURRENCY_SERVICE_ID, 0);
+        let to_table: MapProof<Hash, Hash> = general_schema.get_proof_to_service_table(WALLETS);
 
         let to_wallet: MapProof<PublicKey, Wallet> =
             currency_schema.wallets().get_proof(query.pub_key);
@@ -20,6 +20,9 @@ use exonum::crypto::{Hash, PublicKey};
 
 use crate::{wallet::Wallet, INITIAL_BALANCE};
 
+/// Name of the `ProofMapIndex` with wallets.
+pub const WALLETS: &str = "cryptocurrency.wallets";
+
 /// Database schema for the cryptocurrency.
 #[derive(Debug)]
 pub struct Schema<T> {
@@ -43,7 +46,7 @@ where
 
     /// Returns `ProofMapIndex` with wallets.
     pub fn wallets(&self) -> ProofMapIndex<T, PublicKey, Wallet> {
-        ProofMapIndex::new("cryptocurrency.wallets", self.access.clone())
+        ProofMapIndex::new(WALLETS, self.access.clone())
     }
 
     /// Returns history of the wallet with the given public key.
@@ -65,6 +68,11 @@ where
         vec![self.wallets().object_hash()]
     }
 
+    /// Returns the state hash of cryptocurrency service together with the table names.
+    pub fn state_hash_tables(&self) -> Vec<(String, Hash)> {
+        vec![(WALLETS.to_owned(), self.wallets().object_hash())]
+    }
+
     /// Increase balance of the wallet and append new record to its history.
     ///
     /// Panics if there is no wallet with given public key.
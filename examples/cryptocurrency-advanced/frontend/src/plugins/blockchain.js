@@ -8,9 +8,19 @@ const SERVICE_ID = 128
 const TX_TRANSFER_ID = 0
 const TX_ISSUE_ID = 1
 const TX_WALLET_ID = 2
-const TABLE_INDEX = 0
+const TABLE_NAME = 'cryptocurrency.wallets'
 const Wallet = Exonum.newType(proto.exonum.examples.cryptocurrency_advanced.Wallet)
 
+// Extracts the root hash of the table with the given name from the proof to the state hash
+function verifyTable(proof, stateHash, tableName) {
+  const tableProof = new Exonum.MapProof(proof, Exonum.Hash, Exonum.Hash)
+  if (tableProof.merkleRoot !== stateHash) {
+    throw new Error('Table proof is corrupted')
+  }
+  const tableKey = Exonum.hash(new TextEncoder().encode(tableName))
+  return tableProof.entries.get(tableKey)
+}
+
 function TransferTransaction(publicKey) {
   return Exonum.newTransaction({
     author: publicKey,
@@ -115,7 +125,7 @@ module.exports = {
             .then(data => {
               return Exonum.verifyBlock(data.block_proof, validators).then(() => {
                 // verify table timestamps in the root tree
-                const tableRootHash = Exonum.verifyTable(data.wallet_proof.to_table, data.block_proof.block.state_hash, SERVICE_ID, TABLE_INDEX)
+                const tableRootHash = verifyTable(data.wallet_proof.to_table, data.block_proof.block.state_hash, TABLE_NAME)
 
                 // find wallet in the tree of all wallets
                 const walletProof = new Exonum.MapProof(data.wallet_proof.to_wallet, Exonum.PublicKey, Wallet)
@@ -23,10 +23,7 @@ fn node_config() -> NodeConfig {
     let (consensus_public_key, consensus_secret_key) = exonum::crypto::gen_keypair();
     let (service_public_key, service_secret_key) = exonum::crypto::gen_keypair();
 
-    let validator_keys = ValidatorKeys {
-        consensus_key: consensus_public_key,
-        service_key: service_public_key,
-    };
+    let validator_keys = ValidatorKeys::new(consensus_public_key, service_public_key);
     let genesis = GenesisConfig::new(vec![validator_keys].into_iter());
 
     let api_address = "0.0.0.0:8000".parse().unwrap();
@@ -52,6 +49,16 @@ fn node_config() -> NodeConfig {
         services_configs: Default::default(),
         database: Default::default(),
         thread_pool_size: Default::default(),
+        genesis_state: None,
+        retention: Default::default(),
+        observer_address: None,
+        integrity_check_depth: None,
+        clock: Default::default(),
+        isolated_services: Vec::new(),
+        record_patch_hashes: false,
+        alerts: Vec::new(),
+        resources: Default::default(),
+        consensus_archive: None,
     }
 }
 
@@ -451,7 +451,7 @@ pub mod service {
         // for now, so we return an empty vector.
         //
         // [merkle]: https://exonum.com/doc/version/latest/architecture/storage/#merkelized-indices
-        fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
             vec![]
         }
 
@@ -21,10 +21,7 @@ use exonum::{
     crypto::Hash,
 };
 
-use crate::{
-    schema::{Schema, TimestampEntry},
-    TIMESTAMPING_SERVICE,
-};
+use crate::schema::{Schema, TimestampEntry, TIMESTAMPS};
 
 /// Describes query parameters for `handle_timestamp` and `handle_timestamp_proof` endpoints.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -76,7 +73,7 @@ impl PublicApi {
             let core_schema = blockchain::Schema::new(&snapshot);
             let last_block_height = state.blockchain().last_block().height();
             let block_proof = core_schema.block_and_precommits(last_block_height).unwrap();
-            let state_proof = core_schema.get_proof_to_service_table(TIMESTAMPING_SERVICE, 0);
+            let state_proof = core_schema.get_proof_to_service_table(TIMESTAMPS);
             (state_proof, block_proof)
         };
         let schema = Schema::new(&snapshot);
@@ -41,7 +41,7 @@ use exonum_merkledb::Snapshot;
 
 use exonum::{
     api::ServiceApiBuilder,
-    blockchain::{self, Transaction, TransactionSet},
+    blockchain::{self, Namespace, Transaction, TransactionSet},
     crypto::Hash,
     helpers::fabric,
     messages::RawTransaction,
@@ -65,9 +65,9 @@ impl blockchain::Service for Service {
         SERVICE_NAME
     }
 
-    fn state_hash(&self, view: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, view: &dyn Snapshot) -> Vec<(String, Hash)> {
         let schema = Schema::new(view);
-        schema.state_hash()
+        schema.state_hash_tables()
     }
 
     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
@@ -75,6 +75,10 @@ impl blockchain::Service for Service {
         Ok(tx.into())
     }
 
+    fn namespace(&self) -> Option<Namespace> {
+        Some(Namespace::new(SERVICE_NAME))
+    }
+
     fn wire_api(&self, builder: &mut ServiceApiBuilder) {
         PublicApi::wire(builder);
     }
@@ -21,6 +21,9 @@ use exonum_merkledb::{IndexAccess, ObjectHash, ProofMapIndex};
 
 use exonum::crypto::Hash;
 
+/// Name of the `ProofMapIndex` of timestamps.
+pub const TIMESTAMPS: &str = "timestamping.timestamps";
+
 /// Stores content's hash and some metadata about it.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ProtobufConvert)]
 #[exonum(pb = "proto::Timestamp")]
@@ -86,7 +89,7 @@ where
 {
     /// Returns the `ProofMapIndex` of timestamps.
     pub fn timestamps(&self) -> ProofMapIndex<T, Hash, TimestampEntry> {
-        ProofMapIndex::new("timestamping.timestamps", self.access.clone())
+        ProofMapIndex::new(TIMESTAMPS, self.access.clone())
     }
 
     /// Returns the state hash of the timestamping service.
@@ -94,6 +97,11 @@ where
         vec![self.timestamps().object_hash()]
     }
 
+    /// Returns the state hash of the timestamping service together with the table names.
+    pub fn state_hash_tables(&self) -> Vec<(String, Hash)> {
+        vec![(TIMESTAMPS.to_owned(), self.timestamps().object_hash())]
+    }
+
     /// Adds the timestamp entry to the database.
     pub fn add_timestamp(&self, timestamp_entry: TimestampEntry) {
         let timestamp = timestamp_entry.timestamp.clone();
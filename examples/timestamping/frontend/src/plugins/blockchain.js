@@ -5,9 +5,19 @@ import * as proto from '../../proto/stubs.js'
 const PER_PAGE = 10
 const SERVICE_ID = 130
 const TX_ID = 0
-const TABLE_INDEX = 0
+const TABLE_NAME = 'timestamping.timestamps'
 const TimestampEntry = Exonum.newType(proto.exonum.examples.timestamping.TimestampEntry)
 
+// Extracts the root hash of the table with the given name from the proof to the state hash
+function verifyTable(proof, stateHash, tableName) {
+  const tableProof = new Exonum.MapProof(proof, Exonum.Hash, Exonum.Hash)
+  if (tableProof.merkleRoot !== stateHash) {
+    throw new Error('Table proof is corrupted')
+  }
+  const tableKey = Exonum.hash(new TextEncoder().encode(tableName))
+  return tableProof.entries.get(tableKey)
+}
+
 module.exports = {
   install(Vue) {
     Vue.prototype.$blockchain = {
@@ -50,7 +60,7 @@ module.exports = {
             .then(data => {
               return Exonum.verifyBlock(data.block_info, validators).then(() => {
                 // verify table timestamps in the root tree
-                const tableRootHash = Exonum.verifyTable(data.state_proof, data.block_info.block.state_hash, SERVICE_ID, TABLE_INDEX)
+                const tableRootHash = verifyTable(data.state_proof, data.block_info.block.state_hash, TABLE_NAME)
 
                 // find timestamp in the tree of all timestamps
                 const timestampProof = new Exonum.MapProof(data.timestamp_proof, Exonum.Hash, TimestampEntry)
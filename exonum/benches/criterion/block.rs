@@ -96,10 +96,10 @@ fn create_blockchain(db: impl Database, services: Vec<Box<dyn Service>>) -> Bloc
     );
 
     let consensus_keypair = crypto::gen_keypair();
-    let config = GenesisConfig::new(iter::once(ValidatorKeys {
-        consensus_key: consensus_keypair.0,
-        service_key: service_keypair.0,
-    }));
+    let config = GenesisConfig::new(iter::once(ValidatorKeys::new(
+        consensus_keypair.0,
+        service_keypair.0,
+    )));
     blockchain.initialize(config).unwrap();
 
     blockchain
@@ -138,7 +138,7 @@ mod timestamping {
             "timestamping"
         }
 
-        fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
             Vec::new()
         }
 
@@ -241,7 +241,7 @@ mod cryptocurrency {
             "cryptocurrency"
         }
 
-        fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
             Vec::new()
         }
 
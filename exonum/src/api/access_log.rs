@@ -0,0 +1,238 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Access logging and latency metrics of the API endpoints.
+//!
+//! The latency of every handled request is recorded into the histogram of its endpoint
+//! in [`ApiMetrics`]. The histograms are available via the `v1/api_metrics` endpoint
+//! of the private API. If [`AccessLogConfig`] is specified for the API, requests are also
+//! written to the log as JSON objects with the `exonum::api::access` target. Only a
+//! sampled fraction of requests is logged, so that high-traffic nodes do not produce
+//! excessive log volume; failed requests can be logged regardless of sampling.
+//!
+//! [`ApiMetrics`]: struct.ApiMetrics.html
+//! [`AccessLogConfig`]: struct.AccessLogConfig.html
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::crypto::Hash;
+
+/// Log target of the access log records.
+pub const ACCESS_LOG_TARGET: &str = "exonum::api::access";
+
+/// Upper bounds of the latency histogram buckets, in milliseconds. Requests slower than
+/// the last bound are counted in an additional overflow bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 12] =
+    [1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Access log configuration.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AccessLogConfig {
+    /// Fraction of the requests written to the log, from 0 to 1.
+    pub sample_rate: f64,
+    /// Whether to log all requests that have failed, regardless of `sample_rate`.
+    pub log_errors: bool,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 1.0,
+            log_errors: true,
+        }
+    }
+}
+
+/// Access log record of a handled request.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AccessLogEntry {
+    /// Endpoint path with placeholders, e.g. `/api/explorer/v1/transactions/{hash}/status`.
+    pub endpoint: String,
+    /// HTTP method.
+    pub method: String,
+    /// Hash of the query string of the request.
+    pub params_hash: Hash,
+    /// HTTP status of the response.
+    pub status: u16,
+    /// Time spent on handling the request, in milliseconds.
+    pub latency_ms: u64,
+    /// Address of the client, if known.
+    pub client: Option<String>,
+}
+
+/// Histogram of the request latencies of an endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LatencyHistogram {
+    /// Numbers of requests in the buckets with upper bounds specified by
+    /// `LATENCY_BUCKETS_MS`. The last element counts requests exceeding all bounds.
+    pub buckets: Vec<u64>,
+    /// Total number of requests.
+    pub count: u64,
+    /// Number of requests with an error status.
+    pub errors: u64,
+    /// Total latency of all requests, in milliseconds.
+    pub total_ms: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            count: 0,
+            errors: 0,
+            total_ms: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn observe(&mut self, latency_ms: u64, is_error: bool) {
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| latency_ms <= bound)
+            .unwrap_or_else(|| LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total_ms = self.total_ms.saturating_add(latency_ms);
+        if is_error {
+            self.errors += 1;
+        }
+    }
+}
+
+/// Registry of the latency histograms of the API endpoints.
+#[derive(Clone, Debug, Default)]
+pub struct ApiMetrics {
+    histograms: Arc<Mutex<BTreeMap<String, LatencyHistogram>>>,
+}
+
+impl ApiMetrics {
+    /// Returns the histograms of all endpoints which have handled at least one request.
+    pub fn histograms(&self) -> BTreeMap<String, LatencyHistogram> {
+        self.histograms.lock().expect("Expected mutex lock").clone()
+    }
+
+    fn observe(&self, entry: &AccessLogEntry) {
+        let key = format!("{} {}", entry.method, entry.endpoint);
+        let mut histograms = self.histograms.lock().expect("Expected mutex lock");
+        histograms
+            .entry(key)
+            .or_default()
+            .observe(entry.latency_ms, is_error(entry.status));
+    }
+}
+
+/// Records handled requests into the metrics and the access log.
+#[derive(Clone, Debug)]
+pub struct AccessLog {
+    config: Option<AccessLogConfig>,
+    metrics: ApiMetrics,
+}
+
+impl AccessLog {
+    /// Creates an access log writing into the given metrics registry. Records are
+    /// logged only if `config` is specified.
+    pub fn new(config: Option<AccessLogConfig>, metrics: ApiMetrics) -> Self {
+        Self { config, metrics }
+    }
+
+    /// Records the handled request.
+    pub fn record(&self, entry: AccessLogEntry) {
+        self.metrics.observe(&entry);
+
+        let config = match self.config {
+            Some(ref config) => config,
+            None => return,
+        };
+        let sampled = config.sample_rate >= 1.0 || rand::random::<f64>() < config.sample_rate;
+        if sampled || (config.log_errors && is_error(entry.status)) {
+            match serde_json::to_string(&entry) {
+                Ok(record) => info!(target: ACCESS_LOG_TARGET, "{}", record),
+                Err(e) => warn!("Unable to serialize access log record: {}", e),
+            }
+        }
+    }
+}
+
+/// Returns the path of the endpoint with placeholders, given the path of the request
+/// and the endpoint name relative to its scope.
+pub(crate) fn endpoint_path(request_path: &str, name: &str) -> String {
+    let name = name.trim_start_matches('/');
+    let segments = request_path.trim_end_matches('/').split('/').count();
+    let prefix_len = segments.saturating_sub(name.split('/').count());
+    let prefix = request_path.split('/').take(prefix_len).collect::<Vec<_>>();
+    format!("{}/{}", prefix.join("/"), name)
+}
+
+fn is_error(status: u16) -> bool {
+    status >= 400
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash;
+
+    fn entry(endpoint: &str, status: u16, latency_ms: u64) -> AccessLogEntry {
+        AccessLogEntry {
+            endpoint: endpoint.to_owned(),
+            method: "GET".to_owned(),
+            params_hash: hash(&[]),
+            status,
+            latency_ms,
+            client: None,
+        }
+    }
+
+    #[test]
+    fn endpoint_path_restores_placeholders() {
+        assert_eq!(
+            endpoint_path(
+                "/api/explorer/v1/transactions/abcd/status",
+                "v1/transactions/{hash}/status"
+            ),
+            "/api/explorer/v1/transactions/{hash}/status"
+        );
+        assert_eq!(
+            endpoint_path("/api/system/v1/peers", "v1/peers"),
+            "/api/system/v1/peers"
+        );
+    }
+
+    #[test]
+    fn latencies_are_counted_per_endpoint() {
+        let metrics = ApiMetrics::default();
+        let log = AccessLog::new(None, metrics.clone());
+        log.record(entry("/api/a", 200, 0));
+        log.record(entry("/api/a", 404, 7));
+        log.record(entry("/api/a", 200, 20_000));
+        log.record(entry("/api/b", 200, 100));
+
+        let histograms = metrics.histograms();
+        let a = &histograms["GET /api/a"];
+        assert_eq!(a.count, 3);
+        assert_eq!(a.errors, 1);
+        assert_eq!(a.total_ms, 20_007);
+        assert_eq!(a.buckets[0], 1);
+        assert_eq!(a.buckets[2], 1);
+        assert_eq!(a.buckets[LATENCY_BUCKETS_MS.len()], 1);
+
+        let b = &histograms["GET /api/b"];
+        assert_eq!(b.count, 1);
+        assert_eq!(b.buckets[5], 1);
+    }
+}
@@ -22,9 +22,10 @@ pub use actix_web::middleware::cors::Cors;
 use actix::{Addr, System};
 use actix_net::server::Server;
 use actix_web::{
-    error::ResponseError,
+    error::{self as actix_error, ResponseError},
+    http::header,
     server::{HttpServer, StopServer},
-    AsyncResponder, FromRequest, HttpMessage, HttpResponse, Query,
+    AsyncResponder, HttpMessage, HttpResponse,
 };
 use futures::{Future, IntoFuture};
 use serde::{
@@ -33,18 +34,28 @@ use serde::{
 };
 
 use std::{
-    fmt,
+    cmp, fmt,
     net::SocketAddr,
     result,
     str::FromStr,
     sync::{mpsc, Arc},
     thread::{self, JoinHandle},
+    time::Instant,
 };
 
 use crate::api::{
-    error::Error as ApiError, ApiAccess, ApiAggregator, ExtendApiBackend, FutureResult, Immutable,
-    Mutable, NamedWith, Result, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+    access_log::{self, AccessLog, AccessLogConfig, AccessLogEntry},
+    canonical,
+    error::Error as ApiError,
+    ApiAccess, ApiAggregator, ExtendApiBackend, FutureResult, Immutable, Mutable, NamedWith,
+    Result, ServiceApiBackend, ServiceApiScope, ServiceApiState,
 };
+use crate::blockchain::lifecycle::{self, ServiceState};
+use crate::crypto::hash;
+use crate::helpers::Milliseconds;
+
+/// Default time given to the in-flight requests to complete when the API is stopped.
+pub const DEFAULT_DRAIN_TIMEOUT: Milliseconds = 30_000;
 
 /// Type alias for the concrete `actix-web` HTTP response.
 pub type FutureResponse = actix_web::FutureResponse<HttpResponse, actix_web::Error>;
@@ -81,6 +92,8 @@ impl fmt::Debug for RequestHandler {
 #[derive(Debug, Clone, Default)]
 pub struct ApiBuilder {
     handlers: Vec<RequestHandler>,
+    deployable_service: Option<u16>,
+    lifecycle_service: Option<u16>,
 }
 
 impl ApiBuilder {
@@ -88,6 +101,18 @@ impl ApiBuilder {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Makes the handlers respond with `404 Not Found` until the service with the given
+    /// identifier is deployed.
+    pub(crate) fn require_deployed(&mut self, service_id: u16) {
+        self.deployable_service = Some(service_id);
+    }
+
+    /// Makes the handlers respond with `404 Not Found` while the service with the given
+    /// identifier is stopped, and the handlers of the mutating endpoints while it is frozen.
+    pub(crate) fn require_running(&mut self, service_id: u16) {
+        self.lifecycle_service = Some(service_id);
+    }
 }
 
 impl ServiceApiBackend for ApiBuilder {
@@ -102,14 +127,101 @@ impl ServiceApiBackend for ApiBuilder {
     fn wire(&self, mut output: Self::Backend) -> Self::Backend {
         for handler in self.handlers.clone() {
             let inner = handler.inner;
-            output = output.route(&handler.name, handler.method.clone(), move |request| {
-                inner(request)
-            });
+            let name = handler.name.clone();
+            let deployable_service = self.deployable_service;
+            let lifecycle_service = self.lifecycle_service;
+            let read_only = handler.method == actix_web::http::Method::GET;
+            output = output.route(
+                &handler.name,
+                handler.method.clone(),
+                move |request: HttpRequest| {
+                    if let Some(service_id) = deployable_service {
+                        if !is_deployed(request.state(), service_id) {
+                            return not_deployed(service_id);
+                        }
+                    }
+                    if let Some(service_id) = lifecycle_service {
+                        let snapshot = request.state().snapshot();
+                        let state = lifecycle::service_state(snapshot.as_ref(), service_id);
+                        let serves = match state {
+                            ServiceState::Active => true,
+                            ServiceState::Frozen => read_only,
+                            ServiceState::Stopped => false,
+                        };
+                        if !serves {
+                            return not_running(service_id, state);
+                        }
+                    }
+                    match request.state().access_log().cloned() {
+                        Some(access_log) => {
+                            handle_logged(&access_log, &name, request, inner.as_ref())
+                        }
+                        None => inner(request),
+                    }
+                },
+            );
         }
         output
     }
 }
 
+/// Checks whether the service is deployed in the current blockchain state.
+fn is_deployed(state: &ServiceApiState, service_id: u16) -> bool {
+    state
+        .blockchain()
+        .is_service_deployed(state.snapshot().as_ref(), service_id)
+}
+
+/// Responds to the request to the endpoint of a service which is not deployed yet.
+fn not_deployed(service_id: u16) -> FutureResponse {
+    let error = ApiError::NotFound(format!(
+        "Service with ID={} is not deployed yet",
+        service_id
+    ));
+    let response: result::Result<HttpResponse, actix_web::Error> = Err(error.into());
+    Box::new(response.into_future())
+}
+
+/// Responds to the request to the endpoint of a service which is stopped or frozen.
+fn not_running(service_id: u16, state: ServiceState) -> FutureResponse {
+    let error = ApiError::NotFound(format!("Service with ID={} is {}", service_id, state));
+    let response: result::Result<HttpResponse, actix_web::Error> = Err(error.into());
+    Box::new(response.into_future())
+}
+
+/// Handles the request and records it into the access log.
+fn handle_logged(
+    access_log: &AccessLog,
+    name: &str,
+    request: HttpRequest,
+    inner: &RawHandler,
+) -> FutureResponse {
+    let start = Instant::now();
+    let endpoint = access_log::endpoint_path(request.path(), name);
+    let method = request.method().to_string();
+    let params_hash = hash(request.query_string().as_bytes());
+    let client = request.connection_info().remote().map(ToOwned::to_owned);
+
+    let access_log = access_log.clone();
+    let future = inner(request).then(move |result| {
+        let status = match result {
+            Ok(ref response) => response.status(),
+            Err(ref e) => e.as_response_error().error_response().status(),
+        };
+        let elapsed = start.elapsed();
+        access_log.record(AccessLogEntry {
+            endpoint,
+            method,
+            params_hash,
+            status: status.as_u16(),
+            latency_ms: elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis()),
+            client,
+        });
+        result
+    });
+    Box::new(future)
+}
+
 impl ExtendApiBackend for actix_web::Scope<ServiceApiState> {
     fn extend<'a, I>(mut self, items: I) -> Self
     where
@@ -133,6 +245,9 @@ impl ResponseError for ApiError {
             ApiError::Storage(err) => HttpResponse::InternalServerError().body(err.to_string()),
             ApiError::NotFound(err) => HttpResponse::NotFound().body(err.to_string()),
             ApiError::Unauthorized => HttpResponse::Unauthorized().finish(),
+            ApiError::ServiceUnavailable(err) => {
+                HttpResponse::ServiceUnavailable().body(err.to_string())
+            }
         }
     }
 }
@@ -147,10 +262,10 @@ where
         let handler = f.inner.handler;
         let index = move |request: HttpRequest| -> FutureResponse {
             let context = request.state();
-            let future = Query::from_request(&request, &Default::default())
-                .map(Query::into_inner)
+            let canonical = canonical_requested(&request);
+            let future = parse_query(&request)
                 .and_then(|query| handler(context, query).map_err(From::from))
-                .and_then(|value| Ok(HttpResponse::Ok().json(value)))
+                .map(|value| json_response(canonical, &value))
                 .into_future();
             Box::new(future)
         };
@@ -174,12 +289,13 @@ where
         let index = move |request: HttpRequest| -> FutureResponse {
             let handler = handler.clone();
             let context = request.state().clone();
+            let canonical = canonical_requested(&request);
             request
                 .json()
                 .from_err()
                 .and_then(move |query: Q| {
                     handler(&context, query)
-                        .map(|value| HttpResponse::Ok().json(value))
+                        .map(|value| json_response(canonical, &value))
                         .map_err(From::from)
                 })
                 .responder()
@@ -204,11 +320,11 @@ where
         let index = move |request: HttpRequest| -> FutureResponse {
             let context = request.state().clone();
             let handler = handler.clone();
-            Query::from_request(&request, &Default::default())
-                .map(Query::into_inner)
+            let canonical = canonical_requested(&request);
+            parse_query(&request)
                 .into_future()
                 .and_then(move |query| handler(&context, query).map_err(From::from))
-                .map(|value| HttpResponse::Ok().json(value))
+                .map(move |value| json_response(canonical, &value))
                 .responder()
         };
 
@@ -231,12 +347,13 @@ where
         let index = move |request: HttpRequest| -> FutureResponse {
             let handler = handler.clone();
             let context = request.state().clone();
+            let canonical = canonical_requested(&request);
             request
                 .json()
                 .from_err()
                 .and_then(move |query: Q| {
                     handler(&context, query)
-                        .map(|value| HttpResponse::Ok().json(value))
+                        .map(|value| json_response(canonical, &value))
                         .map_err(From::from)
                 })
                 .responder()
@@ -250,11 +367,43 @@ where
     }
 }
 
+/// Deserializes the query of the endpoint, ignoring the canonical form parameter.
+fn parse_query<Q: DeserializeOwned>(request: &HttpRequest) -> result::Result<Q, actix_web::Error> {
+    let query = canonical::strip_parameter(request.query_string());
+    serde_urlencoded::from_str(&query).map_err(actix_error::ErrorBadRequest)
+}
+
+/// Checks whether the request asks for the canonical JSON form of the response.
+fn canonical_requested(request: &HttpRequest) -> bool {
+    let accept = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok());
+    canonical::is_requested(request.query_string(), accept)
+}
+
+/// Serializes the value into the JSON response, in the canonical form if requested.
+fn json_response<I: Serialize>(canonical: bool, value: &I) -> HttpResponse {
+    if !canonical {
+        return HttpResponse::Ok().json(value);
+    }
+    match canonical::to_string(value) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(body),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
 /// Creates `actix_web::App` for the given aggregator and runtime configuration.
 pub(crate) fn create_app(aggregator: &ApiAggregator, runtime_config: ApiRuntimeConfig) -> App {
     let app_config = runtime_config.app_config;
     let access = runtime_config.access;
-    let state = ServiceApiState::new(aggregator.blockchain.clone());
+    let access_log = AccessLog::new(
+        runtime_config.access_log,
+        aggregator.node_state.api_metrics().clone(),
+    );
+    let state = ServiceApiState::new(aggregator.blockchain.clone()).with_access_log(access_log);
     let mut app = App::with_state(state);
     app = app.scope("api", |scope| aggregator.extend_backend(access, scope));
     if let Some(app_config) = app_config {
@@ -272,6 +421,11 @@ pub struct ApiRuntimeConfig {
     pub access: ApiAccess,
     /// Optional App configuration.
     pub app_config: Option<AppConfig>,
+    /// Access log configuration. If not specified, requests are not logged.
+    pub access_log: Option<AccessLogConfig>,
+    /// Time given to the in-flight requests to complete when the runtime is stopped.
+    /// New connections are not accepted during this time.
+    pub drain_timeout: Milliseconds,
 }
 
 impl ApiRuntimeConfig {
@@ -281,6 +435,8 @@ impl ApiRuntimeConfig {
             listen_address,
             access,
             app_config: Default::default(),
+            access_log: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
         }
     }
 }
@@ -291,6 +447,8 @@ impl fmt::Debug for ApiRuntimeConfig {
             .field("listen_address", &self.listen_address)
             .field("access", &self.access)
             .field("app_config", &self.app_config.as_ref().map(drop))
+            .field("access_log", &self.access_log)
+            .field("drain_timeout", &self.drain_timeout)
             .finish()
     }
 }
@@ -339,9 +497,13 @@ impl SystemRuntime {
                 let listen_address = runtime_config.listen_address;
                 info!("Starting {} web api on {}", access, listen_address);
 
+                // Actix measures the shutdown timeout in whole seconds.
+                let drain_timeout = (runtime_config.drain_timeout + 999) / 1000;
+                let drain_timeout = cmp::min(drain_timeout, u64::from(u16::max_value())) as u16;
                 let aggregator = aggregator.clone();
                 HttpServer::new(move || create_app(&aggregator, runtime_config.clone()))
                     .disable_signals()
+                    .shutdown_timeout(drain_timeout)
                     .bind(listen_address)
                     .map(HttpServer::start)
             });
@@ -388,6 +550,9 @@ impl SystemRuntime {
     }
 
     /// Stops the actix system runtime along with all web runtimes.
+    ///
+    /// The web runtimes stop accepting connections immediately and wait for
+    /// the in-flight requests to complete during their `drain_timeout`.
     pub fn stop(self) -> result::Result<(), failure::Error> {
         // Stop all actix web servers.
         for api_runtime_address in self.api_runtime_addresses {
@@ -0,0 +1,192 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Canonical JSON form of the API responses.
+//!
+//! The fields of the JSON responses are serialized in the order of the declaration
+//! of the response types, which may change between releases. Clients hashing the
+//! responses, e.g., to cache or to audit them, can request the canonical form, in which:
+//!
+//! - The keys of each object are sorted by their UTF-8 bytes.
+//! - There is no whitespace between the tokens.
+//! - Integers are written in decimal without a fraction or an exponent, and
+//!   floating-point numbers in the shortest form that is parsed back to the same
+//!   value. The formatting does not depend on the locale of the node.
+//! - Strings are escaped as required by JSON only; other characters are written
+//!   as UTF-8.
+//!
+//! The canonical form is requested with the `canonical` query parameter, e.g.,
+//! `v1/blocks?count=10&canonical=true`, or with the `canonical` profile of the JSON
+//! media type: `Accept: application/json; profile="canonical"`. The form applies to
+//! all endpoints returning JSON.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+
+/// Name of the query parameter requesting the canonical form.
+pub const QUERY_PARAMETER: &str = "canonical";
+/// Profile of the JSON media type requesting the canonical form.
+pub const PROFILE: &str = "canonical";
+
+/// Serializes the value into the canonical JSON form.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<String> {
+    serde_json::to_value(value).and_then(|value| serde_json::to_string(&Sorted::from(value)))
+}
+
+/// JSON value with the keys of the objects sorted. The keys of `serde_json::Value`
+/// objects are kept in the insertion order if the `preserve_order` feature of
+/// `serde_json` is enabled by any crate in the build, so they are sorted explicitly.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Sorted {
+    Array(Vec<Sorted>),
+    Object(BTreeMap<String, Sorted>),
+    Scalar(Value),
+}
+
+impl From<Value> for Sorted {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Array(items) => Sorted::Array(items.into_iter().map(Sorted::from).collect()),
+            Value::Object(fields) => Sorted::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, Sorted::from(value)))
+                    .collect(),
+            ),
+            scalar => Sorted::Scalar(scalar),
+        }
+    }
+}
+
+/// Checks whether the request with the given query string and the `Accept` header
+/// asks for the canonical form.
+pub(crate) fn is_requested(query: &str, accept: Option<&str>) -> bool {
+    let in_query = query_pairs(query)
+        .filter(|(name, _)| *name == QUERY_PARAMETER)
+        .last()
+        .map_or(false, |(_, value)| value != "false" && value != "0");
+    in_query || accept.map_or(false, accepts_profile)
+}
+
+/// Removes the canonical form parameter from the query string, so that it is not
+/// mistaken for a parameter of the endpoint.
+pub(crate) fn strip_parameter(query: &str) -> String {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some(QUERY_PARAMETER))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn query_pairs(query: &str) -> impl Iterator<Item = (&str, &str)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            (parts.next().unwrap_or(""), parts.next().unwrap_or(""))
+        })
+}
+
+fn accepts_profile(accept: &str) -> bool {
+    accept.split(',').any(|media_range| {
+        media_range.split(';').skip(1).any(|parameter| {
+            let mut parts = parameter.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim().trim_matches('"');
+            name.eq_ignore_ascii_case("profile") && value == PROFILE
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Response {
+        zeta: u64,
+        alpha: Vec<f64>,
+        nested: Nested,
+    }
+
+    #[derive(Serialize)]
+    struct Nested {
+        b: &'static str,
+        a: Option<bool>,
+    }
+
+    #[test]
+    fn canonical_form_sorts_keys() {
+        let response = Response {
+            zeta: 1,
+            alpha: vec![0.5, 1e21],
+            nested: Nested {
+                b: "\u{e9}",
+                a: None,
+            },
+        };
+        assert_eq!(
+            to_string(&response).unwrap(),
+            r#"{"alpha":[0.5,1e21],"nested":{"a":null,"b":"é"},"zeta":1}"#
+        );
+    }
+
+    #[derive(Serialize)]
+    struct Batch {
+        rows: Vec<Vec<Nested>>,
+        count: usize,
+    }
+
+    #[test]
+    fn canonical_form_sorts_keys_in_arrays() {
+        let batch = Batch {
+            rows: vec![
+                vec![
+                    Nested {
+                        b: "x",
+                        a: Some(true),
+                    },
+                    Nested { b: "y", a: None },
+                ],
+                vec![],
+            ],
+            count: 2,
+        };
+        assert_eq!(
+            to_string(&batch).unwrap(),
+            r#"{"count":2,"rows":[[{"a":true,"b":"x"},{"a":null,"b":"y"}],[]]}"#
+        );
+    }
+
+    #[test]
+    fn canonical_form_is_requested() {
+        assert!(is_requested("canonical=true", None));
+        assert!(is_requested("count=10&canonical", None));
+        assert!(!is_requested("count=10&canonical=false", None));
+        assert!(!is_requested("", Some("application/json")));
+        assert!(is_requested(
+            "",
+            Some("text/html, application/json; profile=\"canonical\"")
+        ));
+        assert_eq!(
+            strip_parameter("count=10&canonical=1&latest=5"),
+            "count=10&latest=5"
+        );
+        assert_eq!(strip_parameter("canonical"), "");
+    }
+}
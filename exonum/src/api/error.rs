@@ -50,6 +50,11 @@ pub enum Error {
     /// authentication credentials.
     #[fail(display = "Unauthorized")]
     Unauthorized,
+
+    /// Service unavailable. This error occurs when the node temporarily refuses
+    /// the request, for example, because it sheds load.
+    #[fail(display = "Service unavailable: {}", _0)]
+    ServiceUnavailable(String),
 }
 
 impl From<io::Error> for Error {
@@ -28,7 +28,9 @@ use crate::blockchain::{Blockchain, SharedNodeState};
 use crate::crypto::PublicKey;
 use crate::node::ApiSender;
 
+pub mod access_log;
 pub mod backends;
+pub mod canonical;
 pub mod error;
 pub mod node;
 mod state;
@@ -243,6 +245,18 @@ impl ServiceApiBuilder {
         }
     }
 
+    fn require_deployed(&mut self, service_id: u16) {
+        self.public_scope.actix_backend.require_deployed(service_id);
+        self.private_scope
+            .actix_backend
+            .require_deployed(service_id);
+    }
+
+    fn require_running(&mut self, service_id: u16) {
+        self.public_scope.actix_backend.require_running(service_id);
+        self.private_scope.actix_backend.require_running(service_id);
+    }
+
     /// Returns a mutable reference to the public API scope builder.
     pub fn public_scope(&mut self) -> &mut ServiceApiScope {
         &mut self.public_scope
@@ -321,10 +335,16 @@ impl ApiAggregator {
             "explorer".to_owned(),
             Self::explorer_api(&blockchain, node_state.clone()),
         );
-        // Adds services APIs.
+        // Adds services APIs. The endpoints of deployable services are available
+        // once the service is deployed, and the endpoints of stopped or frozen services
+        // are restricted according to their lifecycle state.
         inner.extend(blockchain.service_map().iter().map(|(_, service)| {
             let mut builder = ServiceApiBuilder::with_blockchain(blockchain.clone());
             service.wire_api(&mut builder);
+            if service.is_deployable() {
+                builder.require_deployed(service.service_id());
+            }
+            builder.require_running(service.service_id());
             // TODO think about prefixes for non web backends. (ECR-1758)
             let prefix = format!("services/{}", service.service_name());
             (prefix, builder)
@@ -0,0 +1,138 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal administrative dashboard served by the private API.
+//!
+//! The dashboard is a static page polling the `v1/dashboard` endpoint, which
+//! gathers the current height, peers, pool size, validator status and recent blocks
+//! of the node in a single response. The dashboard is available only with the
+//! `dashboard` feature enabled.
+
+use actix_web::{http, HttpResponse};
+use futures::future;
+
+use std::sync::Arc;
+
+use crate::api::{
+    backends::actix::{FutureResponse, HttpRequest, RawHandler, RequestHandler},
+    node::public::system::ConsensusStatus,
+    ServiceApiBackend, ServiceApiScope, ServiceApiState,
+};
+use crate::blockchain::{Schema, SharedNodeState};
+use crate::crypto::Hash;
+use crate::helpers::{Height, ValidatorId};
+use crate::node::ConnectInfo;
+
+/// Number of the latest blocks shown by the dashboard.
+pub const DASHBOARD_BLOCKS_COUNT: u64 = 10;
+
+const DASHBOARD_PAGE: &str = include_str!("dashboard.html");
+
+/// Summary of a committed block shown by the dashboard.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DashboardBlock {
+    /// Height of the block.
+    pub height: Height,
+    /// Hash of the block.
+    pub hash: Hash,
+    /// Identifier of the validator which has proposed the block.
+    pub proposer_id: ValidatorId,
+    /// Number of transactions in the block.
+    pub tx_count: u32,
+}
+
+/// State of the node shown by the dashboard.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DashboardInfo {
+    /// Height of the latest committed block.
+    pub height: Height,
+    /// Identifier of the node in the validators list, absent for auditors.
+    pub validator_id: Option<ValidatorId>,
+    /// Consensus status.
+    pub consensus_status: ConsensusStatus,
+    /// Peers connected to the node.
+    pub incoming_connections: Vec<ConnectInfo>,
+    /// Peers the node is connected to.
+    pub outgoing_connections: Vec<ConnectInfo>,
+    /// Total number of uncommitted transactions stored in persistent pool.
+    pub tx_pool_size: u64,
+    /// Size of the transaction cache.
+    pub tx_cache_size: usize,
+    /// Latest blocks, starting from the newest one.
+    pub recent_blocks: Vec<DashboardBlock>,
+}
+
+/// Adds the dashboard page and its data endpoint to the private API scope.
+pub(super) fn wire(shared_api_state: SharedNodeState, api_scope: &mut ServiceApiScope) {
+    api_scope.endpoint(
+        "v1/dashboard",
+        move |state: &ServiceApiState, _query: ()| Ok(dashboard_info(&shared_api_state, state)),
+    );
+
+    let index = move |_request: HttpRequest| -> FutureResponse {
+        let response = HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(DASHBOARD_PAGE);
+        Box::new(future::ok(response))
+    };
+    api_scope.web_backend().raw_handler(RequestHandler {
+        name: "dashboard".to_owned(),
+        method: http::Method::GET,
+        inner: Arc::from(index) as Arc<RawHandler>,
+    });
+}
+
+fn dashboard_info(shared_api_state: &SharedNodeState, state: &ServiceApiState) -> DashboardInfo {
+    let snapshot = state.snapshot();
+    let schema = Schema::new(&snapshot);
+    let height = schema.height();
+
+    let blocks = schema.blocks();
+    let block_hashes = schema.block_hashes_by_height();
+    let first = block_hashes.len().saturating_sub(DASHBOARD_BLOCKS_COUNT);
+    let recent_blocks = (first..block_hashes.len())
+        .rev()
+        .filter_map(|height| {
+            let hash = block_hashes.get(height)?;
+            let block = blocks.get(&hash)?;
+            Some(DashboardBlock {
+                height: block.height(),
+                hash,
+                proposer_id: block.proposer_id(),
+                tx_count: block.tx_count(),
+            })
+        })
+        .collect();
+
+    let consensus_status = if shared_api_state.is_enabled() {
+        if shared_api_state.consensus_status() {
+            ConsensusStatus::Active
+        } else {
+            ConsensusStatus::Enabled
+        }
+    } else {
+        ConsensusStatus::Disabled
+    };
+
+    DashboardInfo {
+        height,
+        validator_id: shared_api_state.validator_id(),
+        consensus_status,
+        incoming_connections: shared_api_state.incoming_connections(),
+        outgoing_connections: shared_api_state.outgoing_connections(),
+        tx_pool_size: schema.transactions_pool_len(),
+        tx_cache_size: shared_api_state.tx_cache_size(),
+        recent_blocks,
+    }
+}
@@ -17,13 +17,42 @@
 //! Private API includes requests that are available only to the blockchain
 //! administrators, e.g. view the list of services on the current node.
 
-use std::{collections::HashMap, net::SocketAddr};
+use actix_web::{http, HttpResponse};
+use exonum_merkledb::{inspect, proof_map_index::ProofPath, IndexType};
+use futures::{future, Future};
+use hex::FromHex;
 
-use crate::api::{Error as ApiError, ServiceApiScope, ServiceApiState};
-use crate::blockchain::{Service, SharedNodeState};
-use crate::crypto::PublicKey;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use crate::api::{
+    access_log::LatencyHistogram,
+    backends::actix::{FutureResponse, HttpRequest, RawHandler, RequestHandler},
+    node::public::AuthorWebhook,
+    Error as ApiError, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+};
+use crate::blockchain::{
+    divergence::{self, MerkleNode, StateHashes},
+    BlockMetrics, Schema, Service, SharedNodeState,
+};
+use crate::crypto::{Hash, PublicKey};
+use crate::helpers::{
+    log_filters::{self, LogFilters, LogFiltersUpdate},
+    Height,
+};
 use crate::messages::PROTOCOL_MAJOR_VERSION;
-use crate::node::{ConnectInfo, ExternalMessage};
+use crate::node::{
+    archived_messages, simulate_change, ArchivedMessage, BlockAssemblyStats, ClockSkewInfo,
+    ConnectInfo, ExternalMessage, FiringAlert, QuorumImpact, RejectedTransaction,
+    ValidatorSetChange,
+};
+
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
 
 /// Short information about the service.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -101,6 +130,152 @@ struct ConsensusEnabledQuery {
     enabled: bool,
 }
 
+/// Query for the nodes of the Merkle Patricia tree of a `ProofMapIndex`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MerkleNodesQuery {
+    /// Name of the index.
+    pub index: String,
+    /// Path to the node whose children are requested. If absent, the root node
+    /// is returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<ProofPath>,
+}
+
+/// Result of the transaction rebroadcast.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RebroadcastInfo {
+    /// Hash of the transaction.
+    pub tx_hash: Hash,
+    /// Number of the connected peers the transaction has been sent to.
+    pub peers: usize,
+}
+
+/// Query for the rejected transactions.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct RejectedTransactionsQuery {
+    /// Hash of the transaction. If absent, all recorded rejections are returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<Hash>,
+}
+
+/// Query for the hash of the changes made by a block.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PatchHashQuery {
+    /// Height of the block.
+    pub height: Height,
+}
+
+/// Hash of the changes made by a block.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PatchHashInfo {
+    /// Height of the block.
+    pub height: Height,
+    /// Hash of the changes made by the block, as computed by `Patch::changes_hash`.
+    pub patch_hash: Hash,
+}
+
+/// Query for the consensus messages stored in the archive.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ConsensusArchiveQuery {
+    /// Height of the messages.
+    pub height: Height,
+}
+
+/// Number of the records returned by `v1/metrics/history` if the limit is not specified.
+pub const DEFAULT_METRICS_HISTORY_LIMIT: usize = 100;
+/// Maximum number of the records returned by `v1/metrics/history`.
+pub const MAX_METRICS_HISTORY_LIMIT: usize = 1_000;
+
+/// Query for the performance records of the committed blocks.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct MetricsHistoryQuery {
+    /// Height of the first returned record. If absent, the latest records are returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<Height>,
+    /// Maximum number of the returned records, `DEFAULT_METRICS_HISTORY_LIMIT` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+/// Query for creating a read-only checkpoint of the node database.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CheckpointQuery {
+    /// Absolute path to the checkpoint directory, which should not exist.
+    pub path: PathBuf,
+}
+
+/// Read-only checkpoint of the node database.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CheckpointInfo {
+    /// Path to the checkpoint directory.
+    pub path: PathBuf,
+    /// Height of the latest block committed before the checkpoint was created.
+    pub height: Height,
+}
+
+/// Number of the entries returned by `v1/storage/entries` if the limit is not specified.
+pub const DEFAULT_STORAGE_ENTRIES_LIMIT: usize = 100;
+/// Maximum number of the entries returned by `v1/storage/entries`.
+pub const MAX_STORAGE_ENTRIES_LIMIT: usize = 1_000;
+
+/// Index stored in the node database.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StorageIndexInfo {
+    /// Name of the index.
+    pub name: String,
+    /// Hex-encoded family identifier of the index, if the index belongs to a family.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_id: Option<String>,
+    /// Type of the index.
+    pub index_type: IndexType,
+    /// Number of the entries stored by the index, including the tree nodes
+    /// of the Merkelized indexes.
+    pub entries: u64,
+}
+
+/// Query for the raw entries of an index.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct StorageEntriesQuery {
+    /// Name of the index.
+    pub index: String,
+    /// Hex-encoded family identifier of the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_id: Option<String>,
+    /// Hex-encoded key of the requested entry. If specified, `from` and `limit` are ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Hex-encoded key to start the iteration from. If absent, the entries are returned
+    /// from the beginning of the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// Maximum number of the returned entries, `DEFAULT_STORAGE_ENTRIES_LIMIT` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+/// Raw entry of an index.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StorageEntry {
+    /// Hex-encoded key of the entry.
+    pub key: String,
+    /// Hex-encoded value of the entry.
+    pub value: String,
+}
+
+impl StorageEntry {
+    fn new(key: &[u8], value: &[u8]) -> Self {
+        Self {
+            key: hex::encode(key),
+            value: hex::encode(value),
+        }
+    }
+}
+
+fn decode_hex_param(param: &str, value: &str) -> Result<Vec<u8>, ApiError> {
+    Vec::<u8>::from_hex(value)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid `{}` parameter: {}", param, e)))
+}
+
 /// Private system API.
 #[derive(Clone, Debug)]
 pub struct SystemApi {
@@ -125,10 +300,52 @@ impl SystemApi {
             .handle_is_consensus_enabled("v1/consensus_enabled", api_scope)
             .handle_set_consensus_enabled("v1/consensus_enabled", api_scope)
             .handle_shutdown("v1/shutdown", api_scope)
-            .handle_rebroadcast("v1/rebroadcast", api_scope);
+            .handle_rebroadcast("v1/rebroadcast", api_scope)
+            .handle_rebroadcast_transaction("v1/transactions/{hash}/rebroadcast", api_scope)
+            .handle_consensus_state("v1/consensus_state", api_scope)
+            .handle_block_assembly("v1/block_assembly", api_scope)
+            .handle_rejected_transactions("v1/rejected_transactions", api_scope)
+            .handle_clock_skew("v1/clock_skew", api_scope)
+            .handle_alerts("v1/alerts", api_scope)
+            .handle_api_metrics("v1/api_metrics", api_scope)
+            .handle_state_hash_tables("v1/state_hash/tables", api_scope)
+            .handle_state_hash_nodes("v1/state_hash/nodes", api_scope)
+            .handle_patch_hash("v1/patch_hash", api_scope)
+            .handle_consensus_archive("v1/consensus_archive", api_scope)
+            .handle_metrics_history("v1/metrics/history", api_scope)
+            .handle_quorum_simulation("v1/quorum/simulate", api_scope)
+            .handle_create_checkpoint("v1/checkpoints", api_scope)
+            .handle_log_filters("v1/log_filters", api_scope)
+            .handle_set_log_filters("v1/log_filters", api_scope)
+            .handle_webhooks("v1/webhooks", api_scope)
+            .handle_add_webhook("v1/webhooks", api_scope)
+            .handle_remove_webhook("v1/webhooks/remove", api_scope)
+            .handle_storage_inspection(api_scope)
+            .handle_dashboard(api_scope);
         api_scope
     }
 
+    #[cfg(feature = "dashboard")]
+    fn handle_dashboard(self, api_scope: &mut ServiceApiScope) -> Self {
+        dashboard::wire(self.shared_api_state.clone(), api_scope);
+        self
+    }
+
+    #[cfg(not(feature = "dashboard"))]
+    fn handle_dashboard(self, _api_scope: &mut ServiceApiScope) -> Self {
+        self
+    }
+
+    /// The storage endpoints are wired only if they are enabled in the node configuration.
+    fn handle_storage_inspection(self, api_scope: &mut ServiceApiScope) -> Self {
+        if !self.shared_api_state.is_storage_inspection_enabled() {
+            return self;
+        }
+        warn!("Private API exposes the raw contents of the database at `v1/storage`");
+        self.handle_storage_indexes("v1/storage/indexes", api_scope)
+            .handle_storage_entries("v1/storage/entries", api_scope)
+    }
+
     fn handle_peers_info(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
         let self_ = self.clone();
         api_scope.endpoint(name, move |_state: &ServiceApiState, _query: ()| {
@@ -235,4 +452,428 @@ impl SystemApi {
         );
         self
     }
+
+    fn handle_rebroadcast_transaction(
+        self,
+        name: &'static str,
+        api_scope: &mut ServiceApiScope,
+    ) -> Self {
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let tx_hash = request.match_info().get("hash").unwrap_or_default();
+            let tx_hash = match Hash::from_hex(tx_hash) {
+                Ok(tx_hash) => tx_hash,
+                Err(e) => {
+                    let e = ApiError::BadRequest(format!("Invalid transaction hash: {}", e));
+                    return Box::new(future::err(e.into()));
+                }
+            };
+            let receiver = match request.state().sender().rebroadcast_transaction(tx_hash) {
+                Ok(receiver) => receiver,
+                Err(e) => return Box::new(future::err(ApiError::from(e).into())),
+            };
+            let future = receiver.then(move |result| match result {
+                Ok(Some(peers)) => Ok(HttpResponse::Ok().json(RebroadcastInfo { tx_hash, peers })),
+                Ok(None) => Err(ApiError::NotFound(format!(
+                    "Transaction {:?} is not in the pool",
+                    tx_hash
+                ))
+                .into()),
+                Err(_) => Err(ApiError::InternalError(format_err!(
+                    "Node has stopped before rebroadcasting transaction {:?}",
+                    tx_hash
+                ))
+                .into()),
+            });
+            Box::new(future)
+        };
+
+        api_scope.web_backend().raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::POST,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+        self
+    }
+
+    fn handle_consensus_state(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let receiver = match request.state().sender().consensus_state() {
+                Ok(receiver) => receiver,
+                Err(e) => return Box::new(future::err(ApiError::from(e).into())),
+            };
+            let future = receiver.then(|result| match result {
+                Ok(info) => Ok(HttpResponse::Ok().json(info)),
+                Err(_) => Err(ApiError::InternalError(format_err!(
+                    "Node has not reported its consensus state"
+                ))
+                .into()),
+            });
+            Box::new(future)
+        };
+
+        api_scope.web_backend().raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::GET,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+        self
+    }
+
+    fn handle_block_assembly(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState,
+                  _query: ()|
+                  -> Result<Vec<BlockAssemblyStats>, ApiError> {
+                Ok(self.shared_api_state.block_assembly_log().records())
+            },
+        );
+        self_
+    }
+
+    fn handle_rejected_transactions(
+        self,
+        name: &'static str,
+        api_scope: &mut ServiceApiScope,
+    ) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState,
+                  query: RejectedTransactionsQuery|
+                  -> Result<Vec<RejectedTransaction>, ApiError> {
+                let rejected = self.shared_api_state.rejected_transactions();
+                Ok(match query.hash {
+                    Some(hash) => rejected.find(&hash),
+                    None => rejected.records(),
+                })
+            },
+        );
+        self_
+    }
+
+    fn handle_clock_skew(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState, _query: ()| -> Result<ClockSkewInfo, ApiError> {
+                Ok(self.shared_api_state.clock_skew())
+            },
+        );
+        self_
+    }
+
+    fn handle_alerts(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState, _query: ()| -> Result<Vec<FiringAlert>, ApiError> {
+                Ok(self.shared_api_state.firing_alerts().alerts())
+            },
+        );
+        self_
+    }
+
+    fn handle_api_metrics(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState,
+                  _query: ()|
+                  -> Result<BTreeMap<String, LatencyHistogram>, ApiError> {
+                Ok(self.shared_api_state.api_metrics().histograms())
+            },
+        );
+        self_
+    }
+
+    fn handle_state_hash_tables(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState, _query: ()| -> Result<StateHashes, ApiError> {
+                Ok(divergence::state_hashes(state.blockchain()))
+            },
+        );
+        self
+    }
+
+    fn handle_state_hash_nodes(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState,
+                  query: MerkleNodesQuery|
+                  -> Result<Vec<MerkleNode>, ApiError> {
+                let snapshot = state.snapshot();
+                divergence::merkle_nodes(snapshot.as_ref(), &query.index, query.path.as_ref())
+                    .map_err(|e| ApiError::BadRequest(e.to_string()))
+            },
+        );
+        self
+    }
+
+    fn handle_patch_hash(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState,
+                  query: PatchHashQuery|
+                  -> Result<PatchHashInfo, ApiError> {
+                let snapshot = state.snapshot();
+                let patch_hash = Schema::new(&snapshot)
+                    .patch_hashes()
+                    .get(&query.height.0)
+                    .ok_or_else(|| {
+                        ApiError::NotFound(format!(
+                            "Patch hash is not recorded for the block at height {}",
+                            query.height
+                        ))
+                    })?;
+                Ok(PatchHashInfo {
+                    height: query.height,
+                    patch_hash,
+                })
+            },
+        );
+        self
+    }
+
+    fn handle_consensus_archive(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState,
+                  query: ConsensusArchiveQuery|
+                  -> Result<Vec<ArchivedMessage>, ApiError> {
+                let snapshot = state.snapshot();
+                Ok(archived_messages(&Schema::new(&snapshot), query.height))
+            },
+        );
+        self
+    }
+
+    fn handle_metrics_history(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState,
+                  query: MetricsHistoryQuery|
+                  -> Result<Vec<BlockMetrics>, ApiError> {
+                let limit = query.limit.unwrap_or(DEFAULT_METRICS_HISTORY_LIMIT);
+                if limit > MAX_METRICS_HISTORY_LIMIT {
+                    return Err(ApiError::BadRequest(format!(
+                        "Limit {} exceeds the maximum of {} records",
+                        limit, MAX_METRICS_HISTORY_LIMIT
+                    )));
+                }
+                let snapshot = state.snapshot();
+                let schema = Schema::new(&snapshot);
+                let from = query.from.unwrap_or_else(|| {
+                    Height((schema.height().0 + 1).saturating_sub(limit as u64))
+                });
+                Ok(schema
+                    .block_metrics()
+                    .values_from(&from.0)
+                    .take(limit)
+                    .collect())
+            },
+        );
+        self
+    }
+
+    fn handle_quorum_simulation(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint_mut(
+            name,
+            move |state: &ServiceApiState,
+                  change: ValidatorSetChange|
+                  -> Result<QuorumImpact, ApiError> {
+                let snapshot = state.snapshot();
+                let config = Schema::new(&snapshot).actual_configuration();
+                let online = self.shared_api_state.connected_keys();
+                simulate_change(&config, &change, &online)
+                    .map_err(|e| ApiError::BadRequest(e.to_string()))
+            },
+        );
+        self_
+    }
+
+    fn handle_create_checkpoint(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint_mut(
+            name,
+            move |state: &ServiceApiState,
+                  query: CheckpointQuery|
+                  -> Result<CheckpointInfo, ApiError> {
+                if !query.path.is_absolute() {
+                    return Err(ApiError::BadRequest(format!(
+                        "Checkpoint path {} is not absolute",
+                        query.path.display()
+                    )));
+                }
+                if query.path.exists() {
+                    return Err(ApiError::BadRequest(format!(
+                        "Checkpoint path {} already exists",
+                        query.path.display()
+                    )));
+                }
+                let height = state
+                    .blockchain()
+                    .create_checkpoint(&query.path)
+                    .map_err(|e| ApiError::Storage(e.into()))?;
+                info!(
+                    "Created database checkpoint at {} (height {})",
+                    query.path.display(),
+                    height
+                );
+                Ok(CheckpointInfo {
+                    path: query.path,
+                    height,
+                })
+            },
+        );
+        self
+    }
+
+    fn handle_storage_indexes(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState, _query: ()| -> Result<Vec<StorageIndexInfo>, ApiError> {
+                let snapshot = state.snapshot();
+                Ok(inspect::indexes(snapshot.as_ref())
+                    .into_iter()
+                    .map(|index| StorageIndexInfo {
+                        name: index.name().to_owned(),
+                        family_id: index.family_id().map(hex::encode),
+                        index_type: index.index_type(),
+                        entries: index.entries_count(snapshot.as_ref()),
+                    })
+                    .collect())
+            },
+        );
+        self
+    }
+
+    fn handle_storage_entries(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState,
+                  query: StorageEntriesQuery|
+                  -> Result<Vec<StorageEntry>, ApiError> {
+                let family_id = match query.family_id {
+                    Some(ref family_id) => Some(decode_hex_param("family_id", family_id)?),
+                    None => None,
+                };
+                let snapshot = state.snapshot();
+                let index = inspect::find_index(
+                    snapshot.as_ref(),
+                    &query.index,
+                    family_id.as_ref().map(Vec::as_slice),
+                )
+                .ok_or_else(|| {
+                    ApiError::NotFound(format!("Index `{}` does not exist", query.index))
+                })?;
+
+                if let Some(ref key) = query.key {
+                    let key = decode_hex_param("key", key)?;
+                    let value = index.get(snapshot.as_ref(), &key).ok_or_else(|| {
+                        ApiError::NotFound(format!(
+                            "Index `{}` does not contain key {}",
+                            query.index,
+                            hex::encode(&key)
+                        ))
+                    })?;
+                    return Ok(vec![StorageEntry::new(&key, &value)]);
+                }
+
+                let from = match query.from {
+                    Some(ref from) => decode_hex_param("from", from)?,
+                    None => Vec::new(),
+                };
+                let limit = query.limit.unwrap_or(DEFAULT_STORAGE_ENTRIES_LIMIT);
+                if limit > MAX_STORAGE_ENTRIES_LIMIT {
+                    return Err(ApiError::BadRequest(format!(
+                        "Limit {} exceeds the maximum of {} entries",
+                        limit, MAX_STORAGE_ENTRIES_LIMIT
+                    )));
+                }
+                Ok(index
+                    .entries(snapshot.as_ref(), &from, limit)
+                    .into_iter()
+                    .map(|(key, value)| StorageEntry::new(&key, &value))
+                    .collect())
+            },
+        );
+        self
+    }
+
+    fn handle_webhooks(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState, _query: ()| -> Result<Vec<AuthorWebhook>, ApiError> {
+                Ok(self.shared_api_state.author_watchers().webhooks())
+            },
+        );
+        self_
+    }
+
+    fn handle_add_webhook(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint_mut(
+            name,
+            move |_state: &ServiceApiState, webhook: AuthorWebhook| -> Result<(), ApiError> {
+                self.shared_api_state
+                    .author_watchers()
+                    .add_webhook(webhook)
+                    .map_err(|e| ApiError::BadRequest(e.to_string()))
+            },
+        );
+        self_
+    }
+
+    fn handle_remove_webhook(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint_mut(
+            name,
+            move |_state: &ServiceApiState, webhook: AuthorWebhook| -> Result<(), ApiError> {
+                if self
+                    .shared_api_state
+                    .author_watchers()
+                    .remove_webhook(&webhook)
+                {
+                    Ok(())
+                } else {
+                    Err(ApiError::NotFound("Webhook is not registered".to_owned()))
+                }
+            },
+        );
+        self_
+    }
+
+    fn handle_log_filters(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState, _query: ()| -> Result<LogFilters, ApiError> {
+                log_filters::current_filters().ok_or_else(|| {
+                    ApiError::NotFound("Runtime log filters are not initialized".to_owned())
+                })
+            },
+        );
+        self
+    }
+
+    fn handle_set_log_filters(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint_mut(
+            name,
+            move |_state: &ServiceApiState,
+                  update: LogFiltersUpdate|
+                  -> Result<LogFilters, ApiError> {
+                if log_filters::current_filters().is_none() {
+                    return Err(ApiError::NotFound(
+                        "Runtime log filters are not initialized".to_owned(),
+                    ));
+                }
+                log_filters::update_filters(update)
+                    .map_err(|e| ApiError::BadRequest(format!("Invalid log filters: {}", e)))
+            },
+        );
+        self
+    }
 }
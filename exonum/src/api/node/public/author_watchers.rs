@@ -0,0 +1,337 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Notifications about the transactions signed by watched keys.
+//!
+//! Clients such as exchanges watch the accounts of their users without holding
+//! their keys. The node reports every transaction signed by a watched key when
+//! the transaction enters the pool, is rejected by the node or is committed.
+//! The notifications are sent to the WebSocket clients subscribed to the author
+//! and to the webhooks registered for the author via the private API.
+//!
+//! Webhooks are delivered by a background thread as `POST` requests with the JSON
+//! notification in the body. Only `http` URLs are supported, and failed deliveries
+//! are logged but not retried.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::blockchain::{TransactionResult, TxLocation};
+use crate::crypto::{Hash, PublicKey};
+use crate::explorer::TxStatus;
+
+/// Timeout of connecting to a webhook and of reading its response.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum number of notifications waiting for the delivery to webhooks.
+const WEBHOOK_QUEUE_SIZE: usize = 1024;
+
+/// Notification about a transaction signed by a watched key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorNotification {
+    /// Author of the transaction.
+    pub author: PublicKey,
+    /// Hash of the transaction.
+    pub tx_hash: Hash,
+    /// Progress of the transaction.
+    pub event: AuthorEvent,
+}
+
+/// Progress of a transaction reported to the watchers of its author.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum AuthorEvent {
+    /// The transaction is verified and added to the pool.
+    Pooled,
+    /// The transaction is rejected by the node.
+    Rejected {
+        /// Reason of the rejection.
+        reason: String,
+    },
+    /// The transaction is committed. The transaction has failed if the status
+    /// is not successful.
+    Committed {
+        /// Location of the transaction in the blockchain.
+        location: TxLocation,
+        /// Result of the transaction execution.
+        #[serde(with = "TxStatus")]
+        status: TransactionResult,
+    },
+}
+
+/// Webhook notified about the transactions of the author.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AuthorWebhook {
+    /// Watched key.
+    pub author: PublicKey,
+    /// URL the notifications are posted to, e.g., `http://127.0.0.1:8000/deposits`.
+    pub url: String,
+}
+
+#[derive(Debug, Default)]
+struct Watchers {
+    webhooks: HashMap<PublicKey, BTreeSet<String>>,
+    subscribed: HashSet<PublicKey>,
+    delivery: Option<SyncSender<(String, String)>>,
+}
+
+/// Registry of the watched keys.
+///
+/// The registry is cheaply cloneable; all clones share the same watchers.
+#[derive(Debug, Clone, Default)]
+pub struct AuthorWatchers {
+    inner: Arc<Mutex<Watchers>>,
+}
+
+impl AuthorWatchers {
+    /// Registers the webhook. Returns an error if the URL is not supported.
+    pub fn add_webhook(&self, webhook: AuthorWebhook) -> Result<(), failure::Error> {
+        parse_url(&webhook.url)?;
+        let mut watchers = self.inner.lock().expect("Expected mutex lock");
+        watchers
+            .webhooks
+            .entry(webhook.author)
+            .or_default()
+            .insert(webhook.url);
+        Ok(())
+    }
+
+    /// Removes the webhook. Returns `false` if the webhook is not registered.
+    pub fn remove_webhook(&self, webhook: &AuthorWebhook) -> bool {
+        let mut watchers = self.inner.lock().expect("Expected mutex lock");
+        let urls = match watchers.webhooks.get_mut(&webhook.author) {
+            Some(urls) => urls,
+            None => return false,
+        };
+        let removed = urls.remove(&webhook.url);
+        if urls.is_empty() {
+            watchers.webhooks.remove(&webhook.author);
+        }
+        removed
+    }
+
+    /// Returns the registered webhooks.
+    pub fn webhooks(&self) -> Vec<AuthorWebhook> {
+        let watchers = self.inner.lock().expect("Expected mutex lock");
+        watchers
+            .webhooks
+            .iter()
+            .flat_map(|(author, urls)| {
+                urls.iter().map(move |url| AuthorWebhook {
+                    author: *author,
+                    url: url.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the transactions of the author are watched.
+    pub fn is_watched(&self, author: &PublicKey) -> bool {
+        let watchers = self.inner.lock().expect("Expected mutex lock");
+        watchers.webhooks.contains_key(author) || watchers.subscribed.contains(author)
+    }
+
+    /// Returns `true` if no key is watched.
+    pub fn is_empty(&self) -> bool {
+        let watchers = self.inner.lock().expect("Expected mutex lock");
+        watchers.webhooks.is_empty() && watchers.subscribed.is_empty()
+    }
+
+    /// Replaces the keys watched by the WebSocket clients.
+    pub(crate) fn set_subscribed<I>(&self, authors: I)
+    where
+        I: IntoIterator<Item = PublicKey>,
+    {
+        let mut watchers = self.inner.lock().expect("Expected mutex lock");
+        watchers.subscribed = authors.into_iter().collect();
+    }
+
+    /// Queues the notification for the delivery to the webhooks of its author.
+    pub(crate) fn notify_webhooks(&self, notification: &AuthorNotification) {
+        let mut watchers = self.inner.lock().expect("Expected mutex lock");
+        let urls: Vec<_> = match watchers.webhooks.get(&notification.author) {
+            Some(urls) => urls.iter().cloned().collect(),
+            None => return,
+        };
+        let body = serde_json::to_string(notification).expect("Cannot serialize notification");
+        let delivery = watchers.delivery.get_or_insert_with(spawn_delivery);
+        for url in urls {
+            match delivery.try_send((url, body.clone())) {
+                Ok(()) => {}
+                Err(TrySendError::Full((url, _))) => {
+                    warn!("Webhook queue is full, notification to {} is dropped", url)
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    error!("Webhook delivery thread has stopped");
+                    watchers.delivery = None;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn spawn_delivery() -> SyncSender<(String, String)> {
+    let (sender, receiver) = mpsc::sync_channel::<(String, String)>(WEBHOOK_QUEUE_SIZE);
+    thread::Builder::new()
+        .name("webhooks".to_owned())
+        .spawn(move || {
+            for (url, body) in receiver {
+                if let Err(e) = post(&url, &body) {
+                    warn!("Cannot deliver notification to webhook {}: {}", url, e);
+                }
+            }
+        })
+        .expect("Cannot spawn webhook delivery thread");
+    sender
+}
+
+/// Splits the URL into the host with the port and the path.
+pub(crate) fn parse_url(url: &str) -> Result<(String, String), failure::Error> {
+    const SCHEME: &str = "http://";
+    ensure!(
+        url.starts_with(SCHEME),
+        "Only http webhook URLs are supported, got {}",
+        url
+    );
+    let rest = &url[SCHEME.len()..];
+    let (host, path) = match rest.find('/') {
+        Some(position) => (&rest[..position], &rest[position..]),
+        None => (rest, "/"),
+    };
+    ensure!(!host.is_empty(), "Webhook URL {} has no host", url);
+    let host = if host.contains(':') {
+        host.to_owned()
+    } else {
+        format!("{}:80", host)
+    };
+    Ok((host, path.to_owned()))
+}
+
+pub(crate) fn post(url: &str, body: &str) -> Result<(), failure::Error> {
+    let (host, path) = parse_url(url)?;
+    let address = host
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format_err!("Cannot resolve {}", host))?;
+    let mut stream = TcpStream::connect_timeout(&address, WEBHOOK_TIMEOUT)?;
+    stream.set_read_timeout(Some(WEBHOOK_TIMEOUT))?;
+    stream.set_write_timeout(Some(WEBHOOK_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        body.len(),
+        body
+    )?;
+
+    // The status line starts with `HTTP/1.1 200`.
+    let mut status_line = [0; 12];
+    stream.read_exact(&mut status_line)?;
+    let status = String::from_utf8_lossy(&status_line[9..]).into_owned();
+    ensure!(status.starts_with('2'), "Webhook responded with {}", status);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::BufRead, io::BufReader, net::TcpListener};
+
+    use super::*;
+    use crate::crypto;
+
+    #[test]
+    fn webhooks_are_registered_per_author() {
+        let watchers = AuthorWatchers::default();
+        let author = crypto::gen_keypair().0;
+        assert!(watchers.is_empty());
+
+        let webhook = AuthorWebhook {
+            author,
+            url: "http://127.0.0.1:8000/deposits".to_owned(),
+        };
+        watchers.add_webhook(webhook.clone()).unwrap();
+        assert!(watchers.is_watched(&author));
+        assert_eq!(watchers.webhooks(), vec![webhook.clone()]);
+
+        let https = AuthorWebhook {
+            author,
+            url: "https://127.0.0.1/deposits".to_owned(),
+        };
+        assert!(watchers.add_webhook(https.clone()).is_err());
+        assert!(!watchers.remove_webhook(&https));
+
+        assert!(watchers.remove_webhook(&webhook));
+        assert!(!watchers.is_watched(&author));
+
+        watchers.set_subscribed(vec![author]);
+        assert!(watchers.is_watched(&author));
+        watchers.set_subscribed(vec![]);
+        assert!(watchers.is_empty());
+    }
+
+    #[test]
+    fn notification_is_posted_to_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/deposits", listener.local_addr().unwrap());
+        let notification = AuthorNotification {
+            author: crypto::gen_keypair().0,
+            tx_hash: crypto::hash(&[1, 2, 3]),
+            event: AuthorEvent::Rejected {
+                reason: "Invalid transaction".to_owned(),
+            },
+        };
+
+        let watchers = AuthorWatchers::default();
+        watchers
+            .add_webhook(AuthorWebhook {
+                author: notification.author,
+                url,
+            })
+            .unwrap();
+        watchers.notify_webhooks(&notification);
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        assert_eq!(request_line, "POST /deposits HTTP/1.1\r\n");
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header == "\r\n" {
+                break;
+            }
+            if header.starts_with("Content-Length: ") {
+                content_length = header["Content-Length: ".len()..].trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        let received: AuthorNotification = serde_json::from_slice(&body).unwrap();
+        assert_eq!(received, notification);
+        stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+    }
+}
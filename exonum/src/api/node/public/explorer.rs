@@ -15,32 +15,63 @@
 //! Exonum blockchain explorer API.
 
 use actix::Arbiter;
-use actix_web::{http, ws, AsyncResponder, Error as ActixError, FromRequest, Query};
+use actix_web::{http, ws, AsyncResponder, Error as ActixError, FromRequest, HttpResponse, Query};
 use chrono::{DateTime, Utc};
-use futures::{Future, IntoFuture};
+use exonum_merkledb::ListProof;
+use futures::{
+    future::{self, Either},
+    Future, IntoFuture,
+};
+use hex::FromHex;
+use tokio::timer::Delay;
 
+use std::collections::BTreeMap;
 use std::ops::{Bound, Range};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::{
     api::{
         backends::actix::{
             self as actix_backend, FutureResponse, HttpRequest, RawHandler, RequestHandler,
         },
+        node::public::{
+            tx_waiters::{TransactionWaiters, WaitFor},
+            BlockProofCache,
+        },
         websocket::{Server, Session, SubscriptionType, TransactionFilter},
-        Error as ApiError, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+        Error as ApiError, FutureResult, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+    },
+    blockchain::{
+        Block, Schema, SharedNodeState, TransactionEvent, TransactionResult, TransactionTag,
+        TxLocation,
+    },
+    crypto::{self, Hash, PublicKey},
+    explorer::{
+        self, median_precommits_time, BlockFinality, BlockHeaderCache, BlockchainExplorer,
+        ListPage, TransactionInfo, TxStatus,
     },
-    blockchain::{Block, SharedNodeState},
-    crypto::Hash,
-    explorer::{self, median_precommits_time, BlockchainExplorer, TransactionInfo},
-    helpers::Height,
-    messages::{Message, Precommit, RawTransaction, Signed, SignedMessage},
+    helpers::{Height, Milliseconds},
+    messages::{
+        self, Message, Precommit, RawTransaction, Signed, SignedMessage, EMPTY_SIGNED_MESSAGE_SIZE,
+    },
+    node::{RejectedTransaction, RejectedTransactionsLog, RejectionReason, TransactionSource},
 };
 
 /// The maximum number of blocks to return per blocks request, in this way
 /// the parameter limits the maximum execution time for such requests.
 pub const MAX_BLOCKS_PER_REQUEST: usize = 1000;
 
+/// The maximum number of list elements to return per page request.
+pub const MAX_LIST_PAGE_SIZE: u64 = 1000;
+
+/// Time the transaction submission waits for the requested stage if the timeout
+/// is not specified.
+pub const DEFAULT_TX_WAIT_TIMEOUT: Milliseconds = 30_000;
+
+/// The maximum time the transaction submission can wait for the requested stage.
+pub const MAX_TX_WAIT_TIMEOUT: Milliseconds = 300_000;
+
 /// Information on blocks coupled with the corresponding range in the blockchain.
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct BlocksRange {
@@ -75,6 +106,10 @@ pub struct BlockInfo {
     /// Median time from the block precommits.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time: Option<DateTime<Utc>>,
+
+    /// Finality statement of the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finality: Option<BlockFinality>,
 }
 
 /// Blocks in range parameters.
@@ -106,6 +141,10 @@ pub struct BlocksQuery {
     /// corresponding returned blocks.
     #[serde(default)]
     pub add_precommits: bool,
+    /// If true, then the returned `BlocksRange.finality` will contain finality statements
+    /// for the corresponding returned blocks.
+    #[serde(default)]
+    pub add_finality: bool,
 }
 
 /// Block query parameters.
@@ -122,11 +161,100 @@ impl BlockQuery {
     }
 }
 
+/// Query of the first block committed at or after a specific time.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BlockAtTimeQuery {
+    /// The time of the desired block.
+    pub ts: DateTime<Utc>,
+}
+
+impl BlockAtTimeQuery {
+    /// Creates a new query with the given time.
+    pub fn new(ts: DateTime<Utc>) -> Self {
+        Self { ts }
+    }
+}
+
+/// Parameters of the block transactions page query.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BlockTransactionsQuery {
+    /// The height of the block.
+    pub height: Height,
+    /// Index of the first transaction on the page. The default value is 0.
+    #[serde(default)]
+    pub offset: u64,
+    /// The maximum number of transactions on the page. Should not be greater than
+    /// `MAX_LIST_PAGE_SIZE`, which is also the default value.
+    pub limit: Option<u64>,
+}
+
+/// Page of the transactions in a block.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockTransactionsPage {
+    /// Block header as recorded in the blockchain. The page is verified against
+    /// its `tx_hash` field.
+    pub block: Block,
+    /// Hashes of the transactions on the page with the range proof for them.
+    pub transactions: ListPage<Hash>,
+}
+
+/// Parameters of the block events page query.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BlockEventsQuery {
+    /// The height of the block.
+    pub height: Height,
+    /// Index of the first event on the page. The default value is 0.
+    #[serde(default)]
+    pub offset: u64,
+    /// The maximum number of events on the page. Should not be greater than
+    /// `MAX_LIST_PAGE_SIZE`, which is also the default value.
+    pub limit: Option<u64>,
+}
+
+/// Page of the events emitted by the transactions in a block.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockEventsPage {
+    /// Block header as recorded in the blockchain. The page is verified against
+    /// its `events_hash` header; for the blocks without events the header is absent,
+    /// and the page proves that the list of events is empty.
+    pub block: Block,
+    /// Events on the page with the range proof for them.
+    pub events: ListPage<TransactionEvent>,
+}
+
 /// Raw Transaction in hex representation.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TransactionHex {
     /// The hex value of the transaction to be broadcasted.
     pub tx_body: String,
+    /// If set, the submission does not return until the transaction reaches the given
+    /// stage, or is rejected by the node. Supported by the `v1/transactions` HTTP
+    /// endpoint only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for: Option<WaitFor>,
+    /// The maximum time to wait for the `wait_for` stage in milliseconds.
+    /// `DEFAULT_TX_WAIT_TIMEOUT` is used if the value is absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_timeout: Option<Milliseconds>,
+    /// Correlation tag stored by the node together with the transaction.
+    /// See [`TransactionTag`] for details.
+    ///
+    /// [`TransactionTag`]: ../../../../blockchain/struct.TransactionTag.html
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<TransactionTag>,
+}
+
+impl TransactionHex {
+    /// Creates a submission of the given hex-encoded transaction which does not wait
+    /// for the transaction to be processed.
+    pub fn new(tx_body: String) -> Self {
+        Self {
+            tx_body,
+            wait_for: None,
+            wait_timeout: None,
+            tag: None,
+        }
+    }
 }
 
 /// Transaction response.
@@ -150,6 +278,97 @@ impl TransactionQuery {
     }
 }
 
+/// Query for the pool transactions sharing conflict keys.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ConflictsQuery {
+    /// Conflict key. If absent, the keys declared by several pool transactions are returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+/// Pool transactions sharing a conflict key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConflictGroup {
+    /// Conflict key.
+    pub key: String,
+    /// Hashes of the pool transactions declaring the key.
+    pub tx_hashes: Vec<Hash>,
+}
+
+/// Query parameters of the subscription to the transactions of the author.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct AuthorQuery {
+    /// Public key of the author.
+    pub author: PublicKey,
+}
+
+/// Transaction status query parameters.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct TransactionStatusQuery {
+    /// If true, then the status of a committed transaction includes the proof
+    /// of its inclusion into the block.
+    #[serde(default)]
+    pub with_proof: bool,
+}
+
+/// Status of a transaction, as returned by the `v1/transactions/{hash}/status` endpoint.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TransactionStatus {
+    /// Transaction is not known to the node.
+    Unknown,
+    /// Transaction is in the memory pool, but not yet committed to the blockchain.
+    InPool,
+    /// Transaction is committed to the blockchain.
+    Committed {
+        /// Location of the transaction in the blockchain.
+        location: TxLocation,
+        /// Execution result of the transaction.
+        #[serde(with = "TxStatus")]
+        status: TransactionResult,
+        /// Proof of the transaction inclusion into the block; present only if requested.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location_proof: Option<ListProof<Hash>>,
+        /// Tag of the transaction, if it has been submitted to this node with a tag.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<TransactionTag>,
+    },
+}
+
+fn explorer_with_cache<'a>(
+    state: &'a ServiceApiState,
+    header_cache: Option<&BlockHeaderCache>,
+) -> BlockchainExplorer<'a> {
+    let explorer = BlockchainExplorer::new(state.blockchain());
+    match header_cache {
+        Some(cache) => explorer.with_header_cache(cache.clone()),
+        None => explorer,
+    }
+}
+
+/// Rejects the request if the node sheds load because of its resource usage.
+fn check_not_overloaded(node_state: &SharedNodeState) -> Result<(), ApiError> {
+    if node_state.is_overloaded() {
+        Err(ApiError::ServiceUnavailable(
+            "Node is approaching its resource limits".to_owned(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks the limit of a list page request, which defaults to `MAX_LIST_PAGE_SIZE`.
+fn page_limit(limit: Option<u64>) -> Result<u64, ApiError> {
+    let limit = limit.unwrap_or(MAX_LIST_PAGE_SIZE);
+    if limit == 0 || limit > MAX_LIST_PAGE_SIZE {
+        return Err(ApiError::BadRequest(format!(
+            "Page limit should be between 1 and {}",
+            MAX_LIST_PAGE_SIZE
+        )));
+    }
+    Ok(limit)
+}
+
 /// Exonum blockchain explorer API.
 #[derive(Debug, Clone, Copy)]
 pub struct ExplorerApi;
@@ -161,7 +380,15 @@ impl ExplorerApi {
     ///
     /// [`BlocksQuery`]: struct.BlocksQuery.html
     pub fn blocks(state: &ServiceApiState, query: BlocksQuery) -> Result<BlocksRange, ApiError> {
-        let explorer = BlockchainExplorer::new(state.blockchain());
+        Self::blocks_with_cache(state, query, None)
+    }
+
+    fn blocks_with_cache(
+        state: &ServiceApiState,
+        query: BlocksQuery,
+        header_cache: Option<&BlockHeaderCache>,
+    ) -> Result<BlocksRange, ApiError> {
+        let explorer = explorer_with_cache(state, header_cache);
         if query.count > MAX_BLOCKS_PER_REQUEST {
             return Err(ApiError::BadRequest(format!(
                 "Max block count per request exceeded ({})",
@@ -195,6 +422,12 @@ impl ExplorerApi {
             .map(|block| BlockInfo {
                 txs: None,
 
+                finality: if query.add_finality {
+                    Some(block.finality())
+                } else {
+                    None
+                },
+
                 time: if query.add_blocks_time {
                     Some(median_precommits_time(&block.precommits()))
                 } else {
@@ -225,7 +458,15 @@ impl ExplorerApi {
 
     /// Returns the content for a block at a specific height.
     pub fn block(state: &ServiceApiState, query: BlockQuery) -> Result<BlockInfo, ApiError> {
-        BlockchainExplorer::new(state.blockchain())
+        Self::block_with_cache(state, query, None)
+    }
+
+    fn block_with_cache(
+        state: &ServiceApiState,
+        query: BlockQuery,
+        header_cache: Option<&BlockHeaderCache>,
+    ) -> Result<BlockInfo, ApiError> {
+        explorer_with_cache(state, header_cache)
             .block(query.height)
             .map(From::from)
             .ok_or_else(|| {
@@ -233,6 +474,118 @@ impl ExplorerApi {
             })
     }
 
+    /// Returns the first block with the consensus time at or after the specified time.
+    /// The block is found by a binary search over the consensus times of the blocks,
+    /// so reporting systems can map wall-clock ranges to heights.
+    pub fn block_at_time(
+        state: &ServiceApiState,
+        query: BlockAtTimeQuery,
+    ) -> Result<BlockInfo, ApiError> {
+        BlockchainExplorer::new(state.blockchain())
+            .block_at_time(query.ts)
+            .map(From::from)
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("Block committed after {} not found", query.ts))
+            })
+    }
+
+    /// Returns a page of the transaction hashes in the block at a specific height together
+    /// with a single proof for the page, so that clients can verify each page against
+    /// the block header instead of requesting proofs for separate transactions.
+    pub fn block_transactions(
+        state: &ServiceApiState,
+        query: BlockTransactionsQuery,
+    ) -> Result<BlockTransactionsPage, ApiError> {
+        Self::block_transactions_with_cache(state, query, None)
+    }
+
+    fn block_transactions_with_cache(
+        state: &ServiceApiState,
+        query: BlockTransactionsQuery,
+        header_cache: Option<&BlockHeaderCache>,
+    ) -> Result<BlockTransactionsPage, ApiError> {
+        let limit = page_limit(query.limit)?;
+        explorer_with_cache(state, header_cache)
+            .block_transactions_page(query.height, query.offset, limit)
+            .map(|(block, transactions)| BlockTransactionsPage {
+                block,
+                transactions,
+            })
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("Block for height: {} not found", query.height))
+            })
+    }
+
+    /// Returns a page of the events emitted by the transactions in the block at a specific
+    /// height together with a single proof for the page, which is checked against
+    /// the `events_hash` header of the block.
+    pub fn block_events(
+        state: &ServiceApiState,
+        query: BlockEventsQuery,
+    ) -> Result<BlockEventsPage, ApiError> {
+        let limit = page_limit(query.limit)?;
+        BlockchainExplorer::new(state.blockchain())
+            .block_events_page(query.height, query.offset, limit)
+            .map(|(block, events)| BlockEventsPage { block, events })
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("Block for height: {} not found", query.height))
+            })
+    }
+
+    /// Returns the block at a specific height together with the precommits authorizing it.
+    ///
+    /// Serialized proofs are cached, and every response carries an `ETag` derived from
+    /// the block hash. Requests with a matching `If-None-Match` header receive
+    /// `304 Not Modified` without the body.
+    pub fn handle_block_proof(
+        name: &'static str,
+        backend: &mut actix_backend::ApiBuilder,
+        cache: BlockProofCache,
+    ) {
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let future = Query::from_request(&request, &Default::default())
+                .map(Query::into_inner)
+                .and_then(|query: BlockQuery| {
+                    Self::block_proof_response(&request, &cache, query.height).map_err(From::from)
+                })
+                .into_future();
+            Box::new(future)
+        };
+
+        backend.raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::GET,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+    }
+
+    fn block_proof_response(
+        request: &HttpRequest,
+        cache: &BlockProofCache,
+        height: Height,
+    ) -> Result<HttpResponse, ApiError> {
+        let snapshot = request.state().snapshot();
+        let proof = cache.get_or_load(&*snapshot, height)?.ok_or_else(|| {
+            ApiError::NotFound(format!("Block for height: {} not found", height))
+        })?;
+
+        let not_modified = request
+            .headers()
+            .get(http::header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, |value| proof.matches(value));
+        if not_modified {
+            return Ok(HttpResponse::NotModified()
+                .header(http::header::ETAG, proof.etag)
+                .finish());
+        }
+
+        Ok(HttpResponse::Ok()
+            .header(http::header::ETAG, proof.etag)
+            .content_type("application/json")
+            .body(proof.body))
+    }
+
     /// Searches for a transaction, either committed or uncommitted, by the hash.
     pub fn transaction_info(
         state: &ServiceApiState,
@@ -246,26 +599,288 @@ impl ExplorerApi {
                 ApiError::NotFound(description)
             })
     }
+
+    /// Groups the transactions in the pool by their conflict keys. Without a key in
+    /// the query, only the keys declared by several transactions are returned.
+    pub fn transaction_conflicts(
+        state: &ServiceApiState,
+        query: ConflictsQuery,
+    ) -> Result<Vec<ConflictGroup>, ApiError> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+        let transactions = schema.transactions();
+
+        let mut groups: BTreeMap<String, Vec<Hash>> = BTreeMap::new();
+        for tx_hash in schema.transactions_pool().iter() {
+            let keys = transactions
+                .get(&tx_hash)
+                .and_then(|tx| state.blockchain().tx_from_raw(tx.payload().clone()).ok())
+                .map(|tx| tx.conflict_keys())
+                .unwrap_or_default();
+            for key in keys {
+                if query.key.as_ref().map_or(true, |queried| *queried == key) {
+                    groups.entry(key).or_default().push(tx_hash);
+                }
+            }
+        }
+
+        Ok(groups
+            .into_iter()
+            .filter(|(_, tx_hashes)| query.key.is_some() || tx_hashes.len() > 1)
+            .map(|(key, tx_hashes)| ConflictGroup { key, tx_hashes })
+            .collect())
+    }
+
+    /// Returns the status of a transaction without its content. Unlike
+    /// [`transaction_info`](#method.transaction_info), unknown transactions are reported
+    /// as a regular status rather than an error.
+    pub fn transaction_status(
+        state: &ServiceApiState,
+        node_state: &SharedNodeState,
+        hash: &Hash,
+        query: TransactionStatusQuery,
+    ) -> TransactionStatus {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let location = match schema.transactions_locations().get(hash) {
+            Some(location) => location,
+            None if schema.transactions().contains(hash) || node_state.is_tx_cached(hash) => {
+                return TransactionStatus::InPool;
+            }
+            None => return TransactionStatus::Unknown,
+        };
+        let location_proof = if query.with_proof {
+            Some(
+                schema
+                    .block_transactions(location.block_height())
+                    .get_proof(location.position_in_block()),
+            )
+        } else {
+            None
+        };
+        // Results are recorded together with locations when the block is committed.
+        let status = schema.transaction_results().get(hash).unwrap();
+
+        TransactionStatus::Committed {
+            location,
+            status,
+            location_proof,
+            tag: schema.transaction_tag(hash),
+        }
+    }
+
+    /// Adds the transaction status endpoint with the hash of the transaction
+    /// passed as a path segment.
+    pub fn handle_transaction_status(
+        name: &'static str,
+        backend: &mut actix_backend::ApiBuilder,
+        node_state: SharedNodeState,
+    ) {
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let future = Query::from_request(&request, &Default::default())
+                .map(Query::into_inner)
+                .and_then(|query: TransactionStatusQuery| {
+                    let hash = request.match_info().get("hash").unwrap_or_default();
+                    let hash = Hash::from_hex(hash).map_err(|e| {
+                        ApiError::BadRequest(format!("Invalid transaction hash: {}", e))
+                    })?;
+                    let status =
+                        Self::transaction_status(request.state(), &node_state, &hash, query);
+                    Ok(HttpResponse::Ok().json(status))
+                })
+                .into_future();
+            Box::new(future)
+        };
+
+        backend.raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::GET,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+    }
+
     /// Adds transaction into unconfirmed tx pool, and broadcast transaction to other nodes.
+    /// The `wait_for` field of the query is ignored; see `add_transaction_and_wait`.
     pub fn add_transaction(
         state: &ServiceApiState,
         query: TransactionHex,
     ) -> Result<TransactionResponse, ApiError> {
-        use crate::events::error::into_failure;
-        use crate::messages::ProtocolMessage;
-
-        let buf: Vec<u8> = ::hex::decode(query.tx_body).map_err(into_failure)?;
-        let signed = SignedMessage::from_raw_buffer(buf)?;
-        let tx_hash = signed.hash();
-        let signed = RawTransaction::try_from(Message::deserialize(signed)?)
-            .map_err(|_| format_err!("Couldn't deserialize transaction message."))?;
+        let (tx_hash, signed) = Self::parse_transaction(state, &query, None)?;
         let _ = state
             .sender()
-            .broadcast_transaction(signed)
+            .broadcast_tagged_transaction(signed, query.tag)
             .map_err(ApiError::from);
         Ok(TransactionResponse { tx_hash })
     }
 
+    /// Adds transaction into unconfirmed tx pool like `add_transaction` does. If
+    /// the `wait_for` field of the query is set, the returned future is resolved
+    /// once the transaction reaches the requested stage; it fails if the node rejects
+    /// the transaction or the timeout expires.
+    ///
+    /// Transactions rejected by the API are recorded into `rejected`.
+    pub fn add_transaction_and_wait(
+        state: &ServiceApiState,
+        query: TransactionHex,
+        waiters: &TransactionWaiters,
+        rejected: &RejectedTransactionsLog,
+    ) -> FutureResult<TransactionResponse> {
+        let stage = match query.wait_for {
+            Some(stage) => stage,
+            None => {
+                let response = Self::parse_transaction(state, &query, Some(rejected)).map(
+                    |(tx_hash, signed)| {
+                        let _ = state
+                            .sender()
+                            .broadcast_tagged_transaction(signed, query.tag);
+                        TransactionResponse { tx_hash }
+                    },
+                );
+                return Box::new(future::result(response));
+            }
+        };
+        let timeout = query.wait_timeout.unwrap_or(DEFAULT_TX_WAIT_TIMEOUT);
+        if timeout > MAX_TX_WAIT_TIMEOUT {
+            return Box::new(future::err(ApiError::BadRequest(format!(
+                "Wait timeout is greater than the maximum value {}",
+                MAX_TX_WAIT_TIMEOUT
+            ))));
+        }
+        let (tx_hash, signed) = match Self::parse_transaction(state, &query, Some(rejected)) {
+            Ok(parsed) => parsed,
+            Err(e) => return Box::new(future::err(e)),
+        };
+
+        // The waiter is registered first so that the notification cannot be missed.
+        let receiver = waiters.wait(tx_hash, stage);
+        let reached = {
+            let snapshot = state.snapshot();
+            let schema = Schema::new(&snapshot);
+            match stage {
+                WaitFor::Pool => schema.transactions().contains(&tx_hash),
+                WaitFor::Commit => schema.transactions_locations().contains(&tx_hash),
+            }
+        };
+        if let Err(e) = state
+            .sender()
+            .broadcast_tagged_transaction(signed, query.tag)
+        {
+            return Box::new(future::err(ApiError::from(e)));
+        }
+        if reached {
+            return Box::new(future::ok(TransactionResponse { tx_hash }));
+        }
+
+        let deadline = Delay::new(Instant::now() + Duration::from_millis(timeout));
+        let future = receiver.select2(deadline).then(move |result| match result {
+            Ok(Either::A((Ok(()), _))) => Ok(TransactionResponse { tx_hash }),
+            Ok(Either::A((Err(reason), _))) => Err(ApiError::BadRequest(format!(
+                "Transaction {:?} is rejected: {}",
+                tx_hash, reason
+            ))),
+            Ok(Either::B(_)) => Err(ApiError::InternalError(format_err!(
+                "Transaction {:?} has not reached the {:?} stage in {} ms",
+                tx_hash,
+                stage,
+                timeout
+            ))),
+            Err(Either::A(_)) => Err(ApiError::InternalError(format_err!(
+                "Node has stopped before transaction {:?} reached the {:?} stage",
+                tx_hash,
+                stage
+            ))),
+            Err(Either::B((e, _))) => Err(ApiError::InternalError(e.into())),
+        });
+        Box::new(future)
+    }
+
+    /// Returns the hash of the transaction the node would use for it, without
+    /// submitting the transaction. The `wait_for` and `wait_timeout` fields
+    /// of the query are ignored.
+    pub fn transaction_hash(
+        _state: &ServiceApiState,
+        query: TransactionHex,
+    ) -> Result<TransactionResponse, ApiError> {
+        let buf: Vec<u8> = ::hex::decode(&query.tx_body)
+            .map_err(|e| ApiError::BadRequest(format!("Invalid hex: {}", e)))?;
+        let tx_hash =
+            messages::transaction_hash(&buf).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        Ok(TransactionResponse { tx_hash })
+    }
+
+    /// Decodes the transaction from the query. If `rejected` is specified, invalid
+    /// transactions are recorded into it.
+    fn parse_transaction(
+        state: &ServiceApiState,
+        query: &TransactionHex,
+        rejected: Option<&RejectedTransactionsLog>,
+    ) -> Result<(Hash, Signed<RawTransaction>), ApiError> {
+        use crate::events::error::into_failure;
+
+        let buf: Vec<u8> = ::hex::decode(&query.tx_body).map_err(into_failure)?;
+        let tx_hash = crypto::hash(&buf);
+        match Self::decode_transaction(state, buf) {
+            Ok(signed) => Ok((tx_hash, signed)),
+            Err((reason, e)) => {
+                if let Some(rejected) = rejected {
+                    rejected.push(RejectedTransaction::new(
+                        tx_hash,
+                        reason,
+                        e.to_string(),
+                        TransactionSource::Api,
+                        SystemTime::now(),
+                    ));
+                }
+                Err(ApiError::from(e))
+            }
+        }
+    }
+
+    fn decode_transaction(
+        state: &ServiceApiState,
+        buf: Vec<u8>,
+    ) -> Result<Signed<RawTransaction>, (RejectionReason, failure::Error)> {
+        use crate::messages::ProtocolMessage;
+
+        let max_message_len = Schema::new(&state.snapshot())
+            .actual_configuration()
+            .consensus
+            .max_message_len as usize;
+        if buf.len() > max_message_len {
+            let e = format_err!(
+                "Transaction size {} exceeds the maximum message length {}",
+                buf.len(),
+                max_message_len
+            );
+            return Err((RejectionReason::TooLarge, e));
+        }
+
+        // Shorter messages cannot contain a signature.
+        let reason = if buf.len() > EMPTY_SIGNED_MESSAGE_SIZE {
+            RejectionReason::InvalidSignature
+        } else {
+            RejectionReason::Malformed
+        };
+        let signed = SignedMessage::from_raw_buffer(buf).map_err(|e| (reason, e))?;
+        let message = Message::deserialize(signed).map_err(|e| (RejectionReason::Malformed, e))?;
+        let transaction = RawTransaction::try_from(message).map_err(|_| {
+            let e = format_err!("Couldn't deserialize transaction message.");
+            (RejectionReason::Malformed, e)
+        })?;
+
+        let network_id = Schema::new(&state.snapshot()).network_id();
+        if transaction.network_id() != network_id {
+            let e = format_err!(
+                "Transaction is signed for the network {}, expected {}",
+                transaction.network_id(),
+                network_id
+            );
+            return Err((RejectionReason::WrongNetwork, e));
+        }
+        Ok(transaction)
+    }
+
     /// Subscribes to events.
     pub fn handle_ws<Q>(
         name: &'static str,
@@ -284,7 +899,10 @@ impl ExplorerApi {
             let service_api_state = service_api_state.clone();
             let mut address = server.lock().expect("Expected mutex lock");
             if address.is_none() {
-                *address = Some(Arbiter::start(|_| Server::new(service_api_state)));
+                let author_watchers = shared_node_state.author_watchers().clone();
+                *address = Some(Arbiter::start(|_| {
+                    Server::new(service_api_state, author_watchers)
+                }));
 
                 shared_node_state.set_broadcast_server_address(address.to_owned().unwrap());
             }
@@ -340,6 +958,20 @@ impl ExplorerApi {
                     .unwrap_or(Ok(SubscriptionType::None))
             },
         );
+        // Subscription for the transactions of the author.
+        Self::handle_ws(
+            "v1/authors/subscribe",
+            api_scope.web_backend(),
+            service_api_state.clone(),
+            shared_node_state.clone(),
+            |request| {
+                Query::from_request(request, &Default::default()).map(
+                    |query: Query<AuthorQuery>| SubscriptionType::Author {
+                        author: query.into_inner().author,
+                    },
+                )
+            },
+        );
         // Default websocket connection.
         Self::handle_ws(
             "v1/ws",
@@ -348,11 +980,59 @@ impl ExplorerApi {
             shared_node_state.clone(),
             |_| Ok(SubscriptionType::None),
         );
+        Self::handle_block_proof(
+            "v1/block/proof",
+            api_scope.web_backend(),
+            shared_node_state.block_proof_cache().clone(),
+        );
+        Self::handle_transaction_status(
+            "v1/transactions/{hash}/status",
+            api_scope.web_backend(),
+            shared_node_state.clone(),
+        );
+        #[cfg(feature = "graphql")]
+        super::graphql::wire(api_scope);
+        let header_cache = shared_node_state.block_header_cache().clone();
+        let blocks_cache = header_cache.clone();
+        let page_cache = header_cache.clone();
+        let tx_waiters = shared_node_state.transaction_waiters().clone();
+        let rejected_txs = shared_node_state.rejected_transactions().clone();
+        // Lists of blocks and transactions are not served while the node sheds load.
+        let blocks_node_state = shared_node_state.clone();
+        let page_node_state = shared_node_state.clone();
         api_scope
-            .endpoint("v1/blocks", Self::blocks)
-            .endpoint("v1/block", Self::block)
+            .endpoint(
+                "v1/blocks",
+                move |state: &ServiceApiState, query: BlocksQuery| {
+                    check_not_overloaded(&blocks_node_state)?;
+                    Self::blocks_with_cache(state, query, Some(&blocks_cache))
+                },
+            )
+            .endpoint(
+                "v1/block",
+                move |state: &ServiceApiState, query: BlockQuery| {
+                    Self::block_with_cache(state, query, Some(&header_cache))
+                },
+            )
+            .endpoint("v1/blocks/at_time", Self::block_at_time)
+            .endpoint(
+                "v1/block/transactions",
+                move |state: &ServiceApiState, query: BlockTransactionsQuery| {
+                    check_not_overloaded(&page_node_state)?;
+                    Self::block_transactions_with_cache(state, query, Some(&page_cache))
+                },
+            )
+            .endpoint("v1/block/events", Self::block_events)
             .endpoint("v1/transactions", Self::transaction_info)
-            .endpoint_mut("v1/transactions", Self::add_transaction)
+            .endpoint("v1/transactions/conflicts", Self::transaction_conflicts)
+            .endpoint_mut(
+                "v1/transactions",
+                move |state: &ServiceApiState, query: TransactionHex| {
+                    check_not_overloaded(&shared_node_state)?;
+                    Self::add_transaction_and_wait(state, query, &tx_waiters, &rejected_txs)
+                },
+            )
+            .endpoint_mut("v1/transactions/hash", Self::transaction_hash)
     }
 }
 
@@ -376,6 +1056,7 @@ impl<'a> From<explorer::BlockInfo<'a>> for BlockInfo {
             precommits: Some(inner.precommits().to_vec()),
             txs: Some(txs),
             time: Some(median_precommits_time(&inner.precommits())),
+            finality: Some(inner.finality()),
         }
     }
 }
@@ -0,0 +1,642 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GraphQL API of the blockchain explorer.
+//!
+//! The API is served at the `v1/graphql` endpoint of the explorer and allows to query
+//! blocks, transactions together with their decoded payloads and execution results,
+//! and the history of the blockchain configuration in a single request. Lists are
+//! paginated with opaque cursors: a page is requested with the `first` and `after`
+//! arguments, and the cursor of the next page is returned in `pageInfo.endCursor`.
+//! The `graphiql` page of the explorer provides an interactive query editor.
+//!
+//! The API is available only with the `graphql` feature enabled.
+//!
+//! # Examples
+//!
+//! ```graphql
+//! {
+//!   blocks(first: 2) {
+//!     nodes {
+//!       height
+//!       hash
+//!       transactions {
+//!         nodes { hash payload status { type code description } }
+//!       }
+//!     }
+//!     pageInfo { hasNextPage endCursor }
+//!   }
+//! }
+//! ```
+
+use actix_web::{http, HttpResponse};
+use futures::future;
+use hex::FromHex;
+use juniper::{
+    http::{graphiql::graphiql_source, GraphQLRequest},
+    EmptyMutation, FieldError, FieldResult, GraphQLObject, RootNode,
+};
+use serde_json::Value;
+
+use std::{convert::TryFrom, sync::Arc};
+
+use crate::api::{
+    backends::actix::{FutureResponse, HttpRequest, RawHandler, RequestHandler},
+    Error as ApiError, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+};
+use crate::blockchain::{Block, Blockchain, Schema, StoredConfiguration, TxLocation};
+use crate::crypto::Hash;
+use crate::explorer::{median_precommits_time, TxStatus};
+use crate::helpers::Height;
+use crate::messages::{RawTransaction, Signed};
+use exonum_merkledb::{ObjectHash, Snapshot};
+
+/// Number of items in a page if the `first` argument is not specified.
+pub const DEFAULT_PAGE_SIZE: i32 = 20;
+
+/// The maximum number of items in a page.
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+/// GraphQL schema of the explorer.
+pub type ExplorerSchema = RootNode<'static, Query, EmptyMutation<GraphQLContext>>;
+
+/// Creates the GraphQL schema of the explorer.
+pub fn schema() -> ExplorerSchema {
+    ExplorerSchema::new(Query, EmptyMutation::new())
+}
+
+/// Context of a GraphQL query. All fields of the query are resolved against
+/// the same snapshot of the blockchain state.
+pub struct GraphQLContext {
+    blockchain: Blockchain,
+    snapshot: Box<dyn Snapshot>,
+}
+
+impl GraphQLContext {
+    /// Creates a context reading the current state of the blockchain.
+    pub fn new(blockchain: Blockchain) -> Self {
+        let snapshot = blockchain.snapshot();
+        Self {
+            blockchain,
+            snapshot,
+        }
+    }
+
+    fn schema(&self) -> Schema<&dyn Snapshot> {
+        Schema::new(self.snapshot.as_ref())
+    }
+
+    fn block(&self, height: Height) -> Option<BlockNode> {
+        let schema = self.schema();
+        let hash = schema.block_hash_by_height(height)?;
+        let header = schema.blocks().get(&hash)?;
+        Some(BlockNode { header, hash })
+    }
+
+    fn transaction(&self, hash: Hash) -> Option<TransactionNode> {
+        let message = self.schema().transactions().get(&hash)?;
+        Some(TransactionNode { hash, message })
+    }
+}
+
+impl juniper::Context for GraphQLContext {}
+
+/// Information about the page of a paginated list.
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct PageInfo {
+    /// Whether there are more items after the page.
+    pub has_next_page: bool,
+    /// Cursor of the last item of the page, which should be passed as the `after`
+    /// argument to get the next page.
+    pub end_cursor: Option<String>,
+}
+
+/// Execution status of a committed transaction.
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct TransactionStatus {
+    /// Status type: `success`, `error`, `panic`, `unauthorized`, `out-of-gas`,
+    /// `insufficient-fee`, `block-limit-exceeded` or `service-inactive`.
+    #[graphql(name = "type")]
+    pub kind: String,
+    /// Error code returned by the transaction.
+    pub code: Option<i32>,
+    /// Description of the error.
+    pub description: Option<String>,
+}
+
+/// Location of a committed transaction.
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct TransactionLocation {
+    /// Height of the block including the transaction.
+    pub block_height: i32,
+    /// Zero-based position of the transaction in the block.
+    pub position_in_block: i32,
+}
+
+/// Keys of a validator.
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct ValidatorKeysNode {
+    /// Consensus key of the validator.
+    pub consensus_key: String,
+    /// Service key of the validator.
+    pub service_key: String,
+}
+
+/// Committed block.
+#[derive(Debug, Clone)]
+pub struct BlockNode {
+    header: Block,
+    hash: Hash,
+}
+
+#[juniper::object(Context = GraphQLContext, name = "Block")]
+impl BlockNode {
+    /// Height of the block.
+    fn height(&self) -> FieldResult<i32> {
+        to_int(self.header.height().0)
+    }
+
+    /// Hash of the block.
+    fn hash(&self) -> String {
+        hex::encode(self.hash)
+    }
+
+    /// Identifier of the validator which has proposed the block.
+    fn proposer_id(&self) -> i32 {
+        i32::from(self.header.proposer_id().0)
+    }
+
+    /// Number of transactions in the block.
+    fn tx_count(&self) -> FieldResult<i32> {
+        to_int(u64::from(self.header.tx_count()))
+    }
+
+    /// Hash of the previous block.
+    fn prev_hash(&self) -> String {
+        hex::encode(self.header.prev_hash())
+    }
+
+    /// Root hash of the Merkle tree of the block transactions.
+    fn tx_hash(&self) -> String {
+        hex::encode(self.header.tx_hash())
+    }
+
+    /// Hash of the blockchain state after the block is applied.
+    fn state_hash(&self) -> String {
+        hex::encode(self.header.state_hash())
+    }
+
+    /// Median time of the precommits authorizing the block, in RFC 3339 format.
+    fn time(&self, context: &GraphQLContext) -> Option<String> {
+        let precommits = context
+            .schema()
+            .precommits(&self.hash)
+            .iter()
+            .collect::<Vec<_>>();
+        if precommits.is_empty() {
+            None
+        } else {
+            Some(median_precommits_time(&precommits).to_rfc3339())
+        }
+    }
+
+    /// Transactions of the block in the order of their execution.
+    fn transactions(
+        &self,
+        context: &GraphQLContext,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<TransactionConnection> {
+        let limit = page_size(first)?;
+        let start = match after {
+            Some(cursor) => parse_cursor(&cursor)?.saturating_add(1),
+            None => 0,
+        };
+        let hashes = context.schema().block_transactions(self.header.height());
+        let end = hashes.len().min(start.saturating_add(limit));
+
+        let nodes = (start..end)
+            .filter_map(|position| context.transaction(hashes.get(position)?))
+            .collect();
+        Ok(TransactionConnection {
+            nodes,
+            page_info: PageInfo {
+                has_next_page: end < hashes.len(),
+                end_cursor: if end > start {
+                    Some((end - 1).to_string())
+                } else {
+                    None
+                },
+            },
+        })
+    }
+}
+
+/// Transaction known to the node, either committed or in the pool.
+#[derive(Debug, Clone)]
+pub struct TransactionNode {
+    hash: Hash,
+    message: Signed<RawTransaction>,
+}
+
+impl TransactionNode {
+    fn tx_location(&self, context: &GraphQLContext) -> Option<TxLocation> {
+        context.schema().transactions_locations().get(&self.hash)
+    }
+}
+
+#[juniper::object(Context = GraphQLContext, name = "Transaction")]
+impl TransactionNode {
+    /// Hash of the transaction.
+    fn hash(&self) -> String {
+        hex::encode(self.hash)
+    }
+
+    /// Identifier of the service the transaction belongs to.
+    fn service_id(&self) -> i32 {
+        i32::from(self.message.payload().service_id())
+    }
+
+    /// Identifier of the transaction within the service.
+    fn transaction_id(&self) -> i32 {
+        i32::from(self.message.payload().transaction_id())
+    }
+
+    /// Public key of the transaction author.
+    fn author(&self) -> String {
+        hex::encode(self.message.author())
+    }
+
+    /// Signed transaction message in the hex format.
+    fn message(&self) -> String {
+        hex::encode(self.message.signed_message().raw())
+    }
+
+    /// Transaction payload decoded by the service, in JSON format. `null` if the
+    /// service cannot decode the transaction.
+    fn payload(&self, context: &GraphQLContext) -> Option<String> {
+        let transaction = context
+            .blockchain
+            .tx_from_raw(self.message.payload().clone())
+            .ok()?;
+        serde_json::to_string(&transaction).ok()
+    }
+
+    /// Correlation tag in the hex format, if the transaction has been submitted
+    /// to this node with a tag.
+    fn tag(&self, context: &GraphQLContext) -> Option<String> {
+        context
+            .schema()
+            .transaction_tags()
+            .get(&self.hash)
+            .map(hex::encode)
+    }
+
+    /// Whether the transaction is in the pool and is not committed yet.
+    fn in_pool(&self, context: &GraphQLContext) -> bool {
+        self.tx_location(context).is_none()
+    }
+
+    /// Location of the transaction in the blockchain, if it is committed.
+    fn location(&self, context: &GraphQLContext) -> FieldResult<Option<TransactionLocation>> {
+        match self.tx_location(context) {
+            Some(location) => Ok(Some(TransactionLocation {
+                block_height: to_int(location.block_height().0)?,
+                position_in_block: to_int(location.position_in_block())?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Block including the transaction, if it is committed.
+    fn block(&self, context: &GraphQLContext) -> Option<BlockNode> {
+        let location = self.tx_location(context)?;
+        context.block(location.block_height())
+    }
+
+    /// Execution status of the transaction, if it is committed.
+    fn status(&self, context: &GraphQLContext) -> Option<TransactionStatus> {
+        let result = context.schema().transaction_results().get(&self.hash)?;
+        let status = match TxStatus::from(&result) {
+            TxStatus::Success => TransactionStatus {
+                kind: "success".to_owned(),
+                code: None,
+                description: None,
+            },
+            TxStatus::Error { code, description } => TransactionStatus {
+                kind: "error".to_owned(),
+                code: Some(i32::from(code)),
+                description: non_empty(description),
+            },
+            TxStatus::Panic { description } => TransactionStatus {
+                kind: "panic".to_owned(),
+                code: None,
+                description: non_empty(description),
+            },
+            TxStatus::Unauthorized { description } => TransactionStatus {
+                kind: "unauthorized".to_owned(),
+                code: None,
+                description: non_empty(description),
+            },
+            TxStatus::OutOfGas { description } => TransactionStatus {
+                kind: "out-of-gas".to_owned(),
+                code: None,
+                description: non_empty(description),
+            },
+            TxStatus::InsufficientFee { description } => TransactionStatus {
+                kind: "insufficient-fee".to_owned(),
+                code: None,
+                description: non_empty(description),
+            },
+            TxStatus::BlockLimitExceeded { description } => TransactionStatus {
+                kind: "block-limit-exceeded".to_owned(),
+                code: None,
+                description: non_empty(description),
+            },
+            TxStatus::ServiceInactive { description } => TransactionStatus {
+                kind: "service-inactive".to_owned(),
+                code: None,
+                description: non_empty(description),
+            },
+        };
+        Some(status)
+    }
+}
+
+/// Configuration of the blockchain.
+#[derive(Debug, Clone)]
+pub struct ConfigurationNode {
+    config: StoredConfiguration,
+}
+
+#[juniper::object(Context = GraphQLContext, name = "Configuration")]
+impl ConfigurationNode {
+    /// Hash of the configuration.
+    fn hash(&self) -> String {
+        hex::encode(self.config.object_hash())
+    }
+
+    /// Hash of the previous configuration.
+    fn previous_hash(&self) -> String {
+        hex::encode(self.config.previous_cfg_hash)
+    }
+
+    /// Height starting from which the configuration is actual.
+    fn actual_from(&self) -> FieldResult<i32> {
+        to_int(self.config.actual_from.0)
+    }
+
+    /// Keys of the validators.
+    fn validator_keys(&self) -> Vec<ValidatorKeysNode> {
+        self.config
+            .validator_keys
+            .iter()
+            .map(|keys| ValidatorKeysNode {
+                consensus_key: hex::encode(keys.consensus_key),
+                service_key: hex::encode(keys.service_key),
+            })
+            .collect()
+    }
+
+    /// Consensus parameters in JSON format.
+    fn consensus(&self) -> FieldResult<String> {
+        Ok(serde_json::to_string(&self.config.consensus)?)
+    }
+
+    /// Configuration of the services in JSON format.
+    fn services(&self) -> FieldResult<String> {
+        Ok(serde_json::to_string(&self.config.services)?)
+    }
+}
+
+/// Page of blocks.
+#[derive(Debug, Clone)]
+pub struct BlockConnection {
+    nodes: Vec<BlockNode>,
+    page_info: PageInfo,
+}
+
+#[juniper::object(Context = GraphQLContext)]
+impl BlockConnection {
+    /// Blocks of the page.
+    fn nodes(&self) -> &[BlockNode] {
+        &self.nodes
+    }
+
+    /// Information about the page.
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+}
+
+/// Page of transactions.
+#[derive(Debug, Clone)]
+pub struct TransactionConnection {
+    nodes: Vec<TransactionNode>,
+    page_info: PageInfo,
+}
+
+#[juniper::object(Context = GraphQLContext)]
+impl TransactionConnection {
+    /// Transactions of the page.
+    fn nodes(&self) -> &[TransactionNode] {
+        &self.nodes
+    }
+
+    /// Information about the page.
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+}
+
+/// Page of configurations.
+#[derive(Debug, Clone)]
+pub struct ConfigurationConnection {
+    nodes: Vec<ConfigurationNode>,
+    page_info: PageInfo,
+}
+
+#[juniper::object(Context = GraphQLContext)]
+impl ConfigurationConnection {
+    /// Configurations of the page.
+    fn nodes(&self) -> &[ConfigurationNode] {
+        &self.nodes
+    }
+
+    /// Information about the page.
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+}
+
+/// Root of the GraphQL queries.
+#[derive(Debug, Clone, Copy)]
+pub struct Query;
+
+#[juniper::object(Context = GraphQLContext)]
+impl Query {
+    /// Height of the latest committed block.
+    fn height(context: &GraphQLContext) -> FieldResult<i32> {
+        to_int(context.schema().height().0)
+    }
+
+    /// Block at the given height.
+    fn block(context: &GraphQLContext, height: i32) -> FieldResult<Option<BlockNode>> {
+        Ok(context.block(to_height(height)?))
+    }
+
+    /// Committed blocks, starting from the latest one. The cursor of a block is its height.
+    fn blocks(
+        context: &GraphQLContext,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<BlockConnection> {
+        let limit = page_size(first)?;
+        // Heights of the page blocks are `end..start`, in the descending order.
+        let start = match after {
+            Some(cursor) => parse_cursor(&cursor)?,
+            None => context.schema().height().next().0,
+        };
+        let end = start.saturating_sub(limit);
+
+        let nodes = (end..start)
+            .rev()
+            .filter_map(|height| context.block(Height(height)))
+            .collect();
+        Ok(BlockConnection {
+            nodes,
+            page_info: PageInfo {
+                has_next_page: end > 0,
+                end_cursor: if end < start {
+                    Some(end.to_string())
+                } else {
+                    None
+                },
+            },
+        })
+    }
+
+    /// Transaction with the given hash in the hex format.
+    fn transaction(context: &GraphQLContext, hash: String) -> FieldResult<Option<TransactionNode>> {
+        let hash = Hash::from_hex(&hash)
+            .map_err(|e| FieldError::from(format!("Invalid transaction hash: {}", e)))?;
+        Ok(context.transaction(hash))
+    }
+
+    /// Configuration actual for the next block.
+    fn actual_configuration(context: &GraphQLContext) -> ConfigurationNode {
+        ConfigurationNode {
+            config: context.schema().actual_configuration(),
+        }
+    }
+
+    /// History of the blockchain configurations in the order of their activation,
+    /// including the scheduled ones. The cursor of a configuration is its index
+    /// in the history.
+    fn configurations(
+        context: &GraphQLContext,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<ConfigurationConnection> {
+        let limit = page_size(first)?;
+        let start = match after {
+            Some(cursor) => parse_cursor(&cursor)?.saturating_add(1),
+            None => 0,
+        };
+        let schema = context.schema();
+        let references = schema.configs_actual_from();
+        let end = references.len().min(start.saturating_add(limit));
+
+        let nodes = (start..end)
+            .filter_map(|index| {
+                let reference = references.get(index)?;
+                let config = schema.configuration_by_hash(reference.cfg_hash())?;
+                Some(ConfigurationNode { config })
+            })
+            .collect();
+        Ok(ConfigurationConnection {
+            nodes,
+            page_info: PageInfo {
+                has_next_page: end < references.len(),
+                end_cursor: if end > start {
+                    Some((end - 1).to_string())
+                } else {
+                    None
+                },
+            },
+        })
+    }
+}
+
+/// Adds the GraphQL endpoint and the GraphiQL page to the explorer API scope.
+pub(super) fn wire(api_scope: &mut ServiceApiScope) {
+    let schema = Arc::new(schema());
+    api_scope.endpoint_mut(
+        "v1/graphql",
+        move |state: &ServiceApiState, request: GraphQLRequest| -> Result<Value, ApiError> {
+            let context = GraphQLContext::new(state.blockchain().clone());
+            let response = request.execute(&schema, &context);
+            serde_json::to_value(&response).map_err(|e| ApiError::InternalError(e.into()))
+        },
+    );
+
+    let index = move |request: HttpRequest| -> FutureResponse {
+        let endpoint = request.path().replace("graphiql", "v1/graphql");
+        let response = HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(graphiql_source(&endpoint));
+        Box::new(future::ok(response))
+    };
+    api_scope.web_backend().raw_handler(RequestHandler {
+        name: "graphiql".to_owned(),
+        method: http::Method::GET,
+        inner: Arc::from(index) as Arc<RawHandler>,
+    });
+}
+
+fn page_size(first: Option<i32>) -> FieldResult<u64> {
+    let first = first.unwrap_or(DEFAULT_PAGE_SIZE);
+    if first < 0 || first > MAX_PAGE_SIZE {
+        return Err(FieldError::from(format!(
+            "`first` should be in the range from 0 to {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+    Ok(first as u64)
+}
+
+fn parse_cursor(cursor: &str) -> FieldResult<u64> {
+    cursor
+        .parse()
+        .map_err(|_| FieldError::from(format!("Invalid cursor: {}", cursor)))
+}
+
+fn to_height(height: i32) -> FieldResult<Height> {
+    u64::try_from(height)
+        .map(Height)
+        .map_err(|_| FieldError::from(format!("Invalid height: {}", height)))
+}
+
+fn to_int(value: u64) -> FieldResult<i32> {
+    i32::try_from(value)
+        .map_err(|_| FieldError::from(format!("Value {} does not fit into Int", value)))
+}
+
+fn non_empty(description: &str) -> Option<String> {
+    if description.is_empty() {
+        None
+    } else {
+        Some(description.to_owned())
+    }
+}
@@ -17,7 +17,18 @@
 //! Public API includes requests for information which is available to outside
 //! users, e.g., for requesting proofs.
 
-pub use self::{explorer::ExplorerApi, system::SystemApi};
+pub use self::{
+    author_watchers::{AuthorEvent, AuthorNotification, AuthorWatchers, AuthorWebhook},
+    explorer::ExplorerApi,
+    proof_cache::BlockProofCache,
+    system::SystemApi,
+    tx_waiters::TransactionWaiters,
+};
 
+pub mod author_watchers;
 pub mod explorer;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod proof_cache;
 pub mod system;
+pub mod tx_waiters;
@@ -0,0 +1,235 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cache of serialized block proofs served by the explorer API.
+//!
+//! Committed blocks and their precommits never change, so a serialized
+//! [`BlockProof`] stays valid for as long as the block is stored. Entries are
+//! evicted in the least-recently-used order once the cache is full, and are
+//! otherwise dropped only when the corresponding blocks are pruned.
+//!
+//! [`BlockProof`]: ../../../../blockchain/struct.BlockProof.html
+
+use bytes::Bytes;
+use exonum_merkledb::Snapshot;
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    api::Error as ApiError,
+    blockchain::Schema,
+    helpers::{lru::LruMap, Height},
+};
+
+/// Default number of block proofs kept by the cache.
+pub const DEFAULT_BLOCK_PROOF_CACHE_CAPACITY: usize = 1024;
+
+/// Serialized block proof together with its entity tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedBlockProof {
+    /// Entity tag of the proof, i.e., the quoted hex-encoded hash of the block.
+    pub etag: String,
+    /// JSON representation of the proof.
+    pub body: Bytes,
+}
+
+impl CachedBlockProof {
+    /// Returns `true` if the proof matches the value of the `If-None-Match` header.
+    pub fn matches(&self, if_none_match: &str) -> bool {
+        if_none_match
+            .split(',')
+            .map(|tag| tag.trim())
+            .map(|tag| tag.trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == self.etag)
+    }
+}
+
+/// LRU cache of serialized block proofs keyed by the block height.
+///
+/// The cache is cheaply cloneable; all clones share the same storage.
+#[derive(Clone)]
+pub struct BlockProofCache {
+    state: Arc<Mutex<LruMap<Height, CachedBlockProof>>>,
+    capacity: usize,
+}
+
+impl BlockProofCache {
+    /// Creates a cache that holds at most `capacity` proofs. A zero capacity
+    /// disables caching.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::default(),
+            capacity,
+        }
+    }
+
+    /// Returns the maximum number of proofs kept by the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached proofs.
+    pub fn len(&self) -> usize {
+        self.state.lock().expect("Expected mutex lock").len()
+    }
+
+    /// Returns `true` if the cache contains no proofs.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the cached proof for the given height, if any.
+    pub fn get(&self, height: Height) -> Option<CachedBlockProof> {
+        self.state
+            .lock()
+            .expect("Expected mutex lock")
+            .get(&height)
+            .cloned()
+    }
+
+    /// Returns the proof for the given height, assembling it from the snapshot
+    /// and storing it in the cache on a miss. Returns `None` if the block
+    /// at the given height has not been committed yet.
+    pub fn get_or_load(
+        &self,
+        snapshot: &dyn Snapshot,
+        height: Height,
+    ) -> Result<Option<CachedBlockProof>, ApiError> {
+        if let Some(proof) = self.get(height) {
+            return Ok(Some(proof));
+        }
+
+        let proof = match Schema::new(snapshot).block_and_precommits(height) {
+            Some(proof) => proof,
+            None => return Ok(None),
+        };
+        let body = serde_json::to_vec(&proof).map_err(|e| ApiError::InternalError(e.into()))?;
+        let proof = CachedBlockProof {
+            etag: format!("\"{}\"", proof.block.hash().to_hex()),
+            body: Bytes::from(body),
+        };
+
+        if self.capacity > 0 {
+            self.state.lock().expect("Expected mutex lock").insert(
+                height,
+                proof.clone(),
+                self.capacity,
+            );
+        }
+        Ok(Some(proof))
+    }
+
+    /// Drops cached proofs for all heights below `height`. Must be called
+    /// whenever the corresponding blocks are pruned from the storage.
+    pub fn invalidate_below(&self, height: Height) {
+        self.state
+            .lock()
+            .expect("Expected mutex lock")
+            .retain(|&cached| cached >= height);
+    }
+
+    /// Drops all cached proofs.
+    pub fn clear(&self) {
+        self.state.lock().expect("Expected mutex lock").clear();
+    }
+}
+
+impl Default for BlockProofCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_BLOCK_PROOF_CACHE_CAPACITY)
+    }
+}
+
+impl fmt::Debug for BlockProofCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlockProofCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proof(tag: &str) -> CachedBlockProof {
+        CachedBlockProof {
+            etag: format!("\"{}\"", tag),
+            body: Bytes::from(tag.as_bytes().to_vec()),
+        }
+    }
+
+    fn insert(cache: &BlockProofCache, height: u64, tag: &str) {
+        cache
+            .state
+            .lock()
+            .unwrap()
+            .insert(Height(height), proof(tag), cache.capacity);
+    }
+
+    #[test]
+    fn least_recently_used_proof_is_evicted() {
+        let cache = BlockProofCache::new(2);
+        insert(&cache, 1, "a");
+        insert(&cache, 2, "b");
+        // Accessing the first proof makes the second one the eviction candidate.
+        assert_eq!(cache.get(Height(1)), Some(proof("a")));
+        insert(&cache, 3, "c");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(Height(1)), Some(proof("a")));
+        assert_eq!(cache.get(Height(2)), None);
+        assert_eq!(cache.get(Height(3)), Some(proof("c")));
+    }
+
+    #[test]
+    fn reinserted_proof_is_not_duplicated() {
+        let cache = BlockProofCache::new(2);
+        insert(&cache, 1, "a");
+        insert(&cache, 1, "a");
+        insert(&cache, 2, "b");
+        insert(&cache, 3, "c");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(Height(1)), None);
+    }
+
+    #[test]
+    fn pruned_heights_are_invalidated() {
+        let cache = BlockProofCache::new(10);
+        for height in 0..5 {
+            insert(&cache, height, &height.to_string());
+        }
+        cache.invalidate_below(Height(3));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(Height(2)), None);
+        assert_eq!(cache.get(Height(3)), Some(proof("3")));
+    }
+
+    #[test]
+    fn if_none_match_parsing() {
+        let proof = proof("abc");
+        assert!(proof.matches("\"abc\""));
+        assert!(proof.matches("W/\"abc\""));
+        assert!(proof.matches("\"def\", \"abc\""));
+        assert!(proof.matches("*"));
+        assert!(!proof.matches("\"def\""));
+        assert!(!proof.matches("abc"));
+    }
+}
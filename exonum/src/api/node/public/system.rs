@@ -14,9 +14,13 @@
 
 //! Public system API.
 
+use std::ops::RangeInclusive;
+
+use crate::api::{node::public::explorer::TransactionHex, Error as ApiError};
 use crate::api::{ServiceApiScope, ServiceApiState};
-use crate::blockchain::{Schema, SharedNodeState};
+use crate::blockchain::{Schema, SharedNodeState, StateTable, RESERVED_SERVICE_IDS};
 use crate::helpers::user_agent;
+use crate::messages::{check_transaction, test_vectors, SignatureCheck, TestVector};
 
 /// Information about the current state of the node memory pool.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -49,6 +53,17 @@ pub struct HealthCheckInfo {
     pub consensus_status: ConsensusStatus,
     /// The number of connected peers to the node.
     pub connected_peers: usize,
+    /// Names of the firing alert rules with the `healthcheck` action.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub degraded_by: Vec<String>,
+    /// Whether the node is shutting down. Load balancers should stop routing requests
+    /// to a stopping node; the requests already sent to it are still served.
+    #[serde(default)]
+    pub stopping: bool,
+    /// Whether the node sheds load because its memory or disk usage approaches
+    /// the configured limits.
+    #[serde(default)]
+    pub overloaded: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -63,6 +78,28 @@ pub struct ServicesResponse {
     services: Vec<ServiceInfo>,
 }
 
+/// Identifier allocated to a service.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServiceIdAllocation {
+    /// Identifier of the service.
+    pub id: u16,
+    /// Name of the service.
+    pub name: String,
+    /// Whether the service is built into the framework.
+    pub builtin: bool,
+    /// Whether the service is deployed to the blockchain.
+    pub deployed: bool,
+}
+
+/// Service identifiers response.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServiceIdsResponse {
+    /// Identifiers reserved for the built-in services.
+    pub reserved: RangeInclusive<u16>,
+    /// Identifiers of the registered services, ordered by their values.
+    pub services: Vec<ServiceIdAllocation>,
+}
+
 /// Public system API.
 #[derive(Clone, Debug)]
 pub struct SystemApi {
@@ -102,6 +139,9 @@ impl SystemApi {
             Ok(HealthCheckInfo {
                 consensus_status: self.get_consensus_status(),
                 connected_peers: self.get_number_of_connected_peers(),
+                degraded_by: self.shared_api_state.firing_alerts().degraded_by(),
+                stopping: self.shared_api_state.is_stopping(),
+                overloaded: self.shared_api_state.is_overloaded(),
             })
         });
         self_
@@ -114,9 +154,11 @@ impl SystemApi {
     ) -> Self {
         api_scope.endpoint(name, move |state: &ServiceApiState, _query: ()| {
             let blockchain = state.blockchain();
+            let snapshot = state.snapshot();
             let services = blockchain
                 .service_map()
                 .iter()
+                .filter(|(&id, _)| blockchain.is_service_deployed(snapshot.as_ref(), id))
                 .map(|(&id, service)| ServiceInfo {
                     name: service.service_name().to_string(),
                     id,
@@ -127,6 +169,72 @@ impl SystemApi {
         self
     }
 
+    fn handle_service_ids(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(name, move |state: &ServiceApiState, _query: ()| {
+            let blockchain = state.blockchain();
+            let snapshot = state.snapshot();
+            let mut services = blockchain
+                .service_map()
+                .iter()
+                .map(|(&id, service)| ServiceIdAllocation {
+                    id,
+                    name: service.service_name().to_string(),
+                    builtin: service.is_builtin(),
+                    deployed: blockchain.is_service_deployed(snapshot.as_ref(), id),
+                })
+                .collect::<Vec<_>>();
+            services.sort_by_key(|service| service.id);
+            Ok(ServiceIdsResponse {
+                reserved: RESERVED_SERVICE_IDS,
+                services,
+            })
+        });
+        self
+    }
+
+    fn handle_state_tables(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState, _query: ()| -> Result<Vec<StateTable>, ApiError> {
+                let snapshot = state.snapshot();
+                Ok(state.blockchain().state_tables(snapshot.as_ref()))
+            },
+        );
+        self
+    }
+
+    fn handle_test_vectors(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState, _query: ()| -> Result<Vec<TestVector>, ApiError> {
+                Ok(test_vectors())
+            },
+        );
+        self
+    }
+
+    fn handle_verify_signature(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint_mut(
+            name,
+            move |state: &ServiceApiState,
+                  query: TransactionHex|
+                  -> Result<SignatureCheck, ApiError> {
+                let message = hex::decode(&query.tx_body)
+                    .map_err(|e| ApiError::BadRequest(format!("Invalid hex: {}", e)))?;
+                let mut check = check_transaction(&message);
+                if let Some(ref parts) = check.parts {
+                    if let Err(e) = state.blockchain().tx_from_raw(parts.raw_transaction()) {
+                        check
+                            .errors
+                            .push(format!("Unable to deserialize the transaction: {}", e));
+                    }
+                }
+                Ok(check)
+            },
+        );
+        self
+    }
+
     fn get_number_of_connected_peers(&self) -> usize {
         let in_conn = self.shared_api_state.incoming_connections().len();
         let out_conn = self.shared_api_state.outgoing_connections().len();
@@ -152,7 +260,11 @@ impl SystemApi {
         self.handle_stats_info("v1/stats", api_scope)
             .handle_healthcheck_info("v1/healthcheck", api_scope)
             .handle_user_agent_info("v1/user_agent", api_scope)
-            .handle_list_services_info("v1/services", api_scope);
+            .handle_list_services_info("v1/services", api_scope)
+            .handle_service_ids("v1/services/ids", api_scope)
+            .handle_state_tables("v1/state_tables", api_scope)
+            .handle_test_vectors("v1/test-vectors", api_scope)
+            .handle_verify_signature("v1/verify-signature", api_scope);
         api_scope
     }
 }
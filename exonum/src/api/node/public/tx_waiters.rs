@@ -0,0 +1,179 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Notifications about the progress of transactions submitted via the API.
+//!
+//! Transactions accepted by the node are kept in memory until the next commit, so
+//! the API cannot find out from the storage that a transaction has reached the pool.
+//! Instead, the API registers a waiter for the transaction before passing it to the
+//! node, and the node resolves the waiter once the transaction is verified into the
+//! pool, rejected or committed.
+
+use futures::sync::oneshot;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::crypto::Hash;
+
+/// Stage of the transaction processing awaited by the submitter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitFor {
+    /// The transaction is verified and added to the pool.
+    Pool,
+    /// The transaction is committed to the blockchain.
+    Commit,
+}
+
+/// Outcome of the awaited stage: `Err` contains the reason of the transaction rejection.
+pub type WaitResult = Result<(), String>;
+
+type Waiter = oneshot::Sender<WaitResult>;
+
+#[derive(Debug, Default)]
+struct Waiters {
+    pool: HashMap<Hash, Vec<Waiter>>,
+    commit: HashMap<Hash, Vec<Waiter>>,
+}
+
+impl Waiters {
+    fn stage_mut(&mut self, stage: WaitFor) -> &mut HashMap<Hash, Vec<Waiter>> {
+        match stage {
+            WaitFor::Pool => &mut self.pool,
+            WaitFor::Commit => &mut self.commit,
+        }
+    }
+
+    // Forgets the waiters whose submitters have given up, e.g., after a timeout.
+    fn prune(&mut self) {
+        for waiters in &mut [&mut self.pool, &mut self.commit] {
+            waiters.retain(|_, senders| {
+                senders.retain(|sender| !sender.is_canceled());
+                !senders.is_empty()
+            });
+        }
+    }
+}
+
+fn resolve(waiters: &mut HashMap<Hash, Vec<Waiter>>, tx_hash: &Hash, result: &WaitResult) {
+    for sender in waiters.remove(tx_hash).unwrap_or_default() {
+        // The submitter may have given up waiting.
+        let _ = sender.send(result.clone());
+    }
+}
+
+/// Registry of the API requests waiting for the transactions to reach a certain stage.
+///
+/// The registry is cheaply cloneable; all clones share the same waiters.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionWaiters {
+    inner: Arc<Mutex<Waiters>>,
+}
+
+impl TransactionWaiters {
+    /// Returns a receiver resolved once the transaction reaches the given stage or
+    /// is rejected by the node. The waiter must be registered before the transaction
+    /// is sent to the node, so that the notification is not missed.
+    pub fn wait(&self, tx_hash: Hash, stage: WaitFor) -> oneshot::Receiver<WaitResult> {
+        let (sender, receiver) = oneshot::channel();
+        let mut waiters = self.inner.lock().expect("Expected mutex lock");
+        waiters.prune();
+        waiters
+            .stage_mut(stage)
+            .entry(tx_hash)
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    /// Returns the number of transactions with registered waiters.
+    pub fn len(&self) -> usize {
+        let waiters = self.inner.lock().expect("Expected mutex lock");
+        waiters.pool.len() + waiters.commit.len()
+    }
+
+    /// Returns `true` if no transaction is awaited.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Notifies the waiters that the transaction has been added to the pool.
+    pub(crate) fn notify_pooled(&self, tx_hash: &Hash) {
+        let mut waiters = self.inner.lock().expect("Expected mutex lock");
+        resolve(&mut waiters.pool, tx_hash, &Ok(()));
+    }
+
+    /// Notifies the waiters that the transaction has been rejected by the node.
+    pub(crate) fn notify_rejected(&self, tx_hash: &Hash, reason: &str) {
+        let mut waiters = self.inner.lock().expect("Expected mutex lock");
+        let result = Err(reason.to_owned());
+        resolve(&mut waiters.pool, tx_hash, &result);
+        resolve(&mut waiters.commit, tx_hash, &result);
+    }
+
+    /// Notifies the waiters that the transactions have been committed.
+    pub(crate) fn notify_committed<'a, I>(&self, tx_hashes: I)
+    where
+        I: IntoIterator<Item = &'a Hash>,
+    {
+        let mut waiters = self.inner.lock().expect("Expected mutex lock");
+        if waiters.pool.is_empty() && waiters.commit.is_empty() {
+            return;
+        }
+        for tx_hash in tx_hashes {
+            resolve(&mut waiters.pool, tx_hash, &Ok(()));
+            resolve(&mut waiters.commit, tx_hash, &Ok(()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::Future;
+
+    use super::*;
+    use crate::crypto;
+
+    #[test]
+    fn waiters_are_resolved_by_stage() {
+        let waiters = TransactionWaiters::default();
+        let (first, second) = (crypto::hash(&[1]), crypto::hash(&[2]));
+        let pooled = waiters.wait(first, WaitFor::Pool);
+        let committed = waiters.wait(first, WaitFor::Commit);
+        let rejected = waiters.wait(second, WaitFor::Commit);
+        assert_eq!(waiters.len(), 3);
+
+        waiters.notify_pooled(&first);
+        assert_eq!(pooled.wait(), Ok(Ok(())));
+        waiters.notify_rejected(&second, "Invalid transaction");
+        assert_eq!(rejected.wait(), Ok(Err("Invalid transaction".to_owned())));
+        assert_eq!(waiters.len(), 1);
+
+        waiters.notify_committed(&[first, second]);
+        assert_eq!(committed.wait(), Ok(Ok(())));
+        assert!(waiters.is_empty());
+    }
+
+    #[test]
+    fn abandoned_waiters_are_pruned() {
+        let waiters = TransactionWaiters::default();
+        drop(waiters.wait(crypto::hash(&[1]), WaitFor::Pool));
+        drop(waiters.wait(crypto::hash(&[2]), WaitFor::Commit));
+        let _receiver = waiters.wait(crypto::hash(&[3]), WaitFor::Commit);
+        assert_eq!(waiters.len(), 1);
+    }
+}
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::api::access_log::AccessLog;
 use crate::blockchain::Blockchain;
 use crate::crypto::{PublicKey, SecretKey};
 use crate::node::ApiSender;
@@ -26,12 +27,27 @@ use exonum_merkledb::Snapshot;
 #[derive(Debug, Clone)]
 pub struct ServiceApiState {
     blockchain: Blockchain,
+    access_log: Option<AccessLog>,
 }
 
 impl ServiceApiState {
     /// Constructs state for the given blockchain.
     pub fn new(blockchain: Blockchain) -> Self {
-        Self { blockchain }
+        Self {
+            blockchain,
+            access_log: None,
+        }
+    }
+
+    /// Makes the API record the handled requests into the given access log.
+    pub fn with_access_log(mut self, access_log: AccessLog) -> Self {
+        self.access_log = Some(access_log);
+        self
+    }
+
+    /// Returns the access log of the API, if any.
+    pub fn access_log(&self) -> Option<&AccessLog> {
+        self.access_log.as_ref()
     }
 
     /// Returns a reference to the blockchain of this node.
@@ -22,16 +22,21 @@ use log::error;
 use rand::{rngs::ThreadRng, Rng};
 use std::{
     cell::RefCell,
+    cmp::Ordering,
     collections::{BTreeMap, HashMap},
+    hash::{Hash as StdHash, Hasher},
     sync::Arc,
 };
 
 use crate::api::{
-    node::public::explorer::{TransactionHex, TransactionResponse},
+    node::public::{
+        explorer::{TransactionHex, TransactionResponse},
+        AuthorNotification, AuthorWatchers,
+    },
     ServiceApiState,
 };
-use crate::blockchain::{Block, Schema, TransactionResult, TxLocation};
-use crate::crypto::Hash;
+use crate::blockchain::{Block, Schema, TransactionResult, TransactionTag, TxLocation};
+use crate::crypto::{Hash, PublicKey};
 use crate::events::error::into_failure;
 use crate::explorer::{median_precommits_time, TxStatus};
 use crate::messages::{Message as ExonumMessage, ProtocolMessage, RawTransaction, SignedMessage};
@@ -61,16 +66,28 @@ pub enum SubscriptionType {
         /// Optional filter for subscription.
         filter: Option<TransactionFilter>,
     },
+    /// Subscription on the transactions signed by the key, which are reported
+    /// once they enter the pool, are rejected or are committed.
+    Author {
+        /// Watched key.
+        author: PublicKey,
+    },
 }
 
-/// Describe filter for transactions by ID of service and (optionally)
-/// transaction type in service.
+/// Describe filter for transactions by ID of service, (optionally)
+/// transaction type in service and (optionally) the values of the transaction fields.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
 pub struct TransactionFilter {
     /// ID of service.
     pub service_id: u16,
     /// Optional ID of transaction in service (if not set, all transaction of service will be sent).
     pub message_id: Option<u16>,
+    /// Conditions on the fields of the transaction payload, all of which must hold.
+    ///
+    /// The payload is decoded by the service on the node, so the subscriber receives
+    /// only the matching transactions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldFilter>,
 }
 
 impl TransactionFilter {
@@ -79,8 +96,80 @@ impl TransactionFilter {
         Self {
             service_id,
             message_id,
+            fields: Vec::new(),
         }
     }
+
+    /// Adds the condition that the field of the transaction payload equals the value.
+    pub fn with_field(mut self, field: impl Into<String>, value: serde_json::Value) -> Self {
+        self.fields.push(FieldFilter::new(field, value));
+        self
+    }
+
+    fn matches_ids(&self, service_id: u16, message_id: u16) -> bool {
+        self.service_id == service_id && self.message_id.map_or(true, |id| id == message_id)
+    }
+
+    fn matches_payload(&self, payload: &serde_json::Value) -> bool {
+        self.fields.iter().all(|filter| filter.matches(payload))
+    }
+}
+
+/// Condition that a field of the decoded transaction payload equals the given value.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct FieldFilter {
+    /// Path to the field in the JSON representation of the payload, with the names
+    /// of nested fields separated by dots, e.g., `owner.name`. Array elements are
+    /// referred to by their indexes.
+    pub field: String,
+    /// Expected value of the field.
+    pub value: serde_json::Value,
+}
+
+impl FieldFilter {
+    /// Creates a new field filter.
+    pub fn new(field: impl Into<String>, value: serde_json::Value) -> Self {
+        Self {
+            field: field.into(),
+            value,
+        }
+    }
+
+    fn matches(&self, payload: &serde_json::Value) -> bool {
+        self.field
+            .split('.')
+            .try_fold(payload, |value, name| match value {
+                serde_json::Value::Object(map) => map.get(name),
+                serde_json::Value::Array(items) => {
+                    name.parse::<usize>().ok().and_then(|i| items.get(i))
+                }
+                _ => None,
+            })
+            .map_or(false, |value| *value == self.value)
+    }
+}
+
+// `serde_json::Value` implements neither `Hash` nor `Ord`, so the filters are compared
+// by the textual representation of the expected values.
+impl StdHash for FieldFilter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.field.hash(state);
+        self.value.to_string().hash(state);
+    }
+}
+
+impl PartialOrd for FieldFilter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FieldFilter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.field
+            .cmp(&other.field)
+            .then_with(|| self.value.to_string().cmp(&other.value.to_string()))
+    }
 }
 
 /// Summary about a particular transaction in the blockchain (without transaction content).
@@ -96,6 +185,8 @@ pub struct CommittedTransactionSummary {
     location: TxLocation,
     location_proof: ListProof<Hash>,
     time: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<TransactionTag>,
 }
 
 impl CommittedTransactionSummary {
@@ -125,6 +216,7 @@ impl CommittedTransactionSummary {
             location,
             location_proof,
             time,
+            tag: schema.transaction_tag(tx_hash),
         })
     }
 }
@@ -138,6 +230,8 @@ pub enum Notification {
     Block(Block),
     /// Notification about new transaction.
     Transaction(CommittedTransactionSummary),
+    /// Notification about a transaction signed by the watched key.
+    AuthorTransaction(AuthorNotification),
 }
 
 /// WebSocket message for communication between clients(`Session`) and server(`Server`).
@@ -176,6 +270,9 @@ pub(crate) struct Broadcast {
     pub block_hash: Hash,
 }
 
+#[derive(Message)]
+pub(crate) struct AuthorTransaction(pub AuthorNotification);
+
 #[derive(Message)]
 #[rtype("Result<TransactionResponse, failure::Error>")]
 pub(crate) struct Transaction {
@@ -185,14 +282,16 @@ pub(crate) struct Transaction {
 pub(crate) struct Server {
     pub subscribers: BTreeMap<SubscriptionType, HashMap<u64, Recipient<Message>>>,
     service_api_state: Arc<ServiceApiState>,
+    author_watchers: AuthorWatchers,
     rng: RefCell<ThreadRng>,
 }
 
 impl Server {
-    pub fn new(service_api_state: Arc<ServiceApiState>) -> Self {
+    pub fn new(service_api_state: Arc<ServiceApiState>, author_watchers: AuthorWatchers) -> Self {
         Self {
             subscribers: BTreeMap::new(),
             service_api_state,
+            author_watchers,
             rng: RefCell::new(rand::thread_rng()),
         }
     }
@@ -201,6 +300,20 @@ impl Server {
         self.subscribers.iter_mut().for_each(|(_, v)| {
             v.remove(&id);
         });
+        self.update_watched_authors();
+    }
+
+    // The node reports the transactions of the watched keys only.
+    fn update_watched_authors(&self) {
+        let authors = self
+            .subscribers
+            .iter()
+            .filter(|(_, subscribers)| !subscribers.is_empty())
+            .filter_map(|(subscription, _)| match subscription {
+                SubscriptionType::Author { author } => Some(*author),
+                _ => None,
+            });
+        self.author_watchers.set_subscribed(authors);
     }
 
     fn set_subscriptions(
@@ -215,6 +328,7 @@ impl Server {
                 .or_insert_with(HashMap::new)
                 .insert(id, addr.clone());
         });
+        self.update_watched_authors();
     }
 
     fn disconnect_all(&mut self) {
@@ -227,6 +341,7 @@ impl Server {
             subscriber.clear();
         }
         self.subscribers.clear();
+        self.update_watched_authors();
     }
 }
 
@@ -317,26 +432,24 @@ impl Handler<Broadcast> for Server {
                         hash
                     );
                 }
-                res
+                res.map(|tx_info| (hash, tx_info))
             })
-            .for_each(|tx_info| {
-                let service_id = tx_info.service_id;
-                let tx_id = tx_info.message_id;
-                let data = Notification::Transaction(tx_info);
-                self.broadcast_message(SubscriptionType::Transactions { filter: None }, &data);
-                self.broadcast_message(
-                    SubscriptionType::Transactions {
-                        filter: Some(TransactionFilter::new(service_id, None)),
-                    },
-                    &data,
-                );
-                self.broadcast_message(
-                    SubscriptionType::Transactions {
-                        filter: Some(TransactionFilter::new(service_id, Some(tx_id))),
-                    },
-                    &data,
-                );
-            });
+            .for_each(|(hash, tx_info)| self.broadcast_transaction(&schema, &hash, tx_info));
+    }
+}
+
+impl Handler<AuthorTransaction> for Server {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        AuthorTransaction(notification): AuthorTransaction,
+        _ctx: &mut Self::Context,
+    ) {
+        let subscription = SubscriptionType::Author {
+            author: notification.author,
+        };
+        self.broadcast_message(subscription, &Notification::AuthorTransaction(notification));
     }
 }
 
@@ -356,7 +469,7 @@ impl Handler<Transaction> for Server {
         let _ = self
             .service_api_state
             .sender()
-            .broadcast_transaction(signed);
+            .broadcast_tagged_transaction(signed, tx.tag);
         Ok(TransactionResponse { tx_hash })
     }
 }
@@ -385,6 +498,67 @@ impl Server {
     }
 }
 
+impl Server {
+    fn broadcast_transaction<T>(
+        &self,
+        schema: &Schema<T>,
+        tx_hash: &Hash,
+        tx_info: CommittedTransactionSummary,
+    ) where
+        T: AsRef<dyn Snapshot> + IndexAccess,
+    {
+        let service_id = tx_info.service_id;
+        let message_id = tx_info.message_id;
+        let serialized = serde_json::to_string(&Notification::Transaction(tx_info)).unwrap();
+        // The payload is decoded only if some subscriber filters on its fields.
+        let mut payload = None;
+
+        for (sub_type, subscribers) in &self.subscribers {
+            let filter = match sub_type {
+                SubscriptionType::Transactions { filter } => filter,
+                _ => continue,
+            };
+            if let Some(filter) = filter {
+                if !filter.matches_ids(service_id, message_id) {
+                    continue;
+                }
+                if !filter.fields.is_empty() {
+                    let payload =
+                        payload.get_or_insert_with(|| self.decode_payload(schema, tx_hash));
+                    match payload {
+                        Some(payload) if filter.matches_payload(payload) => {}
+                        _ => continue,
+                    }
+                }
+            }
+            for addr in subscribers.values() {
+                let _ = addr.do_send(Message::Data(serialized.clone()));
+            }
+        }
+    }
+
+    fn decode_payload<T>(&self, schema: &Schema<T>, tx_hash: &Hash) -> Option<serde_json::Value>
+    where
+        T: AsRef<dyn Snapshot> + IndexAccess,
+    {
+        let raw = schema.transactions().get(tx_hash)?;
+        let transaction = self
+            .service_api_state
+            .blockchain()
+            .tx_from_raw(raw.payload().clone())
+            .map_err(|e| warn!("Cannot decode committed transaction {:?}: {}", tx_hash, e))
+            .ok()?;
+        serde_json::to_value(transaction.as_ref())
+            .map_err(|e| {
+                warn!(
+                    "Cannot serialize committed transaction {:?}: {}",
+                    tx_hash, e
+                )
+            })
+            .ok()
+    }
+}
+
 pub(crate) struct Session {
     pub id: u64,
     pub subscriptions: Vec<SubscriptionType>,
@@ -0,0 +1,176 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Declarative access control for service transactions.
+//!
+//! A service may restrict the authors of its transactions by returning an
+//! [`AccessControlList`] from [`Service::access_control`]. The core checks the list
+//! before executing a transaction. Transactions of unauthorized authors are not
+//! executed; they are committed with the `TransactionErrorType::Unauthorized` status.
+//!
+//! [`AccessControlList`]: struct.AccessControlList.html
+//! [`Service::access_control`]: trait.Service.html#method.access_control
+
+use exonum_merkledb::{KeySetIndex, MapIndex, ProofMapIndex, Snapshot};
+
+use std::collections::BTreeMap;
+
+use super::Schema;
+use crate::crypto::PublicKey;
+
+/// Set of keys allowed to author a transaction.
+///
+/// The indexes are looked up by their names in the current state of the blockchain,
+/// so the set of authorized keys changes together with the service data. The type of
+/// the index must match the variant, otherwise the check panics and the transaction
+/// is committed with the `Panic` status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum AuthorizedKeys {
+    /// Service keys of the validators from the actual configuration.
+    Validators,
+    /// Explicitly listed keys.
+    Keys(Vec<PublicKey>),
+    /// Elements of the `KeySetIndex<PublicKey>` with the given name.
+    KeySetIndex(String),
+    /// Keys of the `MapIndex` with the given name. Values of the index are not read.
+    MapIndex(String),
+    /// Keys of the `ProofMapIndex` with the given name. Values of the index are not read.
+    ProofMapIndex(String),
+}
+
+impl AuthorizedKeys {
+    fn contains(&self, snapshot: &dyn Snapshot, key: &PublicKey) -> bool {
+        match self {
+            AuthorizedKeys::Validators => Schema::new(snapshot)
+                .actual_configuration()
+                .validator_keys
+                .iter()
+                .any(|keys| keys.service_key == *key),
+            AuthorizedKeys::Keys(keys) => keys.contains(key),
+            AuthorizedKeys::KeySetIndex(name) => {
+                KeySetIndex::<_, PublicKey>::new(name.as_str(), snapshot).contains(key)
+            }
+            AuthorizedKeys::MapIndex(name) => {
+                MapIndex::<_, PublicKey, Vec<u8>>::new(name.as_str(), snapshot).contains(key)
+            }
+            AuthorizedKeys::ProofMapIndex(name) => {
+                ProofMapIndex::<_, PublicKey, Vec<u8>>::new(name.as_str(), snapshot).contains(key)
+            }
+        }
+    }
+}
+
+/// Authorization requirements for the transactions of a service, keyed by
+/// the transaction identifier.
+///
+/// Transactions without requirements can be authored by anyone. If several sets
+/// of keys are allowed for the same transaction, the author must belong to any
+/// of them.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::blockchain::{AccessControlList, AuthorizedKeys};
+///
+/// // Transaction 0 creates wallets and is open to everyone, transaction 1 can be
+/// // sent by the wallet owners only, and transaction 2 by the validators.
+/// let acl = AccessControlList::new()
+///     .allow(1, AuthorizedKeys::ProofMapIndex("cryptocurrency.wallets".to_owned()))
+///     .allow(2, AuthorizedKeys::Validators);
+/// assert!(acl.rules(0).is_empty());
+/// assert_eq!(acl.rules(2), &[AuthorizedKeys::Validators]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccessControlList {
+    rules: BTreeMap<u16, Vec<AuthorizedKeys>>,
+}
+
+impl AccessControlList {
+    /// Creates a list without requirements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the transaction with the given identifier to be authored by the given keys.
+    pub fn allow(mut self, transaction_id: u16, keys: AuthorizedKeys) -> Self {
+        self.rules.entry(transaction_id).or_default().push(keys);
+        self
+    }
+
+    /// Returns the sets of keys allowed to author the transaction with the given
+    /// identifier. An empty slice means that the transaction is not restricted.
+    pub fn rules(&self, transaction_id: u16) -> &[AuthorizedKeys] {
+        self.rules
+            .get(&transaction_id)
+            .map_or(&[][..], Vec::as_slice)
+    }
+
+    /// Returns `true` if no transaction is restricted.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Checks if the `author` is allowed to author the transaction with the given
+    /// identifier in the given state of the blockchain.
+    pub fn is_authorized(
+        &self,
+        snapshot: &dyn Snapshot,
+        transaction_id: u16,
+        author: &PublicKey,
+    ) -> bool {
+        let rules = self.rules(transaction_id);
+        rules.is_empty() || rules.iter().any(|keys| keys.contains(snapshot, author))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::tests::create_initialized_blockchain;
+    use crate::crypto::gen_keypair;
+
+    #[test]
+    fn authorized_keys() {
+        let mut blockchain = create_initialized_blockchain(Vec::new());
+        let validator = blockchain.service_keypair.0;
+        let (member, stranger) = (gen_keypair().0, gen_keypair().0);
+
+        let fork = blockchain.fork();
+        KeySetIndex::new("acl.members", &fork).insert(member);
+        MapIndex::new("acl.balances", &fork).put(&member, 10_u64);
+        ProofMapIndex::new("acl.wallets", &fork).put(&member, "wallet".to_owned());
+        blockchain.merge(fork.into_patch()).unwrap();
+
+        let acl = AccessControlList::new()
+            .allow(1, AuthorizedKeys::Validators)
+            .allow(2, AuthorizedKeys::Keys(vec![stranger]))
+            .allow(3, AuthorizedKeys::KeySetIndex("acl.members".to_owned()))
+            .allow(4, AuthorizedKeys::MapIndex("acl.balances".to_owned()))
+            .allow(5, AuthorizedKeys::ProofMapIndex("acl.wallets".to_owned()))
+            .allow(5, AuthorizedKeys::Validators);
+
+        let snapshot = blockchain.snapshot();
+        let allowed =
+            |transaction_id, author| acl.is_authorized(snapshot.as_ref(), transaction_id, author);
+        for author in &[validator, member, stranger] {
+            assert!(allowed(0, author));
+        }
+        assert!(allowed(1, &validator) && !allowed(1, &member));
+        assert!(allowed(2, &stranger) && !allowed(2, &validator));
+        assert!(allowed(3, &member) && !allowed(3, &stranger));
+        assert!(allowed(4, &member) && !allowed(4, &stranger));
+        assert!(allowed(5, &member) && allowed(5, &validator) && !allowed(5, &stranger));
+    }
+}
@@ -0,0 +1,222 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Offloading of old transactions to an external object storage.
+//!
+//! Transactions of the blocks outside of the `transactions` retention window are moved
+//! from the local database to an [`ArchiveStore`], where they are keyed by the hex-encoded
+//! transaction hash. The hashes of the offloaded transactions are kept locally
+//! in the [`archived_transactions`] table, and the transactions fetched from the store
+//! are checked against them. Hence, the store does not need to be trusted.
+//!
+//! The explorer fetches the offloaded transactions on demand and keeps the latest
+//! of them in memory. Other components, e.g., the handler of transaction requests
+//! from the peers, only see the transactions in the local database.
+//!
+//! [`ArchiveStore`]: trait.ArchiveStore.html
+//! [`archived_transactions`]: ../struct.Schema.html#method.archived_transactions
+
+use exonum_merkledb::BinaryValue;
+use failure::Error;
+
+use std::{
+    fmt, fs,
+    io::{ErrorKind, Read},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    crypto::{self, CryptoHash, Hash},
+    helpers::lru::LruMap,
+    messages::{RawTransaction, Signed},
+};
+
+/// Default number of the fetched transactions kept in memory.
+pub const DEFAULT_ARCHIVE_CACHE_SIZE: usize = 1_000;
+
+/// Storage of the offloaded transactions.
+pub trait ArchiveStore: Send + Sync {
+    /// Saves the object with the given key.
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), Error>;
+    /// Loads the object with the given key. Returns `None` if there is no such object.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+}
+
+/// Store keeping the objects as files in a local directory, e.g., a mounted
+/// network file system.
+#[derive(Debug, Clone)]
+pub struct DirectoryStore {
+    root: PathBuf,
+}
+
+impl DirectoryStore {
+    /// Creates a store in the given directory. The directory is created if necessary.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Result<Self, Error> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+}
+
+impl ArchiveStore for DirectoryStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), Error> {
+        fs::write(self.root.join(key), data)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match fs::read(self.root.join(key)) {
+            Ok(data) => Ok(Some(data)),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Store accessing the objects with `PUT` and `GET` requests at `{base_url}/{key}`.
+///
+/// This is compatible with S3 buckets and S3-compatible storages whose policy allows
+/// the node to access the objects, as well as with authenticating proxies in front
+/// of such storages.
+#[derive(Debug, Clone)]
+pub struct HttpStore {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl HttpStore {
+    /// Creates a store with the given base URL.
+    pub fn new<S: Into<String>>(base_url: S) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, key)
+    }
+}
+
+impl ArchiveStore for HttpStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), Error> {
+        self.client
+            .put(&self.url(key))
+            .body(data.to_vec())
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let mut response = self.client.get(&self.url(key)).send()?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let mut data = Vec::new();
+        response.error_for_status_ref()?;
+        response.read_to_end(&mut data)?;
+        Ok(Some(data))
+    }
+}
+
+/// Configuration of the transaction offloading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveConfig {
+    /// Number of the latest blocks whose transactions are kept in the local database.
+    pub window: u64,
+    /// Location of the store: either a `file://` URL of a local directory or
+    /// an `http(s)://` URL of a bucket.
+    pub url: String,
+    /// Number of the fetched transactions kept in memory.
+    #[serde(default = "default_cache_size")]
+    pub cache_size: usize,
+}
+
+fn default_cache_size() -> usize {
+    DEFAULT_ARCHIVE_CACHE_SIZE
+}
+
+/// Handle of the store of the offloaded transactions.
+///
+/// The handle is cheaply cloneable; all clones share the same cache.
+#[derive(Clone)]
+pub struct TransactionArchive {
+    store: Arc<dyn ArchiveStore>,
+    cache: Arc<Mutex<LruMap<Hash, Signed<RawTransaction>>>>,
+    cache_size: usize,
+}
+
+impl TransactionArchive {
+    /// Creates an archive over the given store.
+    pub fn new(store: Arc<dyn ArchiveStore>, cache_size: usize) -> Self {
+        Self {
+            store,
+            cache: Arc::new(Mutex::new(LruMap::default())),
+            cache_size,
+        }
+    }
+
+    /// Creates an archive with the store specified in the configuration.
+    pub fn from_config(config: &ArchiveConfig) -> Result<Self, Error> {
+        let store: Arc<dyn ArchiveStore> = if config.url.starts_with("file://") {
+            Arc::new(DirectoryStore::new(&config.url["file://".len()..])?)
+        } else if config.url.starts_with("http://") || config.url.starts_with("https://") {
+            Arc::new(HttpStore::new(config.url.as_str()))
+        } else {
+            bail!("Unsupported transaction archive URL: {}", config.url);
+        };
+        Ok(Self::new(store, config.cache_size))
+    }
+
+    /// Saves the transaction to the store.
+    pub fn offload(&self, tx: &Signed<RawTransaction>) -> Result<(), Error> {
+        self.store
+            .put(&tx.hash().to_hex(), tx.signed_message().raw())
+    }
+
+    /// Loads the transaction with the given hash from the store. The loaded
+    /// transaction is checked against the hash.
+    pub fn fetch(&self, tx_hash: &Hash) -> Result<Option<Signed<RawTransaction>>, Error> {
+        if let Some(tx) = self.cache.lock().expect("Expected mutex lock").get(tx_hash) {
+            return Ok(Some(tx.clone()));
+        }
+
+        let data = match self.store.get(&tx_hash.to_hex())? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        ensure!(
+            crypto::hash(&data) == *tx_hash,
+            "Archived transaction {:?} does not match its hash",
+            tx_hash
+        );
+        let tx = Signed::<RawTransaction>::from_bytes(data.into())?;
+        self.cache.lock().expect("Expected mutex lock").insert(
+            *tx_hash,
+            tx.clone(),
+            self.cache_size,
+        );
+        Ok(Some(tx))
+    }
+}
+
+impl fmt::Debug for TransactionArchive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TransactionArchive")
+            .field("cache_size", &self.cache_size)
+            .finish()
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Audit of the transaction execution.
+//!
+//! The integrity check verifies that the stored blocks are consistent with each other,
+//! but it trusts the validators to have executed the transactions correctly. The audit
+//! replays the whole chain from the genesis block in a separate blockchain: each block
+//! is re-executed from its stored transactions, and the resulting block, including its
+//! `tx_hash` and `state_hash`, is compared with the stored one. The first divergent block
+//! is reported; the blocks after it cannot be re-executed, since their state differs.
+
+use exonum_merkledb::Snapshot;
+
+use std::collections::BTreeMap;
+
+use super::interchange::{
+    chain_block, chain_configs, chain_forks, initialize_genesis, parse_transaction, restore_forks,
+};
+use super::{Block, Blockchain, GenesisState, Schema};
+use crate::crypto::CryptoHash;
+use crate::helpers::Height;
+use crate::messages::Precommit;
+
+/// Number of blocks between the progress messages of the audit.
+const PROGRESS_INTERVAL: u64 = 1_000;
+
+/// Block which differs from the stored one after re-execution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditDivergence {
+    /// Height of the block.
+    pub height: Height,
+    /// Block stored in the audited blockchain.
+    pub stored: Block,
+    /// Block produced by the re-execution.
+    pub executed: Block,
+}
+
+impl AuditDivergence {
+    /// Returns `true` if the transactions of the block differ, e.g., because the
+    /// stored transactions have been tampered with.
+    pub fn tx_hash_differs(&self) -> bool {
+        self.stored.tx_hash() != self.executed.tx_hash()
+    }
+
+    /// Returns `true` if the state after the execution of the block differs.
+    pub fn state_hash_differs(&self) -> bool {
+        self.stored.state_hash() != self.executed.state_hash()
+    }
+}
+
+/// Result of the audit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditReport {
+    /// Height of the last block confirmed by the re-execution, or `None` if even
+    /// the genesis block differs.
+    pub verified_height: Option<Height>,
+    /// The first block which differs after the re-execution, if any.
+    pub divergence: Option<AuditDivergence>,
+}
+
+impl AuditReport {
+    /// Returns `true` if all the blocks are confirmed by the re-execution.
+    pub fn is_consistent(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+/// Re-executes the blocks committed to the snapshot and compares them with the stored
+/// ones. The blocks are executed by `blockchain`, which must use an empty database
+/// (e.g., a `TemporaryDB`) and have the same services as the audited one. `genesis_state`
+/// must be the same as the one used to create the audited chain.
+///
+/// Returns an error if the chain cannot be replayed, e.g., if the transactions of some
+/// block are not stored because of the retention policies.
+pub fn audit_chain(
+    snapshot: &dyn Snapshot,
+    blockchain: &mut Blockchain,
+    genesis_state: GenesisState,
+) -> Result<AuditReport, failure::Error> {
+    ensure!(
+        Schema::new(&blockchain.snapshot())
+            .block_hashes_by_height()
+            .is_empty(),
+        "The chain can be audited with an empty database only"
+    );
+    let schema = Schema::new(snapshot);
+    let block_hashes = schema.block_hashes_by_height();
+    ensure!(
+        !block_hashes.is_empty(),
+        "The blockchain is not initialized"
+    );
+    let last_height = Height(block_hashes.len() - 1);
+    let configs = chain_configs(&schema)?;
+    let forks = chain_forks(&schema);
+
+    let genesis = chain_block(&schema, &block_hashes.get(0).unwrap_or_default())?;
+    initialize_genesis(blockchain, genesis_state, &configs, schema.network_id())?;
+    if blockchain.last_hash() != genesis.block.hash() {
+        return Ok(AuditReport {
+            verified_height: None,
+            divergence: Some(AuditDivergence {
+                height: Height::zero(),
+                stored: genesis.block,
+                executed: blockchain.last_block(),
+            }),
+        });
+    }
+
+    for height in 1..=last_height.0 {
+        restore_forks(blockchain, &forks, Height(height - 1))?;
+        let block_hash = block_hashes.get(height).unwrap_or_default();
+        let stored = chain_block(&schema, &block_hash)
+            .map_err(|e| format_err!("Cannot replay the block at height {}: {}", height, e))?;
+
+        let mut tx_cache = BTreeMap::new();
+        let mut tx_hashes = Vec::with_capacity(stored.transactions.len());
+        for raw in stored.transactions {
+            let tx = parse_transaction(raw)?;
+            tx_hashes.push(tx.hash());
+            tx_cache.insert(tx.hash(), tx);
+        }
+        let precommits = stored
+            .precommits
+            .into_iter()
+            .map(Precommit::verify_precommit)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (executed_hash, patch) = blockchain.create_patch_with_time(
+            stored.block.proposer_id(),
+            Height(height),
+            stored.block.time(),
+            &tx_hashes,
+            &mut tx_cache,
+        );
+        if executed_hash != block_hash {
+            let executed = Schema::new(&patch as &dyn Snapshot)
+                .blocks()
+                .get(&executed_hash)
+                .expect("Executed block is missing in the patch");
+            return Ok(AuditReport {
+                verified_height: Some(Height(height - 1)),
+                divergence: Some(AuditDivergence {
+                    height: Height(height),
+                    stored: stored.block,
+                    executed,
+                }),
+            });
+        }
+        blockchain.commit(patch, block_hash, precommits.into_iter(), &mut tx_cache)?;
+
+        if height % PROGRESS_INTERVAL == 0 {
+            info!("Audited blocks up to height {} of {}", height, last_height);
+        }
+    }
+
+    Ok(AuditReport {
+        verified_height: Some(last_height),
+        divergence: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::interchange::tests::{create_blockchain, create_chain};
+    use crate::crypto::Hash;
+
+    #[test]
+    fn replayed_chain_matches_stored_blocks() {
+        let (source, _) = create_chain(3);
+        let mut replay = create_blockchain();
+        let report = audit_chain(
+            source.snapshot().as_ref(),
+            &mut replay,
+            GenesisState::default(),
+        )
+        .unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.verified_height, Some(Height(3)));
+        assert_eq!(replay.last_hash(), source.last_hash());
+    }
+
+    #[test]
+    fn tampered_state_is_reported() {
+        let (mut source, _) = create_chain(3);
+        // Replace the stored block at height 2 with a block of a different state.
+        let fork = source.fork();
+        {
+            let schema = Schema::new(&fork);
+            let block_hash = schema.block_hash_by_height(Height(2)).unwrap();
+            let block = schema.blocks().get(&block_hash).unwrap();
+            let tampered = Block::new(
+                block.proposer_id(),
+                block.height(),
+                block.tx_count(),
+                block.prev_hash(),
+                block.tx_hash(),
+                &Hash::zero(),
+            )
+            .with_additional_headers(block.additional_headers().clone());
+            schema.blocks().put(&tampered.hash(), tampered.clone());
+            schema.block_hashes_by_height().set(2, tampered.hash());
+        }
+        source.merge(fork.into_patch()).unwrap();
+
+        let mut replay = create_blockchain();
+        let report = audit_chain(
+            source.snapshot().as_ref(),
+            &mut replay,
+            GenesisState::default(),
+        )
+        .unwrap();
+        assert_eq!(report.verified_height, Some(Height(1)));
+        let divergence = report.divergence.unwrap();
+        assert_eq!(divergence.height, Height(2));
+        assert!(divergence.state_hash_differs());
+        assert!(!divergence.tx_hash_differs());
+    }
+}
@@ -12,10 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use chrono::{DateTime, Utc};
+use exonum_merkledb::BinaryValue;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use std::{borrow::Cow, collections::BTreeMap};
+
 use crate::crypto::Hash;
 use crate::helpers::{Height, ValidatorId};
 use crate::messages::{Precommit, Signed};
-use crate::proto;
+use crate::proto::{self, ProtobufConvert};
 
 /// Exonum block header data structure.
 ///
@@ -41,6 +47,9 @@ pub struct Block {
     tx_hash: Hash,
     /// Hash of the blockchain state after applying transactions in the block.
     state_hash: Hash,
+    /// Additional key/value entries of the header.
+    #[serde(default, skip_serializing_if = "AdditionalHeaders::is_empty")]
+    additional_headers: AdditionalHeaders,
 }
 
 impl Block {
@@ -60,8 +69,15 @@ impl Block {
             prev_hash: *prev_hash,
             tx_hash: *tx_hash,
             state_hash: *state_hash,
+            additional_headers: AdditionalHeaders::new(),
         }
     }
+
+    /// Returns the block with the given additional headers.
+    pub fn with_additional_headers(mut self, additional_headers: AdditionalHeaders) -> Self {
+        self.additional_headers = additional_headers;
+        self
+    }
     /// Identifier of the leader node which has proposed the block.
     pub fn proposer_id(&self) -> ValidatorId {
         self.proposer_id
@@ -87,6 +103,209 @@ impl Block {
     pub fn state_hash(&self) -> &Hash {
         &self.state_hash
     }
+    /// Additional key/value entries of the header.
+    pub fn additional_headers(&self) -> &AdditionalHeaders {
+        &self.additional_headers
+    }
+    /// Root hash of the Merkle tree of the execution result hashes of the transactions
+    /// in this block, see [`Schema::block_transaction_results`]. The hash is absent
+    /// for the blocks without transactions and for the blocks created before the hash
+    /// was introduced.
+    ///
+    /// [`Schema::block_transaction_results`]: struct.Schema.html#method.block_transaction_results
+    pub fn tx_results_hash(&self) -> Option<Hash> {
+        self.additional_headers.get::<TxResultsHash>().ok()?
+    }
+    /// Root hash of the Merkle tree of the events emitted by the transactions in this
+    /// block, see [`Schema::block_events`]. The hash is absent for the blocks without
+    /// events.
+    ///
+    /// [`Schema::block_events`]: struct.Schema.html#method.block_events
+    pub fn events_hash(&self) -> Option<Hash> {
+        self.additional_headers.get::<EventsHash>().ok()?
+    }
+    /// Consensus time of this block, i.e., the time of the `Propose` the block has been
+    /// created from. The time is absent for the genesis block and for the blocks created
+    /// without a `Propose`, e.g., by the testing tools.
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        self.additional_headers.get::<BlockTime>().ok()?
+    }
+}
+
+/// Key of the root hash of the transaction execution results in the additional
+/// headers of a block.
+#[derive(Debug)]
+pub struct TxResultsHash;
+
+impl BlockHeaderKey for TxResultsHash {
+    const NAME: &'static str = "tx_results_hash";
+    type Value = Hash;
+}
+
+/// Key of the root hash of the transaction events in the additional headers of a block.
+#[derive(Debug)]
+pub struct EventsHash;
+
+impl BlockHeaderKey for EventsHash {
+    const NAME: &'static str = "events_hash";
+    type Value = Hash;
+}
+
+/// Key of the consensus time in the additional headers of a block.
+#[derive(Debug)]
+pub struct BlockTime;
+
+impl BlockHeaderKey for BlockTime {
+    const NAME: &'static str = "time";
+    type Value = DateTime<Utc>;
+}
+
+/// Key of a typed entry in the additional headers of a block.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::blockchain::{AdditionalHeaders, BlockHeaderKey};
+///
+/// struct ProtocolVersion;
+///
+/// impl BlockHeaderKey for ProtocolVersion {
+///     const NAME: &'static str = "protocol_version";
+///     type Value = u32;
+/// }
+///
+/// let mut headers = AdditionalHeaders::new();
+/// headers.insert::<ProtocolVersion>(3);
+/// assert_eq!(headers.get::<ProtocolVersion>().unwrap(), Some(3));
+/// ```
+pub trait BlockHeaderKey {
+    /// Name of the entry.
+    const NAME: &'static str;
+    /// Type of the entry value.
+    type Value: BinaryValue;
+}
+
+/// Additional entries of a block header, which are set by the services and the
+/// consensus layer.
+///
+/// The entries are stored as raw bytes sorted by the keys, and can be accessed
+/// in a typed way with a [`BlockHeaderKey`]. A block without the additional
+/// headers has the same hash as the blocks created before they were introduced,
+/// so the existing block proofs remain valid. In JSON, the values are represented
+/// as hex strings.
+///
+/// [`BlockHeaderKey`]: trait.BlockHeaderKey.html
+#[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug, Default)]
+pub struct AdditionalHeaders {
+    headers: BTreeMap<String, Vec<u8>>,
+}
+
+impl AdditionalHeaders {
+    /// Creates empty additional headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the typed entry, replacing the previous value with the same key.
+    pub fn insert<K: BlockHeaderKey>(&mut self, value: K::Value) {
+        self.insert_raw(K::NAME, value.into_bytes());
+    }
+
+    /// Returns the typed entry, or `None` if there is no entry with the key.
+    /// Returns an error if the entry cannot be decoded.
+    pub fn get<K: BlockHeaderKey>(&self) -> Result<Option<K::Value>, failure::Error> {
+        self.get_raw(K::NAME)
+            .map(|bytes| K::Value::from_bytes(Cow::Borrowed(bytes)))
+            .transpose()
+    }
+
+    /// Inserts the raw entry, replacing the previous value with the same key.
+    pub fn insert_raw(&mut self, key: impl Into<String>, value: Vec<u8>) {
+        self.headers.insert(key.into(), value);
+    }
+
+    /// Returns the raw entry, or `None` if there is no entry with the key.
+    pub fn get_raw(&self, key: &str) -> Option<&[u8]> {
+        self.headers.get(key).map(Vec::as_slice)
+    }
+
+    /// Returns `true` if there is an entry with the key.
+    pub fn contains(&self, key: &str) -> bool {
+        self.headers.contains_key(key)
+    }
+
+    /// Returns an iterator over the raw entries sorted by the keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.headers
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_slice()))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+}
+
+impl ProtobufConvert for AdditionalHeaders {
+    type ProtoStruct = Vec<proto::BlockHeaderEntry>;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        self.headers
+            .iter()
+            .map(|(key, value)| {
+                let mut entry = proto::BlockHeaderEntry::new();
+                entry.set_key(key.clone());
+                entry.set_value(value.clone());
+                entry
+            })
+            .collect()
+    }
+
+    fn from_pb(pb: Self::ProtoStruct) -> Result<Self, failure::Error> {
+        let mut headers = BTreeMap::new();
+        for mut entry in pb {
+            let key = entry.take_key();
+            ensure!(
+                headers.keys().next_back().map_or(true, |last| *last < key),
+                "Additional headers are not sorted by the keys or contain duplicates"
+            );
+            headers.insert(key, entry.take_value());
+        }
+        Ok(Self { headers })
+    }
+}
+
+impl Serialize for AdditionalHeaders {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.headers
+            .iter()
+            .map(|(key, value)| (key, hex::encode(value)))
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AdditionalHeaders {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let headers = BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| hex::decode(value).map(|value| (key, value)))
+            .collect::<Result<_, _>>()
+            .map_err(de::Error::custom)?;
+        Ok(Self { headers })
+    }
 }
 
 /// Block with its `Precommit` messages.
@@ -137,4 +356,55 @@ mod tests {
         let block1: Block = ::serde_json::from_str(&json_str).unwrap();
         assert_eq!(block1, block);
     }
+
+    struct ProtocolVersion;
+
+    impl BlockHeaderKey for ProtocolVersion {
+        const NAME: &'static str = "protocol_version";
+        type Value = u32;
+    }
+
+    #[test]
+    fn test_block_additional_headers() {
+        use crate::crypto::CryptoHash;
+
+        let block = Block::new(
+            ValidatorId(0),
+            Height(1),
+            0,
+            &hash(&[1]),
+            &Hash::zero(),
+            &hash(&[2]),
+        );
+        let mut headers = AdditionalHeaders::new();
+        headers.insert::<ProtocolVersion>(2);
+        headers.insert_raw("anchoring", vec![1, 2, 3]);
+        let extended = block.clone().with_additional_headers(headers);
+
+        assert_ne!(extended.hash(), block.hash());
+        assert_eq!(extended.additional_headers().len(), 2);
+        assert_eq!(
+            extended
+                .additional_headers()
+                .get::<ProtocolVersion>()
+                .unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            extended.additional_headers().get_raw("anchoring"),
+            Some(&[1, 2, 3][..])
+        );
+
+        // Blocks without additional headers keep their encoding.
+        let json = serde_json::to_value(&block).unwrap();
+        assert!(json.get("additional_headers").is_none());
+        let restored = Block::from_pb(block.to_pb()).unwrap();
+        assert_eq!(restored, block);
+
+        let json = serde_json::to_string(&extended).unwrap();
+        let restored: Block = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, extended);
+        let restored = Block::from_pb(extended.to_pb()).unwrap();
+        assert_eq!(restored, extended);
+    }
 }
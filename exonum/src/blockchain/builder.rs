@@ -0,0 +1,258 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fallible construction of the blockchain.
+
+use exonum_merkledb::{Database, Snapshot};
+
+use std::{collections::HashSet, fmt, ops::RangeInclusive, sync::Arc};
+
+use super::{Blockchain, GenesisConfig, GenesisState, Service};
+use crate::crypto::{PublicKey, SecretKey};
+use crate::node::ApiSender;
+
+/// Service identifiers reserved for the services built into the framework, such as
+/// the configuration and the time services. See [`Service::is_builtin`].
+///
+/// [`Service::is_builtin`]: trait.Service.html#method.is_builtin
+pub const RESERVED_SERVICE_IDS: RangeInclusive<u16> = 0..=127;
+
+/// Prefix of the names of the core tables.
+const CORE_TABLES_PREFIX: &str = "core.";
+
+/// Error of the blockchain construction.
+#[derive(Debug, Fail, PartialEq)]
+pub enum BlockchainBuildError {
+    /// Several services have the same identifier.
+    #[fail(display = "Services already contain a service with id={}", _0)]
+    DuplicateServiceId(u16),
+    /// Several services have the same name.
+    #[fail(display = "Services already contain a service with name={}", _0)]
+    DuplicateServiceName(String),
+    /// A service other than the built-in ones uses a reserved identifier.
+    #[fail(
+        display = "Service <{}> uses id={} reserved for the built-in services",
+        _1, _0
+    )]
+    ReservedServiceId(u16, String),
+    /// The number of the services exceeds the limit.
+    #[fail(display = "{} services are registered, the limit is {}", _0, _1)]
+    TooManyServices(usize, usize),
+    /// The name of a state table of the service is not prefixed with the service name.
+    #[fail(
+        display = "Service <{}> aggregates the state table {} not prefixed with the service name",
+        _0, _1
+    )]
+    InvalidStateTableName(String, String),
+    /// Several state tables have the same name.
+    #[fail(display = "State table {} is aggregated several times", _0)]
+    DuplicateStateTable(String),
+    /// The genesis configuration is logically incorrect.
+    #[fail(display = "Invalid genesis configuration: {}", _0)]
+    InvalidGenesisConfig(String),
+    /// The genesis block cannot be created, e.g., because a service fails
+    /// to import its genesis state.
+    #[fail(display = "Cannot create the genesis block: {}", _0)]
+    GenesisInitialization(String),
+}
+
+/// Limits on the services registered in the blockchain.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ServiceLimits {
+    /// Maximum number of the services, or `None` if the number is not limited.
+    pub max_services: Option<usize>,
+    /// Whether the services other than the built-in ones are rejected if their
+    /// identifiers are in [`RESERVED_SERVICE_IDS`]. Otherwise, such services are
+    /// only reported in the log, since the blockchains created before the range
+    /// has been reserved may contain them.
+    ///
+    /// [`RESERVED_SERVICE_IDS`]: constant.RESERVED_SERVICE_IDS.html
+    pub reserve_builtin_ids: bool,
+}
+
+impl ServiceLimits {
+    /// Checks the services against the limits.
+    pub(super) fn check(&self, services: &[Box<dyn Service>]) -> Result<(), BlockchainBuildError> {
+        if let Some(max_services) = self.max_services {
+            if services.len() > max_services {
+                return Err(BlockchainBuildError::TooManyServices(
+                    services.len(),
+                    max_services,
+                ));
+            }
+        }
+        for service in services {
+            let id = service.service_id();
+            if service.is_builtin() || !RESERVED_SERVICE_IDS.contains(&id) {
+                continue;
+            }
+            if self.reserve_builtin_ids {
+                return Err(BlockchainBuildError::ReservedServiceId(
+                    id,
+                    service.service_name().to_owned(),
+                ));
+            }
+            warn!(
+                "Service <{}> uses id={} reserved for the built-in services",
+                service.service_name(),
+                id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the state tables of each service are prefixed with the service name,
+/// so that they cannot collide with the core tables or with the tables of other services.
+pub(super) fn check_state_tables<'a, I>(
+    snapshot: &dyn Snapshot,
+    services: I,
+) -> Result<(), BlockchainBuildError>
+where
+    I: IntoIterator<Item = &'a dyn Service>,
+{
+    let mut names = HashSet::new();
+    for service in services {
+        let prefix = format!("{}.", service.service_name());
+        for (name, _) in service.state_hash_tables(snapshot) {
+            if !name.starts_with(&prefix) || name.starts_with(CORE_TABLES_PREFIX) {
+                return Err(BlockchainBuildError::InvalidStateTableName(
+                    service.service_name().to_owned(),
+                    name,
+                ));
+            }
+            if !names.insert(name.clone()) {
+                return Err(BlockchainBuildError::DuplicateStateTable(name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builder of the blockchain, which returns an error instead of panicking
+/// if the services or the genesis configuration are incorrect.
+///
+/// # Examples
+///
+/// ```
+/// # use exonum::blockchain::{BlockchainBuilder, GenesisConfig, ValidatorKeys};
+/// # use exonum::crypto::gen_keypair;
+/// # use exonum::node::ApiSender;
+/// # use exonum_merkledb::TemporaryDB;
+/// # use futures::sync::mpsc;
+/// let validator_keys = ValidatorKeys::new(gen_keypair().0, gen_keypair().0);
+/// let blockchain = BlockchainBuilder::new(
+///     TemporaryDB::new(),
+///     gen_keypair(),
+///     ApiSender::new(mpsc::channel(0).0),
+/// )
+/// .with_genesis_config(GenesisConfig::new(vec![validator_keys].into_iter()))
+/// .build()
+/// .expect("Cannot create blockchain");
+/// assert_eq!(blockchain.last_block().height(), exonum::helpers::Height(0));
+/// ```
+pub struct BlockchainBuilder {
+    database: Arc<dyn Database>,
+    services: Vec<Box<dyn Service>>,
+    service_keypair: (PublicKey, SecretKey),
+    api_sender: ApiSender,
+    genesis_config: Option<GenesisConfig>,
+    genesis_state: GenesisState,
+    service_limits: ServiceLimits,
+}
+
+impl fmt::Debug for BlockchainBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlockchainBuilder")
+            .field("services", &self.services.len())
+            .field("genesis_config", &self.genesis_config)
+            .field("service_limits", &self.service_limits)
+            .finish()
+    }
+}
+
+impl BlockchainBuilder {
+    /// Creates a builder of the blockchain without services for the given storage.
+    pub fn new<D: Into<Arc<dyn Database>>>(
+        database: D,
+        service_keypair: (PublicKey, SecretKey),
+        api_sender: ApiSender,
+    ) -> Self {
+        Self {
+            database: database.into(),
+            services: Vec::new(),
+            service_keypair,
+            api_sender,
+            genesis_config: None,
+            genesis_state: GenesisState::default(),
+            service_limits: ServiceLimits::default(),
+        }
+    }
+
+    /// Adds a service to the blockchain.
+    pub fn with_service(mut self, service: Box<dyn Service>) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Adds services to the blockchain.
+    pub fn with_services<I>(mut self, services: I) -> Self
+    where
+        I: IntoIterator<Item = Box<dyn Service>>,
+    {
+        self.services.extend(services);
+        self
+    }
+
+    /// Makes the builder create the genesis block with the given configuration
+    /// if the storage does not contain it yet.
+    pub fn with_genesis_config(mut self, config: GenesisConfig) -> Self {
+        self.genesis_config = Some(config);
+        self
+    }
+
+    /// Sets the state imported by the services before the genesis block is created.
+    /// The state is ignored unless the genesis configuration is specified.
+    pub fn with_genesis_state(mut self, state: GenesisState) -> Self {
+        self.genesis_state = state;
+        self
+    }
+
+    /// Sets the limits on the services of the blockchain.
+    pub fn with_service_limits(mut self, limits: ServiceLimits) -> Self {
+        self.service_limits = limits;
+        self
+    }
+
+    /// Creates the blockchain and initializes it with the genesis configuration,
+    /// if one is specified.
+    pub fn build(self) -> Result<Blockchain, BlockchainBuildError> {
+        let mut blockchain = Blockchain::from_parts(
+            self.database,
+            self.services,
+            self.service_keypair,
+            self.api_sender,
+            self.service_limits,
+        )?;
+        if let Some(config) = self.genesis_config {
+            config
+                .validate()
+                .map_err(|e| BlockchainBuildError::InvalidGenesisConfig(e.to_string()))?;
+            blockchain
+                .initialize_with_state(config, self.genesis_state)
+                .map_err(|e| BlockchainBuildError::GenesisInitialization(e.to_string()))?;
+        }
+        Ok(blockchain)
+    }
+}
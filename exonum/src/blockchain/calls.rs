@@ -0,0 +1,188 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Calls of the methods of other services made by the transactions.
+//!
+//! A transaction may synchronously invoke a method of another service with
+//! [`TransactionContext::call`]; the called service handles it in
+//! [`Service::handle_call`]. The call is executed within the same fork and charges
+//! the gas to the same meter as the calling transaction, and the error returned by
+//! the called service is returned to the caller, which usually propagates it with `?`.
+//! Calls may be nested up to [`MAX_CALL_DEPTH`].
+//!
+//! The changes made by a failed call are not rolled back by themselves: the called
+//! service should check its arguments before changing the storage, or the caller
+//! should fail as well, which discards all the changes of the transaction. If the
+//! called service is unknown, is not active, does not handle the method, or the depth
+//! limit is exceeded, the transaction is aborted and committed with the `Panic` status.
+//!
+//! The tables changed by a transaction are checked against the namespaces of all the
+//! services it has called in addition to the namespace of its own service.
+//!
+//! [`TransactionContext::call`]: ../struct.TransactionContext.html#method.call
+//! [`Service::handle_call`]: ../trait.Service.html#method.handle_call
+//! [`MAX_CALL_DEPTH`]: constant.MAX_CALL_DEPTH.html
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use super::Service;
+
+/// Maximum depth of the nested calls of the service methods.
+pub const MAX_CALL_DEPTH: usize = 8;
+
+/// Dispatches the calls of a transaction to the services of the blockchain.
+pub(crate) struct CallDispatcher<'a> {
+    services: &'a HashMap<u16, Box<dyn Service>>,
+    called: RefCell<BTreeSet<u16>>,
+}
+
+impl<'a> CallDispatcher<'a> {
+    /// Creates a dispatcher for the given services.
+    pub(crate) fn new(services: &'a HashMap<u16, Box<dyn Service>>) -> Self {
+        Self {
+            services,
+            called: RefCell::default(),
+        }
+    }
+
+    /// Returns the service with the given identifier and records it as called.
+    pub(crate) fn service(&self, service_id: u16) -> Option<&'a dyn Service> {
+        let service = self.services.get(&service_id)?;
+        self.called.borrow_mut().insert(service_id);
+        Some(service.as_ref())
+    }
+
+    /// Returns the identifiers of the services called so far.
+    pub(crate) fn called(&self) -> BTreeSet<u16> {
+        self.called.borrow().clone()
+    }
+}
+
+impl<'a> fmt::Debug for CallDispatcher<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CallDispatcher")
+            .field("called", &self.called)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_merkledb::{Database, ListIndex, Snapshot, TemporaryDB};
+
+    use super::*;
+    use crate::blockchain::{ExecutionError, Transaction, TransactionContext};
+    use crate::crypto::{gen_keypair, hash, Hash};
+    use crate::messages::RawTransaction;
+
+    const COUNTER_ID: u16 = 10;
+    const RELAY_ID: u16 = 11;
+
+    struct TestService(u16);
+
+    impl Service for TestService {
+        fn service_id(&self) -> u16 {
+            self.0
+        }
+
+        fn service_name(&self) -> &str {
+            if self.0 == COUNTER_ID {
+                "counter"
+            } else {
+                "relay"
+            }
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, _: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            unreachable!("Transactions are not sent in the test");
+        }
+
+        fn handle_call(
+            &self,
+            context: &TransactionContext,
+            method: &str,
+            args: &[u8],
+        ) -> Result<Vec<u8>, ExecutionError> {
+            match method {
+                "add" => {
+                    ListIndex::new("counter.values", context.fork()).push(args[0]);
+                    Ok(vec![context.caller().unwrap() as u8])
+                }
+                "fail" => Err(ExecutionError::new(3)),
+                "relay" => context.call(RELAY_ID, "relay", args),
+                _ => panic!("Unknown method `{}`", method),
+            }
+        }
+    }
+
+    fn services() -> HashMap<u16, Box<dyn Service>> {
+        vec![COUNTER_ID, RELAY_ID]
+            .into_iter()
+            .map(|id| (id, Box::new(TestService(id)) as Box<dyn Service>))
+            .collect()
+    }
+
+    /// Calls the method on behalf of a transaction of service 1. Returns the result
+    /// of the call, the called services and the values added by the counter.
+    fn call(
+        service_id: u16,
+        method: &str,
+    ) -> (Result<Vec<u8>, ExecutionError>, BTreeSet<u16>, Vec<u8>) {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let services = services();
+        let dispatcher = CallDispatcher::new(&services);
+        let context =
+            TransactionContext::from_parts(&fork, 1, "caller", hash(&[1]), gen_keypair().0)
+                .with_dispatcher(&dispatcher);
+        let result = context.call(service_id, method, &[5]);
+        let values = ListIndex::new("counter.values", &fork).iter().collect();
+        (result, dispatcher.called(), values)
+    }
+
+    #[test]
+    fn calls_are_dispatched_to_services() {
+        let (result, called, values) = call(COUNTER_ID, "add");
+        assert_eq!(result, Ok(vec![1]));
+        assert_eq!(called, vec![COUNTER_ID].into_iter().collect());
+        assert_eq!(values, vec![5]);
+    }
+
+    #[test]
+    fn call_errors_are_propagated() {
+        let (result, _, values) = call(COUNTER_ID, "fail");
+        assert_eq!(result, Err(ExecutionError::new(3)));
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Depth of the service calls exceeds 8")]
+    fn call_depth_is_limited() {
+        call(RELAY_ID, "relay").0.unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Service with id=12 is not found")]
+    fn unknown_service_cannot_be_called() {
+        call(12, "add").0.unwrap();
+    }
+}
@@ -24,16 +24,24 @@
 use serde::de::Error;
 use serde_json::Error as JsonError;
 
-use std::collections::{BTreeMap, HashSet};
+use std::{
+    cmp,
+    collections::{BTreeMap, HashSet},
+};
 
 use crate::crypto::{hash, CryptoHash, Hash, PublicKey};
-use crate::helpers::{Height, Milliseconds};
-use crate::messages::EMPTY_SIGNED_MESSAGE_SIZE;
+use crate::helpers::{Height, Milliseconds, ValidatorId};
+use crate::messages::{SignatureAlgorithm, EMPTY_SIGNED_MESSAGE_SIZE};
 use exonum_merkledb::{BinaryValue, ObjectHash};
 
 /// Public keys of a validator. Each validator has two public keys: the
 /// `consensus_key` is used for internal operations in the consensus process,
 /// while the `service_key` is used in services.
+///
+/// Each key is accompanied by its signature scheme. The schemes are omitted from
+/// the serialized configuration if they are Ed25519, so that the hashes of the
+/// existing configurations do not change. The same applies to the voting weight
+/// of the validator if it is equal to the default weight of `1`.
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ValidatorKeys {
     /// Consensus key is used for messages related to the consensus algorithm.
@@ -41,6 +49,39 @@ pub struct ValidatorKeys {
     /// Service key is used for services, for example, the configuration
     /// updater service, the anchoring service, etc.
     pub service_key: PublicKey,
+    /// Signature scheme of the consensus key.
+    #[serde(default, skip_serializing_if = "is_ed25519")]
+    pub consensus_key_algorithm: SignatureAlgorithm,
+    /// Signature scheme of the service key.
+    #[serde(default, skip_serializing_if = "is_ed25519")]
+    pub service_key_algorithm: SignatureAlgorithm,
+    /// Voting power of the validator. A quorum is reached once the validators with
+    /// the total weight greater than `2/3` of the weight of all validators have voted,
+    /// and the validators propose blocks in proportion to their weights.
+    #[serde(
+        default = "default_validator_weight",
+        skip_serializing_if = "is_default_validator_weight"
+    )]
+    pub weight: u64,
+}
+
+impl ValidatorKeys {
+    /// Creates the keys of a validator using the Ed25519 signature scheme.
+    pub fn new(consensus_key: PublicKey, service_key: PublicKey) -> Self {
+        Self {
+            consensus_key,
+            service_key,
+            consensus_key_algorithm: SignatureAlgorithm::Ed25519,
+            service_key_algorithm: SignatureAlgorithm::Ed25519,
+            weight: DEFAULT_VALIDATOR_WEIGHT,
+        }
+    }
+
+    /// Sets the voting weight of the validator.
+    pub fn with_weight(mut self, weight: u64) -> Self {
+        self.weight = weight;
+        self
+    }
 }
 
 /// Exonum blockchain global configuration. Services
@@ -121,6 +162,93 @@ pub struct ConsensusConfig {
     /// in a block if the transaction pool is almost empty, and create blocks faster when there are
     /// enough transactions in the pool.
     pub propose_timeout_threshold: u32,
+    /// Whether validators lagging behind the network may ask to be skipped
+    /// in the proposer rotation until they catch up.
+    ///
+    /// If enabled, a lagging validator sets the `skip_propose` flag in its `Status`
+    /// messages. The leaders are selected as usual, but other nodes wait for
+    /// the proposals of such a validator only for `max_propose_timeout` and then move
+    /// to the next round. The flag is omitted from the serialized
+    /// configuration if disabled, so it does not affect hashes of existing configurations.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub skip_lagging_proposers: bool,
+    /// Whether the leaders broadcast compact proposals, which list 8-byte short
+    /// identifiers of the transactions instead of their hashes.
+    ///
+    /// The nodes resolve the identifiers against their pools and request all unresolved
+    /// transactions from the proposer in a single message right away. The flag is omitted
+    /// from the serialized configuration if disabled.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub compact_proposals: bool,
+    /// Whether the transactions of different services within a block are executed
+    /// in parallel.
+    ///
+    /// A transaction then observes the changes made within the block only by the
+    /// transactions of its own service. See the [`create_patch`] method for details.
+    /// The flag is omitted from the serialized configuration if disabled.
+    ///
+    /// [`create_patch`]: ../struct.Blockchain.html#method.create_patch
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub parallel_execution: bool,
+    /// Maximum amount of gas a single transaction may consume, or `0` if
+    /// the gas is not limited.
+    ///
+    /// The framework charges one unit of gas per byte of the transaction message
+    /// before its execution, and services charge the gas for the work they do
+    /// with the [`charge_gas`] method. A transaction exceeding the limit fails with
+    /// the `OutOfGas` error and its changes are discarded. The limit is omitted
+    /// from the serialized configuration if not set.
+    ///
+    /// [`charge_gas`]: ../struct.TransactionContext.html#method.charge_gas
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub transaction_gas_limit: u64,
+    /// Maximum total size of the transaction messages in a block, in bytes, or `0`
+    /// if the size is not limited.
+    ///
+    /// The proposer does not include the transactions exceeding the limit, and
+    /// the transactions of a block exceeding it are not executed and fail with
+    /// the `BlockLimitExceeded` error. The limit is omitted from the serialized
+    /// configuration if not set.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub max_block_size: u64,
+    /// Maximum total amount of gas consumed by the transactions of a block, or `0`
+    /// if the gas is not limited.
+    ///
+    /// A transaction may consume at most the gas left in the block, so a transaction
+    /// exceeding the limit runs out of gas, and the subsequent transactions fail with
+    /// the `BlockLimitExceeded` error without being executed. The proposer only accounts
+    /// for the gas charged for the transaction messages, since the gas consumed by
+    /// the execution is not known in advance. The limit is omitted from the serialized
+    /// configuration if not set.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub max_block_gas: u64,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Voting weight of the validators that do not specify it.
+pub const DEFAULT_VALIDATOR_WEIGHT: u64 = 1;
+
+fn default_validator_weight() -> u64 {
+    DEFAULT_VALIDATOR_WEIGHT
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_default_validator_weight(weight: &u64) -> bool {
+    *weight == DEFAULT_VALIDATOR_WEIGHT
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_ed25519(algorithm: &SignatureAlgorithm) -> bool {
+    *algorithm == SignatureAlgorithm::Ed25519
 }
 
 impl ConsensusConfig {
@@ -176,11 +304,66 @@ impl Default for ConsensusConfig {
             min_propose_timeout: 10,
             max_propose_timeout: 200,
             propose_timeout_threshold: 500,
+            skip_lagging_proposers: false,
+            compact_proposals: false,
+            parallel_execution: false,
+            transaction_gas_limit: 0,
+            max_block_size: 0,
+            max_block_gas: 0,
         }
     }
 }
 
 impl StoredConfiguration {
+    /// Returns the total voting weight of the validators.
+    pub fn total_weight(&self) -> u64 {
+        self.validator_keys
+            .iter()
+            .fold(0, |total, keys| total.saturating_add(keys.weight))
+    }
+
+    /// Returns the voting weight sufficient for a quorum, i.e., the minimal weight greater
+    /// than `2/3` of the total weight. If all validators have the default weight, this is
+    /// the same as the byzantine majority of the validators.
+    pub fn majority_weight(&self) -> u64 {
+        (u128::from(self.total_weight()) * 2 / 3 + 1) as u64
+    }
+
+    /// Returns the total voting weight of the given validators. Each validator should be
+    /// listed at most once; unknown validators have no weight.
+    pub fn weight_of<I>(&self, validators: I) -> u64
+    where
+        I: IntoIterator<Item = ValidatorId>,
+    {
+        validators
+            .into_iter()
+            .filter_map(|id| self.validator_keys.get(id.0 as usize))
+            .fold(0, |total, keys| total.saturating_add(keys.weight))
+    }
+
+    /// Returns `true` if the given validators have the voting weight sufficient for a quorum.
+    /// Each validator should be listed at most once.
+    pub fn has_majority<I>(&self, validators: I) -> bool
+    where
+        I: IntoIterator<Item = ValidatorId>,
+    {
+        self.weight_of(validators) >= self.majority_weight()
+    }
+
+    /// Returns the validator owning the given slot, if the slots are allotted to
+    /// the validators in their order in proportion to their weights.
+    pub(crate) fn validator_by_slot(&self, slot: u64) -> ValidatorId {
+        let slot = slot % cmp::max(self.total_weight(), 1);
+        let mut end = 0_u64;
+        for (id, keys) in self.validator_keys.iter().enumerate() {
+            end = end.saturating_add(keys.weight);
+            if slot < end {
+                return ValidatorId(id as u16);
+            }
+        }
+        ValidatorId::zero()
+    }
+
     /// Tries to serialize the given configuration into a UTF-8 encoded JSON.
     /// The method returns either the result of execution or an error.
     pub fn try_serialize(&self) -> Result<Vec<u8>, JsonError> {
@@ -210,6 +393,22 @@ impl StoredConfiguration {
             }
         }
 
+        // Check voting weights.
+        if config.validator_keys.iter().any(|keys| keys.weight == 0) {
+            return Err(JsonError::custom(
+                "Validator weight should not be equal to zero",
+            ));
+        }
+        let total_weight = config
+            .validator_keys
+            .iter()
+            .try_fold(0_u64, |total, keys| total.checked_add(keys.weight));
+        if total_weight.is_none() {
+            return Err(JsonError::custom(
+                "Total weight of the validators does not fit into u64",
+            ));
+        }
+
         // Check timeouts.
         if config.consensus.min_propose_timeout > config.consensus.max_propose_timeout {
             return Err(JsonError::custom(format!(
@@ -241,6 +440,15 @@ impl StoredConfiguration {
             )));
         }
 
+        // Check that a transaction of the maximum length fits into a block.
+        let max_block_size = config.consensus.max_block_size;
+        if max_block_size != 0 && max_block_size < u64::from(config.consensus.max_message_len) {
+            return Err(JsonError::custom(format!(
+                "max_block_size ({}) must be at least max_message_len ({})",
+                max_block_size, config.consensus.max_message_len
+            )));
+        }
+
         Ok(config)
     }
 }
@@ -268,6 +476,7 @@ impl BinaryValue for StoredConfiguration {
 mod tests {
     use super::*;
     use crate::crypto::{gen_keypair_from_seed, Seed, SEED_LENGTH};
+    use crate::node::State;
 
     // TOML doesn't support all rust types, but `StoredConfiguration` must be able to save as TOML.
     #[test]
@@ -318,14 +527,76 @@ mod tests {
         assert_eq!(configuration, serialize_deserialize(&configuration));
     }
 
+    #[test]
+    fn ed25519_validator_keys_serialization() {
+        let keys = ValidatorKeys::new(PublicKey::zero(), PublicKey::zero());
+        let json = serde_json::to_value(&keys).unwrap();
+        assert_eq!(json.as_object().unwrap().len(), 2);
+        assert_eq!(serde_json::from_value::<ValidatorKeys>(json).unwrap(), keys);
+    }
+
+    #[test]
+    fn weighted_validator_keys_serialization() {
+        let keys = ValidatorKeys::new(PublicKey::zero(), PublicKey::zero()).with_weight(5);
+        let json = serde_json::to_value(&keys).unwrap();
+        assert_eq!(json["weight"], 5);
+        assert_eq!(serde_json::from_value::<ValidatorKeys>(json).unwrap(), keys);
+    }
+
+    #[test]
+    #[should_panic(expected = "Validator weight should not be equal to zero")]
+    fn zero_validator_weight() {
+        let mut configuration = create_test_configuration();
+        configuration.validator_keys[0].weight = 0;
+        serialize_deserialize(&configuration);
+    }
+
+    #[test]
+    fn equal_weights_majority() {
+        let mut configuration = create_test_configuration();
+        for count in 1..10 {
+            configuration.validator_keys = (0..count)
+                .map(|_| ValidatorKeys::new(PublicKey::zero(), PublicKey::zero()))
+                .collect();
+            assert_eq!(
+                configuration.majority_weight(),
+                State::byzantine_majority_count(count) as u64
+            );
+        }
+    }
+
+    #[test]
+    fn weighted_majority() {
+        let mut configuration = create_test_configuration();
+        for (keys, &weight) in configuration.validator_keys.iter_mut().zip(&[6, 2, 1]) {
+            keys.weight = weight;
+        }
+        assert_eq!(configuration.total_weight(), 9);
+        assert_eq!(configuration.majority_weight(), 7);
+        assert!(!configuration.has_majority(vec![ValidatorId(0)]));
+        assert!(configuration.has_majority(vec![ValidatorId(0), ValidatorId(2)]));
+        assert!(!configuration.has_majority(vec![ValidatorId(1), ValidatorId(2)]));
+    }
+
+    #[test]
+    fn weighted_slots() {
+        let mut configuration = create_test_configuration();
+        for (keys, &weight) in configuration.validator_keys.iter_mut().zip(&[1, 3, 2]) {
+            keys.weight = weight;
+        }
+        let validators = (0..12)
+            .map(|slot| configuration.validator_by_slot(slot).0)
+            .collect::<Vec<_>>();
+        assert_eq!(validators, vec![0, 1, 1, 1, 2, 2, 0, 1, 1, 1, 2, 2]);
+    }
+
     #[test]
     #[should_panic(expected = "Duplicated keys are found")]
     fn duplicated_validators_keys() {
         let mut configuration = create_test_configuration();
-        configuration.validator_keys.push(ValidatorKeys {
-            consensus_key: PublicKey::zero(),
-            service_key: PublicKey::zero(),
-        });
+        configuration
+            .validator_keys
+            .push(ValidatorKeys::new(PublicKey::zero(), PublicKey::zero()));
         serialize_deserialize(&configuration);
     }
 
@@ -365,11 +636,21 @@ mod tests {
         serialize_deserialize(&configuration);
     }
 
+    #[test]
+    #[should_panic(expected = "max_block_size (1024) must be at least max_message_len")]
+    fn too_small_max_block_size() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.max_block_size = 1024;
+        serialize_deserialize(&configuration);
+    }
+
     fn create_test_configuration() -> StoredConfiguration {
         let validator_keys = (1..4)
-            .map(|i| ValidatorKeys {
-                consensus_key: gen_keypair_from_seed(&Seed::new([i; SEED_LENGTH])).0,
-                service_key: gen_keypair_from_seed(&Seed::new([i * 10; SEED_LENGTH])).0,
+            .map(|i| {
+                ValidatorKeys::new(
+                    gen_keypair_from_seed(&Seed::new([i; SEED_LENGTH])).0,
+                    gen_keypair_from_seed(&Seed::new([i * 10; SEED_LENGTH])).0,
+                )
             })
             .collect();
 
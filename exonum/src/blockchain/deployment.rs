@@ -0,0 +1,78 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deployment of services to the running blockchain.
+//!
+//! A service returning `true` from [`Service::is_deployable`] is compiled into the node,
+//! but is not initialized in the genesis block. The service is deployed by the first
+//! configuration that includes its section in `services`: when the configuration
+//! becomes actual, the service is initialized with [`Service::initialize`] at the
+//! beginning of the block, right before [`Service::on_config_change`] is invoked.
+//! Starting from this block, the core dispatches the transactions of the service,
+//! aggregates its tables into the state hash, invokes its hooks and serves its API.
+//! Before that, the transactions of the service are rejected and its endpoints
+//! respond with `404 Not Found`.
+//!
+//! The configuration is agreed upon by the validators, so all nodes deploy the service
+//! in the same block; the nodes have to be upgraded to a version containing the service
+//! before the configuration becomes actual. Deployment is permanent: removing the section
+//! of the service from a later configuration does not undeploy the service.
+//!
+//! [`Service::is_deployable`]: ../trait.Service.html#method.is_deployable
+//! [`Service::initialize`]: ../trait.Service.html#method.initialize
+//! [`Service::on_config_change`]: ../trait.Service.html#method.on_config_change
+
+use exonum_merkledb::IndexAccess;
+
+use std::collections::HashMap;
+
+use super::{Schema, Service, StoredConfiguration};
+use crate::helpers::Height;
+
+/// Checks whether the service is deployed in the blockchain state. The services
+/// that are not deployable are deployed in the genesis block.
+pub fn is_deployed<T: IndexAccess>(access: T, service: &dyn Service) -> bool {
+    !service.is_deployable()
+        || Schema::new(access)
+            .deployed_services()
+            .contains(&service.service_id())
+}
+
+/// Returns the height of the block that has deployed the service, or `None` if the
+/// service has been deployed in the genesis block or is not deployed yet.
+pub fn deployment_height<T: IndexAccess>(access: T, service_id: u16) -> Option<Height> {
+    Schema::new(access)
+        .deployed_services()
+        .get(&service_id)
+        .map(Height)
+}
+
+/// Returns the deployable services that are included into the configuration,
+/// but have not been deployed yet, in the order of their identifiers.
+pub(crate) fn pending_services<'a, T: IndexAccess>(
+    access: T,
+    services: &'a HashMap<u16, Box<dyn Service>>,
+    config: &StoredConfiguration,
+) -> Vec<&'a dyn Service> {
+    let deployed = Schema::new(access).deployed_services();
+    let mut pending: Vec<&dyn Service> = services
+        .values()
+        .filter(|service| service.is_deployable())
+        .filter(|service| config.services.contains_key(service.service_name()))
+        .filter(|service| !deployed.contains(&service.service_id()))
+        .map(|service| service.as_ref())
+        .collect();
+    pending.sort_by_key(|service| service.service_id());
+    pending
+}
@@ -0,0 +1,283 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Search for the divergence of the blockchain state between nodes.
+//!
+//! If nodes disagree on the `state_hash` of a block, the divergent data is located
+//! in two steps:
+//!
+//! 1. The nodes compare the root hashes of the tables aggregated into the state hash,
+//!    returned by [`state_hashes`]. See [`divergent_tables`].
+//! 2. For each divergent table being a `ProofMapIndex`, the nodes descend its Merkle
+//!    Patricia tree level by level, following only the nodes with different hashes,
+//!    until they reach the differing entries. See [`find_divergent_entries`].
+//!
+//! Both steps are served by the `v1/state_hash/tables` and `v1/state_hash/nodes`
+//! endpoints of the private system API, so that the nodes do not need to transfer
+//! the whole state. Since only the latest state is retained, the nodes must be
+//! stopped at the same height for the comparison.
+//!
+//! [`state_hashes`]: fn.state_hashes.html
+//! [`divergent_tables`]: fn.divergent_tables.html
+//! [`find_divergent_entries`]: fn.find_divergent_entries.html
+
+use exonum_merkledb::{proof_map_index::ProofPath, ProofMapIndex, Snapshot};
+use failure::Error;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use super::{Blockchain, Schema, StateTable};
+use crate::crypto::Hash;
+use crate::helpers::Height;
+
+/// Root hashes of the tables at the latest height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateHashes {
+    /// Height of the latest committed block.
+    pub height: Height,
+    /// State hash of the latest committed block.
+    pub state_hash: Hash,
+    /// Root hashes of the core and service tables.
+    pub tables: Vec<StateTable>,
+}
+
+/// Node of the Merkle Patricia tree of a `ProofMapIndex`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MerkleNode {
+    /// Path to the node; the path of a leaf node is the hash of its key.
+    pub path: ProofPath,
+    /// Hash of the subtree, or `HashTag::hash_leaf` of the value for a leaf node.
+    pub hash: Hash,
+}
+
+/// Entry of a `ProofMapIndex` differing between the nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DivergentEntry {
+    /// Path to the entry, that is, the hash of its key.
+    pub path: ProofPath,
+    /// Leaf hash of the local value, `None` if the entry is absent locally.
+    pub local: Option<Hash>,
+    /// Leaf hash of the remote value, `None` if the entry is absent on the remote node.
+    pub remote: Option<Hash>,
+}
+
+/// Returns the root hashes of the core and service tables at the latest height.
+pub fn state_hashes(blockchain: &Blockchain) -> StateHashes {
+    let snapshot = blockchain.snapshot();
+    let tables = blockchain.state_tables(snapshot.as_ref());
+    let last_block = Schema::new(snapshot.as_ref()).last_block();
+    StateHashes {
+        height: last_block.height(),
+        state_hash: *last_block.state_hash(),
+        tables,
+    }
+}
+
+/// Returns the local tables whose root hashes differ from the remote ones or which are
+/// absent on the remote node.
+pub fn divergent_tables(local: &StateHashes, remote: &StateHashes) -> Vec<StateTable> {
+    local
+        .tables
+        .iter()
+        .filter(|table| {
+            !remote
+                .tables
+                .iter()
+                .any(|other| other.name == table.name && other.hash == table.hash)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Returns the children of the tree node with the given path of the `ProofMapIndex`
+/// with the given name, or the root node if `path` is `None`.
+///
+/// Fails if the index name is invalid or the index is not a `ProofMapIndex`.
+pub fn merkle_nodes(
+    snapshot: &dyn Snapshot,
+    index_name: &str,
+    path: Option<&ProofPath>,
+) -> Result<Vec<MerkleNode>, Error> {
+    // The node hashes do not depend on the key and value types.
+    let nodes = panic::catch_unwind(AssertUnwindSafe(|| {
+        ProofMapIndex::<_, Hash, Vec<u8>>::new(index_name, snapshot).child_nodes(path)
+    }))
+    .map_err(|_| format_err!("`{}` is not a valid ProofMapIndex name", index_name))?;
+    Ok(nodes
+        .into_iter()
+        .map(|(path, hash)| MerkleNode { path, hash })
+        .collect())
+}
+
+/// Locates the differing entries of a `ProofMapIndex` on two nodes.
+///
+/// `local` and `remote` return the children of the tree node with the given path
+/// on the corresponding node, as [`merkle_nodes`] does. Only the nodes with different
+/// hashes are requested, so the number of requests is proportional to the number
+/// of the differing entries times the depth of the tree.
+///
+/// [`merkle_nodes`]: fn.merkle_nodes.html
+pub fn find_divergent_entries<L, R>(
+    mut local: L,
+    mut remote: R,
+) -> Result<Vec<DivergentEntry>, Error>
+where
+    L: FnMut(Option<&ProofPath>) -> Result<Vec<MerkleNode>, Error>,
+    R: FnMut(Option<&ProofPath>) -> Result<Vec<MerkleNode>, Error>,
+{
+    let mut entries = Vec::new();
+    // Paths to descend along with the flags of their presence on the local and remote nodes.
+    let mut pending: Vec<(Option<ProofPath>, bool, bool)> = vec![(None, true, true)];
+    while let Some((path, in_local, in_remote)) = pending.pop() {
+        let local_nodes = if in_local {
+            local(path.as_ref())?
+        } else {
+            Vec::new()
+        };
+        let remote_nodes = if in_remote {
+            remote(path.as_ref())?
+        } else {
+            Vec::new()
+        };
+
+        for node in &local_nodes {
+            let other = remote_nodes.iter().find(|other| other.path == node.path);
+            if other.map(|other| other.hash) == Some(node.hash) {
+                continue;
+            }
+            if node.path.is_leaf() {
+                entries.push(DivergentEntry {
+                    path: node.path,
+                    local: Some(node.hash),
+                    remote: other.map(|other| other.hash),
+                });
+            } else {
+                pending.push((Some(node.path), true, other.is_some()));
+            }
+        }
+        for node in &remote_nodes {
+            if local_nodes.iter().any(|other| other.path == node.path) {
+                continue;
+            }
+            if node.path.is_leaf() {
+                entries.push(DivergentEntry {
+                    path: node.path,
+                    local: None,
+                    remote: Some(node.hash),
+                });
+            } else {
+                pending.push((Some(node.path), false, true));
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.path.partial_cmp(&b.path).expect("Paths are comparable"));
+
+    // If the trees differ in shape, an entry present on both nodes is reached through
+    // different branches and thus is reported once for each node.
+    let mut merged: Vec<DivergentEntry> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match merged.last_mut() {
+            Some(last) if last.path == entry.path => {
+                last.local = last.local.or(entry.local);
+                last.remote = last.remote.or(entry.remote);
+            }
+            _ => merged.push(entry),
+        }
+    }
+    merged.retain(|entry| entry.local != entry.remote);
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_merkledb::{Database, Fork, HashTag, TemporaryDB};
+
+    use super::*;
+    use crate::blockchain::tests::create_initialized_blockchain;
+    use crate::crypto::hash;
+
+    fn fill_map(fork: &Fork, keys: impl IntoIterator<Item = u8>) {
+        let mut index = ProofMapIndex::new("divergence.map", fork);
+        for key in keys {
+            index.put(&hash(&[key]), vec![key]);
+        }
+    }
+
+    #[test]
+    fn divergent_entries() {
+        let (first, second) = (TemporaryDB::new(), TemporaryDB::new());
+        let fork = first.fork();
+        fill_map(&fork, 0..100);
+        first.merge(fork.into_patch()).unwrap();
+
+        let fork = second.fork();
+        // Key 7 is missing, key 42 has a different value, and key 200 is extra.
+        fill_map(&fork, (0..100).filter(|&key| key != 7).chain(vec![200]));
+        ProofMapIndex::new("divergence.map", &fork).put(&hash(&[42]), vec![0]);
+        second.merge(fork.into_patch()).unwrap();
+
+        let (first, second) = (first.snapshot(), second.snapshot());
+        let mut requests = 0;
+        let entries = find_divergent_entries(
+            |path| {
+                requests += 1;
+                merkle_nodes(first.as_ref(), "divergence.map", path)
+            },
+            |path| merkle_nodes(second.as_ref(), "divergence.map", path),
+        )
+        .unwrap();
+        assert!(requests < 100, "The whole tree has been requested");
+
+        let entry = |key: u8| {
+            entries
+                .iter()
+                .find(|entry| entry.path == ProofPath::new(&hash(&[key])))
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entry(7).remote, None);
+        assert_eq!(entry(200).local, None);
+        assert_eq!(entry(42).local, Some(HashTag::hash_leaf(&[42])));
+        assert_eq!(entry(42).remote, Some(HashTag::hash_leaf(&[0])));
+
+        let same = find_divergent_entries(
+            |path| merkle_nodes(first.as_ref(), "divergence.map", path),
+            |path| merkle_nodes(first.as_ref(), "divergence.map", path),
+        )
+        .unwrap();
+        assert!(same.is_empty());
+        assert!(merkle_nodes(first.as_ref(), "invalid name!", None).is_err());
+    }
+
+    #[test]
+    fn state_hashes_of_blockchain() {
+        let blockchain = create_initialized_blockchain(Vec::new());
+
+        let local = state_hashes(&blockchain);
+        assert_eq!(local.height, Height(0));
+        assert!(local
+            .tables
+            .iter()
+            .all(|table| table.service_name == "core"));
+        assert!(divergent_tables(&local, &local).is_empty());
+
+        let mut remote = local.clone();
+        remote.tables[1].hash = Hash::zero();
+        assert_eq!(
+            divergent_tables(&local, &remote),
+            vec![local.tables[1].clone()]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Events emitted by the transactions.
+//!
+//! A transaction reports what it has done with [`TransactionContext::emit_event`],
+//! e.g., a transfer may emit a `transfer` event with the sender, the receiver and
+//! the amount. The events of a block are appended to the [`block_events`] list in
+//! the order of emission, and the root hash of the list is stored in the block header
+//! as [`Block::events_hash`]. Clients can therefore request the events of a block
+//! from the explorer and verify them against the block header instead of reading
+//! the storage of the service.
+//!
+//! The events are a part of the transaction changes: the events of a failed transaction
+//! are discarded together with its other changes. The events are not a part of the
+//! state hash and are deleted together with the transaction history by the retention
+//! policies.
+//!
+//! [`TransactionContext::emit_event`]: ../struct.TransactionContext.html#method.emit_event
+//! [`block_events`]: ../struct.Schema.html#method.block_events
+//! [`Block::events_hash`]: ../struct.Block.html#method.events_hash
+
+use exonum_merkledb::{Fork, ObjectHash};
+
+use super::Schema;
+use crate::crypto::Hash;
+use crate::helpers::Height;
+use crate::messages::HexStringRepresentation;
+use crate::proto;
+
+/// Event emitted by a transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::TransactionEvent", crate = "crate")]
+pub struct TransactionEvent {
+    /// Hash of the transaction which has emitted the event.
+    pub tx_hash: Hash,
+    /// Identifier of the service which has emitted the event. For the events emitted
+    /// by the called services, this is the identifier of the called service.
+    pub service_id: u16,
+    /// Topic of the event defined by the service, e.g., `transfer`.
+    pub topic: String,
+    /// Payload of the event encoded by the service.
+    #[serde(with = "HexStringRepresentation")]
+    pub payload: Vec<u8>,
+}
+
+/// Returns the root hash of the events emitted in the block at the given height,
+/// or `None` if there are no events.
+pub(crate) fn events_hash(fork: &Fork, height: Height) -> Option<Hash> {
+    let events = Schema::new(fork).block_events(height);
+    if events.is_empty() {
+        None
+    } else {
+        Some(events.object_hash())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_merkledb::{Database, TemporaryDB};
+
+    use super::*;
+    use crate::blockchain::TransactionContext;
+    use crate::crypto::{gen_keypair, hash};
+
+    #[test]
+    fn events_are_appended_to_block_list() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        assert_eq!(events_hash(&fork, Height(0)), None);
+
+        let tx_hash = hash(&[1]);
+        let context = TransactionContext::from_parts(&fork, 5, "events", tx_hash, gen_keypair().0);
+        context.emit_event("created", vec![1_u8, 2]);
+        context.emit_event("transferred", &b"bob"[..]);
+
+        let events = Schema::new(&fork).block_events(Height(0));
+        assert_eq!(
+            events.iter().collect::<Vec<_>>(),
+            vec![
+                TransactionEvent {
+                    tx_hash,
+                    service_id: 5,
+                    topic: "created".to_owned(),
+                    payload: vec![1, 2],
+                },
+                TransactionEvent {
+                    tx_hash,
+                    service_id: 5,
+                    topic: "transferred".to_owned(),
+                    payload: b"bob".to_vec(),
+                },
+            ]
+        );
+        assert_eq!(events_hash(&fork, Height(0)), Some(events.object_hash()));
+        assert!(Schema::new(&fork).block_events(Height(1)).is_empty());
+    }
+
+    #[test]
+    fn event_payload_is_serialized_as_hex() {
+        let event = TransactionEvent {
+            tx_hash: Hash::zero(),
+            service_id: 1,
+            topic: "created".to_owned(),
+            payload: vec![0xab, 0x01],
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["payload"], "ab01");
+        let restored: TransactionEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, event);
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cache of the transaction executions within a height.
+//!
+//! If no block is accepted in a round, the proposals of the following rounds usually
+//! contain the same transactions, which are executed again from the same state.
+//! The changes made by each transaction are recorded together with the remaining block
+//! budget, so that the following executions of the transaction from the same state
+//! apply the changes instead of running the transaction.
+//!
+//! The state preceding a transaction is identified by a hash chain over the hash
+//! of the latest committed block, the height, the size of the persistent pool,
+//! the hash of the changes made before the transactions and the hashes of the preceding
+//! transactions of the block. The changes made before the transactions include the block
+//! time and the changes of the services in `before_transactions`, which may differ
+//! between the proposals of the same height. The pool is included because it may grow
+//! within a height when the node flushes its transactions cache into the storage.
+//! The cache is cleared when the blockchain moves to the next height.
+
+use exonum_merkledb::{Changeset, Fork};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use super::limits::BlockBudget;
+use crate::crypto::Hash;
+use crate::helpers::{CompositeKey, Height};
+use crate::messages::{RawTransaction, Signed};
+
+/// Maximum number of the transactions cached within a height.
+const MAX_CACHED_TRANSACTIONS: usize = 10_000;
+
+/// Changes made by the transaction and the block budget remaining after it.
+#[derive(Debug)]
+struct CachedExecution {
+    changes: Changeset,
+    budget: BlockBudget,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    initial_state: Option<Hash>,
+    executions: HashMap<(Hash, Hash), CachedExecution>,
+}
+
+/// Transaction executions cached within the current height.
+#[derive(Debug, Default)]
+pub(super) struct ExecutionCache {
+    entries: Mutex<CacheEntries>,
+}
+
+impl ExecutionCache {
+    /// Returns the identifier of the state preceding the transactions of the block
+    /// at the given height, where `block_changes` are the changes made before
+    /// the transactions. The executions cached for other states are discarded.
+    pub(super) fn start_block(
+        &self,
+        last_hash: &Hash,
+        height: Height,
+        pool_len: u64,
+        block_changes: &Changeset,
+    ) -> Hash {
+        let state = CompositeKey::new()
+            .hash(last_hash)
+            .u64(height.0)
+            .u64(pool_len)
+            .hash(&block_changes.changes_hash())
+            .into_hash();
+
+        let mut entries = self.entries.lock().expect("Expected mutex lock");
+        if entries.initial_state != Some(state) {
+            entries.initial_state = Some(state);
+            entries.executions.clear();
+        }
+        state
+    }
+
+    /// Returns the identifier of the state following the execution of the transaction.
+    pub(super) fn next_state(state: &Hash, tx_hash: &Hash) -> Hash {
+        CompositeKey::new().hash(state).hash(tx_hash).into_hash()
+    }
+
+    /// Applies the changes of the transaction executed from the given state to the fork.
+    /// Returns `false` without changing the fork if the execution is not cached.
+    pub(super) fn replay(
+        &self,
+        state: &Hash,
+        tx_hash: &Hash,
+        budget: &mut BlockBudget,
+        fork: &mut Fork,
+        tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> bool {
+        let entries = self.entries.lock().expect("Expected mutex lock");
+        let cached = match entries.executions.get(&(*state, *tx_hash)) {
+            Some(cached) => cached,
+            None => return false,
+        };
+        fork.apply(&cached.changes);
+        *budget = cached.budget.clone();
+        tx_cache.remove(tx_hash);
+        true
+    }
+
+    /// Caches the changes of the transaction executed from the given state.
+    pub(super) fn insert(
+        &self,
+        state: &Hash,
+        tx_hash: &Hash,
+        changes: Changeset,
+        budget: &BlockBudget,
+    ) {
+        let mut entries = self.entries.lock().expect("Expected mutex lock");
+        if entries.executions.len() >= MAX_CACHED_TRANSACTIONS {
+            return;
+        }
+        let execution = CachedExecution {
+            changes,
+            budget: budget.clone(),
+        };
+        entries.executions.insert((*state, *tx_hash), execution);
+    }
+
+    /// Returns the number of the cached transaction executions.
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .expect("Expected mutex lock")
+            .executions
+            .len()
+    }
+}
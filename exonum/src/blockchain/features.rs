@@ -0,0 +1,306 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Optional behaviors of services activated through the configuration.
+//!
+//! A service declares the names of its optional behaviors by returning them from
+//! [`Service::features`]. A feature is scheduled by listing it together with its
+//! activation height in the `features` object of the service section of the
+//! configuration, for example, `{ "features": { "reduced_fees": 1000 } }`. The activation
+//! can also be given as a [`Schedule`] relative to the height since which the configuration
+//! is actual, for example, `{ "reduced_fees": { "after_duration": 86400000 } }`.
+//! Transactions check the feature with [`TransactionContext::is_active`]; the feature
+//! is active in the blocks starting from its activation height. A feature scheduled after
+//! a duration is activated by the first block with a sufficient consensus time, so its
+//! activation height is known only once the block is committed. Since the configuration
+//! is agreed upon by the validators, all nodes switch to the new behavior in the same
+//! block, and services do not need to hard-code the activation heights.
+//!
+//! The activations are checked with [`check_activations`] when a new configuration is
+//! proposed: a feature cannot be activated before the configuration becomes actual, and
+//! a feature that is active by that time cannot be rescheduled or removed. A feature
+//! scheduled after a duration that has not passed by the proposal may still be changed.
+//! The genesis configuration may only schedule the features declared by the services.
+//!
+//! [`Service::features`]: ../trait.Service.html#method.features
+//! [`Schedule`]: ../../helpers/enum.Schedule.html
+//! [`TransactionContext::is_active`]: ../struct.TransactionContext.html#method.is_active
+//! [`check_activations`]: fn.check_activations.html
+
+use exonum_merkledb::IndexAccess;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{Schema, Service, StoredConfiguration};
+use crate::helpers::{Height, Schedule};
+
+/// Field of the service configuration listing the scheduled features.
+pub const FEATURES_FIELD: &str = "features";
+
+/// Returns the features scheduled in the configuration section of the service.
+pub fn scheduled_features(
+    config: &StoredConfiguration,
+    service_name: &str,
+) -> Result<BTreeMap<String, Schedule>, failure::Error> {
+    let features = match config
+        .services
+        .get(service_name)
+        .and_then(|section| section.get(FEATURES_FIELD))
+    {
+        Some(features) => features.clone(),
+        None => return Ok(BTreeMap::new()),
+    };
+    serde_json::from_value(features).map_err(|e| {
+        format_err!(
+            "Invalid features in the configuration of service <{}>: {}",
+            service_name,
+            e
+        )
+    })
+}
+
+/// Returns the features scheduled in the configuration section of the service,
+/// together with their activation heights if the features are activated by the block
+/// at `height`. Relative schedules are counted from the height since which
+/// the configuration is actual.
+pub fn feature_activations<T: IndexAccess>(
+    config: &StoredConfiguration,
+    service_name: &str,
+    access: T,
+    height: Height,
+) -> Result<BTreeMap<String, Option<Height>>, failure::Error> {
+    let schema = Schema::new(access);
+    Ok(scheduled_features(config, service_name)?
+        .into_iter()
+        .map(|(name, schedule)| {
+            let activation = schedule.due_height(config.actual_from, height, &schema);
+            (name, activation)
+        })
+        .collect())
+}
+
+/// Checks the feature activations of the candidate configuration against
+/// the configuration it replaces.
+pub fn check_activations<T: IndexAccess>(
+    actual: &StoredConfiguration,
+    candidate: &StoredConfiguration,
+    access: T,
+) -> Result<(), failure::Error> {
+    let actual_from = candidate.actual_from;
+    // The features activated by the blocks preceding the candidate. The blocks after
+    // the latest one are not known, so the durations are compared with the known blocks.
+    let active_by = actual_from.previous();
+    let service_names = actual
+        .services
+        .keys()
+        .chain(candidate.services.keys())
+        .collect::<BTreeSet<_>>();
+
+    for service_name in service_names {
+        let active = feature_activations(actual, service_name, access.clone(), active_by)?;
+        let scheduled = feature_activations(candidate, service_name, access.clone(), active_by)?;
+        for (feature, height) in active.iter().filter_map(|(f, h)| h.map(|h| (f, h))) {
+            ensure!(
+                scheduled.get(feature) == Some(&Some(height)),
+                "Feature `{}` of service <{}> is active since height {} and cannot be \
+                 rescheduled or removed",
+                feature,
+                service_name,
+                height
+            );
+        }
+        for (feature, height) in scheduled.iter().filter_map(|(f, h)| h.map(|h| (f, h))) {
+            ensure!(
+                active.get(feature) == Some(&Some(height)),
+                "Feature `{}` of service <{}> cannot be activated at height {} before \
+                 the configuration becomes actual at height {}",
+                feature,
+                service_name,
+                height,
+                actual_from
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the configuration schedules only the features declared by the service.
+pub(crate) fn check_declared(
+    config: &StoredConfiguration,
+    service: &dyn Service,
+) -> Result<(), failure::Error> {
+    let declared = service.features();
+    for feature in scheduled_features(config, service.service_name())?.keys() {
+        ensure!(
+            declared.contains(&feature.as_str()),
+            "Service <{}> does not declare feature `{}`",
+            service.service_name(),
+            feature
+        );
+    }
+    Ok(())
+}
+
+/// Checks whether the feature of the service is active in the block at the given height.
+pub fn is_active<T: IndexAccess>(
+    access: T,
+    service_name: &str,
+    feature: &str,
+    height: Height,
+) -> bool {
+    Schema::new(access.clone())
+        .configuration_at(height)
+        .and_then(|config| feature_activations(&config, service_name, access, height).ok())
+        .and_then(|features| features.get(feature).cloned())
+        .map_or(false, |activation| activation.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use exonum_merkledb::{Database, Fork, TemporaryDB};
+
+    use super::*;
+    use crate::blockchain::ConsensusConfig;
+    use crate::crypto::Hash;
+
+    fn config(actual_from: u64, features: serde_json::Value) -> StoredConfiguration {
+        let mut services = BTreeMap::new();
+        services.insert("service".to_owned(), json!({ "features": features }));
+        StoredConfiguration {
+            previous_cfg_hash: Hash::zero(),
+            actual_from: Height(actual_from),
+            validator_keys: vec![],
+            consensus: ConsensusConfig::default(),
+            services,
+        }
+    }
+
+    fn commit_block(fork: &Fork, time: Option<i64>) {
+        let schema = Schema::new(fork);
+        if let Some(time) = time {
+            let height = schema.block_hashes_by_height().len();
+            schema.block_times().put(&height, Utc.timestamp(time, 0));
+        }
+        schema.block_hashes_by_height().push(Hash::zero());
+    }
+
+    #[test]
+    fn parse_activations() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let valid = config(0, json!({ "a": 5, "b": 10 }));
+        let features = feature_activations(&valid, "service", &fork, Height(7)).unwrap();
+        assert_eq!(features["a"], Some(Height(5)));
+        assert_eq!(features["b"], None);
+        let features = feature_activations(&valid, "other", &fork, Height(7)).unwrap();
+        assert!(features.is_empty());
+
+        let invalid = config(0, json!({ "a": "soon" }));
+        assert!(feature_activations(&invalid, "service", &fork, Height(7)).is_err());
+    }
+
+    #[test]
+    fn relative_activations_are_resolved() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        // The blocks following the genesis one have the times 10, 11, 12, 12, ...
+        for height in 0..15_i64 {
+            let time = if height < 2 {
+                None
+            } else {
+                Some(8 + height.min(4))
+            };
+            commit_block(&fork, time);
+        }
+
+        let valid = config(
+            2,
+            json!({
+                "a": { "at_height": 12 },
+                "b": { "after_blocks": 5 },
+                "c": { "after_duration": 1_000 },
+                "d": { "after_duration": 2_000 }
+            }),
+        );
+        let features = feature_activations(&valid, "service", &fork, Height(20)).unwrap();
+        assert_eq!(features["a"], Some(Height(12)));
+        assert_eq!(features["b"], Some(Height(7)));
+        // The durations are compared with the consensus time of the blocks.
+        assert_eq!(features["c"], Some(Height(3)));
+        assert_eq!(features["d"], Some(Height(4)));
+        let features = feature_activations(&valid, "service", &fork, Height(3)).unwrap();
+        assert_eq!(features["d"], None);
+    }
+
+    #[test]
+    fn activations_in_future_are_allowed() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let actual = config(0, json!({ "a": 5, "b": 20 }));
+        // `b` is not active yet, so it can be rescheduled.
+        let candidate = config(10, json!({ "a": 5, "b": 30, "c": 10 }));
+        check_activations(&actual, &candidate, &fork).unwrap();
+        let candidate = config(10, json!({ "a": 5 }));
+        check_activations(&actual, &candidate, &fork).unwrap();
+    }
+
+    #[test]
+    fn active_features_cannot_be_changed() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let actual = config(0, json!({ "a": 5 }));
+        let removed = config(10, json!({}));
+        assert!(check_activations(&actual, &removed, &fork).is_err());
+        let rescheduled = config(10, json!({ "a": 15 }));
+        assert!(check_activations(&actual, &rescheduled, &fork).is_err());
+    }
+
+    #[test]
+    fn features_scheduled_after_duration_can_be_changed_until_active() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        for time in &[None, None, Some(10), Some(11)] {
+            commit_block(&fork, *time);
+        }
+        let actual = config(2, json!({ "a": { "after_duration": 2_000 } }));
+        let removed = config(10, json!({}));
+        check_activations(&actual, &removed, &fork).unwrap();
+
+        commit_block(&fork, Some(12));
+        assert!(check_activations(&actual, &removed, &fork).is_err());
+        let kept = config(10, json!({ "a": 4 }));
+        check_activations(&actual, &kept, &fork).unwrap();
+    }
+
+    #[test]
+    fn activation_in_past_is_rejected() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let actual = config(0, json!({}));
+        let candidate = config(10, json!({ "a": 9 }));
+        assert!(check_activations(&actual, &candidate, &fork).is_err());
+    }
+
+    #[test]
+    fn feature_is_active_from_activation_height() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        Schema::new(&fork).commit_configuration(config(0, json!({ "a": 5 })));
+        assert!(!is_active(&fork, "service", "a", Height(4)));
+        assert!(is_active(&fork, "service", "a", Height(5)));
+        assert!(!is_active(&fork, "service", "b", Height(5)));
+        assert!(!is_active(&fork, "other", "a", Height(5)));
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transaction fees.
+//!
+//! The fees are charged from the transaction authors before the execution and are
+//! kept even if the execution fails, so that sending invalid transactions still costs
+//! their authors. The fees collected in a block are credited to its proposer.
+
+use exonum_merkledb::Fork;
+
+use crate::crypto::PublicKey;
+
+/// Charges the transaction fees and rewards the block proposers.
+///
+/// The handler is set with [`Blockchain::set_fee_handler`] and keeps the balances in
+/// the storage, usually in the schema of a token service. The fee of a transaction
+/// is returned by [`Transaction::fee`]; free transactions are executed without
+/// invoking the handler. If the author cannot pay the fee, the transaction is not
+/// executed and is committed with the `InsufficientFee` status. After the transactions
+/// of a block are executed, the collected fees are credited to the service key of
+/// the validator that has proposed the block.
+///
+/// The handler must be the same on all nodes of the network.
+///
+/// [`Blockchain::set_fee_handler`]: struct.Blockchain.html#method.set_fee_handler
+/// [`Transaction::fee`]: trait.Transaction.html#method.fee
+pub trait FeeHandler: Send + Sync + 'static {
+    /// Deducts the fee from the balance of the transaction author. Returns an error
+    /// if the author cannot pay the fee; the changes made by the handler are then
+    /// discarded.
+    fn charge(&self, fork: &Fork, author: &PublicKey, fee: u64) -> Result<(), failure::Error>;
+
+    /// Credits the fees collected in a block to the block proposer.
+    fn reward(&self, fork: &Fork, proposer: &PublicKey, amount: u64);
+}
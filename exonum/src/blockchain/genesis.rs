@@ -12,7 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::config::{ConsensusConfig, ValidatorKeys};
+use serde_json::Value;
+
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::Path};
+
+use super::config::{ConsensusConfig, StoredConfiguration, ValidatorKeys};
+use crate::crypto::Hash;
+use crate::helpers::Height;
 
 /// The initial configuration which is committed into the genesis block.
 ///
@@ -27,6 +33,18 @@ pub struct GenesisConfig {
     pub consensus: ConsensusConfig,
     /// List of public keys of validators.
     pub validator_keys: Vec<ValidatorKeys>,
+    /// Identifier of the network, which is covered by the signatures of the transactions
+    /// and consensus messages. Nodes reject messages signed for other networks, so
+    /// the transactions signed for a test network cannot be replayed on the production
+    /// one. The identifier `0` is not stored in the messages and is omitted from
+    /// the serialized configuration.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub network_id: u32,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_zero(value: &u32) -> bool {
+    *value == 0
 }
 
 impl GenesisConfig {
@@ -44,6 +62,69 @@ impl GenesisConfig {
         Self {
             consensus,
             validator_keys: validator_keys.collect(),
+            network_id: 0,
         }
     }
+
+    /// Sets the identifier of the network.
+    pub fn with_network_id(mut self, network_id: u32) -> Self {
+        self.network_id = network_id;
+        self
+    }
+
+    /// Checks the configuration for logical correctness in the same way as
+    /// the configurations read from the storage are checked.
+    pub fn validate(&self) -> Result<(), failure::Error> {
+        let config = StoredConfiguration {
+            previous_cfg_hash: Hash::zero(),
+            actual_from: Height::zero(),
+            validator_keys: self.validator_keys.clone(),
+            consensus: self.consensus.clone(),
+            services: BTreeMap::new(),
+        };
+        StoredConfiguration::try_deserialize(&config.try_serialize()?)?;
+        Ok(())
+    }
+}
+
+/// Pre-populated blockchain state which is imported before the genesis block is created.
+///
+/// The dump consists of per-service index dumps together with the expected root hashes
+/// of the service tables. The state is applied to the storage before the genesis
+/// block is created, so block 0 already commits to the imported state. Each dump is
+/// verified against its expected roots; if the roots do not match, the genesis block
+/// is not created.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct GenesisState {
+    /// Service state dumps keyed by the service name.
+    #[serde(default)]
+    pub services: BTreeMap<String, ServiceStateDump>,
+}
+
+/// State dump of a single service.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ServiceStateDump {
+    /// Service-specific representation of the index contents. It is passed as is
+    /// to the `Service::import_state` method.
+    pub data: Value,
+    /// Expected root hashes of the service tables, in the same order as they are
+    /// returned by the `Service::state_hash` method.
+    pub state_hash: Vec<Hash>,
+}
+
+impl GenesisState {
+    /// Loads the state dump from the JSON file at the given path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, failure::Error> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| format_err!("Unable to open genesis state {:?}: {}", path, e))?;
+        let state = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format_err!("Unable to parse genesis state {:?}: {}", path, e))?;
+        Ok(state)
+    }
+
+    /// Returns `true` if the dump does not contain any service state.
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
 }
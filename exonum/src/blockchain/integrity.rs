@@ -0,0 +1,368 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Integrity check of the committed blocks.
+//!
+//! The check verifies that the latest blocks are consistent with the indexes maintained
+//! by the core: the blocks are linked by their hashes, the transactions of each block
+//! match its `tx_hash`, and every committed transaction is stored together with its
+//! location and execution result. The state hash can be verified for the last block
+//! only, since the state at earlier heights is not retained. For this block, the root
+//! hashes of the core and service tables are recomputed and compared with the ones
+//! aggregated into the `state_hash`.
+
+use exonum_merkledb::{ObjectHash, Snapshot};
+
+use super::schema::CHAIN_FORKS;
+use super::{deployment, retention, Blockchain, Schema, TxLocation};
+use crate::crypto::{self, CryptoHash, Hash};
+use crate::helpers::Height;
+
+/// Inconsistency detected by the integrity check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityProblem {
+    /// Height of the inconsistent block.
+    pub height: Height,
+    /// Description of the problem.
+    pub description: String,
+}
+
+/// Result of the integrity check.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IntegrityReport {
+    /// Number of checked blocks.
+    pub checked_blocks: u64,
+    /// Detected problems ordered by the block height.
+    pub problems: Vec<IntegrityProblem>,
+}
+
+impl IntegrityReport {
+    /// Returns `true` if no problems were detected.
+    pub fn is_consistent(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Returns the lowest height of the inconsistent blocks.
+    pub fn first_corrupt_height(&self) -> Option<Height> {
+        self.problems.first().map(|problem| problem.height)
+    }
+
+    /// Returns inclusive ranges of the heights of the inconsistent blocks.
+    pub fn corrupt_ranges(&self) -> Vec<(Height, Height)> {
+        let mut ranges: Vec<(Height, Height)> = Vec::new();
+        for problem in &self.problems {
+            match ranges.last_mut() {
+                Some((_, end)) if problem.height <= end.next() => *end = problem.height,
+                _ => ranges.push((problem.height, problem.height)),
+            }
+        }
+        ranges
+    }
+
+    /// Returns a suggestion on restoring the storage if problems were detected.
+    pub fn repair_hint(&self) -> Option<String> {
+        self.first_corrupt_height().map(|height| {
+            format!(
+                "The storage is corrupted starting from height {}. Re-synchronize the node \
+                 from this height: restore a backup of the database made before it, or \
+                 import the chain exported from a healthy node with `import-chain` into \
+                 an empty database, or clear the database and sync from peers",
+                height
+            )
+        })
+    }
+}
+
+/// Checks the consistency of the last `depth` committed blocks. See the module
+/// documentation for the list of checks.
+pub fn check_integrity(blockchain: &Blockchain, depth: u64) -> IntegrityReport {
+    let snapshot = blockchain.snapshot();
+    let schema = Schema::new(snapshot.as_ref());
+    let blocks_count = schema.block_hashes_by_height().len();
+    let checked_blocks = depth.min(blocks_count);
+    let mut report = IntegrityReport {
+        checked_blocks,
+        problems: Vec::new(),
+    };
+    if checked_blocks == 0 {
+        return report;
+    }
+
+    let last_height = Height(blocks_count - 1);
+    for height in blocks_count - checked_blocks..blocks_count {
+        let height = Height(height);
+        let mut report_problem = |description: String| {
+            report.problems.push(IntegrityProblem {
+                height,
+                description,
+            })
+        };
+        check_block(&schema, height, &mut report_problem);
+        if height == last_height {
+            check_state(blockchain, snapshot.as_ref(), height, &mut report_problem);
+        }
+    }
+    report
+}
+
+fn check_block<F>(schema: &Schema<&dyn Snapshot>, height: Height, report: &mut F)
+where
+    F: FnMut(String),
+{
+    let block_hash = match schema.block_hash_by_height(height) {
+        Some(hash) => hash,
+        None => return report("The block hash is missing".to_owned()),
+    };
+    let block = match schema.blocks().get(&block_hash) {
+        Some(block) => block,
+        None => return report(format!("The block {:?} is missing", block_hash)),
+    };
+    if block.hash() != block_hash {
+        report(format!(
+            "The block does not match its hash {:?}",
+            block_hash
+        ));
+    }
+    if block.height() != height {
+        report(format!("The block is stored at height {}", block.height()));
+    }
+    if height > Height::zero() {
+        let prev_hash = schema.block_hash_by_height(height.previous());
+        if prev_hash.as_ref() != Some(block.prev_hash()) {
+            report(format!(
+                "The previous block hash {:?} does not match the stored block {:?}",
+                block.prev_hash(),
+                prev_hash
+            ));
+        }
+    }
+
+    let transactions = schema.block_transactions(height);
+    if transactions.len() != u64::from(block.tx_count()) {
+        report(format!(
+            "The block contains {} transactions, {} are stored",
+            block.tx_count(),
+            transactions.len()
+        ));
+    }
+    if transactions.object_hash() != *block.tx_hash() {
+        report("The stored transactions do not match the block tx_hash".to_owned());
+    }
+    // The transaction history of the block may have been deleted by the retention policy.
+    let history_pruned = height < retention::history_pruned_to(schema);
+    for (position, tx_hash) in transactions.iter().enumerate() {
+        if !history_pruned
+            && !schema.transactions().contains(&tx_hash)
+            && !schema.archived_transactions().contains(&tx_hash)
+        {
+            report(format!("The transaction {:?} is missing", tx_hash));
+        }
+        let location = TxLocation::new(height, position as u64);
+        if schema.transactions_locations().get(&tx_hash) != Some(location) {
+            report(format!(
+                "The location of the transaction {:?} does not match the block",
+                tx_hash
+            ));
+        }
+        if !schema.transaction_results().contains(&tx_hash) {
+            report(format!(
+                "The execution result of the transaction {:?} is missing",
+                tx_hash
+            ));
+        }
+    }
+    let results_hash = block.tx_results_hash().filter(|_| !history_pruned);
+    if let Some(results_hash) = results_hash {
+        if schema.block_transaction_results(height).object_hash() != results_hash {
+            report("The stored execution results do not match the block header".to_owned());
+        }
+    }
+    if let Some(events_hash) = block.events_hash().filter(|_| !history_pruned) {
+        if schema.block_events(height).object_hash() != events_hash {
+            report("The stored events do not match the block header".to_owned());
+        }
+    }
+}
+
+fn check_state<F>(blockchain: &Blockchain, snapshot: &dyn Snapshot, height: Height, report: &mut F)
+where
+    F: FnMut(String),
+{
+    let schema = Schema::new(snapshot);
+    let block = match schema
+        .block_hash_by_height(height)
+        .and_then(|hash| schema.blocks().get(&hash))
+    {
+        Some(block) => block,
+        // Reported by `check_block`.
+        None => return,
+    };
+
+    let aggregator = schema.state_hash_aggregator();
+    let mut check_table = |owner: &str, name: &str, hash: Hash| {
+        if aggregator.get(&Blockchain::state_table_key(name)) != Some(hash) {
+            report(format!(
+                "The root hash of table {} of {} does not match the block state_hash",
+                name, owner
+            ));
+        }
+    };
+    let mut core_tables = schema.core_state_tables();
+    // The chain identifier assigned by a fork at the latest height enters the state
+    // with the next block.
+    let forks: Vec<_> = schema.chain_forks().iter().collect();
+    if let Some((fork_height, _)) = forks.last() {
+        if *fork_height == height.0 {
+            core_tables.retain(|(name, _)| name != CHAIN_FORKS);
+            if let Some((_, chain_id)) = forks.iter().rev().nth(1) {
+                core_tables.push((CHAIN_FORKS.to_owned(), crypto::hash(chain_id.as_bytes())));
+            }
+        }
+    }
+    for (name, hash) in core_tables {
+        check_table("the core", &name, hash);
+    }
+    let services = blockchain
+        .service_map()
+        .values()
+        .filter(|service| deployment::is_deployed(snapshot, service.as_ref()));
+    for service in services {
+        let owner = format!("service {}", service.service_name());
+        for (name, hash) in service.state_hash_tables(snapshot) {
+            check_table(&owner, &name, hash);
+        }
+    }
+
+    if aggregator.object_hash() != *block.state_hash() {
+        report("The aggregated state does not match the block state_hash".to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::blockchain::{tests::create_initialized_blockchain, TransactionResult};
+    use crate::helpers::ValidatorId;
+    use crate::sandbox::timestamping::{TimestampingService, TimestampingTxGenerator};
+    use exonum_merkledb::Fork;
+
+    /// Creates a blockchain with `blocks` blocks containing 2 transactions each.
+    fn create_blockchain(blocks: u64) -> Blockchain {
+        let mut blockchain =
+            create_initialized_blockchain(vec![Box::new(TimestampingService::new())]);
+
+        let mut txs = TimestampingTxGenerator::new(16);
+        for height in 1..=blocks {
+            let mut tx_cache = BTreeMap::new();
+            for tx in txs.by_ref().take(2) {
+                tx_cache.insert(tx.hash(), tx);
+            }
+            let tx_hashes: Vec<Hash> = tx_cache.keys().cloned().collect();
+            let (block_hash, patch) = blockchain.create_patch(
+                ValidatorId::zero(),
+                Height(height),
+                &tx_hashes,
+                &mut tx_cache,
+            );
+            blockchain
+                .commit(patch, block_hash, Vec::new().into_iter(), &mut tx_cache)
+                .unwrap();
+        }
+        blockchain
+    }
+
+    fn corrupt<F>(blockchain: &mut Blockchain, corruption: F)
+    where
+        F: FnOnce(&Schema<&Fork>),
+    {
+        let fork = blockchain.fork();
+        corruption(&Schema::new(&fork));
+        blockchain.merge(fork.into_patch()).unwrap();
+    }
+
+    fn tx_hash(blockchain: &Blockchain, height: u64) -> Hash {
+        let snapshot = blockchain.snapshot();
+        Schema::new(&snapshot)
+            .block_transactions(Height(height))
+            .get(0)
+            .unwrap()
+    }
+
+    #[test]
+    fn consistent_storage() {
+        let blockchain = create_blockchain(5);
+        let report = check_integrity(&blockchain, 10);
+        assert_eq!(report.checked_blocks, 6);
+        assert!(report.is_consistent(), "{:?}", report.problems);
+        assert_eq!(report.repair_hint(), None);
+    }
+
+    #[test]
+    fn corrupt_ranges_are_reported() {
+        let mut blockchain = create_blockchain(8);
+        let (missing_result, moved, foreign) = (
+            tx_hash(&blockchain, 3),
+            tx_hash(&blockchain, 4),
+            tx_hash(&blockchain, 7),
+        );
+        corrupt(&mut blockchain, |schema| {
+            schema.transaction_results().remove(&missing_result);
+            schema
+                .transactions_locations()
+                .put(&moved, TxLocation::new(Height(1), 0));
+            schema.transactions().remove(&foreign);
+        });
+
+        let report = check_integrity(&blockchain, 6);
+        assert_eq!(report.checked_blocks, 6);
+        assert_eq!(
+            report.corrupt_ranges(),
+            vec![(Height(3), Height(4)), (Height(7), Height(7))]
+        );
+        assert_eq!(report.first_corrupt_height(), Some(Height(3)));
+        assert!(report.problems[0].description.contains("execution result"));
+        assert!(report.problems[1].description.contains("location"));
+        assert!(report.repair_hint().unwrap().contains("from height 3"));
+
+        // Blocks below the checked depth are not verified.
+        let report = check_integrity(&blockchain, 2);
+        assert_eq!(report.corrupt_ranges(), vec![(Height(7), Height(7))]);
+    }
+
+    #[test]
+    fn state_of_last_block_is_verified() {
+        let mut blockchain = create_blockchain(2);
+        corrupt(&mut blockchain, |schema| {
+            let result = TransactionResult(Ok(()));
+            schema.transaction_results().put(&Hash::zero(), result);
+        });
+        let report = check_integrity(&blockchain, 1);
+        assert_eq!(report.checked_blocks, 1);
+        assert_eq!(report.corrupt_ranges(), vec![(Height(2), Height(2))]);
+        assert!(report.problems[0]
+            .description
+            .contains("table #1 of the core"));
+
+        corrupt(&mut blockchain, |schema| {
+            schema
+                .state_hash_aggregator()
+                .put(&Hash::zero(), Hash::zero());
+        });
+        let report = check_integrity(&blockchain, 1);
+        assert_eq!(report.problems.len(), 2);
+        assert!(report.problems[1].description.contains("aggregated state"));
+    }
+}
@@ -0,0 +1,899 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Chain interchange format.
+//!
+//! An exported chain contains everything required to rebuild the blockchain from scratch:
+//! blocks with their precommits, committed transactions and configurations. The format
+//! does not depend on the storage layout, so it can be used to migrate a chain to another
+//! storage engine or to a new major version of the core library.
+//!
+//! The file starts with the `EXONUM-CHAIN` magic bytes followed by the format version
+//! as a little-endian `u32`. The rest of the file is a sequence of frames, each consisting
+//! of the length of a Protobuf message as a little-endian `u32` and the message itself
+//! (see `interchange.proto`). The first frame is a `ChainHeader`; it is followed by
+//! a `ChainBlock` frame for each block starting from the genesis one.
+//!
+//! The import does not trust the exported data. The genesis block is created from
+//! the exported genesis configuration, and each following block is re-executed and
+//! compared with the exported one. Precommits are checked against the validators
+//! of the configuration actual at the block height. Precommits pruned by the retention
+//! policy may be absent for all blocks but the last one: such blocks are authenticated
+//! by the hash links of the following blocks.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use exonum_merkledb::{BinaryValue, Snapshot};
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    io::{Read, Write},
+    u32,
+};
+
+use super::{Block, Blockchain, GenesisConfig, GenesisState, Schema, StoredConfiguration};
+use crate::crypto::{self, CryptoHash, Hash};
+use crate::helpers::Height;
+use crate::messages::{Message, Precommit, ProtocolMessage, RawTransaction, Signed};
+use crate::proto;
+
+/// Version of the chain interchange format written by `export_chain`.
+pub const CHAIN_FORMAT_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 12] = b"EXONUM-CHAIN";
+
+#[derive(Debug, Clone, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::schema::interchange::ChainConfig", crate = "crate")]
+pub(super) struct ChainConfig {
+    actual_from: Height,
+    config: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::schema::interchange::ChainFork", crate = "crate")]
+pub(super) struct ChainFork {
+    height: Height,
+    chain_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::schema::interchange::ChainHeader", crate = "crate")]
+struct ChainHeader {
+    exonum_version: String,
+    height: Height,
+    configs: Vec<ChainConfig>,
+    forks: Vec<ChainFork>,
+    network_id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::schema::interchange::ChainBlock", crate = "crate")]
+pub(super) struct ChainBlock {
+    pub(super) block: Block,
+    pub(super) precommits: Vec<Vec<u8>>,
+    pub(super) transactions: Vec<Vec<u8>>,
+}
+
+/// Writes the blocks committed to the snapshot in the chain interchange format.
+/// Returns the height of the last exported block.
+pub fn export_chain<W: Write>(
+    snapshot: &dyn Snapshot,
+    mut writer: W,
+) -> Result<Height, failure::Error> {
+    let schema = Schema::new(snapshot);
+    let block_hashes = schema.block_hashes_by_height();
+    ensure!(
+        !block_hashes.is_empty(),
+        "The blockchain is not initialized"
+    );
+    let height = Height(block_hashes.len() - 1);
+
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<LittleEndian>(CHAIN_FORMAT_VERSION)?;
+    let header = ChainHeader {
+        exonum_version: env!("CARGO_PKG_VERSION").to_owned(),
+        height,
+        configs: chain_configs(&schema)?,
+        forks: chain_forks(&schema),
+        network_id: schema.network_id(),
+    };
+    write_frame(&mut writer, header)?;
+
+    for block_hash in block_hashes.iter() {
+        write_frame(&mut writer, chain_block(&schema, &block_hash)?)?;
+    }
+
+    writer.flush()?;
+    Ok(height)
+}
+
+pub(super) fn chain_configs(
+    schema: &Schema<&dyn Snapshot>,
+) -> Result<Vec<ChainConfig>, failure::Error> {
+    schema
+        .configs_actual_from()
+        .iter()
+        .map(|reference| {
+            let config = schema.configs().get(reference.cfg_hash()).ok_or_else(|| {
+                format_err!("Configuration {:?} is missing", reference.cfg_hash())
+            })?;
+            Ok(ChainConfig {
+                actual_from: reference.actual_from(),
+                config: config.into_bytes(),
+            })
+        })
+        .collect()
+}
+
+pub(super) fn chain_forks(schema: &Schema<&dyn Snapshot>) -> Vec<ChainFork> {
+    schema
+        .chain_forks()
+        .iter()
+        .map(|(height, chain_id)| ChainFork {
+            height: Height(height),
+            chain_id,
+        })
+        .collect()
+}
+
+pub(super) fn chain_block(
+    schema: &Schema<&dyn Snapshot>,
+    block_hash: &Hash,
+) -> Result<ChainBlock, failure::Error> {
+    let block = schema
+        .blocks()
+        .get(block_hash)
+        .ok_or_else(|| format_err!("Block {:?} is missing", block_hash))?;
+    let precommits = schema
+        .precommits(block_hash)
+        .iter()
+        .map(|precommit| precommit.signed_message().raw().to_vec())
+        .collect();
+    let transactions = schema
+        .block_transactions(block.height())
+        .iter()
+        .map(|tx_hash| {
+            schema
+                .transactions()
+                .get(&tx_hash)
+                .map(|tx| tx.signed_message().raw().to_vec())
+                .ok_or_else(|| {
+                    if schema.archived_transactions().contains(&tx_hash) {
+                        format_err!("Transaction {:?} is offloaded to the archive", tx_hash)
+                    } else {
+                        format_err!("Transaction {:?} is missing", tx_hash)
+                    }
+                })
+        })
+        .collect::<Result<_, failure::Error>>()?;
+
+    Ok(ChainBlock {
+        block,
+        precommits,
+        transactions,
+    })
+}
+
+/// Rebuilds the blockchain from the data in the chain interchange format, verifying
+/// every block. The blockchain must use an empty database. `genesis_state` must be
+/// the same as the one used to create the exported chain.
+///
+/// Returns the height of the last imported block.
+pub fn import_chain<R: Read>(
+    blockchain: &mut Blockchain,
+    genesis_state: GenesisState,
+    mut reader: R,
+) -> Result<Height, failure::Error> {
+    ensure!(
+        Schema::new(&blockchain.snapshot())
+            .block_hashes_by_height()
+            .is_empty(),
+        "The chain can be imported into an empty database only"
+    );
+
+    let mut magic = [0; 12];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| format_err!("The file does not contain an exported chain"))?;
+    ensure!(
+        &magic == MAGIC,
+        "The file does not contain an exported chain"
+    );
+    let version = reader.read_u32::<LittleEndian>()?;
+    ensure!(
+        version == CHAIN_FORMAT_VERSION,
+        "Unsupported chain format version {}, expected {}",
+        version,
+        CHAIN_FORMAT_VERSION
+    );
+
+    let header: ChainHeader = read_frame(&mut reader)?;
+    info!(
+        "Importing {} blocks exported by Exonum {}",
+        header.height.next(),
+        header.exonum_version
+    );
+    let genesis: ChainBlock = read_frame(&mut reader)?;
+    import_genesis(
+        blockchain,
+        genesis_state,
+        &header.configs,
+        header.network_id,
+        &genesis.block,
+    )?;
+    restore_forks(blockchain, &header.forks, Height::zero())?;
+
+    for height in 1..=header.height.0 {
+        let block: ChainBlock = read_frame(&mut reader)?;
+        let is_last = height == header.height.0;
+        import_block(blockchain, Height(height), block, is_last)?;
+        restore_forks(blockchain, &header.forks, Height(height))?;
+    }
+    ensure!(
+        reader.read(&mut [0])? == 0,
+        "Unexpected data after the last block"
+    );
+
+    // Configurations are committed by services, hence they can be compared only
+    // after all the blocks are executed.
+    let snapshot = blockchain.snapshot();
+    let imported: Vec<_> = Schema::new(&snapshot)
+        .configs_actual_from()
+        .iter()
+        .map(|reference| (reference.actual_from(), *reference.cfg_hash()))
+        .collect();
+    let exported: Vec<_> = header
+        .configs
+        .iter()
+        .map(|config| (config.actual_from, crypto::hash(&config.config)))
+        .collect();
+    ensure!(
+        imported == exported,
+        "Configurations of the imported chain differ from the exported ones"
+    );
+
+    Ok(header.height)
+}
+
+/// Rebuilds the blocks of the chain up to the given height into another blockchain
+/// and marks the rebuilt chain as forked at this height. The blockchain must use
+/// an empty database. `genesis_state` must be the same as the one used to create
+/// the source chain. Returns the identifier of the forked chain.
+///
+/// The chain identifier is a part of the core state, so the blocks committed after
+/// the fork differ from the blocks of the source chain even if they contain the same
+/// transactions. Thus a network can be restarted from the fork without the nodes which
+/// remain on the source chain interfering with the consensus. All the validators
+/// must fork the chain at the same height with the same suffix.
+pub fn fork_chain(
+    snapshot: &dyn Snapshot,
+    height: Height,
+    suffix: &str,
+    blockchain: &mut Blockchain,
+    genesis_state: GenesisState,
+) -> Result<String, failure::Error> {
+    ensure!(
+        !suffix.is_empty() && !suffix.contains('.'),
+        "The chain identifier suffix must be non-empty and must not contain dots"
+    );
+    rebuild_chain(snapshot, height, blockchain, genesis_state)?;
+
+    let fork = blockchain.fork();
+    let chain_id = Schema::new(&fork).add_chain_fork(height, suffix);
+    blockchain.merge(fork.into_patch())?;
+    Ok(chain_id)
+}
+
+/// Rebuilds the blocks of the chain up to the given height into another blockchain,
+/// dropping the later blocks. The blockchain must use an empty database.
+/// `genesis_state` must be the same as the one used to create the source chain.
+///
+/// Unlike [`fork_chain`], the rebuilt chain keeps the chain identifier, so the node
+/// using it catches up with the rest of the network again. This allows an operator
+/// to recover a stopped node from a mistake or a corrupted chain: the indexes, such
+/// as the block hashes and the transaction locations, and the state of the services
+/// are rebuilt by re-executing the preserved blocks. The transactions of the dropped
+/// blocks are not returned to the pool.
+///
+/// [`fork_chain`]: fn.fork_chain.html
+pub fn rollback_chain(
+    snapshot: &dyn Snapshot,
+    height: Height,
+    blockchain: &mut Blockchain,
+    genesis_state: GenesisState,
+) -> Result<(), failure::Error> {
+    rebuild_chain(snapshot, height, blockchain, genesis_state)?;
+    // Forks made right after the last preserved block apply to the blocks
+    // the network commits next.
+    let forks = chain_forks(&Schema::new(snapshot));
+    restore_forks(blockchain, &forks, height)
+}
+
+fn rebuild_chain(
+    snapshot: &dyn Snapshot,
+    height: Height,
+    blockchain: &mut Blockchain,
+    genesis_state: GenesisState,
+) -> Result<(), failure::Error> {
+    ensure!(
+        Schema::new(&blockchain.snapshot())
+            .block_hashes_by_height()
+            .is_empty(),
+        "The chain can be rebuilt into an empty database only"
+    );
+    let schema = Schema::new(snapshot);
+    let block_hashes = schema.block_hashes_by_height();
+    ensure!(
+        !block_hashes.is_empty(),
+        "The blockchain is not initialized"
+    );
+    let last_height = Height(block_hashes.len() - 1);
+    ensure!(
+        height <= last_height,
+        "Cannot rebuild the chain up to height {}, the last block is at height {}",
+        height,
+        last_height
+    );
+
+    let configs = chain_configs(&schema)?;
+    let forks = chain_forks(&schema);
+    let genesis = chain_block(&schema, &block_hashes.get(0).unwrap_or_default())?;
+    import_genesis(
+        blockchain,
+        genesis_state,
+        &configs,
+        schema.network_id(),
+        &genesis.block,
+    )?;
+    for block_height in 1..=height.0 {
+        restore_forks(blockchain, &forks, Height(block_height - 1))?;
+        let block_hash = block_hashes.get(block_height).unwrap_or_default();
+        let block = chain_block(&schema, &block_hash)?;
+        import_block(blockchain, Height(block_height), block, false)?;
+    }
+    Ok(())
+}
+
+fn import_genesis(
+    blockchain: &mut Blockchain,
+    genesis_state: GenesisState,
+    configs: &[ChainConfig],
+    network_id: u32,
+    exported: &Block,
+) -> Result<(), failure::Error> {
+    initialize_genesis(blockchain, genesis_state, configs, network_id)?;
+    ensure!(
+        blockchain.last_hash() == exported.hash(),
+        "The genesis block differs from the exported one, check the services \
+         and the genesis state"
+    );
+    Ok(())
+}
+
+/// Creates the genesis block from the genesis configuration of the chain.
+pub(super) fn initialize_genesis(
+    blockchain: &mut Blockchain,
+    genesis_state: GenesisState,
+    configs: &[ChainConfig],
+    network_id: u32,
+) -> Result<(), failure::Error> {
+    let genesis_config = match configs.first() {
+        Some(config) if config.actual_from == Height::zero() => {
+            StoredConfiguration::from_bytes(Cow::Borrowed(&config.config))?
+        }
+        _ => bail!("The genesis configuration is missing"),
+    };
+
+    blockchain.initialize_with_state(
+        GenesisConfig {
+            consensus: genesis_config.consensus,
+            validator_keys: genesis_config.validator_keys,
+            network_id,
+        },
+        genesis_state,
+    )?;
+    Ok(())
+}
+
+/// Restores the forks of the chain made after the block at the given height.
+pub(super) fn restore_forks(
+    blockchain: &mut Blockchain,
+    forks: &[ChainFork],
+    height: Height,
+) -> Result<(), failure::Error> {
+    for chain_fork in forks
+        .iter()
+        .filter(|chain_fork| chain_fork.height == height)
+    {
+        let fork = blockchain.fork();
+        Schema::new(&fork)
+            .chain_forks()
+            .put(&height.0, chain_fork.chain_id.clone());
+        blockchain.merge(fork.into_patch())?;
+    }
+    Ok(())
+}
+
+fn import_block(
+    blockchain: &mut Blockchain,
+    height: Height,
+    exported: ChainBlock,
+    is_last: bool,
+) -> Result<(), failure::Error> {
+    let block = exported.block;
+    ensure!(
+        block.height() == height,
+        "Expected a block at height {}, found a block at height {}",
+        height,
+        block.height()
+    );
+    let block_hash = block.hash();
+
+    let mut tx_cache = BTreeMap::new();
+    let mut tx_hashes = Vec::with_capacity(exported.transactions.len());
+    {
+        let snapshot = blockchain.snapshot();
+        let locations = Schema::new(&snapshot).transactions_locations();
+        for raw in exported.transactions {
+            let tx = parse_transaction(raw)
+                .map_err(|e| format_err!("Invalid transaction at height {}: {}", height, e))?;
+            let tx_hash = tx.hash();
+            ensure!(
+                !locations.contains(&tx_hash) && !tx_cache.contains_key(&tx_hash),
+                "Transaction {:?} at height {} has already been committed",
+                tx_hash,
+                height
+            );
+            blockchain
+                .tx_from_raw(tx.payload().clone())
+                .map_err(|e| format_err!("Invalid transaction {:?}: {}", tx_hash, e))?;
+            tx_hashes.push(tx_hash);
+            tx_cache.insert(tx_hash, tx);
+        }
+    }
+
+    let precommits = exported
+        .precommits
+        .into_iter()
+        .map(Precommit::verify_precommit)
+        .collect::<Result<Vec<_>, _>>()?;
+    if precommits.is_empty() {
+        ensure!(
+            !is_last,
+            "Precommits for the last block at height {} are missing",
+            height
+        );
+    } else {
+        let config = Schema::new(&blockchain.snapshot()).actual_configuration();
+        verify_precommits(&config, &precommits, &block_hash, height)?;
+    }
+
+    let (executed_hash, patch) = blockchain.create_patch_with_time(
+        block.proposer_id(),
+        height,
+        block.time(),
+        &tx_hashes,
+        &mut tx_cache,
+    );
+    ensure!(
+        executed_hash == block_hash,
+        "The block at height {} differs from the exported one after execution",
+        height
+    );
+    blockchain.commit(patch, block_hash, precommits.into_iter(), &mut tx_cache)?;
+    Ok(())
+}
+
+pub(super) fn parse_transaction(raw: Vec<u8>) -> Result<Signed<RawTransaction>, failure::Error> {
+    let message = Message::from_raw_buffer(raw)?;
+    RawTransaction::try_from(message).map_err(|_| format_err!("The message is not a transaction"))
+}
+
+/// Checks that the precommits are signed by the validators of the given configuration,
+/// agree on the block and have the weight sufficient for a quorum.
+pub(crate) fn verify_precommits(
+    config: &StoredConfiguration,
+    precommits: &[Signed<Precommit>],
+    block_hash: &Hash,
+    height: Height,
+) -> Result<(), failure::Error> {
+    ensure!(
+        !precommits.is_empty(),
+        "The block at height {} has no precommits",
+        height
+    );
+
+    let round = precommits[0].round();
+    let mut validators = HashSet::with_capacity(precommits.len());
+    for precommit in precommits {
+        let validator = precommit.validator();
+        let consensus_key = config
+            .validator_keys
+            .get(validator.0 as usize)
+            .map(|keys| keys.consensus_key);
+        ensure!(
+            consensus_key == Some(precommit.author()),
+            "Precommit at height {} is not signed by validator {}",
+            height,
+            validator
+        );
+        ensure!(
+            validators.insert(validator),
+            "Several precommits from validator {} at height {}",
+            validator,
+            height
+        );
+        ensure!(
+            precommit.block_hash() == block_hash
+                && precommit.height() == height
+                && precommit.round() == round,
+            "Precommit from validator {} does not match the block at height {}",
+            validator,
+            height
+        );
+    }
+
+    let weight = config.weight_of(validators);
+    let majority = config.majority_weight();
+    ensure!(
+        weight >= majority,
+        "The block at height {} has precommits of weight {}, at least {} is required",
+        height,
+        weight,
+        majority
+    );
+    Ok(())
+}
+
+fn write_frame<W: Write, T: BinaryValue>(writer: &mut W, value: T) -> Result<(), failure::Error> {
+    let bytes = value.into_bytes();
+    ensure!(
+        bytes.len() <= u32::MAX as usize,
+        "Frame of {} bytes is too large",
+        bytes.len()
+    );
+    writer.write_u32::<LittleEndian>(bytes.len() as u32)?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+fn read_frame<R: Read, T: BinaryValue>(reader: &mut R) -> Result<T, failure::Error> {
+    let len = reader
+        .read_u32::<LittleEndian>()
+        .map_err(|_| format_err!("Unexpected end of the exported chain"))?;
+    // The buffer grows with the data actually read, so a corrupted length cannot
+    // cause a huge allocation.
+    let mut bytes = Vec::new();
+    reader.take(u64::from(len)).read_to_end(&mut bytes)?;
+    ensure!(
+        bytes.len() == len as usize,
+        "Unexpected end of the exported chain"
+    );
+    T::from_bytes(Cow::Owned(bytes))
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::blockchain::{
+        check_integrity, ExecutionResult, Service, Transaction, TransactionContext, TransactionSet,
+        ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, PublicKey, SecretKey};
+    use crate::helpers::{Round, ValidatorId};
+    use exonum_merkledb::{ObjectHash, ProofListIndex};
+
+    const SERVICE_ID: u16 = 5;
+    const VALUES: &str = "interchange.values";
+
+    #[derive(Serialize, Deserialize, ProtobufConvert, Debug, Clone)]
+    #[exonum(pb = "proto::schema::tests::TestServiceTx", crate = "crate")]
+    struct Append {
+        value: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, TransactionSet, Debug)]
+    #[exonum(crate = "crate")]
+    enum ValuesTransactions {
+        Append(Append),
+    }
+
+    impl Transaction for Append {
+        fn execute(&self, context: TransactionContext) -> ExecutionResult {
+            ProofListIndex::new(VALUES, context.fork()).push(self.value);
+            Ok(())
+        }
+    }
+
+    struct ValuesService;
+
+    impl Service for ValuesService {
+        fn service_id(&self) -> u16 {
+            SERVICE_ID
+        }
+
+        fn service_name(&self) -> &str {
+            "interchange"
+        }
+
+        fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+            let values: ProofListIndex<_, u64> = ProofListIndex::new(VALUES, snapshot);
+            vec![(VALUES.to_owned(), values.object_hash())]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(ValuesTransactions::tx_from_raw(raw)?.into())
+        }
+    }
+
+    pub(in crate::blockchain) fn create_blockchain() -> Blockchain {
+        crate::blockchain::tests::create_blockchain(vec![Box::new(ValuesService)])
+    }
+
+    /// Creates a chain with a block containing a transaction at each of the given heights.
+    pub(in crate::blockchain) fn create_chain(
+        blocks: u64,
+    ) -> (Blockchain, Vec<Signed<RawTransaction>>) {
+        let (consensus_key, consensus_secret_key) = gen_keypair();
+        let mut blockchain = create_blockchain();
+        let validator_keys = ValidatorKeys::new(consensus_key, blockchain.service_keypair.0);
+        blockchain
+            .initialize(GenesisConfig::new(vec![validator_keys].into_iter()))
+            .unwrap();
+
+        let (author, secret_key) = gen_keypair();
+        let mut txs = Vec::new();
+        for height in 1..=blocks {
+            let tx = Message::sign_transaction(
+                Append { value: height },
+                SERVICE_ID,
+                author,
+                &secret_key,
+            );
+            commit_block(
+                &mut blockchain,
+                Height(height),
+                tx.clone(),
+                (consensus_key, &consensus_secret_key),
+            );
+            txs.push(tx);
+        }
+        (blockchain, txs)
+    }
+
+    fn commit_block(
+        blockchain: &mut Blockchain,
+        height: Height,
+        tx: Signed<RawTransaction>,
+        validator: (PublicKey, &SecretKey),
+    ) {
+        let tx_hash = tx.hash();
+        let mut tx_cache = BTreeMap::new();
+        tx_cache.insert(tx_hash, tx);
+        let (block_hash, patch) =
+            blockchain.create_patch(ValidatorId::zero(), height, &[tx_hash], &mut tx_cache);
+        let precommit = Message::concrete(
+            Precommit::new(
+                ValidatorId::zero(),
+                height,
+                Round::first(),
+                &Hash::zero(),
+                &block_hash,
+                Utc::now(),
+            ),
+            validator.0,
+            validator.1,
+        );
+        blockchain
+            .commit(
+                patch,
+                block_hash,
+                vec![precommit].into_iter(),
+                &mut tx_cache,
+            )
+            .unwrap();
+    }
+
+    fn export(blockchain: &Blockchain) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        export_chain(blockchain.snapshot().as_ref(), &mut buffer).unwrap();
+        buffer
+    }
+
+    fn import(buffer: &[u8]) -> Result<Blockchain, failure::Error> {
+        let mut blockchain = create_blockchain();
+        import_chain(&mut blockchain, GenesisState::default(), buffer)?;
+        Ok(blockchain)
+    }
+
+    #[test]
+    fn exported_chain_is_imported() {
+        let (source, txs) = create_chain(3);
+        let buffer = export(&source);
+
+        let imported = import(&buffer).unwrap();
+        assert_eq!(imported.last_hash(), source.last_hash());
+        let snapshot = imported.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.block_hashes_by_height().len(), 4);
+        for (index, tx) in txs.iter().enumerate() {
+            let location = schema.transactions_locations().get(&tx.hash()).unwrap();
+            assert_eq!(location.block_height(), Height(index as u64 + 1));
+            let block_hash = schema
+                .block_hash_by_height(location.block_height())
+                .unwrap();
+            assert_eq!(schema.precommits(&block_hash).len(), 1);
+        }
+
+        // The chain cannot be imported twice.
+        let mut imported = imported;
+        let err = import_chain(&mut imported, GenesisState::default(), &buffer[..]).unwrap_err();
+        assert!(err.to_string().contains("empty database"));
+    }
+
+    #[test]
+    fn tampered_chain_is_rejected() {
+        let (source, txs) = create_chain(2);
+        let buffer = export(&source);
+
+        // Replace the signature of the last transaction.
+        let raw = txs[1].signed_message().raw();
+        let position = buffer
+            .windows(raw.len())
+            .position(|window| window == raw)
+            .unwrap();
+        let mut tampered = buffer.clone();
+        tampered[position + raw.len() - 1] ^= 1;
+        let err = import(&tampered).unwrap_err();
+        assert!(err.to_string().contains("Invalid transaction at height 2"));
+
+        let err = import(&buffer[..buffer.len() - 1]).unwrap_err();
+        assert!(err.to_string().contains("Unexpected end"));
+
+        let mut extended = buffer.clone();
+        extended.push(0);
+        let err = import(&extended).unwrap_err();
+        assert!(err.to_string().contains("Unexpected data"));
+    }
+
+    #[test]
+    fn pruned_precommits_are_allowed_except_for_the_last_block() {
+        let (source, _) = create_chain(2);
+        let prune = |height: u64| {
+            let fork = source.fork();
+            {
+                let schema = Schema::new(&fork);
+                let block_hash = schema.block_hash_by_height(Height(height)).unwrap();
+                schema.precommits(&block_hash).clear();
+            }
+            let mut source = source.clone();
+            source.merge(fork.into_patch()).unwrap();
+        };
+
+        prune(1);
+        let imported = import(&export(&source)).unwrap();
+        assert_eq!(imported.last_hash(), source.last_hash());
+
+        prune(2);
+        let err = import(&export(&source)).unwrap_err();
+        assert!(err.to_string().contains("Precommits for the last block"));
+    }
+
+    #[test]
+    fn forked_chain_diverges_from_source() {
+        let (source, txs) = create_chain(2);
+        let source_snapshot = source.snapshot();
+        let source_schema = Schema::new(&source_snapshot);
+        let mut forked = create_blockchain();
+
+        let err = fork_chain(
+            source_snapshot.as_ref(),
+            Height(3),
+            "reset",
+            &mut forked,
+            GenesisState::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("last block is at height 2"));
+
+        let chain_id = fork_chain(
+            source_snapshot.as_ref(),
+            Height(1),
+            "reset",
+            &mut forked,
+            GenesisState::default(),
+        )
+        .unwrap();
+        assert_eq!(chain_id, "reset");
+        assert_eq!(
+            Some(forked.last_hash()),
+            source_schema.block_hash_by_height(Height(1))
+        );
+        assert!(check_integrity(&forked, 1).is_consistent());
+
+        // The same transaction produces a different block on the fork.
+        let (validator_key, validator_secret_key) = gen_keypair();
+        commit_block(
+            &mut forked,
+            Height(2),
+            txs[1].clone(),
+            (validator_key, &validator_secret_key),
+        );
+        assert_ne!(
+            Some(forked.last_hash()),
+            source_schema.block_hash_by_height(Height(2))
+        );
+        assert!(check_integrity(&forked, 1).is_consistent());
+
+        // Forks are preserved by the export.
+        let imported = import(&export(&forked)).unwrap();
+        assert_eq!(imported.last_hash(), forked.last_hash());
+        assert_eq!(Schema::new(&imported.snapshot()).chain_id(), "reset");
+
+        let mut forked_again = create_blockchain();
+        let chain_id = fork_chain(
+            forked.snapshot().as_ref(),
+            Height(2),
+            "again",
+            &mut forked_again,
+            GenesisState::default(),
+        )
+        .unwrap();
+        assert_eq!(chain_id, "reset.again");
+        assert_eq!(forked_again.last_hash(), forked.last_hash());
+        assert!(check_integrity(&forked_again, 1).is_consistent());
+    }
+
+    #[test]
+    fn rolled_back_chain_drops_latest_blocks() {
+        let (source, txs) = create_chain(3);
+        let source_snapshot = source.snapshot();
+        let source_schema = Schema::new(&source_snapshot);
+
+        let mut rolled_back = create_blockchain();
+        rollback_chain(
+            source_snapshot.as_ref(),
+            Height(1),
+            &mut rolled_back,
+            GenesisState::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(rolled_back.last_hash()),
+            source_schema.block_hash_by_height(Height(1))
+        );
+        assert!(check_integrity(&rolled_back, 1).is_consistent());
+
+        let snapshot = rolled_back.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.chain_id(), source_schema.chain_id());
+        assert!(schema.transactions_locations().contains(&txs[0].hash()));
+        assert!(!schema.transactions_locations().contains(&txs[1].hash()));
+
+        // The dropped blocks are committed again as in the source chain.
+        let (validator_key, validator_secret_key) = gen_keypair();
+        commit_block(
+            &mut rolled_back,
+            Height(2),
+            txs[1].clone(),
+            (validator_key, &validator_secret_key),
+        );
+        assert_eq!(
+            Some(rolled_back.last_hash()),
+            source_schema.block_hash_by_height(Height(2))
+        );
+    }
+}
@@ -0,0 +1,717 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Experimental execution of untrusted services in separate processes.
+//!
+//! An [`IsolatedService`] runs the transactions, `initialize`, `before_transactions`
+//! and `before_commit` hooks of the wrapped service in a worker process, so that a crash or a memory
+//! blowup in the service does not take down the node. Other hooks, including
+//! `state_hash` and the API, are still called in the node process.
+//!
+//! The worker receives requests from the node over its standard input and replies
+//! over its standard output with one JSON message per line; therefore, isolated
+//! services must not print to the standard output. While executing a request, the
+//! worker reads the state through the node, which serves the reads from the current
+//! fork. When the request is completed, the worker sends the changes of its fork,
+//! and the node applies them to the fork in the order of the index names, so the
+//! resulting state does not depend on the worker.
+//!
+//! If the worker exits or breaks the protocol, the node restarts it on the next
+//! request, and the current call panics. Thus, a transaction crashing the worker
+//! fails the same way as a panicking transaction.
+//!
+//! [`IsolatedService`]: struct.IsolatedService.html
+
+use exonum_merkledb::{
+    Change, Database, Error as StorageError, Fork, IndexAccess, IndexAddress, Iter,
+    Iterator as StorageIterator, Patch, Result as StorageResult, Snapshot, View,
+};
+use serde::{de::DeserializeOwned, Serialize, Serializer};
+use serde_json::Value;
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    panic,
+    path::PathBuf,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use super::{
+    AccessControlList, BackgroundJob, BlockSummary, ExecutionError, ExecutionResult, Namespace,
+    ReclaimedSpace, Service, ServiceContext, StoredConfiguration, Transaction, TransactionContext,
+    TransactionError,
+};
+use crate::api::ServiceApiBuilder;
+use crate::crypto::{Hash, PublicKey};
+use crate::helpers::{Height, ValidatorId};
+use crate::messages::{RawTransaction, ServiceTransaction};
+
+/// Maximum number of entries transferred to the worker per iteration request.
+const ITER_PAGE_SIZE: usize = 1_000;
+
+/// Request of the node to the worker.
+#[derive(Debug, Serialize, Deserialize)]
+enum NodeRequest {
+    /// Call `initialize` of the service.
+    Initialize,
+    /// Execute the transaction.
+    Execute {
+        transaction: RawTransaction,
+        tx_hash: Hash,
+        author: PublicKey,
+    },
+    /// Call `before_transactions` of the service.
+    BeforeTransactions,
+    /// Call `before_commit` of the service.
+    BeforeCommit,
+    /// Value read by the worker.
+    Value(Option<Vec<u8>>),
+    /// Entries iterated by the worker.
+    Entries(Vec<(Vec<u8>, Vec<u8>)>),
+}
+
+/// Request of the worker to the node.
+#[derive(Debug, Serialize, Deserialize)]
+enum WorkerRequest {
+    /// Read the value with the key from the index.
+    Get { name: String, key: Vec<u8> },
+    /// Iterate over the index starting from the key.
+    Iter { name: String, from: Vec<u8> },
+    /// Complete the current request.
+    Done(Outcome),
+}
+
+/// Result of the request processed by the worker.
+#[derive(Debug, Serialize, Deserialize)]
+enum Outcome {
+    /// The request is completed. The result contains the service configuration
+    /// returned by `initialize`.
+    Completed {
+        changes: Vec<IndexChanges>,
+        result: Result<Value, ExecutionError>,
+    },
+    /// The service has panicked.
+    Panicked(String),
+}
+
+/// Changes of the worker fork in a single column family.
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexChanges {
+    name: String,
+    prefixes_to_remove: Vec<Vec<u8>>,
+    /// Values put by the worker, or `None` for removed keys.
+    changes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+}
+
+/// Line-delimited JSON channel between the node and the worker.
+#[derive(Debug)]
+struct Channel<R, W> {
+    input: BufReader<R>,
+    output: W,
+}
+
+impl<R: Read, W: Write> Channel<R, W> {
+    fn new(input: R, output: W) -> Self {
+        Self {
+            input: BufReader::new(input),
+            output,
+        }
+    }
+
+    fn send<T: Serialize>(&mut self, message: &T) -> Result<(), failure::Error> {
+        serde_json::to_writer(&mut self.output, message)?;
+        self.output.write_all(b"\n")?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    /// Receives the next message, or `None` if the other side has closed the channel.
+    fn receive<T: DeserializeOwned>(&mut self) -> Result<Option<T>, failure::Error> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+}
+
+/// Running worker process.
+#[derive(Debug)]
+struct Process {
+    child: Child,
+    channel: Channel<ChildStdout, ChildStdin>,
+}
+
+impl Process {
+    fn call(
+        &mut self,
+        snapshot: &dyn Snapshot,
+        request: &NodeRequest,
+    ) -> Result<Outcome, failure::Error> {
+        self.channel.send(request)?;
+        loop {
+            let request = self
+                .channel
+                .receive()?
+                .ok_or_else(|| format_err!("Worker has exited"))?;
+            match request {
+                WorkerRequest::Get { name, key } => {
+                    let value = snapshot.get(&name, &key);
+                    self.channel.send(&NodeRequest::Value(value))?;
+                }
+                WorkerRequest::Iter { name, from } => {
+                    let mut iter = snapshot.iter(&name, &from);
+                    let mut entries = Vec::new();
+                    while entries.len() < ITER_PAGE_SIZE {
+                        match iter.next() {
+                            Some((key, value)) => entries.push((key.to_vec(), value.to_vec())),
+                            None => break,
+                        }
+                    }
+                    self.channel.send(&NodeRequest::Entries(entries))?;
+                }
+                WorkerRequest::Done(outcome) => return Ok(outcome),
+            }
+        }
+    }
+
+    fn stop(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Worker process of an isolated service, which is started on the first request.
+#[derive(Debug)]
+struct Worker {
+    service_name: String,
+    program: PathBuf,
+    args: Vec<String>,
+    process: Mutex<Option<Process>>,
+}
+
+impl Worker {
+    fn spawn(&self) -> Result<Process, failure::Error> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let input = child.stdin.take().expect("Worker stdin is piped");
+        let output = child.stdout.take().expect("Worker stdout is piped");
+        info!(
+            "Started worker {} of isolated service <{}>",
+            child.id(),
+            self.service_name
+        );
+        Ok(Process {
+            child,
+            channel: Channel::new(output, input),
+        })
+    }
+
+    /// Sends the request to the worker, starting the worker if necessary. The failed
+    /// worker is stopped and restarted on the next request.
+    fn send(
+        &self,
+        snapshot: &dyn Snapshot,
+        request: &NodeRequest,
+    ) -> Result<Outcome, failure::Error> {
+        let mut process = self.process.lock().expect("Expected mutex lock");
+        let mut running = match process.take() {
+            Some(running) => running,
+            None => self.spawn()?,
+        };
+        let outcome = running.call(snapshot, request);
+        if outcome.is_ok() {
+            *process = Some(running);
+        } else {
+            running.stop();
+        }
+        outcome
+    }
+
+    /// Processes the request in the worker and applies the resulting changes to the fork.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the service has panicked or the worker has failed.
+    fn call(&self, fork: &Fork, request: NodeRequest) -> Result<Value, ExecutionError> {
+        match self.send(fork.snapshot(), &request) {
+            Ok(Outcome::Completed { changes, result }) => {
+                if result.is_ok() {
+                    apply_changes(fork, changes);
+                }
+                result
+            }
+            Ok(Outcome::Panicked(description)) => panic!(
+                "Isolated service <{}> panicked: {}",
+                self.service_name, description
+            ),
+            Err(e) => panic!(
+                "Worker of isolated service <{}> has failed: {}",
+                self.service_name, e
+            ),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        if let Ok(mut process) = self.process.lock() {
+            if let Some(process) = process.take() {
+                process.stop();
+            }
+        }
+    }
+}
+
+/// Applies the changes made by the worker to the fork.
+fn apply_changes(fork: &Fork, changes: Vec<IndexChanges>) {
+    for index in changes {
+        let mut view = View::new(fork, IndexAddress::with_root(index.name));
+        for prefix in index.prefixes_to_remove {
+            let keys: Vec<Vec<u8>> = view
+                .iter::<_, Vec<u8>, Vec<u8>>(&prefix)
+                .map(|(key, _)| key)
+                .collect();
+            for key in keys {
+                view.remove(&key);
+            }
+        }
+        for (key, value) in index.changes {
+            match value {
+                Some(value) => view.put(&key, value),
+                None => view.remove(&key),
+            }
+        }
+    }
+}
+
+/// Collects the changes of the worker fork ordered by the index names.
+fn collect_changes(patch: Patch) -> Vec<IndexChanges> {
+    let mut changes: Vec<_> = patch
+        .into_iter()
+        .map(|(name, changes)| IndexChanges {
+            name,
+            prefixes_to_remove: changes.prefixes_to_remove().to_vec(),
+            changes: changes
+                .into_iter()
+                .map(|(key, change)| match change {
+                    Change::Put(value) => (key, Some(value)),
+                    Change::Delete => (key, None),
+                })
+                .collect(),
+        })
+        .collect();
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
+
+/// Service running its transactions, `initialize`, `before_transactions` and
+/// `before_commit` hooks in a separate process. See [the module documentation](index.html) for details.
+///
+/// The worker process is started with the given program and arguments. The worker
+/// must create the same service and call [`run_worker`] with it.
+///
+/// [`run_worker`]: fn.run_worker.html
+pub struct IsolatedService {
+    service: Box<dyn Service>,
+    worker: Arc<Worker>,
+}
+
+impl IsolatedService {
+    /// Wraps the service, which is executed by the worker started with the given
+    /// program and arguments.
+    pub fn new(service: Box<dyn Service>, program: PathBuf, args: Vec<String>) -> Self {
+        let worker = Worker {
+            service_name: service.service_name().to_owned(),
+            program,
+            args,
+            process: Mutex::new(None),
+        };
+        Self {
+            service,
+            worker: Arc::new(worker),
+        }
+    }
+}
+
+impl fmt::Debug for IsolatedService {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IsolatedService")
+            .field("service", &self.service.service_name())
+            .field("worker", &self.worker)
+            .finish()
+    }
+}
+
+impl Service for IsolatedService {
+    fn service_id(&self) -> u16 {
+        self.service.service_id()
+    }
+
+    fn service_name(&self) -> &str {
+        self.service.service_name()
+    }
+
+    fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+        self.service.state_hash_tables(snapshot)
+    }
+
+    fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+        let transaction = self.service.tx_from_raw(raw.clone())?;
+        Ok(Box::new(IsolatedTransaction {
+            raw,
+            transaction,
+            worker: Arc::clone(&self.worker),
+        }))
+    }
+
+    fn access_control(&self) -> AccessControlList {
+        self.service.access_control()
+    }
+
+    fn namespace(&self) -> Option<Namespace> {
+        self.service.namespace()
+    }
+
+    fn is_deployable(&self) -> bool {
+        self.service.is_deployable()
+    }
+
+    fn initialize(&self, fork: &Fork) -> Value {
+        self.worker
+            .call(fork, NodeRequest::Initialize)
+            .unwrap_or_else(|e| panic!("Cannot initialize isolated service: {:?}", e))
+    }
+
+    fn import_state(&self, fork: &Fork, data: &Value) -> Result<(), failure::Error> {
+        self.service.import_state(fork, data)
+    }
+
+    fn before_transactions(&self, fork: &Fork) {
+        if let Err(e) = self.worker.call(fork, NodeRequest::BeforeTransactions) {
+            panic!("Isolated service before_transactions failed: {:?}", e);
+        }
+    }
+
+    fn after_transactions(&self, fork: &Fork) -> Vec<ServiceTransaction> {
+        self.service.after_transactions(fork)
+    }
+
+    fn before_commit(&self, fork: &Fork) {
+        if let Err(e) = self.worker.call(fork, NodeRequest::BeforeCommit) {
+            panic!("Isolated service before_commit failed: {:?}", e);
+        }
+    }
+
+    fn on_config_change(&self, fork: &Fork, config: &StoredConfiguration) {
+        self.service.on_config_change(fork, config)
+    }
+
+    fn after_commit(&self, context: &ServiceContext) {
+        self.service.after_commit(context)
+    }
+
+    fn after_commit_block(&self, context: &ServiceContext, summary: &BlockSummary) {
+        self.service.after_commit_block(context, summary)
+    }
+
+    fn handle_validator_message(
+        &self,
+        context: &ServiceContext,
+        from: ValidatorId,
+        payload: &[u8],
+    ) {
+        self.service
+            .handle_validator_message(context, from, payload)
+    }
+
+    fn background_jobs(&self) -> Vec<BackgroundJob> {
+        self.service.background_jobs()
+    }
+
+    fn prune_ephemeral(&self, fork: &Fork, retain_from: Height) -> ReclaimedSpace {
+        self.service.prune_ephemeral(fork, retain_from)
+    }
+
+    fn wire_api(&self, builder: &mut ServiceApiBuilder) {
+        self.service.wire_api(builder)
+    }
+}
+
+/// Transaction of an isolated service, which is executed by the worker.
+#[derive(Debug)]
+struct IsolatedTransaction {
+    raw: RawTransaction,
+    transaction: Box<dyn Transaction>,
+    worker: Arc<Worker>,
+}
+
+impl Serialize for IsolatedTransaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.transaction.serialize(serializer)
+    }
+}
+
+impl Transaction for IsolatedTransaction {
+    fn execute(&self, context: TransactionContext) -> ExecutionResult {
+        let request = NodeRequest::Execute {
+            transaction: self.raw.clone(),
+            tx_hash: context.tx_hash(),
+            author: context.author(),
+        };
+        self.worker.call(context.fork(), request).map(drop)
+    }
+}
+
+type WorkerChannel = Channel<io::Stdin, io::Stdout>;
+
+/// Database of the worker reading the state through the node.
+#[derive(Debug, Clone)]
+struct RemoteDatabase {
+    channel: Arc<Mutex<WorkerChannel>>,
+}
+
+impl Database for RemoteDatabase {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        Box::new(self.clone())
+    }
+
+    fn merge(&self, _patch: Patch) -> StorageResult<()> {
+        Err(StorageError::new(
+            "Isolated service cannot merge changes into the database",
+        ))
+    }
+
+    fn merge_sync(&self, patch: Patch) -> StorageResult<()> {
+        self.merge(patch)
+    }
+}
+
+impl RemoteDatabase {
+    fn request(&self, request: &WorkerRequest) -> NodeRequest {
+        let mut channel = self.channel.lock().expect("Expected mutex lock");
+        channel
+            .send(request)
+            .and_then(|_| {
+                channel
+                    .receive()?
+                    .ok_or_else(|| format_err!("Node has closed the channel"))
+            })
+            .unwrap_or_else(|e| panic!("Cannot read the state from the node: {}", e))
+    }
+
+    fn entries(&self, name: &str, from: Vec<u8>) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let request = WorkerRequest::Iter {
+            name: name.to_owned(),
+            from,
+        };
+        match self.request(&request) {
+            NodeRequest::Entries(entries) => entries,
+            other => panic!("Unexpected response of the node: {:?}", other),
+        }
+    }
+}
+
+impl Snapshot for RemoteDatabase {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        let request = WorkerRequest::Get {
+            name: name.to_owned(),
+            key: key.to_vec(),
+        };
+        match self.request(&request) {
+            NodeRequest::Value(value) => value,
+            other => panic!("Unexpected response of the node: {:?}", other),
+        }
+    }
+
+    fn iter(&self, name: &str, from: &[u8]) -> Iter {
+        Box::new(RemoteIter {
+            database: self,
+            name: name.to_owned(),
+            entries: Vec::new(),
+            position: 0,
+            next_from: Some(from.to_vec()),
+        })
+    }
+}
+
+/// Iterator fetching the entries from the node page by page.
+struct RemoteIter<'a> {
+    database: &'a RemoteDatabase,
+    name: String,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    position: usize,
+    next_from: Option<Vec<u8>>,
+}
+
+impl RemoteIter<'_> {
+    fn fetch(&mut self) {
+        if self.position < self.entries.len() {
+            return;
+        }
+        if let Some(from) = self.next_from.take() {
+            self.entries = self.database.entries(&self.name, from);
+            self.position = 0;
+            if self.entries.len() == ITER_PAGE_SIZE {
+                // The smallest key following the last fetched one.
+                let mut next_from = self.entries[ITER_PAGE_SIZE - 1].0.clone();
+                next_from.push(0);
+                self.next_from = Some(next_from);
+            }
+        }
+    }
+}
+
+impl StorageIterator for RemoteIter<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.fetch();
+        let (key, value) = self.entries.get(self.position)?;
+        self.position += 1;
+        Some((key.as_slice(), value.as_slice()))
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        self.fetch();
+        let (key, value) = self.entries.get(self.position)?;
+        Some((key.as_slice(), value.as_slice()))
+    }
+}
+
+/// Serves the requests of the node to the isolated service until the node closes
+/// the standard input of the worker.
+pub fn run_worker(service: Box<dyn Service>) -> Result<(), failure::Error> {
+    let channel = Arc::new(Mutex::new(Channel::new(io::stdin(), io::stdout())));
+    let database = RemoteDatabase {
+        channel: Arc::clone(&channel),
+    };
+
+    loop {
+        let request = channel.lock().expect("Expected mutex lock").receive()?;
+        let request = match request {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+
+        let fork = database.fork();
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            process_request(service.as_ref(), &fork, request)
+        }));
+        let outcome = match result {
+            Ok(Ok(config)) => Outcome::Completed {
+                changes: collect_changes(fork.into_patch()),
+                result: Ok(config),
+            },
+            Ok(Err(e)) => Outcome::Completed {
+                changes: Vec::new(),
+                result: Err(e),
+            },
+            Err(panic) => {
+                let error = TransactionError::from_panic(&panic);
+                Outcome::Panicked(error.description().unwrap_or_default().to_owned())
+            }
+        };
+
+        channel
+            .lock()
+            .expect("Expected mutex lock")
+            .send(&WorkerRequest::Done(outcome))?;
+    }
+}
+
+fn process_request(
+    service: &dyn Service,
+    fork: &Fork,
+    request: NodeRequest,
+) -> Result<Value, ExecutionError> {
+    match request {
+        NodeRequest::Initialize => Ok(service.initialize(fork)),
+        NodeRequest::Execute {
+            transaction,
+            tx_hash,
+            author,
+        } => {
+            let service_id = transaction.service_id();
+            let transaction = service
+                .tx_from_raw(transaction)
+                .unwrap_or_else(|e| panic!("Cannot decode transaction {:?}: {}", tx_hash, e));
+            let context = TransactionContext::from_parts(
+                fork,
+                service_id,
+                service.service_name(),
+                tx_hash,
+                author,
+            );
+            transaction.execute(context).map(|_| Value::Null)
+        }
+        NodeRequest::BeforeTransactions => {
+            service.before_transactions(fork);
+            Ok(Value::Null)
+        }
+        NodeRequest::BeforeCommit => {
+            service.before_commit(fork);
+            Ok(Value::Null)
+        }
+        other => panic!("Unexpected request of the node: {:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_merkledb::{Database, Entry, MapIndex, TemporaryDB};
+
+    use super::*;
+
+    #[test]
+    fn worker_changes_are_applied_to_fork() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        {
+            let mut map: MapIndex<_, u64, String> = MapIndex::new("map", &fork);
+            map.put(&1, "one".to_owned());
+            map.put(&2, "two".to_owned());
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        // Changes made by the worker on top of the same state.
+        let worker_fork = db.fork();
+        {
+            let mut map: MapIndex<_, u64, String> = MapIndex::new("map", &worker_fork);
+            map.clear();
+            map.put(&3, "three".to_owned());
+            let mut entry: Entry<_, u64> = Entry::new("entry", &worker_fork);
+            entry.set(42);
+        }
+        let changes = collect_changes(worker_fork.into_patch());
+        let names: Vec<_> = changes.iter().map(|index| index.name.as_str()).collect();
+        let mut sorted_names = names.clone();
+        sorted_names.sort();
+        assert_eq!(names, sorted_names);
+
+        let fork = db.fork();
+        apply_changes(&fork, changes);
+        let map: MapIndex<_, u64, String> = MapIndex::new("map", &fork);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![(3, "three".to_owned())]
+        );
+        let entry: Entry<_, u64> = Entry::new("entry", &fork);
+        assert_eq!(entry.get(), Some(42));
+    }
+}
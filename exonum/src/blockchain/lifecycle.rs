@@ -0,0 +1,227 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lifecycle states of the services.
+//!
+//! A service is active unless the validators decide otherwise. The state of a service
+//! is changed by a configuration listing it in the `lifecycle` field of the service
+//! section, for example, `{ "lifecycle": "frozen" }`. When the configuration becomes
+//! actual, the state is recorded into the `service_states` core table at the beginning
+//! of the block, right after the deployment of the services; a configuration omitting
+//! the field makes the service active again.
+//!
+//! - A **stopped** service does not accept transactions and its API responds with
+//!   `404 Not Found`.
+//! - A **frozen** service does not accept transactions either, but serves the read
+//!   requests of its API.
+//!
+//! The hooks and the background jobs of inactive services are not invoked. The
+//! transactions of inactive services remaining in the pool are committed with the
+//! `ServiceInactive` error without being executed. The tables of inactive services
+//! are still aggregated into the state hash.
+//!
+//! The configuration service should not be stopped or frozen, since the validators
+//! would be unable to change the configuration afterwards.
+
+use exonum_merkledb::{BinaryValue, Fork, IndexAccess};
+
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use super::{Schema, Service, StoredConfiguration};
+use crate::helpers::Height;
+
+/// Field of the service configuration specifying the lifecycle state of the service.
+pub const LIFECYCLE_FIELD: &str = "lifecycle";
+
+/// Lifecycle state of a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceState {
+    /// The service accepts transactions and serves its API.
+    Active,
+    /// The service does not accept transactions and does not serve its API.
+    Stopped,
+    /// The service does not accept transactions and serves only the read requests
+    /// of its API.
+    Frozen,
+}
+
+impl ServiceState {
+    /// Returns `true` if the transactions of the service are accepted and executed.
+    pub fn accepts_transactions(self) -> bool {
+        self == ServiceState::Active
+    }
+}
+
+impl Default for ServiceState {
+    fn default() -> Self {
+        ServiceState::Active
+    }
+}
+
+impl fmt::Display for ServiceState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServiceState::Active => f.write_str("active"),
+            ServiceState::Stopped => f.write_str("stopped"),
+            ServiceState::Frozen => f.write_str("frozen"),
+        }
+    }
+}
+
+impl BinaryValue for ServiceState {
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, failure::Error> {
+        match bytes.as_ref() {
+            [0] => Ok(ServiceState::Active),
+            [1] => Ok(ServiceState::Stopped),
+            [2] => Ok(ServiceState::Frozen),
+            other => bail!("Invalid service state: {:?}", other),
+        }
+    }
+}
+
+/// Returns the lifecycle state of the service specified in its configuration section.
+pub fn configured_state(
+    config: &StoredConfiguration,
+    service_name: &str,
+) -> Result<ServiceState, failure::Error> {
+    match config
+        .services
+        .get(service_name)
+        .and_then(|section| section.get(LIFECYCLE_FIELD))
+    {
+        Some(state) => serde_json::from_value(state.clone()).map_err(|e| {
+            format_err!(
+                "Invalid lifecycle state in the configuration of service <{}>: {}",
+                service_name,
+                e
+            )
+        }),
+        None => Ok(ServiceState::Active),
+    }
+}
+
+/// Checks that the configuration specifies valid lifecycle states for all services.
+pub fn check_states(config: &StoredConfiguration) -> Result<(), failure::Error> {
+    for service_name in config.services.keys() {
+        configured_state(config, service_name)?;
+    }
+    Ok(())
+}
+
+/// Returns the lifecycle state of the service in the blockchain state.
+pub fn service_state<T: IndexAccess>(access: T, service_id: u16) -> ServiceState {
+    Schema::new(access)
+        .service_states()
+        .get(&service_id)
+        .unwrap_or_default()
+}
+
+/// Records the lifecycle states of the services specified in the configuration
+/// activated at the given height.
+pub(crate) fn update_states(
+    fork: &mut Fork,
+    services: &HashMap<u16, Box<dyn Service>>,
+    config: &StoredConfiguration,
+    height: Height,
+) {
+    {
+        let mut states = Schema::new(&*fork).service_states();
+        for service in services.values() {
+            let service_id = service.service_id();
+            let state = match configured_state(config, service.service_name()) {
+                Ok(state) => state,
+                Err(e) => {
+                    error!("{}, the state remains unchanged", e);
+                    continue;
+                }
+            };
+            if states.get(&service_id).unwrap_or_default() == state {
+                continue;
+            }
+            if state == ServiceState::Active {
+                states.remove(&service_id);
+            } else {
+                states.put(&service_id, state);
+            }
+            info!(
+                "Service <{}> is {} since height {}",
+                service.service_name(),
+                state,
+                height
+            );
+        }
+    }
+    fork.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusConfig;
+    use crate::crypto::Hash;
+
+    fn config(services: serde_json::Value) -> StoredConfiguration {
+        StoredConfiguration {
+            previous_cfg_hash: Hash::zero(),
+            actual_from: Height(1),
+            validator_keys: vec![],
+            consensus: ConsensusConfig::default(),
+            services: serde_json::from_value(services).unwrap(),
+        }
+    }
+
+    #[test]
+    fn configured_states() {
+        let valid = config(json!({
+            "a": {},
+            "b": { "lifecycle": "stopped" },
+            "c": { "lifecycle": "frozen" },
+            "d": { "lifecycle": "active" }
+        }));
+        assert_eq!(configured_state(&valid, "a").unwrap(), ServiceState::Active);
+        assert_eq!(
+            configured_state(&valid, "b").unwrap(),
+            ServiceState::Stopped
+        );
+        assert_eq!(configured_state(&valid, "c").unwrap(), ServiceState::Frozen);
+        assert_eq!(configured_state(&valid, "d").unwrap(), ServiceState::Active);
+        assert_eq!(
+            configured_state(&valid, "unknown").unwrap(),
+            ServiceState::Active
+        );
+        assert!(check_states(&valid).is_ok());
+
+        let invalid = config(json!({ "a": { "lifecycle": "paused" } }));
+        assert!(configured_state(&invalid, "a").is_err());
+        assert!(check_states(&invalid).is_err());
+    }
+
+    #[test]
+    fn state_binary_roundtrip() {
+        for &state in &[
+            ServiceState::Active,
+            ServiceState::Stopped,
+            ServiceState::Frozen,
+        ] {
+            let bytes = state.to_bytes();
+            assert_eq!(ServiceState::from_bytes(bytes.into()).unwrap(), state);
+        }
+        assert!(ServiceState::from_bytes(vec![3].into()).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Limits on the resources consumed by the transactions of a block.
+
+use std::cmp;
+
+use super::ConsensusConfig;
+
+/// Resources left to the transactions of the block being executed.
+#[derive(Debug, Clone)]
+pub(super) struct BlockBudget {
+    transaction_gas_limit: Option<u64>,
+    block_gas_limit: Option<u64>,
+    block_size_limit: Option<u64>,
+    gas_used: u64,
+    size_used: u64,
+}
+
+/// Converts a limit from the configuration, where zero means no limit.
+fn limit(value: u64) -> Option<u64> {
+    Some(value).filter(|&value| value > 0)
+}
+
+impl BlockBudget {
+    pub(super) fn new(config: &ConsensusConfig) -> Self {
+        Self {
+            transaction_gas_limit: limit(config.transaction_gas_limit),
+            block_gas_limit: limit(config.max_block_gas),
+            block_size_limit: limit(config.max_block_size),
+            gas_used: 0,
+            size_used: 0,
+        }
+    }
+
+    /// Checks whether a transaction with the message of the given size fits into
+    /// the block. The transaction fits if its message does not exceed the remaining
+    /// size of the block and there is gas left in the block.
+    pub(super) fn fits(&self, size: u64) -> bool {
+        self.block_size_limit
+            .map_or(true, |limit| self.size_used + size <= limit)
+            && self
+                .block_gas_limit
+                .map_or(true, |limit| self.gas_used < limit)
+    }
+
+    /// Returns the amount of gas available to the next transaction, or `None`
+    /// if the gas is not limited.
+    pub(super) fn gas_limit(&self) -> Option<u64> {
+        let block_gas_left = self
+            .block_gas_limit
+            .map(|limit| limit.saturating_sub(self.gas_used));
+        match (self.transaction_gas_limit, block_gas_left) {
+            (Some(tx_limit), Some(block_limit)) => Some(cmp::min(tx_limit, block_limit)),
+            (tx_limit, block_limit) => tx_limit.or(block_limit),
+        }
+    }
+
+    /// Accounts for the resources consumed by an executed transaction.
+    pub(super) fn consume(&mut self, size: u64, gas: u64) {
+        self.size_used += size;
+        self.gas_used = self.gas_used.saturating_add(gas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(transaction_gas_limit: u64, max_block_gas: u64, max_block_size: u64) -> BlockBudget {
+        BlockBudget::new(&ConsensusConfig {
+            transaction_gas_limit,
+            max_block_gas,
+            max_block_size,
+            ..ConsensusConfig::default()
+        })
+    }
+
+    #[test]
+    fn unlimited_budget() {
+        let mut budget = budget(0, 0, 0);
+        budget.consume(1_000_000, 1_000_000);
+        assert!(budget.fits(1_000_000));
+        assert_eq!(budget.gas_limit(), None);
+    }
+
+    #[test]
+    fn block_size_limit() {
+        let mut budget = budget(0, 0, 100);
+        assert!(budget.fits(100));
+        budget.consume(60, 0);
+        assert!(budget.fits(40));
+        assert!(!budget.fits(41));
+    }
+
+    #[test]
+    fn block_gas_limit() {
+        let mut budget = budget(50, 120, 0);
+        assert_eq!(budget.gas_limit(), Some(50));
+        budget.consume(10, 80);
+        assert_eq!(budget.gas_limit(), Some(40));
+        assert!(budget.fits(10));
+        budget.consume(10, 41);
+        assert_eq!(budget.gas_limit(), Some(0));
+        assert!(!budget.fits(10));
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Migration of the service data between versions.
+//!
+//! The layout of the data stored by a service is identified by its data version returned
+//! by [`Service::data_version`]. A release of the service changing the layout increases
+//! the data version and implements [`Service::migrate`], which transforms the data
+//! of the previous version into the new layout.
+//!
+//! Migrations are started by a configuration specifying the target version in
+//! the `data_version` field of the service section, for example, `{ "data_version": 2 }`.
+//! When the configuration becomes actual, the core invokes [`Service::migrate`] for each
+//! version from the stored one up to the target at the beginning of the block, after
+//! the lifecycle states are updated. The configuration is agreed upon by the validators,
+//! so all nodes migrate the data in the same block; the nodes have to be upgraded to
+//! a release supporting the target version beforehand. A node that does not support
+//! the target version cannot execute the block and panics.
+//!
+//! The data versions and the results of the migrations are kept in the
+//! `service_migrations` core table. The services initialized with a non-zero data version
+//! record it on initialization; other services start from the version `0`. If a migration
+//! fails, its changes are discarded, the error is recorded, and the service is stopped
+//! (see [`lifecycle`]) until the next configuration, which retries the migration.
+//! Configurations specifying a version not above the stored one do not change the data.
+//!
+//! [`Service::data_version`]: ../trait.Service.html#method.data_version
+//! [`Service::migrate`]: ../trait.Service.html#method.migrate
+//! [`lifecycle`]: ../lifecycle/index.html
+
+use exonum_merkledb::{BinaryValue, Error as StorageError, Fork, IndexAccess};
+
+use std::{borrow::Cow, panic};
+
+use super::{lifecycle::ServiceState, Schema, Service, StoredConfiguration, TransactionError};
+use crate::helpers::Height;
+
+/// Field of the service configuration specifying the target data version of the service.
+pub const DATA_VERSION_FIELD: &str = "data_version";
+
+/// Status of the data of a service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationStatus {
+    /// Version of the stored data.
+    pub version: u32,
+    /// Height of the block that has performed the latest migration attempt, or
+    /// initialized the service.
+    pub height: Height,
+    /// Error of the latest migration attempt, if it has failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BinaryValue for MigrationStatus {
+    fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, failure::Error> {
+        serde_json::from_slice(bytes.as_ref()).map_err(Into::into)
+    }
+}
+
+/// Returns the target data version of the service specified in its configuration section.
+pub fn configured_version(
+    config: &StoredConfiguration,
+    service_name: &str,
+) -> Result<Option<u32>, failure::Error> {
+    match config
+        .services
+        .get(service_name)
+        .and_then(|section| section.get(DATA_VERSION_FIELD))
+    {
+        Some(version) => serde_json::from_value(version.clone())
+            .map(Some)
+            .map_err(|e| {
+                format_err!(
+                    "Invalid data version in the configuration of service <{}>: {}",
+                    service_name,
+                    e
+                )
+            }),
+        None => Ok(None),
+    }
+}
+
+/// Checks that the configuration specifies valid data versions for all services.
+pub fn check_versions(config: &StoredConfiguration) -> Result<(), failure::Error> {
+    for service_name in config.services.keys() {
+        configured_version(config, service_name)?;
+    }
+    Ok(())
+}
+
+/// Returns the version of the data of the service in the blockchain state.
+pub fn data_version<T: IndexAccess>(access: T, service_id: u16) -> u32 {
+    Schema::new(access)
+        .service_migrations()
+        .get(&service_id)
+        .map_or(0, |status| status.version)
+}
+
+/// Records the data version of the service initialized at the given height.
+pub(crate) fn record_initial_version(fork: &Fork, service: &dyn Service, height: Height) {
+    let version = service.data_version();
+    if version > 0 {
+        let status = MigrationStatus {
+            version,
+            height,
+            error: None,
+        };
+        Schema::new(fork)
+            .service_migrations()
+            .put(&service.service_id(), status);
+    }
+}
+
+/// Migrates the data of the services to the versions specified in the configuration
+/// activated at the given height.
+///
+/// # Panics
+///
+/// Panics if a service does not support the version specified in the configuration.
+pub(crate) fn migrate_services(
+    fork: &mut Fork,
+    services: &[&dyn Service],
+    config: &StoredConfiguration,
+    height: Height,
+) {
+    for &service in services {
+        let target = match configured_version(config, service.service_name()) {
+            Ok(Some(target)) => target,
+            Ok(None) => continue,
+            Err(e) => {
+                error!("{}, the data is not migrated", e);
+                continue;
+            }
+        };
+        let current = data_version(&*fork, service.service_id());
+        if target <= current {
+            continue;
+        }
+        assert!(
+            service.data_version() >= target,
+            "Service <{}> supports data version {}, but the configuration activated \
+             at height {} requires version {}; the node has to be upgraded",
+            service.service_name(),
+            service.data_version(),
+            height,
+            target
+        );
+        migrate(service, fork, current, target, height);
+    }
+}
+
+fn migrate(service: &dyn Service, fork: &mut Fork, current: u32, target: u32, height: Height) {
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        (current..target).try_for_each(|version| service.migrate(version, fork))
+    }));
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(err) => {
+            if err.is::<StorageError>() {
+                // Continue panic unwind if the reason is StorageError.
+                panic::resume_unwind(err);
+            }
+            let error = TransactionError::from_panic(&err);
+            Some(error.description().unwrap_or("panic").to_owned())
+        }
+    };
+
+    let service_id = service.service_id();
+    let status = match error {
+        None => {
+            info!(
+                "Data of service <{}> has been migrated from version {} to {} at height {}",
+                service.service_name(),
+                current,
+                target,
+                height
+            );
+            MigrationStatus {
+                version: target,
+                height,
+                error: None,
+            }
+        }
+        Some(error) => {
+            fork.rollback();
+            error!(
+                "Migration of service <{}> from version {} to {} failed, the service \
+                 is stopped: {}",
+                service.service_name(),
+                current,
+                target,
+                error
+            );
+            Schema::new(&*fork)
+                .service_states()
+                .put(&service_id, ServiceState::Stopped);
+            MigrationStatus {
+                version: current,
+                height,
+                error: Some(error),
+            }
+        }
+    };
+    Schema::new(&*fork)
+        .service_migrations()
+        .put(&service_id, status);
+    fork.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusConfig;
+    use crate::crypto::Hash;
+
+    #[test]
+    fn configured_versions() {
+        let config = |services: serde_json::Value| StoredConfiguration {
+            previous_cfg_hash: Hash::zero(),
+            actual_from: Height(1),
+            validator_keys: vec![],
+            consensus: ConsensusConfig::default(),
+            services: serde_json::from_value(services).unwrap(),
+        };
+
+        let valid = config(json!({ "a": {}, "b": { "data_version": 2 } }));
+        assert_eq!(configured_version(&valid, "a").unwrap(), None);
+        assert_eq!(configured_version(&valid, "b").unwrap(), Some(2));
+        assert!(check_versions(&valid).is_ok());
+
+        let invalid = config(json!({ "a": { "data_version": -1 } }));
+        assert!(configured_version(&invalid, "a").is_err());
+        assert!(check_versions(&invalid).is_err());
+    }
+
+    #[test]
+    fn status_binary_roundtrip() {
+        let status = MigrationStatus {
+            version: 3,
+            height: Height(10),
+            error: Some("Invalid data".to_owned()),
+        };
+        let bytes = status.to_bytes();
+        assert_eq!(MigrationStatus::from_bytes(bytes.into()).unwrap(), status);
+    }
+}
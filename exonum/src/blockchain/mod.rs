@@ -32,48 +32,119 @@
 //! [doc:create-service]: https://exonum.com/doc/version/latest/get-started/create-service
 
 pub use self::{
-    block::{Block, BlockProof},
+    access::{AccessControlList, AuthorizedKeys},
+    archive::{ArchiveConfig, TransactionArchive},
+    audit::{audit_chain, AuditDivergence, AuditReport},
+    block::{
+        AdditionalHeaders, Block, BlockHeaderKey, BlockProof, BlockTime, EventsHash, TxResultsHash,
+    },
+    builder::{BlockchainBuildError, BlockchainBuilder, ServiceLimits, RESERVED_SERVICE_IDS},
     config::{ConsensusConfig, StoredConfiguration, ValidatorKeys},
-    genesis::GenesisConfig,
-    schema::{Schema, TxLocation},
-    service::{Service, ServiceContext, SharedNodeState},
+    events::TransactionEvent,
+    fees::FeeHandler,
+    genesis::{GenesisConfig, GenesisState, ServiceStateDump},
+    integrity::{check_integrity, IntegrityProblem, IntegrityReport},
+    interchange::{export_chain, fork_chain, import_chain, rollback_chain, CHAIN_FORMAT_VERSION},
+    isolation::{run_worker, IsolatedService},
+    namespace::Namespace,
+    oracle::{OracleAggregation, OracleFeed, OracleValue},
+    replay::{replay_transaction, EntryChange, IndexChanges, TransactionReplay},
+    retention::{ReclaimedSpace, RetentionConfig},
+    scheduler::{BackgroundJob, JobContext, JobHandler, JobTrigger},
+    schema::{BlockMetrics, ConfigActivation, Schema, TxLocation},
+    service::{
+        BlockCommitted, BlockSummary, Service, ServiceContext, ServiceInstance, SharedNodeState,
+        TransactionSummary,
+    },
+    system::system_transaction_author,
+    tags::{TransactionTag, MAX_TRANSACTION_TAG_SIZE},
     transaction::{
         ExecutionError, ExecutionResult, Transaction, TransactionContext, TransactionError,
         TransactionErrorType, TransactionMessage, TransactionResult, TransactionSet,
     },
 };
 
-pub mod config;
+pub(crate) use self::interchange::verify_precommits;
 
-use byteorder::{ByteOrder, LittleEndian};
+pub mod archive;
+pub mod calls;
+pub mod config;
+pub mod deployment;
+pub mod divergence;
+pub mod events;
+pub mod features;
+pub mod lifecycle;
+pub mod migration;
+pub mod oracle;
 
 use std::{
-    collections::{BTreeMap, HashMap},
-    fmt, iter, mem, panic,
-    sync::Arc,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt, iter, panic,
+    path::Path,
+    rc::Rc,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
 };
 
+use self::calls::CallDispatcher;
+use self::execution_cache::ExecutionCache;
+use self::limits::BlockBudget;
+use self::transaction::{GasMeter, OutOfGas};
 use crate::crypto::{self, CryptoHash, Hash, PublicKey, SecretKey};
 use crate::helpers::{Height, Round, ValidatorId};
-use crate::messages::{Connect, Message, Precommit, ProtocolMessage, RawTransaction, Signed};
+use crate::messages::{
+    Connect, Message, Precommit, ProtocolMessage, RawTransaction, ServiceTransaction, Signed,
+};
 use crate::node::ApiSender;
+use chrono::{DateTime, Utc};
 use exonum_merkledb::{
     self, Database, Error as StorageError, Fork, IndexAccess, MapIndex, ObjectHash, Patch,
     Result as StorageResult, Snapshot,
 };
 
+mod access;
+mod audit;
 mod block;
+mod builder;
+mod execution_cache;
+mod fees;
 mod genesis;
+mod integrity;
+mod interchange;
+mod isolation;
+mod limits;
+mod namespace;
+mod parallel;
+mod replay;
+pub(crate) mod retention;
+pub(crate) mod scheduler;
 mod schema;
 mod service;
+mod system;
+mod tags;
 #[macro_use]
 mod transaction;
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
 
 /// Id of core service table family.
 pub const CORE_SERVICE: u16 = 0;
 
+/// Table aggregated into the state hash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateTable {
+    /// Identifier of the service owning the table, `CORE_SERVICE` for the core tables.
+    pub service_id: u16,
+    /// Name of the service owning the table, `core` for the core tables.
+    pub service_name: String,
+    /// Full name of the table, which identifies the table in the state hash.
+    pub name: String,
+    /// Root hash of the table.
+    pub hash: Hash,
+}
+
 /// Exonum blockchain instance with a certain services set and data storage.
 ///
 /// Only nodes with an identical set of services and genesis block can be combined
@@ -81,13 +152,30 @@ pub const CORE_SERVICE: u16 = 0;
 pub struct Blockchain {
     db: Arc<dyn Database>,
     service_map: Arc<HashMap<u16, Box<dyn Service>>>,
+    access_control: Arc<HashMap<u16, AccessControlList>>,
+    namespaces: Arc<HashMap<u16, Namespace>>,
+    oracle_feeds: Arc<HashMap<String, OracleFeed>>,
+    transaction_archive: Option<TransactionArchive>,
+    fee_handler: Option<Arc<dyn FeeHandler>>,
     #[doc(hidden)]
     pub service_keypair: (PublicKey, SecretKey),
     pub(crate) api_sender: ApiSender,
+    record_patch_hashes: bool,
+    execution_cache: Arc<ExecutionCache>,
+    commit_subscribers: Arc<Mutex<Vec<Sender<BlockCommitted>>>>,
 }
 
 impl Blockchain {
     /// Constructs a blockchain for the given `storage` and list of `services`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if several services have the same ID or name. Use [`BlockchainBuilder`]
+    /// to handle these errors and to set the [`ServiceLimits`]; the services using
+    /// the identifiers reserved for the built-in services are only reported in the log.
+    ///
+    /// [`BlockchainBuilder`]: struct.BlockchainBuilder.html
+    /// [`ServiceLimits`]: struct.ServiceLimits.html
     pub fn new<D: Into<Arc<dyn Database>>>(
         storage: D,
         services: Vec<Box<dyn Service>>,
@@ -95,24 +183,121 @@ impl Blockchain {
         service_secret_key: SecretKey,
         api_sender: ApiSender,
     ) -> Self {
-        let mut service_map = HashMap::new();
+        Self::from_parts(
+            storage.into(),
+            services,
+            (service_public_key, service_secret_key),
+            api_sender,
+            ServiceLimits::default(),
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn from_parts(
+        storage: Arc<dyn Database>,
+        services: Vec<Box<dyn Service>>,
+        service_keypair: (PublicKey, SecretKey),
+        api_sender: ApiSender,
+        service_limits: ServiceLimits,
+    ) -> Result<Self, BlockchainBuildError> {
+        service_limits.check(&services)?;
+        let mut service_map: HashMap<u16, Box<dyn Service>> = HashMap::new();
+        let mut access_control = HashMap::new();
+        let mut namespaces = HashMap::new();
+        let mut oracle_feeds = HashMap::new();
         for service in services {
             let id = service.service_id();
             if service_map.contains_key(&id) {
-                panic!(
-                    "Services have already contain service with id={}, please change it.",
-                    id
-                );
+                return Err(BlockchainBuildError::DuplicateServiceId(id));
+            }
+            // Instances of the same service are distinguished by the name in the API
+            // and in the configuration.
+            if service_map
+                .values()
+                .any(|other| other.service_name() == service.service_name())
+            {
+                return Err(BlockchainBuildError::DuplicateServiceName(
+                    service.service_name().to_owned(),
+                ));
+            }
+            let acl = service.access_control();
+            if !acl.is_empty() {
+                access_control.insert(id, acl);
+            }
+            if let Some(namespace) = service.namespace() {
+                namespaces.insert(id, namespace);
+            }
+            for feed in service.oracle_feeds() {
+                oracle_feeds.insert(feed.full_name(service.service_name()), feed);
             }
             service_map.insert(id, service);
         }
+        let services = service_map.values().map(AsRef::as_ref);
+        builder::check_state_tables(storage.snapshot().as_ref(), services)?;
 
-        Self {
-            db: storage.into(),
+        Ok(Self {
+            db: storage,
             service_map: Arc::new(service_map),
-            service_keypair: (service_public_key, service_secret_key),
+            access_control: Arc::new(access_control),
+            namespaces: Arc::new(namespaces),
+            oracle_feeds: Arc::new(oracle_feeds),
+            transaction_archive: None,
+            fee_handler: None,
+            service_keypair,
             api_sender,
-        }
+            record_patch_hashes: false,
+            execution_cache: Arc::default(),
+            commit_subscribers: Arc::default(),
+        })
+    }
+
+    /// Enables or disables recording of the hashes of the block patches.
+    ///
+    /// If enabled, the hash of the changes made by each block is stored in the
+    /// [`patch_hashes`] table, so that the patches created by different nodes for the
+    /// same block can be compared when diagnosing nondeterministic services.
+    ///
+    /// [`patch_hashes`]: struct.Schema.html#method.patch_hashes
+    pub fn set_record_patch_hashes(&mut self, enabled: bool) {
+        self.record_patch_hashes = enabled;
+    }
+
+    /// Subscribes to the notifications of the committed blocks.
+    ///
+    /// A notification is sent after each block is committed, either by the node
+    /// or with [`commit`], once the services have handled the commit in their
+    /// `after_commit` and `after_commit_block` hooks. The notifications are shared
+    /// by all the clones of the blockchain, so the node blockchain can be subscribed to
+    /// with `Node::blockchain` before the node is run. The channel is unbounded:
+    /// the receiver should be read regularly or dropped, which unsubscribes it
+    /// on the next commit.
+    ///
+    /// [`commit`]: #method.commit
+    pub fn subscribe(&self) -> Receiver<BlockCommitted> {
+        let (sender, receiver) = mpsc::channel();
+        self.commit_subscribers
+            .lock()
+            .expect("Commit subscribers lock is poisoned")
+            .push(sender);
+        receiver
+    }
+
+    /// Sets the archive of the transactions offloaded from the local database.
+    pub fn set_transaction_archive(&mut self, archive: TransactionArchive) {
+        self.transaction_archive = Some(archive);
+    }
+
+    /// Sets the handler charging the transaction fees. The transactions are free
+    /// if the handler is not set. See [`FeeHandler`] for details.
+    ///
+    /// [`FeeHandler`]: trait.FeeHandler.html
+    pub fn set_fee_handler<H: FeeHandler>(&mut self, handler: H) {
+        self.fee_handler = Some(Arc::new(handler));
+    }
+
+    /// Returns the archive of the offloaded transactions, if any.
+    pub fn transaction_archive(&self) -> Option<&TransactionArchive> {
+        self.transaction_archive.as_ref()
     }
 
     /// Recreates the blockchain to reuse with a sandbox.
@@ -125,11 +310,49 @@ impl Blockchain {
     }
 
     /// Returns mapping from the service identifier (`u16`) to service (`Box<dyn Service>`) for
-    /// all services.
+    /// all services, including the deployable services that are not deployed yet.
     pub fn service_map(&self) -> &Arc<HashMap<u16, Box<dyn Service>>> {
         &self.service_map
     }
 
+    /// Checks whether the blockchain has a service with the given ID and the service
+    /// is deployed in the given state. See [`deployment`] for details.
+    ///
+    /// [`deployment`]: deployment/index.html
+    pub fn is_service_deployed(&self, snapshot: &dyn Snapshot, service_id: u16) -> bool {
+        self.service_map.get(&service_id).map_or(false, |service| {
+            deployment::is_deployed(snapshot, service.as_ref())
+        })
+    }
+
+    /// Checks whether the blockchain has a service with the given ID and the service
+    /// is deployed and active in the given state. See [`lifecycle`] for details.
+    ///
+    /// [`lifecycle`]: lifecycle/index.html
+    pub fn is_service_active(&self, snapshot: &dyn Snapshot, service_id: u16) -> bool {
+        self.is_service_deployed(snapshot, service_id)
+            && lifecycle::service_state(snapshot, service_id).accepts_transactions()
+    }
+
+    /// Returns the services deployed in the given state.
+    fn deployed_services(&self, snapshot: &dyn Snapshot) -> Vec<&dyn Service> {
+        self.service_map
+            .values()
+            .map(|service| service.as_ref())
+            .filter(|service| deployment::is_deployed(snapshot, *service))
+            .collect()
+    }
+
+    /// Returns the services deployed and active in the given state, whose hooks are invoked.
+    fn active_services(&self, snapshot: &dyn Snapshot) -> Vec<&dyn Service> {
+        self.deployed_services(snapshot)
+            .into_iter()
+            .filter(|service| {
+                lifecycle::service_state(snapshot, service.service_id()).accepts_transactions()
+            })
+            .collect()
+    }
+
     /// Creates a read-only snapshot of the current storage state.
     pub fn snapshot(&self) -> Box<dyn Snapshot> {
         self.db.snapshot()
@@ -146,12 +369,40 @@ impl Blockchain {
     /// if the following conditions are met:
     ///
     /// - Blockchain has a service with the `service_id` of the given raw message.
+    /// - The service is deployed and active.
     /// - Service can deserialize the given raw message.
     pub fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
         let service = self
             .service_map
             .get(&raw.service_id())
             .ok_or_else(|| format_err!("Service not found."))?;
+        let snapshot = self.snapshot();
+        let state = lifecycle::service_state(snapshot.as_ref(), service.service_id());
+        ensure!(
+            state.accepts_transactions(),
+            "Service <{}> is {}.",
+            service.service_name(),
+            state
+        );
+        self.tx_from_raw_at(raw, snapshot.as_ref())
+    }
+
+    /// Creates a `Transaction` object from the given raw message if the service
+    /// of the message is deployed in the given state.
+    fn tx_from_raw_at(
+        &self,
+        raw: RawTransaction,
+        snapshot: &dyn Snapshot,
+    ) -> Result<Box<dyn Transaction>, failure::Error> {
+        let service = self
+            .service_map
+            .get(&raw.service_id())
+            .ok_or_else(|| format_err!("Service not found."))?;
+        ensure!(
+            deployment::is_deployed(snapshot, service.as_ref()),
+            "Service <{}> is not deployed yet.",
+            service.service_name()
+        );
         service.tx_from_raw(raw)
     }
 
@@ -161,6 +412,18 @@ impl Blockchain {
         self.db.merge(patch)
     }
 
+    /// Writes a consistent read-only copy of the storage to the specified path, which
+    /// should not exist. The copy can be opened by external processes, such as analytics
+    /// engines, without stopping the node.
+    ///
+    /// Returns the height of the latest block committed before the copy was made. Blocks are
+    /// committed atomically, so the copy contains this block and possibly a few later ones.
+    pub fn create_checkpoint(&self, path: &Path) -> StorageResult<Height> {
+        let height = Schema::new(&self.snapshot()).height();
+        self.db.create_checkpoint(path)?;
+        Ok(height)
+    }
+
     /// Returns the hash of the latest committed block.
     ///
     /// # Panics
@@ -191,17 +454,51 @@ impl Blockchain {
     /// * If the genesis block was not committed.
     /// * If storage version is not specified or not supported.
     pub fn initialize(&mut self, cfg: GenesisConfig) -> Result<(), failure::Error> {
+        self.initialize_with_state(cfg, GenesisState::default())
+    }
+
+    /// Creates and commits the genesis block with the given genesis configuration
+    /// and the pre-populated state if the blockchain has not been initialized.
+    ///
+    /// The state is imported by the services before the genesis block is created,
+    /// so the genesis block commits to the imported state. The state is ignored if
+    /// the blockchain already has the genesis block.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state dump refers to an unknown service, if a service
+    /// fails to import its state, or if the root hashes of the imported state do not
+    /// match the expected ones.
+    pub fn initialize_with_state(
+        &mut self,
+        cfg: GenesisConfig,
+        state: GenesisState,
+    ) -> Result<(), failure::Error> {
         let has_genesis_block = !Schema::new(&self.snapshot())
             .block_hashes_by_height()
             .is_empty();
         if !has_genesis_block {
-            self.create_genesis_block(cfg)?;
+            self.create_genesis_block(cfg, state)?;
         }
         Ok(())
     }
 
     /// Creates and commits the genesis block with the given genesis configuration.
-    fn create_genesis_block(&mut self, cfg: GenesisConfig) -> Result<(), failure::Error> {
+    fn create_genesis_block(
+        &mut self,
+        cfg: GenesisConfig,
+        mut state: GenesisState,
+    ) -> Result<(), failure::Error> {
+        for name in state.services.keys() {
+            if !self
+                .service_map
+                .values()
+                .any(|service| service.service_name() == name && !service.is_deployable())
+            {
+                bail!("Genesis state contains a dump for unknown service <{}>", name);
+            }
+        }
+
         let mut config_propose = StoredConfiguration {
             previous_cfg_hash: Hash::zero(),
             actual_from: Height::zero(),
@@ -213,16 +510,23 @@ impl Blockchain {
         let patch = {
             let fork = self.fork();
             // Update service tables
-            for (_, service) in self.service_map.iter() {
+            let services = self
+                .service_map
+                .values()
+                .filter(|service| !service.is_deployable());
+            for service in services {
                 let cfg = service.initialize(&fork);
+                migration::record_initial_version(&fork, service.as_ref(), Height::zero());
                 let name = service.service_name();
                 if config_propose.services.contains_key(name) {
-                    panic!(
-                        "Services already contain service with '{}' name, please change it",
-                        name
-                    );
+                    return Err(BlockchainBuildError::DuplicateServiceName(name.to_owned()).into());
                 }
                 config_propose.services.insert(name.into(), cfg);
+                features::check_declared(&config_propose, service.as_ref())?;
+
+                if let Some(dump) = state.services.remove(name) {
+                    import_service_state(service.as_ref(), &fork, &dump)?;
+                }
             }
             // Commit actual configuration
             {
@@ -231,6 +535,7 @@ impl Blockchain {
                     // TODO create genesis block for MemoryDB and compare it hash with zero block. (ECR-1630)
                     return Ok(());
                 }
+                schema.set_network_id(cfg.network_id);
                 schema.commit_configuration(config_propose);
             };
             self.merge(fork.into_patch())?;
@@ -246,28 +551,91 @@ impl Blockchain {
         Ok(())
     }
 
-    /// Helper function to map a tuple (`u16`, `u16`) of service table coordinates
-    /// to a 32-byte value to be used as the `ProofMapIndex` key (it currently
-    /// supports only fixed size keys). The `hash` function is used to distribute
-    /// keys uniformly (compared to padding).
-    /// # Arguments
-    ///
-    /// * `service_id` - `service_id` as returned by instance of type of
-    /// `Service` trait
-    /// * `table_idx` - index of service table in `Vec`, returned by the
-    /// `state_hash` method of instance of type of `Service` trait
-    // also, it was the first idea around, to use `hash`
-    pub fn service_table_unique_key(service_id: u16, table_idx: usize) -> Hash {
-        debug_assert!(table_idx <= u16::max_value() as usize);
-        let size = mem::size_of::<u16>();
-        let mut vec = vec![0; 2 * size];
-        LittleEndian::write_u16(&mut vec[0..size], service_id);
-        LittleEndian::write_u16(&mut vec[size..2 * size], table_idx as u16);
-        crypto::hash(&vec)
+    /// Returns the key of the table with the given full name in the `ProofMapIndex`
+    /// aggregating the state hash. The key is the hash of the name, so the proofs
+    /// to the tables can be checked by light clients knowing only the table names,
+    /// regardless of the order in which the services return their tables.
+    pub fn state_table_key(name: &str) -> Hash {
+        crypto::hash(name.as_bytes())
+    }
+
+    /// Returns the core and service tables aggregated into the state hash
+    /// in the given state.
+    pub fn state_tables(&self, snapshot: &dyn Snapshot) -> Vec<StateTable> {
+        let mut tables = core_state_tables(Schema::new(snapshot));
+        tables.extend(self.service_state_tables(snapshot));
+        tables
+    }
+
+    /// Returns the tables of the deployed services aggregated into the state hash.
+    fn service_state_tables(&self, snapshot: &dyn Snapshot) -> Vec<StateTable> {
+        let mut tables = Vec::new();
+        for service in self.deployed_services(snapshot) {
+            let service_tables = service.state_hash_tables(snapshot);
+            tables.extend(service_tables.into_iter().map(|(name, hash)| StateTable {
+                service_id: service.service_id(),
+                service_name: service.service_name().to_owned(),
+                name,
+                hash,
+            }));
+        }
+        tables
+    }
+
+    /// Adds the additional block headers of the active services. An entry whose key
+    /// has already been set by the core or by a service with a smaller ID is skipped.
+    fn add_service_block_headers(&self, snapshot: &dyn Snapshot, headers: &mut AdditionalHeaders) {
+        for service in self.active_services(snapshot) {
+            for (key, value) in service.block_headers(snapshot).iter() {
+                if headers.contains(key) {
+                    error!(
+                        "{} service sets the block header {} already set by another service",
+                        service.service_name(),
+                        key
+                    );
+                    continue;
+                }
+                headers.insert_raw(key, value.to_vec());
+            }
+        }
+    }
+
+    /// Records the hashes of the execution results of the block transactions and
+    /// returns the root hash of the results, or `None` if the block has no transactions.
+    fn record_transaction_results(fork: &Fork, height: Height) -> Option<Hash> {
+        let schema = Schema::new(fork);
+        let transactions = schema.block_transactions(height);
+        if transactions.is_empty() {
+            return None;
+        }
+        let results = schema.transaction_results();
+        let mut result_hashes = schema.block_transaction_results(height);
+        for tx_hash in transactions.iter() {
+            let result = results
+                .get(&tx_hash)
+                .expect("Execution result of a block transaction is not recorded");
+            result_hashes.push(result.object_hash());
+        }
+        Some(result_hashes.object_hash())
     }
 
     #[doc(hidden)]
     pub fn broadcast_raw_transaction(&self, tx: RawTransaction) -> Result<(), failure::Error> {
+        self.broadcast_raw_transaction_with_keypair(
+            tx,
+            self.service_keypair.0,
+            &self.service_keypair.1,
+        )
+    }
+
+    /// Signs the transaction with the given keypair instead of the service keypair
+    /// of the node and broadcasts it to other nodes.
+    pub fn broadcast_raw_transaction_with_keypair(
+        &self,
+        tx: RawTransaction,
+        public_key: PublicKey,
+        secret_key: &SecretKey,
+    ) -> Result<(), failure::Error> {
         let service_id = tx.service_id();
         if !self.service_map.contains_key(&service_id) {
             return Err(format_err!(
@@ -275,11 +643,20 @@ impl Blockchain {
                 service_id
             ));
         }
-        let msg = Message::sign_transaction(
+        if !self.is_service_active(self.snapshot().as_ref(), service_id) {
+            return Err(format_err!(
+                "Unable to broadcast transaction: service with ID={} is not deployed yet \
+                 or is inactive",
+                service_id
+            ));
+        }
+        let network_id = Schema::new(&self.snapshot()).network_id();
+        let msg = Message::sign_transaction_for_network(
             tx.service_transaction(),
             service_id,
-            self.service_keypair.0,
-            &self.service_keypair.1,
+            network_id,
+            public_key,
+            secret_key,
         );
 
         self.api_sender.broadcast_transaction(msg)
@@ -288,66 +665,151 @@ impl Blockchain {
     /// Executes the given transactions from the pool.
     /// Then collects the resulting changes from the current storage state and returns them
     /// with the hash of the resulting block.
+    ///
+    /// If `parallel_execution` is enabled in the consensus configuration, the transactions
+    /// of different services are executed in parallel, while the transactions of each service
+    /// are executed in the block order. The block is executed sequentially if it activates
+    /// a configuration, or if the transactions of different services have changed the same
+    /// index. The outcome depends only on the block, so it is the same on all nodes.
+    ///
+    /// The block is created without the consensus time; see `create_patch_with_time`.
     pub fn create_patch(
         &self,
         proposer_id: ValidatorId,
         height: Height,
         tx_hashes: &[Hash],
         tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> (Hash, Patch) {
+        self.create_patch_with_time(proposer_id, height, None, tx_hashes, tx_cache)
+    }
+
+    /// Executes the given transactions from the pool like `create_patch`, recording
+    /// the given consensus time of the block.
+    ///
+    /// The time is computed from the precommits for the previous block included into
+    /// the `Propose` the block is created from (see `Schema::next_block_time`), so that it is
+    /// the same on all nodes. It is stored in the block header (see `Block::time`)
+    /// and in the `block_times` table, which is a part of the state hash, before
+    /// the transactions are executed, so that the services can read it.
+    pub fn create_patch_with_time(
+        &self,
+        proposer_id: ValidatorId,
+        height: Height,
+        time: Option<DateTime<Utc>>,
+        tx_hashes: &[Hash],
+        tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
     ) -> (Hash, Patch) {
         // Create fork
         let mut fork = self.fork();
+        // The changes made before the transactions are passed to the parallel lanes.
+        fork.start_recording();
+        if let Some(time) = time {
+            Schema::new(&fork).block_times().put(&height.0, time);
+        }
 
         let block_hash = {
             // Get last hash.
             let last_hash = self.last_hash();
+            let activated_config = Schema::new(&fork).configuration_activated_at(height);
+            // Deploy the services included into the configuration activated at this height
+            // and notify the deployed services about the configuration.
+            if height > Height(0) {
+                if let Some(ref config) = activated_config {
+                    let pending = deployment::pending_services(&fork, &self.service_map, config);
+                    for service in pending {
+                        deploy(service, &mut fork, height);
+                    }
+                    lifecycle::update_states(&mut fork, &self.service_map, config, height);
+                    let mut services = self.deployed_services(fork.snapshot());
+                    services.sort_by_key(|service| service.service_id());
+                    migration::migrate_services(&mut fork, &services, config, height);
+                    for service in self.active_services(fork.snapshot()) {
+                        let namespace = self.namespaces.get(&service.service_id());
+                        on_config_change(service, namespace, &mut fork, config);
+                    }
+                }
+                for service in self.active_services(fork.snapshot()) {
+                    let namespace = self.namespaces.get(&service.service_id());
+                    before_transactions(service, namespace, &mut fork);
+                }
+            }
             // Save & execute transactions.
-            for (index, hash) in tx_hashes.iter().enumerate() {
-                self.execute_transaction(*hash, height, index, &mut fork, tx_cache)
-                    // Execution could fail if the transaction
-                    // cannot be deserialized or it isn't in the pool.
-                    .expect("Transaction execution error.");
+            let config = Schema::new(&fork).configuration_at(height);
+            let consensus = config
+                .as_ref()
+                .map(|config| config.consensus.clone())
+                .unwrap_or_default();
+            let mut budget = BlockBudget::new(&consensus);
+            // The transactions generated by the services are present in the list if
+            // the block has been received from a peer; they are generated again below.
+            let tx_hashes = &tx_hashes[..self.proposed_tx_count(tx_hashes, &fork, tx_cache)];
+            fork.flush();
+            let block_changes = fork.stop_recording().expect("Recording is started");
+            // The genesis block is always executed sequentially.
+            let executed_in_parallel = height > Height(0)
+                && consensus.parallel_execution
+                && activated_config.is_none()
+                && self.execute_in_parallel(
+                    height,
+                    tx_hashes,
+                    &block_changes,
+                    &mut budget,
+                    &mut fork,
+                    tx_cache,
+                );
+            if !executed_in_parallel {
+                // The transactions executed in the previous rounds at this height
+                // are not executed again. See `execution_cache` for details.
+                let cache = &self.execution_cache;
+                let pool_len = Schema::new(&fork).transactions_pool_len();
+                let mut state = cache.start_block(&last_hash, height, pool_len, &block_changes);
+                for (index, &hash) in tx_hashes.iter().enumerate() {
+                    if !cache.replay(&state, &hash, &mut budget, &mut fork, tx_cache) {
+                        fork.start_recording();
+                        // Execution could fail if the transaction
+                        // cannot be deserialized or it isn't in the pool.
+                        self.execute_transaction(
+                            hash,
+                            height,
+                            index,
+                            &mut budget,
+                            &mut fork,
+                            tx_cache,
+                        )
+                        .expect("Transaction execution error.");
+                        let changes = fork.stop_recording().expect("Recording is started");
+                        cache.insert(&state, &hash, changes, &budget);
+                    }
+                    state = ExecutionCache::next_state(&state, &hash);
+                }
             }
+            if height > Height(0) {
+                self.execute_service_transactions(height, &mut fork, tx_cache);
+                self.reward_proposer(proposer_id, height, config.as_ref(), &fork);
+            }
+            // Aggregate the oracle feeds observed in the block.
+            oracle::aggregate_feeds(&fork, &self.oracle_feeds, height);
 
             // Invoke execute method for all services.
-            for service in self.service_map.values() {
+            for service in self.active_services(fork.snapshot()) {
                 // Skip execution for genesis block.
                 if height > Height(0) {
-                    before_commit(service.as_ref(), &mut fork);
+                    let namespace = self.namespaces.get(&service.service_id());
+                    before_commit(service, namespace, &mut fork);
                 }
             }
 
             // Get tx & state hash.
             let (tx_hash, state_hash) = {
-                let state_hashes = {
-                    let schema = Schema::new(&fork);
-
-                    let vec_core_state = schema.core_state_hash();
-                    let mut state_hashes = Vec::new();
-
-                    for (idx, core_table_hash) in vec_core_state.into_iter().enumerate() {
-                        let key = Self::service_table_unique_key(CORE_SERVICE, idx);
-                        state_hashes.push((key, core_table_hash));
-                    }
-
-                    for service in self.service_map.values() {
-                        let service_id = service.service_id();
-                        let vec_service_state = service.state_hash((&fork).snapshot());
-                        for (idx, service_table_hash) in vec_service_state.into_iter().enumerate() {
-                            let key = Self::service_table_unique_key(service_id, idx);
-                            state_hashes.push((key, service_table_hash));
-                        }
-                    }
-
-                    state_hashes
-                };
+                let mut state_tables = core_state_tables(Schema::new(&fork));
+                state_tables.extend(self.service_state_tables(fork.snapshot()));
 
                 let schema = Schema::new(&fork);
 
                 let state_hash = {
                     let mut sum_table = schema.state_hash_aggregator();
-                    for (key, hash) in state_hashes {
-                        sum_table.put(&key, hash)
+                    for table in state_tables {
+                        sum_table.put(&Self::state_table_key(&table.name), table.hash)
                     }
                     sum_table.object_hash()
                 };
@@ -357,15 +819,32 @@ impl Blockchain {
                 (tx_hash, state_hash)
             };
 
+            // Collect the additional headers of the core and the services.
+            let mut additional_headers = AdditionalHeaders::new();
+            if let Some(results_hash) = Self::record_transaction_results(&fork, height) {
+                additional_headers.insert::<TxResultsHash>(results_hash);
+            }
+            if let Some(events_hash) = events::events_hash(&fork, height) {
+                additional_headers.insert::<EventsHash>(events_hash);
+            }
+            if let Some(time) = time {
+                additional_headers.insert::<BlockTime>(time);
+            }
+            if height > Height(0) {
+                self.add_service_block_headers(fork.snapshot(), &mut additional_headers);
+            }
+
             // Create block.
+            let tx_count = Schema::new(&fork).block_transactions(height).len();
             let block = Block::new(
                 proposer_id,
                 height,
-                tx_hashes.len() as u32,
+                tx_count as u32,
                 &last_hash,
                 &tx_hash,
                 &state_hash,
-            );
+            )
+            .with_additional_headers(additional_headers);
             trace!("execute block = {:?}", block);
             // Calculate block hash.
             let block_hash = block.hash();
@@ -374,10 +853,25 @@ impl Blockchain {
             schema.block_hashes_by_height().push(block_hash);
             // Save block.
             schema.blocks().put(&block_hash, block);
+            if let Some(config) = activated_config {
+                schema
+                    .config_activations()
+                    .put(&config.hash(), ConfigActivation::new(height, block_hash));
+            }
 
             block_hash
         };
 
+        let patch = fork.into_patch();
+        if !self.record_patch_hashes {
+            return (block_hash, patch);
+        }
+        // The hash is stored in the patch it is computed for, so it does not cover itself.
+        let changes_hash = patch.changes_hash();
+        let fork = Fork::from(patch);
+        Schema::new(&fork)
+            .patch_hashes()
+            .put(&height.0, changes_hash);
         (block_hash, fork.into_patch())
     }
 
@@ -386,45 +880,167 @@ impl Blockchain {
         tx_hash: Hash,
         height: Height,
         index: usize,
+        budget: &mut BlockBudget,
         fork: &mut Fork,
         tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
     ) -> Result<(), failure::Error> {
-        let (tx, raw, service_name) = {
-            let new_fork = &*fork;
-            let snapshot = new_fork.snapshot();
-            let schema = Schema::new(snapshot);
+        let (tx, raw, service_name) =
+            self.prepare_transaction(tx_hash, fork.snapshot(), tx_cache)?;
+        let size = message_size(&raw);
+        let state = lifecycle::service_state(fork.snapshot(), raw.service_id());
+        let outcome = if !state.accepts_transactions() {
+            info!(
+                "Service <{}>: {:?} transaction is not executed, the service is {}",
+                service_name, tx_hash, state
+            );
+            TransactionOutcome::rejected(TransactionError::service_inactive(None))
+        } else if budget.fits(size) {
+            let outcome =
+                self.run_transaction(tx.as_ref(), &raw, service_name, budget.gas_limit(), fork);
+            budget.consume(size, outcome.gas_used);
+            outcome
+        } else {
+            info!(
+                "Service <{}>: {:?} transaction does not fit into the block",
+                service_name, tx_hash
+            );
+            TransactionOutcome::rejected(TransactionError::block_limit_exceeded(None))
+        };
+        Self::record_transaction(tx_hash, raw, outcome, height, index, fork, tx_cache);
+        Ok(())
+    }
 
-            let raw = get_tx(&tx_hash, &schema.transactions(), &tx_cache).ok_or_else(|| {
+    /// Returns the number of the transactions in the list preceding the first transaction
+    /// signed with the system key.
+    fn proposed_tx_count(
+        &self,
+        tx_hashes: &[Hash],
+        fork: &Fork,
+        tx_cache: &BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> usize {
+        let schema = Schema::new(fork);
+        let transactions = schema.transactions();
+        tx_hashes
+            .iter()
+            .position(|hash| {
+                get_tx(hash, &transactions, tx_cache)
+                    .map_or(false, |raw| system::is_system_transaction(&raw))
+            })
+            .unwrap_or_else(|| tx_hashes.len())
+    }
+
+    /// Executes the transactions generated by the services in `after_transactions`
+    /// and appends them to the block. The services are invoked in the order of their IDs,
+    /// so that the transactions are appended in the same order on all nodes.
+    fn execute_service_transactions(
+        &self,
+        height: Height,
+        fork: &mut Fork,
+        tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
+    ) {
+        let mut services = self.active_services(fork.snapshot());
+        services.sort_by_key(|service| service.service_id());
+        for service in services {
+            let namespace = self.namespaces.get(&service.service_id());
+            for tx in after_transactions(service, namespace, fork) {
+                let raw = system::sign_system_transaction(service.service_id(), tx);
+                let tx_hash = raw.hash();
+                let index = {
+                    let schema = Schema::new(&*fork);
+                    if schema.transactions_locations().contains(&tx_hash) {
+                        error!(
+                            "Service <{}>: generated transaction {:?} has already been committed",
+                            service.service_name(),
+                            tx_hash
+                        );
+                        continue;
+                    }
+                    schema.block_transactions(height).len() as usize
+                };
+                let outcome = match self.tx_from_raw_at(raw.payload().clone(), fork.snapshot()) {
+                    Ok(tx) => {
+                        self.run_transaction(tx.as_ref(), &raw, service.service_name(), None, fork)
+                    }
+                    Err(e) => {
+                        error!(
+                            "Service <{}>: cannot parse generated transaction {:?}: {}",
+                            service.service_name(),
+                            tx_hash,
+                            e
+                        );
+                        continue;
+                    }
+                };
+                Self::record_transaction(tx_hash, raw, outcome, height, index, fork, tx_cache);
+            }
+        }
+    }
+
+    /// Looks up the transaction in the storage and deserializes it.
+    fn prepare_transaction(
+        &self,
+        tx_hash: Hash,
+        snapshot: &dyn Snapshot,
+        tx_cache: &BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> Result<(Box<dyn Transaction>, Signed<RawTransaction>, &str), failure::Error> {
+        let schema = Schema::new(snapshot);
+
+        let raw = get_tx(&tx_hash, &schema.transactions(), &tx_cache).ok_or_else(|| {
+            failure::err_msg(format!(
+                "BUG: Cannot find transaction in database. tx: {:?}",
+                tx_hash
+            ))
+        })?;
+
+        let service_name = self
+            .service_map
+            .get(&raw.service_id())
+            .ok_or_else(|| {
                 failure::err_msg(format!(
-                    "BUG: Cannot find transaction in database. tx: {:?}",
-                    tx_hash
+                    "Service not found. Service id: {}",
+                    raw.service_id()
                 ))
-            })?;
+            })?
+            .service_name();
 
-            let service_name = self
-                .service_map
-                .get(&raw.service_id())
-                .ok_or_else(|| {
-                    failure::err_msg(format!(
-                        "Service not found. Service id: {}",
-                        raw.service_id()
-                    ))
-                })?
-                .service_name();
-
-            let tx = self.tx_from_raw(raw.payload().clone()).map_err(|error| {
+        let tx = self
+            .tx_from_raw_at(raw.payload().clone(), snapshot)
+            .map_err(|error| {
                 format_err!("Service <{}>: {}, tx: {:?}", service_name, error, tx_hash)
             })?;
 
-            (tx, raw, service_name)
-        };
+        Ok((tx, raw, service_name))
+    }
 
+    /// Executes the transaction, discarding its changes if it fails.
+    fn run_transaction(
+        &self,
+        tx: &dyn Transaction,
+        raw: &Signed<RawTransaction>,
+        service_name: &str,
+        gas_limit: Option<u64>,
+        fork: &mut Fork,
+    ) -> TransactionOutcome {
+        let tx_hash = raw.hash();
+        let gas_meter = Rc::new(GasMeter::new(gas_limit));
+        let mut fee_paid = 0;
         let catch_result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
-            let context = TransactionContext::new(&*fork, service_name, &raw);
-            tx.execute(context)
+            gas_meter.charge(message_size(raw));
+            // Transactions generated by the services are authorized by the services
+            // themselves and are free.
+            if !system::is_system_transaction(raw) {
+                self.authorize(raw, fork.snapshot())?;
+                fee_paid = self.charge_fee(tx, raw, fork)?;
+            }
+            let dispatcher = CallDispatcher::new(&self.service_map);
+            let context = TransactionContext::new(&*fork, service_name, raw)
+                .with_gas_meter(Rc::clone(&gas_meter))
+                .with_dispatcher(&dispatcher);
+            tx.execute(context).map_err(TransactionError::from)?;
+            self.check_namespace(raw.service_id(), &dispatcher.called(), fork)
         }));
 
-        let tx_result = TransactionResult(match catch_result {
+        let result = TransactionResult(match catch_result {
             Ok(execution_result) => {
                 if let Err(ref e) = execution_result {
                     // Unlike panic, transaction failure isn't that rare, so logging the
@@ -435,7 +1051,7 @@ impl Blockchain {
                     );
                     fork.rollback();
                 }
-                execution_result.map_err(TransactionError::from)
+                execution_result
             }
             Err(err) => {
                 if err.is::<StorageError>() {
@@ -443,24 +1059,146 @@ impl Blockchain {
                     panic::resume_unwind(err);
                 }
                 fork.rollback();
-                error!(
-                    "Service <{}>: {:?} transaction execution panicked: {:?}",
-                    service_name, tx, err
-                );
-
-                Err(TransactionError::from_panic(&err))
+                if let Some(out_of_gas) = err.downcast_ref::<OutOfGas>() {
+                    info!(
+                        "Service <{}>: {:?} transaction ran out of gas: {}",
+                        service_name, tx_hash, out_of_gas
+                    );
+                    Err(TransactionError::out_of_gas(Some(out_of_gas.to_string())))
+                } else {
+                    error!(
+                        "Service <{}>: {:?} transaction execution panicked: {:?}",
+                        service_name, tx, err
+                    );
+                    Err(TransactionError::from_panic(&err))
+                }
             }
         });
+        TransactionOutcome {
+            result,
+            gas_used: gas_meter.used(),
+            fee_paid,
+        }
+    }
+
+    /// Charges the fee of the transaction from its author if the fees are enabled.
+    /// The fee is flushed into the fork, so that it is kept if the transaction fails.
+    fn charge_fee(
+        &self,
+        tx: &dyn Transaction,
+        raw: &Signed<RawTransaction>,
+        fork: &mut Fork,
+    ) -> Result<u64, TransactionError> {
+        let fee = tx.fee();
+        let handler = match self.fee_handler {
+            Some(ref handler) if fee > 0 => handler,
+            _ => return Ok(0),
+        };
+        handler
+            .charge(fork, &raw.author(), fee)
+            .map_err(|e| TransactionError::insufficient_fee(Some(e.to_string())))?;
+        fork.flush();
+        Ok(fee)
+    }
 
+    /// Credits the fees collected in the block to the service key of its proposer.
+    fn reward_proposer(
+        &self,
+        proposer_id: ValidatorId,
+        height: Height,
+        config: Option<&StoredConfiguration>,
+        fork: &Fork,
+    ) {
+        let handler = match self.fee_handler {
+            Some(ref handler) => handler,
+            None => return,
+        };
+        let schema = Schema::new(fork);
+        let fees = schema.transaction_fees();
+        let collected = schema
+            .block_transactions(height)
+            .iter()
+            .filter_map(|tx_hash| fees.get(&tx_hash))
+            .fold(0, u64::saturating_add);
+        if collected == 0 {
+            return;
+        }
+        match config.and_then(|config| config.validator_keys.get(proposer_id.0 as usize)) {
+            Some(keys) => handler.reward(fork, &keys.service_key, collected),
+            None => error!(
+                "Cannot reward unknown proposer {} of block {}",
+                proposer_id, height
+            ),
+        }
+    }
+
+    /// Stores the executed transaction with its outcome and location in the block.
+    fn record_transaction(
+        tx_hash: Hash,
+        raw: Signed<RawTransaction>,
+        outcome: TransactionOutcome,
+        height: Height,
+        index: usize,
+        fork: &mut Fork,
+        tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
+    ) {
         let mut schema = Schema::new(&*fork);
-        schema.transaction_results().put(&tx_hash, tx_result);
+        schema.transaction_results().put(&tx_hash, outcome.result);
+        schema.transaction_gas().put(&tx_hash, outcome.gas_used);
+        if outcome.fee_paid > 0 {
+            schema.transaction_fees().put(&tx_hash, outcome.fee_paid);
+        }
         schema.commit_transaction(&tx_hash, raw);
         tx_cache.remove(&tx_hash);
         schema.block_transactions(height).push(tx_hash);
         let location = TxLocation::new(height, index as u64);
         schema.transactions_locations().put(&tx_hash, location);
         fork.flush();
-        Ok(())
+    }
+
+    /// Checks that the transaction has changed only the tables allowed by the namespace
+    /// of its service or by the namespaces of the services it has called.
+    fn check_namespace(
+        &self,
+        service_id: u16,
+        called: &BTreeSet<u16>,
+        fork: &Fork,
+    ) -> Result<(), TransactionError> {
+        let mut namespaces = Vec::with_capacity(called.len() + 1);
+        for id in iter::once(service_id).chain(called.iter().cloned()) {
+            match self.namespaces.get(&id) {
+                Some(namespace) => namespaces.push((id, namespace)),
+                // The tables changed on behalf of a service without a namespace
+                // cannot be told apart, so the writes are not restricted.
+                None => return Ok(()),
+            }
+        }
+        namespace::check_joint_writes(&namespaces, fork)
+            .map_err(|e| TransactionError::panic(Some(e.to_string())))
+    }
+
+    /// Checks the transaction author against the access control list of the service.
+    fn authorize(
+        &self,
+        raw: &Signed<RawTransaction>,
+        snapshot: &dyn Snapshot,
+    ) -> Result<(), TransactionError> {
+        let service_id = raw.service_id();
+        let transaction_id = raw.payload().transaction_id();
+        let acl = match self.access_control.get(&service_id) {
+            Some(acl) => acl,
+            None => return Ok(()),
+        };
+        if acl.is_authorized(snapshot, transaction_id, &raw.author()) {
+            Ok(())
+        } else {
+            Err(TransactionError::unauthorized(Some(format!(
+                "Key {:?} is not allowed to send transaction {} of service {}",
+                raw.author(),
+                transaction_id,
+                service_id
+            ))))
+        }
     }
 
     /// Commits to the blockchain a new block with the indicated changes (patch),
@@ -476,45 +1214,84 @@ impl Blockchain {
     where
         I: Iterator<Item = Signed<Precommit>>,
     {
-        let patch = {
-            let fork: Fork = patch.into();
+        let patch = self.prepare_commit(patch, block_hash, precommits, tx_cache);
+        self.merge(patch)?;
+        self.notify_services_about_commit();
+        Ok(())
+    }
 
-            {
-                let mut schema = Schema::new(&fork);
-                schema.precommits(&block_hash).extend(precommits);
+    /// Adds the precommits and the cached transactions to the block patch. The node
+    /// merges the returned patch concurrently with broadcasting its status, and then
+    /// calls `notify_services_about_commit`.
+    pub(crate) fn prepare_commit<I>(
+        &self,
+        patch: Patch,
+        block_hash: Hash,
+        precommits: I,
+        tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> Patch
+    where
+        I: Iterator<Item = Signed<Precommit>>,
+    {
+        let fork: Fork = patch.into();
+
+        {
+            let mut schema = Schema::new(&fork);
+            schema.precommits(&block_hash).extend(precommits);
 
-                // Consensus messages cache is useful only during one height, so it should be
-                // cleared when a new height is achieved.
-                schema.consensus_messages_cache().clear();
-                let txs_in_block = schema.last_block().tx_count();
+            // Consensus messages cache is useful only during one height, so it should be
+            // cleared when a new height is achieved.
+            schema.consensus_messages_cache().clear();
+            let txs_in_block = schema.last_block().tx_count();
 
-                schema.update_transaction_count(u64::from(txs_in_block));
+            schema.update_transaction_count(u64::from(txs_in_block));
 
-                let tx_hashes = tx_cache.keys().cloned().collect::<Vec<Hash>>();
-                for tx_hash in tx_hashes {
-                    if let Some(tx) = tx_cache.remove(&tx_hash) {
-                        if !schema.transactions().contains(&tx_hash) {
-                            schema.add_transaction_into_pool(tx);
-                        }
+            let tx_hashes = tx_cache.keys().cloned().collect::<Vec<Hash>>();
+            for tx_hash in tx_hashes {
+                if let Some(tx) = tx_cache.remove(&tx_hash) {
+                    if !schema.transactions().contains(&tx_hash) {
+                        schema.add_transaction_into_pool(tx);
                     }
                 }
             }
-            fork.into_patch()
-        };
-        self.merge(patch)?;
+        }
+        fork.into_patch()
+    }
 
-        // Invokes `after_commit` for each service in order of their identifiers
-        for (service_id, service) in self.service_map.iter() {
-            let context = ServiceContext::new(
+    /// Invokes `after_commit` and `after_commit_block` for each service in order
+    /// of their identifiers, and then notifies the commit subscribers.
+    pub(crate) fn notify_services_about_commit(&self) {
+        // All services observe the same snapshot pinned at the committed block.
+        let snapshot: Arc<dyn Snapshot> = Arc::from(self.snapshot());
+        let services = self.active_services(snapshot.as_ref());
+        for service in &services {
+            let context = ServiceContext::with_snapshot(
                 self.service_keypair.0,
                 self.service_keypair.1.clone(),
                 self.api_sender.clone(),
-                self.fork(),
-                *service_id,
+                Arc::clone(&snapshot),
+                service.service_id(),
             );
             service.after_commit(&context);
         }
-        Ok(())
+
+        let committed = BlockCommitted::from_snapshot(snapshot.as_ref());
+        for service in &services {
+            let context = ServiceContext::with_snapshot(
+                self.service_keypair.0,
+                self.service_keypair.1.clone(),
+                self.api_sender.clone(),
+                Arc::clone(&snapshot),
+                service.service_id(),
+            );
+            service.after_commit_block(&context, &committed.service_summary(service.service_id()));
+        }
+
+        // Subscribers with dropped receivers are removed.
+        self.commit_subscribers
+            .lock()
+            .expect("Commit subscribers lock is poisoned")
+            .retain(|subscriber| subscriber.send(committed.clone()).is_ok());
     }
 
     /// Saves the `Connect` message from a peer to the cache.
@@ -563,9 +1340,125 @@ impl Blockchain {
     }
 }
 
-fn before_commit(service: &dyn Service, fork: &mut Fork) {
+fn import_service_state(
+    service: &dyn Service,
+    fork: &Fork,
+    dump: &ServiceStateDump,
+) -> Result<(), failure::Error> {
+    let name = service.service_name();
+    service
+        .import_state(fork, &dump.data)
+        .map_err(|e| format_err!("Service <{}> failed to import genesis state: {}", name, e))?;
+
+    let state_hash = service.state_hash(fork.snapshot());
+    if state_hash != dump.state_hash {
+        bail!(
+            "Service <{}>: state hash of the imported genesis state does not match \
+             the expected one: actual {:?}, expected {:?}",
+            name,
+            state_hash,
+            dump.state_hash
+        );
+    }
+    info!(
+        "Service <{}>: imported genesis state with {} table(s)",
+        name,
+        state_hash.len()
+    );
+    Ok(())
+}
+
+/// Initializes the deployable service and records its deployment. If the service panics,
+/// its changes are discarded and the service remains undeployed.
+fn deploy(service: &dyn Service, fork: &mut Fork, height: Height) {
+    match panic::catch_unwind(panic::AssertUnwindSafe(|| service.initialize(fork))) {
+        Ok(..) => {
+            Schema::new(&*fork)
+                .deployed_services()
+                .put(&service.service_id(), height.0);
+            migration::record_initial_version(fork, service, height);
+            fork.flush();
+            info!(
+                "Service <{}> has been deployed at height {}",
+                service.service_name(),
+                height
+            );
+        }
+        Err(err) => {
+            if err.is::<StorageError>() {
+                // Continue panic unwind if the reason is StorageError.
+                panic::resume_unwind(err);
+            }
+            fork.rollback();
+            error!(
+                "{} service deployment failed with error: {:?}",
+                service.service_name(),
+                err
+            );
+        }
+    }
+}
+
+fn before_transactions(service: &dyn Service, namespace: Option<&Namespace>, fork: &mut Fork) {
+    // The changes made before the hook are not checked against the namespace.
+    fork.flush();
+    match panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        service.before_transactions(fork)
+    })) {
+        Ok(..) => {
+            flush_hook_changes(service, namespace, fork, "before_transactions");
+        }
+        Err(err) => {
+            if err.is::<StorageError>() {
+                // Continue panic unwind if the reason is StorageError.
+                panic::resume_unwind(err);
+            }
+            fork.rollback();
+            error!(
+                "{} service before_transactions failed with error: {:?}",
+                service.service_name(),
+                err
+            );
+        }
+    }
+}
+
+fn after_transactions(
+    service: &dyn Service,
+    namespace: Option<&Namespace>,
+    fork: &mut Fork,
+) -> Vec<ServiceTransaction> {
+    fork.flush();
+    match panic::catch_unwind(panic::AssertUnwindSafe(|| service.after_transactions(fork))) {
+        Ok(transactions) => {
+            if flush_hook_changes(service, namespace, fork, "after_transactions") {
+                transactions
+            } else {
+                Vec::new()
+            }
+        }
+        Err(err) => {
+            if err.is::<StorageError>() {
+                // Continue panic unwind if the reason is StorageError.
+                panic::resume_unwind(err);
+            }
+            fork.rollback();
+            error!(
+                "{} service after_transactions failed with error: {:?}",
+                service.service_name(),
+                err
+            );
+            Vec::new()
+        }
+    }
+}
+
+fn before_commit(service: &dyn Service, namespace: Option<&Namespace>, fork: &mut Fork) {
+    fork.flush();
     match panic::catch_unwind(panic::AssertUnwindSafe(|| service.before_commit(fork))) {
-        Ok(..) => fork.flush(),
+        Ok(..) => {
+            flush_hook_changes(service, namespace, fork, "before_commit");
+        }
         Err(err) => {
             if err.is::<StorageError>() {
                 // Continue panic unwind if the reason is StorageError.
@@ -581,6 +1474,95 @@ fn before_commit(service: &dyn Service, fork: &mut Fork) {
     }
 }
 
+fn on_config_change(
+    service: &dyn Service,
+    namespace: Option<&Namespace>,
+    fork: &mut Fork,
+    config: &StoredConfiguration,
+) {
+    fork.flush();
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        service.on_config_change(fork, config)
+    }));
+    match result {
+        Ok(..) => {
+            flush_hook_changes(service, namespace, fork, "on_config_change");
+        }
+        Err(err) => {
+            if err.is::<StorageError>() {
+                panic::resume_unwind(err);
+            }
+            fork.rollback();
+            error!(
+                "{} service on_config_change failed with error: {:?}",
+                service.service_name(),
+                err
+            );
+        }
+    }
+}
+
+/// Flushes the changes made by the hook of the service, or rolls them back if the service
+/// has changed the tables outside of its namespace. Returns `true` if the changes are flushed.
+fn flush_hook_changes(
+    service: &dyn Service,
+    namespace: Option<&Namespace>,
+    fork: &mut Fork,
+    hook: &str,
+) -> bool {
+    if let Some(namespace) = namespace {
+        if let Err(e) = namespace.check_writes(fork, service.service_id()) {
+            fork.rollback();
+            error!(
+                "{} service {} failed with error: {}",
+                service.service_name(),
+                hook,
+                e
+            );
+            return false;
+        }
+    }
+    fork.flush();
+    true
+}
+
+/// Outcome of the transaction execution.
+struct TransactionOutcome {
+    result: TransactionResult,
+    gas_used: u64,
+    fee_paid: u64,
+}
+
+impl TransactionOutcome {
+    /// Creates the outcome of a transaction that has not been executed.
+    fn rejected(error: TransactionError) -> Self {
+        Self {
+            result: TransactionResult(Err(error)),
+            gas_used: 0,
+            fee_paid: 0,
+        }
+    }
+}
+
+/// Returns the core tables aggregated into the state hash.
+fn core_state_tables<T: IndexAccess>(schema: Schema<T>) -> Vec<StateTable> {
+    schema
+        .core_state_tables()
+        .into_iter()
+        .map(|(name, hash)| StateTable {
+            service_id: CORE_SERVICE,
+            service_name: "core".to_owned(),
+            name,
+            hash,
+        })
+        .collect()
+}
+
+/// Returns the size of the transaction message, which is also its base gas cost.
+fn message_size(raw: &Signed<RawTransaction>) -> u64 {
+    raw.signed_message().raw().len() as u64
+}
+
 impl fmt::Debug for Blockchain {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Blockchain(..)")
@@ -592,8 +1574,16 @@ impl Clone for Blockchain {
         Self {
             db: Arc::clone(&self.db),
             service_map: Arc::clone(&self.service_map),
+            access_control: Arc::clone(&self.access_control),
+            namespaces: Arc::clone(&self.namespaces),
+            oracle_feeds: Arc::clone(&self.oracle_feeds),
+            transaction_archive: self.transaction_archive.clone(),
+            fee_handler: self.fee_handler.clone(),
             api_sender: self.api_sender.clone(),
             service_keypair: self.service_keypair.clone(),
+            record_patch_hashes: self.record_patch_hashes,
+            execution_cache: Arc::clone(&self.execution_cache),
+            commit_subscribers: Arc::clone(&self.commit_subscribers),
         }
     }
 }
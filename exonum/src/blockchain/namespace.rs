@@ -0,0 +1,194 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Confinement of the writes of a service to its own tables.
+//!
+//! A service may declare its [`Namespace`] with [`Service::namespace`]. The tables of
+//! the namespace are named with the namespace prefix followed by a dot, for example,
+//! `cryptocurrency.wallets`. The core checks the tables changed by the transactions of
+//! the service and by its `before_transactions`, `after_transactions`, `before_commit`
+//! and `on_config_change` hooks. If a transaction changes a table outside of the namespace,
+//! its changes are discarded and it is committed with the `Panic` status; the changes
+//! of such a hook are discarded as if the hook panicked.
+//!
+//! Tables of the core or other services can be changed only if they are explicitly
+//! granted with [`Namespace::grant`]; for example, the configuration service is granted
+//! the core tables storing the configurations. The core tables changed on behalf of the
+//! service, such as the counters of [`TransactionContext::next_id`], are always allowed.
+//! Services that do not declare a namespace are not restricted.
+//!
+//! [`Namespace`]: struct.Namespace.html
+//! [`Service::namespace`]: trait.Service.html#method.namespace
+//! [`Namespace::grant`]: struct.Namespace.html#method.grant
+//! [`TransactionContext::next_id`]: struct.TransactionContext.html#method.next_id
+
+use exonum_merkledb::{inspect, Fork};
+
+use std::collections::BTreeSet;
+
+use super::schema;
+
+/// Tables a service is allowed to change.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::blockchain::Namespace;
+///
+/// let namespace = Namespace::new("cryptocurrency").grant("exonum_time.time");
+/// assert_eq!(namespace.table_name("wallets"), "cryptocurrency.wallets");
+/// assert!(namespace.contains("cryptocurrency.wallets"));
+/// assert!(namespace.contains("exonum_time.time"));
+/// assert!(!namespace.contains("cryptocurrency_v2.wallets"));
+/// assert!(!namespace.contains("core.configs"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Namespace {
+    prefix: String,
+    shared_tables: BTreeSet<String>,
+}
+
+impl Namespace {
+    /// Creates a namespace with the given prefix and without shared tables.
+    pub fn new<S: Into<String>>(prefix: S) -> Self {
+        Self {
+            prefix: prefix.into(),
+            shared_tables: BTreeSet::new(),
+        }
+    }
+
+    /// Allows the service to change the table with the given full name, which is outside
+    /// of the namespace. All tables of the family with the given name are granted.
+    pub fn grant<S: Into<String>>(mut self, table: S) -> Self {
+        self.shared_tables.insert(table.into());
+        self
+    }
+
+    /// Allows the service to commit new configurations with [`Schema::commit_configuration`]
+    /// by granting it the core tables storing the configurations.
+    ///
+    /// [`Schema::commit_configuration`]: struct.Schema.html#method.commit_configuration
+    pub fn grant_configuration_tables(self) -> Self {
+        schema::configuration_tables()
+            .iter()
+            .fold(self, |namespace, &table| namespace.grant(table))
+    }
+
+    /// Returns the prefix of the namespace.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the names of the tables granted to the service.
+    pub fn shared_tables(&self) -> impl Iterator<Item = &str> {
+        self.shared_tables.iter().map(String::as_str)
+    }
+
+    /// Returns the full name of the table of the namespace.
+    pub fn table_name(&self, table: &str) -> String {
+        format!("{}.{}", self.prefix, table)
+    }
+
+    /// Checks whether the service is allowed to change the table with the given full name.
+    pub fn contains(&self, table: &str) -> bool {
+        let in_namespace =
+            table.starts_with(&self.prefix) && table[self.prefix.len()..].starts_with('.');
+        in_namespace || self.shared_tables.contains(table)
+    }
+
+    /// Checks the tables changed in the fork since the last flush. Returns an error
+    /// listing the tables the service is not allowed to change.
+    pub(crate) fn check_writes(&self, fork: &Fork, service_id: u16) -> Result<(), failure::Error> {
+        let mut foreign_tables = BTreeSet::new();
+        for index in inspect::modified_indexes(fork) {
+            if !self.contains(index.name()) && !schema::is_service_core_table(&index, service_id) {
+                foreign_tables.insert(index.name().to_owned());
+            }
+        }
+        ensure!(
+            foreign_tables.is_empty(),
+            "Tables {:?} are outside of the namespace `{}`",
+            foreign_tables,
+            self.prefix
+        );
+        Ok(())
+    }
+}
+
+/// Checks the tables changed in the fork since the last flush by a transaction which has
+/// called the methods of other services. Each table must be allowed to one of the services;
+/// `namespaces` contains the identifiers of the services with their namespaces, starting
+/// from the service of the transaction.
+pub(crate) fn check_joint_writes(
+    namespaces: &[(u16, &Namespace)],
+    fork: &Fork,
+) -> Result<(), failure::Error> {
+    if let [(service_id, namespace)] = namespaces {
+        return namespace.check_writes(fork, *service_id);
+    }
+    let mut foreign_tables = BTreeSet::new();
+    for index in inspect::modified_indexes(fork) {
+        let allowed = namespaces.iter().any(|&(service_id, namespace)| {
+            namespace.contains(index.name()) || schema::is_service_core_table(&index, service_id)
+        });
+        if !allowed {
+            foreign_tables.insert(index.name().to_owned());
+        }
+    }
+    ensure!(
+        foreign_tables.is_empty(),
+        "Tables {:?} are outside of the namespaces {:?}",
+        foreign_tables,
+        namespaces
+            .iter()
+            .map(|(_, namespace)| namespace.prefix())
+            .collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_merkledb::{Database, ListIndex, MapIndex, ProofMapIndex, TemporaryDB};
+
+    use super::*;
+    use crate::blockchain::Schema;
+
+    #[test]
+    fn writes_are_checked_against_namespace() {
+        let namespace = Namespace::new("acme").grant("shared.values");
+        let db = TemporaryDB::new();
+        let mut fork = db.fork();
+        MapIndex::new("acme.balances", &fork).put(&1_u64, 10_u64);
+        ListIndex::new_in_family("shared.values", &2_u8, &fork).push(1_u64);
+        Schema::new(&fork)
+            .id_counters(5)
+            .put(&"orders".to_owned(), 1);
+        assert!(namespace.check_writes(&fork, 5).is_ok());
+        fork.flush();
+
+        // Reading foreign tables is not restricted.
+        let _ = ProofMapIndex::<_, u64, u64>::new("acme_v2.balances", &fork).get(&1);
+        assert!(namespace.check_writes(&fork, 5).is_ok());
+
+        ProofMapIndex::new("acme_v2.balances", &fork).put(&1_u64, 10_u64);
+        Schema::new(&fork)
+            .id_counters(6)
+            .put(&"orders".to_owned(), 1);
+        let err = namespace.check_writes(&fork, 5).unwrap_err().to_string();
+        assert!(err.contains("acme_v2.balances"));
+        assert!(err.contains("core.id_counters"));
+        assert!(!err.contains("acme.balances"));
+    }
+}
@@ -0,0 +1,348 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! External data agreed upon by the validators.
+//!
+//! A service declares the feeds it needs by returning [`OracleFeed`]s from
+//! [`Service::oracle_feeds`]. Each validator submits its observations of a feed
+//! in a transaction of the service, which calls [`submit_observation`] with
+//! the observed value. When a block contains observations of a feed, the core
+//! aggregates the latest observations of the current validators and stores the result
+//! in the [`oracle_values`] table under the full feed name `service_name.feed_name`.
+//! The value is only updated if validators with more than `2/3` of the total voting weight
+//! have observed the feed recently; hence, a minority of faulty validators cannot control it.
+//!
+//! The table is a part of the core state hash, so that the aggregated values
+//! can be proven to light clients.
+//!
+//! [`OracleFeed`]: struct.OracleFeed.html
+//! [`Service::oracle_feeds`]: ../trait.Service.html#method.oracle_feeds
+//! [`submit_observation`]: fn.submit_observation.html
+//! [`oracle_values`]: ../struct.Schema.html#method.oracle_values
+
+use exonum_merkledb::{BinaryValue, Fork, IndexAccess};
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::{Schema, TransactionContext};
+use crate::{helpers::Height, proto};
+
+/// Default number of blocks during which an observation is taken into account.
+pub const DEFAULT_OBSERVATION_MAX_AGE: u64 = 10;
+
+/// Method of combining the observations of the validators into a single value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleAggregation {
+    /// Observations are `i64` numbers; the median of them weighted by the voting weights
+    /// of the validators is taken. If the weight is split evenly, the lower median is taken,
+    /// so that the value is always one of the observed ones.
+    Median,
+    /// The value observed by the validators with more than `2/3` of the total voting weight
+    /// is taken.
+    Majority,
+}
+
+/// External data feed declared by a service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OracleFeed {
+    name: String,
+    aggregation: OracleAggregation,
+    max_age: u64,
+}
+
+impl OracleFeed {
+    /// Creates a feed with the given name, unique within the service.
+    pub fn new<S: Into<String>>(name: S, aggregation: OracleAggregation) -> Self {
+        Self {
+            name: name.into(),
+            aggregation,
+            max_age: DEFAULT_OBSERVATION_MAX_AGE,
+        }
+    }
+
+    /// Sets the number of blocks during which an observation is taken into account.
+    pub fn with_max_age(mut self, max_age: u64) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Name of the feed.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Aggregation method of the feed.
+    pub fn aggregation(&self) -> OracleAggregation {
+        self.aggregation
+    }
+
+    /// Number of blocks during which an observation is taken into account.
+    pub fn max_age(&self) -> u64 {
+        self.max_age
+    }
+
+    /// Returns the name of the feed in the `oracle_values` table.
+    pub fn full_name(&self, service_name: &str) -> String {
+        format!("{}.{}", service_name, self.name)
+    }
+}
+
+/// Observation of a feed submitted by a validator.
+#[derive(Debug, Clone, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::OracleObservation", crate = "crate")]
+pub struct OracleObservation {
+    /// Height of the block that includes the observation.
+    pub height: Height,
+    /// Observed value.
+    pub value: Vec<u8>,
+}
+
+/// Aggregated value of a feed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::OracleValue", crate = "crate")]
+pub struct OracleValue {
+    /// Height of the block in which the value has been aggregated.
+    pub height: Height,
+    /// Aggregated value.
+    pub value: Vec<u8>,
+    /// Number of observations the value has been aggregated from.
+    pub observations: u32,
+}
+
+impl OracleValue {
+    /// Returns the value of a `Median` feed.
+    pub fn as_i64(&self) -> Option<i64> {
+        decode_number(&self.value)
+    }
+}
+
+/// Error returned when an observation cannot be submitted.
+#[derive(Debug, Clone, PartialEq, Fail)]
+pub enum OracleError {
+    /// The author of the transaction is not a validator.
+    #[fail(display = "Observations may only be submitted by the validators")]
+    NotValidator,
+    /// The value cannot be aggregated by the feed.
+    #[fail(display = "Observed value for feed {} should be an i64 number", _0)]
+    InvalidValue(String),
+}
+
+/// Records the observation of the feed made by the author of the transaction.
+///
+/// The observation replaces the previous observation of the author; the feed
+/// is aggregated when the block is created. Services usually convert the error
+/// into an `ExecutionError` with a code of their own.
+pub fn submit_observation(
+    context: &TransactionContext,
+    feed: &OracleFeed,
+    value: Vec<u8>,
+) -> Result<(), OracleError> {
+    let author = context.author();
+    let schema = Schema::new(context.fork());
+    let is_validator = schema
+        .actual_configuration()
+        .validator_keys
+        .iter()
+        .any(|keys| keys.service_key == author);
+    if !is_validator {
+        return Err(OracleError::NotValidator);
+    }
+    if feed.aggregation == OracleAggregation::Median && decode_number(&value).is_none() {
+        return Err(OracleError::InvalidValue(feed.name.clone()));
+    }
+
+    let full_name = feed.full_name(context.service_name());
+    let height = schema.height().next();
+    schema
+        .oracle_observations(&full_name)
+        .put(&author, OracleObservation { height, value });
+    schema.oracle_pending_feeds().insert(full_name);
+    Ok(())
+}
+
+/// Returns the aggregated value of the feed declared by the service.
+pub fn feed_value<T: IndexAccess>(
+    access: T,
+    service_name: &str,
+    feed: &OracleFeed,
+) -> Option<OracleValue> {
+    Schema::new(access)
+        .oracle_values()
+        .get(&feed.full_name(service_name))
+}
+
+/// Aggregates the feeds observed in the block at the given height.
+pub(crate) fn aggregate_feeds(fork: &Fork, feeds: &HashMap<String, OracleFeed>, height: Height) {
+    let schema = Schema::new(fork);
+    let pending = schema.oracle_pending_feeds().iter().collect::<Vec<_>>();
+    if pending.is_empty() {
+        return;
+    }
+
+    let config = schema.actual_configuration();
+    let quorum = config.majority_weight();
+
+    for full_name in pending {
+        let feed = match feeds.get(&full_name) {
+            Some(feed) => feed,
+            None => continue,
+        };
+        let observations = schema.oracle_observations(&full_name);
+        let values = config
+            .validator_keys
+            .iter()
+            .filter_map(|keys| Some((observations.get(&keys.service_key)?, keys.weight)))
+            .filter(|(observation, _)| observation.height.0 + feed.max_age > height.0)
+            .map(|(observation, weight)| (observation.value, weight))
+            .collect::<Vec<_>>();
+        let observed = values.len() as u32;
+        if let Some(value) = aggregate(feed.aggregation, values, quorum) {
+            let value = OracleValue {
+                height,
+                value,
+                observations: observed,
+            };
+            schema.oracle_values().put(&full_name, value);
+        }
+    }
+    schema.oracle_pending_feeds().clear();
+}
+
+/// Combines the observations paired with the voting weights of the validators, returning
+/// `None` if the observations agreeing on the value have less than `quorum` weight.
+fn aggregate(
+    aggregation: OracleAggregation,
+    values: Vec<(Vec<u8>, u64)>,
+    quorum: u64,
+) -> Option<Vec<u8>> {
+    if total_weight(&values) < quorum {
+        return None;
+    }
+    match aggregation {
+        OracleAggregation::Median => {
+            let mut numbers = values
+                .iter()
+                .filter_map(|(value, weight)| Some((decode_number(value)?, *weight)))
+                .collect::<Vec<_>>();
+            let total = total_weight(&numbers);
+            if total < quorum {
+                return None;
+            }
+            numbers.sort();
+            // The least number such that at least a half of the weight is at or below it.
+            let mut accumulated = 0_u64;
+            numbers
+                .into_iter()
+                .find(|(_, weight)| {
+                    accumulated = accumulated.saturating_add(*weight);
+                    u128::from(accumulated) * 2 >= u128::from(total)
+                })
+                .map(|(number, _)| number.into_bytes())
+        }
+        OracleAggregation::Majority => {
+            let mut weights = BTreeMap::new();
+            for (value, weight) in values {
+                let total = weights.entry(value).or_insert(0_u64);
+                *total = total.saturating_add(weight);
+            }
+            weights
+                .into_iter()
+                .find(|(_, weight)| *weight >= quorum)
+                .map(|(value, _)| value)
+        }
+    }
+}
+
+fn total_weight<T>(values: &[(T, u64)]) -> u64 {
+    values
+        .iter()
+        .fold(0, |total, (_, weight)| total.saturating_add(*weight))
+}
+
+fn decode_number(value: &[u8]) -> Option<i64> {
+    if value.len() != 8 {
+        return None;
+    }
+    i64::from_bytes(value.into()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_merkledb::BinaryValue;
+
+    use super::{aggregate, OracleAggregation};
+
+    fn numbers(values: &[i64]) -> Vec<(Vec<u8>, u64)> {
+        values.iter().map(|value| (value.into_bytes(), 1)).collect()
+    }
+
+    fn strings(values: &[&str]) -> Vec<(Vec<u8>, u64)> {
+        values
+            .iter()
+            .map(|value| (value.as_bytes().to_vec(), 1))
+            .collect()
+    }
+
+    #[test]
+    fn median_aggregation() {
+        let value = aggregate(OracleAggregation::Median, numbers(&[7, -2, 100, 5]), 3);
+        assert_eq!(value, Some(5_i64.into_bytes()));
+        let value = aggregate(OracleAggregation::Median, numbers(&[7, 1, 3]), 3);
+        assert_eq!(value, Some(3_i64.into_bytes()));
+    }
+
+    #[test]
+    fn median_requires_quorum() {
+        assert_eq!(
+            aggregate(OracleAggregation::Median, numbers(&[1, 2]), 3),
+            None
+        );
+        let mut values = numbers(&[1, 2]);
+        values.push((vec![1, 2, 3], 1));
+        assert_eq!(aggregate(OracleAggregation::Median, values, 3), None);
+    }
+
+    #[test]
+    fn majority_aggregation() {
+        let values = strings(&["a", "b", "a", "a"]);
+        assert_eq!(
+            aggregate(OracleAggregation::Majority, values, 3),
+            Some(b"a".to_vec())
+        );
+        let values = strings(&["a", "b", "a", "b"]);
+        assert_eq!(aggregate(OracleAggregation::Majority, values, 3), None);
+    }
+
+    #[test]
+    fn weighted_aggregation() {
+        let values = vec![
+            (1_i64.into_bytes(), 1),
+            (2_i64.into_bytes(), 1),
+            (10_i64.into_bytes(), 4),
+        ];
+        assert_eq!(
+            aggregate(OracleAggregation::Median, values, 5),
+            Some(10_i64.into_bytes())
+        );
+
+        let mut values = strings(&["a", "b"]);
+        values[1].1 = 4;
+        assert_eq!(
+            aggregate(OracleAggregation::Majority, values.clone(), 4),
+            Some(b"b".to_vec())
+        );
+        assert_eq!(aggregate(OracleAggregation::Majority, values, 5), None);
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parallel execution of the transactions of a block.
+//!
+//! The transactions are split into lanes by their services. Each lane is executed
+//! on a dedicated thread with its own fork of the state preceding the transactions,
+//! i.e., including the changes made by the service hooks at the start of the block,
+//! and the transactions within a lane are executed in the block order with the usual
+//! rollback of the failed ones. The write sets of the lanes are detected from
+//! the storage keys changed in their forks, and the read sets are recorded by the
+//! snapshots underlying the forks. If no lane reads or changes the data changed by
+//! another lane, the changes of the lanes are applied to the block fork and
+//! the transactions are recorded in the block order; otherwise, the results are
+//! discarded and the block is executed sequentially. Iterating over an index counts
+//! as reading the whole index.
+//!
+//! The lanes are executed with the gas limit available to the first transaction
+//! of the block. The block is also executed sequentially if its transactions exceed
+//! the block limits, or if a transaction consumes more gas than it would be given
+//! in the sequential execution.
+//!
+//! Indices share the storage namespace and keep their metadata in a common pool, so
+//! the lanes conflict whenever they change the same index, create new indices
+//! or clear an index.
+
+use exonum_merkledb::{Changeset, Fork, Iter, Snapshot};
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem, panic,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use super::{lifecycle, message_size, BlockBudget, Blockchain, Transaction, TransactionOutcome};
+use crate::crypto::Hash;
+use crate::helpers::Height;
+use crate::messages::{RawTransaction, Signed};
+
+/// Transactions of a single service within a block.
+struct Lane {
+    service_name: String,
+    transactions: Vec<(usize, Box<dyn Transaction>, Signed<RawTransaction>)>,
+}
+
+/// Changes made by a lane, the data read by it and the results of its transactions.
+type LaneOutcome = (
+    Changeset,
+    ReadSet,
+    Vec<(usize, Signed<RawTransaction>, TransactionOutcome)>,
+);
+
+/// Data read by a lane from the state preceding the transactions.
+#[derive(Debug, Default)]
+struct ReadSet {
+    /// Keys read by their index names.
+    keys: HashSet<(String, Vec<u8>)>,
+    /// Names of the indexes iterated over.
+    indexes: HashSet<String>,
+}
+
+/// Snapshot recording the data read from it.
+struct RecordingSnapshot {
+    inner: Box<dyn Snapshot>,
+    reads: Arc<Mutex<ReadSet>>,
+}
+
+impl RecordingSnapshot {
+    fn record_key(&self, name: &str, key: &[u8]) {
+        let mut reads = self.reads.lock().expect("Expected mutex lock");
+        reads.keys.insert((name.to_owned(), key.to_vec()));
+    }
+}
+
+impl Snapshot for RecordingSnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        self.record_key(name, key);
+        self.inner.get(name, key)
+    }
+
+    fn contains(&self, name: &str, key: &[u8]) -> bool {
+        self.record_key(name, key);
+        self.inner.contains(name, key)
+    }
+
+    fn iter(&self, name: &str, from: &[u8]) -> Iter {
+        let mut reads = self.reads.lock().expect("Expected mutex lock");
+        reads.indexes.insert(name.to_owned());
+        self.inner.iter(name, from)
+    }
+}
+
+/// Checks that the lanes change different keys, do not remove any keys by prefix
+/// and do not read the data changed by other lanes.
+fn are_independent(outcomes: &[LaneOutcome]) -> bool {
+    let mut changed_keys = HashMap::new();
+    let mut changed_indexes: HashMap<&str, HashSet<usize>> = HashMap::new();
+    for (lane, (changeset, ..)) in outcomes.iter().enumerate() {
+        for (name, changes) in changeset.iter() {
+            if !changes.prefixes_to_remove().is_empty() {
+                return false;
+            }
+            for (key, _) in changes.iter() {
+                if changed_keys
+                    .insert((name.as_str(), key.as_slice()), lane)
+                    .is_some()
+                {
+                    return false;
+                }
+            }
+            changed_indexes
+                .entry(name.as_str())
+                .or_default()
+                .insert(lane);
+        }
+    }
+
+    outcomes.iter().enumerate().all(|(lane, (_, reads, _))| {
+        let keys_unchanged = reads.keys.iter().all(|(name, key)| {
+            changed_keys
+                .get(&(name.as_str(), key.as_slice()))
+                .map_or(true, |&other| other == lane)
+        });
+        let indexes_unchanged = reads.indexes.iter().all(|name| {
+            changed_indexes
+                .get(name.as_str())
+                .map_or(true, |lanes| lanes.iter().all(|&other| other == lane))
+        });
+        keys_unchanged && indexes_unchanged
+    })
+}
+
+impl Blockchain {
+    /// Executes the transactions in parallel lanes and records them into the fork.
+    /// `block_changes` are the changes made to the fork since the start of the block,
+    /// which the lanes are executed on. Returns `false` without changing the fork
+    /// if the block should be executed sequentially instead.
+    pub(super) fn execute_in_parallel(
+        &self,
+        height: Height,
+        tx_hashes: &[Hash],
+        block_changes: &Changeset,
+        budget: &mut BlockBudget,
+        fork: &mut Fork,
+        tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> bool {
+        let mut lanes = BTreeMap::new();
+        for (index, &tx_hash) in tx_hashes.iter().enumerate() {
+            let (tx, raw, service_name) =
+                match self.prepare_transaction(tx_hash, fork.snapshot(), tx_cache) {
+                    Ok(prepared) => prepared,
+                    // The error is reported by the sequential execution.
+                    Err(_) => return false,
+                };
+            // Transactions of inactive services are recorded by the sequential execution.
+            if !lifecycle::service_state(fork.snapshot(), raw.service_id()).accepts_transactions() {
+                return false;
+            }
+            lanes
+                .entry(raw.service_id())
+                .or_insert_with(|| Lane {
+                    service_name: service_name.to_owned(),
+                    transactions: Vec::new(),
+                })
+                .transactions
+                .push((index, tx, raw));
+        }
+        if lanes.len() < 2 {
+            return false;
+        }
+
+        let gas_limit = budget.gas_limit();
+        let block_changes = Arc::new(block_changes.clone());
+        let handles = lanes
+            .into_iter()
+            .map(|(_, lane)| {
+                let blockchain = self.clone();
+                let block_changes = Arc::clone(&block_changes);
+                thread::spawn(move || blockchain.execute_lane(lane, &block_changes, gas_limit))
+            })
+            .collect::<Vec<_>>();
+        let mut outcomes = Vec::with_capacity(handles.len());
+        for handle in handles {
+            match handle.join() {
+                Ok(outcome) => outcomes.push(outcome),
+                // Storage errors are propagated the same way as in sequential execution.
+                Err(err) => panic::resume_unwind(err),
+            }
+        }
+
+        if !are_independent(&outcomes) {
+            info!(
+                "Services have accessed the same data, executing block {} sequentially",
+                height
+            );
+            return false;
+        }
+
+        let mut changesets = Vec::with_capacity(outcomes.len());
+        let mut results = Vec::with_capacity(tx_hashes.len());
+        for (changes, _, lane_results) in outcomes {
+            changesets.push(changes);
+            results.extend(lane_results);
+        }
+        results.sort_by_key(|&(index, ..)| index);
+
+        let mut block_budget = budget.clone();
+        for (_, raw, outcome) in &results {
+            let size = message_size(raw);
+            let sequential_gas_limit = block_budget.gas_limit();
+            let gas_limit_reduced = sequential_gas_limit != gas_limit
+                && sequential_gas_limit.map_or(false, |limit| outcome.gas_used > limit);
+            if !block_budget.fits(size) || gas_limit_reduced {
+                info!(
+                    "Transactions exceed the block limits, executing block {} sequentially",
+                    height
+                );
+                return false;
+            }
+            block_budget.consume(size, outcome.gas_used);
+        }
+        *budget = block_budget;
+
+        for changes in &changesets {
+            fork.apply(changes);
+        }
+        for (index, raw, outcome) in results {
+            Self::record_transaction(raw.hash(), raw, outcome, height, index, fork, tx_cache);
+        }
+        true
+    }
+
+    fn execute_lane(
+        &self,
+        lane: Lane,
+        block_changes: &Changeset,
+        gas_limit: Option<u64>,
+    ) -> LaneOutcome {
+        let mut base = self.fork();
+        base.apply(block_changes);
+        let reads = Arc::new(Mutex::new(ReadSet::default()));
+        let mut fork = Fork::from_snapshot(Box::new(RecordingSnapshot {
+            inner: Box::new(base.into_patch()),
+            reads: Arc::clone(&reads),
+        }));
+        fork.start_recording();
+        let results = lane
+            .transactions
+            .into_iter()
+            .map(|(index, tx, raw)| {
+                let outcome = self.run_transaction(
+                    tx.as_ref(),
+                    &raw,
+                    &lane.service_name,
+                    gas_limit,
+                    &mut fork,
+                );
+                fork.flush();
+                (index, raw, outcome)
+            })
+            .collect();
+        let changes = fork.stop_recording().expect("Recording is started");
+        let reads = mem::replace(
+            &mut *reads.lock().expect("Expected mutex lock"),
+            ReadSet::default(),
+        );
+        (changes, reads, results)
+    }
+}
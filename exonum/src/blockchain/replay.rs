@@ -0,0 +1,229 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replay of a single committed transaction.
+//!
+//! Only the latest state of the blockchain is stored, so the state the transaction
+//! is replayed on is rebuilt by re-executing the blocks in a separate blockchain,
+//! as done by [`rollback_chain`]. The transaction is then executed on a fork of the
+//! rebuilt state, and the changes it makes are recorded instead of being committed.
+//!
+//! The storage does not track reads, so the replay reports the changes written to
+//! the indexes only.
+//!
+//! [`rollback_chain`]: fn.rollback_chain.html
+
+use exonum_merkledb::{Change, Changeset, Snapshot};
+
+use std::collections::BTreeMap;
+
+use super::{
+    interchange::rollback_chain, lifecycle, limits::BlockBudget, Blockchain, GenesisState, Schema,
+    TransactionError, TransactionOutcome, TransactionResult,
+};
+use crate::crypto::{CryptoHash, Hash};
+use crate::helpers::Height;
+
+/// Change of a single entry of an index.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntryChange {
+    /// Hex-encoded key of the entry.
+    pub key: String,
+    /// Hex-encoded new value of the entry, or `None` if the entry is removed.
+    pub value: Option<String>,
+}
+
+/// Changes made to a single index.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexChanges {
+    /// Name of the index.
+    pub index: String,
+    /// Hex-encoded prefixes of the keys removed from the index, e.g., when the index
+    /// is cleared.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed_prefixes: Vec<String>,
+    /// Changed entries ordered by their keys.
+    pub entries: Vec<EntryChange>,
+}
+
+/// Result of the transaction replay.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransactionReplay {
+    /// Hash of the transaction.
+    pub tx_hash: Hash,
+    /// Height of the block the transaction is executed in. The transaction is
+    /// executed on the state after the previous block.
+    pub height: Height,
+    /// Result of the execution.
+    #[serde(with = "crate::explorer::TxStatus")]
+    pub status: TransactionResult,
+    /// Gas consumed by the execution.
+    pub gas_used: u64,
+    /// Fee charged from the author of the transaction.
+    pub fee_paid: u64,
+    /// Changes the transaction would make to the storage, ordered by the index names.
+    /// The changes of a failed transaction are rolled back, except for the fee charge.
+    pub changes: Vec<IndexChanges>,
+}
+
+/// Re-executes a committed transaction as if it were included into the block at
+/// `height`, and returns the changes it would make. The transaction is executed on
+/// the state after the block at `height - 1`, i.e., neither the service hooks run
+/// before the transactions nor the preceding transactions of the block are applied.
+/// If `height` is not specified, the height of the block the transaction has been
+/// committed in is used.
+///
+/// The state is rebuilt in `blockchain`, which must use an empty database (e.g.,
+/// a `TemporaryDB`) and have the same services as the source one. The changes are
+/// not merged into it. `genesis_state` must be the same as the one used to create
+/// the source chain.
+pub fn replay_transaction(
+    snapshot: &dyn Snapshot,
+    tx_hash: &Hash,
+    height: Option<Height>,
+    blockchain: &mut Blockchain,
+    genesis_state: GenesisState,
+) -> Result<TransactionReplay, failure::Error> {
+    let schema = Schema::new(snapshot);
+    let raw = schema
+        .transactions()
+        .get(tx_hash)
+        .ok_or_else(|| format_err!("Transaction {:?} is not stored in the blockchain", tx_hash))?;
+    let height = match height {
+        Some(height) => height,
+        None => schema
+            .transactions_locations()
+            .get(tx_hash)
+            .map(|location| location.block_height())
+            .ok_or_else(|| format_err!("Transaction {:?} is not committed", tx_hash))?,
+    };
+    ensure!(
+        height > Height(0),
+        "Transactions cannot be replayed in the genesis block"
+    );
+    rollback_chain(snapshot, height.previous(), blockchain, genesis_state)?;
+
+    let mut fork = blockchain.fork();
+    let mut tx_cache = BTreeMap::new();
+    tx_cache.insert(raw.hash(), raw);
+    let (tx, raw, service_name) =
+        blockchain.prepare_transaction(*tx_hash, fork.snapshot(), &tx_cache)?;
+    let consensus = Schema::new(&fork)
+        .configuration_at(height)
+        .map(|config| config.consensus)
+        .unwrap_or_default();
+
+    fork.start_recording();
+    let state = lifecycle::service_state(fork.snapshot(), raw.service_id());
+    let outcome = if state.accepts_transactions() {
+        info!(
+            "Replaying {:?} transaction of service <{}> at height {}",
+            tx_hash, service_name, height
+        );
+        let gas_limit = BlockBudget::new(&consensus).gas_limit();
+        blockchain.run_transaction(tx.as_ref(), &raw, service_name, gas_limit, &mut fork)
+    } else {
+        info!(
+            "Service <{}>: {:?} transaction is not executed, the service is {}",
+            service_name, tx_hash, state
+        );
+        TransactionOutcome::rejected(TransactionError::service_inactive(None))
+    };
+    fork.flush();
+    let changeset = fork.stop_recording().expect("Recording is started");
+
+    Ok(TransactionReplay {
+        tx_hash: *tx_hash,
+        height,
+        status: outcome.result,
+        gas_used: outcome.gas_used,
+        fee_paid: outcome.fee_paid,
+        changes: index_changes(&changeset),
+    })
+}
+
+fn index_changes(changeset: &Changeset) -> Vec<IndexChanges> {
+    let mut changes = changeset
+        .iter()
+        .map(|(index, changes)| {
+            let entries = changes
+                .iter()
+                .map(|(key, change)| {
+                    let key = hex::encode(key);
+                    let value = match change {
+                        Change::Put(value) => Some(hex::encode(value)),
+                        Change::Delete => None,
+                    };
+                    trace!("Replayed change of {}: {} => {:?}", index, key, value);
+                    EntryChange { key, value }
+                })
+                .collect();
+            IndexChanges {
+                index: index.clone(),
+                removed_prefixes: changes
+                    .prefixes_to_remove()
+                    .iter()
+                    .map(hex::encode)
+                    .collect(),
+                entries,
+            }
+        })
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| a.index.cmp(&b.index));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::interchange::tests::{create_blockchain, create_chain};
+
+    #[test]
+    fn replayed_transaction_is_not_committed() {
+        let (source, transactions) = create_chain(3);
+        let tx_hash = transactions[1].hash();
+        let mut replay = create_blockchain();
+        let report = replay_transaction(
+            source.snapshot().as_ref(),
+            &tx_hash,
+            None,
+            &mut replay,
+            GenesisState::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.height, Height(2));
+        assert_eq!(report.status, TransactionResult(Ok(())));
+        assert!(!report.changes.is_empty());
+        // The state before the block is rebuilt, but the transaction is not merged into it.
+        assert_eq!(replay.last_block().height(), Height(1));
+        assert!(!Schema::new(&replay.snapshot())
+            .transactions_locations()
+            .contains(&tx_hash));
+    }
+
+    #[test]
+    fn unknown_transaction_is_not_replayed() {
+        let (source, _) = create_chain(1);
+        let mut replay = create_blockchain();
+        let result = replay_transaction(
+            source.snapshot().as_ref(),
+            &Hash::zero(),
+            None,
+            &mut replay,
+            GenesisState::default(),
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,637 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retention policies for the auxiliary data kept by the node.
+//!
+//! The data is divided into categories, and each configurable category is assigned
+//! a retention window, i.e., the number of the latest blocks for which the data
+//! is kept. The windows are enforced by a compactor running on a separate thread
+//! after each block commit.
+//!
+//! | Category | Retention |
+//! |----------|-----------|
+//! | Block precommits | `precommits` window |
+//! | Ephemeral service indexes | `ephemeral` window, see [`Service::prune_ephemeral`] |
+//! | Consensus messages cache | Until the next block commit |
+//! | Transactions | `transactions` window, then offloaded to the archive, see [`archive`] |
+//! | Transaction history | `history` window, then deleted |
+//! | Transaction results | Forever, since they are aggregated into the block state hash |
+//!
+//! A node that has pruned the precommits of a block can no longer provide this block
+//! to lagging peers, as the block cannot be verified without its precommits.
+//!
+//! Similarly, a node that has offloaded the transactions of a block cannot provide
+//! them to lagging peers, although they are still available in the explorer.
+//!
+//! The transaction history of a block comprises the transaction messages, their gas
+//! and fees, and the per-block lists of the execution result hashes and the events.
+//! Unlike offloading, deletion of the history makes the transactions unavailable
+//! in the explorer as well; the block headers, the transaction locations and the current
+//! state are kept intact.
+//!
+//! [`Service::prune_ephemeral`]: trait.Service.html#method.prune_ephemeral
+//! [`archive`]: archive/index.html
+
+use exonum_merkledb::{BinaryValue, Fork, IndexAccess};
+
+use std::{
+    cmp, fmt,
+    ops::AddAssign,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use crate::{api::node::public::BlockProofCache, crypto::HASH_SIZE, helpers::Height};
+
+use super::{ArchiveConfig, Blockchain, Schema};
+
+const PRECOMMITS_CATEGORY: &str = "precommits";
+const EPHEMERAL_CATEGORY: &str = "ephemeral";
+const TRANSACTIONS_CATEGORY: &str = "transactions";
+const HISTORY_CATEGORY: &str = "history";
+
+/// Retention windows of the pruned data categories, in blocks.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct RetentionConfig {
+    /// Number of the latest blocks for which precommits are kept.
+    /// `None` disables pruning of precommits.
+    #[serde(default)]
+    pub precommits: Option<u64>,
+    /// Number of the latest blocks for which the data of ephemeral service indexes
+    /// is kept. `None` disables pruning of ephemeral indexes.
+    #[serde(default)]
+    pub ephemeral: Option<u64>,
+    /// Offloading of the transactions of old blocks to the archive.
+    /// `None` keeps all the transactions in the local database.
+    #[serde(default)]
+    pub transactions: Option<ArchiveConfig>,
+    /// Number of the latest blocks for which the transaction history is kept.
+    /// Older transactions are deleted without offloading. `None` disables deletion
+    /// of the transaction history.
+    #[serde(default)]
+    pub history: Option<u64>,
+}
+
+impl RetentionConfig {
+    /// Returns `true` if no data is pruned under this configuration.
+    pub fn is_disabled(&self) -> bool {
+        self.precommits.is_none()
+            && self.ephemeral.is_none()
+            && self.transactions.is_none()
+            && self.history.is_none()
+    }
+}
+
+/// Amount of data removed from the storage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReclaimedSpace {
+    /// Number of removed index entries.
+    pub entries: u64,
+    /// Total size of the removed values in bytes.
+    pub bytes: u64,
+}
+
+impl ReclaimedSpace {
+    /// Creates a new `ReclaimedSpace` instance.
+    pub fn new(entries: u64, bytes: u64) -> Self {
+        Self { entries, bytes }
+    }
+}
+
+impl AddAssign for ReclaimedSpace {
+    fn add_assign(&mut self, other: Self) {
+        self.entries += other.entries;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Data removed by a single compaction.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct CompactionReport {
+    pub precommits: ReclaimedSpace,
+    pub ephemeral: ReclaimedSpace,
+    pub transactions: ReclaimedSpace,
+    pub history: ReclaimedSpace,
+}
+
+/// Returns the lowest height whose data is kept under the given retention window.
+/// The data of the latest committed block is never pruned.
+fn retain_from(height: Height, window: u64) -> Height {
+    Height((height.0 + 1).saturating_sub(cmp::max(window, 1)))
+}
+
+/// Enforces the retention policies after the block at the given height is committed.
+pub(crate) fn compact(
+    blockchain: &mut Blockchain,
+    config: &RetentionConfig,
+    height: Height,
+) -> Result<CompactionReport, failure::Error> {
+    let fork = blockchain.fork();
+    let mut report = CompactionReport::default();
+    if let Some(window) = config.precommits {
+        report.precommits = prune_precommits(&fork, retain_from(height, window));
+    }
+    if let Some(window) = config.ephemeral {
+        report.ephemeral = prune_ephemeral(blockchain, &fork, retain_from(height, window));
+    }
+    if let Some(ref archive) = config.transactions {
+        let retain_from = retain_from(height, archive.window);
+        report.transactions = offload_transactions(blockchain, &fork, retain_from)?;
+    }
+    if let Some(window) = config.history {
+        report.history = prune_history(&fork, retain_from(height, window));
+    }
+    blockchain.merge(fork.into_patch())?;
+    Ok(report)
+}
+
+/// Returns the height below which the transaction history has been deleted.
+pub(crate) fn history_pruned_to<T: IndexAccess>(schema: &Schema<T>) -> Height {
+    let pruned_to = schema.retention_progress().get(HISTORY_CATEGORY);
+    Height(pruned_to.unwrap_or(0))
+}
+
+/// Advances the pruning boundary of the category. Returns the previous boundary
+/// if the new one is greater.
+fn advance_progress(fork: &Fork, category: &str, retain_from: Height) -> Option<Height> {
+    let mut progress = Schema::new(fork).retention_progress();
+    let pruned_to = Height(progress.get(category).unwrap_or(0));
+    if retain_from > pruned_to {
+        progress.put(&category.to_owned(), retain_from.0);
+        Some(pruned_to)
+    } else {
+        None
+    }
+}
+
+fn prune_precommits(fork: &Fork, retain_from: Height) -> ReclaimedSpace {
+    let mut reclaimed = ReclaimedSpace::default();
+    let pruned_to = match advance_progress(fork, PRECOMMITS_CATEGORY, retain_from) {
+        Some(height) => height,
+        None => return reclaimed,
+    };
+
+    let schema = Schema::new(fork);
+    for height in pruned_to.0..retain_from.0 {
+        let block_hash = match schema.block_hash_by_height(Height(height)) {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let mut precommits = schema.precommits(&block_hash);
+        for precommit in precommits.iter() {
+            reclaimed += ReclaimedSpace::new(1, precommit.into_bytes().len() as u64);
+        }
+        precommits.clear();
+    }
+    reclaimed
+}
+
+fn prune_ephemeral(blockchain: &Blockchain, fork: &Fork, retain_from: Height) -> ReclaimedSpace {
+    let mut reclaimed = ReclaimedSpace::default();
+    if advance_progress(fork, EPHEMERAL_CATEGORY, retain_from).is_none() {
+        return reclaimed;
+    }
+
+    let service_map = blockchain.service_map();
+    let mut service_ids: Vec<_> = service_map.keys().collect();
+    service_ids.sort();
+    for id in service_ids {
+        reclaimed += service_map[id].prune_ephemeral(fork, retain_from);
+    }
+    reclaimed
+}
+
+fn offload_transactions(
+    blockchain: &Blockchain,
+    fork: &Fork,
+    retain_from: Height,
+) -> Result<ReclaimedSpace, failure::Error> {
+    let mut reclaimed = ReclaimedSpace::default();
+    let archive = blockchain
+        .transaction_archive()
+        .ok_or_else(|| format_err!("Transaction archive is not configured"))?;
+    let pruned_to = match advance_progress(fork, TRANSACTIONS_CATEGORY, retain_from) {
+        Some(height) => height,
+        None => return Ok(reclaimed),
+    };
+
+    let schema = Schema::new(fork);
+    let mut transactions = schema.transactions();
+    let mut archived = schema.archived_transactions();
+    for height in pruned_to.0..retain_from.0 {
+        for tx_hash in schema.block_transactions(Height(height)).iter() {
+            let tx = match transactions.get(&tx_hash) {
+                Some(tx) => tx,
+                None => continue,
+            };
+            // The fork is dropped on error, so the transactions offloaded so far
+            // are offloaded again on the next attempt.
+            archive.offload(&tx)?;
+            reclaimed += ReclaimedSpace::new(1, tx.into_bytes().len() as u64);
+            transactions.remove(&tx_hash);
+            archived.insert(tx_hash);
+        }
+    }
+    Ok(reclaimed)
+}
+
+fn prune_history(fork: &Fork, retain_from: Height) -> ReclaimedSpace {
+    let mut reclaimed = ReclaimedSpace::default();
+    let pruned_to = match advance_progress(fork, HISTORY_CATEGORY, retain_from) {
+        Some(height) => height,
+        None => return reclaimed,
+    };
+
+    let schema = Schema::new(fork);
+    let mut transactions = schema.transactions();
+    let mut gas = schema.transaction_gas();
+    let mut fees = schema.transaction_fees();
+    for height in pruned_to.0..retain_from.0 {
+        let height = Height(height);
+        for tx_hash in schema.block_transactions(height).iter() {
+            if let Some(tx) = transactions.get(&tx_hash) {
+                reclaimed += ReclaimedSpace::new(1, tx.into_bytes().len() as u64);
+                transactions.remove(&tx_hash);
+            }
+            if gas.contains(&tx_hash) {
+                reclaimed += ReclaimedSpace::new(1, 8);
+                gas.remove(&tx_hash);
+            }
+            if fees.contains(&tx_hash) {
+                reclaimed += ReclaimedSpace::new(1, 8);
+                fees.remove(&tx_hash);
+            }
+        }
+
+        let mut results = schema.block_transaction_results(height);
+        if !results.is_empty() {
+            reclaimed += ReclaimedSpace::new(results.len(), results.len() * HASH_SIZE as u64);
+            results.clear();
+        }
+
+        let mut events = schema.block_events(height);
+        if !events.is_empty() {
+            let bytes = events
+                .iter()
+                .map(|event| event.into_bytes().len() as u64)
+                .sum();
+            reclaimed += ReclaimedSpace::new(events.len(), bytes);
+            events.clear();
+        }
+    }
+    reclaimed
+}
+
+/// Handle of the compactor thread. The thread exits once the handle is dropped.
+pub(crate) struct Compactor {
+    heights: Sender<Height>,
+}
+
+impl Compactor {
+    /// Launches the compactor thread. Returns `None` if the configuration disables pruning.
+    pub(crate) fn start(
+        blockchain: Blockchain,
+        config: RetentionConfig,
+        proof_cache: BlockProofCache,
+    ) -> Option<Self> {
+        if config.is_disabled() {
+            return None;
+        }
+
+        let (heights, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("retention-compactor".to_owned())
+            .spawn(move || run_compactor(blockchain, config, proof_cache, receiver))
+            .expect("Unable to spawn the retention compactor thread");
+        Some(Self { heights })
+    }
+
+    /// Notifies the compactor that the block at the given height has been committed.
+    pub(crate) fn notify_commit(&self, height: Height) {
+        if self.heights.send(height).is_err() {
+            error!(
+                "Retention compactor has stopped, the data at height {} will not be pruned",
+                height
+            );
+        }
+    }
+}
+
+impl fmt::Debug for Compactor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Compactor").finish()
+    }
+}
+
+fn run_compactor(
+    mut blockchain: Blockchain,
+    config: RetentionConfig,
+    proof_cache: BlockProofCache,
+    receiver: Receiver<Height>,
+) {
+    while let Ok(mut height) = receiver.recv() {
+        // If the compactor falls behind, it is enough to process the latest height.
+        while let Ok(next) = receiver.try_recv() {
+            height = next;
+        }
+
+        let report = match compact(&mut blockchain, &config, height) {
+            Ok(report) => report,
+            Err(e) => {
+                error!("Unable to enforce retention policies at height {}: {}", height, e);
+                continue;
+            }
+        };
+        if let Some(window) = config.precommits {
+            proof_cache.invalidate_below(retain_from(height, window));
+        }
+
+        metric!("node.retention.precommits.entries", report.precommits.entries);
+        metric!("node.retention.precommits.bytes", report.precommits.bytes);
+        metric!("node.retention.ephemeral.entries", report.ephemeral.entries);
+        metric!("node.retention.ephemeral.bytes", report.ephemeral.bytes);
+        metric!(
+            "node.retention.transactions.entries",
+            report.transactions.entries
+        );
+        metric!(
+            "node.retention.transactions.bytes",
+            report.transactions.bytes
+        );
+        metric!("node.retention.history.entries", report.history.entries);
+        metric!("node.retention.history.bytes", report.history.bytes);
+        if report != CompactionReport::default() {
+            info!(
+                "Pruned data at height {}: precommits {:?}, ephemeral {:?}, transactions {:?}, \
+                 history {:?}",
+                height, report.precommits, report.ephemeral, report.transactions, report.history
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use tempdir::TempDir;
+
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::blockchain::{
+        GenesisConfig, Service, Transaction, TransactionArchive, ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, CryptoHash, Hash, PublicKey, SecretKey};
+    use crate::explorer::BlockchainExplorer;
+    use crate::helpers::{Round, ValidatorId};
+    use crate::messages::{Message, Precommit, RawTransaction, ServiceTransaction};
+    use exonum_merkledb::Snapshot;
+
+    struct EphemeralService {
+        calls: Arc<Mutex<Vec<Height>>>,
+    }
+
+    impl Service for EphemeralService {
+        fn service_id(&self) -> u16 {
+            7
+        }
+
+        fn service_name(&self) -> &str {
+            "ephemeral"
+        }
+
+        fn state_hash_tables(&self, _snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+            Vec::new()
+        }
+
+        fn tx_from_raw(
+            &self,
+            _raw: RawTransaction,
+        ) -> Result<Box<dyn Transaction>, failure::Error> {
+            unimplemented!()
+        }
+
+        fn prune_ephemeral(&self, _fork: &Fork, retain_from: Height) -> ReclaimedSpace {
+            self.calls.lock().unwrap().push(retain_from);
+            ReclaimedSpace::new(1, 10)
+        }
+    }
+
+    fn create_blockchain(services: Vec<Box<dyn Service>>) -> (Blockchain, PublicKey, SecretKey) {
+        let (consensus_key, consensus_secret_key) = gen_keypair();
+        let mut blockchain = crate::blockchain::tests::create_blockchain(services);
+        let validator_keys = ValidatorKeys::new(consensus_key, blockchain.service_keypair.0);
+        blockchain
+            .initialize(GenesisConfig::new(vec![validator_keys].into_iter()))
+            .unwrap();
+        (blockchain, consensus_key, consensus_secret_key)
+    }
+
+    fn commit_blocks(blockchain: &mut Blockchain, key: (PublicKey, &SecretKey), count: u64) {
+        for height in 1..=count {
+            let height = Height(height);
+            let (block_hash, patch) = blockchain.create_patch(
+                ValidatorId::zero(),
+                height,
+                &[],
+                &mut BTreeMap::new(),
+            );
+            let precommit = Message::concrete(
+                Precommit::new(
+                    ValidatorId::zero(),
+                    height,
+                    Round::first(),
+                    &Hash::zero(),
+                    &block_hash,
+                    Utc::now(),
+                ),
+                key.0,
+                key.1,
+            );
+            blockchain
+                .commit(
+                    patch,
+                    block_hash,
+                    vec![precommit].into_iter(),
+                    &mut BTreeMap::new(),
+                )
+                .unwrap();
+        }
+    }
+
+    fn precommits_count(blockchain: &Blockchain, height: u64) -> u64 {
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let block_hash = schema.block_hash_by_height(Height(height)).unwrap();
+        schema.precommits(&block_hash).len()
+    }
+
+    #[test]
+    fn retention_window_bounds() {
+        assert_eq!(retain_from(Height(10), 3), Height(8));
+        assert_eq!(retain_from(Height(2), 5), Height(0));
+        // The latest block is always retained.
+        assert_eq!(retain_from(Height(10), 0), Height(10));
+    }
+
+    #[test]
+    fn precommits_outside_window_are_pruned() {
+        let (mut blockchain, public_key, secret_key) = create_blockchain(Vec::new());
+        commit_blocks(&mut blockchain, (public_key, &secret_key), 5);
+        let config = RetentionConfig {
+            precommits: Some(2),
+            ..RetentionConfig::default()
+        };
+
+        let report = compact(&mut blockchain, &config, Height(5)).unwrap();
+        assert_eq!(report.precommits.entries, 3);
+        assert!(report.precommits.bytes > 0);
+        assert_eq!(report.ephemeral, ReclaimedSpace::default());
+        for height in 1..4 {
+            assert_eq!(precommits_count(&blockchain, height), 0);
+        }
+        for height in 4..6 {
+            assert_eq!(precommits_count(&blockchain, height), 1);
+        }
+
+        // Already pruned heights are not visited again.
+        let report = compact(&mut blockchain, &config, Height(5)).unwrap();
+        assert_eq!(report, CompactionReport::default());
+    }
+
+    #[test]
+    fn services_prune_ephemeral_data_once_per_boundary() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let service = EphemeralService {
+            calls: Arc::clone(&calls),
+        };
+        let (mut blockchain, _, _) = create_blockchain(vec![Box::new(service)]);
+        let config = RetentionConfig {
+            ephemeral: Some(1),
+            ..RetentionConfig::default()
+        };
+
+        let report = compact(&mut blockchain, &config, Height(0)).unwrap();
+        assert_eq!(report.ephemeral, ReclaimedSpace::default());
+
+        let report = compact(&mut blockchain, &config, Height(3)).unwrap();
+        assert_eq!(report.ephemeral, ReclaimedSpace::new(1, 10));
+        compact(&mut blockchain, &config, Height(3)).unwrap();
+        compact(&mut blockchain, &config, Height(4)).unwrap();
+
+        assert_eq!(*calls.lock().unwrap(), vec![Height(3), Height(4)]);
+    }
+
+    #[test]
+    fn transactions_outside_window_are_offloaded() {
+        let dir = TempDir::new("exonum_archive").unwrap();
+        let (mut blockchain, public_key, secret_key) = create_blockchain(Vec::new());
+        let config = RetentionConfig {
+            transactions: Some(ArchiveConfig {
+                window: 1,
+                url: format!("file://{}", dir.path().display()),
+                cache_size: 10,
+            }),
+            ..RetentionConfig::default()
+        };
+        let archive = TransactionArchive::from_config(config.transactions.as_ref().unwrap());
+        blockchain.set_transaction_archive(archive.unwrap());
+        commit_blocks(&mut blockchain, (public_key, &secret_key), 2);
+
+        // Pretend that the committed blocks contain transactions.
+        let txs = (1..=2)
+            .map(|height| {
+                let payload = ServiceTransaction::from_raw_unchecked(0, vec![height]);
+                Message::concrete(RawTransaction::new(7, payload), public_key, &secret_key)
+            })
+            .collect::<Vec<_>>();
+        let fork = blockchain.fork();
+        {
+            let schema = Schema::new(&fork);
+            for (height, tx) in (1..).zip(&txs) {
+                schema.transactions().put(&tx.hash(), tx.clone());
+                schema.block_transactions(Height(height)).push(tx.hash());
+            }
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+
+        let report = compact(&mut blockchain, &config, Height(2)).unwrap();
+        assert_eq!(report.transactions.entries, 1);
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(!schema.transactions().contains(&txs[0].hash()));
+        assert!(schema.archived_transactions().contains(&txs[0].hash()));
+        assert!(schema.transactions().contains(&txs[1].hash()));
+
+        let archive = blockchain.transaction_archive().unwrap();
+        assert_eq!(archive.fetch(&txs[0].hash()).unwrap(), Some(txs[0].clone()));
+        assert_eq!(archive.fetch(&txs[1].hash()).unwrap(), None);
+    }
+
+    #[test]
+    fn transaction_history_outside_window_is_deleted() {
+        let (mut blockchain, public_key, secret_key) = create_blockchain(Vec::new());
+        let config = RetentionConfig {
+            history: Some(1),
+            ..RetentionConfig::default()
+        };
+        commit_blocks(&mut blockchain, (public_key, &secret_key), 2);
+
+        // Pretend that the committed blocks contain transactions.
+        let txs = (1..=2)
+            .map(|height| {
+                let payload = ServiceTransaction::from_raw_unchecked(0, vec![height]);
+                Message::concrete(RawTransaction::new(7, payload), public_key, &secret_key)
+            })
+            .collect::<Vec<_>>();
+        let fork = blockchain.fork();
+        {
+            let schema = Schema::new(&fork);
+            for (height, tx) in (1..).zip(&txs) {
+                schema.transactions().put(&tx.hash(), tx.clone());
+                schema.transaction_gas().put(&tx.hash(), 100);
+                schema.block_transactions(Height(height)).push(tx.hash());
+                schema
+                    .block_transaction_results(Height(height))
+                    .push(Hash::zero());
+            }
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+
+        let report = compact(&mut blockchain, &config, Height(2)).unwrap();
+        assert_eq!(report.history.entries, 3);
+        assert_eq!(report.transactions, ReclaimedSpace::default());
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(history_pruned_to(&schema), Height(2));
+        assert!(!schema.transactions().contains(&txs[0].hash()));
+        assert!(!schema.transaction_gas().contains(&txs[0].hash()));
+        assert!(schema.block_transaction_results(Height(1)).is_empty());
+        assert!(schema.transactions().contains(&txs[1].hash()));
+        assert!(schema.transaction_gas().contains(&txs[1].hash()));
+        assert_eq!(schema.block_transaction_results(Height(2)).len(), 1);
+        // The block headers are kept intact.
+        assert!(schema.block_hash_by_height(Height(1)).is_some());
+
+        // Blocks with pruned transactions are still served by the explorer.
+        let explorer = BlockchainExplorer::new(&blockchain);
+        let block = explorer.block_with_txs(Height(1)).unwrap();
+        assert!(block.transactions.is_empty());
+    }
+}
@@ -0,0 +1,460 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Background jobs of services.
+//!
+//! Services declare their jobs in [`Service::background_jobs`]. The node launches
+//! each job on a dedicated thread pool either periodically or when the blockchain
+//! reaches a certain height. Jobs receive a read-only snapshot of the storage and
+//! may broadcast transactions; they never block the consensus.
+//!
+//! [`Service::background_jobs`]: trait.Service.html#method.background_jobs
+
+use exonum_merkledb::Snapshot;
+use futures::{future, Future};
+use tokio_threadpool::{Builder as ThreadPoolBuilder, ThreadPool};
+
+use std::{
+    fmt, mem, panic,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    crypto::{PublicKey, SecretKey},
+    helpers::Height,
+    messages::{Message, RawTransaction, ServiceTransaction, Signed},
+    node::ApiSender,
+};
+
+use super::{Blockchain, Schema, Transaction};
+
+/// Period of checking the job triggers. It bounds the delay between the moment
+/// a job becomes due and its launch.
+const SCHEDULER_TICK: Duration = Duration::from_millis(100);
+
+/// Condition that triggers a background job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobTrigger {
+    /// The job is launched periodically, with the given interval between launches.
+    Interval(Duration),
+    /// The job is launched each time the blockchain height reaches a multiple of
+    /// the given number of blocks.
+    Height(u64),
+}
+
+/// Type alias for the background job handler.
+pub type JobHandler = dyn Fn(&JobContext) -> Result<(), failure::Error> + Send + Sync;
+
+/// Named background job of a service.
+#[derive(Clone)]
+pub struct BackgroundJob {
+    name: String,
+    trigger: JobTrigger,
+    handler: Arc<JobHandler>,
+}
+
+impl BackgroundJob {
+    /// Creates a new background job.
+    ///
+    /// # Panics
+    ///
+    /// If the trigger has zero interval or zero number of blocks.
+    pub fn new<S, F>(name: S, trigger: JobTrigger, handler: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&JobContext) -> Result<(), failure::Error> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        match trigger {
+            JobTrigger::Interval(interval) => assert!(
+                interval > Duration::from_millis(0),
+                "Interval of the background job <{}> must be positive",
+                name
+            ),
+            JobTrigger::Height(blocks) => assert!(
+                blocks > 0,
+                "Height trigger of the background job <{}> must be positive",
+                name
+            ),
+        }
+
+        Self {
+            name,
+            trigger,
+            handler: Arc::new(handler),
+        }
+    }
+
+    /// Returns the job name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the job trigger.
+    pub fn trigger(&self) -> JobTrigger {
+        self.trigger
+    }
+}
+
+impl fmt::Debug for BackgroundJob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BackgroundJob")
+            .field("name", &self.name)
+            .field("trigger", &self.trigger)
+            .finish()
+    }
+}
+
+/// Context of a background job launch.
+pub struct JobContext {
+    service_id: u16,
+    height: Height,
+    snapshot: Box<dyn Snapshot>,
+    service_keypair: (PublicKey, SecretKey),
+    api_sender: ApiSender,
+}
+
+impl JobContext {
+    /// Returns the identifier of the service that owns the job.
+    pub fn service_id(&self) -> u16 {
+        self.service_id
+    }
+
+    /// Returns the height of the latest committed block at the moment of the launch.
+    pub fn height(&self) -> Height {
+        self.height
+    }
+
+    /// Returns a read-only snapshot of the storage taken at the moment of the launch.
+    pub fn snapshot(&self) -> &dyn Snapshot {
+        self.snapshot.as_ref()
+    }
+
+    /// Returns the service public key of the current node.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.service_keypair.0
+    }
+
+    /// Returns the service secret key of the current node.
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.service_keypair.1
+    }
+
+    /// Signs the transaction with the service key of the current node and
+    /// broadcasts it to other nodes.
+    pub fn broadcast_transaction<T>(&self, tx: T) -> Result<(), failure::Error>
+    where
+        T: Into<ServiceTransaction> + Transaction,
+    {
+        let msg = Message::sign_transaction_for_network(
+            tx,
+            self.service_id,
+            Schema::new(self.snapshot.as_ref()).network_id(),
+            self.service_keypair.0,
+            &self.service_keypair.1,
+        );
+        self.api_sender.broadcast_transaction(msg)
+    }
+
+    /// Broadcasts an externally signed transaction to other nodes.
+    pub fn broadcast_signed_transaction(
+        &self,
+        msg: Signed<RawTransaction>,
+    ) -> Result<(), failure::Error> {
+        self.api_sender.broadcast_transaction(msg)
+    }
+}
+
+impl fmt::Debug for JobContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JobContext")
+            .field("service_id", &self.service_id)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+struct ScheduledJob {
+    service_id: u16,
+    job: BackgroundJob,
+    next_launch: Instant,
+    is_running: Arc<AtomicBool>,
+}
+
+/// Keeps track of the job triggers.
+struct JobQueue {
+    jobs: Vec<ScheduledJob>,
+    last_height: Height,
+}
+
+impl JobQueue {
+    fn new<I>(jobs: I, now: Instant, height: Height) -> Self
+    where
+        I: IntoIterator<Item = (u16, BackgroundJob)>,
+    {
+        let jobs = jobs
+            .into_iter()
+            .map(|(service_id, job)| {
+                let next_launch = match job.trigger {
+                    JobTrigger::Interval(interval) => now + interval,
+                    JobTrigger::Height(_) => now,
+                };
+                ScheduledJob {
+                    service_id,
+                    job,
+                    next_launch,
+                    is_running: Arc::new(AtomicBool::new(false)),
+                }
+            })
+            .collect();
+
+        Self {
+            jobs,
+            last_height: height,
+        }
+    }
+
+    /// Returns indices of the jobs that should be launched at the given moment.
+    fn due_jobs(&mut self, now: Instant, height: Height) -> Vec<usize> {
+        let last_height = mem::replace(&mut self.last_height, height);
+        self.jobs
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, scheduled)| {
+                let is_due = match scheduled.job.trigger {
+                    JobTrigger::Interval(interval) => {
+                        let is_due = now >= scheduled.next_launch;
+                        if is_due {
+                            scheduled.next_launch = now + interval;
+                        }
+                        is_due
+                    }
+                    JobTrigger::Height(blocks) => height.0 / blocks > last_height.0 / blocks,
+                };
+                if is_due {
+                    Some(index)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Runs background jobs of the blockchain services.
+pub(crate) struct JobScheduler {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl JobScheduler {
+    /// Starts the scheduler for the jobs of the blockchain services. Returns `None`
+    /// if no service has background jobs.
+    pub(crate) fn start(blockchain: Blockchain) -> Option<Self> {
+        let jobs = blockchain
+            .service_map()
+            .iter()
+            .flat_map(|(&service_id, service)| {
+                service
+                    .background_jobs()
+                    .into_iter()
+                    .map(move |job| (service_id, job))
+            })
+            .collect::<Vec<_>>();
+        if jobs.is_empty() {
+            return None;
+        }
+
+        info!("Starting scheduler with {} background jobs", jobs.len());
+        let queue = JobQueue::new(jobs, Instant::now(), blockchain.last_block().height());
+        let pool = ThreadPoolBuilder::new()
+            .name_prefix("background-job-")
+            .build();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || run_jobs(&blockchain, queue, &pool, &stop))
+        };
+        Some(Self { stop, thread })
+    }
+
+    /// Stops the scheduler. The jobs which are running at the moment are not waited for.
+    pub(crate) fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if self.thread.join().is_err() {
+            error!("Background jobs scheduler thread panicked");
+        }
+    }
+}
+
+impl fmt::Debug for JobScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JobScheduler").finish()
+    }
+}
+
+fn run_jobs(blockchain: &Blockchain, mut queue: JobQueue, pool: &ThreadPool, stop: &AtomicBool) {
+    while !stop.load(Ordering::SeqCst) {
+        let height = blockchain.last_block().height();
+        for index in queue.due_jobs(Instant::now(), height) {
+            let scheduled = &queue.jobs[index];
+            let snapshot = blockchain.snapshot();
+            // The jobs of deployable services are launched once the service is deployed,
+            // and the jobs of stopped or frozen services are not launched.
+            if !blockchain.is_service_active(snapshot.as_ref(), scheduled.service_id) {
+                continue;
+            }
+            if scheduled.is_running.swap(true, Ordering::SeqCst) {
+                warn!(
+                    "Skipping background job <{}>, its previous launch is still running",
+                    scheduled.job.name
+                );
+                continue;
+            }
+
+            let context = JobContext {
+                service_id: scheduled.service_id,
+                height,
+                snapshot,
+                service_keypair: blockchain.service_keypair.clone(),
+                api_sender: blockchain.api_sender.clone(),
+            };
+            let job = scheduled.job.clone();
+            let is_running = Arc::clone(&scheduled.is_running);
+            pool.spawn(future::lazy(move || -> Result<(), ()> {
+                trace!("Launching background job <{}>", job.name);
+                let handler = &job.handler;
+                match panic::catch_unwind(panic::AssertUnwindSafe(|| (**handler)(&context))) {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!("Background job <{}> failed: {}", job.name, e),
+                    Err(_) => error!("Background job <{}> panicked", job.name),
+                }
+                is_running.store(false, Ordering::SeqCst);
+                Ok(())
+            }));
+        }
+        thread::sleep(SCHEDULER_TICK);
+    }
+
+    pool.shutdown_now().wait().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::blockchain::{tests::create_initialized_blockchain, Service};
+    use crate::crypto::Hash;
+
+    fn job(trigger: JobTrigger) -> BackgroundJob {
+        BackgroundJob::new("job", trigger, |_| Ok(()))
+    }
+
+    #[test]
+    fn interval_jobs_are_due_after_interval() {
+        let start = Instant::now();
+        let interval = Duration::from_secs(10);
+        let jobs = vec![(1, job(JobTrigger::Interval(interval)))];
+        let mut queue = JobQueue::new(jobs, start, Height(0));
+
+        assert!(queue.due_jobs(start, Height(0)).is_empty());
+        assert_eq!(queue.due_jobs(start + interval, Height(0)), vec![0]);
+        assert!(queue.due_jobs(start + interval, Height(0)).is_empty());
+        assert_eq!(queue.due_jobs(start + interval * 2, Height(0)), vec![0]);
+    }
+
+    #[test]
+    fn height_jobs_are_due_on_multiples_of_blocks() {
+        let now = Instant::now();
+        let mut queue = JobQueue::new(vec![(1, job(JobTrigger::Height(3)))], now, Height(1));
+
+        assert!(queue.due_jobs(now, Height(2)).is_empty());
+        assert_eq!(queue.due_jobs(now, Height(3)), vec![0]);
+        assert!(queue.due_jobs(now, Height(3)).is_empty());
+        assert!(queue.due_jobs(now, Height(5)).is_empty());
+        // Several blocks committed between the checks trigger the job only once.
+        assert_eq!(queue.due_jobs(now, Height(10)), vec![0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be positive")]
+    fn zero_height_trigger_is_rejected() {
+        job(JobTrigger::Height(0));
+    }
+
+    struct JobService {
+        launches: Arc<AtomicUsize>,
+    }
+
+    impl Service for JobService {
+        fn service_id(&self) -> u16 {
+            5
+        }
+
+        fn service_name(&self) -> &str {
+            "job_service"
+        }
+
+        fn state_hash_tables(&self, _snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+            Vec::new()
+        }
+
+        fn tx_from_raw(
+            &self,
+            _raw: RawTransaction,
+        ) -> Result<Box<dyn Transaction>, failure::Error> {
+            unimplemented!()
+        }
+
+        fn background_jobs(&self) -> Vec<BackgroundJob> {
+            let launches = Arc::clone(&self.launches);
+            let counter = BackgroundJob::new(
+                "counter",
+                JobTrigger::Interval(Duration::from_millis(10)),
+                move |context| {
+                    assert_eq!(context.service_id(), 5);
+                    assert_eq!(context.height(), Height(0));
+                    launches.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                },
+            );
+            let failing = BackgroundJob::new(
+                "failing",
+                JobTrigger::Interval(Duration::from_millis(10)),
+                |_| panic!("Job failure"),
+            );
+            vec![counter, failing]
+        }
+    }
+
+    #[test]
+    fn scheduler_runs_service_jobs() {
+        let launches = Arc::new(AtomicUsize::new(0));
+        let blockchain = create_initialized_blockchain(vec![Box::new(JobService {
+            launches: Arc::clone(&launches),
+        })]);
+
+        let scheduler = JobScheduler::start(blockchain).expect("No jobs were scheduled");
+        thread::sleep(SCHEDULER_TICK * 5);
+        scheduler.stop();
+
+        assert!(launches.load(Ordering::SeqCst) > 1);
+    }
+}
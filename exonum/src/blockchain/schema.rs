@@ -12,14 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use chrono::{DateTime, Utc};
 use exonum_merkledb::{
-    Entry, IndexAccess, KeySetIndex, ListIndex, MapIndex, MapProof, ObjectHash, ProofListIndex,
-    ProofMapIndex,
+    inspect::IndexInfo, BinaryKey, Entry, IndexAccess, KeySetIndex, ListIndex, MapIndex, MapProof,
+    ObjectHash, ProofListIndex, ProofMapIndex,
 };
 
-use super::{config::StoredConfiguration, Block, BlockProof, Blockchain, TransactionResult};
+use super::{
+    config::StoredConfiguration,
+    events::TransactionEvent,
+    lifecycle::ServiceState,
+    migration::MigrationStatus,
+    oracle::{OracleObservation, OracleValue},
+    Block, BlockProof, Blockchain, TransactionResult, TransactionTag,
+};
 use crate::{
-    crypto::{CryptoHash, Hash, PublicKey},
+    crypto::{self, CryptoHash, Hash, PublicKey},
     helpers::{Height, Round},
     messages::{Connect, Message, Precommit, RawTransaction, Signed},
     proto,
@@ -32,7 +40,7 @@ macro_rules! define_names {
             $name:ident => $value:expr;
         )+
     ) => (
-        $(const $name: &str = concat!("core.", $value);)*
+        $(pub(crate) const $name: &str = concat!("core.", $value);)*
     )
 }
 
@@ -46,6 +54,10 @@ define_names!(
     BLOCKS => "blocks";
     BLOCK_HASHES_BY_HEIGHT => "block_hashes_by_height";
     BLOCK_TRANSACTIONS => "block_transactions";
+    BLOCK_TRANSACTION_RESULTS => "block_transaction_results";
+    BLOCK_EVENTS => "block_events";
+    BLOCK_TIMES => "block_times";
+    BLOCK_METRICS => "block_metrics";
     PRECOMMITS => "precommits";
     CONFIGS => "configs";
     CONFIGS_ACTUAL_FROM => "configs_actual_from";
@@ -53,6 +65,25 @@ define_names!(
     PEERS_CACHE => "peers_cache";
     CONSENSUS_MESSAGES_CACHE => "consensus_messages_cache";
     CONSENSUS_ROUND => "consensus_round";
+    RETENTION_PROGRESS => "retention_progress";
+    SPILLED_TRANSACTIONS => "spilled_transactions";
+    CHAIN_FORKS => "chain_forks";
+    PATCH_HASHES => "patch_hashes";
+    CONFIG_ACTIVATIONS => "config_activations";
+    ORACLE_OBSERVATIONS => "oracle_observations";
+    ORACLE_PENDING_FEEDS => "oracle_pending_feeds";
+    ORACLE_VALUES => "oracle_values";
+    ID_COUNTERS => "id_counters";
+    ARCHIVED_TRANSACTIONS => "archived_transactions";
+    TRANSACTION_GAS => "transaction_gas";
+    TRANSACTION_FEES => "transaction_fees";
+    TRANSACTION_TAGS => "transaction_tags";
+    DEPLOYED_SERVICES => "deployed_services";
+    SERVICE_STATES => "service_states";
+    SERVICE_MIGRATIONS => "service_migrations";
+    NETWORK_ID => "network_id";
+    CONSENSUS_ARCHIVE => "consensus_archive";
+    CONSENSUS_ARCHIVE_HEIGHTS => "consensus_archive_heights";
 );
 
 /// Configuration index.
@@ -85,10 +116,53 @@ impl ConfigReference {
     }
 }
 
+/// Block at which a configuration has become actual.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::ConfigActivation", crate = "crate")]
+pub struct ConfigActivation {
+    /// Height of the block.
+    height: Height,
+    /// Hash of the block.
+    block_hash: Hash,
+}
+
+impl ConfigActivation {
+    /// Creates a new activation record.
+    pub fn new(height: Height, block_hash: Hash) -> Self {
+        Self { height, block_hash }
+    }
+
+    /// Height of the first block created with the configuration.
+    pub fn height(&self) -> Height {
+        self.height
+    }
+
+    /// Hash of the first block created with the configuration.
+    pub fn block_hash(&self) -> &Hash {
+        &self.block_hash
+    }
+}
+
+/// Performance record of a block committed by the node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::BlockMetrics", crate = "crate")]
+pub struct BlockMetrics {
+    /// Height of the block.
+    pub height: Height,
+    /// Number of transactions in the block.
+    pub tx_count: u32,
+    /// Total size of the transaction messages of the block in bytes.
+    pub block_size: u64,
+    /// Time spent by the node executing the block, in microseconds.
+    pub execution_time_us: u64,
+    /// Number of uncommitted transactions known to the node when it executed the block.
+    pub pool_size: u64,
+}
+
 /// Transaction location in a block.
 /// The given entity defines the block where the transaction was
 /// included and the position of this transaction in that block.
-#[derive(Debug, Serialize, Deserialize, PartialEq, ProtobufConvert)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ProtobufConvert)]
 #[exonum(pb = "proto::TxLocation", crate = "crate")]
 pub struct TxLocation {
     /// Height of the block where the transaction was included.
@@ -150,6 +224,32 @@ where
         ProofMapIndex::new(TRANSACTION_RESULTS, self.access.clone())
     }
 
+    /// Returns a table that keeps the amount of gas consumed by every committed
+    /// transaction, including the failed ones.
+    pub fn transaction_gas(&self) -> MapIndex<T, Hash, u64> {
+        MapIndex::new(TRANSACTION_GAS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the fees paid for the committed transactions.
+    /// Free transactions are not listed.
+    pub fn transaction_fees(&self) -> MapIndex<T, Hash, u64> {
+        MapIndex::new(TRANSACTION_FEES, self.access.clone())
+    }
+
+    /// Returns a table that keeps the tags of the transactions submitted to this node.
+    /// The table is local to the node and is not a part of the blockchain state.
+    pub fn transaction_tags(&self) -> MapIndex<T, Hash, Vec<u8>> {
+        MapIndex::new(TRANSACTION_TAGS, self.access.clone())
+    }
+
+    /// Returns the tag of the transaction, if the transaction has been submitted
+    /// to this node with a tag.
+    pub fn transaction_tag(&self, tx_hash: &Hash) -> Option<TransactionTag> {
+        self.transaction_tags()
+            .get(tx_hash)
+            .map(|bytes| TransactionTag::new(bytes).expect("Invalid transaction tag"))
+    }
+
     /// Returns an entry that represents a count of committed transactions in the blockchain.
     pub(crate) fn transactions_len_index(&self) -> Entry<T, u64> {
         Entry::new(TRANSACTIONS_LEN, self.access.clone())
@@ -200,6 +300,46 @@ where
         ProofListIndex::new_in_family(BLOCK_TRANSACTIONS, &height, self.access.clone())
     }
 
+    /// Returns a table that keeps the hashes of the execution results of the transactions
+    /// for each block, in the order of the transactions in the block. The root hash of
+    /// the table is stored in the block header, see [`Block::tx_results_hash`].
+    ///
+    /// [`Block::tx_results_hash`]: struct.Block.html#method.tx_results_hash
+    pub fn block_transaction_results(&self, height: Height) -> ProofListIndex<T, Hash> {
+        let height: u64 = height.into();
+        ProofListIndex::new_in_family(BLOCK_TRANSACTION_RESULTS, &height, self.access.clone())
+    }
+
+    /// Returns a table that keeps the events emitted by the transactions of each block,
+    /// in the order of emission. The root hash of the table is stored in the block header,
+    /// see [`Block::events_hash`].
+    ///
+    /// [`Block::events_hash`]: struct.Block.html#method.events_hash
+    pub fn block_events(&self, height: Height) -> ProofListIndex<T, TransactionEvent> {
+        let height: u64 = height.into();
+        ProofListIndex::new_in_family(BLOCK_EVENTS, &height, self.access.clone())
+    }
+
+    /// Returns a table that keeps the consensus time of the blocks, indexed by the block
+    /// height. The consensus time is computed from the precommits for the previous block,
+    /// see [`next_block_time`]; the genesis block and the block following it have
+    /// no consensus time.
+    ///
+    /// The time of a block is recorded before its transactions are executed, and the table
+    /// is a part of the state hash.
+    ///
+    /// [`next_block_time`]: #method.next_block_time
+    pub fn block_times(&self) -> ProofMapIndex<T, u64, DateTime<Utc>> {
+        ProofMapIndex::new(BLOCK_TIMES, self.access.clone())
+    }
+
+    /// Returns a table that keeps the performance records of the blocks committed
+    /// by the node, indexed by the block height. The records are local to the node
+    /// and are not a part of the state hash.
+    pub fn block_metrics(&self) -> MapIndex<T, u64, BlockMetrics> {
+        MapIndex::new(BLOCK_METRICS, self.access.clone())
+    }
+
     /// Returns a table that keeps a list of precommits for the block with the given hash.
     pub fn precommits(&self, hash: &Hash) -> ListIndex<T, Signed<Precommit>> {
         ListIndex::new_in_family(PRECOMMITS, hash, self.access.clone())
@@ -226,13 +366,13 @@ where
     /// scattered across distinct services and their tables. Sum is performed by
     /// means of computing the root hash of this table.
     ///
-    /// - Table **key** is 32 bytes of normalized coordinates of a service
-    /// table, as returned by the `service_table_unique_key` helper function.
+    /// - Table **key** is the hash of the full name of a service table, as returned
+    /// by the `state_table_key` helper function.
     /// - Table **value** is the root hash of a service table, which contributes
     /// to the `state_hash` of the resulting block.
     ///
-    /// Core tables participate in the resulting state_hash with `CORE_SERVICE`
-    /// service_id. Their vector is returned by the `core_state_hash` method.
+    /// Core tables participate in the resulting state_hash under their names as well.
+    /// They are returned by the `core_state_tables` method.
     pub fn state_hash_aggregator(&self) -> ProofMapIndex<T, Hash, Hash> {
         ProofMapIndex::new(STATE_HASH_AGGREGATOR, self.access.clone())
     }
@@ -257,11 +397,191 @@ where
             .unwrap_or_else(Round::first)
     }
 
+    /// Returns heights below which the data of each retention category has been pruned.
+    pub(crate) fn retention_progress(&self) -> MapIndex<T, String, u64> {
+        MapIndex::new(RETENTION_PROGRESS, self.access.clone())
+    }
+
+    /// Returns a set of uncommitted transactions moved from the node memory to the storage
+    /// during the current height. These transactions are stored in `transactions`, but are
+    /// added to the pool only after the next commit. Adding them to the pool earlier would
+    /// invalidate the pool length recorded in the blocks executed before the move.
+    pub(crate) fn spilled_transactions(&self) -> KeySetIndex<T, Hash> {
+        KeySetIndex::new(SPILLED_TRANSACTIONS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the chain identifiers assigned by the forks of the chain,
+    /// indexed by the height of the last block preserved by the fork. See [`fork_chain`]
+    /// for details.
+    ///
+    /// [`fork_chain`]: fn.fork_chain.html
+    pub fn chain_forks(&self) -> MapIndex<T, u64, String> {
+        MapIndex::new(CHAIN_FORKS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the hashes of the changes made by the blocks, indexed
+    /// by the block height. The hashes are recorded only if enabled in the node
+    /// configuration and are not a part of the state hash; hence, they may be absent
+    /// for some blocks.
+    pub fn patch_hashes(&self) -> MapIndex<T, u64, Hash> {
+        MapIndex::new(PATCH_HASHES, self.access.clone())
+    }
+
+    /// Returns a table that keeps the consensus messages observed by the node at
+    /// the given height, indexed by the message hash. The messages are recorded only
+    /// if the consensus archive is enabled in the node configuration and are not
+    /// a part of the state hash.
+    pub fn consensus_archive(&self, height: Height) -> MapIndex<T, Hash, Message> {
+        MapIndex::new_in_family(CONSENSUS_ARCHIVE, &height.0, self.access.clone())
+    }
+
+    /// Returns a table that keeps the number of the archived consensus messages,
+    /// indexed by the height of the messages.
+    pub fn consensus_archive_heights(&self) -> MapIndex<T, u64, u64> {
+        MapIndex::new(CONSENSUS_ARCHIVE_HEIGHTS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the blocks at which the configurations have become
+    /// actual, indexed by the configuration hash. The table is filled when such a block
+    /// is created and allows to find the block of a configuration without scanning
+    /// the history of the blockchain.
+    pub fn config_activations(&self) -> MapIndex<T, Hash, ConfigActivation> {
+        MapIndex::new(CONFIG_ACTIVATIONS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the latest observations of the oracle feed with the given
+    /// full name, indexed by the service keys of the validators.
+    pub(crate) fn oracle_observations(
+        &self,
+        feed: &str,
+    ) -> MapIndex<T, PublicKey, OracleObservation> {
+        MapIndex::new_in_family(ORACLE_OBSERVATIONS, feed, self.access.clone())
+    }
+
+    /// Returns a set of the oracle feeds that have received observations in the block
+    /// being created.
+    pub(crate) fn oracle_pending_feeds(&self) -> KeySetIndex<T, String> {
+        KeySetIndex::new(ORACLE_PENDING_FEEDS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the aggregated values of the oracle feeds, indexed
+    /// by the full feed name (`service_name.feed_name`). See [`oracle`] for details.
+    ///
+    /// [`oracle`]: oracle/index.html
+    pub fn oracle_values(&self) -> ProofMapIndex<T, String, OracleValue> {
+        ProofMapIndex::new(ORACLE_VALUES, self.access.clone())
+    }
+
+    /// Returns a table that keeps the last identifiers issued by
+    /// [`TransactionContext::next_id`] to the service, indexed by the namespace.
+    ///
+    /// [`TransactionContext::next_id`]: struct.TransactionContext.html#method.next_id
+    pub fn id_counters(&self, service_id: u16) -> MapIndex<T, String, u64> {
+        MapIndex::new_in_family(ID_COUNTERS, &service_id, self.access.clone())
+    }
+
+    /// Returns a set of the hashes of the committed transactions offloaded to
+    /// the transaction archive. See [`archive`] for details.
+    ///
+    /// [`archive`]: archive/index.html
+    pub fn archived_transactions(&self) -> KeySetIndex<T, Hash> {
+        KeySetIndex::new(ARCHIVED_TRANSACTIONS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the heights of the blocks that have deployed
+    /// the deployable services, indexed by the service identifier. See [`deployment`]
+    /// for details.
+    ///
+    /// [`deployment`]: deployment/index.html
+    pub fn deployed_services(&self) -> MapIndex<T, u16, u64> {
+        MapIndex::new(DEPLOYED_SERVICES, self.access.clone())
+    }
+
+    /// Returns a table that keeps the lifecycle states of the inactive services,
+    /// indexed by the service identifier. Services missing from the table are active.
+    /// See [`lifecycle`] for details.
+    ///
+    /// [`lifecycle`]: lifecycle/index.html
+    pub fn service_states(&self) -> MapIndex<T, u16, ServiceState> {
+        MapIndex::new(SERVICE_STATES, self.access.clone())
+    }
+
+    /// Returns a table that keeps the data versions of the services and the results
+    /// of their latest migrations, indexed by the service identifier. Services missing
+    /// from the table keep the data of the version `0`. See [`migration`] for details.
+    ///
+    /// [`migration`]: migration/index.html
+    pub fn service_migrations(&self) -> MapIndex<T, u16, MigrationStatus> {
+        MapIndex::new(SERVICE_MIGRATIONS, self.access.clone())
+    }
+
+    /// Returns the identifier of the network set in the genesis configuration.
+    /// The messages of the network are signed for this identifier.
+    pub fn network_id(&self) -> u32 {
+        Entry::new(NETWORK_ID, self.access.clone())
+            .get()
+            .unwrap_or_default()
+    }
+
+    /// Returns the identifier of the chain, which is empty unless the chain has been forked.
+    pub fn chain_id(&self) -> String {
+        self.chain_forks().values().last().unwrap_or_default()
+    }
+
     /// Returns the block hash for the given height.
     pub fn block_hash_by_height(&self, height: Height) -> Option<Hash> {
         self.block_hashes_by_height().get(height.into())
     }
 
+    /// Returns the consensus time of the block at the given height, or `None` if
+    /// the block is not committed or has been created without the consensus time.
+    pub fn block_time(&self, height: Height) -> Option<DateTime<Utc>> {
+        self.block_times().get(&height.0)
+    }
+
+    /// Returns the consensus time of the latest committed block, or `None` if
+    /// the block has been created without the consensus time.
+    pub fn last_block_time(&self) -> Option<DateTime<Utc>> {
+        self.block_time(self.height())
+    }
+
+    /// Returns the consensus time of the next block given the precommits for the latest
+    /// committed block, which are included into the `Propose` of the next block.
+    ///
+    /// The time is the weighted median of the precommit times, i.e., the earliest time
+    /// such that the validators with at least half of the weight of the precommits have
+    /// precommitted no later. The precommits are weighted according to the configuration
+    /// of the latest committed block, so more than `1/3` of the total weight is needed
+    /// to move the time outside of the range of the times of other validators. The time
+    /// does not decrease along the chain: if the median is before the time of the latest
+    /// committed block, the latter is used. Returns `None` if there are no precommits.
+    pub fn next_block_time(&self, precommits: &[Signed<Precommit>]) -> Option<DateTime<Utc>> {
+        let config = self.configuration_by_height(self.height());
+        let mut times: Vec<_> = precommits
+            .iter()
+            .map(|precommit| {
+                (
+                    precommit.time(),
+                    config.weight_of(Some(precommit.validator())),
+                )
+            })
+            .collect();
+        times.sort();
+
+        let total_weight = times
+            .iter()
+            .fold(0_u64, |total, (_, weight)| total.saturating_add(*weight));
+        let mut accumulated = 0_u64;
+        let (median, _) = times.into_iter().find(|(_, weight)| {
+            accumulated = accumulated.saturating_add(*weight);
+            u128::from(accumulated) * 2 >= u128::from(total_weight)
+        })?;
+        Some(
+            self.last_block_time()
+                .map_or(median, |last_block_time| last_block_time.max(median)),
+        )
+    }
+
     /// Returns the block for the given height with the proof of its inclusion.
     pub fn block_and_precommits(&self, height: Height) -> Option<BlockProof> {
         let block_hash = match self.block_hash_by_height(height) {
@@ -362,6 +682,29 @@ where
             .unwrap_or_else(|| panic!("Config with hash {:?} is absent in configs table", cfg_hash))
     }
 
+    /// Returns the configuration actual at the given height, or `None` if there
+    /// are no configurations yet.
+    pub(crate) fn configuration_at(&self, height: Height) -> Option<StoredConfiguration> {
+        self.configs_actual_from()
+            .iter()
+            .rev()
+            .find(|cfg_ref| cfg_ref.actual_from() <= height)
+            .and_then(|cfg_ref| self.configuration_by_hash(cfg_ref.cfg_hash()))
+    }
+
+    /// Returns the configuration that becomes actual exactly at the given height, if any.
+    pub(crate) fn configuration_activated_at(&self, height: Height) -> Option<StoredConfiguration> {
+        let cfg_ref = self
+            .configs_actual_from()
+            .iter()
+            .rev()
+            .find(|cfg_ref| cfg_ref.actual_from() <= height)?;
+        if cfg_ref.actual_from() != height {
+            return None;
+        }
+        self.configuration_by_hash(cfg_ref.cfg_hash())
+    }
+
     /// Returns the configuration for the given configuration hash.
     pub fn configuration_by_hash(&self, hash: &Hash) -> Option<StoredConfiguration> {
         self.configs().get(hash)
@@ -369,19 +712,58 @@ where
 
     /// Returns the `state_hash` table for core tables.
     pub fn core_state_hash(&self) -> Vec<Hash> {
-        vec![
-            self.configs().object_hash(),
-            self.transaction_results().object_hash(),
-        ]
+        self.core_state_tables()
+            .into_iter()
+            .map(|(_, hash)| hash)
+            .collect()
+    }
+
+    /// Returns the root hashes of the core tables aggregated into the state hash,
+    /// together with the full names of the tables.
+    pub fn core_state_tables(&self) -> Vec<(String, Hash)> {
+        let mut tables = vec![
+            (CONFIGS.to_owned(), self.configs().object_hash()),
+            (
+                TRANSACTION_RESULTS.to_owned(),
+                self.transaction_results().object_hash(),
+            ),
+        ];
+        // The blocks of a fork differ from the blocks of the original chain
+        // at the same heights, since the state of the fork includes its identifier.
+        let chain_id = self.chain_id();
+        if !chain_id.is_empty() {
+            tables.push((CHAIN_FORKS.to_owned(), crypto::hash(chain_id.as_bytes())));
+        }
+        // Networks with different identifiers have different genesis blocks.
+        let network_id = self.network_id();
+        if network_id != 0 {
+            tables.push((
+                NETWORK_ID.to_owned(),
+                crypto::hash(&network_id.to_le_bytes()),
+            ));
+        }
+        // Added only once a block with the consensus time is created, so that the state
+        // hash of the genesis block is unchanged.
+        let block_times = self.block_times();
+        if block_times.keys().next().is_some() {
+            tables.push((BLOCK_TIMES.to_owned(), block_times.object_hash()));
+        }
+        // Added only once an oracle feed is aggregated, so that the state hashes
+        // of the chains without oracles are unchanged.
+        let oracle_values = self.oracle_values();
+        if oracle_values.keys().next().is_some() {
+            tables.push((ORACLE_VALUES.to_owned(), oracle_values.object_hash()));
+        }
+        tables
     }
 
     /// Constructs a proof of inclusion of a root hash of a specific service
     /// table into the block `state_hash`.
     ///
-    /// The `service_id` and `table_idx` are automatically combined to form the key of the
-    /// required service table; this key serves as a search query for the method.
-    /// The service table key is uniquely identified by a `(u16, u16)` tuple
-    /// of table coordinates.
+    /// The table is identified by its full name, as returned by the `state_hash_tables`
+    /// method of the `Service` trait, or `core_state_tables` for the core tables.
+    /// The name is hashed to form the key of the table; this key serves as a search query
+    /// for the method.
     ///
     /// If found, the method returns the root hash as a value of the proof leaf
     /// corresponding to the required service table key. Otherwise, a partial
@@ -391,29 +773,37 @@ where
     /// entity stored in the blockchain state at a specific height. The proof is
     /// tied to the `state_hash` of the corresponding `Block`. State of some meta tables
     /// of core and services isn't tracked.
-    ///
-    /// # Arguments
-    ///
-    /// * `service_id` - `service_id` as returned by instance of type of
-    /// `Service` trait.
-    /// * `table_idx` - index of the service table in `Vec`, returned by the
-    /// `state_hash` method of an instance of a type of the `Service` trait.
-    pub fn get_proof_to_service_table(
-        &self,
-        service_id: u16,
-        table_idx: usize,
-    ) -> MapProof<Hash, Hash> {
-        let key = Blockchain::service_table_unique_key(service_id, table_idx);
+    pub fn get_proof_to_service_table(&self, name: &str) -> MapProof<Hash, Hash> {
+        let key = Blockchain::state_table_key(name);
         let sum_table = self.state_hash_aggregator();
         sum_table.get_proof(key)
     }
 
+    /// Marks the chain as forked after the block at the given height, appending
+    /// the suffix to the chain identifier. Returns the new chain identifier.
+    pub(crate) fn add_chain_fork(&mut self, height: Height, suffix: &str) -> String {
+        let chain_id = match self.chain_id() {
+            ref previous if previous.is_empty() => suffix.to_owned(),
+            previous => format!("{}.{}", previous, suffix),
+        };
+        self.chain_forks().put(&height.0, chain_id.clone());
+        chain_id
+    }
+
     /// Saves the given consensus round value into the storage.
     pub(crate) fn set_consensus_round(&mut self, round: Round) {
         let mut entry: Entry<T, _> = Entry::new(CONSENSUS_ROUND, self.access.clone());
         entry.set(round);
     }
 
+    /// Saves the identifier of the network. The zero identifier is not stored.
+    pub(crate) fn set_network_id(&mut self, network_id: u32) {
+        if network_id != 0 {
+            let mut entry: Entry<T, _> = Entry::new(NETWORK_ID, self.access.clone());
+            entry.set(network_id);
+        }
+    }
+
     /// Adds a new configuration to the blockchain, which will become actual at
     /// the `actual_from` height in `config_data`.
     pub fn commit_configuration(&mut self, config_data: StoredConfiguration) {
@@ -462,6 +852,30 @@ where
         self.transactions().put(&tx.hash(), tx);
     }
 
+    /// Stores the uncommitted transaction outside of the pool. See `spilled_transactions`.
+    pub(crate) fn spill_transaction(&mut self, tx: Signed<RawTransaction>) {
+        let hash = tx.hash();
+        self.spilled_transactions().insert(hash);
+        self.transactions().put(&hash, tx);
+    }
+
+    /// Adds the spilled transactions which have not been committed yet into the pool.
+    /// Returns the number of added transactions.
+    pub(crate) fn pool_spilled_transactions(&mut self) -> usize {
+        let hashes: Vec<Hash> = self.spilled_transactions().iter().collect();
+        let mut pooled = 0;
+        for hash in hashes {
+            if !self.transactions_locations().contains(&hash) {
+                self.transactions_pool().insert(hash);
+                pooled += 1;
+            }
+        }
+        let pool_len = self.transactions_pool_len_index().get().unwrap_or(0);
+        self.transactions_pool_len_index().set(pool_len + pooled as u64);
+        self.spilled_transactions().clear();
+        pooled
+    }
+
     /// Changes the transaction status from `in_pool`, to `committed`.
     pub(crate) fn commit_transaction(&mut self, hash: &Hash, tx: Signed<RawTransaction>) {
         if !self.transactions().contains(hash) {
@@ -514,7 +928,24 @@ where
 
     /// Returns the next height of the blockchain.
     /// Its value is equal to "height of the latest committed block" + 1.
-    fn next_height(&self) -> Height {
+    pub(crate) fn next_height(&self) -> Height {
         Height(self.block_hashes_by_height().len())
     }
 }
+
+/// Checks whether the index is a core table changed on behalf of the service with
+/// the given identifier, such as the counters of `TransactionContext::next_id` or
+/// the events of `TransactionContext::emit_event`.
+pub(crate) fn is_service_core_table(index: &IndexInfo, service_id: u16) -> bool {
+    if index.name() == BLOCK_EVENTS {
+        return true;
+    }
+    let mut family_id = vec![0; service_id.size()];
+    service_id.write(&mut family_id);
+    index.name() == ID_COUNTERS && index.family_id() == Some(&family_id[..])
+}
+
+/// Returns the names of the tables changed by `Schema::commit_configuration`.
+pub(crate) fn configuration_tables() -> [&'static str; 2] {
+    [CONFIGS, CONFIGS_ACTUAL_FROM]
+}
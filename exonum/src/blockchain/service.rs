@@ -28,16 +28,74 @@ use std::{
 };
 
 use crate::{
-    api::{websocket, ServiceApiBuilder},
-    blockchain::{ConsensusConfig, Schema, StoredConfiguration, ValidatorKeys},
+    api::{
+        access_log::ApiMetrics,
+        node::public::{AuthorNotification, AuthorWatchers, BlockProofCache, TransactionWaiters},
+        websocket, ServiceApiBuilder,
+    },
+    blockchain::{Block, ConsensusConfig, Schema, StoredConfiguration, ValidatorKeys},
     crypto::{Hash, PublicKey, SecretKey},
     events::network::ConnectedPeerAddr,
+    explorer::BlockHeaderCache,
     helpers::{Height, Milliseconds, ValidatorId},
     messages::{Message, RawTransaction, ServiceTransaction, Signed},
-    node::{ApiSender, ConnectInfo, NodeRole, State},
+    node::{
+        ApiSender, BlockAssemblyLog, ClockSkewInfo, ConnectInfo, FiringAlerts, NodeRole,
+        RejectedTransactionsLog, ResourceUsage, State,
+    },
 };
 
-use super::transaction::Transaction;
+use super::{
+    access::AccessControlList,
+    block::AdditionalHeaders,
+    namespace::Namespace,
+    oracle::OracleFeed,
+    retention::ReclaimedSpace,
+    scheduler::BackgroundJob,
+    transaction::{ExecutionError, Transaction, TransactionContext, TransactionResult},
+};
+
+/// Parameters of a service instance distinct from the service code.
+///
+/// A service implementation keeping the instance parameters can be registered
+/// in the same blockchain several times, e.g., to run two token instances. Each
+/// instance has its own identifier, which is used to dispatch transactions and
+/// to aggregate the state hash, its own API mount point and its own tables, named
+/// with [`table_name`]. Transactions of the instance can access its parameters
+/// with [`TransactionContext::instance`].
+///
+/// [`table_name`]: #method.table_name
+/// [`TransactionContext::instance`]: struct.TransactionContext.html#method.instance
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ServiceInstance {
+    id: u16,
+    name: String,
+}
+
+impl ServiceInstance {
+    /// Creates the parameters of the instance with the given identifier and name.
+    pub fn new<S: Into<String>>(id: u16, name: S) -> Self {
+        Self {
+            id,
+            name: name.into(),
+        }
+    }
+
+    /// Returns the identifier of the instance.
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Returns the name of the instance.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the table of the instance, prefixed with the instance name.
+    pub fn table_name(&self, table: &str) -> String {
+        format!("{}.{}", self.name, table)
+    }
+}
 
 /// A trait that describes the business logic of a certain service.
 ///
@@ -76,8 +134,8 @@ use super::transaction::Transaction;
 ///         MyServiceSchema { view }
 ///     }
 ///
-///     fn state_hash(&self) -> Vec<Hash> {
-///         // Calculates the state hash of the service
+///     fn state_hash_tables(&self) -> Vec<(String, Hash)> {
+///         // Calculates the root hashes of the service tables
 /// #       vec![]
 ///     }
 /// }
@@ -121,8 +179,8 @@ use super::transaction::Transaction;
 ///         "my_special_unique_service"
 ///     }
 ///
-///     fn state_hash(&self, snapshot: &Snapshot) -> Vec<Hash> {
-///         MyServiceSchema::new(snapshot).state_hash()
+///     fn state_hash_tables(&self, snapshot: &Snapshot) -> Vec<(String, Hash)> {
+///         MyServiceSchema::new(snapshot).state_hash_tables()
 ///     }
 ///
 ///     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<Transaction>, failure::Error> {
@@ -138,23 +196,44 @@ use super::transaction::Transaction;
 pub trait Service: Send + Sync + 'static {
     /// Service identifier for database schema and service messages.
     /// This ID must be unique within the blockchain.
+    ///
+    /// The identifier may be a parameter of the service instance rather than
+    /// a constant, so that the same implementation can be registered several times.
+    /// See [`ServiceInstance`] for details.
+    ///
+    /// [`ServiceInstance`]: struct.ServiceInstance.html
     fn service_id(&self) -> u16;
 
     /// A comprehensive string service name. This name must be unique within the
-    /// blockchain.
+    /// blockchain. The name is used as the mount point of the service API and
+    /// as the key of the service configuration.
     fn service_name(&self) -> &str;
 
-    /// Returns a list of root hashes of tables that determine the current state
-    /// of the service database. These hashes are collected from all the services in a common
-    /// `ProofMapIndex` accessible in the core schema as [`state_hash_aggregator`][1].
+    /// Returns the root hashes of the tables that determine the current state of the service
+    /// database together with the full names of the tables. These hashes are collected
+    /// from all the services in a common `ProofMapIndex` accessible in the core schema
+    /// as [`state_hash_aggregator`][1] under the keys derived from the names
+    /// (see [`state_table_key`][2]), so that the proofs to the tables do not depend
+    /// on the order of the tables.
     ///
+    /// The tables should be named by the names of the corresponding indexes, which must
+    /// start with the service name followed by a dot, e.g., `my_service.wallets`.
     /// An empty vector can be returned if the service does not influence the blockchain state.
     ///
-    /// See also [`service_table_unique_key`][2].
-    ///
     /// [1]: struct.Schema.html#method.state_hash_aggregator
-    /// [2]: struct.Blockchain.html#method.service_table_unique_key
-    fn state_hash(&self, snapshot: &dyn Snapshot) -> Vec<Hash>;
+    /// [2]: struct.Blockchain.html#method.state_table_key
+    fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)>;
+
+    /// Returns the root hashes of the tables returned by [`state_hash_tables`][1]
+    /// without their names.
+    ///
+    /// [1]: #tymethod.state_hash_tables
+    fn state_hash(&self, snapshot: &dyn Snapshot) -> Vec<Hash> {
+        self.state_hash_tables(snapshot)
+            .into_iter()
+            .map(|(_, hash)| hash)
+            .collect()
+    }
 
     /// Tries to create a `Transaction` from the given raw message.
     ///
@@ -176,6 +255,130 @@ pub trait Service: Send + Sync + 'static {
     /// [the `Service` example above](#examples).
     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error>;
 
+    /// Returns the authorization requirements for the transactions of the service.
+    /// The core checks them before invoking [`Transaction::execute`]; transactions
+    /// of unauthorized authors are committed with the `Unauthorized` status without
+    /// being executed.
+    ///
+    /// The method is invoked once when the blockchain is created.
+    ///
+    /// *Default implementation allows all transactions to be authored by anyone.*
+    ///
+    /// [`Transaction::execute`]: trait.Transaction.html#tymethod.execute
+    fn access_control(&self) -> AccessControlList {
+        AccessControlList::default()
+    }
+
+    /// Returns the namespace confining the tables the service is allowed to change
+    /// in its transactions and hooks. See [`Namespace`] for details.
+    ///
+    /// The method is invoked once when the blockchain is created.
+    ///
+    /// *Default implementation returns `None`, so the service is not restricted.*
+    ///
+    /// [`Namespace`]: struct.Namespace.html
+    fn namespace(&self) -> Option<Namespace> {
+        None
+    }
+
+    /// Returns the external data feeds of the service, which are observed by
+    /// the validators and aggregated by the core. See [`oracle`] for details.
+    ///
+    /// The method is invoked once when the blockchain is created.
+    ///
+    /// *Default implementation returns no feeds.*
+    ///
+    /// [`oracle`]: oracle/index.html
+    fn oracle_feeds(&self) -> Vec<OracleFeed> {
+        Vec::new()
+    }
+
+    /// Returns the names of the optional behaviors of the service, which are activated
+    /// through the service configuration. See [`features`] for details.
+    ///
+    /// *Default implementation declares no features.*
+    ///
+    /// [`features`]: features/index.html
+    fn features(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Returns `true` if the service is not initialized in the genesis block, but is
+    /// deployed to the running blockchain by a configuration that includes its section.
+    /// See [`deployment`] for details.
+    ///
+    /// *Default implementation returns `false`, i.e., the service is deployed
+    /// in the genesis block.*
+    ///
+    /// [`deployment`]: deployment/index.html
+    fn is_deployable(&self) -> bool {
+        false
+    }
+
+    /// Handles a call of the service method made by a transaction of another service
+    /// with [`TransactionContext::call`]. The `context` belongs to this service, and
+    /// its [`caller`] returns the identifier of the calling service. The returned error
+    /// is propagated to the caller.
+    ///
+    /// The service should panic if it does not handle the method, which aborts
+    /// the calling transaction. See [`calls`] for details.
+    ///
+    /// *Default implementation panics, i.e., the service does not handle any calls.*
+    ///
+    /// [`TransactionContext::call`]: struct.TransactionContext.html#method.call
+    /// [`caller`]: struct.TransactionContext.html#method.caller
+    /// [`calls`]: calls/index.html
+    fn handle_call(
+        &self,
+        context: &TransactionContext,
+        method: &str,
+        args: &[u8],
+    ) -> Result<Vec<u8>, ExecutionError> {
+        panic!(
+            "Service <{}> does not handle method `{}`",
+            self.service_name(),
+            method
+        )
+    }
+
+    /// Returns `true` if the service is built into the framework, such as the
+    /// configuration and the time services. Only the built-in services may use
+    /// the identifiers in [`RESERVED_SERVICE_IDS`].
+    ///
+    /// *Default implementation returns `false`.*
+    ///
+    /// [`RESERVED_SERVICE_IDS`]: constant.RESERVED_SERVICE_IDS.html
+    fn is_builtin(&self) -> bool {
+        false
+    }
+
+    /// Returns the version of the layout of the data stored by the service. A release
+    /// of the service changing the layout increases the version and implements
+    /// [`migrate`](#method.migrate). See [`migration`] for details.
+    ///
+    /// *Default implementation returns `0`.*
+    ///
+    /// [`migration`]: migration/index.html
+    fn data_version(&self) -> u32 {
+        0
+    }
+
+    /// Migrates the data of the service from `old_version` to the next version, i.e.,
+    /// `old_version + 1`. The method is invoked at the beginning of the block activating
+    /// a configuration that specifies a newer data version for the service, once for each
+    /// version step. If the method returns an error or panics, the changes made by
+    /// the migration are discarded and the service is stopped.
+    ///
+    /// *Default implementation returns an error, i.e., the service does not support
+    /// migrations.*
+    fn migrate(&self, old_version: u32, fork: &Fork) -> Result<(), failure::Error> {
+        bail!(
+            "Service <{}> does not support migration from data version {}",
+            self.service_name(),
+            old_version
+        )
+    }
+
     /// Invoked for all deployed services during the blockchain initialization
     /// on genesis block creation each time a node is started.
     /// During the handling of the method the service is able to perform the following activities:
@@ -190,6 +393,49 @@ pub trait Service: Send + Sync + 'static {
         Value::Null
     }
 
+    /// Imports a pre-populated service state from the genesis state dump.
+    ///
+    /// This method is invoked once during the genesis block creation, right after
+    /// [`initialize`](#method.initialize), if the [`GenesisState`] contains a dump for
+    /// this service. After the import, the core checks that the root hashes returned by
+    /// [`state_hash`](#tymethod.state_hash) match the roots declared in the dump.
+    ///
+    /// *Default implementation returns an error, i.e., the service does not support
+    /// state import.*
+    ///
+    /// [`GenesisState`]: struct.GenesisState.html
+    fn import_state(&self, fork: &Fork, data: &Value) -> Result<(), failure::Error> {
+        bail!(
+            "Service <{}> does not support genesis state import",
+            self.service_name()
+        )
+    }
+
+    /// Handles the beginning of a block. This method is invoked for each service
+    /// before execution of the transactions in the block, after `on_config_change`
+    /// if the block activates a configuration. It is not invoked for the genesis block.
+    ///
+    /// A service can use this hook to update its state deterministically once per block,
+    /// for example, to reset per-block counters, accrue interest or expire entries.
+    fn before_transactions(&self, fork: &Fork) {}
+
+    /// Generates transactions to be appended to the current block. This method is invoked
+    /// for each service in the order of their IDs after execution of the transactions of
+    /// the block, before `before_commit`. It is not invoked for the genesis block.
+    ///
+    /// The returned transactions of this service are signed with the
+    /// [system key][system_transaction_author], executed and included into the block
+    /// right away, e.g., to settle the results of the block or to finalize an oracle
+    /// round. Since all nodes generate the transactions independently, they must depend
+    /// only on the blockchain state. A transaction identical to an already committed one
+    /// is skipped, so services should include the block height or a similar nonce
+    /// into the generated transactions.
+    ///
+    /// [system_transaction_author]: fn.system_transaction_author.html
+    fn after_transactions(&self, fork: &Fork) -> Vec<ServiceTransaction> {
+        Vec::new()
+    }
+
     /// A service execution. This method is invoked for each service after execution
     /// of all transactions in the block but before `after_commit` handler.
     ///
@@ -200,6 +446,30 @@ pub trait Service: Send + Sync + 'static {
     /// Service::execute invocations.
     fn before_commit(&self, fork: &Fork) {}
 
+    /// Returns the entries this service adds to the additional headers of the block,
+    /// e.g., an anchoring hash. This method is invoked for each service in the order
+    /// of their IDs after `before_commit`, with the final state of the block. It is
+    /// not invoked for the genesis block.
+    ///
+    /// Since all nodes compute the headers independently, they must depend only on
+    /// the blockchain state. If the key of an entry has already been set by another
+    /// service, the entry is skipped, so services should prefix the keys with their
+    /// names.
+    fn block_headers(&self, snapshot: &dyn Snapshot) -> AdditionalHeaders {
+        AdditionalHeaders::new()
+    }
+
+    /// Handles activation of a new configuration. This method is invoked for each service
+    /// at the beginning of the block at the `actual_from` height of the configuration,
+    /// before the transactions of the block are executed.
+    ///
+    /// A service can re-read its section of [`StoredConfiguration::services`] here and
+    /// update the parameters it keeps in the blockchain state. The method is not invoked
+    /// for the genesis configuration.
+    ///
+    /// [`StoredConfiguration::services`]: struct.StoredConfiguration.html#structfield.services
+    fn on_config_change(&self, fork: &Fork, config: &StoredConfiguration) {}
+
     /// Handles block commit. This handler is invoked for each service after commit of the block.
     /// For example, a service can create one or more transactions if a specific condition
     /// has occurred.
@@ -207,6 +477,66 @@ pub trait Service: Send + Sync + 'static {
     /// *Try not to perform long operations in this handler*.
     fn after_commit(&self, context: &ServiceContext) {}
 
+    /// Handles block commit with the summary of the committed block, which contains
+    /// the header of the block and the transactions of this service included into it.
+    /// The handler is invoked for each service after `after_commit`, so that services
+    /// can react to their own transactions without rescanning the block.
+    ///
+    /// *Try not to perform long operations in this handler*.
+    fn after_commit_block(&self, context: &ServiceContext, summary: &BlockSummary) {}
+
+    /// Handles a message sent by the same service of another validator with
+    /// [`ServiceContext::send_to_validator`]. Messages are delivered directly over
+    /// the peer connections and are not recorded in the blockchain, which makes them
+    /// suitable for off-chain protocols among validators, e.g., distributed key
+    /// generation. Use `context` to reply to the sender.
+    ///
+    /// The sender is authenticated, but the delivery is not guaranteed, and the
+    /// messages can be reordered or replayed by the network. The handler is invoked
+    /// on the node thread, hence it must not block.
+    ///
+    /// *Default implementation ignores the message.*
+    ///
+    /// [`ServiceContext::send_to_validator`]: struct.ServiceContext.html#method.send_to_validator
+    fn handle_validator_message(
+        &self,
+        context: &ServiceContext,
+        from: ValidatorId,
+        payload: &[u8],
+    ) {
+    }
+
+    /// Returns background jobs of the service. The jobs are launched by the node on
+    /// a separate thread pool, either periodically or upon reaching certain heights;
+    /// see [`JobTrigger`] for details. Use them instead of spawning threads in
+    /// `after_commit` for long-running activities such as polling external systems.
+    ///
+    /// The method is invoked once on the node start.
+    ///
+    /// *Default implementation returns an empty vector.*
+    ///
+    /// [`JobTrigger`]: enum.JobTrigger.html
+    fn background_jobs(&self) -> Vec<BackgroundJob> {
+        Vec::new()
+    }
+
+    /// Removes the data of the service's ephemeral indexes related to the heights
+    /// below `retain_from`, i.e., the data which is no longer needed according to
+    /// the `ephemeral` retention window of the node. Ephemeral indexes must not
+    /// be aggregated into the state hash, since the pruning is local to the node.
+    ///
+    /// The method is invoked by the compactor thread concurrently with transaction
+    /// processing, hence it must not modify the data that can be changed by
+    /// transactions. The changes made to the `fork` are merged into the storage
+    /// after all services are invoked.
+    ///
+    /// Returns the amount of removed data, which is reported in node metrics.
+    ///
+    /// *Default implementation removes nothing.*
+    fn prune_ephemeral(&self, fork: &Fork, retain_from: Height) -> ReclaimedSpace {
+        ReclaimedSpace::default()
+    }
+
     /// Extends API by handlers of this service. The request handlers are mounted on
     /// the `/api/services/{service_name}` path at the listen address of every
     /// full node in the blockchain network.
@@ -215,6 +545,122 @@ pub trait Service: Send + Sync + 'static {
     fn wire_api(&self, _builder: &mut ServiceApiBuilder) {}
 }
 
+/// Transaction of a service included into a committed block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionSummary {
+    /// Hash of the transaction.
+    pub tx_hash: Hash,
+    /// Identifier of the transaction within the service.
+    pub transaction_id: u16,
+    /// Result of the transaction execution.
+    pub result: TransactionResult,
+}
+
+/// Summary of a committed block passed to [`Service::after_commit_block`].
+///
+/// [`Service::after_commit_block`]: trait.Service.html#method.after_commit_block
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSummary {
+    block: Block,
+    transactions: Vec<TransactionSummary>,
+}
+
+impl BlockSummary {
+    /// Creates a summary of the block with the given transactions of a service
+    /// in the order of their execution.
+    pub fn new(block: Block, transactions: Vec<TransactionSummary>) -> Self {
+        Self {
+            block,
+            transactions,
+        }
+    }
+
+    /// Header of the committed block.
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+
+    /// Transactions of the service in the block in the order of their execution.
+    pub fn transactions(&self) -> &[TransactionSummary] {
+        &self.transactions
+    }
+
+    /// Returns the transactions of the service with the given identifier.
+    pub fn transactions_of_type(
+        &self,
+        transaction_id: u16,
+    ) -> impl Iterator<Item = &TransactionSummary> {
+        self.transactions
+            .iter()
+            .filter(move |tx| tx.transaction_id == transaction_id)
+    }
+}
+
+/// Notification of a committed block sent to the subscribers of [`Blockchain::subscribe`].
+///
+/// [`Blockchain::subscribe`]: struct.Blockchain.html#method.subscribe
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockCommitted {
+    block: Block,
+    transactions: Vec<(u16, TransactionSummary)>,
+}
+
+impl BlockCommitted {
+    /// Collects the transactions of the last block committed to the snapshot.
+    pub(crate) fn from_snapshot(snapshot: &dyn Snapshot) -> Self {
+        let schema = Schema::new(snapshot);
+        let block = schema.last_block();
+        let transactions = schema
+            .block_transactions(block.height())
+            .iter()
+            .map(|tx_hash| {
+                let tx = schema
+                    .transactions()
+                    .get(&tx_hash)
+                    .expect("BUG: Cannot find transaction of the committed block");
+                let result = schema
+                    .transaction_results()
+                    .get(&tx_hash)
+                    .expect("BUG: Cannot find result of the committed transaction");
+                let summary = TransactionSummary {
+                    tx_hash,
+                    transaction_id: tx.payload().transaction_id(),
+                    result,
+                };
+                (tx.service_id(), summary)
+            })
+            .collect();
+        Self {
+            block,
+            transactions,
+        }
+    }
+
+    /// Header of the committed block.
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+
+    /// Transactions of all the services in the block in the order of their execution.
+    pub fn transactions(&self) -> impl Iterator<Item = &TransactionSummary> {
+        self.transactions.iter().map(|(_, tx)| tx)
+    }
+
+    /// Returns the summary of the block for the service with the given identifier,
+    /// i.e., the summary passed to its [`Service::after_commit_block`].
+    ///
+    /// [`Service::after_commit_block`]: trait.Service.html#method.after_commit_block
+    pub fn service_summary(&self, service_id: u16) -> BlockSummary {
+        let transactions = self
+            .transactions
+            .iter()
+            .filter(|(id, _)| *id == service_id)
+            .map(|(_, tx)| tx.clone())
+            .collect();
+        BlockSummary::new(self.block.clone(), transactions)
+    }
+}
+
 /// The current node state on which the blockchain is running, or in other words
 /// execution context. This structure is passed to the `after_commit` method
 /// of the `Service` trait and is used for the interaction between service
@@ -224,9 +670,9 @@ pub struct ServiceContext {
     validator_id: Option<ValidatorId>,
     service_keypair: (PublicKey, SecretKey),
     api_sender: ApiSender,
-    fork: Fork,
+    snapshot: Arc<dyn Snapshot>,
     stored_configuration: StoredConfiguration,
-    height: Height,
+    last_block: Block,
     service_id: u16,
 }
 
@@ -243,11 +689,29 @@ impl ServiceContext {
         fork: Fork,
         service_id: u16,
     ) -> Self {
-        let (stored_configuration, height) = {
-            let schema = Schema::new(&fork);
-            let stored_configuration = schema.actual_configuration();
-            let height = schema.height();
-            (stored_configuration, height)
+        Self::with_snapshot(
+            service_public_key,
+            service_secret_key,
+            api_sender,
+            Arc::new(fork.into_patch()),
+            service_id,
+        )
+    }
+
+    /// Creates service context for the given node based on the database snapshot.
+    ///
+    /// The snapshot may be shared among the contexts of several services, so that
+    /// they observe exactly the same state of the blockchain.
+    pub fn with_snapshot(
+        service_public_key: PublicKey,
+        service_secret_key: SecretKey,
+        api_sender: ApiSender,
+        snapshot: Arc<dyn Snapshot>,
+        service_id: u16,
+    ) -> Self {
+        let (stored_configuration, last_block) = {
+            let schema = Schema::new(snapshot.as_ref());
+            (schema.actual_configuration(), schema.last_block())
         };
         let validator_id = stored_configuration
             .validator_keys
@@ -259,10 +723,10 @@ impl ServiceContext {
             validator_id,
             service_keypair: (service_public_key, service_secret_key),
             api_sender,
-            fork,
+            snapshot,
             stored_configuration,
+            last_block,
             service_id,
-            height,
         }
     }
 
@@ -274,13 +738,27 @@ impl ServiceContext {
 
     /// Returns the current database snapshot. This snapshot is used to
     /// retrieve schema information from the database.
+    ///
+    /// The snapshot is immutable and pinned at the [`last_block`]: in `after_commit`,
+    /// it reflects the state right after the commit of the block even if the node has
+    /// already changed the database since then.
+    ///
+    /// [`last_block`]: #method.last_block
     pub fn snapshot(&self) -> &dyn Snapshot {
-        self.fork.as_ref()
+        self.snapshot.as_ref()
     }
 
     /// Returns the current blockchain height. This height is "height of the last committed block".
     pub fn height(&self) -> Height {
-        self.height
+        self.last_block.height()
+    }
+
+    /// Returns the header of the last committed block, which the [`snapshot`]
+    /// corresponds to. In `after_commit`, this is the block that has just been committed.
+    ///
+    /// [`snapshot`]: #method.snapshot
+    pub fn last_block(&self) -> &Block {
+        &self.last_block
     }
 
     /// Returns the current list of validator public keys.
@@ -313,18 +791,79 @@ impl ServiceContext {
     where
         T: Into<ServiceTransaction> + Transaction,
     {
-        let msg = Message::sign_transaction(
+        self.broadcast_transaction_with_keypair(
             tx,
-            self.service_id,
             self.service_keypair.0,
             &self.service_keypair.1,
         );
+    }
+
+    /// Signs the transaction with the given keypair instead of the service keypair
+    /// of the node and broadcasts it to other nodes in the network. The transaction
+    /// is signed for the network identifier stored in the blockchain.
+    ///
+    /// Services acting as oracles can use this method to author transactions with
+    /// their own identity, e.g., a key loaded by the service from its own key file.
+    pub fn broadcast_transaction_with_keypair<T>(
+        &self,
+        tx: T,
+        public_key: PublicKey,
+        secret_key: &SecretKey,
+    ) where
+        T: Into<ServiceTransaction> + Transaction,
+    {
+        let network_id = Schema::new(self.snapshot.as_ref()).network_id();
+        let msg = Message::sign_transaction_for_network(
+            tx,
+            self.service_id,
+            network_id,
+            public_key,
+            secret_key,
+        );
 
         if let Err(e) = self.api_sender.broadcast_transaction(msg) {
             error!("Couldn't broadcast transaction {}.", e);
         }
     }
 
+    /// Returns the validator elected to emit the transactions triggered by the
+    /// current block.
+    ///
+    /// The heights are allotted to the validators in proportion to their weights, like
+    /// the rounds of the consensus: a validator with weight `w` is elected at `w`
+    /// consecutive heights in each cycle over the validators. All nodes agree
+    /// on the leader without communication.
+    pub fn leader(&self) -> ValidatorId {
+        self.stored_configuration
+            .validator_by_slot(self.height().into())
+    }
+
+    /// Signs and broadcasts the transaction if this node is the [`leader`] at the
+    /// current height. Returns `true` if the transaction has been broadcast.
+    ///
+    /// Use this method instead of [`broadcast_transaction`] for the transactions that
+    /// every validator would create in `after_commit` in response to the same event,
+    /// so that the network receives one transaction per event instead of one per
+    /// validator. The core does not detect that the leader has missed the event
+    /// (e.g., because it is offline), so the service has to call this method on every
+    /// commit until the event is processed. The event is then handled by the first
+    /// leader of the subsequent heights that is online, which is elected within
+    /// one cycle over the validators. Since a transaction of a previous leader may
+    /// still be in flight at that moment, the transaction should be idempotent.
+    ///
+    /// [`leader`]: #method.leader
+    /// [`broadcast_transaction`]: #method.broadcast_transaction
+    pub fn broadcast_if_leader<T>(&self, tx: T) -> bool
+    where
+        T: Into<ServiceTransaction> + Transaction,
+    {
+        if self.validator_id != Some(self.leader()) {
+            return false;
+        }
+        self.broadcast_transaction(tx);
+        true
+    }
+
     /// Broadcast transaction to other nodes in the network.
     /// This transaction should be signed externally.
     pub fn broadcast_signed_transaction(&self, msg: Signed<RawTransaction>) {
@@ -333,6 +872,22 @@ impl ServiceContext {
         }
     }
 
+    /// Sends a message to the same service of the validator with the given identifier.
+    /// The message is signed with the consensus key of the node, so only validators
+    /// are able to send messages.
+    ///
+    /// See [`Service::handle_validator_message`] for the delivery guarantees.
+    ///
+    /// [`Service::handle_validator_message`]: trait.Service.html#method.handle_validator_message
+    pub fn send_to_validator(&self, to: ValidatorId, payload: Vec<u8>) {
+        if let Err(e) = self
+            .api_sender
+            .send_to_validator(to, self.service_id, payload)
+        {
+            error!("Couldn't send message to validator {}: {}.", to, e);
+        }
+    }
+
     /// Returns the actual blockchain global configuration.
     pub fn stored_configuration(&self) -> &StoredConfiguration {
         &self.stored_configuration
@@ -346,11 +901,14 @@ pub struct ApiNodeState {
     outgoing_connections: HashSet<ConnectInfo>,
     reconnects_timeout: HashMap<SocketAddr, Milliseconds>,
     is_enabled: bool,
+    is_stopping: bool,
     node_role: NodeRole,
-    majority_count: usize,
+    majority_weight: u64,
     validators: Vec<ValidatorKeys>,
     broadcast_server_address: Option<Addr<websocket::Server>>,
-    tx_cache_len: usize,
+    tx_cache: HashSet<Hash>,
+    clock_skew: ClockSkewInfo,
+    resource_usage: ResourceUsage,
 }
 
 impl fmt::Debug for ApiNodeState {
@@ -360,8 +918,9 @@ impl fmt::Debug for ApiNodeState {
             .field("outgoing_connections", &self.outgoing_connections)
             .field("reconnects_timeout", &self.reconnects_timeout)
             .field("is_enabled", &self.is_enabled)
+            .field("is_stopping", &self.is_stopping)
             .field("node_role", &self.node_role)
-            .field("majority_count", &self.majority_count)
+            .field("majority_weight", &self.majority_weight)
             .field("validators", &self.validators)
             .finish()
     }
@@ -383,6 +942,15 @@ impl ApiNodeState {
 #[derive(Clone, Debug)]
 pub struct SharedNodeState {
     state: Arc<RwLock<ApiNodeState>>,
+    block_proof_cache: BlockProofCache,
+    block_header_cache: BlockHeaderCache,
+    block_assembly_log: BlockAssemblyLog,
+    rejected_transactions: RejectedTransactionsLog,
+    firing_alerts: FiringAlerts,
+    transaction_waiters: TransactionWaiters,
+    author_watchers: AuthorWatchers,
+    api_metrics: ApiMetrics,
+    storage_inspection: bool,
     /// Timeout to update API state.
     pub state_update_timeout: Milliseconds,
 }
@@ -392,9 +960,31 @@ impl SharedNodeState {
     pub fn new(state_update_timeout: Milliseconds) -> Self {
         Self {
             state: Arc::new(RwLock::new(ApiNodeState::new())),
+            block_proof_cache: BlockProofCache::default(),
+            block_header_cache: BlockHeaderCache::default(),
+            block_assembly_log: BlockAssemblyLog::default(),
+            rejected_transactions: RejectedTransactionsLog::default(),
+            firing_alerts: FiringAlerts::default(),
+            transaction_waiters: TransactionWaiters::default(),
+            author_watchers: AuthorWatchers::default(),
+            api_metrics: ApiMetrics::default(),
+            storage_inspection: false,
             state_update_timeout,
         }
     }
+
+    /// Replaces the cache of block headers used by the explorer API.
+    pub fn with_block_header_cache(mut self, cache: BlockHeaderCache) -> Self {
+        self.block_header_cache = cache;
+        self
+    }
+
+    /// Enables or disables the private endpoints exposing the raw contents of the database.
+    pub fn with_storage_inspection(mut self, enabled: bool) -> Self {
+        self.storage_inspection = enabled;
+        self
+    }
+
     /// Returns a list of connected addresses of other nodes.
     pub fn incoming_connections(&self) -> Vec<ConnectInfo> {
         self.state
@@ -434,10 +1024,11 @@ impl SharedNodeState {
 
         lock.incoming_connections.clear();
         lock.outgoing_connections.clear();
-        lock.majority_count = state.majority_count();
+        lock.majority_weight = state.config().majority_weight();
         lock.node_role = NodeRole::new(state.validator_id());
         lock.validators = state.validators().to_vec();
-        lock.tx_cache_len = state.tx_cache_len();
+        lock.tx_cache = state.tx_cache().keys().cloned().collect();
+        lock.clock_skew = state.clock_skew().info();
 
         for (p, a) in state.connections() {
             match a {
@@ -462,26 +1053,31 @@ impl SharedNodeState {
     /// Returns a boolean value which indicates whether the consensus is achieved.
     pub fn consensus_status(&self) -> bool {
         let lock = self.state.read().expect("Expected read lock.");
-        let mut active_validators = lock
+        let connected_keys = lock
             .incoming_connections
             .iter()
             .chain(lock.outgoing_connections.iter())
-            .filter(|ci| {
-                lock.validators
-                    .iter()
-                    .any(|v| v.consensus_key == ci.public_key)
-            })
-            .count();
+            .map(|ci| ci.public_key)
+            .collect::<HashSet<_>>();
+        let own_id = match lock.node_role {
+            NodeRole::Validator(id) => Some(id),
+            NodeRole::Auditor => None,
+        };
 
-        if lock.node_role.is_validator() {
-            // Peers list doesn't include current node address, so we have to increment its length.
-            // E.g. if we have 3 items in peers list, it means that we have 4 nodes overall.
-            active_validators += 1;
-        }
+        // Peers list doesn't include current node, so it is counted separately.
+        let active_weight = lock
+            .validators
+            .iter()
+            .enumerate()
+            .filter(|(id, keys)| {
+                own_id == Some(ValidatorId(*id as u16))
+                    || connected_keys.contains(&keys.consensus_key)
+            })
+            .fold(0_u64, |total, (_, keys)| total.saturating_add(keys.weight));
 
-        // Just after Node is started (node status isn't updated) majority_count = 0,
-        // so we have to check that majority count is greater than 0.
-        active_validators >= lock.majority_count && lock.majority_count > 0
+        // Just after Node is started (node status isn't updated) majority_weight = 0,
+        // so we have to check that majority weight is greater than 0.
+        active_weight >= lock.majority_weight && lock.majority_weight > 0
     }
 
     /// Returns a boolean value which indicates whether the node is enabled
@@ -498,11 +1094,97 @@ impl SharedNodeState {
         state.is_enabled = is_enabled;
     }
 
+    /// Returns `true` if the node is shutting down and its API is being drained.
+    pub fn is_stopping(&self) -> bool {
+        let state = self.state.read().expect("Expected read lock.");
+        state.is_stopping
+    }
+
+    /// Marks the node as shutting down, which is reported by the health check.
+    pub fn set_stopping(&self) {
+        let mut state = self.state.write().expect("Expected write lock.");
+        state.is_stopping = true;
+    }
+
+    /// Returns the consensus keys of the peers connected to the node, including
+    /// the key of the node itself if it is a validator.
+    pub fn connected_keys(&self) -> HashSet<PublicKey> {
+        let state = self.state.read().expect("Expected read lock.");
+        let mut online = state
+            .incoming_connections
+            .iter()
+            .chain(state.outgoing_connections.iter())
+            .map(|connect_info| connect_info.public_key)
+            .collect::<HashSet<_>>();
+        if let NodeRole::Validator(validator_id) = state.node_role {
+            if let Some(keys) = state.validators.get(validator_id.0 as usize) {
+                online.insert(keys.consensus_key);
+            }
+        }
+        online
+    }
+
+    /// Returns the identifier of the node in the validators list, or `None`
+    /// if the node is an auditor.
+    pub fn validator_id(&self) -> Option<ValidatorId> {
+        let state = self.state.read().expect("Expected read lock.");
+        match state.node_role {
+            NodeRole::Validator(validator_id) => Some(validator_id),
+            NodeRole::Auditor => None,
+        }
+    }
+
     pub(crate) fn set_node_role(&self, role: NodeRole) {
         let mut state = self.state.write().expect("Expected write lock.");
         state.node_role = role;
     }
 
+    /// Returns the cache of block proofs served by the explorer API.
+    pub fn block_proof_cache(&self) -> &BlockProofCache {
+        &self.block_proof_cache
+    }
+
+    /// Returns the cache of block headers used by the explorer API.
+    pub fn block_header_cache(&self) -> &BlockHeaderCache {
+        &self.block_header_cache
+    }
+
+    /// Returns `true` if the private endpoints exposing the raw contents of the database
+    /// are enabled.
+    pub fn is_storage_inspection_enabled(&self) -> bool {
+        self.storage_inspection
+    }
+
+    /// Returns the statistics of the latest blocks proposed by the node.
+    pub fn block_assembly_log(&self) -> &BlockAssemblyLog {
+        &self.block_assembly_log
+    }
+
+    /// Returns the latest transactions rejected by the node.
+    pub fn rejected_transactions(&self) -> &RejectedTransactionsLog {
+        &self.rejected_transactions
+    }
+
+    /// Returns the alerts currently firing on the node.
+    pub fn firing_alerts(&self) -> &FiringAlerts {
+        &self.firing_alerts
+    }
+
+    /// Returns the registry of the API requests waiting for the submitted transactions.
+    pub fn transaction_waiters(&self) -> &TransactionWaiters {
+        &self.transaction_waiters
+    }
+
+    /// Returns the registry of the keys whose transactions are watched by the API clients.
+    pub fn author_watchers(&self) -> &AuthorWatchers {
+        &self.author_watchers
+    }
+
+    /// Returns the latency histograms of the API endpoints.
+    pub fn api_metrics(&self) -> &ApiMetrics {
+        &self.api_metrics
+    }
+
     /// Returns the value of the `state_update_timeout`.
     pub fn state_update_timeout(&self) -> Milliseconds {
         self.state_update_timeout
@@ -549,6 +1231,20 @@ impl SharedNodeState {
         }
     }
 
+    /// Sends the notification to the webhooks and the WebSocket subscribers
+    /// of the transaction author.
+    pub(crate) fn notify_author(&self, notification: AuthorNotification) {
+        self.author_watchers.notify_webhooks(&notification);
+        if let Some(ref address) = self
+            .state
+            .read()
+            .expect("Expected read lock")
+            .broadcast_server_address
+        {
+            address.do_send(websocket::AuthorTransaction(notification))
+        }
+    }
+
     pub(crate) fn shutdown_broadcast_server(&self) {
         let state = self.state.read().expect("Expected read lock");
         if let Some(server) = state.broadcast_server_address.as_ref() {
@@ -558,7 +1254,37 @@ impl SharedNodeState {
 
     pub(crate) fn tx_cache_size(&self) -> usize {
         let state = self.state.read().expect("Expected read lock");
-        state.tx_cache_len
+        state.tx_cache.len()
+    }
+
+    /// Checks whether the transaction is kept in the in-memory cache of the node
+    /// and is not yet flushed to the persistent pool.
+    pub(crate) fn is_tx_cached(&self, hash: &Hash) -> bool {
+        let state = self.state.read().expect("Expected read lock");
+        state.tx_cache.contains(hash)
+    }
+
+    /// Returns the estimated skew of the local clock relative to the validators.
+    pub fn clock_skew(&self) -> ClockSkewInfo {
+        let state = self.state.read().expect("Expected read lock");
+        state.clock_skew
+    }
+
+    /// Returns the resources used by the node as of the latest check.
+    pub fn resource_usage(&self) -> ResourceUsage {
+        let state = self.state.read().expect("Expected read lock");
+        state.resource_usage
+    }
+
+    /// Returns `true` if the node sheds load because its resource usage approaches
+    /// the limits.
+    pub fn is_overloaded(&self) -> bool {
+        self.resource_usage().overloaded
+    }
+
+    pub(crate) fn set_resource_usage(&self, usage: ResourceUsage) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.resource_usage = usage;
     }
 }
 
@@ -0,0 +1,58 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transactions generated by the services while executing a block.
+//!
+//! The transactions returned by [`Service::after_transactions`] are signed with
+//! a keypair derived from a constant seed, so that every node generates the same
+//! messages for the block. Since the secret key is public, such transactions are
+//! never accepted from the clients: they are only executed by the blockchain at the
+//! end of the block and are received from the peers along with the blocks they belong to.
+//!
+//! [`Service::after_transactions`]: trait.Service.html#method.after_transactions
+
+use crate::crypto::{self, gen_keypair_from_seed, PublicKey, SecretKey, Seed};
+use crate::messages::{Message, RawTransaction, ServiceTransaction, Signed};
+
+/// Domain of the seed of the system keypair.
+const SYSTEM_KEY_DOMAIN: &[u8] = b"exonum.system_transactions";
+
+lazy_static! {
+    static ref SYSTEM_KEYPAIR: (PublicKey, SecretKey) = {
+        let hash = crypto::hash(SYSTEM_KEY_DOMAIN);
+        let seed = Seed::from_slice(hash.as_ref()).expect("Hash has the size of a seed");
+        gen_keypair_from_seed(&seed)
+    };
+}
+
+/// Returns the public key authoring the transactions generated by the services
+/// in [`Service::after_transactions`].
+///
+/// [`Service::after_transactions`]: trait.Service.html#method.after_transactions
+pub fn system_transaction_author() -> PublicKey {
+    SYSTEM_KEYPAIR.0
+}
+
+/// Returns `true` if the transaction is signed with the system key.
+pub(crate) fn is_system_transaction(raw: &Signed<RawTransaction>) -> bool {
+    raw.author() == SYSTEM_KEYPAIR.0
+}
+
+/// Signs the transaction generated by the service with the system key.
+pub(crate) fn sign_system_transaction(
+    service_id: u16,
+    tx: ServiceTransaction,
+) -> Signed<RawTransaction> {
+    Message::sign_transaction(tx, service_id, SYSTEM_KEYPAIR.0, &SYSTEM_KEYPAIR.1)
+}
@@ -0,0 +1,111 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Correlation tags of the transactions.
+
+use hex::FromHex;
+use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
+
+use std::fmt;
+
+/// Maximum size of a transaction tag in bytes.
+pub const MAX_TRANSACTION_TAG_SIZE: usize = 64;
+
+/// Opaque tag attached to a transaction by the client submitting it, for example,
+/// an identifier of the corresponding record in an external system.
+///
+/// The tag is not a part of the signed transaction message and is not available
+/// to the services. It is kept by the node the transaction has been submitted to
+/// and is returned together with the transaction by the explorer API, the transaction
+/// status and the WebSocket notifications of that node. Tags are not shared with
+/// the other nodes.
+///
+/// In JSON, the tag is represented as a hex string.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TransactionTag(Vec<u8>);
+
+impl TransactionTag {
+    /// Creates a tag from the given bytes. Returns an error if the tag is longer than
+    /// `MAX_TRANSACTION_TAG_SIZE` bytes.
+    pub fn new(bytes: Vec<u8>) -> Result<Self, failure::Error> {
+        ensure!(
+            bytes.len() <= MAX_TRANSACTION_TAG_SIZE,
+            "Transaction tag size {} exceeds the maximum of {} bytes",
+            bytes.len(),
+            MAX_TRANSACTION_TAG_SIZE
+        );
+        Ok(TransactionTag(bytes))
+    }
+
+    /// Returns the bytes of the tag.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Converts the tag into its bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl fmt::Debug for TransactionTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TransactionTag({})", hex::encode(&self.0))
+    }
+}
+
+impl FromHex for TransactionTag {
+    type Error = failure::Error;
+
+    fn from_hex<T: AsRef<[u8]>>(hex: T) -> Result<Self, Self::Error> {
+        let bytes = Vec::<u8>::from_hex(hex)?;
+        Self::new(bytes)
+    }
+}
+
+impl Serialize for TransactionTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactionTag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Self::from_hex(hex).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_size_is_bounded() {
+        assert!(TransactionTag::new(vec![1; MAX_TRANSACTION_TAG_SIZE]).is_ok());
+        assert!(TransactionTag::new(vec![1; MAX_TRANSACTION_TAG_SIZE + 1]).is_err());
+    }
+
+    #[test]
+    fn tag_is_serialized_as_hex() {
+        let tag = TransactionTag::new(vec![0xab, 0x01]).unwrap();
+        assert_eq!(serde_json::to_value(&tag).unwrap(), json!("ab01"));
+        let parsed: TransactionTag = serde_json::from_value(json!("ab01")).unwrap();
+        assert_eq!(parsed, tag);
+
+        let too_long = "00".repeat(MAX_TRANSACTION_TAG_SIZE + 1);
+        assert!(serde_json::from_value::<TransactionTag>(json!(too_long)).is_err());
+        assert!(serde_json::from_value::<TransactionTag>(json!("xyz")).is_err());
+    }
+}
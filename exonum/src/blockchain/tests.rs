@@ -14,22 +14,136 @@
 
 #![allow(dead_code, unsafe_code)]
 
+use futures::sync::mpsc;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde_json::Value;
 
 use crate::blockchain::{
-    Blockchain, ExecutionError, ExecutionResult, Schema, Service, Transaction, TransactionContext,
-    TransactionSet,
+    Blockchain, ConsensusConfig, ExecutionError, ExecutionResult, GenesisConfig, Schema, Service,
+    StoredConfiguration, Transaction, TransactionContext, TransactionSet, ValidatorKeys,
 };
 use crate::crypto::{gen_keypair, Hash};
 use crate::helpers::{Height, ValidatorId};
-use crate::messages::{Message, RawTransaction};
+use crate::messages::{Message, RawTransaction, ServiceTransaction, Signed};
+use crate::node::ApiSender;
 use crate::proto;
-use exonum_merkledb::{Database, Error as StorageError, Fork, ListIndex, Snapshot};
-use std::collections::BTreeMap;
+use exonum_merkledb::{Database, Error as StorageError, Fork, ListIndex, Snapshot, TemporaryDB};
+use std::{collections::BTreeMap, sync::Arc};
 
 const IDX_NAME: &str = "idx_name";
 const TEST_SERVICE_ID: u16 = 255;
 
+/// Creates a blockchain with the given services on top of the given database.
+pub(crate) fn create_blockchain_in<D>(db: D, services: Vec<Box<dyn Service>>) -> Blockchain
+where
+    D: Into<Arc<dyn Database>>,
+{
+    let service_keypair = gen_keypair();
+    Blockchain::new(
+        db,
+        services,
+        service_keypair.0,
+        service_keypair.1,
+        ApiSender::new(mpsc::channel(0).0),
+    )
+}
+
+/// Creates a blockchain with the given services on top of a temporary database.
+pub(crate) fn create_blockchain(services: Vec<Box<dyn Service>>) -> Blockchain {
+    create_blockchain_in(TemporaryDB::new(), services)
+}
+
+/// Creates a blockchain with the given services and initializes it with a single
+/// validator, whose service key is the service key of the blockchain.
+pub(crate) fn create_initialized_blockchain(services: Vec<Box<dyn Service>>) -> Blockchain {
+    let mut blockchain = create_blockchain(services);
+    let validator_keys = ValidatorKeys::new(gen_keypair().0, blockchain.service_keypair.0);
+    blockchain
+        .initialize(GenesisConfig::new(vec![validator_keys].into_iter()))
+        .unwrap();
+    blockchain
+}
+
+/// Creates a blockchain with the given services and commits the genesis block
+/// with the given consensus parameters and no validators.
+pub(crate) fn create_blockchain_with_consensus(
+    services: Vec<Box<dyn Service>>,
+    consensus: ConsensusConfig,
+) -> Blockchain {
+    let mut blockchain = create_blockchain(services);
+    let config = StoredConfiguration {
+        previous_cfg_hash: Hash::zero(),
+        actual_from: Height(0),
+        validator_keys: vec![],
+        consensus,
+        services: BTreeMap::new(),
+    };
+    let fork = blockchain.fork();
+    Schema::new(&fork).commit_configuration(config);
+    blockchain.merge(fork.into_patch()).unwrap();
+
+    let (_, patch) =
+        blockchain.create_patch(ValidatorId::zero(), Height(0), &[], &mut BTreeMap::new());
+    blockchain.merge(patch).unwrap();
+    blockchain
+}
+
+/// Commits a configuration with the given section of the service, which becomes actual
+/// at the given height.
+pub(crate) fn schedule_config(
+    blockchain: &mut Blockchain,
+    service_name: &str,
+    actual_from: Height,
+    section: Value,
+) {
+    let fork = blockchain.fork();
+    {
+        let mut schema = Schema::new(&fork);
+        let actual = schema.actual_configuration();
+        let mut config = StoredConfiguration {
+            previous_cfg_hash: actual.hash(),
+            actual_from,
+            ..actual
+        };
+        config.services.insert(service_name.to_owned(), section);
+        schema.commit_configuration(config);
+    }
+    blockchain.merge(fork.into_patch()).unwrap();
+}
+
+/// Commits a block at the given height with the given transactions.
+pub(crate) fn create_block(
+    blockchain: &mut Blockchain,
+    height: Height,
+    txs: &[Signed<RawTransaction>],
+) {
+    let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+    let mut tx_cache = txs.iter().map(|tx| (tx.hash(), tx.clone())).collect();
+    let (_, patch) =
+        blockchain.create_patch(ValidatorId::zero(), height, &tx_hashes, &mut tx_cache);
+    blockchain.merge(patch).unwrap();
+}
+
+/// Signs the given transactions of the service with a random key and commits them
+/// in the block at height 1. Returns the signed transactions.
+pub(crate) fn execute_block<I>(
+    blockchain: &mut Blockchain,
+    service_id: u16,
+    transactions: I,
+) -> Vec<Signed<RawTransaction>>
+where
+    I: IntoIterator,
+    I::Item: Into<ServiceTransaction>,
+{
+    let (pk, sec_key) = gen_keypair();
+    let txs = transactions
+        .into_iter()
+        .map(|tx| Message::sign_transaction(tx, service_id, pk, &sec_key))
+        .collect::<Vec<_>>();
+    create_block(blockchain, Height(1), &txs);
+    txs
+}
+
 struct TestService;
 
 impl Service for TestService {
@@ -41,7 +155,7 @@ impl Service for TestService {
         "test service"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         vec![]
     }
 
@@ -284,7 +398,7 @@ impl Service for ServiceGood {
         "some_service"
     }
 
-    fn state_hash(&self, _snapshot: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
         vec![]
     }
 
@@ -309,7 +423,7 @@ impl Service for ServicePanic {
         "some_service"
     }
 
-    fn state_hash(&self, _snapshot: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
         vec![]
     }
 
@@ -333,7 +447,7 @@ impl Service for ServicePanicStorageError {
         "some_service"
     }
 
-    fn state_hash(&self, _snapshot: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
         vec![]
     }
 
@@ -346,6 +460,61 @@ impl Service for ServicePanicStorageError {
     }
 }
 
+struct ServiceConfigWatcher;
+
+impl Service for ServiceConfigWatcher {
+    fn service_id(&self) -> u16 {
+        2
+    }
+    fn service_name(&self) -> &'static str {
+        "config_watcher"
+    }
+
+    fn state_hash_tables(&self, _snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+        vec![]
+    }
+
+    fn tx_from_raw(&self, _raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+        unimplemented!()
+    }
+
+    fn on_config_change(&self, fork: &Fork, config: &StoredConfiguration) {
+        let mut index = ListIndex::new(IDX_NAME, fork);
+        index.push(config.actual_from.0);
+    }
+}
+
+struct ServiceBlockWatcher;
+
+impl Service for ServiceBlockWatcher {
+    fn service_id(&self) -> u16 {
+        3
+    }
+    fn service_name(&self) -> &'static str {
+        "block_watcher"
+    }
+
+    fn state_hash_tables(&self, _snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+        vec![]
+    }
+
+    fn tx_from_raw(&self, _raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+        unimplemented!()
+    }
+
+    fn on_config_change(&self, fork: &Fork, _config: &StoredConfiguration) {
+        ListIndex::new(IDX_NAME, fork).push("config".to_owned());
+    }
+
+    fn before_transactions(&self, fork: &Fork) {
+        ListIndex::new(IDX_NAME, fork).push("begin".to_owned());
+    }
+
+    fn before_commit(&self, fork: &Fork) {
+        ListIndex::new(IDX_NAME, fork).push("commit".to_owned());
+    }
+}
+
 fn assert_service_execute(blockchain: &Blockchain, db: &mut dyn Database) {
     let (_, patch) =
         blockchain.create_patch(ValidatorId::zero(), Height(1), &[], &mut BTreeMap::new());
@@ -366,41 +535,34 @@ fn assert_service_execute_panic(blockchain: &Blockchain, db: &mut dyn Database)
 }
 
 mod memorydb_tests {
-    use futures::sync::mpsc;
+    use futures::{sync::mpsc, Stream};
 
-    use crate::blockchain::{Blockchain, Service};
-    use crate::crypto::gen_keypair;
-    use crate::node::ApiSender;
-    use exonum_merkledb::{Database, TemporaryDB};
+    use std::collections::BTreeMap;
 
-    use super::{ServiceGood, ServicePanic, ServicePanicStorageError};
+    use crate::blockchain::{
+        Blockchain, ConfigActivation, ConsensusConfig, Schema, Service, StoredConfiguration,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::Message;
+    use crate::node::{ApiSender, ExternalMessage};
+    use exonum_merkledb::{Database, ListIndex, ObjectHash, TemporaryDB};
+
+    use super::{
+        ServiceBlockWatcher, ServiceConfigWatcher, ServiceGood, ServicePanic,
+        ServicePanicStorageError, IDX_NAME,
+    };
 
     fn create_database() -> Box<dyn Database> {
         Box::new(TemporaryDB::new())
     }
 
     fn create_blockchain() -> Blockchain {
-        let service_keypair = gen_keypair();
-        let api_channel = mpsc::channel(0);
-        Blockchain::new(
-            TemporaryDB::new(),
-            vec![Box::new(super::TestService) as Box<dyn Service>],
-            service_keypair.0,
-            service_keypair.1,
-            ApiSender::new(api_channel.0),
-        )
+        create_blockchain_with_service(Box::new(super::TestService))
     }
 
     fn create_blockchain_with_service(service: Box<dyn Service>) -> Blockchain {
-        let service_keypair = gen_keypair();
-        let api_channel = mpsc::channel(0);
-        Blockchain::new(
-            TemporaryDB::new(),
-            vec![service],
-            service_keypair.0,
-            service_keypair.1,
-            ApiSender::new(api_channel.0),
-        )
+        super::create_blockchain(vec![service])
     }
 
     #[test]
@@ -437,17 +599,148 @@ mod memorydb_tests {
         let mut db = create_database();
         super::assert_service_execute(&blockchain, db.as_mut());
     }
+
+    #[test]
+    fn patch_hashes_are_recorded() {
+        let mut blockchain = create_blockchain_with_service(Box::new(ServiceGood));
+        let (_, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(1), &[], &mut BTreeMap::new());
+        let changes_hash = patch.changes_hash();
+
+        blockchain.set_record_patch_hashes(true);
+        let (_, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(1), &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+
+        let snapshot = blockchain.snapshot();
+        let patch_hashes = Schema::new(&snapshot).patch_hashes();
+        assert_eq!(patch_hashes.get(&1), Some(changes_hash));
+    }
+
+    /// Commits configurations actual from heights 0 and 2, returning their hashes.
+    fn commit_configurations(blockchain: &mut Blockchain) -> (Hash, Hash) {
+        let mut config = StoredConfiguration {
+            previous_cfg_hash: Hash::zero(),
+            actual_from: Height(0),
+            validator_keys: vec![],
+            consensus: ConsensusConfig::default(),
+            services: BTreeMap::new(),
+        };
+        let first_hash = config.object_hash();
+        let fork = blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            schema.commit_configuration(config.clone());
+            config.previous_cfg_hash = first_hash;
+            config.actual_from = Height(2);
+            schema.commit_configuration(config.clone());
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+        (first_hash, config.object_hash())
+    }
+
+    fn create_blocks(blockchain: &mut Blockchain, from: u64, to: u64) -> Vec<Hash> {
+        (from..=to)
+            .map(|height| {
+                let (block_hash, patch) = blockchain.create_patch(
+                    ValidatorId::zero(),
+                    Height(height),
+                    &[],
+                    &mut BTreeMap::new(),
+                );
+                blockchain.merge(patch).unwrap();
+                block_hash
+            })
+            .collect()
+    }
+
+    #[test]
+    fn raw_transaction_is_signed_with_given_keypair() {
+        let (service_public_key, service_secret_key) = gen_keypair();
+        let (oracle_public_key, oracle_secret_key) = gen_keypair();
+        let (api_sender, api_receiver) = mpsc::channel(1);
+        let blockchain = Blockchain::new(
+            TemporaryDB::new(),
+            vec![Box::new(super::TestService) as Box<dyn Service>],
+            service_public_key,
+            service_secret_key,
+            ApiSender::new(api_sender),
+        );
+
+        let tx = Message::sign_transaction(
+            super::Tx::new(3),
+            super::TEST_SERVICE_ID,
+            oracle_public_key,
+            &oracle_secret_key,
+        );
+        blockchain
+            .broadcast_raw_transaction_with_keypair(
+                tx.payload().clone(),
+                oracle_public_key,
+                &oracle_secret_key,
+            )
+            .unwrap();
+
+        match api_receiver.wait().next() {
+            Some(Ok(ExternalMessage::Transaction(msg))) => {
+                assert_eq!(msg.author(), oracle_public_key);
+                assert_eq!(msg.payload(), tx.payload());
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn services_are_notified_about_config_activation() {
+        let mut blockchain = create_blockchain_with_service(Box::new(ServiceConfigWatcher));
+        commit_configurations(&mut blockchain);
+        create_blocks(&mut blockchain, 1, 3);
+
+        let snapshot = blockchain.snapshot();
+        let changes: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(changes.iter().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn services_are_notified_about_block_start() {
+        let mut blockchain = create_blockchain_with_service(Box::new(ServiceBlockWatcher));
+        commit_configurations(&mut blockchain);
+        create_blocks(&mut blockchain, 0, 2);
+
+        let snapshot = blockchain.snapshot();
+        let hooks: ListIndex<_, String> = ListIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(
+            hooks.iter().collect::<Vec<_>>(),
+            vec!["begin", "commit", "config", "begin", "commit"]
+        );
+    }
+
+    #[test]
+    fn config_activations_are_recorded() {
+        let mut blockchain = create_blockchain_with_service(Box::new(ServiceGood));
+        let (first_hash, second_hash) = commit_configurations(&mut blockchain);
+        let block_hashes = create_blocks(&mut blockchain, 0, 3);
+
+        let snapshot = blockchain.snapshot();
+        let activations = Schema::new(&snapshot).config_activations();
+        assert_eq!(
+            activations.get(&first_hash),
+            Some(ConfigActivation::new(Height(0), block_hashes[0]))
+        );
+        assert_eq!(
+            activations.get(&second_hash),
+            Some(ConfigActivation::new(Height(2), block_hashes[2]))
+        );
+        assert_eq!(activations.iter().count(), 2);
+    }
 }
 
 mod rocksdb_tests {
-    use futures::sync::mpsc;
     use tempdir::TempDir;
 
     use std::path::Path;
 
     use crate::blockchain::{Blockchain, Service};
-    use crate::crypto::gen_keypair;
-    use crate::node::ApiSender;
     use exonum_merkledb::{Database, DbOptions, RocksDB};
 
     use super::{ServiceGood, ServicePanic, ServicePanicStorageError};
@@ -458,29 +751,11 @@ mod rocksdb_tests {
     }
 
     fn create_blockchain(path: &Path) -> Blockchain {
-        let db = create_database(path);
-        let service_keypair = gen_keypair();
-        let api_channel = mpsc::channel(0);
-        Blockchain::new(
-            db,
-            vec![Box::new(super::TestService) as Box<dyn Service>],
-            service_keypair.0,
-            service_keypair.1,
-            ApiSender::new(api_channel.0),
-        )
+        create_blockchain_with_service(path, Box::new(super::TestService))
     }
 
     fn create_blockchain_with_service(path: &Path, service: Box<dyn Service>) -> Blockchain {
-        let db = create_database(path);
-        let service_keypair = gen_keypair();
-        let api_channel = mpsc::channel(0);
-        Blockchain::new(
-            db,
-            vec![service],
-            service_keypair.0,
-            service_keypair.1,
-            ApiSender::new(api_channel.0),
-        )
+        super::create_blockchain_in(create_database(path), vec![service])
     }
 
     fn create_temp_dir() -> TempDir {
@@ -531,3 +806,1995 @@ mod rocksdb_tests {
         super::assert_service_execute(&blockchain, db.as_mut());
     }
 }
+
+mod genesis_state_tests {
+    use serde_json::Value;
+
+    use crate::blockchain::{
+        Blockchain, GenesisConfig, GenesisState, Schema, Service, ServiceStateDump, Transaction,
+        ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::messages::RawTransaction;
+    use exonum_merkledb::{Database, Fork, ObjectHash, ProofListIndex, Snapshot, TemporaryDB};
+
+    const IMPORTED_IDX: &str = "imported_service.values";
+
+    struct ImportingService;
+
+    impl Service for ImportingService {
+        fn service_id(&self) -> u16 {
+            2
+        }
+
+        fn service_name(&self) -> &'static str {
+            "imported_service"
+        }
+
+        fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+            let index: ProofListIndex<_, u64> = ProofListIndex::new(IMPORTED_IDX, snapshot);
+            vec![(IMPORTED_IDX.to_owned(), index.object_hash())]
+        }
+
+        fn tx_from_raw(
+            &self,
+            _raw: RawTransaction,
+        ) -> Result<Box<dyn Transaction>, failure::Error> {
+            unimplemented!()
+        }
+
+        fn import_state(&self, fork: &Fork, data: &Value) -> Result<(), failure::Error> {
+            let values: Vec<u64> = serde_json::from_value(data.clone())?;
+            let mut index = ProofListIndex::new(IMPORTED_IDX, fork);
+            index.extend(values);
+            Ok(())
+        }
+    }
+
+    fn create_blockchain() -> Blockchain {
+        super::create_blockchain(vec![Box::new(ImportingService)])
+    }
+
+    fn genesis_config() -> GenesisConfig {
+        let validator_keys = ValidatorKeys::new(gen_keypair().0, gen_keypair().0);
+        GenesisConfig::new(vec![validator_keys].into_iter())
+    }
+
+    fn expected_state_hash(values: &[u64]) -> Vec<Hash> {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut index = ProofListIndex::new(IMPORTED_IDX, &fork);
+        index.extend(values.iter().cloned());
+        vec![index.object_hash()]
+    }
+
+    fn genesis_state(service: &str, values: &[u64], state_hash: Vec<Hash>) -> GenesisState {
+        let mut state = GenesisState::default();
+        state.services.insert(
+            service.to_owned(),
+            ServiceStateDump {
+                data: json!(values),
+                state_hash,
+            },
+        );
+        state
+    }
+
+    #[test]
+    fn genesis_block_commits_to_imported_state() {
+        let values = [1, 2, 3, 5, 8];
+        let state = genesis_state("imported_service", &values, expected_state_hash(&values));
+
+        let mut blockchain = create_blockchain();
+        blockchain
+            .initialize_with_state(genesis_config(), state)
+            .unwrap();
+
+        let snapshot = blockchain.snapshot();
+        let index: ProofListIndex<_, u64> = ProofListIndex::new(IMPORTED_IDX, &snapshot);
+        assert_eq!(index.iter().collect::<Vec<_>>(), values.to_vec());
+
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.height(), crate::helpers::Height(0));
+        let proof = schema
+            .get_proof_to_service_table(IMPORTED_IDX)
+            .check()
+            .unwrap();
+        let entries = proof.entries().map(|(_, hash)| *hash).collect::<Vec<_>>();
+        assert_eq!(entries, expected_state_hash(&values));
+        assert_eq!(
+            schema.state_hash_aggregator().object_hash(),
+            *schema.last_block().state_hash()
+        );
+    }
+
+    #[test]
+    fn genesis_state_with_wrong_roots_is_rejected() {
+        let values = [1, 2, 3];
+        let state = genesis_state("imported_service", &values, vec![Hash::zero()]);
+
+        let mut blockchain = create_blockchain();
+        let err = blockchain
+            .initialize_with_state(genesis_config(), state)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not match the expected one"));
+        assert!(Schema::new(&blockchain.snapshot())
+            .block_hashes_by_height()
+            .is_empty());
+    }
+
+    #[test]
+    fn genesis_state_for_unknown_service_is_rejected() {
+        let state = genesis_state("unknown_service", &[1], vec![]);
+
+        let mut blockchain = create_blockchain();
+        let err = blockchain
+            .initialize_with_state(genesis_config(), state)
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown service"));
+    }
+}
+
+mod access_control_tests {
+    use std::collections::BTreeMap;
+
+    use super::{TestServiceTxs, Tx, IDX_NAME, TEST_SERVICE_ID};
+    use crate::blockchain::{
+        AccessControlList, AuthorizedKeys, Schema, Service, Transaction, TransactionErrorType,
+        TransactionSet,
+    };
+    use crate::crypto::{gen_keypair, Hash, PublicKey};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::{Message, RawTransaction};
+    use exonum_merkledb::{ListIndex, Snapshot};
+
+    struct RestrictedService {
+        allowed_key: PublicKey,
+    }
+
+    impl Service for RestrictedService {
+        fn service_id(&self) -> u16 {
+            TEST_SERVICE_ID
+        }
+
+        fn service_name(&self) -> &'static str {
+            "restricted service"
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(TestServiceTxs::tx_from_raw(raw)?.into())
+        }
+
+        fn access_control(&self) -> AccessControlList {
+            AccessControlList::new().allow(0, AuthorizedKeys::Keys(vec![self.allowed_key]))
+        }
+    }
+
+    #[test]
+    fn unauthorized_transactions_are_not_executed() {
+        let (allowed_key, allowed_secret_key) = gen_keypair();
+        let (other_key, other_secret_key) = gen_keypair();
+        let mut blockchain =
+            super::create_blockchain(vec![Box::new(RestrictedService { allowed_key })]);
+
+        let tx_allowed = Message::sign_transaction(
+            Tx::new(3),
+            TEST_SERVICE_ID,
+            allowed_key,
+            &allowed_secret_key,
+        );
+        let tx_denied =
+            Message::sign_transaction(Tx::new(4), TEST_SERVICE_ID, other_key, &other_secret_key);
+        let mut tx_cache = BTreeMap::new();
+        tx_cache.insert(tx_allowed.hash(), tx_allowed.clone());
+        tx_cache.insert(tx_denied.hash(), tx_denied.clone());
+
+        let (_, patch) = blockchain.create_patch(
+            ValidatorId::zero(),
+            Height::zero(),
+            &[tx_denied.hash(), tx_allowed.hash()],
+            &mut tx_cache,
+        );
+        blockchain.merge(patch).unwrap();
+
+        let snapshot = blockchain.snapshot();
+        let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(index.iter().collect::<Vec<_>>(), vec![3, 14]);
+
+        let results = Schema::new(&snapshot).transaction_results();
+        assert_eq!(results.get(&tx_allowed.hash()).unwrap().0, Ok(()));
+        let error = results.get(&tx_denied.hash()).unwrap().0.unwrap_err();
+        assert_eq!(error.error_type(), TransactionErrorType::Unauthorized);
+        assert!(error.description().unwrap().contains("is not allowed"));
+    }
+}
+
+mod multi_instance_tests {
+    use futures::sync::mpsc;
+
+    use std::collections::BTreeMap;
+
+    use crate::blockchain::{
+        Blockchain, BlockchainBuildError, BlockchainBuilder, ConsensusConfig, ExecutionResult,
+        GenesisConfig, Schema, Service, ServiceInstance, ServiceLimits, Transaction,
+        TransactionContext, TransactionSet, ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::{Message, RawTransaction};
+    use crate::node::ApiSender;
+    use crate::proto;
+    use exonum_merkledb::{ObjectHash, ProofListIndex, Snapshot, TemporaryDB};
+
+    const VALUES: &str = "values";
+
+    struct ValuesService {
+        instance: ServiceInstance,
+    }
+
+    impl Service for ValuesService {
+        fn service_id(&self) -> u16 {
+            self.instance.id()
+        }
+
+        fn service_name(&self) -> &str {
+            self.instance.name()
+        }
+
+        fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+            let name = self.instance.table_name(VALUES);
+            let values: ProofListIndex<_, u64> = ProofListIndex::new(name.as_str(), snapshot);
+            vec![(name, values.object_hash())]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(ValuesTransactions::tx_from_raw(raw)?.into())
+        }
+    }
+
+    #[derive(Serialize, Deserialize, ProtobufConvert, Debug, Clone)]
+    #[exonum(pb = "proto::schema::tests::TestServiceTx", crate = "crate")]
+    struct Append {
+        value: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, TransactionSet, Debug)]
+    #[exonum(crate = "crate")]
+    enum ValuesTransactions {
+        Append(Append),
+    }
+
+    impl Transaction for Append {
+        fn execute(&self, context: TransactionContext) -> ExecutionResult {
+            let name = context.instance().table_name(VALUES);
+            ProofListIndex::new(name, context.fork()).push(self.value);
+            Ok(())
+        }
+    }
+
+    fn create_blockchain(instances: &[ServiceInstance]) -> Blockchain {
+        let services = instances
+            .iter()
+            .map(|instance| {
+                Box::new(ValuesService {
+                    instance: instance.clone(),
+                }) as Box<dyn Service>
+            })
+            .collect();
+        super::create_blockchain(services)
+    }
+
+    #[test]
+    fn instances_of_service_have_separate_state() {
+        let instances = [
+            ServiceInstance::new(10, "first"),
+            ServiceInstance::new(11, "second"),
+        ];
+        let mut blockchain = create_blockchain(&instances);
+
+        let (pk, sec_key) = gen_keypair();
+        let mut tx_cache = BTreeMap::new();
+        let mut tx_hashes = Vec::new();
+        for (value, instance) in instances.iter().enumerate() {
+            let tx = Message::sign_transaction(
+                Append {
+                    value: value as u64,
+                },
+                instance.id(),
+                pk,
+                &sec_key,
+            );
+            tx_hashes.push(tx.hash());
+            tx_cache.insert(tx.hash(), tx);
+        }
+        let (_, patch) = blockchain.create_patch(
+            ValidatorId::zero(),
+            Height::zero(),
+            &tx_hashes,
+            &mut tx_cache,
+        );
+        blockchain.merge(patch).unwrap();
+
+        let snapshot = blockchain.snapshot();
+        let aggregator = Schema::new(&snapshot).state_hash_aggregator();
+        for (value, instance) in instances.iter().enumerate() {
+            let values: ProofListIndex<_, u64> =
+                ProofListIndex::new(instance.table_name(VALUES), &snapshot);
+            assert_eq!(values.iter().collect::<Vec<_>>(), vec![value as u64]);
+
+            let key = Blockchain::state_table_key(&instance.table_name(VALUES));
+            assert_eq!(aggregator.get(&key), Some(values.object_hash()));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "service with name=first")]
+    fn instances_with_same_name_are_rejected() {
+        create_blockchain(&[
+            ServiceInstance::new(10, "first"),
+            ServiceInstance::new(11, "first"),
+        ]);
+    }
+
+    fn builder(instances: &[ServiceInstance]) -> BlockchainBuilder {
+        let services = instances.iter().map(|instance| {
+            Box::new(ValuesService {
+                instance: instance.clone(),
+            }) as Box<dyn Service>
+        });
+        BlockchainBuilder::new(
+            TemporaryDB::new(),
+            gen_keypair(),
+            ApiSender::new(mpsc::channel(0).0),
+        )
+        .with_services(services)
+    }
+
+    #[test]
+    fn builder_rejects_duplicate_services() {
+        let err = builder(&[
+            ServiceInstance::new(10, "first"),
+            ServiceInstance::new(10, "second"),
+        ])
+        .build()
+        .err();
+        assert_eq!(err, Some(BlockchainBuildError::DuplicateServiceId(10)));
+
+        let err = builder(&[
+            ServiceInstance::new(10, "first"),
+            ServiceInstance::new(11, "first"),
+        ])
+        .build()
+        .err();
+        assert_eq!(
+            err,
+            Some(BlockchainBuildError::DuplicateServiceName(
+                "first".to_owned()
+            ))
+        );
+    }
+
+    struct TablesService {
+        name: &'static str,
+        tables: &'static [&'static str],
+    }
+
+    impl Service for TablesService {
+        fn service_id(&self) -> u16 {
+            200
+        }
+
+        fn service_name(&self) -> &str {
+            self.name
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            self.tables
+                .iter()
+                .map(|&table| (table.to_owned(), Hash::zero()))
+                .collect()
+        }
+
+        fn tx_from_raw(&self, _: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn builder_rejects_colliding_state_tables() {
+        let err = builder(&[ServiceInstance::new(10, "core")]).build().err();
+        assert_eq!(
+            err,
+            Some(BlockchainBuildError::InvalidStateTableName(
+                "core".to_owned(),
+                "core.values".to_owned()
+            ))
+        );
+
+        let err = builder(&[ServiceInstance::new(10, "first")])
+            .with_service(Box::new(TablesService {
+                name: "second",
+                tables: &["first.values"],
+            }))
+            .build()
+            .err();
+        assert_eq!(
+            err,
+            Some(BlockchainBuildError::InvalidStateTableName(
+                "second".to_owned(),
+                "first.values".to_owned()
+            ))
+        );
+
+        let err = builder(&[])
+            .with_service(Box::new(TablesService {
+                name: "second",
+                tables: &["second.values", "second.values"],
+            }))
+            .build()
+            .err();
+        assert_eq!(
+            err,
+            Some(BlockchainBuildError::DuplicateStateTable(
+                "second.values".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn builder_checks_service_limits() {
+        let limits = ServiceLimits {
+            max_services: Some(1),
+            reserve_builtin_ids: false,
+        };
+        let err = builder(&[
+            ServiceInstance::new(200, "first"),
+            ServiceInstance::new(201, "second"),
+        ])
+        .with_service_limits(limits)
+        .build()
+        .err();
+        assert_eq!(err, Some(BlockchainBuildError::TooManyServices(2, 1)));
+
+        let limits = ServiceLimits {
+            max_services: None,
+            reserve_builtin_ids: true,
+        };
+        let err = builder(&[ServiceInstance::new(10, "first")])
+            .with_service_limits(limits)
+            .build()
+            .err();
+        assert_eq!(
+            err,
+            Some(BlockchainBuildError::ReservedServiceId(
+                10,
+                "first".to_owned()
+            ))
+        );
+        assert!(builder(&[ServiceInstance::new(200, "first")])
+            .with_service_limits(limits)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn builder_validates_genesis_config() {
+        let instances = [
+            ServiceInstance::new(10, "first"),
+            ServiceInstance::new(11, "second"),
+        ];
+        let validator_keys = vec![ValidatorKeys::new(gen_keypair().0, gen_keypair().0)];
+        let consensus = ConsensusConfig {
+            txs_block_limit: 0,
+            ..ConsensusConfig::default()
+        };
+        let config =
+            GenesisConfig::new_with_consensus(consensus, validator_keys.clone().into_iter());
+        match builder(&instances).with_genesis_config(config).build() {
+            Err(BlockchainBuildError::InvalidGenesisConfig(..)) => {}
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Invalid genesis configuration is accepted"),
+        }
+
+        let config = GenesisConfig::new(validator_keys.into_iter());
+        let blockchain = builder(&instances)
+            .with_genesis_config(config)
+            .build()
+            .unwrap_or_else(|e| panic!("Cannot build blockchain: {}", e));
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.height(), Height(0));
+        let services = schema.actual_configuration().services;
+        assert!(services.contains_key("first") && services.contains_key("second"));
+    }
+}
+
+mod id_generation_tests {
+    use crate::blockchain::TransactionContext;
+    use crate::crypto::{gen_keypair, hash};
+    use exonum_merkledb::{Database, TemporaryDB};
+
+    #[test]
+    fn ids_are_sequential_in_namespace() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let author = gen_keypair().0;
+        let context = TransactionContext::from_parts(&fork, 1, "first", hash(&[1]), author);
+        assert_eq!(context.next_id("orders"), 1);
+        assert_eq!(context.next_id("orders"), 2);
+        assert_eq!(context.next_id("users"), 1);
+
+        let context = TransactionContext::from_parts(&fork, 1, "first", hash(&[2]), author);
+        assert_eq!(context.next_id("orders"), 3);
+        let context = TransactionContext::from_parts(&fork, 2, "second", hash(&[3]), author);
+        assert_eq!(context.next_id("orders"), 1);
+    }
+}
+
+mod parallel_execution_tests {
+    use std::collections::BTreeMap;
+
+    use crate::blockchain::{
+        Blockchain, ConsensusConfig, ExecutionError, ExecutionResult, Schema, Service,
+        ServiceInstance, Transaction, TransactionContext, TransactionSet,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::{Message, RawTransaction};
+    use crate::proto;
+    use exonum_merkledb::{Entry, Fork, ObjectHash, ProofListIndex, Snapshot};
+
+    const VALUES: &str = "values";
+    /// Flag set by the services before the transactions of each block.
+    const STARTED: &str = "started";
+    const SHARED_VALUES: &str = "shared_values";
+    /// Values starting from this one are also appended to the list shared by the services.
+    const SHARED_THRESHOLD: u64 = 100;
+
+    struct ValuesService {
+        instance: ServiceInstance,
+    }
+
+    impl Service for ValuesService {
+        fn service_id(&self) -> u16 {
+            self.instance.id()
+        }
+
+        fn service_name(&self) -> &str {
+            self.instance.name()
+        }
+
+        fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
+            let name = self.instance.table_name(VALUES);
+            let values: ProofListIndex<_, u64> = ProofListIndex::new(name.as_str(), snapshot);
+            vec![(name, values.object_hash())]
+        }
+
+        fn before_transactions(&self, fork: &Fork) {
+            Entry::new(self.instance.table_name(STARTED), fork).set(true);
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(ValuesTransactions::tx_from_raw(raw)?.into())
+        }
+    }
+
+    #[derive(Serialize, Deserialize, ProtobufConvert, Debug, Clone)]
+    #[exonum(pb = "proto::schema::tests::TestServiceTx", crate = "crate")]
+    struct Append {
+        value: u64,
+    }
+
+    /// Appends the number of values of the service with the given index.
+    #[derive(Serialize, Deserialize, ProtobufConvert, Debug, Clone)]
+    #[exonum(pb = "proto::schema::tests::TestServiceTx", crate = "crate")]
+    struct AppendCount {
+        value: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, TransactionSet, Debug)]
+    #[exonum(crate = "crate")]
+    enum ValuesTransactions {
+        Append(Append),
+        AppendCount(AppendCount),
+    }
+
+    impl Transaction for Append {
+        fn execute(&self, context: TransactionContext) -> ExecutionResult {
+            let started = context.instance().table_name(STARTED);
+            if Entry::new(started, context.fork()).get() != Some(true) {
+                return Err(ExecutionError::new(0));
+            }
+            let name = context.instance().table_name(VALUES);
+            ProofListIndex::new(name, context.fork()).push(self.value);
+            if self.value >= SHARED_THRESHOLD {
+                ProofListIndex::new(SHARED_VALUES, context.fork()).push(self.value);
+            }
+            Ok(())
+        }
+    }
+
+    impl Transaction for AppendCount {
+        fn execute(&self, context: TransactionContext) -> ExecutionResult {
+            let other = instances()[self.value as usize].table_name(VALUES);
+            let other_values: ProofListIndex<_, u64> = ProofListIndex::new(other, context.fork());
+            let count = other_values.len();
+            let name = context.instance().table_name(VALUES);
+            ProofListIndex::new(name, context.fork()).push(count);
+            Ok(())
+        }
+    }
+
+    fn instances() -> [ServiceInstance; 2] {
+        [
+            ServiceInstance::new(10, "first"),
+            ServiceInstance::new(11, "second"),
+        ]
+    }
+
+    /// Creates a blockchain with the genesis block committed. The lists of the services
+    /// are created with the initial zero value, so that the services do not create
+    /// indices when executing transactions.
+    fn create_blockchain(parallel_execution: bool) -> Blockchain {
+        let services = instances()
+            .iter()
+            .map(|instance| {
+                Box::new(ValuesService {
+                    instance: instance.clone(),
+                }) as Box<dyn Service>
+            })
+            .collect();
+        let consensus = ConsensusConfig {
+            parallel_execution,
+            ..ConsensusConfig::default()
+        };
+        let mut blockchain = super::create_blockchain_with_consensus(services, consensus);
+
+        let fork = blockchain.fork();
+        for instance in &instances() {
+            ProofListIndex::new(instance.table_name(VALUES), &fork).push(0_u64);
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+        blockchain
+    }
+
+    /// Executes a block with the transactions appending the values by the services
+    /// with the given indices and returns the block hash.
+    fn execute_block(blockchain: &mut Blockchain, values: &[(usize, u64)]) -> Hash {
+        let transactions = values
+            .iter()
+            .map(|&(instance, value)| (instance, ValuesTransactions::Append(Append { value })))
+            .collect::<Vec<_>>();
+        execute_transactions(blockchain, &transactions)
+    }
+
+    /// Executes a block with the given transactions of the services with the given
+    /// indices and returns the block hash.
+    fn execute_transactions(
+        blockchain: &mut Blockchain,
+        transactions: &[(usize, ValuesTransactions)],
+    ) -> Hash {
+        let (pk, sec_key) = gen_keypair();
+        let instances = instances();
+        let mut tx_cache = BTreeMap::new();
+        let mut tx_hashes = Vec::new();
+        for (instance, tx) in transactions {
+            let tx = Message::sign_transaction(tx.clone(), instances[*instance].id(), pk, &sec_key);
+            tx_hashes.push(tx.hash());
+            tx_cache.insert(tx.hash(), tx);
+        }
+        let (block_hash, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(1), &tx_hashes, &mut tx_cache);
+        blockchain.merge(patch).unwrap();
+        assert!(tx_cache.is_empty());
+        block_hash
+    }
+
+    fn values(blockchain: &Blockchain, name: &str) -> Vec<u64> {
+        let snapshot = blockchain.snapshot();
+        let values: ProofListIndex<_, u64> = ProofListIndex::new(name, &snapshot);
+        values.iter().collect()
+    }
+
+    #[test]
+    fn parallel_execution_matches_sequential() {
+        let block = [(0, 1), (1, 2), (0, 3), (1, 4), (1, 5)];
+        let mut sequential = create_blockchain(false);
+        let mut parallel = create_blockchain(true);
+        assert_eq!(
+            execute_block(&mut parallel, &block),
+            execute_block(&mut sequential, &block)
+        );
+
+        let instances = instances();
+        assert_eq!(
+            values(&parallel, &instances[0].table_name(VALUES)),
+            vec![0, 1, 3]
+        );
+        assert_eq!(
+            values(&parallel, &instances[1].table_name(VALUES)),
+            vec![0, 2, 4, 5]
+        );
+
+        let snapshot = parallel.snapshot();
+        let schema = Schema::new(&snapshot);
+        let block_transactions = schema.block_transactions(Height(1));
+        assert_eq!(block_transactions.len(), block.len() as u64);
+        for (index, tx_hash) in block_transactions.iter().enumerate() {
+            let location = schema.transactions_locations().get(&tx_hash).unwrap();
+            assert_eq!(location.position_in_block(), index as u64);
+            let result = schema.transaction_results().get(&tx_hash).unwrap();
+            assert!(result.0.is_ok());
+        }
+    }
+
+    #[test]
+    fn services_changing_same_index_are_executed_sequentially() {
+        let block = [(0, 101), (1, 102), (0, 103), (1, 4)];
+        let mut sequential = create_blockchain(false);
+        let mut parallel = create_blockchain(true);
+        assert_eq!(
+            execute_block(&mut parallel, &block),
+            execute_block(&mut sequential, &block)
+        );
+        assert_eq!(values(&parallel, SHARED_VALUES), vec![101, 102, 103]);
+    }
+
+    #[test]
+    fn lanes_observe_changes_made_before_transactions() {
+        let block = [(0, 1), (1, 2)];
+        let mut sequential = create_blockchain(false);
+        let mut parallel = create_blockchain(true);
+        assert_eq!(
+            execute_block(&mut parallel, &block),
+            execute_block(&mut sequential, &block)
+        );
+
+        let instances = instances();
+        assert_eq!(
+            values(&parallel, &instances[0].table_name(VALUES)),
+            vec![0, 1]
+        );
+        assert_eq!(
+            values(&parallel, &instances[1].table_name(VALUES)),
+            vec![0, 2]
+        );
+    }
+
+    #[test]
+    fn services_reading_changed_data_are_executed_sequentially() {
+        let block = [
+            (0, ValuesTransactions::Append(Append { value: 1 })),
+            (1, ValuesTransactions::AppendCount(AppendCount { value: 0 })),
+        ];
+        let mut sequential = create_blockchain(false);
+        let mut parallel = create_blockchain(true);
+        assert_eq!(
+            execute_transactions(&mut parallel, &block),
+            execute_transactions(&mut sequential, &block)
+        );
+
+        let instances = instances();
+        assert_eq!(
+            values(&parallel, &instances[1].table_name(VALUES)),
+            vec![0, 2]
+        );
+    }
+}
+
+mod gas_metering_tests {
+    use super::execute_block;
+    use crate::blockchain::{
+        Blockchain, ConsensusConfig, ExecutionResult, Schema, Service, Transaction,
+        TransactionContext, TransactionErrorType, TransactionSet,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::messages::{Message, RawTransaction, Signed};
+    use crate::proto;
+    use exonum_merkledb::{ListIndex, Snapshot};
+
+    const SERVICE_ID: u16 = 12;
+    const VALUES: &str = "gas_metering.values";
+
+    struct GasService;
+
+    impl Service for GasService {
+        fn service_id(&self) -> u16 {
+            SERVICE_ID
+        }
+
+        fn service_name(&self) -> &str {
+            "gas_metering"
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(GasTransactions::tx_from_raw(raw)?.into())
+        }
+    }
+
+    /// Charges the gas equal to its value and appends the value to the list.
+    #[derive(Serialize, Deserialize, ProtobufConvert, Debug, Clone)]
+    #[exonum(pb = "proto::schema::tests::TestServiceTx", crate = "crate")]
+    struct Consume {
+        value: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, TransactionSet, Debug)]
+    #[exonum(crate = "crate")]
+    enum GasTransactions {
+        Consume(Consume),
+    }
+
+    impl Transaction for Consume {
+        fn execute(&self, context: TransactionContext) -> ExecutionResult {
+            ListIndex::new(VALUES, context.fork()).push(self.value);
+            context.charge_gas(self.value);
+            Ok(())
+        }
+    }
+
+    fn create_blockchain(consensus: ConsensusConfig) -> Blockchain {
+        super::create_blockchain_with_consensus(vec![Box::new(GasService)], consensus)
+    }
+
+    fn consume(values: &[u64]) -> Vec<Consume> {
+        values.iter().map(|&value| Consume { value }).collect()
+    }
+
+    fn message_len(tx: &Signed<RawTransaction>) -> u64 {
+        tx.signed_message().raw().len() as u64
+    }
+
+    fn error_types(
+        blockchain: &Blockchain,
+        txs: &[Signed<RawTransaction>],
+    ) -> Vec<Option<TransactionErrorType>> {
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        txs.iter()
+            .map(|tx| {
+                let result = schema.transaction_results().get(&tx.hash()).unwrap();
+                result.0.err().map(|e| e.error_type())
+            })
+            .collect()
+    }
+
+    fn values(blockchain: &Blockchain) -> Vec<u64> {
+        let snapshot = blockchain.snapshot();
+        let values: ListIndex<_, u64> = ListIndex::new(VALUES, &snapshot);
+        values.iter().collect()
+    }
+
+    #[test]
+    fn gas_is_recorded_without_limit() {
+        let mut blockchain = create_blockchain(ConsensusConfig::default());
+        let values = [10, 1_000_000];
+        let txs = execute_block(&mut blockchain, SERVICE_ID, consume(&values));
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        for (tx, value) in txs.iter().zip(&values) {
+            let result = schema.transaction_results().get(&tx.hash()).unwrap();
+            assert!(result.0.is_ok());
+            assert_eq!(
+                schema.transaction_gas().get(&tx.hash()),
+                Some(message_len(tx) + value)
+            );
+        }
+        let stored: ListIndex<_, u64> = ListIndex::new(VALUES, &snapshot);
+        assert_eq!(stored.iter().collect::<Vec<_>>(), values.to_vec());
+    }
+
+    #[test]
+    fn transaction_exceeding_gas_limit_is_rolled_back() {
+        let mut blockchain = create_blockchain(ConsensusConfig {
+            transaction_gas_limit: 1_000,
+            ..ConsensusConfig::default()
+        });
+        let txs = execute_block(&mut blockchain, SERVICE_ID, consume(&[10, 5_000, 20]));
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let result = schema.transaction_results().get(&txs[1].hash()).unwrap();
+        let error = result.0.unwrap_err();
+        assert_eq!(error.error_type(), TransactionErrorType::OutOfGas);
+        assert_eq!(
+            schema.transaction_gas().get(&txs[1].hash()),
+            Some(message_len(&txs[1]) + 5_000)
+        );
+        for tx in &[&txs[0], &txs[2]] {
+            let result = schema.transaction_results().get(&tx.hash()).unwrap();
+            assert!(result.0.is_ok());
+        }
+        let values: ListIndex<_, u64> = ListIndex::new(VALUES, &snapshot);
+        assert_eq!(values.iter().collect::<Vec<_>>(), vec![10, 20]);
+    }
+
+    #[test]
+    fn transactions_exceeding_block_gas_are_not_executed() {
+        let mut blockchain = create_blockchain(ConsensusConfig {
+            max_block_gas: 3_000,
+            ..ConsensusConfig::default()
+        });
+        // The second transaction runs out of the gas left in the block,
+        // and the block has no gas left for the third one.
+        let txs = execute_block(&mut blockchain, SERVICE_ID, consume(&[10, 5_000, 20]));
+        assert_eq!(
+            error_types(&blockchain, &txs),
+            vec![
+                None,
+                Some(TransactionErrorType::OutOfGas),
+                Some(TransactionErrorType::BlockLimitExceeded),
+            ]
+        );
+        assert_eq!(values(&blockchain), vec![10]);
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.transaction_gas().get(&txs[2].hash()), Some(0));
+    }
+
+    #[test]
+    fn transactions_exceeding_block_size_are_not_executed() {
+        let (pk, sec_key) = gen_keypair();
+        let sample = Message::sign_transaction(Consume { value: 2 }, SERVICE_ID, pk, &sec_key);
+        let mut blockchain = create_blockchain(ConsensusConfig {
+            max_block_size: 2 * message_len(&sample),
+            ..ConsensusConfig::default()
+        });
+        let txs = execute_block(&mut blockchain, SERVICE_ID, consume(&[2, 4, 6]));
+        assert_eq!(
+            error_types(&blockchain, &txs),
+            vec![None, None, Some(TransactionErrorType::BlockLimitExceeded)]
+        );
+        assert_eq!(values(&blockchain), vec![2, 4]);
+    }
+}
+
+mod fee_tests {
+    use std::collections::BTreeMap;
+
+    use crate::blockchain::{
+        Blockchain, ConsensusConfig, ExecutionError, ExecutionResult, FeeHandler, Schema, Service,
+        StoredConfiguration, Transaction, TransactionContext, TransactionErrorType, TransactionSet,
+        ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, Hash, PublicKey};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::{Message, RawTransaction};
+    use crate::proto;
+    use exonum_merkledb::{Fork, MapIndex, Snapshot};
+
+    const SERVICE_ID: u16 = 13;
+    const BALANCES: &str = "fee_tests.balances";
+
+    struct BalanceFees;
+
+    impl FeeHandler for BalanceFees {
+        fn charge(&self, fork: &Fork, author: &PublicKey, fee: u64) -> Result<(), failure::Error> {
+            let mut balances = MapIndex::new(BALANCES, fork);
+            let balance = balances.get(author).unwrap_or(0);
+            ensure!(
+                balance >= fee,
+                "Balance {} is lower than fee {}",
+                balance,
+                fee
+            );
+            balances.put(author, balance - fee);
+            Ok(())
+        }
+
+        fn reward(&self, fork: &Fork, proposer: &PublicKey, amount: u64) {
+            let mut balances = MapIndex::new(BALANCES, fork);
+            let balance = balances.get(proposer).unwrap_or(0);
+            balances.put(proposer, balance + amount);
+        }
+    }
+
+    struct FeeService;
+
+    impl Service for FeeService {
+        fn service_id(&self) -> u16 {
+            SERVICE_ID
+        }
+
+        fn service_name(&self) -> &str {
+            "fee_tests"
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(FeeTransactions::tx_from_raw(raw)?.into())
+        }
+    }
+
+    /// Pays the fee equal to its value; the execution fails for odd values.
+    #[derive(Serialize, Deserialize, ProtobufConvert, Debug, Clone)]
+    #[exonum(pb = "proto::schema::tests::TestServiceTx", crate = "crate")]
+    struct Paid {
+        value: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, TransactionSet, Debug)]
+    #[exonum(crate = "crate")]
+    enum FeeTransactions {
+        Paid(Paid),
+    }
+
+    impl Transaction for Paid {
+        fn execute(&self, _: TransactionContext) -> ExecutionResult {
+            if self.value % 2 == 1 {
+                return Err(ExecutionError::new(0));
+            }
+            Ok(())
+        }
+
+        fn fee(&self) -> u64 {
+            self.value
+        }
+    }
+
+    fn balance(blockchain: &Blockchain, key: &PublicKey) -> Option<u64> {
+        let snapshot = blockchain.snapshot();
+        let balances: MapIndex<_, PublicKey, u64> = MapIndex::new(BALANCES, &snapshot);
+        balances.get(key)
+    }
+
+    #[test]
+    fn fees_are_charged_and_credited_to_proposer() {
+        let mut blockchain = super::create_blockchain(vec![Box::new(FeeService)]);
+        blockchain.set_fee_handler(BalanceFees);
+        let service_pk = blockchain.service_keypair.0;
+
+        let (author, author_key) = gen_keypair();
+        let config = StoredConfiguration {
+            previous_cfg_hash: Hash::zero(),
+            actual_from: Height(0),
+            validator_keys: vec![ValidatorKeys::new(gen_keypair().0, service_pk)],
+            consensus: ConsensusConfig::default(),
+            services: BTreeMap::new(),
+        };
+        let fork = blockchain.fork();
+        Schema::new(&fork).commit_configuration(config);
+        MapIndex::new(BALANCES, &fork).put(&author, 10_u64);
+        blockchain.merge(fork.into_patch()).unwrap();
+        let (_, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(0), &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+
+        // The second transaction fails, but its fee is kept; the author cannot pay
+        // for the third one.
+        let txs = [4, 3, 8]
+            .iter()
+            .map(|&value| {
+                Message::sign_transaction(Paid { value }, SERVICE_ID, author, &author_key)
+            })
+            .collect::<Vec<_>>();
+        let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+        let mut tx_cache: BTreeMap<_, _> = txs.iter().map(|tx| (tx.hash(), tx.clone())).collect();
+        let (_, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(1), &tx_hashes, &mut tx_cache);
+        blockchain.merge(patch).unwrap();
+
+        assert_eq!(balance(&blockchain, &author), Some(3));
+        assert_eq!(balance(&blockchain, &service_pk), Some(7));
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let error_types = tx_hashes
+            .iter()
+            .map(|tx_hash| {
+                let result = schema.transaction_results().get(tx_hash).unwrap();
+                result.0.err().map(|e| e.error_type())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            error_types,
+            vec![
+                None,
+                Some(TransactionErrorType::Code(0)),
+                Some(TransactionErrorType::InsufficientFee),
+            ]
+        );
+        let fees = schema.transaction_fees();
+        assert_eq!(fees.get(&tx_hashes[0]), Some(4));
+        assert_eq!(fees.get(&tx_hashes[1]), Some(3));
+        assert_eq!(fees.get(&tx_hashes[2]), None);
+    }
+}
+
+mod service_transactions_tests {
+    use std::collections::BTreeMap;
+
+    use super::{TestServiceTxs, Tx, IDX_NAME, TEST_SERVICE_ID};
+    use crate::blockchain::{
+        system_transaction_author, Blockchain, Schema, Service, Transaction, TransactionSet,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::{Message, RawTransaction, ServiceTransaction};
+    use exonum_merkledb::{Fork, ListIndex, Snapshot};
+
+    /// Service settling each block with a transaction depending on the number
+    /// of values pushed by the transactions of the block.
+    struct SettlementService;
+
+    impl Service for SettlementService {
+        fn service_id(&self) -> u16 {
+            TEST_SERVICE_ID
+        }
+
+        fn service_name(&self) -> &'static str {
+            "settlement service"
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(TestServiceTxs::tx_from_raw(raw)?.into())
+        }
+
+        fn after_transactions(&self, fork: &Fork) -> Vec<ServiceTransaction> {
+            let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, fork);
+            vec![Tx::new(index.len() + 100).into()]
+        }
+    }
+
+    fn create_blockchain() -> Blockchain {
+        let mut blockchain = super::create_blockchain(vec![Box::new(SettlementService)]);
+        let (_, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(0), &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+        blockchain
+    }
+
+    #[test]
+    fn generated_transactions_are_appended_to_block() {
+        let mut blockchain = create_blockchain();
+        let (pk, sec_key) = gen_keypair();
+        let tx = Message::sign_transaction(Tx::new(3), TEST_SERVICE_ID, pk, &sec_key);
+        let mut tx_cache = BTreeMap::new();
+        tx_cache.insert(tx.hash(), tx.clone());
+        let (block_hash, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(1), &[tx.hash()], &mut tx_cache);
+        blockchain.merge(patch).unwrap();
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let tx_hashes = schema
+            .block_transactions(Height(1))
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(tx_hashes.len(), 2);
+        assert_eq!(tx_hashes[0], tx.hash());
+        let generated = schema.transactions().get(&tx_hashes[1]).unwrap();
+        assert_eq!(generated.author(), system_transaction_author());
+        assert_eq!(
+            schema.transaction_results().get(&tx_hashes[1]).unwrap().0,
+            Ok(())
+        );
+        assert_eq!(schema.blocks().get(&block_hash).unwrap().tx_count(), 2);
+        let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(index.iter().collect::<Vec<_>>(), vec![3, 14, 102, 0]);
+
+        // A node receiving the block from a peer gets the same block.
+        let other_blockchain = create_blockchain();
+        let mut tx_cache = BTreeMap::new();
+        tx_cache.insert(tx.hash(), tx);
+        tx_cache.insert(generated.hash(), generated);
+        let (other_block_hash, _) = other_blockchain.create_patch(
+            ValidatorId::zero(),
+            Height(1),
+            &tx_hashes,
+            &mut tx_cache,
+        );
+        assert_eq!(other_block_hash, block_hash);
+        assert!(tx_cache.is_empty());
+    }
+}
+
+mod deployment_tests {
+    use serde_json::Value;
+
+    use std::collections::BTreeMap;
+
+    use super::{create_block, schedule_config, TestServiceTxs, Tx, IDX_NAME, TEST_SERVICE_ID};
+    use crate::blockchain::{
+        deployment, Blockchain, GenesisConfig, GenesisState, Schema, Service, ServiceStateDump,
+        Transaction, TransactionSet, ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, CryptoHash, Hash};
+    use crate::helpers::Height;
+    use crate::messages::{Message, RawTransaction};
+    use exonum_merkledb::{Entry, Fork, ListIndex, Snapshot};
+
+    const SERVICE_NAME: &str = "deployable service";
+    const INITIALIZED: &str = "deployable_service.initialized";
+
+    struct DeployableService;
+
+    impl Service for DeployableService {
+        fn service_id(&self) -> u16 {
+            TEST_SERVICE_ID
+        }
+
+        fn service_name(&self) -> &'static str {
+            SERVICE_NAME
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![(format!("{}.state", SERVICE_NAME), Hash::zero())]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(TestServiceTxs::tx_from_raw(raw)?.into())
+        }
+
+        fn is_deployable(&self) -> bool {
+            true
+        }
+
+        fn initialize(&self, fork: &Fork) -> Value {
+            Entry::new(INITIALIZED, fork).set(true);
+            Value::Null
+        }
+    }
+
+    fn create_blockchain() -> Blockchain {
+        super::create_initialized_blockchain(vec![Box::new(DeployableService)])
+    }
+
+    fn is_initialized(snapshot: &dyn Snapshot) -> bool {
+        Entry::new(INITIALIZED, snapshot).get().unwrap_or(false)
+    }
+
+    #[test]
+    fn service_is_deployed_by_configuration() {
+        let mut blockchain = create_blockchain();
+        let (pk, sec_key) = gen_keypair();
+        let tx = Message::sign_transaction(Tx::new(3), TEST_SERVICE_ID, pk, &sec_key);
+        let state_key = Blockchain::state_table_key(&format!("{}.state", SERVICE_NAME));
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(!blockchain.is_service_deployed(snapshot.as_ref(), TEST_SERVICE_ID));
+        assert!(!schema
+            .actual_configuration()
+            .services
+            .contains_key(SERVICE_NAME));
+        assert!(!is_initialized(snapshot.as_ref()));
+        assert!(blockchain.tx_from_raw(tx.payload().clone()).is_err());
+
+        schedule_config(&mut blockchain, SERVICE_NAME, Height(2), json!({}));
+        create_block(&mut blockchain, Height(1), &[]);
+        let snapshot = blockchain.snapshot();
+        assert!(!blockchain.is_service_deployed(snapshot.as_ref(), TEST_SERVICE_ID));
+        assert!(!Schema::new(&snapshot)
+            .state_hash_aggregator()
+            .contains(&state_key));
+
+        // The service is deployed at the beginning of the block and executes
+        // the transactions of this block.
+        create_block(&mut blockchain, Height(2), &[tx.clone()]);
+        let snapshot = blockchain.snapshot();
+        assert!(blockchain.is_service_deployed(snapshot.as_ref(), TEST_SERVICE_ID));
+        assert_eq!(
+            deployment::deployment_height(&snapshot, TEST_SERVICE_ID),
+            Some(Height(2))
+        );
+        assert!(is_initialized(snapshot.as_ref()));
+        let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(index.iter().collect::<Vec<_>>(), vec![3, 14]);
+        assert!(Schema::new(&snapshot)
+            .state_hash_aggregator()
+            .contains(&state_key));
+        assert!(blockchain.tx_from_raw(tx.payload().clone()).is_ok());
+    }
+
+    #[test]
+    fn genesis_state_of_deployable_service_is_rejected() {
+        let mut blockchain = super::create_blockchain(vec![Box::new(DeployableService)]);
+        let validator_keys = ValidatorKeys::new(gen_keypair().0, gen_keypair().0);
+        let mut state = GenesisState::default();
+        state.services.insert(
+            SERVICE_NAME.to_owned(),
+            ServiceStateDump {
+                data: json!(null),
+                state_hash: vec![],
+            },
+        );
+        let config = GenesisConfig::new(vec![validator_keys].into_iter());
+        assert!(blockchain.initialize_with_state(config, state).is_err());
+    }
+}
+
+mod lifecycle_tests {
+    use super::{create_block, schedule_config, TestService, Tx, IDX_NAME, TEST_SERVICE_ID};
+    use crate::blockchain::{
+        lifecycle::{self, ServiceState},
+        Blockchain, Schema, TransactionErrorType,
+    };
+    use crate::crypto::gen_keypair;
+    use crate::helpers::Height;
+    use crate::messages::Message;
+    use exonum_merkledb::ListIndex;
+
+    const SERVICE_NAME: &str = "test service";
+
+    fn create_blockchain() -> Blockchain {
+        super::create_initialized_blockchain(vec![Box::new(TestService)])
+    }
+
+    #[test]
+    fn service_is_stopped_and_resumed_by_configuration() {
+        let mut blockchain = create_blockchain();
+        let (pk, sec_key) = gen_keypair();
+        let tx_stopped = Message::sign_transaction(Tx::new(3), TEST_SERVICE_ID, pk, &sec_key);
+        let tx_resumed = Message::sign_transaction(Tx::new(6), TEST_SERVICE_ID, pk, &sec_key);
+
+        schedule_config(
+            &mut blockchain,
+            SERVICE_NAME,
+            Height(2),
+            json!({ "lifecycle": "stopped" }),
+        );
+        create_block(&mut blockchain, Height(1), &[]);
+        let snapshot = blockchain.snapshot();
+        assert!(blockchain.is_service_active(snapshot.as_ref(), TEST_SERVICE_ID));
+
+        // The transaction has been accepted before the service is stopped; it is
+        // committed without being executed.
+        create_block(&mut blockchain, Height(2), &[tx_stopped.clone()]);
+        let snapshot = blockchain.snapshot();
+        assert_eq!(
+            lifecycle::service_state(snapshot.as_ref(), TEST_SERVICE_ID),
+            ServiceState::Stopped
+        );
+        assert!(!blockchain.is_service_active(snapshot.as_ref(), TEST_SERVICE_ID));
+        assert!(blockchain
+            .tx_from_raw(tx_resumed.payload().clone())
+            .is_err());
+        let schema = Schema::new(&snapshot);
+        let result = schema
+            .transaction_results()
+            .get(&tx_stopped.hash())
+            .unwrap();
+        assert_eq!(
+            result.0.unwrap_err().error_type(),
+            TransactionErrorType::ServiceInactive
+        );
+        let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+        assert!(index.is_empty());
+
+        // A configuration omitting the state makes the service active again.
+        schedule_config(&mut blockchain, SERVICE_NAME, Height(4), json!({}));
+        create_block(&mut blockchain, Height(3), &[]);
+        assert!(blockchain
+            .tx_from_raw(tx_resumed.payload().clone())
+            .is_err());
+        create_block(&mut blockchain, Height(4), &[tx_resumed.clone()]);
+        let snapshot = blockchain.snapshot();
+        assert!(blockchain.is_service_active(snapshot.as_ref(), TEST_SERVICE_ID));
+        assert!(Schema::new(&snapshot).service_states().is_empty());
+        assert!(blockchain.tx_from_raw(tx_resumed.payload().clone()).is_ok());
+        let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(index.iter().collect::<Vec<_>>(), vec![6, 7]);
+    }
+}
+
+mod migration_tests {
+    use serde_json::Value;
+
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use super::{create_block, schedule_config, TestServiceTxs, TEST_SERVICE_ID};
+    use crate::blockchain::{
+        lifecycle::{self, ServiceState},
+        migration, Blockchain, Schema, Service, Transaction, TransactionSet,
+    };
+    use crate::crypto::Hash;
+    use crate::helpers::Height;
+    use crate::messages::RawTransaction;
+    use exonum_merkledb::{Fork, ListIndex, Snapshot};
+
+    const SERVICE_NAME: &str = "migrating service";
+    const VALUES: &str = "migrating_service.values";
+
+    /// Service whose data version can be changed to emulate upgrades of the node.
+    struct MigratingService {
+        data_version: Arc<AtomicU32>,
+    }
+
+    impl Service for MigratingService {
+        fn service_id(&self) -> u16 {
+            TEST_SERVICE_ID
+        }
+
+        fn service_name(&self) -> &'static str {
+            SERVICE_NAME
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(TestServiceTxs::tx_from_raw(raw)?.into())
+        }
+
+        fn data_version(&self) -> u32 {
+            self.data_version.load(Ordering::SeqCst)
+        }
+
+        fn initialize(&self, fork: &Fork) -> Value {
+            ListIndex::new(VALUES, fork).extend(vec![1_u64, 2, 3]);
+            Value::Null
+        }
+
+        fn migrate(&self, old_version: u32, fork: &Fork) -> Result<(), failure::Error> {
+            let mut values: ListIndex<_, u64> = ListIndex::new(VALUES, fork);
+            let migrated = values.iter().map(|value| value * 10).collect::<Vec<_>>();
+            values.clear();
+            values.extend(migrated);
+            ensure!(old_version == 0, "Unsupported data version {}", old_version);
+            Ok(())
+        }
+    }
+
+    fn create_blockchain(data_version: &Arc<AtomicU32>) -> Blockchain {
+        let service = MigratingService {
+            data_version: Arc::clone(data_version),
+        };
+        super::create_initialized_blockchain(vec![Box::new(service)])
+    }
+
+    fn values(snapshot: &dyn Snapshot) -> Vec<u64> {
+        ListIndex::new(VALUES, snapshot).iter().collect()
+    }
+
+    #[test]
+    fn data_is_migrated_by_configuration() {
+        let data_version = Arc::new(AtomicU32::new(0));
+        let mut blockchain = create_blockchain(&data_version);
+        let snapshot = blockchain.snapshot();
+        assert_eq!(migration::data_version(&snapshot, TEST_SERVICE_ID), 0);
+        assert_eq!(values(snapshot.as_ref()), vec![1, 2, 3]);
+
+        // The node is upgraded before the configuration becomes actual.
+        data_version.store(1, Ordering::SeqCst);
+        schedule_config(
+            &mut blockchain,
+            SERVICE_NAME,
+            Height(2),
+            json!({ "data_version": 1 }),
+        );
+        create_block(&mut blockchain, Height(1), &[]);
+        assert_eq!(values(blockchain.snapshot().as_ref()), vec![1, 2, 3]);
+        create_block(&mut blockchain, Height(2), &[]);
+        let snapshot = blockchain.snapshot();
+        assert_eq!(values(snapshot.as_ref()), vec![10, 20, 30]);
+        let status = Schema::new(&snapshot)
+            .service_migrations()
+            .get(&TEST_SERVICE_ID)
+            .unwrap();
+        assert_eq!(status.version, 1);
+        assert_eq!(status.height, Height(2));
+        assert_eq!(status.error, None);
+
+        // The failed migration is discarded and the service is stopped.
+        data_version.store(2, Ordering::SeqCst);
+        schedule_config(
+            &mut blockchain,
+            SERVICE_NAME,
+            Height(4),
+            json!({ "data_version": 2 }),
+        );
+        create_block(&mut blockchain, Height(3), &[]);
+        create_block(&mut blockchain, Height(4), &[]);
+        let snapshot = blockchain.snapshot();
+        assert_eq!(values(snapshot.as_ref()), vec![10, 20, 30]);
+        let status = Schema::new(&snapshot)
+            .service_migrations()
+            .get(&TEST_SERVICE_ID)
+            .unwrap();
+        assert_eq!(status.version, 1);
+        assert_eq!(status.height, Height(4));
+        assert!(status.error.is_some());
+        assert_eq!(
+            lifecycle::service_state(snapshot.as_ref(), TEST_SERVICE_ID),
+            ServiceState::Stopped
+        );
+    }
+
+    #[test]
+    fn service_is_initialized_with_its_data_version() {
+        let data_version = Arc::new(AtomicU32::new(3));
+        let blockchain = create_blockchain(&data_version);
+        let snapshot = blockchain.snapshot();
+        assert_eq!(migration::data_version(&snapshot, TEST_SERVICE_ID), 3);
+    }
+}
+
+mod execution_cache_tests {
+    use chrono::{TimeZone, Utc};
+
+    use std::collections::BTreeMap;
+
+    use super::{TestService, Tx, IDX_NAME, TEST_SERVICE_ID};
+    use crate::blockchain::{Blockchain, Schema};
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::{Message, RawTransaction, Signed};
+    use exonum_merkledb::{ListIndex, Patch};
+
+    fn create_blockchain() -> Blockchain {
+        super::create_initialized_blockchain(vec![Box::new(TestService)])
+    }
+
+    fn create_patch(
+        blockchain: &Blockchain,
+        height: Height,
+        txs: &[&Signed<RawTransaction>],
+    ) -> (Hash, Patch) {
+        let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+        let mut tx_cache: BTreeMap<_, _> =
+            txs.iter().map(|tx| (tx.hash(), (*tx).clone())).collect();
+        let result =
+            blockchain.create_patch(ValidatorId::zero(), height, &tx_hashes, &mut tx_cache);
+        assert!(tx_cache.is_empty());
+        result
+    }
+
+    #[test]
+    fn executions_are_reused_within_height() {
+        let mut blockchain = create_blockchain();
+        let (pk, sec_key) = gen_keypair();
+        let tx_a = Message::sign_transaction(Tx::new(1), TEST_SERVICE_ID, pk, &sec_key);
+        let tx_b = Message::sign_transaction(Tx::new(2), TEST_SERVICE_ID, pk, &sec_key);
+
+        let (block_hash, patch) = create_patch(&blockchain, Height(1), &[&tx_a, &tx_b]);
+        assert_eq!(blockchain.execution_cache.len(), 2);
+
+        // The same proposal in the next round produces the same block.
+        let (cached_hash, cached_patch) = create_patch(&blockchain, Height(1), &[&tx_a, &tx_b]);
+        assert_eq!(blockchain.execution_cache.len(), 2);
+        assert_eq!(cached_hash, block_hash);
+        assert_eq!(cached_patch.changes_hash(), patch.changes_hash());
+
+        // The transactions are executed from other states in a different order.
+        let (reordered_hash, _) = create_patch(&blockchain, Height(1), &[&tx_b, &tx_a]);
+        assert_eq!(blockchain.execution_cache.len(), 4);
+        assert_ne!(reordered_hash, block_hash);
+
+        blockchain.merge(cached_patch).unwrap();
+        let snapshot = blockchain.snapshot();
+        let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(index.iter().collect::<Vec<_>>(), vec![1, 42, 2, 21]);
+
+        // The executions of the previous height are discarded.
+        create_patch(&blockchain, Height(2), &[]);
+        assert_eq!(blockchain.execution_cache.len(), 0);
+    }
+
+    #[test]
+    fn block_state_depends_on_changes_before_transactions() {
+        let blockchain = create_blockchain();
+        let cache = &blockchain.execution_cache;
+        let block_changes = |time| {
+            let mut fork = blockchain.fork();
+            fork.start_recording();
+            Schema::new(&fork)
+                .block_times()
+                .put(&1, Utc.timestamp(time, 0));
+            fork.flush();
+            fork.stop_recording().unwrap()
+        };
+
+        let last_hash = blockchain.last_hash();
+        let state = cache.start_block(&last_hash, Height(1), 0, &block_changes(10));
+        assert_eq!(
+            cache.start_block(&last_hash, Height(1), 0, &block_changes(10)),
+            state
+        );
+        assert_ne!(
+            cache.start_block(&last_hash, Height(1), 0, &block_changes(20)),
+            state
+        );
+    }
+}
+
+mod namespace_tests {
+    use super::execute_block;
+    use crate::blockchain::{
+        Blockchain, ConsensusConfig, ExecutionResult, Namespace, Schema, Service, Transaction,
+        TransactionContext, TransactionErrorType, TransactionSet,
+    };
+    use crate::crypto::Hash;
+    use crate::messages::RawTransaction;
+    use crate::proto;
+    use exonum_merkledb::{Fork, ListIndex, Snapshot};
+
+    const SERVICE_ID: u16 = 14;
+    const TABLES: [&str; 3] = ["acme.values", "shared.values", "foreign.values"];
+    const HOOKS: &str = "foreign.hooks";
+
+    struct AcmeService;
+
+    impl Service for AcmeService {
+        fn service_id(&self) -> u16 {
+            SERVICE_ID
+        }
+
+        fn service_name(&self) -> &str {
+            "acme"
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(AcmeTransactions::tx_from_raw(raw)?.into())
+        }
+
+        fn namespace(&self) -> Option<Namespace> {
+            Some(Namespace::new("acme").grant("shared.values"))
+        }
+
+        fn before_commit(&self, fork: &Fork) {
+            ListIndex::new(HOOKS, fork).push(1_u64);
+        }
+    }
+
+    /// Appends its value to the table with the index equal to the value.
+    #[derive(Serialize, Deserialize, ProtobufConvert, Debug, Clone)]
+    #[exonum(pb = "proto::schema::tests::TestServiceTx", crate = "crate")]
+    struct Write {
+        value: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, TransactionSet, Debug)]
+    #[exonum(crate = "crate")]
+    enum AcmeTransactions {
+        Write(Write),
+    }
+
+    impl Transaction for Write {
+        fn execute(&self, context: TransactionContext) -> ExecutionResult {
+            ListIndex::new(TABLES[self.value as usize], context.fork()).push(self.value);
+            context.next_id("writes");
+            Ok(())
+        }
+    }
+
+    fn create_blockchain() -> Blockchain {
+        super::create_blockchain_with_consensus(
+            vec![Box::new(AcmeService)],
+            ConsensusConfig::default(),
+        )
+    }
+
+    fn values(snapshot: &dyn Snapshot, name: &str) -> Vec<u64> {
+        ListIndex::new(name, snapshot).iter().collect()
+    }
+
+    #[test]
+    fn writes_outside_of_namespace_are_discarded() {
+        let mut blockchain = create_blockchain();
+        let writes = (0..3).map(|value| Write { value });
+        let txs = execute_block(&mut blockchain, SERVICE_ID, writes);
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let error_types = txs
+            .iter()
+            .map(|tx| {
+                let result = schema.transaction_results().get(&tx.hash()).unwrap();
+                result.0.err().map(|e| e.error_type())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            error_types,
+            vec![None, None, Some(TransactionErrorType::Panic)]
+        );
+        assert_eq!(schema.id_counters(SERVICE_ID).get("writes"), Some(2));
+
+        assert_eq!(values(&snapshot, TABLES[0]), vec![0]);
+        assert_eq!(values(&snapshot, TABLES[1]), vec![1]);
+        assert!(values(&snapshot, TABLES[2]).is_empty());
+        assert!(values(&snapshot, HOOKS).is_empty());
+    }
+}
+
+mod block_headers_tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use std::collections::BTreeMap;
+
+    use super::{TestServiceTxs, Tx, TEST_SERVICE_ID};
+    use crate::blockchain::{
+        AdditionalHeaders, BlockHeaderKey, GenesisConfig, Schema, Service, Transaction,
+        TransactionSet, ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, Round, ValidatorId};
+    use crate::messages::{Message, Precommit, RawTransaction};
+    use exonum_merkledb::{ObjectHash, Snapshot};
+
+    struct BlockCount;
+
+    impl BlockHeaderKey for BlockCount {
+        const NAME: &'static str = "headers_service.block_count";
+        type Value = u64;
+    }
+
+    struct HeadersService {
+        id: u16,
+        name: &'static str,
+    }
+
+    impl Service for HeadersService {
+        fn service_id(&self) -> u16 {
+            self.id
+        }
+
+        fn service_name(&self) -> &str {
+            self.name
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            Ok(TestServiceTxs::tx_from_raw(raw)?.into())
+        }
+
+        fn block_headers(&self, snapshot: &dyn Snapshot) -> AdditionalHeaders {
+            let mut headers = AdditionalHeaders::new();
+            headers.insert::<BlockCount>(Schema::new(snapshot).block_hashes_by_height().len());
+            headers.insert_raw(format!("service_{}", self.id), vec![self.id as u8]);
+            headers
+        }
+    }
+
+    #[test]
+    fn services_set_additional_headers() {
+        let mut blockchain = super::create_blockchain(vec![
+            Box::new(HeadersService {
+                id: TEST_SERVICE_ID,
+                name: "first",
+            }),
+            Box::new(HeadersService {
+                id: TEST_SERVICE_ID - 1,
+                name: "second",
+            }),
+        ]);
+        let (genesis_hash, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(0), &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+        let (block_hash, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(1), &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let genesis = schema.blocks().get(&genesis_hash).unwrap();
+        assert!(genesis.additional_headers().is_empty());
+
+        let headers = schema
+            .blocks()
+            .get(&block_hash)
+            .unwrap()
+            .additional_headers()
+            .clone();
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers.get::<BlockCount>().unwrap(), Some(1));
+        assert_eq!(headers.get_raw("service_254"), Some(&[254][..]));
+        assert_eq!(headers.get_raw("service_255"), Some(&[255][..]));
+    }
+
+    #[test]
+    fn block_commits_to_transaction_results() {
+        let mut blockchain = super::create_blockchain(vec![Box::new(HeadersService {
+            id: TEST_SERVICE_ID,
+            name: "headers",
+        })]);
+        let (_, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(0), &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+        let (empty_block_hash, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(1), &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+
+        let (pk, sec_key) = gen_keypair();
+        // The second transaction panics on the division by zero.
+        let txs = vec![
+            Message::sign_transaction(Tx::new(3), TEST_SERVICE_ID, pk, &sec_key),
+            Message::sign_transaction(Tx::new(0), TEST_SERVICE_ID, pk, &sec_key),
+        ];
+        let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+        let mut tx_cache = txs.iter().map(|tx| (tx.hash(), tx.clone())).collect();
+        let (block_hash, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(2), &tx_hashes, &mut tx_cache);
+        blockchain.merge(patch).unwrap();
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let empty_block = schema.blocks().get(&empty_block_hash).unwrap();
+        assert_eq!(empty_block.tx_results_hash(), None);
+        assert!(schema.block_transaction_results(Height(1)).is_empty());
+
+        let block = schema.blocks().get(&block_hash).unwrap();
+        let result_hashes = schema.block_transaction_results(Height(2));
+        assert_eq!(block.tx_results_hash(), Some(result_hashes.object_hash()));
+        let results = schema.transaction_results();
+        let expected = tx_hashes
+            .iter()
+            .map(|hash| results.get(hash).unwrap().object_hash())
+            .collect::<Vec<_>>();
+        assert_eq!(result_hashes.iter().collect::<Vec<_>>(), expected);
+        assert_ne!(expected[0], expected[1]);
+    }
+
+    #[test]
+    fn block_commits_to_consensus_time() {
+        let create_block = |time: DateTime<Utc>| {
+            let mut blockchain = super::create_blockchain(vec![Box::new(HeadersService {
+                id: TEST_SERVICE_ID,
+                name: "headers",
+            })]);
+            let (_, patch) =
+                blockchain.create_patch(ValidatorId::zero(), Height(0), &[], &mut BTreeMap::new());
+            blockchain.merge(patch).unwrap();
+            let (_, patch) = blockchain.create_patch_with_time(
+                ValidatorId::zero(),
+                Height(1),
+                Some(time),
+                &[],
+                &mut BTreeMap::new(),
+            );
+            blockchain.merge(patch).unwrap();
+            blockchain
+        };
+
+        let time = Utc.timestamp(1_500_000_000, 0);
+        let blockchain = create_block(time);
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.block_time(Height(0)), None);
+        assert_eq!(schema.last_block_time(), Some(time));
+        let block = schema.last_block();
+        assert_eq!(block.time(), Some(time));
+
+        let other_blockchain = create_block(time + Duration::seconds(1));
+        let other_block = Schema::new(&other_blockchain.snapshot()).last_block();
+        assert_ne!(block.state_hash(), other_block.state_hash());
+    }
+
+    #[test]
+    fn next_block_time_is_weighted_median_of_precommits() {
+        let mut blockchain = super::create_blockchain(vec![]);
+        let keys = vec![gen_keypair(), gen_keypair(), gen_keypair()];
+        let validators = keys
+            .iter()
+            .zip(&[1, 1, 3])
+            .map(|((key, _), &weight)| ValidatorKeys::new(*key, *key).with_weight(weight));
+        blockchain
+            .initialize(GenesisConfig::new(validators))
+            .unwrap();
+
+        let time = Utc.timestamp(1_500_000_000, 0);
+        let precommits: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, (public_key, secret_key))| {
+                let precommit = Precommit::new(
+                    ValidatorId(i as u16),
+                    Height(0),
+                    Round::first(),
+                    &Hash::zero(),
+                    &Hash::zero(),
+                    time + Duration::seconds(i as i64),
+                );
+                Message::concrete(precommit, *public_key, secret_key)
+            })
+            .collect();
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        // The last validator has more than a half of the weight.
+        let expected_time = time + Duration::seconds(2);
+        assert_eq!(schema.next_block_time(&precommits), Some(expected_time));
+        assert_eq!(schema.next_block_time(&precommits[..2]), Some(time));
+        assert_eq!(schema.next_block_time(&[]), None);
+
+        // The time does not decrease along the chain.
+        let later_time = expected_time + Duration::seconds(1);
+        let (_, patch) = blockchain.create_patch_with_time(
+            ValidatorId::zero(),
+            Height(1),
+            Some(later_time),
+            &[],
+            &mut BTreeMap::new(),
+        );
+        blockchain.merge(patch).unwrap();
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.next_block_time(&precommits), Some(later_time));
+    }
+}
+
+mod commit_subscription_tests {
+    use std::{collections::BTreeMap, iter};
+
+    use super::{TestService, Tx, TEST_SERVICE_ID};
+    use crate::blockchain::Blockchain;
+    use crate::crypto::gen_keypair;
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::Message;
+
+    fn commit_block(blockchain: &mut Blockchain, height: Height, values: &[u64]) {
+        let (pk, sec_key) = gen_keypair();
+        let txs = values
+            .iter()
+            .map(|&value| Message::sign_transaction(Tx::new(value), TEST_SERVICE_ID, pk, &sec_key))
+            .collect::<Vec<_>>();
+        let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+        let mut tx_cache = txs.into_iter().map(|tx| (tx.hash(), tx)).collect();
+        let (block_hash, patch) =
+            blockchain.create_patch(ValidatorId::zero(), height, &tx_hashes, &mut tx_cache);
+        blockchain
+            .commit(patch, block_hash, iter::empty(), &mut tx_cache)
+            .unwrap();
+    }
+
+    #[test]
+    fn subscribers_are_notified_about_commits() {
+        let mut blockchain = super::create_blockchain(vec![Box::new(TestService)]);
+        let (_, patch) =
+            blockchain.create_patch(ValidatorId::zero(), Height(0), &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+
+        // Clones of the blockchain share the subscribers.
+        let receiver = blockchain.clone().subscribe();
+        let dropped_receiver = blockchain.subscribe();
+        drop(dropped_receiver);
+        // The second transaction panics on the division by zero.
+        commit_block(&mut blockchain, Height(1), &[3, 0]);
+
+        let committed = receiver.try_recv().unwrap();
+        assert_eq!(*committed.block(), blockchain.last_block());
+        let results = committed
+            .transactions()
+            .map(|tx| tx.result.0.is_ok())
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![true, false]);
+        let summary = committed.service_summary(TEST_SERVICE_ID);
+        assert_eq!(summary.transactions().len(), 2);
+        assert!(committed.service_summary(1).transactions().is_empty());
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(blockchain.commit_subscribers.lock().unwrap().len(), 1);
+
+        commit_block(&mut blockchain, Height(2), &[]);
+        let committed = receiver.try_recv().unwrap();
+        assert_eq!(committed.block().height(), Height(2));
+        assert_eq!(committed.transactions().count(), 0);
+    }
+}
@@ -18,8 +18,10 @@ use hex::ToHex;
 use protobuf::Message;
 use serde::{de::DeserializeOwned, Serialize};
 
-use std::{any::Any, borrow::Cow, convert::Into, error::Error, fmt, u8};
+use std::{any::Any, borrow::Cow, cell::Cell, convert::Into, error::Error, fmt, panic, rc::Rc, u8};
 
+use super::calls::{CallDispatcher, MAX_CALL_DEPTH};
+use crate::blockchain::{events::TransactionEvent, features, lifecycle, Schema, ServiceInstance};
 use crate::crypto::{CryptoHash, Hash, PublicKey};
 use crate::messages::{HexStringRepresentation, RawTransaction, Signed, SignedMessage};
 use crate::proto::{self, ProtobufConvert};
@@ -32,6 +34,16 @@ const MAX_ERROR_CODE: u16 = u8::max_value() as u16;
 const TRANSACTION_STATUS_OK: u16 = MAX_ERROR_CODE + 1;
 // `Err(TransactionErrorType::Panic)`.
 const TRANSACTION_STATUS_PANIC: u16 = TRANSACTION_STATUS_OK + 1;
+// `Err(TransactionErrorType::Unauthorized)`.
+const TRANSACTION_STATUS_UNAUTHORIZED: u16 = TRANSACTION_STATUS_PANIC + 1;
+// `Err(TransactionErrorType::OutOfGas)`.
+const TRANSACTION_STATUS_OUT_OF_GAS: u16 = TRANSACTION_STATUS_UNAUTHORIZED + 1;
+// `Err(TransactionErrorType::InsufficientFee)`.
+const TRANSACTION_STATUS_INSUFFICIENT_FEE: u16 = TRANSACTION_STATUS_OUT_OF_GAS + 1;
+// `Err(TransactionErrorType::BlockLimitExceeded)`.
+const TRANSACTION_STATUS_BLOCK_LIMIT_EXCEEDED: u16 = TRANSACTION_STATUS_INSUFFICIENT_FEE + 1;
+// `Err(TransactionErrorType::ServiceInactive)`.
+const TRANSACTION_STATUS_SERVICE_INACTIVE: u16 = TRANSACTION_STATUS_BLOCK_LIMIT_EXCEEDED + 1;
 
 /// Returns a result of the `Transaction` `execute` method. This result may be
 /// either an empty unit type, in case of success, or an `ExecutionError`, if execution has
@@ -180,6 +192,28 @@ pub trait Transaction: ::std::fmt::Debug + Send + 'static + ::erased_serde::Seri
     /// }
     /// # fn main() {}
     fn execute<'a>(&self, context: TransactionContext<'a>) -> ExecutionResult;
+
+    /// Returns the conflict keys of the transaction, e.g., `"wallet:alice"` for
+    /// a transaction changing the wallet of Alice.
+    ///
+    /// The keys are determined when the transaction is admitted into the pool. A proposer
+    /// includes at most one transaction with the same key into a block; other transactions
+    /// with this key are postponed till the next blocks, so their outcome does not depend
+    /// on the order of transactions within a block. By default, a transaction has no
+    /// conflict keys.
+    fn conflict_keys(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Returns the fee the author pays for the transaction.
+    ///
+    /// The fee is charged before the execution only if the blockchain has
+    /// a [`FeeHandler`]. By default, a transaction is free.
+    ///
+    /// [`FeeHandler`]: trait.FeeHandler.html
+    fn fee(&self) -> u64 {
+        0
+    }
 }
 
 //TODO: Add doc/examples.
@@ -191,6 +225,10 @@ pub struct TransactionContext<'a> {
     service_name: &'a str,
     tx_hash: Hash,
     author: PublicKey,
+    gas_meter: Rc<GasMeter>,
+    dispatcher: Option<&'a CallDispatcher<'a>>,
+    caller: Option<u16>,
+    depth: usize,
 }
 
 impl<'a> TransactionContext<'a> {
@@ -206,9 +244,46 @@ impl<'a> TransactionContext<'a> {
             service_name,
             tx_hash: raw_message.hash(),
             author: raw_message.author(),
+            gas_meter: Rc::default(),
+            dispatcher: None,
+            caller: None,
+            depth: 0,
+        }
+    }
+
+    /// Creates a context for the transaction with the given hash and author.
+    pub(crate) fn from_parts(
+        fork: &'a Fork,
+        service_id: u16,
+        service_name: &'a str,
+        tx_hash: Hash,
+        author: PublicKey,
+    ) -> Self {
+        TransactionContext {
+            fork,
+            service_id,
+            service_name,
+            tx_hash,
+            author,
+            gas_meter: Rc::default(),
+            dispatcher: None,
+            caller: None,
+            depth: 0,
         }
     }
 
+    /// Makes the transaction charge the gas to the given meter.
+    pub(crate) fn with_gas_meter(mut self, gas_meter: Rc<GasMeter>) -> Self {
+        self.gas_meter = gas_meter;
+        self
+    }
+
+    /// Makes the transaction dispatch its calls with the given dispatcher.
+    pub(crate) fn with_dispatcher(mut self, dispatcher: &'a CallDispatcher<'a>) -> Self {
+        self.dispatcher = Some(dispatcher);
+        self
+    }
+
     /// Returns fork of current blockchain state.
     pub fn fork(&self) -> &Fork {
         self.fork
@@ -224,6 +299,11 @@ impl<'a> TransactionContext<'a> {
         self.service_name
     }
 
+    /// Returns the parameters of the service instance that own this transaction.
+    pub fn instance(&self) -> ServiceInstance {
+        ServiceInstance::new(self.service_id, self.service_name)
+    }
+
     /// Returns transaction author public key
     pub fn author(&self) -> PublicKey {
         self.author
@@ -234,6 +314,185 @@ impl<'a> TransactionContext<'a> {
     pub fn tx_hash(&self) -> Hash {
         self.tx_hash
     }
+
+    /// Returns the identifier of the service which has called the method of this service
+    /// with [`call`], or `None` if the context belongs to the transaction itself.
+    ///
+    /// [`call`]: #method.call
+    pub fn caller(&self) -> Option<u16> {
+        self.caller
+    }
+
+    /// Calls the method of another service with the given arguments and returns the result
+    /// of the call. The service handles the call in [`Service::handle_call`] with a context
+    /// sharing the fork, the author and the gas meter with this one. See [`calls`]
+    /// for details.
+    ///
+    /// # Panics
+    ///
+    /// Aborts the execution of the transaction if the service is not found or is not active,
+    /// does not handle the method, or the depth of the nested calls exceeds [`MAX_CALL_DEPTH`].
+    ///
+    /// [`Service::handle_call`]: trait.Service.html#method.handle_call
+    /// [`calls`]: calls/index.html
+    /// [`MAX_CALL_DEPTH`]: calls/constant.MAX_CALL_DEPTH.html
+    pub fn call(
+        &self,
+        service_id: u16,
+        method: &str,
+        args: &[u8],
+    ) -> Result<Vec<u8>, ExecutionError> {
+        let dispatcher = self.dispatcher.unwrap_or_else(|| {
+            panic!(
+                "Service <{}> cannot call other services in this context",
+                self.service_name
+            )
+        });
+        if self.depth >= MAX_CALL_DEPTH {
+            panic!("Depth of the service calls exceeds {}", MAX_CALL_DEPTH);
+        }
+        let service = dispatcher
+            .service(service_id)
+            .unwrap_or_else(|| panic!("Service with id={} is not found", service_id));
+        let state = lifecycle::service_state(self.fork, service_id);
+        if !state.accepts_transactions() {
+            panic!(
+                "Service <{}> cannot be called, the service is {}",
+                service.service_name(),
+                state
+            );
+        }
+        let context = TransactionContext {
+            fork: self.fork,
+            service_id,
+            service_name: service.service_name(),
+            tx_hash: self.tx_hash,
+            author: self.author,
+            gas_meter: Rc::clone(&self.gas_meter),
+            dispatcher: self.dispatcher,
+            caller: Some(self.service_id),
+            depth: self.depth + 1,
+        };
+        service.handle_call(&context, method, args)
+    }
+
+    /// Returns the next identifier in the given namespace of the service, starting from 1.
+    ///
+    /// The identifiers are unique within the namespace across the whole chain
+    /// and are the same on all nodes. The counter is stored in the blockchain,
+    /// so the identifiers issued by a failed transaction are issued again
+    /// by the next transaction. Unlike the identifiers derived from the transaction
+    /// hashes, they do not collide if the same data is submitted twice.
+    pub fn next_id(&self, namespace: &str) -> u64 {
+        let mut counters = Schema::new(self.fork).id_counters(self.service_id);
+        let id = counters.get(namespace).unwrap_or(0) + 1;
+        counters.put(&namespace.to_owned(), id);
+        id
+    }
+
+    /// Emits an event with the given topic and payload on behalf of the service.
+    ///
+    /// The event is appended to the events of the block being executed, which can be
+    /// requested from the explorer together with a proof against the block header;
+    /// see [`events`] for details. The events of a failed transaction are discarded.
+    ///
+    /// [`events`]: events/index.html
+    pub fn emit_event(&self, topic: &str, payload: impl Into<Vec<u8>>) {
+        let schema = Schema::new(self.fork);
+        let event = TransactionEvent {
+            tx_hash: self.tx_hash,
+            service_id: self.service_id,
+            topic: topic.to_owned(),
+            payload: payload.into(),
+        };
+        schema.block_events(schema.next_height()).push(event);
+    }
+
+    /// Checks whether the feature of the service is active in the block being executed.
+    ///
+    /// The feature is activated according to the schedule listed in the service
+    /// configuration; see [`features`] for details. Features that are not scheduled are inactive.
+    ///
+    /// [`features`]: features/index.html
+    pub fn is_active(&self, feature: &str) -> bool {
+        let height = Schema::new(self.fork).next_height();
+        features::is_active(self.fork, self.service_name, feature, height)
+    }
+
+    /// Charges the given amount of gas to the transaction.
+    ///
+    /// If the transaction exceeds the `transaction_gas_limit` of the consensus
+    /// configuration, its execution is aborted, the changes made by it are discarded
+    /// and it is committed with the `OutOfGas` error. Services should charge the gas
+    /// for the storage operations and the iterations that depend on the transaction
+    /// payload, so that the work done by a transaction is bounded.
+    pub fn charge_gas(&self, amount: u64) {
+        self.gas_meter.charge(amount);
+    }
+
+    /// Returns the amount of gas consumed by the transaction so far.
+    pub fn gas_used(&self) -> u64 {
+        self.gas_meter.used()
+    }
+
+    /// Returns the amount of gas the transaction can still consume, or `None`
+    /// if the gas is not limited.
+    pub fn gas_left(&self) -> Option<u64> {
+        self.gas_meter.left()
+    }
+}
+
+/// Accounts the gas consumed by a transaction against its limit.
+#[derive(Debug, Default)]
+pub(crate) struct GasMeter {
+    limit: Option<u64>,
+    used: Cell<u64>,
+}
+
+impl GasMeter {
+    /// Creates a meter with the given limit, or an unlimited one.
+    pub(crate) fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            used: Cell::new(0),
+        }
+    }
+
+    /// Returns the consumed amount of gas.
+    pub(crate) fn used(&self) -> u64 {
+        self.used.get()
+    }
+
+    fn left(&self) -> Option<u64> {
+        self.limit.map(|limit| limit.saturating_sub(self.used()))
+    }
+
+    /// Adds the amount to the consumed gas, aborting the execution with
+    /// the `OutOfGas` panic if the limit is exceeded.
+    pub(crate) fn charge(&self, amount: u64) {
+        let used = self.used().saturating_add(amount);
+        self.used.set(used);
+        if let Some(limit) = self.limit {
+            if used > limit {
+                // Unlike `panic!`, this does not invoke the panic hook, since
+                // running out of gas is an expected outcome of the execution.
+                panic::resume_unwind(Box::new(OutOfGas { limit, used }));
+            }
+        }
+    }
+}
+
+/// Panic payload of a transaction that has exceeded its gas limit.
+#[derive(Debug)]
+pub(crate) struct OutOfGas {
+    limit: u64,
+    used: u64,
+}
+
+impl fmt::Display for OutOfGas {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Gas limit {} exceeded: {} used", self.limit, self.used)
+    }
 }
 
 /// Result of unsuccessful transaction execution.
@@ -244,7 +503,7 @@ impl<'a> TransactionContext<'a> {
 /// descriptions are mostly used for developer purposes, not for interaction of
 /// the system with users.
 ///
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ExecutionError {
     /// User-defined error code. Error codes can have different meanings for different
     /// transactions and services.
@@ -276,6 +535,20 @@ impl ExecutionError {
 pub enum TransactionErrorType {
     /// Panic occurred during transaction execution.
     Panic,
+    /// The transaction author is not allowed to send the transaction by the access
+    /// control list of the service. Such transactions are not executed.
+    Unauthorized,
+    /// The transaction has exceeded the gas limit set by the consensus configuration.
+    /// Its changes are discarded.
+    OutOfGas,
+    /// The author could not pay the transaction fee. Such transactions are not executed.
+    InsufficientFee,
+    /// The transaction does not fit into the block size or gas limits set by the consensus
+    /// configuration. Such transactions are not executed.
+    BlockLimitExceeded,
+    /// The service of the transaction is stopped or frozen. Such transactions are
+    /// not executed.
+    ServiceInactive,
     /// User-defined error code. Can have different meanings for different transactions and
     /// services.
     Code(u8),
@@ -291,6 +564,16 @@ pub enum TransactionErrorType {
 ///   implementation for the details).
 /// - `TransactionErrorType::Panic` is set by the framework if panic is raised during transaction
 ///   execution.
+/// - `TransactionErrorType::Unauthorized` is set by the framework if the transaction author
+///   is rejected by the access control list of the service.
+/// - `TransactionErrorType::OutOfGas` is set by the framework if the transaction exceeds
+///   the gas limit.
+/// - `TransactionErrorType::InsufficientFee` is set by the framework if the author cannot pay
+///   the transaction fee.
+/// - `TransactionErrorType::BlockLimitExceeded` is set by the framework if the transaction
+///   does not fit into the block limits.
+/// - `TransactionErrorType::ServiceInactive` is set by the framework if the service
+///   of the transaction has been stopped or frozen after the transaction got into the pool.
 /// - `TransactionError` implements `Display` which can be used for obtaining a simple error
 ///   description.
 ///
@@ -348,6 +631,31 @@ impl TransactionError {
         Self::new(TransactionErrorType::Panic, description)
     }
 
+    /// Creates a new `TransactionError` representing rejection by the access control list.
+    pub(crate) fn unauthorized(description: Option<String>) -> Self {
+        Self::new(TransactionErrorType::Unauthorized, description)
+    }
+
+    /// Creates a new `TransactionError` representing the exceeded gas limit.
+    pub(crate) fn out_of_gas(description: Option<String>) -> Self {
+        Self::new(TransactionErrorType::OutOfGas, description)
+    }
+
+    /// Creates a new `TransactionError` representing the unpaid transaction fee.
+    pub(crate) fn insufficient_fee(description: Option<String>) -> Self {
+        Self::new(TransactionErrorType::InsufficientFee, description)
+    }
+
+    /// Creates a new `TransactionError` representing the exceeded block limits.
+    pub(crate) fn block_limit_exceeded(description: Option<String>) -> Self {
+        Self::new(TransactionErrorType::BlockLimitExceeded, description)
+    }
+
+    /// Creates a new `TransactionError` representing the inactive service.
+    pub(crate) fn service_inactive(description: Option<String>) -> Self {
+        Self::new(TransactionErrorType::ServiceInactive, description)
+    }
+
     /// Creates a new `TransactionError` instance from `std::thread::Result`'s `Err`.
     pub(crate) fn from_panic(panic: &Box<dyn Any + Send>) -> Self {
         Self::panic(panic_description(panic))
@@ -375,6 +683,11 @@ impl fmt::Display for TransactionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.error_type {
             TransactionErrorType::Panic => write!(f, "Panic during execution")?,
+            TransactionErrorType::Unauthorized => write!(f, "Unauthorized")?,
+            TransactionErrorType::OutOfGas => write!(f, "Out of gas")?,
+            TransactionErrorType::InsufficientFee => write!(f, "Insufficient fee")?,
+            TransactionErrorType::BlockLimitExceeded => write!(f, "Block limit exceeded")?,
+            TransactionErrorType::ServiceInactive => write!(f, "Service inactive")?,
             TransactionErrorType::Code(c) => write!(f, "Error code: {}", c)?,
         }
 
@@ -428,6 +741,17 @@ impl ProtobufConvert for TransactionResult {
             value @ 0..=MAX_ERROR_CODE => Err(TransactionError::code(value as u8, description)),
             TRANSACTION_STATUS_OK => Ok(()),
             TRANSACTION_STATUS_PANIC => Err(TransactionError::panic(description)),
+            TRANSACTION_STATUS_UNAUTHORIZED => Err(TransactionError::unauthorized(description)),
+            TRANSACTION_STATUS_OUT_OF_GAS => Err(TransactionError::out_of_gas(description)),
+            TRANSACTION_STATUS_INSUFFICIENT_FEE => {
+                Err(TransactionError::insufficient_fee(description))
+            }
+            TRANSACTION_STATUS_BLOCK_LIMIT_EXCEEDED => {
+                Err(TransactionError::block_limit_exceeded(description))
+            }
+            TRANSACTION_STATUS_SERVICE_INACTIVE => {
+                Err(TransactionError::service_inactive(description))
+            }
             value => bail!("Invalid TransactionResult value: {}", value),
         }))
     }
@@ -456,6 +780,11 @@ fn status_as_u16(status: &TransactionResult) -> u16 {
         Ok(()) => TRANSACTION_STATUS_OK,
         Err(ref e) => match e.error_type {
             TransactionErrorType::Panic => TRANSACTION_STATUS_PANIC,
+            TransactionErrorType::Unauthorized => TRANSACTION_STATUS_UNAUTHORIZED,
+            TransactionErrorType::OutOfGas => TRANSACTION_STATUS_OUT_OF_GAS,
+            TransactionErrorType::InsufficientFee => TRANSACTION_STATUS_INSUFFICIENT_FEE,
+            TransactionErrorType::BlockLimitExceeded => TRANSACTION_STATUS_BLOCK_LIMIT_EXCEEDED,
+            TransactionErrorType::ServiceInactive => TRANSACTION_STATUS_SERVICE_INACTIVE,
             TransactionErrorType::Code(c) => u16::from(c),
         },
     }
@@ -487,8 +816,6 @@ fn panic_description(any: &Box<dyn Any + Send>) -> Option<String> {
 
 #[cfg(test)]
 mod tests {
-    use futures::sync::mpsc;
-
     use std::collections::BTreeMap;
     use std::panic;
     use std::sync::Mutex;
@@ -498,7 +825,6 @@ mod tests {
     use crate::crypto;
     use crate::helpers::{Height, ValidatorId};
     use crate::messages::Message;
-    use crate::node::ApiSender;
     use crate::proto;
     use exonum_merkledb::{Database, Entry, Snapshot, TemporaryDB};
 
@@ -535,6 +861,11 @@ mod tests {
         let values = vec![
             (TransactionErrorType::Panic, None),
             (TransactionErrorType::Panic, Some("panic")),
+            (TransactionErrorType::Unauthorized, None),
+            (TransactionErrorType::OutOfGas, Some("out of gas")),
+            (TransactionErrorType::InsufficientFee, None),
+            (TransactionErrorType::BlockLimitExceeded, None),
+            (TransactionErrorType::ServiceInactive, None),
             (TransactionErrorType::Code(0), None),
             (TransactionErrorType::Code(1), Some("")),
             (TransactionErrorType::Code(100), None),
@@ -577,6 +908,19 @@ mod tests {
             Err(TransactionError::panic(Some(
                 "Panic error description".to_owned(),
             ))),
+            Err(TransactionError::unauthorized(None)),
+            Err(TransactionError::unauthorized(Some(
+                "Unauthorized error description".to_owned(),
+            ))),
+            Err(TransactionError::out_of_gas(None)),
+            Err(TransactionError::out_of_gas(Some(
+                "Gas limit 10 exceeded: 11 used".to_owned(),
+            ))),
+            Err(TransactionError::insufficient_fee(Some(
+                "Balance is too low".to_owned(),
+            ))),
+            Err(TransactionError::block_limit_exceeded(None)),
+            Err(TransactionError::service_inactive(None)),
             Err(TransactionError::code(0, None)),
             Err(TransactionError::code(
                 0,
@@ -695,15 +1039,7 @@ mod tests {
     }
 
     fn create_blockchain() -> Blockchain {
-        let service_keypair = crypto::gen_keypair();
-        let api_channel = mpsc::channel(0);
-        Blockchain::new(
-            TemporaryDB::new(),
-            vec![Box::new(TxResultService) as Box<dyn Service>],
-            service_keypair.0,
-            service_keypair.1,
-            ApiSender::new(api_channel.0),
-        )
+        crate::blockchain::tests::create_blockchain(vec![Box::new(TxResultService)])
     }
 
     struct TxResultService;
@@ -717,7 +1053,7 @@ mod tests {
             "test service"
         }
 
-        fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
             vec![]
         }
 
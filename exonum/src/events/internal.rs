@@ -148,7 +148,7 @@ mod tests {
     #[test]
     fn verify_msg() {
         let (pk, sk) = gen_keypair();
-        let tx = SignedMessage::new(0, 0, &[0; 200], pk, &sk);
+        let tx = SignedMessage::new(0, 0, 0, &[0; 200], pk, &sk);
 
         let expected_event =
             InternalEvent::MessageVerified(Box::new(Message::deserialize(tx.clone()).unwrap()));
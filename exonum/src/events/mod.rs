@@ -16,12 +16,14 @@
 
 pub use self::internal::InternalPart;
 pub use self::network::{NetworkConfiguration, NetworkEvent, NetworkPart, NetworkRequest};
+pub use self::proxy::{ProxyConfig, ProxyKind};
 
 pub mod codec;
 pub mod error;
 pub mod internal;
 pub mod network;
 pub mod noise;
+pub mod proxy;
 
 use futures::{
     sink::Wait,
@@ -36,10 +36,11 @@ use crate::{
         codec::MessagesCodec,
         error::into_failure,
         noise::{Handshake, HandshakeParams, NoiseHandshake},
+        proxy::{self, ProxyConfig},
     },
     helpers::Milliseconds,
     messages::{Connect, Message, Service, Signed, SignedMessage},
-    node::state::SharedConnectList,
+    node::{state::SharedConnectList, GossipConfig, ReconnectOrder},
 };
 
 const OUTGOING_CHANNEL_SIZE: usize = 10;
@@ -74,7 +75,7 @@ pub enum NetworkRequest {
     Shutdown,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct NetworkConfiguration {
     // TODO: Think more about config parameters. (ECR-162)
     pub max_incoming_connections: usize,
@@ -83,6 +84,15 @@ pub struct NetworkConfiguration {
     pub tcp_keep_alive: Option<u64>,
     pub tcp_connect_retry_timeout: Milliseconds,
     pub tcp_connect_max_retries: u64,
+    /// Proxy for the outgoing connections to peers.
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Propagation of transactions between the peers.
+    #[serde(default)]
+    pub gossip: GossipConfig,
+    /// Order in which the known peers are connected on start.
+    #[serde(default)]
+    pub reconnect_order: ReconnectOrder,
 }
 
 impl Default for NetworkConfiguration {
@@ -94,6 +104,9 @@ impl Default for NetworkConfiguration {
             tcp_nodelay: true,
             tcp_connect_retry_timeout: 15_000,
             tcp_connect_max_retries: 10,
+            proxy: None,
+            gossip: GossipConfig::default(),
+            reconnect_order: ReconnectOrder::default(),
         }
     }
 }
@@ -355,7 +368,7 @@ impl NetworkHandler {
         let handshake_params = handshake_params.clone();
         let handle = self.handle.clone();
         let network_tx = self.network_tx.clone();
-        let network_config = self.network_config;
+        let network_config = self.network_config.clone();
         let timeout = self.network_config.tcp_connect_retry_timeout;
         let max_tries = self.network_config.tcp_connect_max_retries as usize;
         let max_connections = self.network_config.max_outgoing_connections;
@@ -371,7 +384,8 @@ impl NetworkHandler {
         if let Some(unresolved_address) = unresolved_address {
             let action = {
                 let unresolved_address = unresolved_address.clone();
-                move || tokio_dns::TcpStream::connect(unresolved_address.as_str())
+                let proxy = network_config.proxy.clone();
+                move || proxy::connect(proxy.as_ref(), &unresolved_address)
             };
 
             let (sender_tx, receiver_rx) = mpsc::channel::<SignedMessage>(OUTGOING_CHANNEL_SIZE);
@@ -379,7 +393,7 @@ impl NetworkHandler {
             Either::A(
                 Retry::spawn(strategy, action)
                     .map_err(into_failure)
-                    .and_then(move |socket| Self::configure_socket(socket, network_config))
+                    .and_then(move |socket| Self::configure_socket(socket, &network_config))
                     .and_then(move |outgoing_connection| {
                         Self::build_handshake_initiator(outgoing_connection, key, &handshake_params)
                     })
@@ -486,7 +500,7 @@ impl NetworkHandler {
 
     fn configure_socket(
         socket: TcpStream,
-        network_config: NetworkConfiguration,
+        network_config: &NetworkConfiguration,
     ) -> Result<TcpStream, failure::Error> {
         socket.set_nodelay(network_config.tcp_nodelay)?;
         let duration = network_config.tcp_keep_alive.map(Duration::from_millis);
@@ -0,0 +1,385 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Outgoing connections to peers through SOCKS5 or HTTP `CONNECT` proxies.
+
+use futures::future::{self, Future, Loop};
+use tokio::net::TcpStream;
+use tokio_io::io::{read_exact, write_all};
+
+use std::{io, net::IpAddr};
+
+const SOCKS5_VERSION: u8 = 5;
+const SOCKS5_NO_AUTHENTICATION: u8 = 0;
+const SOCKS5_CONNECT: u8 = 1;
+const SOCKS5_SUCCEEDED: u8 = 0;
+const SOCKS5_ATYP_IPV4: u8 = 1;
+const SOCKS5_ATYP_DOMAIN: u8 = 3;
+const SOCKS5_ATYP_IPV6: u8 = 4;
+
+/// Maximum length of the response head accepted from an HTTP proxy.
+const MAX_HTTP_HEAD_LENGTH: usize = 8192;
+
+type ProxyFuture<T> = Box<dyn Future<Item = T, Error = io::Error>>;
+
+/// Protocol of the proxy server.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyKind {
+    /// SOCKS5 proxy without authentication. Host names of the peers are resolved
+    /// by the proxy.
+    Socks5,
+    /// HTTP proxy supporting the `CONNECT` method.
+    Http,
+}
+
+/// Proxy for the outgoing connections to peers.
+///
+/// Incoming connections are not affected. Since the peer is connected through
+/// the proxy, the address of the proxy is reported as the peer address.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProxyConfig {
+    /// Protocol of the proxy.
+    pub kind: ProxyKind,
+    /// Address of the proxy in the `host:port` format.
+    pub address: String,
+    /// Destinations connected directly, bypassing the proxy. A rule is either a host
+    /// name or an IP address, a domain suffix starting with a dot (e.g., `.internal`),
+    /// or an IP network in the CIDR notation (e.g., `10.0.0.0/8`).
+    #[serde(default)]
+    pub bypass: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// Returns `true` if the destination in the `host:port` format is connected
+    /// directly.
+    pub fn is_bypassed(&self, destination: &str) -> bool {
+        let host = split_host_port(destination).map_or(destination, |(host, _)| host);
+        let host = host.to_ascii_lowercase();
+        self.bypass
+            .iter()
+            .any(|rule| bypass_rule_matches(&rule.to_ascii_lowercase(), &host))
+    }
+}
+
+/// Opens a TCP connection to the destination in the `host:port` format, through
+/// the proxy unless it is not set or the destination bypasses it.
+pub fn connect(proxy: Option<&ProxyConfig>, destination: &str) -> ProxyFuture<TcpStream> {
+    let proxy = match proxy {
+        Some(proxy) if !proxy.is_bypassed(destination) => proxy,
+        _ => return Box::new(tokio_dns::TcpStream::connect(destination)),
+    };
+    let (host, port) = match split_host_port(destination) {
+        Some((host, port)) => (host.to_owned(), port),
+        None => {
+            let message = format!("Invalid peer address {}", destination);
+            return Box::new(future::err(proxy_error(message)));
+        }
+    };
+
+    let stream = tokio_dns::TcpStream::connect(proxy.address.as_str());
+    match proxy.kind {
+        ProxyKind::Socks5 => Box::new(stream.and_then(move |s| socks5_connect(s, &host, port))),
+        ProxyKind::Http => Box::new(stream.and_then(move |s| http_connect(s, &host, port))),
+    }
+}
+
+fn proxy_error(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message.into())
+}
+
+fn split_host_port(address: &str) -> Option<(&str, u16)> {
+    let separator = address.rfind(':')?;
+    let port = address[separator + 1..].parse().ok()?;
+    let host = address[..separator]
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    Some((host, port))
+}
+
+fn bypass_rule_matches(rule: &str, host: &str) -> bool {
+    if rule.starts_with('.') {
+        return host.ends_with(rule) || host == &rule[1..];
+    }
+    if let Some(separator) = rule.find('/') {
+        let network = rule[..separator].parse::<IpAddr>();
+        let prefix_len = rule[separator + 1..].parse::<u32>();
+        return match (network, prefix_len, host.parse::<IpAddr>()) {
+            (Ok(network), Ok(prefix_len), Ok(address)) => {
+                is_in_network(network, prefix_len, address)
+            }
+            _ => false,
+        };
+    }
+    rule == host
+}
+
+fn is_in_network(network: IpAddr, prefix_len: u32, address: IpAddr) -> bool {
+    let (network, address, bits) = match (network, address) {
+        (IpAddr::V4(network), IpAddr::V4(address)) => (
+            u128::from(u32::from(network)),
+            u128::from(u32::from(address)),
+            32,
+        ),
+        (IpAddr::V6(network), IpAddr::V6(address)) => {
+            (u128::from(network), u128::from(address), 128)
+        }
+        _ => return false,
+    };
+    match prefix_len {
+        0 => true,
+        len if len > bits => false,
+        len => network >> (bits - len) == address >> (bits - len),
+    }
+}
+
+fn socks5_connect_request(host: &str, port: u16) -> io::Result<Vec<u8>> {
+    let mut request = vec![SOCKS5_VERSION, SOCKS5_CONNECT, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(address)) => {
+            request.push(SOCKS5_ATYP_IPV4);
+            request.extend_from_slice(&address.octets());
+        }
+        Ok(IpAddr::V6(address)) => {
+            request.push(SOCKS5_ATYP_IPV6);
+            request.extend_from_slice(&address.octets());
+        }
+        Err(_) => {
+            if host.len() > usize::from(u8::max_value()) {
+                return Err(proxy_error(format!("Too long host name {}", host)));
+            }
+            request.push(SOCKS5_ATYP_DOMAIN);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    Ok(request)
+}
+
+fn socks5_connect(stream: TcpStream, host: &str, port: u16) -> ProxyFuture<TcpStream> {
+    let request = match socks5_connect_request(host, port) {
+        Ok(request) => request,
+        Err(e) => return Box::new(future::err(e)),
+    };
+
+    let greeting = [SOCKS5_VERSION, 1, SOCKS5_NO_AUTHENTICATION];
+    let connected = write_all(stream, greeting)
+        .and_then(|(stream, _)| read_exact(stream, [0_u8; 2]))
+        .and_then(|(stream, reply)| {
+            if reply != [SOCKS5_VERSION, SOCKS5_NO_AUTHENTICATION] {
+                return Err(proxy_error("SOCKS5 proxy requires authentication"));
+            }
+            Ok(stream)
+        })
+        .and_then(move |stream| write_all(stream, request))
+        .and_then(|(stream, _)| read_exact(stream, [0_u8; 4]))
+        .and_then(|(stream, reply)| -> ProxyFuture<(TcpStream, usize)> {
+            if reply[0] != SOCKS5_VERSION || reply[1] != SOCKS5_SUCCEEDED {
+                let message = format!("SOCKS5 proxy refused the connection, reply={}", reply[1]);
+                return Box::new(future::err(proxy_error(message)));
+            }
+            match reply[3] {
+                SOCKS5_ATYP_IPV4 => Box::new(future::ok((stream, 4))),
+                SOCKS5_ATYP_IPV6 => Box::new(future::ok((stream, 16))),
+                SOCKS5_ATYP_DOMAIN => Box::new(
+                    read_exact(stream, [0_u8; 1])
+                        .map(|(stream, len)| (stream, usize::from(len[0]))),
+                ),
+                atyp => {
+                    let message = format!("Unknown SOCKS5 address type {}", atyp);
+                    Box::new(future::err(proxy_error(message)))
+                }
+            }
+        })
+        // Skips the address bound by the proxy and its port.
+        .and_then(|(stream, len)| read_exact(stream, vec![0_u8; len + 2]))
+        .map(|(stream, _)| stream);
+    Box::new(connected)
+}
+
+fn http_connect(stream: TcpStream, host: &str, port: u16) -> ProxyFuture<TcpStream> {
+    let target = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", target);
+
+    let connected = write_all(stream, request.into_bytes())
+        .and_then(|(stream, _)| read_http_head(stream))
+        .and_then(|(stream, head)| {
+            let status_line = head.lines().next().unwrap_or_default();
+            if status_line.split_whitespace().nth(1) != Some("200") {
+                let message = format!("HTTP proxy refused the connection: {}", status_line);
+                return Err(proxy_error(message));
+            }
+            Ok(stream)
+        });
+    Box::new(connected)
+}
+
+// Reads the response head byte by byte, so that no data sent by the peer after it
+// is consumed.
+fn read_http_head(stream: TcpStream) -> impl Future<Item = (TcpStream, String), Error = io::Error> {
+    future::loop_fn((stream, Vec::new()), |(stream, mut head)| {
+        read_exact(stream, [0_u8; 1]).and_then(move |(stream, byte)| {
+            head.push(byte[0]);
+            if head.ends_with(b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&head).into_owned();
+                Ok(Loop::Break((stream, head)))
+            } else if head.len() > MAX_HTTP_HEAD_LENGTH {
+                Err(proxy_error("Too long HTTP proxy response"))
+            } else {
+                Ok(Loop::Continue((stream, head)))
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_core::reactor::Core;
+
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    fn proxy(kind: ProxyKind, address: String) -> ProxyConfig {
+        ProxyConfig {
+            kind,
+            address,
+            bypass: vec![
+                "localhost".to_owned(),
+                ".internal".to_owned(),
+                "10.0.0.0/8".to_owned(),
+                "fd00::/8".to_owned(),
+            ],
+        }
+    }
+
+    // Runs a proxy serving a single connection with `serve`.
+    fn spawn_proxy<F>(serve: F) -> (String, thread::JoinHandle<()>)
+    where
+        F: FnOnce(&mut std::net::TcpStream) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            serve(&mut stream);
+        });
+        (address, handle)
+    }
+
+    fn read_greeting(stream: TcpStream) -> Vec<u8> {
+        let read = read_exact(stream, vec![0_u8; 5]).map(|(_, greeting)| greeting);
+        Core::new().unwrap().run(read).unwrap()
+    }
+
+    #[test]
+    fn bypass_rules() {
+        let proxy = proxy(ProxyKind::Socks5, "127.0.0.1:1080".to_owned());
+        assert!(proxy.is_bypassed("localhost:6333"));
+        assert!(proxy.is_bypassed("Node1.Internal:6333"));
+        assert!(proxy.is_bypassed("internal:6333"));
+        assert!(proxy.is_bypassed("10.1.2.3:6333"));
+        assert!(proxy.is_bypassed("[fd12::1]:6333"));
+        assert!(!proxy.is_bypassed("notinternal:6333"));
+        assert!(!proxy.is_bypassed("11.1.2.3:6333"));
+        assert!(!proxy.is_bypassed("[fe80::1]:6333"));
+        assert!(!proxy.is_bypassed("example.com:6333"));
+    }
+
+    #[test]
+    fn socks5_request_encoding() {
+        assert_eq!(
+            socks5_connect_request("127.0.0.1", 6333).unwrap(),
+            vec![5, 1, 0, 1, 127, 0, 0, 1, 0x18, 0xbd]
+        );
+        assert_eq!(
+            socks5_connect_request("node", 80).unwrap(),
+            vec![5, 1, 0, 3, 4, b'n', b'o', b'd', b'e', 0, 80]
+        );
+        assert!(socks5_connect_request(&"a".repeat(256), 80).is_err());
+    }
+
+    #[test]
+    fn connect_through_socks5() {
+        let (address, handle) = spawn_proxy(|stream| {
+            let mut greeting = [0_u8; 3];
+            stream.read_exact(&mut greeting).unwrap();
+            assert_eq!(greeting, [5, 1, 0]);
+            stream.write_all(&[5, 0]).unwrap();
+
+            let mut request = vec![0_u8; 5 + "node.example".len() + 2];
+            stream.read_exact(&mut request).unwrap();
+            assert_eq!(
+                request,
+                socks5_connect_request("node.example", 6333).unwrap()
+            );
+            stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+            // The greeting of the peer.
+            stream.write_all(b"hello").unwrap();
+        });
+
+        let proxy = proxy(ProxyKind::Socks5, address);
+        let connect = connect(Some(&proxy), "node.example:6333");
+        let stream = Core::new().unwrap().run(connect).unwrap();
+        assert_eq!(read_greeting(stream), b"hello");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn connect_through_http_proxy() {
+        let (address, handle) = spawn_proxy(|stream| {
+            let mut request = Vec::new();
+            let mut byte = [0_u8; 1];
+            while !request.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                request.push(byte[0]);
+            }
+            assert!(request.starts_with(b"CONNECT node.example:6333 HTTP/1.1\r\n"));
+            stream
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .unwrap();
+            stream.write_all(b"hello").unwrap();
+        });
+
+        let proxy = proxy(ProxyKind::Http, address);
+        let connect = connect(Some(&proxy), "node.example:6333");
+        let stream = Core::new().unwrap().run(connect).unwrap();
+        assert_eq!(read_greeting(stream), b"hello");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn http_proxy_refusal() {
+        let (address, handle) = spawn_proxy(|stream| {
+            let mut request = [0_u8; 16];
+            stream.read_exact(&mut request).unwrap();
+            stream.write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n").unwrap();
+        });
+
+        let proxy = proxy(ProxyKind::Http, address);
+        let connect = connect(Some(&proxy), "node.example:6333");
+        let error = Core::new().unwrap().run(connect).unwrap_err();
+        assert!(error.to_string().contains("403 Forbidden"));
+        handle.join().unwrap();
+    }
+}
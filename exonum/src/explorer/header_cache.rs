@@ -0,0 +1,176 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! In-memory cache of block headers.
+//!
+//! Headers are small and do not change once the block is committed, so the explorer
+//! can keep a large number of them in memory instead of reading the storage on each
+//! access. This speeds up range queries and proofs over deep history. The cache is
+//! populated lazily and its size is bounded by the configured amount of memory,
+//! which accounts for the additional headers of the blocks stored on the heap.
+
+use exonum_merkledb::Snapshot;
+
+use std::{
+    fmt, mem,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    blockchain::{Block, Schema},
+    helpers::{lru::LruMap, Height},
+};
+
+/// Default memory limit of the block header cache in bytes.
+pub const DEFAULT_BLOCK_HEADER_CACHE_SIZE: usize = 16 * 1024 * 1024;
+
+// Memory used by a cached header apart from the heap data of the header, including
+// its keys in the LRU indexes.
+const ENTRY_OVERHEAD: usize =
+    mem::size_of::<(Height, (u64, Block))>() + mem::size_of::<(u64, Height)>();
+
+/// Returns the approximate memory used by a cached header, including the entries
+/// of its additional headers stored on the heap.
+fn entry_size(header: &Block) -> usize {
+    let additional_headers: usize = header
+        .additional_headers()
+        .iter()
+        .map(|(key, value)| mem::size_of::<(String, Vec<u8>)>() + key.len() + value.len())
+        .sum();
+    ENTRY_OVERHEAD + additional_headers
+}
+
+#[derive(Default)]
+struct CachedHeaders {
+    headers: LruMap<Height, Block>,
+    // Total size of the cached headers calculated by `entry_size`.
+    memory_usage: usize,
+}
+
+/// LRU cache of block headers keyed by the block height.
+///
+/// The cache is cheaply cloneable; all clones share the same storage.
+#[derive(Clone)]
+pub struct BlockHeaderCache {
+    state: Arc<Mutex<CachedHeaders>>,
+    memory_limit: usize,
+}
+
+impl BlockHeaderCache {
+    /// Creates a cache that uses approximately `memory_limit` bytes at most.
+    /// A zero limit disables caching.
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            state: Arc::default(),
+            memory_limit,
+        }
+    }
+
+    /// Returns the maximum amount of memory used by the cached headers in bytes.
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    /// Returns the approximate amount of memory used by the cached headers in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.state.lock().expect("Expected mutex lock").memory_usage
+    }
+
+    /// Returns the number of cached headers.
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .expect("Expected mutex lock")
+            .headers
+            .len()
+    }
+
+    /// Returns `true` if the cache contains no headers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the cached header for the given height, if any.
+    pub fn get(&self, height: Height) -> Option<Block> {
+        self.state
+            .lock()
+            .expect("Expected mutex lock")
+            .headers
+            .get(&height)
+            .cloned()
+    }
+
+    /// Returns the header for the given height, reading it from the snapshot and
+    /// storing it in the cache on a miss. Returns `None` if the block at the given
+    /// height has not been committed yet.
+    pub fn get_or_load(&self, snapshot: &dyn Snapshot, height: Height) -> Option<Block> {
+        if let Some(header) = self.get(height) {
+            return Some(header);
+        }
+
+        let schema = Schema::new(snapshot);
+        let header = schema
+            .block_hash_by_height(height)
+            .and_then(|hash| schema.blocks().get(&hash))?;
+        self.insert(height, header.clone());
+        Some(header)
+    }
+
+    /// Stores the header read by the caller, e.g., while reading a range of blocks.
+    /// The least recently used headers are evicted once the memory limit is exceeded.
+    pub(crate) fn insert(&self, height: Height, header: Block) {
+        let size = entry_size(&header);
+        if size > self.memory_limit {
+            return;
+        }
+
+        let mut state = self.state.lock().expect("Expected mutex lock");
+        if let Some(replaced) = state.headers.remove(&height) {
+            state.memory_usage -= entry_size(&replaced);
+        }
+        state.headers.insert(height, header, usize::max_value());
+        state.memory_usage += size;
+        while state.memory_usage > self.memory_limit {
+            let (_, evicted) = state
+                .headers
+                .pop_oldest()
+                .expect("A single header fits into the memory limit");
+            state.memory_usage -= entry_size(&evicted);
+        }
+    }
+
+    /// Drops all cached headers. Must be called if the committed blocks are
+    /// replaced, e.g., after a rollback of the storage.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().expect("Expected mutex lock");
+        state.headers.clear();
+        state.memory_usage = 0;
+    }
+}
+
+impl Default for BlockHeaderCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_BLOCK_HEADER_CACHE_SIZE)
+    }
+}
+
+impl fmt::Debug for BlockHeaderCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlockHeaderCache")
+            .field("memory_limit", &self.memory_limit)
+            .field("memory_usage", &self.memory_usage())
+            .field("len", &self.len())
+            .finish()
+    }
+}
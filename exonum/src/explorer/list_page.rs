@@ -0,0 +1,157 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pages of Merkelized lists.
+//!
+//! A page contains a contiguous slice of a `ProofListIndex` together with a single
+//! range proof for the slice. Clients paging through a list can check every page
+//! against the trusted list hash without requesting proofs for separate items.
+
+use exonum_merkledb::{
+    proof_list_index::ListProofError, BinaryValue, IndexAccess, ListProof, ObjectHash,
+    ProofListIndex,
+};
+
+use crate::crypto::Hash;
+
+/// Slice of a Merkelized list accompanied by the proof for the slice.
+///
+/// # JSON presentation
+///
+/// | Name | Equivalent type | Description |
+/// |------|-------|--------|
+/// | `offset` | `u64` | Index of the first element of the page |
+/// | `list_len` | `u64` | Length of the whole list |
+/// | `next_offset` | `Option<u64>` | Offset of the next page, if there is one |
+/// | `proof` | [`ListProof`] | Range proof for the elements of the page |
+///
+/// [`ListProof`]: ../../exonum_merkledb/proof_list_index/enum.ListProof.html
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListPage<V> {
+    /// Index of the first element of the page.
+    pub offset: u64,
+    /// Length of the whole list.
+    pub list_len: u64,
+    /// Offset of the next page, or `None` if the page is the last one.
+    pub next_offset: Option<u64>,
+    /// Range proof for the elements of the page. If the offset is beyond the end of the list,
+    /// this is the proof of absence.
+    #[serde(bound(
+        serialize = "V: serde::Serialize",
+        deserialize = "V: serde::de::DeserializeOwned"
+    ))]
+    pub proof: ListProof<V>,
+}
+
+impl<V> ListPage<V>
+where
+    V: BinaryValue + ObjectHash,
+{
+    /// Returns the page of at most `limit` elements of the list starting from `offset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    pub fn new<T: IndexAccess>(list: &ProofListIndex<T, V>, offset: u64, limit: u64) -> Self {
+        assert!(limit > 0, "Page limit should be positive");
+
+        let list_len = list.len();
+        if offset >= list_len {
+            return Self {
+                offset,
+                list_len,
+                next_offset: None,
+                proof: list.get_proof(offset),
+            };
+        }
+
+        let end = offset.saturating_add(limit).min(list_len);
+        Self {
+            offset,
+            list_len,
+            next_offset: if end < list_len { Some(end) } else { None },
+            proof: list.get_range_proof(offset..end),
+        }
+    }
+
+    /// Checks the page against the trusted hash of the list and returns the elements
+    /// of the page with their indexes.
+    ///
+    /// Besides the proof itself, the method checks that the proof covers the whole page,
+    /// so that the server cannot silently omit elements from it.
+    pub fn validate(&self, list_hash: Hash) -> Result<Vec<(u64, &V)>, ListProofError> {
+        let entries = self.proof.validate(list_hash, self.list_len)?;
+        let end = self.next_offset.unwrap_or(self.list_len);
+        let expected = end.saturating_sub(self.offset);
+        let contiguous = entries
+            .iter()
+            .zip(self.offset..)
+            .all(|((index, _), expected_index)| *index == expected_index);
+        if entries.len() as u64 != expected || !contiguous {
+            return Err(ListProofError::UnmatchedRootHash);
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_merkledb::{Database, ObjectHash, ProofListIndex, TemporaryDB};
+
+    use super::ListPage;
+
+    #[test]
+    fn pages_cover_list() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut list = ProofListIndex::new("list", &fork);
+        list.extend(0_u64..10);
+        let list_hash = list.object_hash();
+
+        let mut offset = Some(0);
+        let mut values = Vec::new();
+        while let Some(current) = offset {
+            let page = ListPage::new(&list, current, 3);
+            assert_eq!(page.list_len, 10);
+            let entries = page.validate(list_hash).unwrap();
+            values.extend(entries.into_iter().map(|(_, value)| *value));
+            offset = page.next_offset;
+        }
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn page_beyond_list_end() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut list = ProofListIndex::new("list", &fork);
+        list.extend(0_u64..4);
+
+        let page = ListPage::new(&list, 4, 10);
+        assert_eq!(page.next_offset, None);
+        assert!(page.validate(list.object_hash()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated_page_is_rejected() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut list = ProofListIndex::new("list", &fork);
+        list.extend(0_u64..8);
+
+        let mut page = ListPage::new(&list, 0, 2);
+        page.next_offset = Some(4);
+        assert!(page.validate(list.object_hash()).is_err());
+    }
+}
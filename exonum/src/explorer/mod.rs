@@ -21,23 +21,42 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::{
     cell::{Ref, RefCell},
-    collections::Bound,
+    cmp,
+    collections::{Bound, VecDeque},
     fmt,
     ops::{Index, RangeBounds},
     slice,
 };
 
 use crate::blockchain::{
-    Block, Blockchain, Schema, TransactionError, TransactionErrorType, TransactionMessage,
-    TransactionResult, TxLocation,
+    Block, Blockchain, Schema, StoredConfiguration, TransactionArchive, TransactionError,
+    TransactionErrorType, TransactionEvent, TransactionMessage, TransactionResult, TransactionTag,
+    TxLocation,
 };
-use crate::crypto::{CryptoHash, Hash};
+use crate::crypto::{self, CryptoHash, Hash};
 use crate::helpers::Height;
 use crate::messages::{Precommit, RawTransaction, Signed};
 use chrono::{DateTime, Utc};
 use exonum_merkledb::{ListProof, Snapshot};
 use std::time::UNIX_EPOCH;
 
+pub use self::{
+    header_cache::{BlockHeaderCache, DEFAULT_BLOCK_HEADER_CACHE_SIZE},
+    list_page::ListPage,
+};
+
+mod header_cache;
+mod list_page;
+
+/// Maximum number of blocks read from the storage at once by the [`Blocks`] iterator.
+///
+/// [`Blocks`]: struct.Blocks.html
+pub const BLOCKS_READ_AHEAD: u64 = 256;
+
+/// Number of blocks in the first batch read by the `Blocks` iterator. Each next batch
+/// is twice as large, so that short iterations do not read unneeded blocks.
+const INITIAL_READ_AHEAD: u64 = 8;
+
 /// Transaction parsing result.
 type ParseResult = Result<TransactionMessage, failure::Error>;
 
@@ -79,24 +98,22 @@ pub struct BlockInfo<'a> {
 
 impl<'a> BlockInfo<'a> {
     fn new(explorer: &'a BlockchainExplorer, height: Height) -> Self {
-        let schema = Schema::new(&explorer.snapshot);
-        let header = {
-            let hashes = schema.block_hashes_by_height();
-            let blocks = schema.blocks();
-
-            let block_hash = hashes
-                .get(height.0)
-                .unwrap_or_else(|| panic!("Block not found, height: {:?}", height));
-            blocks
-                .get(&block_hash)
-                .unwrap_or_else(|| panic!("Block not found, hash: {:?}", block_hash))
-        };
+        let header = explorer
+            .block_header(height)
+            .unwrap_or_else(|| panic!("Block not found, height: {:?}", height));
+        Self::with_header(explorer, header, None)
+    }
 
+    fn with_header(
+        explorer: &'a BlockchainExplorer,
+        header: Block,
+        txs: Option<Vec<Hash>>,
+    ) -> Self {
         BlockInfo {
             explorer,
             header,
             precommits: RefCell::new(None),
-            txs: RefCell::new(None),
+            txs: RefCell::new(txs),
         }
     }
 
@@ -139,6 +156,30 @@ impl<'a> BlockInfo<'a> {
         })
     }
 
+    /// Returns the consensus time of this block, i.e., the median of the precommit times
+    /// for the previous block. The genesis block and the block following it have
+    /// no consensus time.
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        Schema::new(&self.explorer.snapshot).block_time(self.height())
+    }
+
+    /// Returns the finality statement for this block.
+    pub fn finality(&self) -> BlockFinality {
+        let schema = Schema::new(&self.explorer.snapshot);
+        let config = schema.configuration_by_height(self.height());
+        let precommits = self.precommits();
+
+        BlockFinality {
+            is_final: true,
+            confirmations: schema.height().0 - self.height().0,
+            precommits: precommits.len(),
+            precommits_weight: config.weight_of(precommits.iter().map(|p| p.validator())),
+            quorum: config.majority_weight(),
+            validators: config.validator_keys.len(),
+            validator_set_hash: validator_set_hash(&config),
+        }
+    }
+
     /// Lists hashes of transactions included in this block.
     pub fn transaction_hashes(&self) -> Ref<[Hash]> {
         if self.txs.borrow().is_none() {
@@ -149,11 +190,12 @@ impl<'a> BlockInfo<'a> {
         Ref::map(self.txs.borrow(), |cache| cache.as_ref().unwrap().as_ref())
     }
 
-    /// Returns a transaction with the specified index in the block.
+    /// Returns a transaction with the specified index in the block. Returns `None`
+    /// if the transaction has been deleted by the retention policy of the node.
     pub fn transaction(&self, index: usize) -> Option<CommittedTransaction> {
-        self.transaction_hashes()
-            .get(index)
-            .map(|hash| self.explorer.committed_transaction(hash, None))
+        let hash = *self.transaction_hashes().get(index)?;
+        let content = self.explorer.transaction_without_proof(&hash)?;
+        Some(self.explorer.committed_transaction(&hash, content))
     }
 
     /// Iterates over transactions in the block.
@@ -165,7 +207,8 @@ impl<'a> BlockInfo<'a> {
         }
     }
 
-    /// Loads transactions and precommits for the block.
+    /// Loads transactions and precommits for the block. The transactions deleted
+    /// by the retention policy of the node are omitted.
     pub fn with_transactions(self) -> BlockWithTransactions {
         let (explorer, header, precommits, transactions) =
             (self.explorer, self.header, self.precommits, self.txs);
@@ -177,7 +220,10 @@ impl<'a> BlockInfo<'a> {
             .into_inner()
             .unwrap_or_else(|| explorer.transaction_hashes(&header))
             .iter()
-            .map(|tx_hash| explorer.committed_transaction(tx_hash, None))
+            .filter_map(|tx_hash| {
+                let content = explorer.transaction_without_proof(tx_hash)?;
+                Some(explorer.committed_transaction(tx_hash, content))
+            })
             .collect();
 
         BlockWithTransactions {
@@ -200,6 +246,41 @@ impl<'a> Serialize for BlockInfo<'a> {
     }
 }
 
+/// Finality statement of a committed block.
+///
+/// Exonum blocks are final as soon as they are committed. A block is committed only
+/// with precommits of a Byzantine majority of validators, so no other block at the same
+/// height can be committed unless more than a third of the validators are Byzantine,
+/// and the blockchain is never reorganized. `confirmations` are provided for clients
+/// that expect such semantics; a block is final regardless of their number.
+///
+/// The genesis block is final by definition and has no precommits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockFinality {
+    /// Whether the block is final. Always `true` for committed blocks.
+    pub is_final: bool,
+    /// Number of blocks committed after this block.
+    pub confirmations: u64,
+    /// Number of precommits authorizing the block.
+    pub precommits: usize,
+    /// Total voting weight of the validators that have authored the precommits.
+    pub precommits_weight: u64,
+    /// Voting weight of the precommits sufficient to commit the block.
+    pub quorum: u64,
+    /// Number of validators at the height of the block.
+    pub validators: usize,
+    /// Hash of the concatenated consensus keys of the validators at the height of the block.
+    pub validator_set_hash: Hash,
+}
+
+fn validator_set_hash(config: &StoredConfiguration) -> Hash {
+    let mut keys = Vec::with_capacity(config.validator_keys.len() * crypto::PUBLIC_KEY_LENGTH);
+    for validator in &config.validator_keys {
+        keys.extend_from_slice(validator.consensus_key.as_ref());
+    }
+    crypto::hash(&keys)
+}
+
 /// Iterator over transactions in a block.
 #[derive(Debug)]
 pub struct Transactions<'r, 'a: 'r> {
@@ -212,13 +293,15 @@ impl<'a, 'r> Iterator for Transactions<'a, 'r> {
     type Item = CommittedTransaction;
 
     fn next(&mut self) -> Option<CommittedTransaction> {
-        if self.ptr == self.len {
-            None
-        } else {
+        // Transactions deleted by the retention policy are skipped.
+        while self.ptr < self.len {
             let transaction = self.block.transaction(self.ptr);
             self.ptr += 1;
-            transaction
+            if transaction.is_some() {
+                return transaction;
+            }
         }
+        None
     }
 }
 
@@ -308,6 +391,7 @@ impl<'a> IntoIterator for &'a BlockWithTransactions {
 /// | `location` | [`TxLocation`] | Location of the transaction in the block |
 /// | `location_proof` | [`ListProof`]`<`[`Hash`]`>` | Proof of transaction inclusion into a block |
 /// | `status` | (custom; see below) | Execution status |
+/// | `status_proof` | [`ListProof`]`<`[`Hash`]`>` | Proof of the execution status (optional) |
 ///
 /// ## `status` field
 ///
@@ -334,12 +418,28 @@ impl<'a> IntoIterator for &'a BlockWithTransactions {
 /// { type: 'panic', description?: string }
 /// ```
 ///
+/// Transactions rejected by the access control list of the service are not executed;
+/// their `status` is
+///
+/// ```javascript
+/// { type: 'unauthorized', description?: string }
+/// ```
+///
+/// ## `status_proof` field
+///
+/// The proof of the hash of the [`TransactionResult`] at the position of the transaction
+/// in the block, which is checked against the [`tx_results_hash`] of the block header.
+/// Together with `location_proof`, it proves that the transaction has been executed
+/// with the given status. The field is absent for the blocks created before the results
+/// hash was introduced.
+///
 /// [`Transaction`]: ../blockchain/trait.Transaction.html
 /// [`TxLocation`]: ../blockchain/struct.TxLocation.html
 /// [`ListProof`]: ../../exonum_merkledb/enum.ListProof.html
 /// [`Hash`]: ../../exonum_crypto/struct.Hash.html
 /// [`TransactionResult`]: ../blockchain/struct.TransactionResult.html
 /// [`ExecutionError`]: ../blockchain/struct.ExecutionError.html
+/// [`tx_results_hash`]: ../blockchain/struct.Block.html#method.tx_results_hash
 /// [`Flow`]: https://flow.org/
 /// [`TypeScript`]: https://www.typescriptlang.org/
 ///
@@ -407,7 +507,11 @@ pub struct CommittedTransaction {
     location_proof: ListProof<Hash>,
     #[serde(with = "TxStatus")]
     status: TransactionResult,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status_proof: Option<ListProof<Hash>>,
     time: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<TransactionTag>,
 }
 
 /// Transaction execution status. Simplified version of `TransactionResult`.
@@ -416,6 +520,11 @@ pub struct CommittedTransaction {
 pub(crate) enum TxStatus<'a> {
     Success,
     Panic { description: &'a str },
+    Unauthorized { description: &'a str },
+    OutOfGas { description: &'a str },
+    InsufficientFee { description: &'a str },
+    BlockLimitExceeded { description: &'a str },
+    ServiceInactive { description: &'a str },
     Error { code: u8, description: &'a str },
 }
 
@@ -447,6 +556,11 @@ impl<'a> From<&'a TransactionResult> for TxStatus<'a> {
                 let description = e.description().unwrap_or_default();
                 match e.error_type() {
                     Panic => TxStatus::Panic { description },
+                    Unauthorized => TxStatus::Unauthorized { description },
+                    OutOfGas => TxStatus::OutOfGas { description },
+                    InsufficientFee => TxStatus::InsufficientFee { description },
+                    BlockLimitExceeded => TxStatus::BlockLimitExceeded { description },
+                    ServiceInactive => TxStatus::ServiceInactive { description },
                     Code(code) => TxStatus::Error { code, description },
                 }
             }
@@ -467,6 +581,21 @@ impl<'a> From<TxStatus<'a>> for TransactionResult {
         TransactionResult(match status {
             TxStatus::Success => Ok(()),
             TxStatus::Panic { description } => Err(TransactionError::panic(to_option(description))),
+            TxStatus::Unauthorized { description } => {
+                Err(TransactionError::unauthorized(to_option(description)))
+            }
+            TxStatus::OutOfGas { description } => {
+                Err(TransactionError::out_of_gas(to_option(description)))
+            }
+            TxStatus::InsufficientFee { description } => {
+                Err(TransactionError::insufficient_fee(to_option(description)))
+            }
+            TxStatus::BlockLimitExceeded { description } => Err(
+                TransactionError::block_limit_exceeded(to_option(description)),
+            ),
+            TxStatus::ServiceInactive { description } => {
+                Err(TransactionError::service_inactive(to_option(description)))
+            }
             TxStatus::Error { code, description } => {
                 Err(TransactionError::code(code, to_option(description)))
             }
@@ -495,10 +624,23 @@ impl CommittedTransaction {
         self.status.0.as_ref().map(|_| ())
     }
 
-    /// Returns a commit time of the block which includes this transaction.
+    /// Returns a proof of the execution status of the transaction, which is checked
+    /// against the `tx_results_hash` of the block. The proof is absent for the blocks
+    /// created before the results hash was introduced.
+    pub fn status_proof(&self) -> Option<&ListProof<Hash>> {
+        self.status_proof.as_ref()
+    }
+
+    /// Returns the consensus time of the block which includes this transaction.
     pub fn time(&self) -> &DateTime<Utc> {
         &self.time
     }
+
+    /// Returns the tag of the transaction, if the transaction has been submitted
+    /// to this node with a tag.
+    pub fn tag(&self) -> Option<&TransactionTag> {
+        self.tag.as_ref()
+    }
 }
 
 /// Information about the transaction.
@@ -526,6 +668,9 @@ impl CommittedTransaction {
 /// - `type` field contains transaction type (`"in-pool"`).
 /// - `content` is JSON serialization of the transaction.
 ///
+/// Both representations contain the `tag` field if the transaction has been submitted
+/// to the node with a tag.
+///
 /// # Examples
 ///
 /// Use of the custom type parameter for deserialization:
@@ -585,6 +730,9 @@ pub enum TransactionInfo {
     InPool {
         /// Transaction contents.
         content: TransactionMessage,
+        /// Tag of the transaction, if it has been submitted to this node with a tag.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<TransactionTag>,
     },
 
     /// Transaction is already committed to the blockchain.
@@ -595,7 +743,7 @@ impl TransactionInfo {
     /// Returns the content of this transaction.
     pub fn content(&self) -> &TransactionMessage {
         match *self {
-            TransactionInfo::InPool { ref content } => content,
+            TransactionInfo::InPool { ref content, .. } => content,
             TransactionInfo::Committed(ref tx) => tx.content(),
         }
     }
@@ -637,6 +785,8 @@ impl TransactionInfo {
 pub struct BlockchainExplorer<'a> {
     snapshot: Box<dyn Snapshot>,
     transaction_parser: Box<dyn 'a + Fn(Signed<RawTransaction>) -> ParseResult>,
+    header_cache: Option<BlockHeaderCache>,
+    archive: Option<TransactionArchive>,
 }
 
 impl<'a> fmt::Debug for BlockchainExplorer<'a> {
@@ -654,25 +804,45 @@ impl<'a> BlockchainExplorer<'a> {
                 let tx = blockchain.tx_from_raw(raw.payload().clone())?;
                 Ok(TransactionMessage::new(raw, tx))
             }),
+            header_cache: None,
+            archive: blockchain.transaction_archive().cloned(),
+        }
+    }
+
+    /// Makes the explorer read block headers through the given cache.
+    pub fn with_header_cache(mut self, cache: BlockHeaderCache) -> Self {
+        self.header_cache = Some(cache);
+        self
+    }
+
+    fn block_header(&self, height: Height) -> Option<Block> {
+        if let Some(ref cache) = self.header_cache {
+            return cache.get_or_load(self.snapshot.as_ref(), height);
         }
+
+        let schema = Schema::new(&self.snapshot);
+        let block_hash = schema.block_hash_by_height(height)?;
+        schema.blocks().get(&block_hash)
     }
 
     /// Returns information about the transaction identified by the hash.
     pub fn transaction(&self, tx_hash: &Hash) -> Option<TransactionInfo> {
         let schema = Schema::new(&self.snapshot);
         let content = self.transaction_without_proof(tx_hash)?;
-        if schema.transactions_pool().contains(tx_hash) {
-            Some(TransactionInfo::InPool { content })
-        } else {
-            let tx = self.committed_transaction(tx_hash, Some(content));
+        // Transactions spilled from the node cache are stored outside of the pool
+        // until the next commit, hence the location is checked instead of the pool.
+        if schema.transactions_locations().contains(tx_hash) {
+            let tx = self.committed_transaction(tx_hash, content);
             Some(TransactionInfo::Committed(tx))
+        } else {
+            let tag = schema.transaction_tag(tx_hash);
+            Some(TransactionInfo::InPool { content, tag })
         }
     }
 
     /// Returns transaction message without proof.
     pub fn transaction_without_proof(&self, tx_hash: &Hash) -> Option<TransactionMessage> {
-        let schema = Schema::new(&self.snapshot);
-        let raw_tx = schema.transactions().get(tx_hash)?;
+        let raw_tx = self.raw_transaction(tx_hash)?;
 
         match (*self.transaction_parser)(raw_tx) {
             Err(e) => {
@@ -683,6 +853,23 @@ impl<'a> BlockchainExplorer<'a> {
         }
     }
 
+    /// Returns the transaction from the local database, or from the archive
+    /// if the transaction has been offloaded.
+    fn raw_transaction(&self, tx_hash: &Hash) -> Option<Signed<RawTransaction>> {
+        let schema = Schema::new(&self.snapshot);
+        if let Some(raw_tx) = schema.transactions().get(tx_hash) {
+            return Some(raw_tx);
+        }
+        if !schema.archived_transactions().contains(tx_hash) {
+            return None;
+        }
+        let archive = self.archive.as_ref()?;
+        archive.fetch(tx_hash).unwrap_or_else(|e| {
+            error!("Unable to fetch archived transaction {:?}: {}", tx_hash, e);
+            None
+        })
+    }
+
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::let_and_return))]
     fn precommits(&self, block: &Block) -> Vec<Signed<Precommit>> {
         let schema = Schema::new(&self.snapshot);
@@ -691,6 +878,40 @@ impl<'a> BlockchainExplorer<'a> {
         precommits
     }
 
+    /// Reads the blocks in the range `from..to` with a single pass over the block hashes,
+    /// optionally together with the hashes of their transactions.
+    fn read_blocks(&self, from: Height, to: Height, with_txs: bool) -> VecDeque<BlockInfo> {
+        let schema = Schema::new(&self.snapshot);
+        let blocks = schema.blocks();
+        (from.0..to.0)
+            .zip(schema.block_hashes_by_height().iter_from(from.0))
+            .map(|(height, block_hash)| {
+                let height = Height(height);
+                let cached = self
+                    .header_cache
+                    .as_ref()
+                    .and_then(|cache| cache.get(height));
+                let header = cached.unwrap_or_else(|| {
+                    let header = blocks
+                        .get(&block_hash)
+                        .unwrap_or_else(|| panic!("Block not found, height: {:?}", height));
+                    if let Some(ref cache) = self.header_cache {
+                        cache.insert(height, header.clone());
+                    }
+                    header
+                });
+                let txs = if header.tx_count() == 0 {
+                    Some(Vec::new())
+                } else if with_txs {
+                    Some(schema.block_transactions(height).iter().collect())
+                } else {
+                    None
+                };
+                BlockInfo::with_header(self, header, txs)
+            })
+            .collect()
+    }
+
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::let_and_return))]
     fn transaction_hashes(&self, block: &Block) -> Vec<Hash> {
         let schema = Schema::new(&self.snapshot);
@@ -703,7 +924,7 @@ impl<'a> BlockchainExplorer<'a> {
     fn committed_transaction(
         &self,
         tx_hash: &Hash,
-        maybe_content: Option<TransactionMessage>,
+        content: TransactionMessage,
     ) -> CommittedTransaction {
         let schema = Schema::new(&self.snapshot);
 
@@ -716,24 +937,31 @@ impl<'a> BlockchainExplorer<'a> {
             .block_transactions(location.block_height())
             .get_proof(location.position_in_block());
 
-        let block_precommits = schema
-            .block_and_precommits(location.block_height())
-            .unwrap();
-        let time = median_precommits_time(&block_precommits.precommits);
+        // The transactions of the blocks without the consensus time are reported
+        // with the zero time.
+        let time = schema
+            .block_time(location.block_height())
+            .unwrap_or_else(|| UNIX_EPOCH.into());
 
         // Unwrap is OK here, because we already know that transaction is committed.
         let status = schema.transaction_results().get(tx_hash).unwrap();
+        let status_proof = {
+            let result_hashes = schema.block_transaction_results(location.block_height());
+            if result_hashes.is_empty() {
+                None
+            } else {
+                Some(result_hashes.get_proof(location.position_in_block()))
+            }
+        };
 
         CommittedTransaction {
-            content: maybe_content.unwrap_or_else(|| {
-                let raw_tx = schema.transactions().get(tx_hash).unwrap();
-                (self.transaction_parser)(raw_tx).unwrap()
-            }),
-
+            content,
             location,
             location_proof,
             status,
+            status_proof,
             time,
+            tag: schema.transaction_tag(tx_hash),
         }
     }
 
@@ -752,27 +980,104 @@ impl<'a> BlockchainExplorer<'a> {
         }
     }
 
+    /// Returns the first block with the consensus time at or after the given time,
+    /// or `None` if all the blocks are older.
+    ///
+    /// The block is found by a binary search over the consensus times, which are
+    /// expected not to decrease along the chain. Blocks without the consensus time,
+    /// i.e., the genesis block, the block following it and the blocks committed before
+    /// the times were recorded, are considered older than any time.
+    pub fn block_at_time(&self, time: DateTime<Utc>) -> Option<BlockInfo> {
+        let schema = Schema::new(&self.snapshot);
+        let (mut low, mut high) = (0, schema.height().next().0);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let is_older = schema
+                .block_time(Height(middle))
+                .map_or(true, |block_time| block_time < time);
+            if is_older {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        self.block(Height(low))
+    }
+
     /// Returns block together with its transactions for the specified height, or `None`
-    /// if there is no such block.
+    /// if there is no such block. Transactions whose contents are pruned or unavailable
+    /// in the archive are skipped.
     pub fn block_with_txs(&self, height: Height) -> Option<BlockWithTransactions> {
         let schema = Schema::new(&self.snapshot);
         let txs_table = schema.block_transactions(height);
-        let block_proof = schema.block_and_precommits(height);
+        let header = self.block_header(height)?;
 
-        block_proof.map(|proof| BlockWithTransactions {
-            header: proof.block,
-            precommits: proof.precommits,
+        Some(BlockWithTransactions {
+            precommits: self.precommits(&header),
+            header,
             transactions: txs_table
                 .iter()
-                .map(|tx_hash| self.committed_transaction(&tx_hash, None))
+                .filter_map(|tx_hash| {
+                    let content = self.transaction_without_proof(&tx_hash)?;
+                    Some(self.committed_transaction(&tx_hash, content))
+                })
                 .collect(),
         })
     }
 
+    /// Returns the header of the block at the specified height together with a page
+    /// of the transaction hashes in the block, or `None` if there is no such block.
+    ///
+    /// The page can be checked against the `tx_hash` and `tx_count` fields of the header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    pub fn block_transactions_page(
+        &self,
+        height: Height,
+        offset: u64,
+        limit: u64,
+    ) -> Option<(Block, ListPage<Hash>)> {
+        let header = self.block_header(height)?;
+        let schema = Schema::new(&self.snapshot);
+        let page = ListPage::new(&schema.block_transactions(height), offset, limit);
+        Some((header, page))
+    }
+
+    /// Returns the header of the block at the specified height together with a page
+    /// of the events emitted by the transactions in the block, or `None` if there is
+    /// no such block.
+    ///
+    /// The page can be checked against the [`events_hash`] of the header; for the blocks
+    /// without events, the page proves that the list of events is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    ///
+    /// [`events_hash`]: ../blockchain/struct.Block.html#method.events_hash
+    pub fn block_events_page(
+        &self,
+        height: Height,
+        offset: u64,
+        limit: u64,
+    ) -> Option<(Block, ListPage<TransactionEvent>)> {
+        let header = self.block_header(height)?;
+        let schema = Schema::new(&self.snapshot);
+        let page = ListPage::new(&schema.block_events(height), offset, limit);
+        Some((header, page))
+    }
+
     /// Iterates over blocks in the blockchain.
+    ///
+    /// The iterator reads the blocks from the storage in batches of up to
+    /// [`BLOCKS_READ_AHEAD`] blocks in the direction of iteration, which is considerably
+    /// faster than reading the blocks one by one with [`block`].
+    ///
+    /// [`BLOCKS_READ_AHEAD`]: constant.BLOCKS_READ_AHEAD.html
+    /// [`block`]: #method.block
     pub fn blocks<R: RangeBounds<Height>>(&self, heights: R) -> Blocks {
-        use std::cmp::max;
-
         let schema = Schema::new(&self.snapshot);
         let max_height = schema.height();
 
@@ -784,16 +1089,51 @@ impl<'a> BlockchainExplorer<'a> {
         Blocks {
             explorer: self,
             ptr,
-            back: max(ptr, end_height(heights.end_bound(), max_height)),
+            back: cmp::max(ptr, end_height(heights.end_bound(), max_height)),
+            front_buffer: VecDeque::new(),
+            back_buffer: VecDeque::new(),
+            read_ahead: INITIAL_READ_AHEAD,
+            with_txs: false,
         }
     }
 }
 
 /// Iterator over blocks in the blockchain.
+///
+/// The blocks are read ahead in batches; `ptr..back` is the range of heights
+/// which have not been read yet.
 pub struct Blocks<'a> {
     explorer: &'a BlockchainExplorer<'a>,
     ptr: Height,
     back: Height,
+    front_buffer: VecDeque<BlockInfo<'a>>,
+    back_buffer: VecDeque<BlockInfo<'a>>,
+    read_ahead: u64,
+    with_txs: bool,
+}
+
+impl<'a> Blocks<'a> {
+    /// Makes the iterator read the hashes of the transactions in the blocks
+    /// together with the blocks, which speeds up the iteration if the transactions
+    /// of most blocks are accessed.
+    pub fn with_transaction_hashes(mut self) -> Self {
+        self.with_txs = true;
+        self
+    }
+
+    fn next_batch_size(&mut self) -> u64 {
+        let size = self.read_ahead;
+        self.read_ahead = cmp::min(size * 2, BLOCKS_READ_AHEAD);
+        size
+    }
+
+    fn buffered(&self) -> usize {
+        self.front_buffer.len() + self.back_buffer.len()
+    }
+
+    fn unread(&self) -> usize {
+        (self.back.0 - self.ptr.0) as usize
+    }
 }
 
 impl<'a> fmt::Debug for Blocks<'a> {
@@ -802,6 +1142,7 @@ impl<'a> fmt::Debug for Blocks<'a> {
             .debug_struct("Blocks")
             .field("ptr", &self.ptr)
             .field("back", &self.back)
+            .field("buffered", &self.buffered())
             .finish()
     }
 }
@@ -810,45 +1151,65 @@ impl<'a> Iterator for Blocks<'a> {
     type Item = BlockInfo<'a>;
 
     fn next(&mut self) -> Option<BlockInfo<'a>> {
-        if self.ptr == self.back {
-            return None;
+        if self.front_buffer.is_empty() && self.ptr < self.back {
+            let to = Height(cmp::min(self.ptr.0 + self.next_batch_size(), self.back.0));
+            self.front_buffer = self.explorer.read_blocks(self.ptr, to, self.with_txs);
+            self.ptr = to;
         }
 
-        let block = BlockInfo::new(self.explorer, self.ptr);
-        self.ptr = self.ptr.next();
-        Some(block)
+        self.front_buffer
+            .pop_front()
+            .or_else(|| self.back_buffer.pop_front())
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let exact = (self.back.0 - self.ptr.0) as usize;
+        let exact = self.unread() + self.buffered();
         (exact, Some(exact))
     }
 
     fn count(self) -> usize {
-        (self.back.0 - self.ptr.0) as usize
+        self.unread() + self.buffered()
     }
 
-    fn nth(&mut self, n: usize) -> Option<BlockInfo<'a>> {
-        if self.ptr.0 + n as u64 >= self.back.0 {
-            self.ptr = self.back;
-            None
-        } else {
+    fn nth(&mut self, mut n: usize) -> Option<BlockInfo<'a>> {
+        if n < self.front_buffer.len() {
+            self.front_buffer.drain(..n);
+            return self.next();
+        }
+        n -= self.front_buffer.len();
+        self.front_buffer.clear();
+
+        // Skipped blocks in the unread range are not read at all.
+        let unread = self.unread();
+        if n < unread {
             self.ptr = Height(self.ptr.0 + n as u64);
-            let block = BlockInfo::new(self.explorer, self.ptr);
-            self.ptr = self.ptr.next();
-            Some(block)
+            return self.next();
+        }
+        n -= unread;
+        self.ptr = self.back;
+
+        if n < self.back_buffer.len() {
+            self.back_buffer.drain(..n);
+            self.next()
+        } else {
+            self.back_buffer.clear();
+            None
         }
     }
 }
 
 impl<'a> DoubleEndedIterator for Blocks<'a> {
     fn next_back(&mut self) -> Option<BlockInfo<'a>> {
-        if self.ptr == self.back {
-            return None;
+        if self.back_buffer.is_empty() && self.ptr < self.back {
+            let batch_size = self.next_batch_size();
+            let from = Height(cmp::max(self.back.0.saturating_sub(batch_size), self.ptr.0));
+            self.back_buffer = self.explorer.read_blocks(from, self.back, self.with_txs);
+            self.back = from;
         }
 
-        self.back = self.back.previous();
-        Some(BlockInfo::new(self.explorer, self.back))
+        self.back_buffer
+            .pop_back()
+            .or_else(|| self.front_buffer.pop_back())
     }
 }
 
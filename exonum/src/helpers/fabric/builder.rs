@@ -12,11 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use exonum_merkledb::{Database, RocksDB, TemporaryDB};
+use futures::sync::mpsc;
+use hex::FromHex;
+
 use std::{
     collections::HashMap,
+    env,
     ffi::OsString,
     fmt,
+    fs::File,
+    io::BufReader,
     panic::{self, PanicInfo},
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
@@ -26,13 +34,22 @@ use super::{
     info::Info,
     internal::{CollectedCommand, Command, Feedback},
     keys,
-    maintenance::Maintenance,
+    maintenance::{
+        AuditChain, CreateCheckpoint, ExportChain, ExportPeers, ForkChain, ImportChain,
+        ImportPeers, IsolatedWorker, Maintenance, ReplayTransaction, RollbackChain, RotateSecrets,
+        CHAIN_FILE_PATH, CHAIN_ID_SUFFIX, FORK_DATABASE_PATH, FORK_HEIGHT, ISOLATED_SERVICE_NAME,
+        REPLAY_HEIGHT, REPLAY_TX_HASH, ROLLBACK_BLOCKS, ROLLBACK_DATABASE_PATH,
+        SERVICE_KEY_PASS_METHOD,
+    },
     password::{PassInputMethod, SecretKeyType},
+    test_vectors::GenerateTestVectors,
     CommandName, Context, ServiceFactory,
 };
 
-use crate::blockchain::Service;
-use crate::node::{ExternalMessage, Node};
+use crate::blockchain::{self, Blockchain, GenesisState, IsolatedService, Schema, Service};
+use crate::crypto::{self, Hash};
+use crate::helpers::Height;
+use crate::node::{ApiSender, ExternalMessage, Node, NodeConfig};
 
 /// `NodeBuilder` is a high level object,
 /// usable for fast prototyping and creating app from services list.
@@ -69,8 +86,17 @@ impl NodeBuilder {
         T: Into<OsString> + Clone,
     {
         let feedback = ClapBackend::execute_cmd_string(&self.commands, cmd_line);
-        if let Feedback::RunNode(ref ctx) = feedback {
-            self.node_from_run_context(ctx);
+        match feedback {
+            Feedback::RunNode(ref ctx) => {
+                self.node_from_run_context(ctx);
+            }
+            Feedback::ImportChain(ref ctx) => self.import_chain(ctx),
+            Feedback::ForkChain(ref ctx) => self.fork_chain(ctx),
+            Feedback::RollbackChain(ref ctx) => self.rollback_chain(ctx),
+            Feedback::AuditChain(ref ctx) => self.audit_chain(ctx),
+            Feedback::ReplayTransaction(ref ctx) => self.replay_transaction(ctx),
+            Feedback::IsolatedWorker(ref ctx) => self.run_isolated_worker(ctx),
+            Feedback::None => {}
         }
         feedback != Feedback::None
     }
@@ -82,7 +108,31 @@ impl NodeBuilder {
                 let node = self.node_from_run_context(ctx);
                 Some(node)
             }
-            _ => None,
+            Feedback::ImportChain(ref ctx) => {
+                self.import_chain(ctx);
+                None
+            }
+            Feedback::ForkChain(ref ctx) => {
+                self.fork_chain(ctx);
+                None
+            }
+            Feedback::RollbackChain(ref ctx) => {
+                self.rollback_chain(ctx);
+                None
+            }
+            Feedback::AuditChain(ref ctx) => {
+                self.audit_chain(ctx);
+                None
+            }
+            Feedback::ReplayTransaction(ref ctx) => {
+                self.replay_transaction(ctx);
+                None
+            }
+            Feedback::IsolatedWorker(ref ctx) => {
+                self.run_isolated_worker(ctx);
+                None
+            }
+            Feedback::None => None,
         }
     }
 
@@ -137,6 +187,18 @@ impl NodeBuilder {
             Box::new(GenerateCommonConfig),
             Box::new(Finalize),
             Box::new(Maintenance),
+            Box::new(ExportChain),
+            Box::new(CreateCheckpoint),
+            Box::new(ImportChain),
+            Box::new(ExportPeers),
+            Box::new(ImportPeers),
+            Box::new(RotateSecrets),
+            Box::new(ForkChain),
+            Box::new(RollbackChain),
+            Box::new(AuditChain),
+            Box::new(ReplayTransaction),
+            Box::new(IsolatedWorker),
+            Box::new(GenerateTestVectors),
         ]
         .into_iter()
         .map(|c| (c.name(), CollectedCommand::new(c)))
@@ -156,6 +218,8 @@ impl NodeBuilder {
             .into_iter()
             .map(|mut factory| factory.make_service(ctx))
             .collect();
+        let services =
+            Self::isolate_services(services, &config.isolated_services, &config_file_path);
 
         let config = {
             let run_config = ctx.get(keys::RUN_CONFIG).unwrap();
@@ -172,7 +236,255 @@ impl NodeBuilder {
                 service_passphrase.as_bytes(),
             )
         };
-        Node::new(db, services, config, Some(config_file_path))
+        Node::try_new(db, services, config, Some(config_file_path))
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn import_chain(self, ctx: &Context) {
+        let config = ctx
+            .get(keys::NODE_CONFIG)
+            .expect("could not find node_config");
+        let path = ctx
+            .arg::<String>(CHAIN_FILE_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", CHAIN_FILE_PATH));
+        let file = File::open(&path).expect("Can't open chain file");
+
+        let db = Run::db_helper(ctx, &config.database);
+        let (mut blockchain, genesis_state) = self.offline_blockchain(ctx, db, &config);
+
+        let height = blockchain::import_chain(&mut blockchain, genesis_state, BufReader::new(file))
+            .unwrap_or_else(|e| panic!("Can't import the chain: {}", e));
+        info!("Imported blocks up to height {} from {}", height, path);
+    }
+
+    fn fork_chain(self, ctx: &Context) {
+        let config = ctx
+            .get(keys::NODE_CONFIG)
+            .expect("could not find node_config");
+        let height = ctx
+            .arg::<u64>(FORK_HEIGHT)
+            .unwrap_or_else(|e| panic!("Invalid {}: {}", FORK_HEIGHT, e));
+        let suffix = ctx
+            .arg::<String>(CHAIN_ID_SUFFIX)
+            .unwrap_or_else(|_| format!("fork-{}", height));
+        let path = ctx
+            .arg::<String>(FORK_DATABASE_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", FORK_DATABASE_PATH));
+
+        let source = Run::db_helper(ctx, &config.database);
+        let db = RocksDB::open(Path::new(&path), &config.database)
+            .expect("Can't create database for the forked chain");
+        let (mut blockchain, genesis_state) = self.offline_blockchain(ctx, Box::new(db), &config);
+
+        let chain_id = blockchain::fork_chain(
+            source.snapshot().as_ref(),
+            Height(height),
+            &suffix,
+            &mut blockchain,
+            genesis_state,
+        )
+        .unwrap_or_else(|e| panic!("Can't fork the chain: {}", e));
+        info!(
+            "Forked the chain at height {} into {}, the chain identifier is {}",
+            height, path, chain_id
+        );
+    }
+
+    fn rollback_chain(self, ctx: &Context) {
+        let config = ctx
+            .get(keys::NODE_CONFIG)
+            .expect("could not find node_config");
+        let blocks = ctx
+            .arg::<u64>(ROLLBACK_BLOCKS)
+            .unwrap_or_else(|e| panic!("Invalid {}: {}", ROLLBACK_BLOCKS, e));
+        let path = ctx
+            .arg::<String>(ROLLBACK_DATABASE_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", ROLLBACK_DATABASE_PATH));
+
+        let source = Run::db_helper(ctx, &config.database);
+        let snapshot = source.snapshot();
+        let last_height = Schema::new(&snapshot).height();
+        let height = last_height
+            .0
+            .checked_sub(blocks)
+            .map(Height)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Cannot roll back {} blocks, the last block is at height {}",
+                    blocks, last_height
+                )
+            });
+
+        let db = RocksDB::open(Path::new(&path), &config.database)
+            .expect("Can't create database for the rolled back chain");
+        let (mut blockchain, genesis_state) = self.offline_blockchain(ctx, Box::new(db), &config);
+        blockchain::rollback_chain(snapshot.as_ref(), height, &mut blockchain, genesis_state)
+            .unwrap_or_else(|e| panic!("Can't roll back the chain: {}", e));
+        info!(
+            "Rolled back the chain to height {} into {}, replace the node database with it",
+            height, path
+        );
+    }
+
+    fn audit_chain(self, ctx: &Context) {
+        let config = ctx
+            .get(keys::NODE_CONFIG)
+            .expect("could not find node_config");
+        let source = Run::db_helper(ctx, &config.database);
+        let (mut blockchain, genesis_state) =
+            self.offline_blockchain(ctx, Box::new(TemporaryDB::new()), &config);
+
+        let report =
+            blockchain::audit_chain(source.snapshot().as_ref(), &mut blockchain, genesis_state)
+                .unwrap_or_else(|e| panic!("Can't audit the chain: {}", e));
+        if let Some(divergence) = report.divergence {
+            panic!(
+                "The block at height {} differs after re-execution: \
+                 stored tx_hash {:?}, state_hash {:?}; executed tx_hash {:?}, state_hash {:?}",
+                divergence.height,
+                divergence.stored.tx_hash(),
+                divergence.stored.state_hash(),
+                divergence.executed.tx_hash(),
+                divergence.executed.state_hash()
+            );
+        }
+        info!(
+            "Re-executed blocks up to height {}, no divergence found",
+            report.verified_height.map_or(0, |height| height.0)
+        );
+    }
+
+    fn replay_transaction(self, ctx: &Context) {
+        let config = ctx
+            .get(keys::NODE_CONFIG)
+            .expect("could not find node_config");
+        let tx_hash = ctx
+            .arg::<String>(REPLAY_TX_HASH)
+            .map_err(failure::Error::from)
+            .and_then(|hash| Ok(Hash::from_hex(hash)?))
+            .unwrap_or_else(|e| panic!("Invalid {}: {}", REPLAY_TX_HASH, e));
+        let height = ctx.arg::<u64>(REPLAY_HEIGHT).ok().map(Height);
+
+        let source = Run::db_helper(ctx, &config.database);
+        let (mut blockchain, genesis_state) =
+            self.offline_blockchain(ctx, Box::new(TemporaryDB::new()), &config);
+        let replay = blockchain::replay_transaction(
+            source.snapshot().as_ref(),
+            &tx_hash,
+            height,
+            &mut blockchain,
+            genesis_state,
+        )
+        .unwrap_or_else(|e| panic!("Can't replay the transaction: {}", e));
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&replay).expect("Can't serialize the replay")
+        );
+    }
+
+    fn run_isolated_worker(self, ctx: &Context) {
+        let name = ctx
+            .arg::<String>(ISOLATED_SERVICE_NAME)
+            .unwrap_or_else(|_| panic!("{} not found.", ISOLATED_SERVICE_NAME));
+        let service = self
+            .service_factories
+            .into_iter()
+            .map(|mut factory| factory.make_service(ctx))
+            .find(|service| service.service_name() == name)
+            .unwrap_or_else(|| panic!("Unknown isolated service <{}>", name));
+
+        blockchain::run_worker(service)
+            .unwrap_or_else(|e| panic!("Worker of isolated service <{}> failed: {}", name, e));
+    }
+
+    /// Wraps the services listed in the node configuration into `IsolatedService`s
+    /// executed by the workers started from the current executable.
+    fn isolate_services(
+        services: Vec<Box<dyn Service>>,
+        isolated: &[String],
+        config_file_path: &str,
+    ) -> Vec<Box<dyn Service>> {
+        if isolated.is_empty() {
+            return services;
+        }
+        for name in isolated {
+            if !services
+                .iter()
+                .any(|service| service.service_name() == name)
+            {
+                panic!("Unknown isolated service <{}>", name);
+            }
+        }
+
+        let program = env::current_exe().expect("Can't get the path to the node executable");
+        services
+            .into_iter()
+            .map(|service| {
+                let name = service.service_name().to_owned();
+                if !isolated.contains(&name) {
+                    return service;
+                }
+                info!("Service <{}> is executed in a separate process", name);
+                let args = vec![
+                    IsolatedWorker.name().to_owned(),
+                    "--node-config".to_owned(),
+                    config_file_path.to_owned(),
+                    "--service-name".to_owned(),
+                    name,
+                ];
+                Box::new(IsolatedService::new(service, program.clone(), args)) as Box<dyn Service>
+            })
+            .collect()
+    }
+
+    /// Creates a blockchain with the services of the node, which is used to rebuild
+    /// the chain while the node is stopped.
+    fn offline_blockchain(
+        self,
+        ctx: &Context,
+        db: Box<dyn Database>,
+        config: &NodeConfig<PathBuf>,
+    ) -> (Blockchain, GenesisState) {
+        let services: Vec<Box<dyn Service>> = self
+            .service_factories
+            .into_iter()
+            .map(|mut factory| factory.make_service(ctx))
+            .collect();
+        let genesis_state = match config.genesis_state {
+            Some(ref path) => GenesisState::load(path).expect("Unable to load genesis state"),
+            None => GenesisState::default(),
+        };
+        // Services determine whether the node is a validator by its service key,
+        // so the chain is rebuilt with the keys of the node.
+        let (service_public_key, service_secret_key) = {
+            let config_file_path = ctx
+                .get(keys::NODE_CONFIG_PATH)
+                .expect("Could not find node_config_path");
+            let key_path = if config.service_secret_key.is_absolute() {
+                config.service_secret_key.clone()
+            } else {
+                Path::new(&config_file_path)
+                    .parent()
+                    .unwrap()
+                    .join(&config.service_secret_key)
+            };
+            let pass_method = ctx
+                .arg::<String>(SERVICE_KEY_PASS_METHOD)
+                .unwrap_or_default();
+            let passphrase = PassInputMethod::from_str(&pass_method)
+                .expect("Incorrect passphrase input method for service key.")
+                .get_passphrase(SecretKeyType::Service, true);
+            crypto::read_keys_from_file(&key_path, passphrase.as_bytes())
+                .expect("Could not read service_secret_key from file")
+        };
+        let blockchain = Blockchain::new(
+            db,
+            services,
+            service_public_key,
+            service_secret_key,
+            ApiSender::new(mpsc::channel(0).0),
+        );
+        (blockchain, genesis_state)
     }
 }
 
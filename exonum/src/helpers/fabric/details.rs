@@ -184,6 +184,13 @@ impl Command for Run {
             config.api.private_api_address = Some(private_api_address);
         }
 
+        if config.resources.database_path.is_none() {
+            config.resources.database_path = new_context
+                .arg::<String>(DATABASE_PATH)
+                .ok()
+                .map(PathBuf::from);
+        }
+
         new_context.set(keys::NODE_CONFIG, config);
 
         let run_config = {
@@ -360,6 +367,14 @@ impl Command for GenerateCommonConfig {
                 "validators-count",
                 false,
             ),
+            Argument::new_named(
+                "NETWORK_ID",
+                false,
+                "Identifier of the network the messages are signed for, 0 by default.",
+                None,
+                "network-id",
+                false,
+            ),
         ]
     }
 
@@ -384,6 +399,7 @@ impl Command for GenerateCommonConfig {
         let validators_count = context
             .arg::<u16>("VALIDATORS_COUNT")
             .expect("VALIDATORS_COUNT not found");
+        let network_id = context.arg::<u32>("NETWORK_ID").unwrap_or_default();
 
         context.set(keys::SERVICES_CONFIG, AbstractConfig::default());
         let new_context = exts(context);
@@ -398,6 +414,7 @@ impl Command for GenerateCommonConfig {
         let template = CommonConfigTemplate {
             services_config,
             general_config,
+            network_id,
             ..CommonConfigTemplate::default()
         };
 
@@ -599,10 +616,7 @@ impl Command for GenerateNodeConfig {
             create_secret_key_file(&service_secret_key_path, passphrase.as_bytes())
         };
 
-        let validator_keys = ValidatorKeys {
-            consensus_key: consensus_public_key,
-            service_key: service_public_key,
-        };
+        let validator_keys = ValidatorKeys::new(consensus_public_key, service_public_key);
         let node_pub_config = NodePublicConfig {
             address: addresses.0.clone(),
             validator_keys,
@@ -646,6 +660,7 @@ impl Finalize {
             template.consensus_config,
             configs.iter().map(|c| c.validator_keys),
         )
+        .with_network_id(template.network_id)
     }
 
     fn reduce_configs(
@@ -821,6 +836,16 @@ impl Command for Finalize {
                 database: Default::default(),
                 connect_list,
                 thread_pool_size: Default::default(),
+                genesis_state: None,
+                retention: Default::default(),
+                observer_address: None,
+                integrity_check_depth: None,
+                clock: Default::default(),
+                isolated_services: Vec::new(),
+                record_patch_hashes: false,
+                alerts: Vec::new(),
+                resources: Default::default(),
+                consensus_archive: None,
             }
         };
 
@@ -23,6 +23,18 @@ use super::{Argument, CommandExtension, CommandName, Context};
 pub enum Feedback {
     /// Run node with current context.
     RunNode(Context),
+    /// Import the chain into the node database with current context.
+    ImportChain(Context),
+    /// Fork the chain stored in the node database with current context.
+    ForkChain(Context),
+    /// Roll back the chain stored in the node database with current context.
+    RollbackChain(Context),
+    /// Audit the chain stored in the node database with current context.
+    AuditChain(Context),
+    /// Replay a committed transaction with current context.
+    ReplayTransaction(Context),
+    /// Run an isolated service in the worker process with current context.
+    IsolatedWorker(Context),
     /// Do nothing
     None,
 }
@@ -14,23 +14,67 @@
 
 //! This module implements node maintenance actions.
 
-use std::{collections::HashMap, path::Path};
+use failure::Error;
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
 
 use super::{
     internal::{CollectedCommand, Command, Feedback},
+    keys,
+    password::{PassInputMethod, SecretKeyType},
     Argument, CommandName, Context,
 };
-use crate::blockchain::Schema;
+use crate::blockchain::{self, Schema, ValidatorKeys};
+use crate::crypto::{self, read_keys_from_file, write_keys_file, PublicKey};
 use crate::helpers::config::ConfigFile;
+use crate::messages::{Connect, Signed};
 use crate::node::NodeConfig;
-use exonum_merkledb::{Database, DbOptions, RocksDB};
+use exonum_merkledb::{Database, DbOptions, Fork, RocksDB, Snapshot};
 
+// Context entry for the path to the database checkpoint.
+const CHECKPOINT_PATH: &str = "CHECKPOINT_PATH";
 // Context entry for the path to the node config.
 const NODE_CONFIG_PATH: &str = "NODE_CONFIG_PATH";
 // Context entry for the path to the database.
 const DATABASE_PATH: &str = "DATABASE_PATH";
 // Context entry for the type of action to be performed.
 const MAINTENANCE_ACTION_PATH: &str = "MAINTENANCE_ACTION_PATH";
+// Context entry for the path to the exported chain.
+pub(super) const CHAIN_FILE_PATH: &str = "CHAIN_FILE_PATH";
+// Context entry for the path to the exported peers cache.
+const PEERS_FILE_PATH: &str = "PEERS_FILE_PATH";
+// Context entry for the path to the database of the forked chain.
+pub(super) const FORK_DATABASE_PATH: &str = "FORK_DATABASE_PATH";
+// Context entry for the height of the last block preserved by the fork.
+pub(super) const FORK_HEIGHT: &str = "FORK_HEIGHT";
+// Context entry for the suffix of the chain identifier assigned by the fork.
+pub(super) const CHAIN_ID_SUFFIX: &str = "CHAIN_ID_SUFFIX";
+// Context entry for the path to the database of the rolled back chain.
+pub(super) const ROLLBACK_DATABASE_PATH: &str = "ROLLBACK_DATABASE_PATH";
+// Context entry for the number of the latest blocks dropped by the rollback.
+pub(super) const ROLLBACK_BLOCKS: &str = "ROLLBACK_BLOCKS";
+// Context entry for the hash of the replayed transaction.
+pub(super) const REPLAY_TX_HASH: &str = "REPLAY_TX_HASH";
+// Context entry for the height of the block the transaction is replayed in.
+pub(super) const REPLAY_HEIGHT: &str = "REPLAY_HEIGHT";
+// Context entry for the name of the service executed by the worker.
+pub(super) const ISOLATED_SERVICE_NAME: &str = "ISOLATED_SERVICE_NAME";
+// Context entries for the current passphrase input methods of the secret keys.
+const CONSENSUS_KEY_PASS_METHOD: &str = "CONSENSUS_KEY_PASS_METHOD";
+pub(super) const SERVICE_KEY_PASS_METHOD: &str = "SERVICE_KEY_PASS_METHOD";
+// Context entries for the new passphrase input methods of the secret keys.
+const NEW_CONSENSUS_KEY_PASS_METHOD: &str = "NEW_CONSENSUS_KEY_PASS_METHOD";
+const NEW_SERVICE_KEY_PASS_METHOD: &str = "NEW_SERVICE_KEY_PASS_METHOD";
+// Context entry for the path to the file with the next consensus key.
+const NEXT_CONSENSUS_KEY_PATH: &str = "NEXT_CONSENSUS_KEY_PATH";
+// Context entry for the path to the node config using the next consensus key.
+const NEXT_NODE_CONFIG_PATH: &str = "NEXT_NODE_CONFIG_PATH";
 
 /// Maintenance command. Supported actions:
 ///
@@ -123,3 +167,936 @@ impl Command for Maintenance {
         Feedback::None
     }
 }
+
+fn chain_args(file_help: &'static str) -> Vec<Argument> {
+    file_args(CHAIN_FILE_PATH, file_help)
+}
+
+fn file_args(file_key: &'static str, file_help: &'static str) -> Vec<Argument> {
+    vec![
+        Argument::new_named(
+            NODE_CONFIG_PATH,
+            true,
+            "Path to node configuration file.",
+            "c",
+            "node-config",
+            false,
+        ),
+        Argument::new_named(
+            DATABASE_PATH,
+            true,
+            "Use database with the given path.",
+            "d",
+            "db-path",
+            false,
+        ),
+        Argument::new_named(file_key, true, file_help, "f", "file", false),
+    ]
+}
+
+/// Argument with the passphrase entry method for the service key, which is required by
+/// the commands re-executing the chain with the services of the node.
+fn service_key_pass_arg() -> Argument {
+    Argument::new_named(
+        SERVICE_KEY_PASS_METHOD,
+        false,
+        "Passphrase entry method for service key.\n\
+         Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD (default: stdin)\n\
+         If ENV_VAR_NAME is not specified $EXONUM_SERVICE_PASS is used",
+        None,
+        "service-key-pass",
+        false,
+    )
+}
+
+fn chain_file_path(ctx: &Context) -> String {
+    ctx.arg::<String>(CHAIN_FILE_PATH)
+        .unwrap_or_else(|_| panic!("{} not found.", CHAIN_FILE_PATH))
+}
+
+/// Exports the blockchain into a file in the chain interchange format.
+/// See [`export_chain`] for details.
+///
+/// [`export_chain`]: ../../blockchain/fn.export_chain.html
+#[derive(Debug)]
+pub struct ExportChain;
+
+impl Command for ExportChain {
+    fn args(&self) -> Vec<Argument> {
+        chain_args("Path to the file the chain is exported to.")
+    }
+
+    fn name(&self) -> CommandName {
+        "export-chain"
+    }
+
+    fn about(&self) -> &str {
+        "Exports blocks, transactions and configurations into a file."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        context: Context,
+        _: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config = Maintenance::node_config(&context);
+        let db = Maintenance::database(&context, &config.database);
+        let path = chain_file_path(&context);
+        let file = File::create(&path).expect("Can't create chain file");
+
+        let height = blockchain::export_chain(db.snapshot().as_ref(), BufWriter::new(file))
+            .unwrap_or_else(|e| panic!("Can't export the chain: {}", e));
+        info!("Exported blocks up to height {} to {}", height, path);
+        Feedback::None
+    }
+}
+
+/// Writes a read-only checkpoint of the node database, which can be opened by
+/// external analytics engines.
+///
+/// The command opens the database, so it can only be used while the node is stopped;
+/// checkpoints of a running node are created with the `v1/checkpoints` endpoint
+/// of the private API.
+#[derive(Debug)]
+pub struct CreateCheckpoint;
+
+impl Command for CreateCheckpoint {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                NODE_CONFIG_PATH,
+                true,
+                "Path to node configuration file.",
+                "c",
+                "node-config",
+                false,
+            ),
+            Argument::new_named(
+                DATABASE_PATH,
+                true,
+                "Use database with the given path.",
+                "d",
+                "db-path",
+                false,
+            ),
+            Argument::new_named(
+                CHECKPOINT_PATH,
+                true,
+                "Path to the checkpoint directory, which should not exist.",
+                "o",
+                "output",
+                false,
+            ),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "create-checkpoint"
+    }
+
+    fn about(&self) -> &str {
+        "Writes a read-only checkpoint of the database for external analytics."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        context: Context,
+        _: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config = Maintenance::node_config(&context);
+        let db = Maintenance::database(&context, &config.database);
+        let path = context
+            .arg::<String>(CHECKPOINT_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", CHECKPOINT_PATH));
+
+        let height = Schema::new(&db.snapshot()).height();
+        db.create_checkpoint(Path::new(&path))
+            .unwrap_or_else(|e| panic!("Can't create the checkpoint: {}", e));
+        info!(
+            "Created checkpoint with blocks up to height {} at {}",
+            height, path
+        );
+        Feedback::None
+    }
+}
+
+/// Peer saved in the peers cache of the node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedPeer {
+    /// Consensus key of the peer.
+    public_key: PublicKey,
+    /// Address of the peer.
+    address: String,
+    /// `Connect` message signed by the peer.
+    connect: Signed<Connect>,
+}
+
+/// Returns the peers saved in the peers cache.
+fn export_peers(snapshot: &dyn Snapshot) -> Vec<SavedPeer> {
+    Schema::new(snapshot)
+        .peers_cache()
+        .values()
+        .map(|connect| SavedPeer {
+            public_key: connect.author(),
+            address: connect.pub_addr().to_owned(),
+            connect,
+        })
+        .collect()
+}
+
+/// Saves the peers into the peers cache, skipping the node itself and the peers
+/// already cached with a newer `Connect` message. Returns the number of saved peers.
+fn import_peers(
+    fork: &Fork,
+    peers: Vec<SavedPeer>,
+    own_key: &PublicKey,
+) -> Result<usize, failure::Error> {
+    let mut peers_cache = Schema::new(fork).peers_cache();
+    let mut imported = 0;
+    for peer in peers {
+        let author = peer.connect.author();
+        ensure!(
+            author == peer.public_key,
+            "Connect message of peer {} is signed by {}",
+            peer.public_key,
+            author
+        );
+        ensure!(
+            peer.address == peer.connect.pub_addr(),
+            "Address {} of peer {} does not match its Connect message",
+            peer.address,
+            peer.public_key
+        );
+        if author == *own_key {
+            continue;
+        }
+        if let Some(cached) = peers_cache.get(&author) {
+            if cached.time() >= peer.connect.time() {
+                continue;
+            }
+        }
+        peers_cache.put(&author, peer.connect);
+        imported += 1;
+    }
+    Ok(imported)
+}
+
+/// Exports the `Connect` messages of the peers cached by the node into a file,
+/// so that they can be imported into another node of the same network.
+#[derive(Debug)]
+pub struct ExportPeers;
+
+impl Command for ExportPeers {
+    fn args(&self) -> Vec<Argument> {
+        file_args(
+            PEERS_FILE_PATH,
+            "Path to the file the peers are exported to.",
+        )
+    }
+
+    fn name(&self) -> CommandName {
+        "export-peers"
+    }
+
+    fn about(&self) -> &str {
+        "Exports the addresses and Connect messages of the known peers into a file."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        context: Context,
+        _: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config = Maintenance::node_config(&context);
+        let db = Maintenance::database(&context, &config.database);
+        let path = context
+            .arg::<String>(PEERS_FILE_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", PEERS_FILE_PATH));
+        let file = File::create(&path).expect("Can't create peers file");
+
+        let peers = export_peers(db.snapshot().as_ref());
+        serde_json::to_writer_pretty(BufWriter::new(file), &peers)
+            .unwrap_or_else(|e| panic!("Can't export peers: {}", e));
+        info!("Exported {} peers to {}", peers.len(), path);
+        Feedback::None
+    }
+}
+
+/// Imports the peers exported by the [`ExportPeers`] command into the peers cache,
+/// so that the node connects to them on start. The node must be stopped.
+///
+/// [`ExportPeers`]: struct.ExportPeers.html
+#[derive(Debug)]
+pub struct ImportPeers;
+
+impl Command for ImportPeers {
+    fn args(&self) -> Vec<Argument> {
+        file_args(PEERS_FILE_PATH, "Path to the exported peers.")
+    }
+
+    fn name(&self) -> CommandName {
+        "import-peers"
+    }
+
+    fn about(&self) -> &str {
+        "Imports the peers exported by the export-peers command into the peers cache."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        context: Context,
+        _: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config = Maintenance::node_config(&context);
+        let db = Maintenance::database(&context, &config.database);
+        let path = context
+            .arg::<String>(PEERS_FILE_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", PEERS_FILE_PATH));
+        let file = File::open(&path).expect("Can't open peers file");
+        let peers: Vec<SavedPeer> = serde_json::from_reader(BufReader::new(file))
+            .unwrap_or_else(|e| panic!("Can't read peers from {}: {}", path, e));
+
+        let fork = db.fork();
+        let imported = import_peers(&fork, peers, &config.consensus_public_key)
+            .unwrap_or_else(|e| panic!("Can't import peers: {}", e));
+        db.merge_sync(fork.into_patch())
+            .expect("Can't save imported peers");
+        info!("Imported {} peers from {}", imported, path);
+        Feedback::None
+    }
+}
+
+/// Rebuilds the blockchain from a file in the chain interchange format.
+/// See [`import_chain`] for details.
+///
+/// The import is performed by the `NodeBuilder`, since it requires the services
+/// of the node.
+///
+/// [`import_chain`]: ../../blockchain/fn.import_chain.html
+#[derive(Debug)]
+pub struct ImportChain;
+
+impl Command for ImportChain {
+    fn args(&self) -> Vec<Argument> {
+        let mut args = chain_args("Path to the exported chain.");
+        args.push(service_key_pass_arg());
+        args
+    }
+
+    fn name(&self) -> CommandName {
+        "import-chain"
+    }
+
+    fn about(&self) -> &str {
+        "Imports the chain exported by the export-chain command into an empty database."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        mut context: Context,
+        exts: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config_path = context
+            .arg::<String>(NODE_CONFIG_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", NODE_CONFIG_PATH));
+        let config: NodeConfig<PathBuf> =
+            ConfigFile::load(&config_path).expect("Can't load node config file");
+
+        context.set(keys::NODE_CONFIG, config);
+        context.set(keys::NODE_CONFIG_PATH, config_path);
+        Feedback::ImportChain(exts(context))
+    }
+}
+
+/// Rebuilds the blockchain up to the given height into a new database and marks
+/// the rebuilt chain as forked. See [`fork_chain`] for details.
+///
+/// The fork is performed by the `NodeBuilder`, since it requires the services
+/// of the node.
+///
+/// [`fork_chain`]: ../../blockchain/fn.fork_chain.html
+#[derive(Debug)]
+pub struct ForkChain;
+
+impl Command for ForkChain {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                NODE_CONFIG_PATH,
+                true,
+                "Path to node configuration file.",
+                "c",
+                "node-config",
+                false,
+            ),
+            Argument::new_named(
+                DATABASE_PATH,
+                true,
+                "Use database with the given path.",
+                "d",
+                "db-path",
+                false,
+            ),
+            Argument::new_named(
+                FORK_DATABASE_PATH,
+                true,
+                "Path to the empty database the forked chain is written to.",
+                "o",
+                "output-db-path",
+                false,
+            ),
+            Argument::new_named(
+                FORK_HEIGHT,
+                true,
+                "Height of the last block preserved by the fork.",
+                None,
+                "height",
+                false,
+            ),
+            Argument::new_named(
+                CHAIN_ID_SUFFIX,
+                false,
+                "Suffix appended to the chain identifier, `fork-<height>` by default. \
+                 Must be the same for all the validators.",
+                None,
+                "suffix",
+                false,
+            ),
+            service_key_pass_arg(),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "fork-chain"
+    }
+
+    fn about(&self) -> &str {
+        "Writes the chain truncated to the given height into a new database \
+         to restart the network from this height."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        mut context: Context,
+        exts: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config_path = context
+            .arg::<String>(NODE_CONFIG_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", NODE_CONFIG_PATH));
+        let config: NodeConfig<PathBuf> =
+            ConfigFile::load(&config_path).expect("Can't load node config file");
+
+        context.set(keys::NODE_CONFIG, config);
+        context.set(keys::NODE_CONFIG_PATH, config_path);
+        Feedback::ForkChain(exts(context))
+    }
+}
+
+/// Rebuilds the blockchain without the given number of the latest blocks into
+/// a new database, which replaces the database of the stopped node afterwards.
+/// See [`rollback_chain`] for details.
+///
+/// The rollback is performed by the `NodeBuilder`, since it requires the services
+/// of the node.
+///
+/// [`rollback_chain`]: ../../blockchain/fn.rollback_chain.html
+#[derive(Debug)]
+pub struct RollbackChain;
+
+impl Command for RollbackChain {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                NODE_CONFIG_PATH,
+                true,
+                "Path to node configuration file.",
+                "c",
+                "node-config",
+                false,
+            ),
+            Argument::new_named(
+                DATABASE_PATH,
+                true,
+                "Use database with the given path.",
+                "d",
+                "db-path",
+                false,
+            ),
+            Argument::new_named(
+                ROLLBACK_DATABASE_PATH,
+                true,
+                "Path to the empty database the rolled back chain is written to.",
+                "o",
+                "output-db-path",
+                false,
+            ),
+            Argument::new_named(
+                ROLLBACK_BLOCKS,
+                true,
+                "Number of the latest blocks to drop.",
+                None,
+                "blocks",
+                false,
+            ),
+            service_key_pass_arg(),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "rollback-chain"
+    }
+
+    fn about(&self) -> &str {
+        "Writes the chain without the given number of the latest blocks \
+         into a new database to recover the node from a mistake or a corrupted chain."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        mut context: Context,
+        exts: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config_path = context
+            .arg::<String>(NODE_CONFIG_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", NODE_CONFIG_PATH));
+        let config: NodeConfig<PathBuf> =
+            ConfigFile::load(&config_path).expect("Can't load node config file");
+
+        context.set(keys::NODE_CONFIG, config);
+        context.set(keys::NODE_CONFIG_PATH, config_path);
+        Feedback::RollbackChain(exts(context))
+    }
+}
+
+/// Re-executes all the blocks stored in the node database and compares them with
+/// the stored ones, reporting the first divergent block. See [`audit_chain`]
+/// for details.
+///
+/// The audit is performed by the `NodeBuilder`, since it requires the services
+/// of the node.
+///
+/// [`audit_chain`]: ../../blockchain/fn.audit_chain.html
+#[derive(Debug)]
+pub struct AuditChain;
+
+impl Command for AuditChain {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                NODE_CONFIG_PATH,
+                true,
+                "Path to node configuration file.",
+                "c",
+                "node-config",
+                false,
+            ),
+            Argument::new_named(
+                DATABASE_PATH,
+                true,
+                "Use database with the given path.",
+                "d",
+                "db-path",
+                false,
+            ),
+            service_key_pass_arg(),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "audit-chain"
+    }
+
+    fn about(&self) -> &str {
+        "Re-executes the stored blocks and verifies their transaction and state hashes."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        mut context: Context,
+        exts: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config_path = context
+            .arg::<String>(NODE_CONFIG_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", NODE_CONFIG_PATH));
+        let config: NodeConfig<PathBuf> =
+            ConfigFile::load(&config_path).expect("Can't load node config file");
+
+        context.set(keys::NODE_CONFIG, config);
+        context.set(keys::NODE_CONFIG_PATH, config_path);
+        Feedback::AuditChain(exts(context))
+    }
+}
+
+/// Re-executes a committed transaction on the state before the given block and prints
+/// the changes it would make to the storage as JSON. The node database is not changed.
+/// See [`replay_transaction`] for details.
+///
+/// The replay is performed by the `NodeBuilder`, since it requires the services
+/// of the node.
+///
+/// [`replay_transaction`]: ../../blockchain/fn.replay_transaction.html
+#[derive(Debug)]
+pub struct ReplayTransaction;
+
+impl Command for ReplayTransaction {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                NODE_CONFIG_PATH,
+                true,
+                "Path to node configuration file.",
+                "c",
+                "node-config",
+                false,
+            ),
+            Argument::new_named(
+                DATABASE_PATH,
+                true,
+                "Use database with the given path.",
+                "d",
+                "db-path",
+                false,
+            ),
+            Argument::new_named(
+                REPLAY_TX_HASH,
+                true,
+                "Hex-encoded hash of the committed transaction.",
+                None,
+                "hash",
+                false,
+            ),
+            Argument::new_named(
+                REPLAY_HEIGHT,
+                false,
+                "Height of the block to replay the transaction in. \
+                 Defaults to the height the transaction has been committed at.",
+                None,
+                "at-height",
+                false,
+            ),
+            service_key_pass_arg(),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "replay-tx"
+    }
+
+    fn about(&self) -> &str {
+        "Re-executes a committed transaction and prints the changes it makes to the storage."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        mut context: Context,
+        exts: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config_path = context
+            .arg::<String>(NODE_CONFIG_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", NODE_CONFIG_PATH));
+        let config: NodeConfig<PathBuf> =
+            ConfigFile::load(&config_path).expect("Can't load node config file");
+
+        context.set(keys::NODE_CONFIG, config);
+        context.set(keys::NODE_CONFIG_PATH, config_path);
+        Feedback::ReplayTransaction(exts(context))
+    }
+}
+
+/// Runs an isolated service in a worker process. The worker is started by the node
+/// for each service listed in the `isolated_services` of the node configuration.
+/// See [`IsolatedService`] for details.
+///
+/// The worker is run by the `NodeBuilder`, since it requires the services of the node.
+///
+/// [`IsolatedService`]: ../../blockchain/struct.IsolatedService.html
+#[derive(Debug)]
+pub struct IsolatedWorker;
+
+impl Command for IsolatedWorker {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                NODE_CONFIG_PATH,
+                true,
+                "Path to node configuration file.",
+                "c",
+                "node-config",
+                false,
+            ),
+            Argument::new_named(
+                ISOLATED_SERVICE_NAME,
+                true,
+                "Name of the service executed by the worker.",
+                None,
+                "service-name",
+                false,
+            ),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "isolated-worker"
+    }
+
+    fn about(&self) -> &str {
+        "Executes an isolated service on behalf of the node. Not intended to be run manually."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        mut context: Context,
+        exts: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config_path = context
+            .arg::<String>(NODE_CONFIG_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", NODE_CONFIG_PATH));
+        let config: NodeConfig<PathBuf> =
+            ConfigFile::load(&config_path).expect("Can't load node config file");
+
+        context.set(keys::NODE_CONFIG, config);
+        context.set(keys::NODE_CONFIG_PATH, config_path);
+        Feedback::IsolatedWorker(exts(context))
+    }
+}
+
+/// Re-encrypts the secret keys of the node with new passphrases and optionally
+/// generates the next consensus key of the validator.
+///
+/// The key files are replaced atomically, so that an interrupted rotation leaves
+/// either the old or the new file in place. If the next consensus key is requested,
+/// it is written to a separate file together with a copy of the node config using it,
+/// while the current config is left intact: the node has to keep the current
+/// consensus key until the change of the validator keys, printed by the command,
+/// is accepted by the network via the configuration service.
+#[derive(Debug)]
+pub struct RotateSecrets;
+
+impl RotateSecrets {
+    fn pass_input_method(ctx: &Context, key: &str) -> PassInputMethod {
+        ctx.arg::<String>(key)
+            .unwrap_or_default()
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid passphrase input method in {}: {}", key, e))
+    }
+
+    fn secret_key_path(config_path: &Path, key_path: &Path) -> PathBuf {
+        if key_path.is_absolute() {
+            key_path.to_owned()
+        } else {
+            config_path.parent().unwrap().join(key_path)
+        }
+    }
+
+    /// Writes the file via a temporary file in the same directory, which is then
+    /// renamed over the target.
+    fn write_atomically(
+        path: &Path,
+        write: impl FnOnce(&Path) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut tmp_name = path
+            .file_name()
+            .ok_or_else(|| format_err!("Invalid file path: {}", path.display()))?
+            .to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        if let Err(e) = write(&tmp_path).and_then(|_| Ok(fs::rename(&tmp_path, path)?)) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn reencrypt_key(path: &Path, passphrase: &[u8], new_passphrase: &[u8]) {
+        let (public_key, secret_key) = read_keys_from_file(path, passphrase)
+            .unwrap_or_else(|e| panic!("Can't read secret key from {}: {}", path.display(), e));
+        Self::write_atomically(path, |tmp_path| {
+            Ok(write_keys_file(
+                tmp_path,
+                public_key,
+                &secret_key,
+                new_passphrase,
+            )?)
+        })
+        .unwrap_or_else(|e| panic!("Can't write secret key to {}: {}", path.display(), e));
+    }
+}
+
+impl Command for RotateSecrets {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                NODE_CONFIG_PATH,
+                true,
+                "Path to node configuration file.",
+                "c",
+                "node-config",
+                false,
+            ),
+            Argument::new_named(
+                CONSENSUS_KEY_PASS_METHOD,
+                false,
+                "Current passphrase entry method for consensus key.\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD (default: stdin)\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_CONSENSUS_PASS is used",
+                None,
+                "consensus-key-pass",
+                false,
+            ),
+            Argument::new_named(
+                SERVICE_KEY_PASS_METHOD,
+                false,
+                "Current passphrase entry method for service key.\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD (default: stdin)\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_SERVICE_PASS is used",
+                None,
+                "service-key-pass",
+                false,
+            ),
+            Argument::new_named(
+                NEW_CONSENSUS_KEY_PASS_METHOD,
+                false,
+                "New passphrase entry method for consensus key, accepting the same values \
+                 as consensus-key-pass.",
+                None,
+                "new-consensus-key-pass",
+                false,
+            ),
+            Argument::new_named(
+                NEW_SERVICE_KEY_PASS_METHOD,
+                false,
+                "New passphrase entry method for service key, accepting the same values \
+                 as service-key-pass.",
+                None,
+                "new-service-key-pass",
+                false,
+            ),
+            Argument::new_named(
+                NEXT_CONSENSUS_KEY_PATH,
+                false,
+                "Path to the file the next consensus key is generated to. \
+                 The key is encrypted with the new consensus key passphrase.",
+                None,
+                "next-consensus-key",
+                false,
+            ),
+            Argument::new_named(
+                NEXT_NODE_CONFIG_PATH,
+                false,
+                "Path to the node configuration file using the next consensus key. \
+                 Required if next-consensus-key is specified.",
+                None,
+                "next-node-config",
+                false,
+            ),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "rotate-secrets"
+    }
+
+    fn about(&self) -> &str {
+        "Re-encrypts the secret keys with new passphrases and optionally generates \
+         the next consensus key."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        context: Context,
+        _: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let config_path = context
+            .arg::<String>(NODE_CONFIG_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", NODE_CONFIG_PATH));
+        let config_path = Path::new(&config_path);
+        let config: NodeConfig<PathBuf> =
+            ConfigFile::load(config_path).expect("Can't load node config file");
+        let next_key_path = context.arg::<String>(NEXT_CONSENSUS_KEY_PATH).ok();
+        let next_config_path = context.arg::<String>(NEXT_NODE_CONFIG_PATH).ok();
+        if next_key_path.is_some() && next_config_path.is_none() {
+            panic!("{} not found.", NEXT_NODE_CONFIG_PATH);
+        }
+
+        let consensus_key_path = Self::secret_key_path(config_path, &config.consensus_secret_key);
+        let consensus_passphrase = Self::pass_input_method(&context, CONSENSUS_KEY_PASS_METHOD)
+            .get_passphrase(SecretKeyType::Consensus, true);
+        let new_consensus_passphrase =
+            Self::pass_input_method(&context, NEW_CONSENSUS_KEY_PASS_METHOD)
+                .get_passphrase(SecretKeyType::Consensus, false);
+        Self::reencrypt_key(
+            &consensus_key_path,
+            consensus_passphrase.as_bytes(),
+            new_consensus_passphrase.as_bytes(),
+        );
+        info!(
+            "Re-encrypted consensus key {}",
+            consensus_key_path.display()
+        );
+
+        let service_key_path = Self::secret_key_path(config_path, &config.service_secret_key);
+        let service_passphrase = Self::pass_input_method(&context, SERVICE_KEY_PASS_METHOD)
+            .get_passphrase(SecretKeyType::Service, true);
+        let new_service_passphrase = Self::pass_input_method(&context, NEW_SERVICE_KEY_PASS_METHOD)
+            .get_passphrase(SecretKeyType::Service, false);
+        Self::reencrypt_key(
+            &service_key_path,
+            service_passphrase.as_bytes(),
+            new_service_passphrase.as_bytes(),
+        );
+        info!("Re-encrypted service key {}", service_key_path.display());
+
+        if let (Some(next_key_path), Some(next_config_path)) = (next_key_path, next_config_path) {
+            let next_key_path = env::current_dir()
+                .expect("Can't get current directory")
+                .join(next_key_path);
+            if next_key_path.exists() {
+                panic!(
+                    "Next consensus key file exists: {}",
+                    next_key_path.display()
+                );
+            }
+            let (consensus_key, secret_key) = crypto::gen_keypair();
+            Self::write_atomically(&next_key_path, |tmp_path| {
+                Ok(write_keys_file(
+                    tmp_path,
+                    consensus_key,
+                    &secret_key,
+                    new_consensus_passphrase.as_bytes(),
+                )?)
+            })
+            .expect("Can't write next consensus key");
+
+            let service_key = config.service_public_key;
+            let next_config = NodeConfig {
+                consensus_public_key: consensus_key,
+                consensus_secret_key: next_key_path,
+                ..config
+            };
+            let next_config_path = Path::new(&next_config_path);
+            Self::write_atomically(next_config_path, |tmp_path| {
+                ConfigFile::save(&next_config, tmp_path)
+            })
+            .expect("Can't write next node config");
+
+            let validator_keys = ValidatorKeys::new(consensus_key, service_key);
+            println!(
+                "Next validator keys to be proposed via the configuration service:\n{}",
+                serde_json::to_string_pretty(&validator_keys)
+                    .expect("Can't serialize validator keys")
+            );
+        }
+        Feedback::None
+    }
+}
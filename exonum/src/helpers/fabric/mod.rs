@@ -19,8 +19,12 @@ pub use self::{
     context_key::ContextKey,
     details::{Finalize, GenerateCommonConfig, GenerateNodeConfig, Run, RunDev},
     internal::Command,
-    maintenance::Maintenance,
+    maintenance::{
+        AuditChain, CreateCheckpoint, ExportChain, ExportPeers, ForkChain, ImportChain,
+        ImportPeers, IsolatedWorker, Maintenance, ReplayTransaction, RollbackChain, RotateSecrets,
+    },
     shared::{AbstractConfig, CommonConfigTemplate, NodePrivateConfig, NodePublicConfig},
+    test_vectors::GenerateTestVectors,
 };
 
 use serde::{Deserialize, Serialize};
@@ -37,6 +41,7 @@ mod info;
 mod internal;
 mod maintenance;
 mod shared;
+mod test_vectors;
 #[macro_use]
 mod context_key;
 mod password;
@@ -65,6 +65,9 @@ pub struct CommonConfigTemplate {
     pub services_config: AbstractConfig,
     /// General configuration.
     pub general_config: AbstractConfig,
+    /// Identifier of the network.
+    #[serde(default)]
+    pub network_id: u32,
 }
 
 /// `NodePrivateConfig` collects all public and secret keys.
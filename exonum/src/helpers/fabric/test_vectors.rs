@@ -0,0 +1,78 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the generation of reference messages for client libraries.
+
+use std::{collections::HashMap, fs::File, io::BufWriter};
+
+use super::{
+    internal::{CollectedCommand, Command, Feedback},
+    Argument, CommandName, Context,
+};
+use crate::messages::test_vector_suite;
+
+// Context entry for the path to the file with the generated test vectors.
+const TEST_VECTORS_PATH: &str = "TEST_VECTORS_PATH";
+
+/// Writes reference transactions and consensus messages signed with fixed keys
+/// in JSON, so that client libraries can check their encoding and signing
+/// against the current code. The vectors are printed to the standard output
+/// unless a file is specified.
+#[derive(Debug)]
+pub struct GenerateTestVectors;
+
+impl Command for GenerateTestVectors {
+    fn args(&self) -> Vec<Argument> {
+        vec![Argument::new_named(
+            TEST_VECTORS_PATH,
+            false,
+            "Path to the file the test vectors are written to.",
+            "o",
+            "output",
+            false,
+        )]
+    }
+
+    fn name(&self) -> CommandName {
+        "gen-test-vectors"
+    }
+
+    fn about(&self) -> &str {
+        "Generates reference signed messages for client libraries."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        context: Context,
+        _: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let suite = test_vector_suite();
+        if let Ok(path) = context.arg::<String>(TEST_VECTORS_PATH) {
+            let file = File::create(&path).expect("Can't create test vectors file");
+            serde_json::to_writer_pretty(BufWriter::new(file), &suite)
+                .unwrap_or_else(|e| panic!("Can't write test vectors: {}", e));
+            info!(
+                "Written {} test vectors to {}",
+                suite.transactions.len() + suite.protocol_messages.len(),
+                path
+            );
+        } else {
+            let json =
+                serde_json::to_string_pretty(&suite).expect("Unable to serialize test vectors");
+            println!("{}", json);
+        }
+        Feedback::None
+    }
+}
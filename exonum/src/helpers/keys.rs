@@ -0,0 +1,216 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Composite keys of the storage indexes.
+
+use crate::crypto::{self, Hash, PublicKey};
+
+/// Byte order of the numeric fields of a `CompositeKey`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// Big-endian order. Keys compare byte-wise in the same way as the tuples of their
+    /// fields, so the entries of a `MapIndex` are iterated in the order of the fields.
+    Big,
+    /// Little-endian order, used by the keys of the core tables.
+    Little,
+}
+
+impl Default for Endianness {
+    fn default() -> Self {
+        Endianness::Big
+    }
+}
+
+/// Builder of keys consisting of several fixed-size fields.
+///
+/// The encoding of the fields does not depend on the platform, so the keys built
+/// on different nodes are the same. Since all the fields have fixed sizes, keys
+/// with the same sequence of field types never collide.
+///
+/// The built key can be used as is, or hashed to be used as a key of `ProofMapIndex`.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::{crypto::gen_keypair, helpers::CompositeKey};
+///
+/// let (owner, _) = gen_keypair();
+/// let key = CompositeKey::new().public_key(&owner).u64(5).into_bytes();
+/// assert_eq!(key.len(), 32 + 8);
+///
+/// // Big-endian keys are ordered in the same way as their fields.
+/// let first = CompositeKey::new().u16(1).i64(-1).into_bytes();
+/// let second = CompositeKey::new().u16(1).i64(2).into_bytes();
+/// assert!(first < second);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompositeKey {
+    endianness: Endianness,
+    buffer: Vec<u8>,
+}
+
+impl CompositeKey {
+    /// Creates an empty key with big-endian fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty key with the fields in the given byte order.
+    pub fn with_endianness(endianness: Endianness) -> Self {
+        Self {
+            endianness,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Appends a `u8` field.
+    pub fn u8(mut self, value: u8) -> Self {
+        self.buffer.push(value);
+        self
+    }
+
+    /// Appends a `u16` field.
+    pub fn u16(self, value: u16) -> Self {
+        match self.endianness {
+            Endianness::Big => self.bytes(&value.to_be_bytes()),
+            Endianness::Little => self.bytes(&value.to_le_bytes()),
+        }
+    }
+
+    /// Appends a `u32` field.
+    pub fn u32(self, value: u32) -> Self {
+        match self.endianness {
+            Endianness::Big => self.bytes(&value.to_be_bytes()),
+            Endianness::Little => self.bytes(&value.to_le_bytes()),
+        }
+    }
+
+    /// Appends a `u64` field.
+    pub fn u64(self, value: u64) -> Self {
+        match self.endianness {
+            Endianness::Big => self.bytes(&value.to_be_bytes()),
+            Endianness::Little => self.bytes(&value.to_le_bytes()),
+        }
+    }
+
+    /// Appends an `i64` field. With the big-endian order, the sign bit is flipped,
+    /// so that negative values precede positive ones.
+    pub fn i64(self, value: i64) -> Self {
+        match self.endianness {
+            Endianness::Big => self.u64((value as u64) ^ (1 << 63)),
+            Endianness::Little => self.bytes(&value.to_le_bytes()),
+        }
+    }
+
+    /// Appends a hash.
+    pub fn hash(self, value: &Hash) -> Self {
+        self.bytes(value.as_ref())
+    }
+
+    /// Appends a public key.
+    pub fn public_key(self, value: &PublicKey) -> Self {
+        self.bytes(value.as_ref())
+    }
+
+    /// Appends raw bytes. To avoid collisions, the bytes should be of the same length
+    /// in all the keys of an index.
+    pub fn bytes(mut self, value: &[u8]) -> Self {
+        self.buffer.extend_from_slice(value);
+        self
+    }
+
+    /// Returns the encoded key.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    /// Returns the hash of the encoded key.
+    pub fn into_hash(self) -> Hash {
+        crypto::hash(&self.buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::FromHex;
+
+    use super::{CompositeKey, Endianness};
+    use crate::crypto::{Hash, HASH_SIZE};
+
+    #[test]
+    fn big_endian_encoding() {
+        let key = CompositeKey::new()
+            .u8(1)
+            .u16(0x0203)
+            .u32(0x0405_0607)
+            .u64(0x0809_0a0b_0c0d_0e0f)
+            .into_bytes();
+        assert_eq!(key, (1..=15).collect::<Vec<u8>>());
+
+        let key = CompositeKey::new().i64(-2).i64(1).into_bytes();
+        assert_eq!(
+            key,
+            vec![
+                0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, //
+                0x80, 0, 0, 0, 0, 0, 0, 1,
+            ]
+        );
+    }
+
+    #[test]
+    fn little_endian_encoding() {
+        let key = CompositeKey::with_endianness(Endianness::Little)
+            .u16(0x0102)
+            .u32(0x0304_0506)
+            .i64(-2)
+            .into_bytes();
+        assert_eq!(
+            key,
+            vec![2, 1, 6, 5, 4, 3, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn big_endian_keys_are_ordered() {
+        let values = [(0_u16, i64::min_value()), (0, -1), (0, 0), (1, -5), (1, 7)];
+        let keys = values
+            .iter()
+            .map(|&(a, b)| CompositeKey::new().u16(a).i64(b).into_bytes())
+            .collect::<Vec<_>>();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn service_table_key_is_stable() {
+        let key = CompositeKey::with_endianness(Endianness::Little)
+            .u16(1)
+            .u16(2)
+            .into_hash();
+        let expected = "7b11c1133330cd161071bf23a0c9b6ce5320a8f3a0f83620035a72be46df4104";
+        assert_eq!(key, Hash::from_hex(expected).unwrap());
+    }
+
+    #[test]
+    fn hashed_key() {
+        let hash = Hash::new([7; HASH_SIZE]);
+        let key = CompositeKey::new().hash(&hash).u8(1);
+        assert_eq!(key.clone().into_bytes().len(), HASH_SIZE + 1);
+        assert_eq!(
+            key.clone().into_hash(),
+            crate::crypto::hash(&key.into_bytes())
+        );
+    }
+}
@@ -0,0 +1,318 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Log filters adjustable at runtime.
+//!
+//! The logger installed by [`init_logger`] checks every record against the current
+//! filters, so the log verbosity of a running node can be changed without a restart,
+//! e.g., via the private system API. The initial filters are read from the `RUST_LOG`
+//! environment variable, which has the `env_logger` syntax except for the regular
+//! expression part.
+//!
+//! Filters are keyed by log targets, which are the module paths of the logging code
+//! by default. Hence, the filter for a service is set using the name of its crate
+//! or module, e.g., `exonum_configuration`.
+//!
+//! [`init_logger`]: ../fn.init_logger.html
+
+use env_logger::{Builder, Logger};
+use failure::Error;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use std::{collections::BTreeMap, env, str::FromStr, sync::RwLock};
+
+lazy_static! {
+    static ref FILTERS: RwLock<Option<LogFilters>> = RwLock::new(None);
+}
+
+/// Verbosity level of a log filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Logging is disabled.
+    Off,
+    /// Only errors are logged.
+    Error,
+    /// Warnings and more severe records are logged.
+    Warn,
+    /// Informational and more severe records are logged.
+    Info,
+    /// Debug and more severe records are logged.
+    Debug,
+    /// All records are logged.
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(LogLevel::Off),
+            "error" => Ok(LogLevel::Error),
+            "warn" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            _ => bail!("Unknown log level: {}", s),
+        }
+    }
+}
+
+/// Log levels applied to the log targets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogFilters {
+    /// Level applied to the targets without a specific filter.
+    pub global: LogLevel,
+    /// Levels applied to the targets starting with the given prefixes. The longest
+    /// matching prefix takes precedence.
+    pub targets: BTreeMap<String, LogLevel>,
+}
+
+impl Default for LogFilters {
+    fn default() -> Self {
+        Self {
+            global: LogLevel::Error,
+            targets: BTreeMap::new(),
+        }
+    }
+}
+
+impl LogFilters {
+    /// Parses filters in the `RUST_LOG` format, e.g., `warn,exonum::node=debug`.
+    /// A target without a level enables all records of this target.
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let mut filters = Self::default();
+        // The regular expression filtering the messages is not supported.
+        let directives = spec.split('/').next().unwrap_or_default();
+        for directive in directives.split(',').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+
+            let mut parts = directive.splitn(2, '=');
+            let target = parts.next().unwrap_or_default();
+            match parts.next() {
+                Some(level) => {
+                    filters.targets.insert(target.to_owned(), level.parse()?);
+                }
+                None => match target.parse() {
+                    Ok(level) => filters.global = level,
+                    Err(_) => {
+                        filters.targets.insert(target.to_owned(), LogLevel::Trace);
+                    }
+                },
+            }
+        }
+        Ok(filters)
+    }
+
+    /// Returns the level applied to the given target.
+    pub fn level(&self, target: &str) -> LogLevel {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.global, |(_, &level)| level)
+    }
+
+    /// Returns the most verbose level among all filters.
+    pub fn max_level(&self) -> LogLevel {
+        self.targets
+            .values()
+            .cloned()
+            .fold(self.global, std::cmp::max)
+    }
+
+    /// Applies the given changes to the filters. Returns an error and leaves
+    /// the filters intact if the filter string of the update cannot be parsed.
+    pub fn update(&mut self, update: LogFiltersUpdate) -> Result<(), Error> {
+        if let Some(ref spec) = update.spec {
+            *self = Self::parse(spec)?;
+        }
+        if let Some(global) = update.global {
+            self.global = global;
+        }
+        for (target, level) in update.targets {
+            match level {
+                Some(level) => self.targets.insert(target, level),
+                None => self.targets.remove(&target),
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Changes of the log filters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LogFiltersUpdate {
+    /// Filters in the `RUST_LOG` format replacing the current ones. The other changes
+    /// are applied on top of them.
+    #[serde(default)]
+    pub spec: Option<String>,
+    /// New global level. The global level is left intact if the value is absent.
+    #[serde(default)]
+    pub global: Option<LogLevel>,
+    /// New levels of the targets. A `null` level removes the filter of the target.
+    #[serde(default)]
+    pub targets: BTreeMap<String, Option<LogLevel>>,
+}
+
+/// Returns the current log filters, or `None` if the logger was not initialized
+/// by [`init_logger`].
+///
+/// [`init_logger`]: ../fn.init_logger.html
+pub fn current_filters() -> Option<LogFilters> {
+    FILTERS.read().expect("Expected read lock").clone()
+}
+
+/// Changes the log filters and returns the resulting ones.
+///
+/// Returns an error if the logger was not initialized by [`init_logger`].
+///
+/// [`init_logger`]: ../fn.init_logger.html
+pub fn update_filters(update: LogFiltersUpdate) -> Result<LogFilters, Error> {
+    let mut filters = FILTERS.write().expect("Expected write lock");
+    let filters = match *filters {
+        Some(ref mut filters) => filters,
+        None => bail!("The logger with runtime filters is not initialized"),
+    };
+    filters.update(update)?;
+    log::set_max_level(filters.max_level().into());
+    Ok(filters.clone())
+}
+
+/// Installs the logger with the filters read from the `RUST_LOG` environment variable.
+pub(crate) fn install() -> Result<(), SetLoggerError> {
+    let filters = env::var("RUST_LOG").ok().map_or_else(LogFilters::default, |spec| {
+        LogFilters::parse(&spec).unwrap_or_else(|e| {
+            eprintln!("warning: invalid logging spec '{}', ignoring it: {}", spec, e);
+            LogFilters::default()
+        })
+    });
+
+    let mut builder = Builder::new();
+    builder
+        .filter_level(LevelFilter::Trace)
+        .default_format_timestamp_nanos(true);
+    if let Ok(style) = env::var("RUST_LOG_STYLE") {
+        builder.parse_write_style(&style);
+    }
+
+    log::set_boxed_logger(Box::new(FilteredLogger {
+        inner: builder.build(),
+    }))?;
+    log::set_max_level(filters.max_level().into());
+    *FILTERS.write().expect("Expected write lock") = Some(filters);
+    Ok(())
+}
+
+/// Logger that applies the runtime filters before passing records to `env_logger`.
+struct FilteredLogger {
+    inner: Logger,
+}
+
+impl Log for FilteredLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        FILTERS
+            .read()
+            .expect("Expected read lock")
+            .as_ref()
+            .map_or(false, |filters| {
+                metadata.level() <= LevelFilter::from(filters.level(metadata.target()))
+            })
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_parsing() {
+        let filters = LogFilters::parse("warn, exonum::node=debug,exonum_time,/regex").unwrap();
+        assert_eq!(filters.global, LogLevel::Warn);
+        assert_eq!(filters.targets.len(), 2);
+        assert_eq!(filters.targets["exonum::node"], LogLevel::Debug);
+        assert_eq!(filters.targets["exonum_time"], LogLevel::Trace);
+
+        assert_eq!(LogFilters::parse("").unwrap(), LogFilters::default());
+        assert!(LogFilters::parse("exonum=verbose").is_err());
+    }
+
+    #[test]
+    fn longest_prefix_takes_precedence() {
+        let filters = LogFilters::parse("info,exonum=warn,exonum::node=trace").unwrap();
+        assert_eq!(filters.level("exonum::node::consensus"), LogLevel::Trace);
+        assert_eq!(filters.level("exonum::blockchain"), LogLevel::Warn);
+        assert_eq!(filters.level("exonum_merkledb"), LogLevel::Warn);
+        assert_eq!(filters.level("actix_web"), LogLevel::Info);
+        assert_eq!(filters.max_level(), LogLevel::Trace);
+    }
+
+    #[test]
+    fn filters_update() {
+        let mut filters = LogFilters::parse("info,exonum=warn,exonum::node=trace").unwrap();
+        let mut update = LogFiltersUpdate {
+            global: Some(LogLevel::Off),
+            ..LogFiltersUpdate::default()
+        };
+        update
+            .targets
+            .insert("exonum::node".to_owned(), Some(LogLevel::Debug));
+        update.targets.insert("exonum".to_owned(), None);
+        filters.update(update).unwrap();
+
+        assert_eq!(filters, LogFilters::parse("off,exonum::node=debug").unwrap());
+        assert_eq!(filters.max_level(), LogLevel::Debug);
+
+        let update = LogFiltersUpdate {
+            spec: Some("warn,exonum=info".to_owned()),
+            global: Some(LogLevel::Error),
+            ..LogFiltersUpdate::default()
+        };
+        filters.update(update).unwrap();
+        assert_eq!(filters, LogFilters::parse("error,exonum=info").unwrap());
+
+        let update = LogFiltersUpdate {
+            spec: Some("exonum=verbose".to_owned()),
+            ..LogFiltersUpdate::default()
+        };
+        assert!(filters.update(update).is_err());
+        assert_eq!(filters, LogFilters::parse("error,exonum=info").unwrap());
+    }
+}
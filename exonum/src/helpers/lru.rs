@@ -0,0 +1,182 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bounded map evicting the least recently used entries.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+/// Map holding a limited number of entries. Once the limit is exceeded, the entries
+/// are evicted in the order of their last access.
+#[derive(Debug)]
+pub(crate) struct LruMap<K, V> {
+    entries: HashMap<K, (u64, V)>,
+    // Keys of the entries ordered by the time of the last access.
+    recency: BTreeMap<u64, K>,
+    clock: u64,
+}
+
+impl<K, V> Default for LruMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<K, V> LruMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the entry for the given key and marks it as the most recently used.
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        self.clock += 1;
+        let clock = self.clock;
+        let (last_access, value) = self.entries.get_mut(key)?;
+        let key = self.recency.remove(last_access).expect("Recency index is corrupted");
+        self.recency.insert(clock, key);
+        *last_access = clock;
+        Some(value)
+    }
+
+    /// Inserts an entry evicting the least recently used ones if there are more than
+    /// `capacity` entries.
+    pub(crate) fn insert(&mut self, key: K, value: V, capacity: usize) {
+        self.clock += 1;
+        if let Some((last_access, _)) = self.entries.insert(key.clone(), (self.clock, value)) {
+            self.recency.remove(&last_access);
+        }
+        self.recency.insert(self.clock, key);
+
+        while self.entries.len() > capacity {
+            let oldest = *self.recency.keys().next().expect("Recency index is empty");
+            let key = self.recency.remove(&oldest).unwrap();
+            self.entries.remove(&key);
+        }
+    }
+
+    /// Removes the entry for the given key and returns its value.
+    pub(crate) fn remove(&mut self, key: &K) -> Option<V> {
+        let (last_access, value) = self.entries.remove(key)?;
+        self.recency.remove(&last_access);
+        Some(value)
+    }
+
+    /// Removes the least recently used entry and returns it.
+    pub(crate) fn pop_oldest(&mut self) -> Option<(K, V)> {
+        let oldest = *self.recency.keys().next()?;
+        let key = self.recency.remove(&oldest).unwrap();
+        let (_, value) = self
+            .entries
+            .remove(&key)
+            .expect("Recency index is corrupted");
+        Some((key, value))
+    }
+
+    /// Removes the entries whose keys do not satisfy the predicate.
+    pub(crate) fn retain<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let removed: Vec<_> = self
+            .recency
+            .iter()
+            .filter(|(_, key)| !predicate(key))
+            .map(|(&last_access, _)| last_access)
+            .collect();
+        for last_access in removed {
+            let key = self.recency.remove(&last_access).unwrap();
+            self.entries.remove(&key);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let mut map = LruMap::default();
+        map.insert(1, "a", 2);
+        map.insert(2, "b", 2);
+        // Accessing the first entry makes the second one the eviction candidate.
+        assert_eq!(map.get(&1), Some(&"a"));
+        map.insert(3, "c", 2);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&1), Some(&"a"));
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get(&3), Some(&"c"));
+    }
+
+    #[test]
+    fn reinserted_entry_does_not_leak_recency_entries() {
+        let mut map = LruMap::default();
+        map.insert(1, "a", 2);
+        map.insert(1, "a", 2);
+        map.insert(2, "b", 2);
+        map.insert(3, "c", 2);
+
+        assert_eq!(map.entries.len(), 2);
+        assert_eq!(map.recency.len(), 2);
+        assert!(!map.entries.contains_key(&1));
+    }
+
+    #[test]
+    fn removed_entries() {
+        let mut map = LruMap::default();
+        map.insert(1, "a", 3);
+        map.insert(2, "b", 3);
+        map.insert(3, "c", 3);
+        map.get(&1);
+
+        assert_eq!(map.remove(&3), Some("c"));
+        assert_eq!(map.remove(&3), None);
+        assert_eq!(map.pop_oldest(), Some((2, "b")));
+        assert_eq!(map.pop_oldest(), Some((1, "a")));
+        assert_eq!(map.pop_oldest(), None);
+        assert!(map.recency.is_empty());
+    }
+
+    #[test]
+    fn retained_entries() {
+        let mut map = LruMap::default();
+        for key in 0..5 {
+            map.insert(key, key.to_string(), 10);
+        }
+        map.retain(|&key| key >= 3);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.recency.len(), 2);
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get(&3), Some(&"3".to_owned()));
+    }
+}
@@ -14,15 +14,18 @@
 
 //! Different assorted utilities.
 
+pub use self::keys::{CompositeKey, Endianness};
+pub use self::schedule::Schedule;
 pub use self::types::{Height, Milliseconds, Round, ValidatorId, ZeroizeOnDrop};
 
 pub mod config;
 pub mod fabric;
+pub mod log_filters;
 pub mod user_agent;
+pub(crate) mod lru;
 #[macro_use]
 pub mod metrics;
 
-use env_logger::Builder;
 use log::SetLoggerError;
 
 use std::path::{Component, Path, PathBuf};
@@ -31,13 +34,17 @@ use crate::blockchain::{GenesisConfig, ValidatorKeys};
 use crate::crypto::gen_keypair;
 use crate::node::{ConnectListConfig, NodeConfig};
 
+mod keys;
+mod schedule;
 mod types;
 
 /// Performs the logger initialization.
+///
+/// The logger filters can be changed at runtime, see the [`log_filters`] module.
+///
+/// [`log_filters`]: log_filters/index.html
 pub fn init_logger() -> Result<(), SetLoggerError> {
-    Builder::from_default_env()
-        .default_format_timestamp_nanos(true)
-        .try_init()
+    log_filters::install()
 }
 
 /// Generates testnet configuration.
@@ -50,10 +57,7 @@ pub fn generate_testnet_config(count: u16, start_port: u16) -> Vec<NodeConfig> {
             validators
                 .iter()
                 .zip(services.iter())
-                .map(|x| ValidatorKeys {
-                    consensus_key: (x.0).0,
-                    service_key: (x.1).0,
-                }),
+                .map(|x| ValidatorKeys::new((x.0).0, (x.1).0)),
         );
     let peers = (0..validators.len())
         .map(|x| format!("127.0.0.1:{}", start_port + x as u16))
@@ -78,6 +82,16 @@ pub fn generate_testnet_config(count: u16, start_port: u16) -> Vec<NodeConfig> {
             services_configs: Default::default(),
             database: Default::default(),
             thread_pool_size: Default::default(),
+            genesis_state: None,
+            retention: Default::default(),
+            observer_address: None,
+            integrity_check_depth: None,
+            clock: Default::default(),
+            isolated_services: Vec::new(),
+            record_patch_hashes: false,
+            alerts: Vec::new(),
+            resources: Default::default(),
+            consensus_archive: None,
         })
         .collect::<Vec<_>>()
 }
@@ -0,0 +1,296 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Moments of the blockchain expressed either in blocks or in time.
+
+use chrono::Duration as TimeDelta;
+use exonum_merkledb::IndexAccess;
+use serde::{Deserialize, Deserializer};
+
+use std::{cmp, fmt, time::Duration};
+
+use super::{Height, Milliseconds};
+use crate::blockchain::Schema;
+
+/// Moment of the blockchain at which something happens, expressed either as a height
+/// or as a delay after a reference height.
+///
+/// A schedule is resolved to a height with [`due_height`] using the blockchain data,
+/// so all nodes agree on it. The delay in milliseconds is compared with the consensus
+/// time of the blocks ([`Schema::block_time`]): the schedule is due at the first block
+/// whose time is at least the delay later than the time of the block at the reference
+/// height. Unlike the other schedules, the height of such a schedule is known only
+/// once the block is committed.
+///
+/// The schedule is serialized as an object with a single field, for example,
+/// `{ "at_height": 1000 }`, `{ "after_blocks": 10 }` or `{ "after_duration": 60000 }`.
+/// A bare number is deserialized as a height.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::helpers::{Height, Schedule};
+///
+/// let schedule: Schedule = serde_json::from_str(r#"{ "after_blocks": 5 }"#).unwrap();
+/// assert_eq!(schedule, Schedule::AfterBlocks(5));
+/// let schedule: Schedule = serde_json::from_str("1000").unwrap();
+/// assert_eq!(schedule, Schedule::AtHeight(Height(1_000)));
+/// ```
+///
+/// [`due_height`]: #method.due_height
+/// [`Schema::block_time`]: ../blockchain/struct.Schema.html#method.block_time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    /// The given height.
+    AtHeight(Height),
+    /// The given number of blocks after the reference height.
+    AfterBlocks(u64),
+    /// The given number of milliseconds after the reference height.
+    AfterDuration(Milliseconds),
+}
+
+impl Schedule {
+    /// Creates a schedule with the given delay after the reference height.
+    pub fn after(delay: Duration) -> Self {
+        let millis = delay.as_secs() * 1_000 + u64::from(delay.subsec_millis());
+        Schedule::AfterDuration(millis)
+    }
+
+    /// Returns the height at which the schedule is due, counting the delays from
+    /// the reference height `from`, or `None` if the schedule is not due by `height`.
+    ///
+    /// A delay in time is counted from the time of the block at the reference height;
+    /// the genesis block and the block following it have no consensus time, so the delays
+    /// from them are counted from the next block. The time is known for the committed
+    /// blocks and for the block being executed.
+    pub fn due_height<T: IndexAccess>(
+        self,
+        from: Height,
+        height: Height,
+        schema: &Schema<T>,
+    ) -> Option<Height> {
+        let due_height = match self {
+            Schedule::AtHeight(due_height) => due_height,
+            Schedule::AfterBlocks(blocks) => Height(from.0.saturating_add(blocks)),
+            Schedule::AfterDuration(millis) => return due_by_time(millis, from, height, schema),
+        };
+        if due_height <= height {
+            Some(due_height)
+        } else {
+            None
+        }
+    }
+
+    /// Checks that the schedule is not due before the reference height `from`.
+    pub fn validate(self, from: Height) -> Result<(), failure::Error> {
+        if let Schedule::AtHeight(height) = self {
+            ensure!(
+                height >= from,
+                "Height {} is in the past relative to height {}",
+                height,
+                from
+            );
+        }
+        Ok(())
+    }
+}
+
+impl From<Height> for Schedule {
+    fn from(height: Height) -> Self {
+        Schedule::AtHeight(height)
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Schedule::AtHeight(height) => write!(f, "at height {}", height),
+            Schedule::AfterBlocks(blocks) => write!(f, "after {} blocks", blocks),
+            Schedule::AfterDuration(millis) => write!(f, "after {} ms", millis),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "snake_case", deny_unknown_fields)]
+        enum Tagged {
+            AtHeight(Height),
+            AfterBlocks(u64),
+            AfterDuration(Milliseconds),
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Height(Height),
+            Tagged(Tagged),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Height(height) => Schedule::AtHeight(height),
+            Repr::Tagged(Tagged::AtHeight(height)) => Schedule::AtHeight(height),
+            Repr::Tagged(Tagged::AfterBlocks(blocks)) => Schedule::AfterBlocks(blocks),
+            Repr::Tagged(Tagged::AfterDuration(millis)) => Schedule::AfterDuration(millis),
+        })
+    }
+}
+
+/// Returns the first block not later than `height` whose consensus time is at least
+/// `millis` later than the time of the block at `from`.
+fn due_by_time<T: IndexAccess>(
+    millis: Milliseconds,
+    from: Height,
+    height: Height,
+    schema: &Schema<T>,
+) -> Option<Height> {
+    // The genesis block and the block following it have no consensus time.
+    let from = cmp::max(from, Height(2));
+    // The next block has the time only if it is being executed.
+    let next = schema.block_hashes_by_height().len();
+    let last = if schema.block_time(Height(next)).is_some() {
+        next
+    } else {
+        next.saturating_sub(1)
+    };
+    let last = cmp::min(height.0, last);
+    if from.0 > last {
+        return None;
+    }
+
+    let delay = TimeDelta::from_std(Duration::from_millis(millis)).ok()?;
+    let due_time = schema.block_time(from)?.checked_add_signed(delay)?;
+    let is_due = |block_height| {
+        schema
+            .block_time(Height(block_height))
+            .map_or(false, |time| time >= due_time)
+    };
+    // The block times do not decrease, so the first due block is found by a binary search.
+    let (mut low, mut high) = (from.0, last + 1);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if is_due(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    if low <= last {
+        Some(Height(low))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use exonum_merkledb::{Database, Fork, TemporaryDB};
+
+    use super::*;
+    use crate::crypto::Hash;
+
+    fn schema_with_block_times(fork: &Fork, times: &[Option<i64>]) {
+        let schema = Schema::new(fork);
+        for (height, time) in times.iter().enumerate() {
+            schema.block_hashes_by_height().push(Hash::zero());
+            if let Some(time) = time {
+                schema
+                    .block_times()
+                    .put(&(height as u64), Utc.timestamp(*time, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn heights_are_due_after_blocks() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        schema_with_block_times(&fork, &[None, None]);
+        let schema = Schema::new(&fork);
+
+        let from = Height(10);
+        let due = |schedule: Schedule, height| schedule.due_height(from, Height(height), &schema);
+        assert_eq!(due(Schedule::AtHeight(Height(5)), 5), Some(Height(5)));
+        assert_eq!(due(Schedule::AtHeight(Height(5)), 4), None);
+        assert_eq!(due(Schedule::AfterBlocks(5), 100), Some(Height(15)));
+        assert_eq!(due(Schedule::AfterBlocks(5), 14), None);
+    }
+
+    #[test]
+    fn duration_is_compared_with_block_time() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let times = [None, None, Some(10), Some(11), Some(13), Some(13)];
+        schema_with_block_times(&fork, &times);
+        let schema = Schema::new(&fork);
+
+        let due = |millis, from, height| {
+            Schedule::AfterDuration(millis).due_height(Height(from), Height(height), &schema)
+        };
+        assert_eq!(due(0, 3, 5), Some(Height(3)));
+        assert_eq!(due(1_000, 2, 5), Some(Height(3)));
+        assert_eq!(due(1_001, 2, 5), Some(Height(4)));
+        assert_eq!(due(2_000, 3, 5), Some(Height(4)));
+        // The delays from the blocks without the time are counted from the next block.
+        assert_eq!(due(3_000, 0, 5), Some(Height(4)));
+        // Not due by the given height.
+        assert_eq!(due(3_000, 2, 3), None);
+        // Not due in the committed blocks.
+        assert_eq!(due(3_001, 2, 100), None);
+        assert_eq!(due(0, 10, 100), None);
+        assert_eq!(due(Milliseconds::max_value(), 2, 100), None);
+
+        // The time of the block being executed is taken into account.
+        Schema::new(&fork)
+            .block_times()
+            .put(&6, Utc.timestamp(20, 0));
+        let schema = Schema::new(&fork);
+        assert_eq!(
+            Schedule::after(Duration::from_secs(10)).due_height(Height(2), Height(6), &schema),
+            Some(Height(6))
+        );
+    }
+
+    #[test]
+    fn schedule_serde() {
+        let schedules = vec![
+            Schedule::AtHeight(Height(5)),
+            Schedule::AfterBlocks(3),
+            Schedule::AfterDuration(1_000),
+        ];
+        let json = serde_json::to_string(&schedules).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"at_height":5},{"after_blocks":3},{"after_duration":1000}]"#
+        );
+        let parsed: Vec<Schedule> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, schedules);
+
+        assert!(serde_json::from_str::<Schedule>(r#"{ "after_days": 1 }"#).is_err());
+        assert!(serde_json::from_str::<Schedule>(r#""soon""#).is_err());
+    }
+
+    #[test]
+    fn past_heights_are_invalid() {
+        assert!(Schedule::AtHeight(Height(5)).validate(Height(5)).is_ok());
+        assert!(Schedule::AtHeight(Height(4)).validate(Height(5)).is_err());
+        assert!(Schedule::AfterBlocks(0).validate(Height(5)).is_ok());
+    }
+}
@@ -57,6 +57,8 @@ pub extern crate exonum_merkledb;
 #[macro_use]
 extern crate failure;
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
 #[macro_use]
 extern crate serde_derive;
@@ -64,9 +66,6 @@ extern crate serde_derive;
 extern crate serde_json;
 
 // Test dependencies.
-#[cfg(test)]
-#[macro_use]
-extern crate lazy_static;
 #[cfg(all(test, feature = "long_benchmarks"))]
 extern crate test;
 
@@ -1,3 +1,4 @@
+use byteorder::{ByteOrder, LittleEndian};
 use failure::Error;
 use hex::{FromHex, ToHex};
 
@@ -8,16 +9,73 @@ use crate::crypto::{
     self, hash, Hash, PublicKey, SecretKey, Signature, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH,
 };
 
+/// Mask of the message class in the byte following the author's public key.
+const MESSAGE_CLASS_MASK: u8 = 0x07;
+/// Flag in the byte following the author's public key which is set if the message
+/// contains the network identifier.
+pub(crate) const NETWORK_ID_FLAG: u8 = 0x08;
+/// Size of the network identifier stored in the message.
+pub(crate) const NETWORK_ID_LENGTH: usize = 4;
+/// Position of the signature algorithm identifier in the byte following the author's public key.
+const SIGNATURE_ALGORITHM_SHIFT: u8 = 4;
+
+/// Signature scheme of a message.
+///
+/// The identifier of the scheme is stored in the high bits of the message class byte.
+/// Ed25519 has the zero identifier, so the messages signed with it have the same layout
+/// as before the introduction of the identifier. Other schemes can be added without
+/// changing the message format; the scheme used by each validator key is specified
+/// in [`ValidatorKeys`], so that the network can switch to a new scheme by a change
+/// of the configuration.
+///
+/// [`ValidatorKeys`]: ../blockchain/struct.ValidatorKeys.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureAlgorithm {
+    /// Ed25519 signature scheme.
+    Ed25519,
+}
+
+impl SignatureAlgorithm {
+    /// Returns the identifier of the scheme stored in the messages.
+    pub fn id(self) -> u8 {
+        match self {
+            SignatureAlgorithm::Ed25519 => 0,
+        }
+    }
+
+    /// Returns the scheme with the given identifier.
+    pub fn from_id(id: u8) -> Result<Self, Error> {
+        match id {
+            0 => Ok(SignatureAlgorithm::Ed25519),
+            _ => bail!("Unsupported signature algorithm {}", id),
+        }
+    }
+}
+
+impl Default for SignatureAlgorithm {
+    fn default() -> Self {
+        SignatureAlgorithm::Ed25519
+    }
+}
+
 /// `SignedMessage` can be constructed from a raw byte buffer which must have the following
 /// data layout:
 ///
-/// | Position  | Stored data             |
-/// | - - - - - | - - - - - - - - - - - - |
-/// | `0..32`   | author's public key     |
-/// | `32`      | message class           |
-/// | `33`      | message type            |
-/// | `34..N`   | payload                 |
-/// | `N..N+64` | signature               |
+/// | Position  | Stored data                                      |
+/// | - - - - - | - - - - - - - - - - - - - - - - - - - - - - - -  |
+/// | `0..32`   | author's public key                              |
+/// | `32`      | signature algorithm (4 high bits), network id    |
+/// |           | flag (bit 3), message class (3 low bits)         |
+/// | `33`      | message type                                     |
+/// | `34..38`  | network identifier, `u32`, if the flag is set    |
+/// | `34..N`   | payload, starting at `38` if the flag is set     |
+/// | `N..N+64` | signature                                        |
+///
+/// The network identifier binds the signature to a certain network, so the messages
+/// signed for one network are rejected by the nodes of another one. The identifier
+/// is omitted for the network with the zero identifier, so the messages of such
+/// a network have the same layout as before the introduction of the identifier.
 ///
 /// `SignedMessage` will verify the size of the buffer and the signature provided in it.
 /// This allows to keep the raw message buffer, but avoid verifying its signature again
@@ -32,14 +90,26 @@ impl SignedMessage {
     pub(crate) fn new(
         class: u8,
         tag: u8,
+        network_id: u32,
         value: &[u8],
         author: PublicKey,
         secret_key: &SecretKey,
     ) -> SignedMessage {
-        let mut buffer = Vec::with_capacity(2 + value.len() + PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH);
+        debug_assert_eq!(class & !MESSAGE_CLASS_MASK, 0);
+        let mut buffer = Vec::with_capacity(
+            2 + NETWORK_ID_LENGTH + value.len() + PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH,
+        );
         buffer.extend_from_slice(author.as_ref());
-        buffer.push(class);
-        buffer.push(tag);
+        if network_id == 0 {
+            buffer.push(class);
+            buffer.push(tag);
+        } else {
+            buffer.push(class | NETWORK_ID_FLAG);
+            buffer.push(tag);
+            let mut id = [0; NETWORK_ID_LENGTH];
+            LittleEndian::write_u32(&mut id, network_id);
+            buffer.extend_from_slice(&id);
+        }
         buffer.extend_from_slice(value);
         let signature = Self::sign(&buffer, secret_key).expect("Couldn't form signature");
         buffer.extend_from_slice(signature.as_ref());
@@ -73,10 +143,16 @@ impl SignedMessage {
             buffer.len()
         );
         let signed = SignedMessage { raw: buffer };
+        ensure!(
+            signed.raw.len() > EMPTY_SIGNED_MESSAGE_SIZE + signed.network_id_len(),
+            "Message too short to contain the network id, message_len = {}",
+            signed.raw.len()
+        );
+        let algorithm = SignatureAlgorithm::from_id(signed.signature_algorithm_id())?;
         let pk = signed.author();
         let signature = signed.signature();
 
-        Self::verify(signed.data_without_signature(), &signature, &pk)?;
+        Self::verify(algorithm, signed.data_without_signature(), &signature, &pk)?;
 
         Ok(signed)
     }
@@ -100,7 +176,39 @@ impl SignedMessage {
     /// Returns message class, which is an ID inside protocol.
     //TODO: revert privacy
     pub fn message_class(&self) -> u8 {
-        self.raw[PUBLIC_KEY_LENGTH]
+        self.raw[PUBLIC_KEY_LENGTH] & MESSAGE_CLASS_MASK
+    }
+
+    /// Returns the signature scheme of the message.
+    pub fn signature_algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::from_id(self.signature_algorithm_id())
+            .expect("Signature algorithm is checked on the message creation")
+    }
+
+    /// Returns the identifier of the signature scheme, which is not checked
+    /// for the messages created from unchecked buffers.
+    pub(in crate::messages) fn signature_algorithm_id(&self) -> u8 {
+        self.raw[PUBLIC_KEY_LENGTH] >> SIGNATURE_ALGORITHM_SHIFT
+    }
+
+    /// Returns the identifier of the network the message is signed for, or `0`
+    /// if the message does not contain the identifier.
+    pub fn network_id(&self) -> u32 {
+        if self.network_id_len() == 0 {
+            0
+        } else {
+            let start = PUBLIC_KEY_LENGTH + 2;
+            LittleEndian::read_u32(&self.raw[start..start + NETWORK_ID_LENGTH])
+        }
+    }
+
+    /// Returns the number of bytes occupied by the network identifier.
+    pub(in crate::messages) fn network_id_len(&self) -> usize {
+        if self.raw[PUBLIC_KEY_LENGTH] & NETWORK_ID_FLAG == 0 {
+            0
+        } else {
+            NETWORK_ID_LENGTH
+        }
     }
 
     /// Returns message type, which is an ID inside some class of messages.
@@ -112,7 +220,7 @@ impl SignedMessage {
     /// Returns serialized payload of the message.
     pub(in crate::messages) fn payload(&self) -> &[u8] {
         let sign_idx = self.raw.len() - SIGNATURE_LENGTH;
-        &self.raw[PUBLIC_KEY_LENGTH + 2..sign_idx]
+        &self.raw[PUBLIC_KEY_LENGTH + 2 + self.network_id_len()..sign_idx]
     }
 
     /// Returns ed25519 signature for this message.
@@ -140,11 +248,15 @@ impl SignedMessage {
 
     /// Verifies buffer integrity, and authenticate buffer.
     fn verify(
+        algorithm: SignatureAlgorithm,
         full_buffer: &[u8],
         signature: &Signature,
         public_key: &PublicKey,
     ) -> Result<(), Error> {
-        if !crypto::verify(signature, &full_buffer, &public_key) {
+        let is_valid = match algorithm {
+            SignatureAlgorithm::Ed25519 => crypto::verify(signature, &full_buffer, &public_key),
+        };
+        if !is_valid {
             bail!("Cannot verify message.");
         }
         Ok(())
@@ -0,0 +1,531 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Canonical encoding of transaction messages for client libraries.
+//!
+//! The signature of a transaction covers the exact bytes of the message, so clients
+//! must reproduce the encoding byte-for-byte. A signed transaction message has
+//! the following layout, with integers encoded in little-endian byte order:
+//!
+//! | Position   | Stored data                                        |
+//! | - - - - - -| - - - - - - - - - - - - - - - - - - - - - - - - - |
+//! | `0..32`    | public key of the author                           |
+//! | `32`       | message class, `0` for transactions; the 4 high    |
+//! |            | bits identify the signature scheme, `0` for Ed25519|
+//! | `33`       | message type, `0` for transactions                 |
+//! | `34..36`   | service identifier, `u16`                          |
+//! | `36..38`   | transaction identifier within the service, `u16`   |
+//! | `38..N`    | transaction payload serialized by the service      |
+//! | `N..N+64`  | Ed25519 signature of the bytes `0..N`              |
+//!
+//! Messages for a network with a non-zero identifier set bit 3 of the message class
+//! byte and contain the identifier, `u32`, right after the message type, which shifts
+//! the following fields by 4 bytes. The signature thus covers the network identifier,
+//! and the nodes of other networks reject the message.
+//!
+//! The transaction hash is the SHA-256 hash of the whole message, including
+//! the signature. The payload is opaque for the encoding; services serialize it
+//! with Protobuf.
+//!
+//! [`TransactionParts`] describes a message in JSON with binary fields encoded
+//! in hex, which allows clients to compare their encoding with the reference one
+//! step by step. Reference messages built with deterministic keys are returned
+//! by [`test_vectors`] and served by the `v1/test-vectors` endpoint of the public
+//! system API.
+//!
+//! Consensus messages share the layout of transactions up to the message type;
+//! their payload is the Protobuf encoding of the message. [`test_vector_suite`]
+//! complements the transactions with reference consensus messages, and the suite
+//! is written by the `gen-test-vectors` command of the node binary.
+//!
+//! [`TransactionParts`]: struct.TransactionParts.html
+//! [`test_vectors`]: fn.test_vectors.html
+//! [`test_vector_suite`]: fn.test_vector_suite.html
+
+use chrono::{TimeZone, Utc};
+use exonum_merkledb::BinaryValue;
+
+use std::borrow::Cow;
+
+use crate::crypto::{
+    self, gen_keypair_from_seed, Hash, PublicKey, SecretKey, Seed, Signature, SEED_LENGTH,
+    SIGNATURE_LENGTH,
+};
+
+use super::{
+    Connect, HexStringRepresentation, Message, Precommit, ProtocolMessage, RawTransaction,
+    ServiceTransaction, SignatureAlgorithm, Signed, SignedMessage, NETWORK_ID_FLAG,
+    RAW_TRANSACTION_EMPTY_SIZE,
+};
+use crate::helpers::{Height, Round, ValidatorId};
+
+/// Parts of a transaction message covered by the signature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionParts {
+    /// Public key of the transaction author.
+    pub author: PublicKey,
+    /// Identifier of the service.
+    pub service_id: u16,
+    /// Identifier of the transaction within the service.
+    pub transaction_id: u16,
+    /// Serialized transaction payload.
+    #[serde(with = "HexStringRepresentation")]
+    pub payload: Vec<u8>,
+    /// Identifier of the network the transaction is signed for. The identifier
+    /// is omitted if it is zero.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub network_id: u32,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+impl TransactionParts {
+    /// Returns the raw transaction formed by the parts.
+    pub fn raw_transaction(&self) -> RawTransaction {
+        RawTransaction::new(
+            self.service_id,
+            ServiceTransaction::from_raw_unchecked(self.transaction_id, self.payload.clone()),
+        )
+    }
+
+    /// Returns the bytes of the message covered by the signature.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let (class, tag) = RawTransaction::message_type();
+        let mut buffer = self.author.as_ref().to_vec();
+        if self.network_id == 0 {
+            buffer.push(class);
+            buffer.push(tag);
+        } else {
+            buffer.push(class | NETWORK_ID_FLAG);
+            buffer.push(tag);
+            buffer.extend_from_slice(&self.network_id.to_le_bytes());
+        }
+        buffer.extend_from_slice(&self.raw_transaction().into_bytes());
+        buffer
+    }
+
+    /// Signs the message with the secret key corresponding to the author key.
+    pub fn sign(&self, secret_key: &SecretKey) -> Signed<RawTransaction> {
+        Message::concrete_for_network(
+            self.raw_transaction(),
+            self.network_id,
+            self.author,
+            secret_key,
+        )
+    }
+}
+
+/// Reference transaction message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// Description of the case covered by the vector.
+    pub description: String,
+    /// Secret key of the author.
+    pub secret_key: SecretKey,
+    /// Parts of the message.
+    pub parts: TransactionParts,
+    /// Bytes covered by the signature.
+    #[serde(with = "HexStringRepresentation")]
+    pub signing_bytes: Vec<u8>,
+    /// Signature of the message.
+    pub signature: Signature,
+    /// Complete signed message.
+    #[serde(with = "HexStringRepresentation")]
+    pub message: Vec<u8>,
+    /// Hash of the message.
+    pub hash: Hash,
+}
+
+impl TestVector {
+    fn new(
+        description: &str,
+        seed: u8,
+        service_id: u16,
+        transaction_id: u16,
+        payload: Vec<u8>,
+    ) -> Self {
+        Self::for_network(description, seed, service_id, transaction_id, payload, 0)
+    }
+
+    fn for_network(
+        description: &str,
+        seed: u8,
+        service_id: u16,
+        transaction_id: u16,
+        payload: Vec<u8>,
+        network_id: u32,
+    ) -> Self {
+        let (author, secret_key) = gen_keypair_from_seed(&Seed::new([seed; SEED_LENGTH]));
+        let parts = TransactionParts {
+            author,
+            service_id,
+            transaction_id,
+            payload,
+            network_id,
+        };
+        let signed = parts.sign(&secret_key);
+        Self {
+            description: description.to_owned(),
+            secret_key,
+            signing_bytes: parts.signing_bytes(),
+            signature: signed.signed_message().signature(),
+            message: signed.signed_message().raw().to_vec(),
+            hash: signed.hash(),
+            parts,
+        }
+    }
+}
+
+/// Returns reference transaction messages. The messages are signed with the keys
+/// generated from fixed seeds, hence the vectors are the same on every node.
+pub fn test_vectors() -> Vec<TestVector> {
+    vec![
+        TestVector::new("Transaction with an empty payload", 1, 1, 0, Vec::new()),
+        TestVector::new(
+            "Transaction with a Protobuf payload containing the string \"abc\"",
+            2,
+            128,
+            1,
+            vec![0x0a, 0x03, 0x61, 0x62, 0x63],
+        ),
+        TestVector::new(
+            "Identifiers occupying both bytes and a payload with every byte value",
+            3,
+            0x1234,
+            0xabcd,
+            (0..=255).collect(),
+        ),
+        TestVector::for_network(
+            "Transaction signed for the network with the identifier 0x0badcafe",
+            4,
+            128,
+            1,
+            vec![0x0a, 0x03, 0x61, 0x62, 0x63],
+            0x0bad_cafe,
+        ),
+    ]
+}
+
+/// Reference consensus message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolTestVector {
+    /// Description of the case covered by the vector.
+    pub description: String,
+    /// Name of the message type.
+    pub message_name: String,
+    /// Message class.
+    pub message_class: u8,
+    /// Message type within the class.
+    pub message_type: u8,
+    /// Public key of the author.
+    pub author: PublicKey,
+    /// Secret key of the author.
+    pub secret_key: SecretKey,
+    /// Protobuf encoding of the message.
+    #[serde(with = "HexStringRepresentation")]
+    pub payload: Vec<u8>,
+    /// Bytes covered by the signature.
+    #[serde(with = "HexStringRepresentation")]
+    pub signing_bytes: Vec<u8>,
+    /// Signature of the message.
+    pub signature: Signature,
+    /// Complete signed message.
+    #[serde(with = "HexStringRepresentation")]
+    pub message: Vec<u8>,
+    /// Hash of the message.
+    pub hash: Hash,
+}
+
+impl ProtocolTestVector {
+    fn new<T: ProtocolMessage>(
+        description: &str,
+        message_name: &str,
+        seed: u8,
+        payload: T,
+    ) -> Self {
+        let (author, secret_key) = gen_keypair_from_seed(&Seed::new([seed; SEED_LENGTH]));
+        let (message_class, message_type) = T::message_type();
+        let encoded = payload.to_bytes();
+        let signed = Message::concrete(payload, author, &secret_key);
+        let message = signed.signed_message().raw().to_vec();
+        Self {
+            description: description.to_owned(),
+            message_name: message_name.to_owned(),
+            message_class,
+            message_type,
+            author,
+            secret_key,
+            payload: encoded,
+            signing_bytes: message[..message.len() - SIGNATURE_LENGTH].to_vec(),
+            signature: signed.signed_message().signature(),
+            hash: signed.hash(),
+            message,
+        }
+    }
+}
+
+/// Returns reference consensus messages signed with the keys generated from fixed seeds.
+pub fn protocol_test_vectors() -> Vec<ProtocolTestVector> {
+    let time = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+    vec![
+        ProtocolTestVector::new(
+            "Connect message of a node listening on 127.0.0.1:6333",
+            "Connect",
+            4,
+            Connect::new("127.0.0.1:6333", time, "exonum 0.12.0/rustc 1.36.0/Linux"),
+        ),
+        ProtocolTestVector::new(
+            "Precommit of the validator #1 at the height 5 in the round 2",
+            "Precommit",
+            5,
+            Precommit::new(
+                ValidatorId(1),
+                Height(5),
+                Round(2),
+                &crypto::hash(b"propose"),
+                &crypto::hash(b"block"),
+                time,
+            ),
+        ),
+    ]
+}
+
+/// Complete set of reference messages for client libraries.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVectorSuite {
+    /// Version of the Exonum core that has generated the suite.
+    pub core_version: String,
+    /// Reference transactions.
+    pub transactions: Vec<TestVector>,
+    /// Reference consensus messages.
+    pub protocol_messages: Vec<ProtocolTestVector>,
+}
+
+/// Returns the transactions of [`test_vectors`] together with the consensus messages
+/// of [`protocol_test_vectors`].
+///
+/// [`test_vectors`]: fn.test_vectors.html
+/// [`protocol_test_vectors`]: fn.protocol_test_vectors.html
+pub fn test_vector_suite() -> TestVectorSuite {
+    TestVectorSuite {
+        core_version: env!("CARGO_PKG_VERSION").to_owned(),
+        transactions: test_vectors(),
+        protocol_messages: protocol_test_vectors(),
+    }
+}
+
+/// Result of checking a transaction message produced by a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignatureCheck {
+    /// Hash of the message.
+    pub hash: Hash,
+    /// Parts of the message, if it is long enough to contain them.
+    pub parts: Option<TransactionParts>,
+    /// Signature contained in the message.
+    pub signature: Option<Signature>,
+    /// Whether the signature is valid for the message and its author.
+    pub signature_valid: bool,
+    /// Detected problems.
+    pub errors: Vec<String>,
+}
+
+impl SignatureCheck {
+    /// Returns `true` if no problems were detected.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Checks the layout and the signature of a transaction message without processing
+/// the transaction.
+pub fn check_transaction(message: &[u8]) -> SignatureCheck {
+    let mut check = SignatureCheck {
+        hash: crypto::hash(message),
+        parts: None,
+        signature: None,
+        signature_valid: false,
+        errors: Vec::new(),
+    };
+    if message.len() < RAW_TRANSACTION_EMPTY_SIZE {
+        check.errors.push(format!(
+            "Message is too short: {} bytes, expected at least {}",
+            message.len(),
+            RAW_TRANSACTION_EMPTY_SIZE
+        ));
+        return check;
+    }
+
+    let signed = SignedMessage::from_vec_unchecked(message.to_vec());
+    let min_len = RAW_TRANSACTION_EMPTY_SIZE + signed.network_id_len();
+    if message.len() < min_len {
+        check.errors.push(format!(
+            "Message with the network id is too short: {} bytes, expected at least {}",
+            message.len(),
+            min_len
+        ));
+        return check;
+    }
+    if let Err(e) = SignatureAlgorithm::from_id(signed.signature_algorithm_id()) {
+        check.errors.push(e.to_string());
+        return check;
+    }
+    let (class, tag) = RawTransaction::message_type();
+    if (signed.message_class(), signed.message_type()) != (class, tag) {
+        check.errors.push(format!(
+            "Message class {} and type {} do not denote a transaction, expected {} and {}",
+            signed.message_class(),
+            signed.message_type(),
+            class,
+            tag
+        ));
+    }
+
+    let raw = RawTransaction::from_bytes(Cow::Borrowed(signed.payload()))
+        .expect("Message length is checked above");
+    let service_id = raw.service_id();
+    let (transaction_id, payload) = raw.service_transaction().into_raw_parts();
+    let author = signed.author();
+    let signature = signed.signature();
+
+    let signing_bytes = &message[..message.len() - SIGNATURE_LENGTH];
+    check.signature_valid = crypto::verify(&signature, signing_bytes, &author);
+    if !check.signature_valid {
+        check.errors.push(format!(
+            "Signature does not correspond to the message and its author {}",
+            author.to_hex()
+        ));
+    }
+
+    check.parts = Some(TransactionParts {
+        author,
+        service_id,
+        transaction_id,
+        payload,
+        network_id: signed.network_id(),
+    });
+    check.signature = Some(signature);
+    check
+}
+
+/// Returns the hash by which the node identifies the transaction message. The message
+/// is checked the same way as on submission, so no hash is returned for a message
+/// the node would reject as malformed or wrongly signed.
+pub fn transaction_hash(message: &[u8]) -> Result<Hash, failure::Error> {
+    let message = Message::from_raw_buffer(message.to_vec())?;
+    let transaction = RawTransaction::try_from(message)
+        .map_err(|_| format_err!("The message is not a transaction"))?;
+    Ok(transaction.hash())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors_are_consistent() {
+        for vector in test_vectors() {
+            let message = SignedMessage::from_raw_buffer(vector.message.clone()).unwrap();
+            assert_eq!(message.hash(), vector.hash);
+            assert_eq!(
+                [&vector.signing_bytes[..], vector.signature.as_ref()].concat(),
+                vector.message
+            );
+            assert!(check_transaction(&vector.message).is_valid());
+        }
+    }
+
+    #[test]
+    fn protocol_test_vectors_are_consistent() {
+        for vector in protocol_test_vectors() {
+            let message = Message::from_raw_buffer(vector.message.clone()).unwrap();
+            assert_eq!(message.hash(), vector.hash);
+            assert_eq!(
+                [&vector.signing_bytes[..], vector.signature.as_ref()].concat(),
+                vector.message
+            );
+            assert_eq!(&vector.signing_bytes[..32], vector.author.as_ref());
+            assert_eq!(
+                &vector.signing_bytes[32..34],
+                &[vector.message_class, vector.message_type]
+            );
+            assert_eq!(&vector.signing_bytes[34..], &vector.payload[..]);
+        }
+    }
+
+    #[test]
+    fn test_vector_suite_is_deterministic() {
+        let suite = test_vector_suite();
+        assert_eq!(suite, test_vector_suite());
+        assert_eq!(suite.transactions, test_vectors());
+        let json = serde_json::to_string(&suite).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TestVectorSuite>(&json).unwrap(),
+            suite
+        );
+    }
+
+    #[test]
+    fn signing_bytes_layout() {
+        let vector = &test_vectors()[2];
+        let bytes = &vector.signing_bytes;
+        assert_eq!(&bytes[..32], vector.parts.author.as_ref());
+        assert_eq!(&bytes[32..38], &[0, 0, 0x34, 0x12, 0xcd, 0xab]);
+        assert_eq!(&bytes[38..], &vector.parts.payload[..]);
+
+        let vector = &test_vectors()[3];
+        let bytes = &vector.signing_bytes;
+        assert_eq!(&bytes[32..38], &[0x08, 0, 0xfe, 0xca, 0xad, 0x0b]);
+        assert_eq!(&bytes[38..42], &[0x80, 0, 0x01, 0]);
+        assert_eq!(&bytes[42..], &vector.parts.payload[..]);
+        let check = check_transaction(&vector.message);
+        assert_eq!(check.parts.as_ref(), Some(&vector.parts));
+        assert!(check.is_valid());
+    }
+
+    #[test]
+    fn invalid_messages_are_reported() {
+        let vector = &test_vectors()[1];
+
+        let mut tampered = vector.message.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let check = check_transaction(&tampered);
+        assert!(!check.signature_valid);
+        assert_eq!(check.parts.as_ref(), Some(&vector.parts));
+        assert_eq!(check.errors.len(), 1);
+
+        let mut precommit = vector.message.clone();
+        precommit[32] = 1;
+        let check = check_transaction(&precommit);
+        assert!(check.errors[0].contains("do not denote a transaction"));
+        assert!(!check.signature_valid);
+
+        let check = check_transaction(&vector.message[..RAW_TRANSACTION_EMPTY_SIZE - 1]);
+        assert!(check.parts.is_none());
+        assert!(check.errors[0].contains("too short"));
+    }
+
+    #[test]
+    fn transaction_hash_matches_vectors() {
+        for vector in test_vectors() {
+            assert_eq!(transaction_hash(&vector.message).unwrap(), vector.hash);
+        }
+
+        let mut tampered = test_vectors()[0].message.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(transaction_hash(&tampered).is_err());
+    }
+}
@@ -41,11 +41,24 @@ use std::{borrow::Cow, cmp::PartialEq, fmt, mem, ops::Deref};
 
 use crate::crypto::{hash, CryptoHash, Hash, PublicKey, Signature};
 
-pub(crate) use self::helpers::HexStringRepresentation;
-pub use self::{authorization::SignedMessage, helpers::to_hex_string, protocol::*};
+pub use self::{
+    authorization::{SignatureAlgorithm, SignedMessage},
+    canonical::{
+        check_transaction, protocol_test_vectors, test_vector_suite, test_vectors,
+        transaction_hash, ProtocolTestVector, SignatureCheck, TestVector, TestVectorSuite,
+        TransactionParts,
+    },
+    helpers::to_hex_string,
+    protocol::*,
+};
+pub(crate) use self::{
+    authorization::{NETWORK_ID_FLAG, NETWORK_ID_LENGTH},
+    helpers::HexStringRepresentation,
+};
 use exonum_merkledb::BinaryValue;
 
 mod authorization;
+mod canonical;
 mod helpers;
 mod protocol;
 #[cfg(test)]
@@ -212,6 +225,16 @@ impl<T: ProtocolMessage> Signed<T> {
     pub fn signature(&self) -> Signature {
         self.message.signature()
     }
+
+    /// Returns the signature scheme of the message.
+    pub fn signature_algorithm(&self) -> SignatureAlgorithm {
+        self.message.signature_algorithm()
+    }
+
+    /// Returns the identifier of the network the message is signed for.
+    pub fn network_id(&self) -> u32 {
+        self.message.network_id()
+    }
 }
 
 impl fmt::Debug for ServiceTransaction {
@@ -31,9 +31,11 @@ use chrono::{DateTime, Utc};
 
 use std::{borrow::Cow, fmt::Debug, mem};
 
-use super::{RawTransaction, ServiceTransaction, Signed, SignedMessage};
+use super::{RawTransaction, ServiceTransaction, SignatureAlgorithm, Signed, SignedMessage};
 use crate::blockchain;
-use crate::crypto::{CryptoHash, Hash, PublicKey, SecretKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use crate::crypto::{
+    self, CryptoHash, Hash, PublicKey, SecretKey, HASH_SIZE, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH,
+};
 use crate::helpers::{Height, Round, ValidatorId};
 use crate::proto;
 use exonum_merkledb::{BinaryValue, HashTag};
@@ -115,6 +117,10 @@ impl Connect {
 /// `Status` message is broadcast regularly with the timeout controlled by
 /// `blockchain::ConsensusConfig::status_timeout`. Also, it is broadcast
 /// after accepting a new block.
+///
+/// If `blockchain::ConsensusConfig::skip_lagging_proposers` is enabled, a validator
+/// lagging behind other nodes sets `skip_propose`, so that other nodes do not wait
+/// for its proposals for the whole round until it catches up.
 #[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug, ProtobufConvert)]
 #[exonum(pb = "proto::Status", crate = "crate")]
 pub struct Status {
@@ -124,6 +130,8 @@ pub struct Status {
     last_hash: Hash,
     /// Transactions pool size.
     pool_size: u64,
+    /// Whether the node asks to be skipped in the proposer rotation.
+    skip_propose: bool,
 }
 
 impl Status {
@@ -133,9 +141,16 @@ impl Status {
             height,
             last_hash: *last_hash,
             pool_size,
+            skip_propose: false,
         }
     }
 
+    /// Sets whether the node asks to be skipped in the proposer rotation.
+    pub fn with_skip_propose(mut self, skip_propose: bool) -> Self {
+        self.skip_propose = skip_propose;
+        self
+    }
+
     /// The height to which the message is related.
     pub fn height(&self) -> Height {
         self.height
@@ -150,6 +165,72 @@ impl Status {
     pub fn pool_size(&self) -> u64 {
         self.pool_size
     }
+
+    /// Whether the node asks to be skipped in the proposer rotation.
+    pub fn skip_propose(&self) -> bool {
+        self.skip_propose
+    }
+}
+
+/// Message sent by a service of one validator to the same service of another validator.
+///
+/// ### Validation
+/// The message is ignored if
+///     * it is addressed to another node
+///     * `validator` does not correspond to the consensus key of the author
+///       in the actual configuration
+///     * the service with `service_id` is not deployed on the node
+///
+/// ### Processing
+/// The payload is passed to the `handle_validator_message` hook of the service.
+///
+/// ### Generation
+/// The message is sent when a service calls `ServiceContext::send_to_validator`.
+/// The delivery is not guaranteed: the message is dropped if the recipient
+/// is not connected.
+#[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::ServiceMessage", crate = "crate")]
+pub struct ServiceMessage {
+    /// Public key of the recipient.
+    to: PublicKey,
+    /// The validator id of the sender.
+    validator: ValidatorId,
+    /// Identifier of the service.
+    service_id: u16,
+    /// Payload of the message, which is opaque for the core.
+    payload: Vec<u8>,
+}
+
+impl ServiceMessage {
+    /// Create new `ServiceMessage`.
+    pub fn new(to: &PublicKey, validator: ValidatorId, service_id: u16, payload: Vec<u8>) -> Self {
+        Self {
+            to: *to,
+            validator,
+            service_id,
+            payload,
+        }
+    }
+
+    /// Public key of the recipient.
+    pub fn to(&self) -> &PublicKey {
+        &self.to
+    }
+
+    /// The validator id of the sender.
+    pub fn validator(&self) -> ValidatorId {
+        self.validator
+    }
+
+    /// Identifier of the service.
+    pub fn service_id(&self) -> u16 {
+        self.service_id
+    }
+
+    /// Payload of the message.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
 }
 
 /// Proposal for a new block.
@@ -158,6 +239,8 @@ impl Status {
 /// The message is ignored if it
 ///     * contains incorrect `prev_hash`
 ///     * is sent by non-leader
+///     * does not contain the precommits for the previous block with the weight
+///       sufficient for a quorum, or contains invalid precommits
 ///     * contains already committed transactions
 ///     * is already known
 ///
@@ -165,10 +248,18 @@ impl Status {
 /// If the message contains unknown transactions, then `TransactionsRequest`
 /// is sent in reply.  Otherwise `Prevote` is broadcast.
 ///
+/// A compact proposal lists the short identifiers of the transactions instead
+/// of their hashes (see [`short_tx_id`]). The identifiers are resolved against
+/// the uncommitted transactions known to the node; if some of them cannot be resolved,
+/// a single `ShortTransactionsRequest` for all of them is sent in reply.
+///
 /// ### Generation
 /// A node broadcasts `Propose` if it is a leader and is not locked for a
 /// different proposal. Also `Propose` can be sent as response to
-/// `ProposeRequest`.
+/// `ProposeRequest`. The proposal is compact if `compact_proposals` is enabled
+/// in the consensus configuration.
+///
+/// [`short_tx_id`]: fn.short_tx_id.html
 #[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug, ProtobufConvert)]
 #[exonum(pb = "proto::Propose", crate = "crate")]
 pub struct Propose {
@@ -182,6 +273,12 @@ pub struct Propose {
     prev_hash: Hash,
     /// The list of transactions to include in the next block.
     transactions: Vec<Hash>,
+    /// Salt of the short identifiers of a compact proposal.
+    short_id_salt: u64,
+    /// Short identifiers of the transactions of a compact proposal.
+    short_ids: Vec<u64>,
+    /// Precommits for the previous block, which determine the consensus time of the block.
+    precommits: Vec<Vec<u8>>,
 }
 
 impl Propose {
@@ -192,6 +289,7 @@ impl Propose {
         round: Round,
         prev_hash: &Hash,
         transactions: &[Hash],
+        precommits: &[Signed<Precommit>],
     ) -> Self {
         Self {
             validator,
@@ -199,6 +297,35 @@ impl Propose {
             round,
             prev_hash: *prev_hash,
             transactions: transactions.to_vec(),
+            short_id_salt: 0,
+            short_ids: Vec::new(),
+            precommits: raw_precommits(precommits),
+        }
+    }
+
+    /// Creates a compact `Propose` message listing the short identifiers of the transactions
+    /// computed with the given salt.
+    pub fn compact(
+        validator: ValidatorId,
+        height: Height,
+        round: Round,
+        prev_hash: &Hash,
+        short_id_salt: u64,
+        transactions: &[Hash],
+        precommits: &[Signed<Precommit>],
+    ) -> Self {
+        Self {
+            validator,
+            height,
+            round,
+            prev_hash: *prev_hash,
+            transactions: Vec::new(),
+            short_id_salt,
+            short_ids: transactions
+                .iter()
+                .map(|tx_hash| short_tx_id(short_id_salt, tx_hash))
+                .collect(),
+            precommits: raw_precommits(precommits),
         }
     }
 
@@ -218,10 +345,48 @@ impl Propose {
     pub fn prev_hash(&self) -> &Hash {
         &self.prev_hash
     }
-    /// The list of transactions to include in the next block.
+    /// The list of transactions to include in the next block. Empty for compact proposals.
     pub fn transactions(&self) -> &[Hash] {
         &self.transactions
     }
+    /// Returns `true` if the proposal lists short identifiers of the transactions.
+    pub fn is_compact(&self) -> bool {
+        !self.short_ids.is_empty()
+    }
+    /// Salt of the short identifiers of a compact proposal.
+    pub fn short_id_salt(&self) -> u64 {
+        self.short_id_salt
+    }
+    /// Short identifiers of the transactions of a compact proposal.
+    pub fn short_ids(&self) -> &[u64] {
+        &self.short_ids
+    }
+    /// Precommits for the previous block. Empty for the block following the genesis block.
+    pub fn precommits(&self) -> Vec<Vec<u8>> {
+        self.precommits.clone()
+    }
+}
+
+fn raw_precommits(precommits: &[Signed<Precommit>]) -> Vec<Vec<u8>> {
+    precommits
+        .iter()
+        .map(|precommit| precommit.signed_message().raw().to_vec())
+        .collect()
+}
+
+/// Returns the short identifier of the transaction used in compact proposals, i.e.,
+/// the first 8 bytes of the hash of the salt and the transaction hash.
+///
+/// The salt is chosen by the proposer, so that the collisions of the identifiers
+/// cannot be prepared in advance.
+pub fn short_tx_id(salt: u64, tx_hash: &Hash) -> u64 {
+    let mut buffer = Vec::with_capacity(8 + HASH_SIZE);
+    buffer.extend_from_slice(&salt.to_le_bytes());
+    buffer.extend_from_slice(tx_hash.as_ref());
+    let digest = crypto::hash(&buffer);
+    let mut id = [0; 8];
+    id.copy_from_slice(&digest.as_ref()[..8]);
+    u64::from_le_bytes(id)
 }
 
 /// Pre-vote for a new block.
@@ -560,6 +725,37 @@ impl PoolTransactionsRequest {
     }
 }
 
+/// Request for the transactions of a compact proposal by their short identifiers.
+///
+/// ### Processing
+/// The requested transactions of the proposal are sent to the recipient
+/// in `TransactionsResponse` messages.
+///
+/// ### Generation
+/// This message is sent during `Propose` handling if the proposal is compact and
+/// some of its short identifiers cannot be resolved.
+#[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::ShortTransactionsRequest", crate = "crate")]
+pub struct ShortTransactionsRequest {
+    /// Public key of the recipient.
+    pub to: PublicKey,
+    /// Hash of the `Propose`.
+    pub propose_hash: Hash,
+    /// Short identifiers of the requested transactions.
+    pub short_ids: Vec<u64>,
+}
+
+impl ShortTransactionsRequest {
+    /// Create new `ShortTransactionsRequest`.
+    pub fn new(to: &PublicKey, propose_hash: &Hash, short_ids: &[u64]) -> Self {
+        Self {
+            to: *to,
+            propose_hash: *propose_hash,
+            short_ids: short_ids.to_vec(),
+        }
+    }
+}
+
 /// Request for pre-votes.
 ///
 /// ### Validation
@@ -826,6 +1022,8 @@ impl_protocol! {
             Connect = 1,
             /// `Status` information of other node.
             Status = 2,
+            /// Message between services of validators.
+            ServiceMessage = 3,
         },
         /// Exonum consensus specific node messages.
         1 => Consensus {
@@ -857,6 +1055,8 @@ impl_protocol! {
             BlockRequest = 4,
             /// Request of uncommitted transactions.
             PoolTransactionsRequest = 5,
+            /// Request of transactions of a compact propose.
+            ShortTransactionsRequest = 6,
         },
 
     }
@@ -886,10 +1086,28 @@ impl Message {
         message: T,
         author: PublicKey,
         secret_key: &SecretKey,
+    ) -> Signed<T> {
+        Self::concrete_for_network(message, 0, author, secret_key)
+    }
+
+    /// Creates new protocol message signed for the network with the given identifier.
+    /// Return concrete `Signed<T>`
+    ///
+    /// Nodes reject messages signed for other networks, so the identifier must match
+    /// the `network_id` of the genesis configuration of the network.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn concrete_for_network<T: ProtocolMessage>(
+        message: T,
+        network_id: u32,
+        author: PublicKey,
+        secret_key: &SecretKey,
     ) -> Signed<T> {
         let value = message.to_bytes();
         let (cls, typ) = T::message_type();
-        let signed = SignedMessage::new(cls, typ, &value, author, secret_key);
+        let signed = SignedMessage::new(cls, typ, network_id, &value, author, secret_key);
         T::into_message_from_parts(message, signed)
     }
 
@@ -910,12 +1128,34 @@ impl Message {
         public_key: PublicKey,
         secret_key: &SecretKey,
     ) -> Signed<RawTransaction>
+    where
+        T: Into<ServiceTransaction>,
+    {
+        Self::sign_transaction_for_network(transaction, service_id, 0, public_key, secret_key)
+    }
+
+    /// Creates a new raw transaction message signed for the network with the given
+    /// identifier.
+    ///
+    /// The transactions signed for a network with a non-zero identifier are rejected
+    /// by the nodes of other networks, even if the author keys match.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn sign_transaction_for_network<T>(
+        transaction: T,
+        service_id: u16,
+        network_id: u32,
+        public_key: PublicKey,
+        secret_key: &SecretKey,
+    ) -> Signed<RawTransaction>
     where
         T: Into<ServiceTransaction>,
     {
         let set: ServiceTransaction = transaction.into();
         let raw_tx = RawTransaction::new(service_id, set);
-        Self::concrete(raw_tx, public_key, secret_key)
+        Self::concrete_for_network(raw_tx, network_id, public_key, secret_key)
     }
 }
 
@@ -929,6 +1169,7 @@ impl Requests {
             Requests::PeersRequest(ref msg) => msg.to,
             Requests::BlockRequest(ref msg) => msg.to,
             Requests::PoolTransactionsRequest(ref msg) => msg.to,
+            Requests::ShortTransactionsRequest(ref msg) => msg.to,
         }
     }
 
@@ -941,6 +1182,7 @@ impl Requests {
             Requests::PeersRequest(ref msg) => msg.author(),
             Requests::BlockRequest(ref msg) => msg.author(),
             Requests::PoolTransactionsRequest(ref msg) => msg.author(),
+            Requests::ShortTransactionsRequest(ref msg) => msg.author(),
         }
     }
 }
@@ -955,6 +1197,15 @@ impl Consensus {
         }
     }
 
+    /// Returns the signature scheme of the message.
+    pub fn signature_algorithm(&self) -> SignatureAlgorithm {
+        match *self {
+            Consensus::Propose(ref msg) => msg.signature_algorithm(),
+            Consensus::Prevote(ref msg) => msg.signature_algorithm(),
+            Consensus::Precommit(ref msg) => msg.signature_algorithm(),
+        }
+    }
+
     /// Returns validator id of the message sender.
     pub fn validator(&self) -> ValidatorId {
         match *self {
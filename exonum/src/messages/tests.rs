@@ -5,7 +5,7 @@ use hex::FromHex;
 
 use super::{
     BinaryValue, BlockResponse, Message, Precommit, ProtocolMessage, RawTransaction,
-    ServiceTransaction, Signed, SignedMessage, Status, TransactionsResponse,
+    ServiceTransaction, SignatureAlgorithm, Signed, SignedMessage, Status, TransactionsResponse,
     RAW_TRANSACTION_EMPTY_SIZE, TRANSACTION_RESPONSE_EMPTY_SIZE,
 };
 use crate::blockchain::{Block, BlockProof};
@@ -233,3 +233,72 @@ fn test_raw_transaction_small_size() {
     assert_eq!(tx.service_id, 0);
     assert_eq!(tx.service_transaction.transaction_id, 0);
 }
+
+#[test]
+fn test_unsupported_signature_algorithm() {
+    use crate::crypto::{sign, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+
+    let (public_key, secret_key) = gen_keypair();
+    let tx = RawTransaction::new(0, ServiceTransaction::from_raw_unchecked(0, vec![1, 2, 3]));
+    let message = Message::concrete(tx, public_key, &secret_key);
+    assert_eq!(message.signature_algorithm(), SignatureAlgorithm::Ed25519);
+
+    // Mark the message as signed with an unknown scheme and re-sign it.
+    let mut raw = message.signed_message().raw().to_vec();
+    let signature_start = raw.len() - SIGNATURE_LENGTH;
+    raw[PUBLIC_KEY_LENGTH] |= 0xF0;
+    let signature = sign(&raw[..signature_start], &secret_key);
+    raw[signature_start..].copy_from_slice(signature.as_ref());
+
+    let err = SignedMessage::from_raw_buffer(raw.clone()).unwrap_err();
+    assert!(err.to_string().contains("Unsupported signature algorithm"));
+    let unchecked = SignedMessage::from_vec_unchecked(raw);
+    assert_eq!(
+        unchecked.message_class(),
+        message.signed_message().message_class()
+    );
+}
+
+#[test]
+fn test_network_id() {
+    use crate::crypto::{PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+
+    let (public_key, secret_key) = gen_keypair();
+    let tx = RawTransaction::new(5, ServiceTransaction::from_raw_unchecked(1, vec![1, 2, 3]));
+    let plain = Message::concrete(tx.clone(), public_key, &secret_key);
+    let message = Message::sign_transaction_for_network(
+        tx.clone().service_transaction(),
+        5,
+        42,
+        public_key,
+        &secret_key,
+    );
+    assert_eq!(plain.network_id(), 0);
+    assert_eq!(message.network_id(), 42);
+    assert_eq!(
+        message.signed_message().raw().len(),
+        plain.signed_message().raw().len() + 4
+    );
+
+    let raw = message.signed_message().raw().to_vec();
+    let parsed = Message::from_raw_buffer(raw.clone()).unwrap();
+    let parsed = RawTransaction::try_from(parsed).unwrap();
+    assert_eq!(parsed.network_id(), 42);
+    assert_eq!(*parsed.payload(), tx);
+    assert_eq!(
+        parsed.signed_message().message_class(),
+        plain.signed_message().message_class()
+    );
+
+    // The network identifier is covered by the signature.
+    let mut replayed = raw.clone();
+    replayed[PUBLIC_KEY_LENGTH + 2] = 43;
+    assert!(SignedMessage::from_raw_buffer(replayed).is_err());
+
+    // The flag requires the message to contain the identifier.
+    let mut truncated = plain.signed_message().raw()[..PUBLIC_KEY_LENGTH + 2].to_vec();
+    truncated[PUBLIC_KEY_LENGTH] |= 0x08;
+    truncated.extend_from_slice(&[0; SIGNATURE_LENGTH + 2]);
+    let err = SignedMessage::from_raw_buffer(truncated).unwrap_err();
+    assert!(err.to_string().contains("network id"));
+}
@@ -0,0 +1,461 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Built-in alerting on the metrics of the node.
+//!
+//! Alert rules are specified in the `alerts` section of the node configuration.
+//! Each rule compares a metric of the node with thresholds and is evaluated every
+//! time the node updates the state shared with the API, i.e., once per
+//! `state_update_timeout`. A rule starts firing when its condition becomes true
+//! and is resolved when the condition becomes false again. The actions of the rule
+//! are triggered only on these transitions, so that a lasting problem does not
+//! flood the log or the webhooks.
+//!
+//! The firing alerts are available via the private `v1/alerts` endpoint. The rules
+//! with the `healthcheck` action are also listed in the public health check.
+
+use chrono::{DateTime, Utc};
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    thread,
+    time::SystemTime,
+};
+
+use crate::api::node::public::author_watchers::{parse_url, post};
+use crate::helpers::Milliseconds;
+
+/// Metric of the node checked by an alert rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Number of the uncommitted transactions known to the node.
+    PoolSize,
+    /// Time since the commit of the latest block, in milliseconds.
+    TimeSinceLastBlock,
+    /// Number of the connected peers.
+    Peers,
+    /// Number of the transactions rejected by the node during the last minute.
+    RejectedTransactions,
+    /// Fraction of the API requests failed since the previous evaluation, from 0 to 1.
+    ApiErrorRate,
+}
+
+/// Action triggered when an alert rule starts firing or is resolved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertAction {
+    /// Logs a warning when the rule starts firing and a message when it is resolved.
+    Log,
+    /// Sends the [`AlertNotification`] as a JSON `POST` request to the URL.
+    /// Only `http` URLs are supported; failed deliveries are logged but not retried.
+    ///
+    /// [`AlertNotification`]: struct.AlertNotification.html
+    Webhook {
+        /// URL of the webhook.
+        url: String,
+    },
+    /// Lists the rule in the public health check while it is firing.
+    Healthcheck,
+}
+
+/// Alert rule of the node configuration.
+///
+/// The rule fires if the value of the metric is greater than `above` or less
+/// than `below`. At least one of the thresholds must be specified.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Unique name of the rule.
+    pub name: String,
+    /// Checked metric.
+    pub metric: AlertMetric,
+    /// Upper threshold of the metric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub above: Option<f64>,
+    /// Lower threshold of the metric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub below: Option<f64>,
+    /// Actions triggered by the rule.
+    #[serde(default = "default_actions")]
+    pub actions: Vec<AlertAction>,
+}
+
+fn default_actions() -> Vec<AlertAction> {
+    vec![AlertAction::Log]
+}
+
+impl AlertRule {
+    /// Checks that the rule has a threshold and that its webhooks are supported.
+    pub fn validate(&self) -> Result<(), failure::Error> {
+        ensure!(
+            self.above.is_some() || self.below.is_some(),
+            "Alert rule {} has no threshold",
+            self.name
+        );
+        for action in &self.actions {
+            if let AlertAction::Webhook { ref url } = action {
+                parse_url(url)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_triggered(&self, value: f64) -> bool {
+        self.above.map_or(false, |above| value > above)
+            || self.below.map_or(false, |below| value < below)
+    }
+
+    fn degrades_health(&self) -> bool {
+        self.actions.contains(&AlertAction::Healthcheck)
+    }
+}
+
+/// Checks the alert rules of the node configuration.
+pub fn validate_alert_rules(rules: &[AlertRule]) -> Result<(), failure::Error> {
+    let mut names = HashSet::new();
+    for rule in rules {
+        ensure!(
+            names.insert(rule.name.as_str()),
+            "Duplicate alert rule {}",
+            rule.name
+        );
+        rule.validate()?;
+    }
+    Ok(())
+}
+
+/// Values of the metrics checked by the alert rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeMetrics {
+    /// Number of the uncommitted transactions.
+    pub pool_size: u64,
+    /// Time since the commit of the latest block.
+    pub time_since_last_block: Milliseconds,
+    /// Number of the connected peers.
+    pub peers: usize,
+    /// Number of the transactions rejected during the last minute.
+    pub rejected_transactions: usize,
+    /// Fraction of the failed API requests.
+    pub api_error_rate: f64,
+}
+
+impl NodeMetrics {
+    fn value(&self, metric: AlertMetric) -> f64 {
+        match metric {
+            AlertMetric::PoolSize => self.pool_size as f64,
+            AlertMetric::TimeSinceLastBlock => self.time_since_last_block as f64,
+            AlertMetric::Peers => self.peers as f64,
+            AlertMetric::RejectedTransactions => self.rejected_transactions as f64,
+            AlertMetric::ApiErrorRate => self.api_error_rate,
+        }
+    }
+}
+
+/// State of an alert reported by the notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    /// The rule has started firing.
+    Firing,
+    /// The rule has stopped firing.
+    Resolved,
+}
+
+/// Notification sent to the webhooks of an alert rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertNotification {
+    /// Name of the rule.
+    pub rule: String,
+    /// Checked metric.
+    pub metric: AlertMetric,
+    /// Value of the metric.
+    pub value: f64,
+    /// New state of the alert.
+    pub state: AlertState,
+    /// Time of the transition.
+    pub time: DateTime<Utc>,
+}
+
+/// Alert which is currently firing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FiringAlert {
+    /// Name of the rule.
+    pub rule: String,
+    /// Checked metric.
+    pub metric: AlertMetric,
+    /// Latest value of the metric.
+    pub value: f64,
+    /// Time when the rule has started firing.
+    pub since: DateTime<Utc>,
+    /// Whether the rule is listed in the health check.
+    pub degrades_health: bool,
+}
+
+/// Alerts firing on the node, shared with the API.
+///
+/// The list is cheaply cloneable; all clones share the same alerts.
+#[derive(Debug, Clone, Default)]
+pub struct FiringAlerts {
+    alerts: Arc<Mutex<Vec<FiringAlert>>>,
+}
+
+impl FiringAlerts {
+    /// Returns the firing alerts in the order of their rules.
+    pub fn alerts(&self) -> Vec<FiringAlert> {
+        self.alerts.lock().expect("Expected mutex lock").clone()
+    }
+
+    /// Returns the names of the firing rules listed in the health check.
+    pub fn degraded_by(&self) -> Vec<String> {
+        let alerts = self.alerts.lock().expect("Expected mutex lock");
+        alerts
+            .iter()
+            .filter(|alert| alert.degrades_health)
+            .map(|alert| alert.rule.clone())
+            .collect()
+    }
+}
+
+/// Evaluates the alert rules and triggers their actions.
+#[derive(Debug)]
+pub(crate) struct AlertEngine {
+    rules: Vec<AlertRule>,
+    firing: FiringAlerts,
+    // Total numbers of the API requests and the failed ones at the previous evaluation.
+    api_requests: (u64, u64),
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>, firing: FiringAlerts) -> Self {
+        Self {
+            rules,
+            firing,
+            api_requests: (0, 0),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns the fraction of the API requests failed since the previous call
+    /// given the total numbers of the handled and failed requests.
+    pub fn api_error_rate(&mut self, requests: u64, errors: u64) -> f64 {
+        let (prev_requests, prev_errors) = self.api_requests;
+        self.api_requests = (requests, errors);
+        let requests = requests.saturating_sub(prev_requests);
+        if requests == 0 {
+            0.0
+        } else {
+            errors.saturating_sub(prev_errors) as f64 / requests as f64
+        }
+    }
+
+    /// Evaluates the rules and returns the notifications about the changed alerts,
+    /// triggering the actions of the rules.
+    pub fn evaluate(&mut self, metrics: &NodeMetrics, now: SystemTime) -> Vec<AlertNotification> {
+        let mut notifications = Vec::new();
+        let mut firing = self.firing.alerts.lock().expect("Expected mutex lock");
+        let mut updated = Vec::with_capacity(firing.len());
+        for rule in &self.rules {
+            let value = metrics.value(rule.metric);
+            let alert = firing.iter().find(|alert| alert.rule == rule.name);
+            let state = match (rule.is_triggered(value), alert) {
+                (true, Some(alert)) => {
+                    updated.push(FiringAlert {
+                        value,
+                        ..alert.clone()
+                    });
+                    continue;
+                }
+                (true, None) => {
+                    updated.push(FiringAlert {
+                        rule: rule.name.clone(),
+                        metric: rule.metric,
+                        value,
+                        since: now.into(),
+                        degrades_health: rule.degrades_health(),
+                    });
+                    AlertState::Firing
+                }
+                (false, Some(_)) => AlertState::Resolved,
+                (false, None) => continue,
+            };
+
+            let notification = AlertNotification {
+                rule: rule.name.clone(),
+                metric: rule.metric,
+                value,
+                state,
+                time: now.into(),
+            };
+            trigger_actions(rule, &notification);
+            notifications.push(notification);
+        }
+        *firing = updated;
+        notifications
+    }
+}
+
+fn trigger_actions(rule: &AlertRule, notification: &AlertNotification) {
+    for action in &rule.actions {
+        match action {
+            AlertAction::Log => match notification.state {
+                AlertState::Firing => warn!(
+                    "Alert {} is firing: {:?} is {}",
+                    rule.name, rule.metric, notification.value
+                ),
+                AlertState::Resolved => info!(
+                    "Alert {} is resolved: {:?} is {}",
+                    rule.name, rule.metric, notification.value
+                ),
+            },
+            AlertAction::Webhook { url } => {
+                let url = url.clone();
+                let body =
+                    serde_json::to_string(notification).expect("Cannot serialize notification");
+                let spawned = thread::Builder::new()
+                    .name("alert-webhook".to_owned())
+                    .spawn(move || {
+                        if let Err(e) = post(&url, &body) {
+                            warn!("Cannot deliver alert to webhook {}: {}", url, e);
+                        }
+                    });
+                if let Err(e) = spawned {
+                    error!("Cannot spawn alert webhook delivery thread: {}", e);
+                }
+            }
+            AlertAction::Healthcheck => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, metric: AlertMetric, above: Option<f64>, below: Option<f64>) -> AlertRule {
+        AlertRule {
+            name: name.to_owned(),
+            metric,
+            above,
+            below,
+            actions: vec![AlertAction::Healthcheck],
+        }
+    }
+
+    #[test]
+    fn rules_fire_and_resolve_on_transitions() {
+        let firing = FiringAlerts::default();
+        let mut engine = AlertEngine::new(
+            vec![
+                rule("pool", AlertMetric::PoolSize, Some(100.0), None),
+                rule("peers", AlertMetric::Peers, None, Some(2.0)),
+            ],
+            firing.clone(),
+        );
+        let now = SystemTime::now();
+        let mut metrics = NodeMetrics {
+            pool_size: 10,
+            peers: 3,
+            ..NodeMetrics::default()
+        };
+        assert!(engine.evaluate(&metrics, now).is_empty());
+
+        metrics.pool_size = 200;
+        metrics.peers = 1;
+        let notifications = engine.evaluate(&metrics, now);
+        assert_eq!(notifications.len(), 2);
+        assert!(notifications
+            .iter()
+            .all(|notification| notification.state == AlertState::Firing));
+        assert_eq!(firing.degraded_by(), vec!["pool", "peers"]);
+
+        // Lasting alerts are not notified again, but their values are updated.
+        metrics.pool_size = 300;
+        assert!(engine.evaluate(&metrics, now).is_empty());
+        assert_eq!(firing.alerts()[0].value as u64, 300);
+
+        metrics.pool_size = 0;
+        let notifications = engine.evaluate(&metrics, now);
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].rule, "pool");
+        assert_eq!(notifications[0].state, AlertState::Resolved);
+        assert_eq!(firing.degraded_by(), vec!["peers"]);
+    }
+
+    #[test]
+    fn api_error_rate_is_computed_between_evaluations() {
+        let mut engine = AlertEngine::new(Vec::new(), FiringAlerts::default());
+        let mut percent =
+            |requests, errors| (engine.api_error_rate(requests, errors) * 100.0) as u64;
+        assert_eq!(percent(0, 0), 0);
+        assert_eq!(percent(10, 5), 50);
+        assert_eq!(percent(20, 5), 0);
+        assert_eq!(percent(20, 6), 0);
+        assert_eq!(percent(24, 7), 25);
+    }
+
+    #[test]
+    fn rules_are_validated() {
+        let mut rules = vec![rule("pool", AlertMetric::PoolSize, Some(100.0), None)];
+        assert!(validate_alert_rules(&rules).is_ok());
+
+        rules.push(rule("pool", AlertMetric::Peers, None, Some(1.0)));
+        assert!(validate_alert_rules(&rules).is_err());
+
+        rules[1].name = "peers".to_owned();
+        rules[1].below = None;
+        assert!(validate_alert_rules(&rules).is_err());
+
+        rules[1].below = Some(1.0);
+        rules[1].actions = vec![AlertAction::Webhook {
+            url: "https://example.com/alerts".to_owned(),
+        }];
+        assert!(validate_alert_rules(&rules).is_err());
+    }
+
+    #[test]
+    fn rules_are_read_from_toml() {
+        let rules: Vec<AlertRule> = toml::from_str::<toml::Value>(
+            r#"
+            [[alerts]]
+            name = "stalled"
+            metric = "time_since_last_block"
+            above = 60000.0
+            actions = [{ type = "log" }, { type = "webhook", url = "http://127.0.0.1/alerts" }]
+
+            [[alerts]]
+            name = "isolated"
+            metric = "peers"
+            below = 1.0
+            "#,
+        )
+        .unwrap()["alerts"]
+            .clone()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(rules[0].metric, AlertMetric::TimeSinceLastBlock);
+        assert_eq!(
+            rules[0].actions[1],
+            AlertAction::Webhook {
+                url: "http://127.0.0.1/alerts".to_owned()
+            }
+        );
+        assert_eq!(rules[1].actions, vec![AlertAction::Log]);
+        assert!(validate_alert_rules(&rules).is_ok());
+    }
+}
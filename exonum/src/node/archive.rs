@@ -0,0 +1,306 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Archive of the consensus messages observed by the node.
+//!
+//! In the archive mode, the node stores every `Propose`, `Prevote` and `Precommit`
+//! it creates or receives, including the messages of the rounds that have not led
+//! to a block, so that the round failures of staging networks can be analyzed after
+//! the fact. Only the quorum of precommits is kept for the committed blocks otherwise.
+//!
+//! The messages are stored in the `core.consensus_archive` table, which RocksDB keeps
+//! in a separate column family, and are not a part of the state hash. The archive
+//! is meant for research and debugging: the node writes to the database on every
+//! consensus message, so the archive is disabled by default. The messages are
+//! served by the private `v1/consensus_archive` endpoint of the system API.
+
+use exonum_merkledb::IndexAccess;
+
+use super::NodeHandler;
+use crate::blockchain::{Blockchain, Schema};
+use crate::crypto::{CryptoHash, Hash};
+use crate::helpers::{Height, Round, ValidatorId};
+use crate::messages::{to_hex_string, Consensus as ConsensusMessage, Message};
+
+/// Default number of the latest heights whose messages are kept in the archive.
+pub const DEFAULT_ARCHIVE_MAX_HEIGHTS: u64 = 1_000;
+/// Default maximum number of the messages archived for a single height.
+pub const DEFAULT_ARCHIVE_MAX_MESSAGES_PER_HEIGHT: u64 = 10_000;
+
+/// Retention limits of the consensus archive.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ConsensusArchiveConfig {
+    /// Number of the latest heights whose messages are kept. The messages of older
+    /// heights are removed after each commit.
+    #[serde(default = "default_max_heights")]
+    pub max_heights: u64,
+    /// Maximum number of the messages archived for a single height. The messages
+    /// exceeding the limit are not archived, which bounds the size of the archive
+    /// if a height takes many rounds or a peer floods the node with messages.
+    #[serde(default = "default_max_messages_per_height")]
+    pub max_messages_per_height: u64,
+}
+
+fn default_max_heights() -> u64 {
+    DEFAULT_ARCHIVE_MAX_HEIGHTS
+}
+
+fn default_max_messages_per_height() -> u64 {
+    DEFAULT_ARCHIVE_MAX_MESSAGES_PER_HEIGHT
+}
+
+impl Default for ConsensusArchiveConfig {
+    fn default() -> Self {
+        Self {
+            max_heights: DEFAULT_ARCHIVE_MAX_HEIGHTS,
+            max_messages_per_height: DEFAULT_ARCHIVE_MAX_MESSAGES_PER_HEIGHT,
+        }
+    }
+}
+
+/// Type of an archived consensus message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchivedMessageKind {
+    /// `Propose` message.
+    Propose,
+    /// `Prevote` message.
+    Prevote,
+    /// `Precommit` message.
+    Precommit,
+}
+
+/// Consensus message stored in the archive.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedMessage {
+    /// Round of the message.
+    pub round: Round,
+    /// Type of the message.
+    pub kind: ArchivedMessageKind,
+    /// Validator that has signed the message.
+    pub validator: ValidatorId,
+    /// Hash of the message.
+    pub hash: Hash,
+    /// Hex-encoded signed message.
+    pub message: String,
+}
+
+/// Returns the consensus messages archived for the given height, ordered by round,
+/// type and validator.
+pub fn archived_messages<T: IndexAccess>(
+    schema: &Schema<T>,
+    height: Height,
+) -> Vec<ArchivedMessage> {
+    let mut messages = schema
+        .consensus_archive(height)
+        .iter()
+        .filter_map(|(hash, message)| {
+            let (kind, msg) = match message {
+                Message::Consensus(msg) => (kind_of(&msg), msg),
+                _ => return None,
+            };
+            Some(ArchivedMessage {
+                round: msg.round(),
+                kind,
+                validator: msg.validator(),
+                hash,
+                message: message_hex(&msg),
+            })
+        })
+        .collect::<Vec<_>>();
+    messages.sort_by_key(|m| (m.round, m.kind, m.validator));
+    messages
+}
+
+fn kind_of(message: &ConsensusMessage) -> ArchivedMessageKind {
+    match message {
+        ConsensusMessage::Propose(_) => ArchivedMessageKind::Propose,
+        ConsensusMessage::Prevote(_) => ArchivedMessageKind::Prevote,
+        ConsensusMessage::Precommit(_) => ArchivedMessageKind::Precommit,
+    }
+}
+
+fn message_hex(message: &ConsensusMessage) -> String {
+    match message {
+        ConsensusMessage::Propose(msg) => to_hex_string(msg),
+        ConsensusMessage::Prevote(msg) => to_hex_string(msg),
+        ConsensusMessage::Precommit(msg) => to_hex_string(msg),
+    }
+}
+
+/// Writer of the consensus archive.
+#[derive(Debug)]
+pub(crate) struct ConsensusArchive {
+    config: ConsensusArchiveConfig,
+}
+
+impl ConsensusArchive {
+    pub fn new(config: ConsensusArchiveConfig) -> Self {
+        Self { config }
+    }
+
+    /// Stores the message unless it has already been archived or the limit
+    /// of the messages for its height is reached.
+    pub fn record(&self, blockchain: &mut Blockchain, message: ConsensusMessage) {
+        let height = message.height();
+        let message = Message::Consensus(message);
+        let hash = message.hash();
+
+        let fork = blockchain.fork();
+        {
+            let schema = Schema::new(&fork);
+            let mut counts = schema.consensus_archive_heights();
+            let count = counts.get(&height.0).unwrap_or_default();
+            if count >= self.config.max_messages_per_height {
+                return;
+            }
+            let mut messages = schema.consensus_archive(height);
+            if messages.contains(&hash) {
+                return;
+            }
+            messages.put(&hash, message);
+            counts.put(&height.0, count + 1);
+        }
+        blockchain
+            .merge(fork.into_patch())
+            .expect("Unable to save message to the consensus archive");
+    }
+
+    /// Removes the messages of the heights which are not among the `max_heights`
+    /// latest ones, counting the given current height.
+    pub fn prune(&self, blockchain: &mut Blockchain, height: Height) {
+        let first_kept = height.next().0.saturating_sub(self.config.max_heights);
+        let fork = blockchain.fork();
+        {
+            let schema = Schema::new(&fork);
+            let mut counts = schema.consensus_archive_heights();
+            let stale = counts
+                .keys()
+                .take_while(|&archived| archived < first_kept)
+                .collect::<Vec<_>>();
+            if stale.is_empty() {
+                return;
+            }
+            for archived in stale {
+                schema.consensus_archive(Height(archived)).clear();
+                counts.remove(&archived);
+            }
+        }
+        blockchain
+            .merge(fork.into_patch())
+            .expect("Unable to prune the consensus archive");
+    }
+}
+
+impl NodeHandler {
+    /// Stores the consensus message in the archive if the archive is enabled.
+    pub(crate) fn archive_message(&mut self, message: &ConsensusMessage) {
+        if let Some(ref archive) = self.consensus_archive {
+            archive.record(&mut self.blockchain, message.clone());
+        }
+    }
+
+    /// Removes the stale messages from the archive if the archive is enabled.
+    pub(crate) fn prune_archive(&mut self, height: Height) {
+        if let Some(ref archive) = self.consensus_archive {
+            archive.prune(&mut self.blockchain, height);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::gen_keypair;
+    use crate::messages::{Precommit, Prevote};
+
+    fn create_blockchain() -> Blockchain {
+        crate::blockchain::tests::create_blockchain(Vec::new())
+    }
+
+    fn prevote(validator: u16, height: Height, round: Round) -> ConsensusMessage {
+        let (public_key, secret_key) = gen_keypair();
+        ConsensusMessage::Prevote(Message::concrete(
+            Prevote::new(
+                ValidatorId(validator),
+                height,
+                round,
+                &Hash::zero(),
+                Round::zero(),
+            ),
+            public_key,
+            &secret_key,
+        ))
+    }
+
+    #[test]
+    fn messages_are_archived_within_limits() {
+        let mut blockchain = create_blockchain();
+        let archive = ConsensusArchive::new(ConsensusArchiveConfig {
+            max_heights: 2,
+            max_messages_per_height: 3,
+        });
+
+        let first = prevote(1, Height(1), Round(2));
+        archive.record(&mut blockchain, first.clone());
+        // Duplicates are not archived.
+        archive.record(&mut blockchain, first);
+        archive.record(&mut blockchain, prevote(0, Height(1), Round(1)));
+        let (public_key, secret_key) = gen_keypair();
+        let precommit = Message::concrete(
+            Precommit::new(
+                ValidatorId(0),
+                Height(1),
+                Round(1),
+                &Hash::zero(),
+                &Hash::zero(),
+                chrono::Utc::now(),
+            ),
+            public_key,
+            &secret_key,
+        );
+        archive.record(&mut blockchain, ConsensusMessage::Precommit(precommit));
+        // The limit for the height is reached.
+        archive.record(&mut blockchain, prevote(2, Height(1), Round(3)));
+        archive.record(&mut blockchain, prevote(2, Height(2), Round(1)));
+
+        let snapshot = blockchain.snapshot();
+        let messages = archived_messages(&Schema::new(&snapshot), Height(1));
+        let summary = messages
+            .iter()
+            .map(|m| (m.round, m.kind, m.validator))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (Round(1), ArchivedMessageKind::Prevote, ValidatorId(0)),
+                (Round(1), ArchivedMessageKind::Precommit, ValidatorId(0)),
+                (Round(2), ArchivedMessageKind::Prevote, ValidatorId(1)),
+            ]
+        );
+
+        archive.prune(&mut blockchain, Height(3));
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(archived_messages(&schema, Height(1)).is_empty());
+        assert_eq!(archived_messages(&schema, Height(2)).len(), 1);
+        assert_eq!(
+            schema
+                .consensus_archive_heights()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+}
@@ -0,0 +1,580 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statistics of the block assembly recorded by the proposer.
+//!
+//! Each time the node proposes a block, it records how many transactions were
+//! available, how many of them were included, why the rest were left out and how long
+//! the included transactions waited since the node received them. The statistics are
+//! reported to metrics and the latest records are served by the private
+//! `v1/block_assembly` endpoint of the system API.
+//!
+//! The module also provides the filters used by the proposer to keep transactions sharing
+//! a conflict key out of the same block, to keep the block within the size limits and
+//! to limit the number of transactions of a single author in the block.
+
+use std::{
+    cell::{Cell, RefCell},
+    cmp,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use super::PriorityLaneConfig;
+use crate::blockchain::ConsensusConfig;
+use crate::crypto::PublicKey;
+use crate::helpers::{Height, Milliseconds, Round};
+
+/// Number of the latest block assembly records kept for the API.
+pub const BLOCK_ASSEMBLY_LOG_SIZE: usize = 128;
+
+/// Statistics of a block proposed by this node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockAssemblyStats {
+    /// Height of the proposed block.
+    pub height: Height,
+    /// Round of the propose.
+    pub round: Round,
+    /// Number of uncommitted transactions known to the node.
+    pub candidates: usize,
+    /// Number of transactions included into the propose.
+    pub included: usize,
+    /// Number of candidates left out of the propose by reason.
+    pub skipped: SkippedTransactions,
+    /// Time spent in the pool by the included transactions. Absent if the receive
+    /// time is unknown for all of them, e.g., they were received before a restart.
+    pub pool_wait: Option<PoolWaitStats>,
+}
+
+/// Number of transactions left out of a propose, by reason.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SkippedTransactions {
+    /// Transactions not fitting into the `txs_block_limit` of the consensus configuration.
+    pub block_limit: usize,
+    /// Regular transactions displaced by the slots reserved for the priority lane.
+    pub priority_lane: usize,
+    /// Transactions postponed because the propose already contains a transaction
+    /// with the same conflict key.
+    #[serde(default)]
+    pub conflicts: usize,
+    /// Transactions not fitting into the `max_block_size` or `max_block_gas` limits
+    /// of the consensus configuration.
+    #[serde(default)]
+    pub size_limit: usize,
+    /// Transactions exceeding the `max_block_txs` limit of their author.
+    #[serde(default)]
+    pub author_quota: usize,
+}
+
+/// Time spent in the pool by the transactions of a propose.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PoolWaitStats {
+    /// Number of transactions with the known receive time.
+    pub measured: usize,
+    /// Minimum wait time.
+    pub min: Milliseconds,
+    /// Mean wait time.
+    pub mean: Milliseconds,
+    /// Maximum wait time.
+    pub max: Milliseconds,
+}
+
+impl BlockAssemblyStats {
+    /// Calculates the statistics of a propose including `included` of `candidates`
+    /// transactions, of which `priority_candidates` belong to the priority lane.
+    pub(crate) fn new<I>(
+        height: Height,
+        round: Round,
+        lane: &PriorityLaneConfig,
+        candidates: usize,
+        priority_candidates: usize,
+        included: usize,
+        waits: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = Duration>,
+    {
+        // Mirrors `PriorityLaneConfig::select_txs`: the reserved slots taken by priority
+        // transactions would have been used by the regular ones otherwise.
+        let reserved = cmp::min(lane.reserved_txs as usize, priority_candidates);
+        let reserved = cmp::min(reserved, included);
+        let regular = candidates.saturating_sub(priority_candidates);
+        let regular_included = cmp::min(regular, included.saturating_sub(reserved));
+        let priority_lane = cmp::min(regular - regular_included, reserved);
+        let skipped = SkippedTransactions {
+            block_limit: candidates - included - priority_lane,
+            priority_lane,
+            conflicts: 0,
+            size_limit: 0,
+            author_quota: 0,
+        };
+
+        Self {
+            height,
+            round,
+            candidates,
+            included,
+            skipped,
+            pool_wait: PoolWaitStats::new(waits),
+        }
+    }
+
+    /// Accounts for the candidates postponed because of their conflict keys. Such candidates
+    /// are otherwise counted as not fitting into the block.
+    pub(crate) fn with_conflicts(mut self, conflicts: usize) -> Self {
+        let conflicts = cmp::min(conflicts, self.skipped.block_limit);
+        self.skipped.block_limit -= conflicts;
+        self.skipped.conflicts = conflicts;
+        self
+    }
+
+    /// Accounts for the candidates left out because of the block size limits. Such
+    /// candidates are otherwise counted as not fitting into the block.
+    pub(crate) fn with_size_limit(mut self, skipped: usize) -> Self {
+        let skipped = cmp::min(skipped, self.skipped.block_limit);
+        self.skipped.block_limit -= skipped;
+        self.skipped.size_limit = skipped;
+        self
+    }
+
+    /// Accounts for the candidates left out because of the quotas of their authors. Such
+    /// candidates are otherwise counted as not fitting into the block.
+    pub(crate) fn with_author_quota(mut self, skipped: usize) -> Self {
+        let skipped = cmp::min(skipped, self.skipped.block_limit);
+        self.skipped.block_limit -= skipped;
+        self.skipped.author_quota = skipped;
+        self
+    }
+
+    /// Reports the statistics to metrics.
+    pub(crate) fn report_metrics(&self) {
+        metric!("node.block_assembly.candidates", self.candidates);
+        metric!("node.block_assembly.included", self.included);
+        metric!(
+            "node.block_assembly.skipped.block_limit",
+            self.skipped.block_limit
+        );
+        metric!(
+            "node.block_assembly.skipped.priority_lane",
+            self.skipped.priority_lane
+        );
+        metric!(
+            "node.block_assembly.skipped.conflicts",
+            self.skipped.conflicts
+        );
+        metric!(
+            "node.block_assembly.skipped.size_limit",
+            self.skipped.size_limit
+        );
+        metric!(
+            "node.block_assembly.skipped.author_quota",
+            self.skipped.author_quota
+        );
+        if let Some(wait) = self.pool_wait {
+            metric!("node.block_assembly.pool_wait.mean", wait.mean);
+            metric!("node.block_assembly.pool_wait.max", wait.max);
+        }
+    }
+}
+
+impl PoolWaitStats {
+    fn new<I: IntoIterator<Item = Duration>>(waits: I) -> Option<Self> {
+        let mut stats: Option<Self> = None;
+        let mut total: Milliseconds = 0;
+        for wait in waits {
+            let wait = wait.as_secs() * 1000 + Milliseconds::from(wait.subsec_millis());
+            total += wait;
+            stats = Some(match stats {
+                Some(stats) => Self {
+                    measured: stats.measured + 1,
+                    min: cmp::min(stats.min, wait),
+                    max: cmp::max(stats.max, wait),
+                    ..stats
+                },
+                None => Self {
+                    measured: 1,
+                    min: wait,
+                    mean: 0,
+                    max: wait,
+                },
+            });
+        }
+        stats.map(|stats| Self {
+            mean: total / stats.measured as Milliseconds,
+            ..stats
+        })
+    }
+}
+
+/// Filter admitting at most one transaction per conflict key into a propose.
+#[derive(Debug, Default)]
+pub(crate) struct ConflictFilter {
+    taken_keys: RefCell<HashSet<String>>,
+    postponed: Cell<usize>,
+}
+
+impl ConflictFilter {
+    /// Checks if a transaction with the given conflict keys can be included into
+    /// the propose, that is, none of its keys is taken by the included transactions.
+    pub(crate) fn admits(&self, keys: &[String]) -> bool {
+        let taken_keys = self.taken_keys.borrow();
+        !keys.iter().any(|key| taken_keys.contains(key))
+    }
+
+    /// Takes the conflict keys of a transaction included into the propose, so
+    /// the subsequent transactions sharing any of them are postponed.
+    pub(crate) fn take(&self, keys: &[String]) {
+        self.taken_keys.borrow_mut().extend(keys.iter().cloned());
+    }
+
+    /// Records a transaction postponed because of its conflict keys.
+    pub(crate) fn postpone(&self) {
+        self.postponed.set(self.postponed.get() + 1);
+    }
+
+    /// Returns the number of postponed transactions.
+    pub(crate) fn postponed(&self) -> usize {
+        self.postponed.get()
+    }
+}
+
+/// Filter keeping the total size of the transaction messages in a propose within
+/// the block limits.
+///
+/// The size of a message is also the gas charged for it before the execution, so
+/// the size is limited by both `max_block_size` and `max_block_gas`.
+#[derive(Debug)]
+pub(crate) struct SizeFilter {
+    limit: Option<u64>,
+    size: Cell<u64>,
+    skipped: Cell<usize>,
+}
+
+impl SizeFilter {
+    pub(crate) fn new(config: &ConsensusConfig) -> Self {
+        let limit = [config.max_block_size, config.max_block_gas]
+            .iter()
+            .cloned()
+            .filter(|&limit| limit > 0)
+            .min();
+        Self {
+            limit,
+            size: Cell::new(0),
+            skipped: Cell::new(0),
+        }
+    }
+
+    /// Checks if a transaction with the message of the given size fits into the propose.
+    pub(crate) fn admits(&self, size: u64) -> bool {
+        let total = self.size.get() + size;
+        self.limit.map_or(true, |limit| total <= limit)
+    }
+
+    /// Adds the size of a transaction included into the propose to the size of the propose.
+    pub(crate) fn take(&self, size: u64) {
+        self.size.set(self.size.get() + size);
+    }
+
+    /// Records a transaction skipped because it does not fit into the propose.
+    pub(crate) fn skip(&self) {
+        self.skipped.set(self.skipped.get() + 1);
+    }
+
+    /// Returns the number of skipped transactions.
+    pub(crate) fn skipped(&self) -> usize {
+        self.skipped.get()
+    }
+}
+
+/// Filter admitting at most `max_block_txs` transactions of each author into a propose.
+#[derive(Debug)]
+pub(crate) struct AuthorQuotaFilter {
+    limit: Option<usize>,
+    taken: RefCell<HashMap<PublicKey, usize>>,
+    throttled: RefCell<HashSet<PublicKey>>,
+    skipped: Cell<usize>,
+}
+
+impl AuthorQuotaFilter {
+    pub(crate) fn new(limit: Option<u32>) -> Self {
+        Self {
+            limit: limit.map(|limit| limit as usize),
+            taken: RefCell::new(HashMap::new()),
+            throttled: RefCell::new(HashSet::new()),
+            skipped: Cell::new(0),
+        }
+    }
+
+    /// Checks if a transaction of the given author can be included into the propose.
+    pub(crate) fn admits(&self, author: &PublicKey) -> bool {
+        self.limit.map_or(true, |limit| {
+            self.taken.borrow().get(author).map_or(0, |&count| count) < limit
+        })
+    }
+
+    /// Counts a transaction included into the propose against the quota of its author.
+    pub(crate) fn take(&self, author: &PublicKey) {
+        if self.limit.is_some() {
+            *self.taken.borrow_mut().entry(*author).or_insert(0) += 1;
+        }
+    }
+
+    /// Records a transaction skipped because its author has exhausted the quota.
+    pub(crate) fn skip(&self, author: &PublicKey) {
+        self.throttled.borrow_mut().insert(*author);
+        self.skipped.set(self.skipped.get() + 1);
+    }
+
+    /// Returns the number of skipped transactions.
+    pub(crate) fn skipped(&self) -> usize {
+        self.skipped.get()
+    }
+
+    /// Returns the number of authors whose transactions have been skipped.
+    pub(crate) fn throttled_authors(&self) -> usize {
+        self.throttled.borrow().len()
+    }
+}
+
+/// Latest block assembly statistics shared with the API.
+///
+/// The log is cheaply cloneable; all clones share the same records.
+#[derive(Debug, Clone, Default)]
+pub struct BlockAssemblyLog {
+    records: Arc<Mutex<VecDeque<BlockAssemblyStats>>>,
+}
+
+impl BlockAssemblyLog {
+    /// Returns the recorded statistics, oldest first.
+    pub fn records(&self) -> Vec<BlockAssemblyStats> {
+        let records = self.records.lock().expect("Expected mutex lock");
+        records.iter().cloned().collect()
+    }
+
+    pub(crate) fn push(&self, stats: BlockAssemblyStats) {
+        let mut records = self.records.lock().expect("Expected mutex lock");
+        if records.len() == BLOCK_ASSEMBLY_LOG_SIZE {
+            records.pop_front();
+        }
+        records.push_back(stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::gen_keypair;
+
+    fn lane(reserved_txs: u32) -> PriorityLaneConfig {
+        PriorityLaneConfig {
+            services: vec![1].into_iter().collect(),
+            reserved_txs,
+        }
+    }
+
+    fn skipped(
+        lane: &PriorityLaneConfig,
+        candidates: usize,
+        priority: usize,
+        included: usize,
+    ) -> SkippedTransactions {
+        BlockAssemblyStats::new(
+            Height(1),
+            Round(1),
+            lane,
+            candidates,
+            priority,
+            included,
+            vec![],
+        )
+        .skipped
+    }
+
+    #[test]
+    fn skipped_transactions() {
+        // Everything fits into the block.
+        assert_eq!(skipped(&lane(2), 5, 1, 5), SkippedTransactions::default());
+        // Without priority transactions the regular ones are limited by the block size only.
+        assert_eq!(
+            skipped(&lane(2), 10, 0, 5),
+            SkippedTransactions {
+                block_limit: 5,
+                priority_lane: 0,
+                conflicts: 0,
+                size_limit: 0,
+                author_quota: 0,
+            }
+        );
+        // Two regular transactions are displaced by the reserved slots.
+        assert_eq!(
+            skipped(&lane(2), 10, 3, 5),
+            SkippedTransactions {
+                block_limit: 3,
+                priority_lane: 2,
+                conflicts: 0,
+                size_limit: 0,
+                author_quota: 0,
+            }
+        );
+        // Unused block space is taken by the priority transactions beyond the reserve.
+        assert_eq!(skipped(&lane(1), 6, 4, 6), SkippedTransactions::default());
+    }
+
+    #[test]
+    fn conflicting_transactions_are_postponed() {
+        let keys = |keys: &[&str]| keys.iter().map(|&key| key.to_owned()).collect::<Vec<_>>();
+        let filter = ConflictFilter::default();
+        let admit = |keys: &[String]| {
+            if filter.admits(keys) {
+                filter.take(keys);
+                true
+            } else {
+                filter.postpone();
+                false
+            }
+        };
+        assert!(admit(&keys(&["wallet:alice", "wallet:bob"])));
+        assert!(admit(&[]));
+        assert!(!admit(&keys(&["wallet:bob"])));
+        assert!(admit(&keys(&["wallet:carol"])));
+        assert!(!admit(&keys(&["wallet:carol", "wallet:dave"])));
+        // The keys of a postponed transaction are not taken.
+        assert!(admit(&keys(&["wallet:dave"])));
+        assert_eq!(filter.postponed(), 2);
+        // Checking the keys does not take them.
+        assert!(filter.admits(&keys(&["wallet:erin"])));
+        assert!(filter.admits(&keys(&["wallet:erin"])));
+
+        let stats = BlockAssemblyStats::new(Height(1), Round(1), &lane(0), 10, 0, 4, vec![])
+            .with_conflicts(filter.postponed());
+        assert_eq!(
+            stats.skipped,
+            SkippedTransactions {
+                block_limit: 4,
+                priority_lane: 0,
+                conflicts: 2,
+                size_limit: 0,
+                author_quota: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn transactions_exceeding_block_size_are_skipped() {
+        let config = ConsensusConfig {
+            max_block_size: 100,
+            max_block_gas: 80,
+            ..ConsensusConfig::default()
+        };
+        let filter = SizeFilter::new(&config);
+        let admit = |size| {
+            if filter.admits(size) {
+                filter.take(size);
+                true
+            } else {
+                filter.skip();
+                false
+            }
+        };
+        assert!(admit(50));
+        assert!(!admit(40));
+        // Checking the size does not add it to the propose.
+        assert!(filter.admits(30));
+        assert!(admit(30));
+        assert!(!admit(1));
+        assert_eq!(filter.skipped(), 2);
+
+        let stats = BlockAssemblyStats::new(Height(1), Round(1), &lane(0), 10, 0, 2, vec![])
+            .with_size_limit(filter.skipped());
+        assert_eq!(stats.skipped.block_limit, 6);
+        assert_eq!(stats.skipped.size_limit, 2);
+    }
+
+    #[test]
+    fn transactions_exceeding_author_quota_are_skipped() {
+        let (alice, bob) = (gen_keypair().0, gen_keypair().0);
+        let admit = |filter: &AuthorQuotaFilter, author: &PublicKey| {
+            if filter.admits(author) {
+                filter.take(author);
+                true
+            } else {
+                filter.skip(author);
+                false
+            }
+        };
+        let filter = AuthorQuotaFilter::new(Some(2));
+        assert!(admit(&filter, &alice));
+        // Checking the quota does not count against it.
+        assert!(filter.admits(&bob));
+        assert!(filter.admits(&bob));
+        assert!(admit(&filter, &bob));
+        assert!(admit(&filter, &alice));
+        assert!(!admit(&filter, &alice));
+        assert!(admit(&filter, &bob));
+        assert!(!admit(&filter, &alice));
+        assert_eq!(filter.skipped(), 2);
+        assert_eq!(filter.throttled_authors(), 1);
+
+        let stats = BlockAssemblyStats::new(Height(1), Round(1), &lane(0), 10, 0, 4, vec![])
+            .with_author_quota(filter.skipped());
+        assert_eq!(stats.skipped.block_limit, 4);
+        assert_eq!(stats.skipped.author_quota, 2);
+
+        let unlimited = AuthorQuotaFilter::new(None);
+        assert!((0..10).all(|_| admit(&unlimited, &alice)));
+        assert_eq!(unlimited.throttled_authors(), 0);
+    }
+
+    #[test]
+    fn pool_wait_stats() {
+        let waits = vec![
+            Duration::from_millis(10),
+            Duration::from_millis(30),
+            Duration::from_secs(2),
+        ];
+        let stats = BlockAssemblyStats::new(Height(1), Round(1), &lane(0), 3, 0, 3, waits);
+        assert_eq!(
+            stats.pool_wait,
+            Some(PoolWaitStats {
+                measured: 3,
+                min: 10,
+                mean: 680,
+                max: 2000,
+            })
+        );
+
+        let stats = BlockAssemblyStats::new(Height(1), Round(1), &lane(0), 3, 0, 3, vec![]);
+        assert_eq!(stats.pool_wait, None);
+    }
+
+    #[test]
+    fn log_is_bounded() {
+        let log = BlockAssemblyLog::default();
+        for height in 0..BLOCK_ASSEMBLY_LOG_SIZE as u64 + 2 {
+            log.push(BlockAssemblyStats::new(
+                Height(height),
+                Round(1),
+                &lane(0),
+                0,
+                0,
+                0,
+                vec![],
+            ));
+        }
+        let records = log.records();
+        assert_eq!(records.len(), BLOCK_ASSEMBLY_LOG_SIZE);
+        assert_eq!(records[0].height, Height(2));
+    }
+}
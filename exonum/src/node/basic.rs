@@ -14,24 +14,54 @@
 
 use rand::Rng;
 
-use super::{NodeHandler, NodeRole, RequestData};
+use std::time::{Duration, SystemTime};
+
+use super::{NodeHandler, NodeMetrics, NodeRole, RequestData, TransactionSource};
+use crate::blockchain::ServiceContext;
 use crate::crypto::PublicKey;
 use crate::events::error::LogError;
 use crate::events::network::ConnectedPeerAddr;
-use crate::helpers::Height;
-use crate::messages::{Connect, Message, PeersRequest, Responses, Service, Signed, Status};
+use crate::helpers::{Height, ValidatorId};
+use crate::messages::{
+    Connect, Consensus as ConsensusMessage, Message, PeersRequest, Precommit, Responses, Service,
+    ServiceMessage, Signed, Status,
+};
 
 impl NodeHandler {
     /// Redirects message to the corresponding `handle_...` function.
     pub fn handle_message(&mut self, msg: Message) {
+        // Transactions are checked in `handle_tx`, so that their rejections are recorded.
+        let is_transaction = match msg {
+            Message::Service(Service::RawTransaction(_)) => true,
+            _ => false,
+        };
+        let network_id = msg.signed_message().network_id();
+        if network_id != self.network_id && !is_transaction {
+            error!(
+                "Received message signed for the network {}, expected {}",
+                network_id, self.network_id
+            );
+            return;
+        }
+
         match msg {
-            Message::Consensus(msg) => self.handle_consensus(msg),
+            Message::Consensus(msg) => {
+                if let ConsensusMessage::Precommit(ref precommit) = msg {
+                    self.record_clock_skew(precommit);
+                }
+                self.archive_message(&msg);
+                self.handle_consensus(msg)
+            }
             Message::Requests(ref msg) => self.handle_request(msg),
 
             Message::Service(Service::Connect(msg)) => self.handle_connect(msg),
             Message::Service(Service::Status(msg)) => self.handle_status(&msg),
             // ignore tx duplication error,
-            Message::Service(Service::RawTransaction(msg)) => drop(self.handle_tx(msg)),
+            Message::Service(Service::RawTransaction(msg)) => match self.handle_tx(msg.clone()) {
+                Ok(()) => self.gossip_transaction(msg, true),
+                Err(e) => self.record_rejected_tx(&e, TransactionSource::Network),
+            },
+            Message::Service(Service::ServiceMessage(msg)) => self.handle_service_message(&msg),
             Message::Responses(Responses::BlockResponse(msg)) => {
                 self.handle_block(&msg).log_error()
             }
@@ -41,6 +71,42 @@ impl NodeHandler {
         }
     }
 
+    /// Compares the time of a `Precommit` for the current height with the local time.
+    ///
+    /// Precommits are sampled on receipt rather than in `handle_consensus`, since queued
+    /// messages are handled again later and their times would appear stale.
+    fn record_clock_skew(&mut self, precommit: &Signed<Precommit>) {
+        let author = precommit.author();
+        if precommit.height() != self.state.height()
+            || author == *self.state.consensus_public_key()
+            || self.state.find_validator(author) != Some(precommit.validator())
+        {
+            return;
+        }
+
+        let warn_skew = self.clock.warn_skew;
+        let exceeds =
+            |estimate: Option<i64>| estimate.map_or(false, |e| e.abs() as u64 > warn_skew);
+        let local_time = self.system_state.current_time();
+        let skew = self.state.clock_skew_mut();
+        let was_skewed = exceeds(skew.estimate());
+        skew.add_sample(author, SystemTime::from(precommit.time()), local_time);
+        let estimate = skew.estimate();
+        if let Some(estimate) = estimate {
+            metric!("node.clock_skew", estimate);
+        }
+
+        // Only the changes are logged, since the estimate is updated on each precommit.
+        match (was_skewed, exceeds(estimate)) {
+            (false, true) => warn!(
+                "Local clock differs from the clocks of the validators by {} ms",
+                estimate.unwrap()
+            ),
+            (true, false) => info!("Local clock is in sync with the clocks of the validators"),
+            _ => {}
+        }
+    }
+
     /// Handles the `Connected` event. Node's `Connect` message is sent as response
     /// if received `Connect` message is correct.
     pub fn handle_connected(&mut self, address: &ConnectedPeerAddr, connect: Signed<Connect>) {
@@ -76,6 +142,83 @@ impl NodeHandler {
         }
     }
 
+    /// Signs the message of the given service and sends it to the validator
+    /// with the given identifier.
+    pub fn send_service_message(&mut self, to: ValidatorId, service_id: u16, payload: Vec<u8>) {
+        let validator = match self.state.validator_id() {
+            Some(validator) => validator,
+            None => {
+                error!(
+                    "Service {} cannot send messages, since the node is not a validator",
+                    service_id
+                );
+                return;
+            }
+        };
+        let recipient = match self.state.consensus_public_key_of(to) {
+            Some(recipient) => recipient,
+            None => {
+                error!(
+                    "Service {} sends message to unknown validator {}",
+                    service_id, to
+                );
+                return;
+            }
+        };
+
+        let message = self.sign_message(ServiceMessage::new(
+            &recipient, validator, service_id, payload,
+        ));
+        if to == validator {
+            self.handle_service_message(&message);
+        } else {
+            self.send_to_peer(recipient, message);
+        }
+    }
+
+    /// Handles the `ServiceMessage` by passing it to the corresponding service.
+    pub fn handle_service_message(&mut self, message: &Signed<ServiceMessage>) {
+        if message.to() != self.state.consensus_public_key() {
+            error!(
+                "Received service message addressed to another node: {:?}",
+                message
+            );
+            return;
+        }
+        let from = message.validator();
+        if self.state.consensus_public_key_of(from) != Some(message.author()) {
+            error!("Received service message from non-validator: {:?}", message);
+            return;
+        }
+
+        let service_id = message.service_id();
+        let service = match self.blockchain.service_map().get(&service_id) {
+            Some(service) => service,
+            None => {
+                warn!("Received message for unknown service {}", service_id);
+                return;
+            }
+        };
+        if !self
+            .blockchain
+            .is_service_active(self.blockchain.snapshot().as_ref(), service_id)
+        {
+            warn!(
+                "Received message for undeployed or inactive service {}",
+                service_id
+            );
+            return;
+        }
+        let context = ServiceContext::new(
+            self.blockchain.service_keypair.0,
+            self.blockchain.service_keypair.1.clone(),
+            self.blockchain.api_sender.clone(),
+            self.blockchain.fork(),
+            service_id,
+        );
+        service.handle_validator_message(&context, from, message.payload());
+    }
+
     /// Handles the `Connect` message and connects to a peer as result.
     pub fn handle_connect(&mut self, message: Signed<Connect>) {
         // TODO Add spam protection (ECR-170)
@@ -158,6 +301,13 @@ impl NodeHandler {
         }
 
         let peer = msg.author();
+        let round = self.state.round();
+        let leader_was_lagging = self.state.is_leader_lagging(round);
+        self.state.set_node_lagging(peer, msg.skip_propose());
+        if !leader_was_lagging && self.state.is_leader_lagging(round) {
+            // Shorten the current round, which has been scheduled in full.
+            self.add_round_timeout();
+        }
 
         // Handle message from future height
         if msg.height() > height {
@@ -227,14 +377,62 @@ impl NodeHandler {
     pub fn handle_update_api_state_timeout(&mut self) {
         self.api_state.update_node_state(&self.state);
         self.node_role = NodeRole::new(self.state.validator_id());
+        self.check_alerts();
+        self.check_resources();
         self.add_update_api_state_timeout();
     }
 
+    /// Measures the resources used by the node and shares the result with the API.
+    fn check_resources(&mut self) {
+        let usage = self.resources.check();
+        self.api_state.set_resource_usage(usage);
+    }
+
+    /// Evaluates the alert rules on the current metrics of the node.
+    fn check_alerts(&mut self) {
+        if self.alerts.is_empty() {
+            return;
+        }
+
+        let now = self.system_state.current_time();
+        let since_last_block = now
+            .duration_since(self.state.height_start_time())
+            .unwrap_or_else(|_| Duration::new(0, 0));
+        let (requests, errors) =
+            self.api_state.api_metrics().histograms().values().fold(
+                (0, 0),
+                |(requests, errors), histogram| {
+                    (requests + histogram.count, errors + histogram.errors)
+                },
+            );
+        let metrics = NodeMetrics {
+            pool_size: self.uncommitted_txs_count(),
+            time_since_last_block: since_last_block.as_secs() * 1000
+                + u64::from(since_last_block.subsec_millis()),
+            peers: self.state.connections().len(),
+            rejected_transactions: self
+                .api_state
+                .rejected_transactions()
+                .count_since(now.checked_sub(Duration::from_secs(60)).unwrap_or(now)),
+            api_error_rate: self.alerts.api_error_rate(requests, errors),
+        };
+        self.alerts.evaluate(&metrics, now);
+    }
+
     /// Broadcasts the `Status` message to all peers.
     pub fn broadcast_status(&mut self) {
         let hash = self.blockchain.last_hash();
         let pool_size = self.uncommitted_txs_count();
-        let status = Status::new(self.state.height(), &hash, pool_size);
+        let skip_propose = self.state.consensus_config().skip_lagging_proposers
+            && self.state.is_validator()
+            && self.state.is_lagging();
+        let status =
+            Status::new(self.state.height(), &hash, pool_size).with_skip_propose(skip_propose);
+        self.send_status(status);
+    }
+
+    /// Signs the `Status` message and broadcasts it to all peers.
+    pub(crate) fn send_status(&mut self, status: Status) {
         trace!("Broadcast status: {:?}", status);
 
         let message = self.sign_message(status);
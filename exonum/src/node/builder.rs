@@ -0,0 +1,353 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Programmatic assembly of custom node binaries.
+//!
+//! Unlike `helpers::fabric::NodeBuilder`, which creates the node from the command
+//! line arguments and the configuration files, the builder in this module takes
+//! all the parts of the node from the code.
+
+use exonum_merkledb::Database;
+
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use super::{ApiSender, ExternalMessage, Node, NodeApiConfig, NodeConfig};
+use crate::blockchain::{BlockchainBuildError, Service};
+use crate::crypto::{self, PublicKey, SecretKey};
+
+/// Error of the node construction.
+#[derive(Debug, Fail, PartialEq)]
+pub enum NodeBuildError {
+    /// The node configuration is not specified.
+    #[fail(display = "Node configuration is not specified")]
+    MissingConfig,
+    /// The database is not specified.
+    #[fail(display = "Database is not specified")]
+    MissingDatabase,
+    /// The node configuration is inconsistent.
+    #[fail(display = "Invalid node configuration: {}", _0)]
+    InvalidConfig(String),
+    /// The services cannot be registered in the blockchain.
+    #[fail(display = "{}", _0)]
+    Blockchain(#[cause] BlockchainBuildError),
+    /// The node cannot be started with the given storage, e.g., the startup checks have failed.
+    #[fail(display = "{}", _0)]
+    Startup(String),
+    /// The thread running the node cannot be spawned.
+    #[fail(display = "Cannot start the node thread: {}", _0)]
+    Thread(String),
+}
+
+/// Keys the node signs its messages with.
+#[derive(Clone)]
+pub struct NodeKeys {
+    /// Keypair the node signs the consensus messages with.
+    pub consensus: (PublicKey, SecretKey),
+    /// Keypair the node signs the service transactions with.
+    pub service: (PublicKey, SecretKey),
+}
+
+impl NodeKeys {
+    /// Creates the keys from the given keypairs.
+    pub fn new(consensus: (PublicKey, SecretKey), service: (PublicKey, SecretKey)) -> Self {
+        Self { consensus, service }
+    }
+
+    /// Generates random keys.
+    pub fn random() -> Self {
+        Self::new(crypto::gen_keypair(), crypto::gen_keypair())
+    }
+}
+
+impl fmt::Debug for NodeKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeKeys")
+            .field("consensus_public_key", &self.consensus.0)
+            .field("service_public_key", &self.service.0)
+            .finish()
+    }
+}
+
+/// Builder of a node from its parts, which allows to create custom node binaries
+/// without the command line interface of `helpers::fabric`.
+///
+/// The configuration and the database are required; the API options and the keys,
+/// if specified, replace the corresponding parts of the configuration.
+///
+/// # Examples
+///
+/// ```no_run
+/// use exonum::helpers;
+/// use exonum::node::NodeBuilder;
+/// use exonum_merkledb::TemporaryDB;
+///
+/// # fn main() -> Result<(), failure::Error> {
+/// let config = helpers::generate_testnet_config(1, 2000).remove(0);
+/// let handle = NodeBuilder::new()
+///     .with_database(TemporaryDB::new())
+///     .with_config(config)
+///     .run()?;
+/// // Send transactions with `handle.api_sender()`...
+/// handle.shutdown()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct NodeBuilder {
+    database: Option<Arc<dyn Database>>,
+    services: Vec<Box<dyn Service>>,
+    config: Option<NodeConfig>,
+    api_options: Option<NodeApiConfig>,
+    keys: Option<NodeKeys>,
+    config_file_path: Option<String>,
+}
+
+impl fmt::Debug for NodeBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeBuilder")
+            .field("services", &self.services.len())
+            .field("api_options", &self.api_options)
+            .field("keys", &self.keys)
+            .finish()
+    }
+}
+
+impl NodeBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the database of the node.
+    pub fn with_database<D: Into<Arc<dyn Database>>>(mut self, database: D) -> Self {
+        self.database = Some(database.into());
+        self
+    }
+
+    /// Adds a service to the node.
+    pub fn with_service(mut self, service: Box<dyn Service>) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Adds services to the node.
+    pub fn with_services<I>(mut self, services: I) -> Self
+    where
+        I: IntoIterator<Item = Box<dyn Service>>,
+    {
+        self.services.extend(services);
+        self
+    }
+
+    /// Sets the node configuration.
+    pub fn with_config(mut self, config: NodeConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Sets the path to the configuration file, which is updated by the node
+    /// when the configuration changes, e.g., when peers are added.
+    pub fn with_config_file(mut self, path: impl Into<String>) -> Self {
+        self.config_file_path = Some(path.into());
+        self
+    }
+
+    /// Replaces the API options of the node configuration.
+    pub fn with_api_options(mut self, api_options: NodeApiConfig) -> Self {
+        self.api_options = Some(api_options);
+        self
+    }
+
+    /// Replaces the keys of the node configuration.
+    pub fn with_signer(mut self, keys: NodeKeys) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    /// Creates the node without starting it. The configuration is checked against
+    /// the storage before the node is created; see [`Node::try_new`].
+    ///
+    /// [`Node::try_new`]: struct.Node.html#method.try_new
+    pub fn build(self) -> Result<Node, NodeBuildError> {
+        let mut config = self.config.ok_or(NodeBuildError::MissingConfig)?;
+        let database = self.database.ok_or(NodeBuildError::MissingDatabase)?;
+        if let Some(api_options) = self.api_options {
+            config.api = api_options;
+        }
+        if let Some(keys) = self.keys {
+            let NodeKeys { consensus, service } = keys;
+            config.consensus_public_key = consensus.0;
+            config.consensus_secret_key = consensus.1;
+            config.service_public_key = service.0;
+            config.service_secret_key = service.1;
+        }
+        config
+            .validate()
+            .map_err(|e| NodeBuildError::InvalidConfig(e.to_string()))?;
+        check_services(&self.services).map_err(NodeBuildError::Blockchain)?;
+
+        Node::try_new(database, self.services, config, self.config_file_path)
+            .map_err(|e| NodeBuildError::Startup(e.to_string()))
+    }
+
+    /// Creates the node and runs it in a separate thread.
+    pub fn run(self) -> Result<NodeHandle, NodeBuildError> {
+        let node = self.build()?;
+        let api_sender = node.channel();
+        let thread = thread::Builder::new()
+            .name("exonum-node".to_owned())
+            .spawn(move || node.run())
+            .map_err(|e| NodeBuildError::Thread(e.to_string()))?;
+        Ok(NodeHandle { api_sender, thread })
+    }
+}
+
+/// Checks that the identifiers and the names of the services are unique.
+fn check_services(services: &[Box<dyn Service>]) -> Result<(), BlockchainBuildError> {
+    let mut ids = HashSet::new();
+    let mut names = HashSet::new();
+    for service in services {
+        if !ids.insert(service.service_id()) {
+            return Err(BlockchainBuildError::DuplicateServiceId(
+                service.service_id(),
+            ));
+        }
+        if !names.insert(service.service_name()) {
+            return Err(BlockchainBuildError::DuplicateServiceName(
+                service.service_name().to_owned(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Handle of a node started by `NodeBuilder::run`.
+#[derive(Debug)]
+pub struct NodeHandle {
+    api_sender: ApiSender,
+    thread: JoinHandle<Result<(), failure::Error>>,
+}
+
+impl NodeHandle {
+    /// Returns the sender of the transactions and other external messages to the node.
+    pub fn api_sender(&self) -> &ApiSender {
+        &self.api_sender
+    }
+
+    /// Stops the node and waits until it shuts down.
+    pub fn shutdown(self) -> Result<(), failure::Error> {
+        self.api_sender
+            .send_external_message(ExternalMessage::Shutdown)?;
+        self.join()
+    }
+
+    /// Waits until the node stops, e.g., after a `Shutdown` message sent by
+    /// the API sender. Returns the error the node has stopped with, if any.
+    pub fn join(self) -> Result<(), failure::Error> {
+        self.thread
+            .join()
+            .map_err(|_| format_err!("Node thread has panicked"))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_merkledb::{Snapshot, TemporaryDB};
+
+    use super::*;
+    use crate::blockchain::Transaction;
+    use crate::crypto::Hash;
+    use crate::helpers;
+    use crate::messages::RawTransaction;
+
+    struct DummyService(u16, &'static str);
+
+    impl Service for DummyService {
+        fn service_id(&self) -> u16 {
+            self.0
+        }
+
+        fn service_name(&self) -> &str {
+            self.1
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![]
+        }
+
+        fn tx_from_raw(&self, _: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            unreachable!("Transactions are not sent in the test");
+        }
+    }
+
+    fn config() -> NodeConfig {
+        helpers::generate_testnet_config(1, 4_300).remove(0)
+    }
+
+    #[test]
+    fn builder_requires_config_and_database() {
+        let error = NodeBuilder::new()
+            .with_database(TemporaryDB::new())
+            .build()
+            .unwrap_err();
+        assert_eq!(error, NodeBuildError::MissingConfig);
+
+        let error = NodeBuilder::new()
+            .with_config(config())
+            .build()
+            .unwrap_err();
+        assert_eq!(error, NodeBuildError::MissingDatabase);
+    }
+
+    #[test]
+    fn builder_checks_services() {
+        let error = NodeBuilder::new()
+            .with_database(TemporaryDB::new())
+            .with_config(config())
+            .with_service(Box::new(DummyService(1, "first")))
+            .with_service(Box::new(DummyService(1, "second")))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            NodeBuildError::Blockchain(BlockchainBuildError::DuplicateServiceId(1))
+        );
+    }
+
+    #[test]
+    fn builder_replaces_config_parts() {
+        let keys = NodeKeys::random();
+        let api_options = NodeApiConfig {
+            state_update_timeout: 500,
+            ..NodeApiConfig::default()
+        };
+        let node = NodeBuilder::new()
+            .with_database(TemporaryDB::new())
+            .with_config(config())
+            .with_service(Box::new(DummyService(1, "first")))
+            .with_api_options(api_options)
+            .with_signer(keys.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(*node.state().consensus_public_key(), keys.consensus.0);
+        assert_eq!(*node.state().service_public_key(), keys.service.0);
+        assert_eq!(node.api_options.state_update_timeout, 500);
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sanity check of the local clock against the clocks of the validators.
+//!
+//! The node compares the creation time of the `Precommit` messages of the current
+//! height with the local time of their receipt. `Connect` messages are not used,
+//! since they are signed once at the node start. The skew of the local clock is
+//! estimated as the median of the latest differences for each validator, so that a
+//! minority of validators with wrong clocks does not affect the estimate. The network
+//! delay is included into the differences, so the estimate is slightly biased towards
+//! the local clock being ahead.
+
+use std::{collections::HashMap, time::SystemTime};
+
+use crate::crypto::PublicKey;
+use crate::helpers::Milliseconds;
+
+/// Default skew of the local clock above which a warning is logged.
+pub const DEFAULT_WARN_CLOCK_SKEW: Milliseconds = 5_000;
+
+/// Clock sanity configuration.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ClockSanityConfig {
+    /// Skew of the local clock, in milliseconds, above which a warning is logged.
+    #[serde(default = "default_warn_skew")]
+    pub warn_skew: Milliseconds,
+    /// Skew of the local clock, in milliseconds, above which the node does not
+    /// propose blocks. The limit is not enforced if absent.
+    #[serde(default)]
+    pub max_propose_skew: Option<Milliseconds>,
+}
+
+fn default_warn_skew() -> Milliseconds {
+    DEFAULT_WARN_CLOCK_SKEW
+}
+
+impl Default for ClockSanityConfig {
+    fn default() -> Self {
+        Self {
+            warn_skew: DEFAULT_WARN_CLOCK_SKEW,
+            max_propose_skew: None,
+        }
+    }
+}
+
+/// Estimated skew of the local clock exposed by the private API.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClockSkewInfo {
+    /// Skew of the local clock in milliseconds, positive if the local clock is ahead
+    /// of the validators. Absent until a `Precommit` is received from another validator.
+    pub skew: Option<i64>,
+    /// Number of validators the estimate is based on.
+    pub validators: usize,
+}
+
+/// Differences between the local clock and the clocks of the validators.
+#[derive(Debug, Clone, Default)]
+pub struct ClockSkew {
+    samples: HashMap<PublicKey, i64>,
+}
+
+impl ClockSkew {
+    /// Records a message of the validator created at `remote_time` and received
+    /// at `local_time`.
+    pub fn add_sample(
+        &mut self,
+        validator: PublicKey,
+        remote_time: SystemTime,
+        local_time: SystemTime,
+    ) {
+        let difference = match local_time.duration_since(remote_time) {
+            Ok(ahead) => duration_millis(ahead),
+            Err(e) => -duration_millis(e.duration()),
+        };
+        self.samples.insert(validator, difference);
+    }
+
+    /// Forgets the samples of the validators for which the predicate returns `false`.
+    pub fn retain<F: FnMut(&PublicKey) -> bool>(&mut self, mut f: F) {
+        self.samples.retain(|key, _| f(key));
+    }
+
+    /// Returns the estimated skew of the local clock in milliseconds, positive if
+    /// the local clock is ahead.
+    pub fn estimate(&self) -> Option<i64> {
+        let mut samples: Vec<_> = self.samples.values().cloned().collect();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort();
+        let middle = samples.len() / 2;
+        Some(if samples.len() % 2 == 0 {
+            (samples[middle - 1] + samples[middle]) / 2
+        } else {
+            samples[middle]
+        })
+    }
+
+    /// Returns the information about the skew for the API.
+    pub fn info(&self) -> ClockSkewInfo {
+        ClockSkewInfo {
+            skew: self.estimate(),
+            validators: self.samples.len(),
+        }
+    }
+}
+
+fn duration_millis(duration: std::time::Duration) -> i64 {
+    duration.as_secs() as i64 * 1000 + i64::from(duration.subsec_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::crypto::gen_keypair;
+
+    #[test]
+    fn skew_is_median_of_validators() {
+        let now = SystemTime::now();
+        let mut skew = ClockSkew::default();
+        assert_eq!(skew.estimate(), None);
+
+        let validators: Vec<_> = (0..4).map(|_| gen_keypair().0).collect();
+        // The local clock is 2 seconds ahead of the most of validators.
+        skew.add_sample(validators[0], now - Duration::from_millis(2_000), now);
+        skew.add_sample(validators[1], now - Duration::from_millis(2_100), now);
+        skew.add_sample(validators[2], now + Duration::from_secs(60), now);
+        assert_eq!(skew.estimate(), Some(2_000));
+
+        skew.add_sample(validators[3], now - Duration::from_millis(1_900), now);
+        assert_eq!(skew.estimate(), Some(1_950));
+        assert_eq!(skew.info().validators, 4);
+
+        // The latest sample of a validator replaces the previous ones.
+        for validator in &validators[..3] {
+            skew.add_sample(*validator, now + Duration::from_millis(500), now);
+        }
+        assert_eq!(skew.estimate(), Some(-500));
+
+        skew.retain(|key| *key == validators[3]);
+        assert_eq!(
+            skew.info(),
+            ClockSkewInfo {
+                skew: Some(1_900),
+                validators: 1,
+            }
+        );
+    }
+}
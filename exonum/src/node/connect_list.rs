@@ -16,6 +16,7 @@
 
 use std::collections::BTreeMap;
 
+use crate::blockchain::ValidatorKeys;
 use crate::crypto::PublicKey;
 use crate::node::{ConnectInfo, ConnectListConfig};
 
@@ -80,6 +81,39 @@ impl ConnectList {
     }
 }
 
+/// Order in which the node connects to the known peers on start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconnectOrder {
+    /// The current validators are connected first, in the order of their identifiers,
+    /// so that the node regains the consensus participation sooner. Auditors and
+    /// other peers are connected afterwards.
+    ValidatorsFirst,
+    /// Peers are connected in arbitrary order.
+    Unordered,
+}
+
+impl Default for ReconnectOrder {
+    fn default() -> Self {
+        ReconnectOrder::ValidatorsFirst
+    }
+}
+
+impl ReconnectOrder {
+    /// Sorts the consensus keys of the peers in the order of connection.
+    pub(crate) fn sort(self, peers: &mut [PublicKey], validators: &[ValidatorKeys]) {
+        if self == ReconnectOrder::Unordered {
+            return;
+        }
+        peers.sort_by_key(|key| {
+            validators
+                .iter()
+                .position(|validator| validator.consensus_key == *key)
+                .unwrap_or_else(|| validators.len())
+        });
+    }
+}
+
 #[cfg(test)]
 mod test {
     use rand::{rngs::StdRng, RngCore, SeedableRng};
@@ -179,6 +213,28 @@ mod test {
         check_in_connect_list(&connect_list, &validators1, &[0, 1], &[]);
     }
 
+    #[test]
+    fn test_reconnect_order() {
+        let regular = make_keys(REGULAR_PEERS, 2);
+        let validators = make_keys(VALIDATORS[0], 2);
+        let validator_keys = validators
+            .iter()
+            .map(|key| ValidatorKeys::new(*key, *key))
+            .collect::<Vec<_>>();
+        let peers = vec![regular[0], validators[1], regular[1], validators[0]];
+
+        let mut ordered = peers.clone();
+        ReconnectOrder::ValidatorsFirst.sort(&mut ordered, &validator_keys);
+        assert_eq!(
+            ordered,
+            vec![validators[0], validators[1], regular[0], regular[1]]
+        );
+
+        let mut unordered = peers.clone();
+        ReconnectOrder::Unordered.sort(&mut unordered, &validator_keys);
+        assert_eq!(unordered, peers);
+    }
+
     #[test]
     fn test_address_allowed() {
         let (public_key, _) = gen_keypair();
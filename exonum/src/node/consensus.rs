@@ -12,19 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashSet;
+use chrono::{DateTime, Utc};
 
-use crate::blockchain::{check_tx, Schema};
+use std::{borrow::Cow, collections::HashSet, panic, thread, time::Instant};
+
+use crate::api::node::public::{AuthorEvent, AuthorNotification};
+use crate::blockchain::{
+    check_tx, get_tx, system_transaction_author, verify_precommits, BlockMetrics, Schema,
+    TransactionTag,
+};
 use crate::crypto::{CryptoHash, Hash, PublicKey};
 use crate::events::InternalRequest;
 use crate::helpers::{Height, Round, ValidatorId};
 use crate::messages::{
     BlockRequest, BlockResponse, Consensus as ConsensusMessage, PoolTransactionsRequest, Precommit,
-    Prevote, PrevotesRequest, Propose, ProposeRequest, RawTransaction, Signed, SignedMessage,
-    TransactionsRequest, TransactionsResponse,
+    Prevote, PrevotesRequest, Propose, ProposeRequest, RawTransaction, ShortTransactionsRequest,
+    Signed, SignedMessage, Status, TransactionsRequest, TransactionsResponse,
+};
+use crate::node::{
+    assembly::{AuthorQuotaFilter, ConflictFilter, SizeFilter},
+    rejected::TransactionRejected,
+    state::BlockExecution,
+    BlockAssemblyStats, ConsensusEvent, NodeHandler, RejectedTransaction, RejectionReason,
+    RequestData, TransactionSource,
 };
-use crate::node::{NodeHandler, RequestData};
-use exonum_merkledb::Patch;
+use exonum_merkledb::{Fork, Patch};
 
 // TODO Reduce view invocations. (ECR-171)
 impl NodeHandler {
@@ -76,6 +88,18 @@ impl NodeHandler {
             }
             return;
         }
+
+        let expected_algorithm = self.state.consensus_key_algorithm_of(msg.validator());
+        if expected_algorithm != Some(msg.signature_algorithm()) {
+            error!(
+                "Received consensus message signed with unexpected scheme: actual={:?}, \
+                 expected={:?}, msg={:?}",
+                msg.signature_algorithm(),
+                expected_algorithm,
+                msg
+            );
+            return;
+        }
         let key = msg.author();
 
         trace!("Handle message={:?}", msg);
@@ -110,17 +134,27 @@ impl NodeHandler {
             return;
         }
 
+        // Check precommits for the previous block
+        if let Err(e) = self.verify_propose_precommits(msg) {
+            error!(
+                "Received propose with invalid precommits: {}, msg={:?}",
+                e, msg
+            );
+            return;
+        }
+
         trace!("Handle propose");
 
         let snapshot = self.blockchain.snapshot();
         let schema = Schema::new(&snapshot);
+
         //TODO: Remove this match after errors refactor. (ECR-979)
-        let has_unknown_txs = match self.state.add_propose(
+        let (has_unknown_txs, transactions) = match self.state.add_propose(
             msg.clone(),
             &schema.transactions(),
             &schema.transactions_pool(),
         ) {
-            Ok(state) => state.has_unknown_txs(),
+            Ok(state) => (state.has_unknown_txs(), state.transactions().len()),
             Err(err) => {
                 warn!("{} from {:?}", err, msg.validator());
                 return;
@@ -128,12 +162,25 @@ impl NodeHandler {
         };
 
         let hash = msg.hash();
+        self.observe(ConsensusEvent::ProposeReceived {
+            height: msg.height(),
+            round: msg.round(),
+            proposer: msg.validator(),
+            propose_hash: hash,
+            transactions,
+        });
 
         // Remove request info
         let known_nodes = self.remove_request(&RequestData::Propose(hash));
 
         if has_unknown_txs {
             trace!("REQUEST TRANSACTIONS");
+            // Missing transactions of a compact propose are requested without waiting
+            // for them to arrive with the gossip.
+            if msg.is_compact() {
+                let message = self.propose_transactions_request(&hash, from);
+                self.send_to_peer(from, message);
+            }
             self.request(RequestData::ProposeTransactions(hash), from);
 
             for node in known_nodes {
@@ -283,8 +330,12 @@ impl NodeHandler {
         let block_hash = block.hash();
 
         if self.state.block(&block_hash).is_none() {
-            let (computed_block_hash, patch) =
-                self.create_block(block.proposer_id(), block.height(), msg.transactions());
+            let (computed_block_hash, patch, execution) = self.create_block(
+                block.proposer_id(),
+                block.height(),
+                block.time(),
+                msg.transactions(),
+            );
             // Verify block_hash.
             assert!(
                 computed_block_hash == block_hash,
@@ -298,6 +349,7 @@ impl NodeHandler {
                 patch,
                 msg.transactions().to_vec(),
                 block.proposer_id(),
+                execution,
             );
         }
         let precommits: Result<Vec<_>, _> = msg
@@ -416,6 +468,11 @@ impl NodeHandler {
                 self.blockchain.save_messages(round, raw_messages);
 
                 self.state.lock(round, propose_hash);
+                self.observe(ConsensusEvent::Locked {
+                    height: self.state.height(),
+                    round,
+                    propose_hash,
+                });
                 // Send precommit
                 if self.state.is_validator() && !self.state.have_incompatible_prevotes() {
                     // Execute block and get state hash
@@ -474,6 +531,7 @@ impl NodeHandler {
         round: Option<Round>,
     ) {
         trace!("COMMIT {:?}", block_hash);
+        metric!("node.tx_cache.bytes", self.state.tx_cache_bytes());
 
         // Merge changes into storage
         let (committed_txs, proposer) = {
@@ -481,18 +539,28 @@ impl NodeHandler {
                 let block_state = self.state.block_mut(&block_hash).unwrap();
                 let committed_txs = block_state.txs().len();
                 let proposer = block_state.proposer_id();
-
-                self.blockchain
-                    .commit(
-                        block_state.patch(),
-                        block_hash,
-                        precommits,
-                        self.state.tx_cache_mut(),
-                    )
-                    .unwrap();
+                let tx_hashes = block_state.txs().clone();
+                let execution = block_state.execution();
+
+                let patch = self.blockchain.prepare_commit(
+                    block_state.patch(),
+                    block_hash,
+                    precommits,
+                    self.state.tx_cache_mut(),
+                );
+                let patch = add_block_metrics(patch, execution);
+                self.merge_block_patch(patch, block_hash);
+                self.blockchain.notify_services_about_commit();
+                self.api_state
+                    .transaction_waiters()
+                    .notify_committed(&tx_hashes);
+                self.notify_committed_authors(&tx_hashes);
 
                 (committed_txs, proposer)
             };
+            // The cache has been moved into the pool.
+            self.state.update_tx_cache_bytes();
+            self.pool_spilled_txs();
             // Update node state.
             self.state
                 .update_config(Schema::new(&self.blockchain.snapshot()).actual_configuration());
@@ -509,9 +577,38 @@ impl NodeHandler {
         let schema = Schema::new(&snapshot);
         let pool_len = schema.transactions_pool_len();
 
+        // After the commit all uncommitted transactions reside in the persistent pool.
+        let pool = schema.transactions_pool();
+        self.state.retain_priority_txs(|hash| pool.contains(hash));
+        self.state
+            .retain_tx_receive_times(|hash| pool.contains(hash));
+        self.state
+            .retain_tx_conflict_keys(|hash| pool.contains(hash));
+        self.state.retain_tx_authors(|hash| pool.contains(hash));
+
         metric!("node.mempool", pool_len);
+        if let Some(limit) = self.author_quota.max_pooled_txs {
+            let throttled = self
+                .state
+                .pooled_txs_by_author()
+                .values()
+                .filter(|&&count| count >= limit as usize)
+                .count();
+            metric!("node.mempool.throttled_authors", throttled);
+        }
 
         let height = self.state.height();
+        if let Some(ref compactor) = self.compactor {
+            compactor.notify_commit(height.previous());
+        }
+        self.prune_archive(height);
+        self.observe(ConsensusEvent::Committed {
+            height: height.previous(),
+            round,
+            block_hash,
+            proposer,
+            transactions: committed_txs,
+        });
         info!(
             "COMMIT ====== height={}, proposer={}, round={}, committed={}, pool={}, hash={}",
             height,
@@ -522,7 +619,6 @@ impl NodeHandler {
             block_hash.to_hex(),
         );
 
-        self.broadcast_status();
         self.add_status_timeout();
 
         // Add timeout for first round
@@ -538,10 +634,54 @@ impl NodeHandler {
         }
     }
 
+    /// Merges the patch of the committed block into the storage on a separate thread
+    /// while broadcasting the `Status` message for the next height. The status is
+    /// derived from the patch, so it is the same as after the merge. Requests of
+    /// the peers prompted by the status are handled by the node thread after
+    /// the merge is complete, so they observe the committed block.
+    fn merge_block_patch(&mut self, patch: Patch, block_hash: Hash) {
+        let fork = Fork::from(patch);
+        let (config, pool_len) = {
+            let schema = Schema::new(&fork);
+            (
+                schema.actual_configuration(),
+                schema.transactions_pool_len(),
+            )
+        };
+        let patch = fork.into_patch();
+
+        let mut blockchain = self.blockchain.clone();
+        let merge = thread::spawn(move || {
+            let start = Instant::now();
+            let result = blockchain.merge(patch);
+            metric!("node.commit.merge_us", start.elapsed().as_micros());
+            result
+        });
+
+        let height = self.state.height().next();
+        // The transactions cache has been moved into the pool by the patch.
+        let pool_size = pool_len + self.state.spilled_txs().len() as u64;
+        let is_validator = config
+            .validator_keys
+            .iter()
+            .any(|keys| keys.consensus_key == *self.state.consensus_public_key());
+        let skip_propose = config.consensus.skip_lagging_proposers
+            && is_validator
+            && self.state.is_lagging_behind(height);
+        let status = Status::new(height, &block_hash, pool_size).with_skip_propose(skip_propose);
+        self.send_status(status);
+
+        merge
+            .join()
+            .unwrap_or_else(|err| panic::resume_unwind(err))
+            .expect("Unable to merge the block into the storage");
+    }
+
     /// Checks if the transaction is new and adds it to the pool. This may trigger an expedited
     /// `Propose` timeout on this node if transaction count in the pool goes over the threshold.
     pub fn handle_tx(&mut self, msg: Signed<RawTransaction>) -> Result<(), failure::Error> {
         let hash = msg.hash();
+        let author = msg.author();
 
         let snapshot = self.blockchain.snapshot();
         let schema = Schema::new(&snapshot);
@@ -550,12 +690,99 @@ impl NodeHandler {
             bail!("Received already processed transaction, hash {:?}", hash)
         }
 
-        if let Err(e) = self.blockchain.tx_from_raw(msg.payload().clone()) {
-            error!("Received invalid transaction {:?}, result: {}", msg, e);
-            bail!("Received malicious transaction.")
+        if msg.network_id() != self.network_id {
+            return Err(TransactionRejected {
+                tx_hash: hash,
+                reason: RejectionReason::WrongNetwork,
+                details: format!(
+                    "Transaction is signed for the network {}, expected {}",
+                    msg.network_id(),
+                    self.network_id
+                ),
+            }
+            .into());
+        }
+
+        // Transactions of the current proposals are accepted to keep the consensus going.
+        if self.resources.is_overloaded() && !self.state.is_awaited_tx(&hash) {
+            return Err(TransactionRejected {
+                tx_hash: hash,
+                reason: RejectionReason::Overloaded,
+                details: "Node is approaching its resource limits".to_owned(),
+            }
+            .into());
         }
 
-        self.state.tx_cache_mut().insert(hash, msg);
+        // Transactions generated by the services are only needed to complete the blocks
+        // received from the peers.
+        if author == system_transaction_author() && !self.state.is_awaited_tx(&hash) {
+            return Err(TransactionRejected {
+                tx_hash: hash,
+                reason: RejectionReason::ReservedAuthor,
+                details: "Transaction is signed with the system key".to_owned(),
+            }
+            .into());
+        }
+
+        if let Some(limit) = self.author_quota.max_pooled_txs {
+            if self.state.author_pooled_txs(&author) >= limit as usize
+                && !self.state.is_awaited_tx(&hash)
+            {
+                return Err(TransactionRejected {
+                    tx_hash: hash,
+                    reason: RejectionReason::AuthorQuota,
+                    details: format!(
+                        "Author {:?} has reached the limit of {} uncommitted transactions",
+                        author, limit
+                    ),
+                }
+                .into());
+            }
+        }
+
+        let tx = match self.blockchain.tx_from_raw(msg.payload().clone()) {
+            Ok(tx) => tx,
+            Err(e) => {
+                error!("Received invalid transaction {:?}, result: {}", msg, e);
+                let service_id = msg.payload().service_id();
+                let snapshot = self.blockchain.snapshot();
+                let reason = if !self
+                    .blockchain
+                    .is_service_deployed(snapshot.as_ref(), service_id)
+                {
+                    RejectionReason::UnknownService
+                } else if !self
+                    .blockchain
+                    .is_service_active(snapshot.as_ref(), service_id)
+                {
+                    RejectionReason::InactiveService
+                } else {
+                    RejectionReason::InvalidPayload
+                };
+                return Err(TransactionRejected {
+                    tx_hash: hash,
+                    reason,
+                    details: e.to_string(),
+                }
+                .into());
+            }
+        };
+
+        if self
+            .priority_lane
+            .is_priority_service(msg.payload().service_id())
+        {
+            self.state.add_priority_tx(hash);
+        }
+        self.state.add_tx_to_cache(hash, msg);
+        self.state
+            .add_tx_receive_time(hash, self.system_state.current_time());
+        self.state.add_tx_conflict_keys(hash, tx.conflict_keys());
+        if self.author_quota.max_pooled_txs.is_some() {
+            self.state.add_tx_author(hash, author);
+        }
+        self.maybe_spill_tx_cache();
+        self.notify_author(author, hash, AuthorEvent::Pooled);
 
         if self.state.is_leader() && self.state.round() != Round::zero() {
             self.maybe_add_propose_timeout();
@@ -577,6 +804,66 @@ impl NodeHandler {
         Ok(())
     }
 
+    /// Records the transaction into the log of rejected transactions if the error
+    /// means that the transaction is invalid.
+    pub(crate) fn record_rejected_tx(&self, error: &failure::Error, source: TransactionSource) {
+        if let Some(rejected) = error.downcast_ref::<TransactionRejected>() {
+            self.api_state
+                .rejected_transactions()
+                .push(RejectedTransaction::new(
+                    rejected.tx_hash,
+                    rejected.reason,
+                    rejected.details.clone(),
+                    source,
+                    self.system_state.current_time(),
+                ));
+        }
+    }
+
+    /// Moves the cached transactions to the storage if their size exceeds the limit.
+    fn maybe_spill_tx_cache(&mut self) {
+        let cache_bytes = self.state.tx_cache_bytes();
+        if cache_bytes <= self.tx_cache_memory_limit {
+            return;
+        }
+
+        let fork = self.blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            for tx in self.state.tx_cache().values() {
+                schema.spill_transaction(tx.clone());
+            }
+        }
+        if let Err(e) = self.blockchain.merge(fork.into_patch()) {
+            error!("Unable to move transactions cache to the storage: {}", e);
+            return;
+        }
+
+        let spilled = self.state.spill_tx_cache();
+        warn!(
+            "Transactions cache exceeded the limit of {} bytes, moved {} transactions \
+             ({} bytes) to the storage",
+            self.tx_cache_memory_limit, spilled, cache_bytes
+        );
+        metric!("node.tx_cache.spilled", self.state.spilled_txs().len());
+    }
+
+    /// Adds the transactions spilled from the cache during the committed height
+    /// into the pool.
+    fn pool_spilled_txs(&mut self) {
+        if self.state.spilled_txs().is_empty() {
+            return;
+        }
+
+        let fork = self.blockchain.fork();
+        let pooled = Schema::new(&fork).pool_spilled_transactions();
+        self.blockchain
+            .merge(fork.into_patch())
+            .expect("Unable to add spilled transactions into the pool");
+        self.state.clear_spilled_txs();
+        trace!("Added {} spilled transactions into the pool", pooled);
+    }
+
     /// Handles raw transactions.
     pub fn handle_txs_batch(
         &mut self,
@@ -603,13 +890,82 @@ impl NodeHandler {
     }
 
     /// Handles external boxed transaction. Additionally transaction will be broadcast to the
-    /// Node's peers.
+    /// Node's peers. Returns `true` if the transaction has been added to the pool.
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::needless_pass_by_value))]
-    pub fn handle_incoming_tx(&mut self, msg: Signed<RawTransaction>) {
+    pub fn handle_incoming_tx(&mut self, msg: Signed<RawTransaction>) -> bool {
         trace!("Handle incoming transaction");
+        let hash = msg.hash();
         match self.handle_tx(msg.clone()) {
-            Ok(_) => self.broadcast(msg),
-            Err(e) => error!("{}", e),
+            Ok(_) => {
+                self.api_state.transaction_waiters().notify_pooled(&hash);
+                self.gossip_transaction(msg, false);
+                true
+            }
+            Err(e) => {
+                let snapshot = self.blockchain.snapshot();
+                let schema = Schema::new(&snapshot);
+                let waiters = self.api_state.transaction_waiters();
+                // Resubmission of a known transaction is not an error for the submitter.
+                if check_tx(&hash, &schema.transactions(), self.state.tx_cache()) {
+                    waiters.notify_pooled(&hash);
+                } else {
+                    self.record_rejected_tx(&e, TransactionSource::Api);
+                    waiters.notify_rejected(&hash, &e.to_string());
+                    let reason = e.to_string();
+                    self.notify_author(msg.author(), hash, AuthorEvent::Rejected { reason });
+                }
+                error!("{}", e);
+                false
+            }
+        }
+    }
+
+    /// Handles external transaction submitted with a tag. The tag is stored if the transaction
+    /// is added to the pool; tags of the known transactions are not changed.
+    pub fn handle_incoming_tagged_tx(&mut self, msg: Signed<RawTransaction>, tag: TransactionTag) {
+        let hash = msg.hash();
+        if !self.handle_incoming_tx(msg) {
+            return;
+        }
+
+        let fork = self.blockchain.fork();
+        Schema::new(&fork)
+            .transaction_tags()
+            .put(&hash, tag.into_bytes());
+        if let Err(e) = self.blockchain.merge(fork.into_patch()) {
+            error!("Unable to store the tag of transaction {:?}: {}", hash, e);
+        }
+    }
+
+    /// Notifies the watchers of the author about the progress of the transaction.
+    fn notify_author(&self, author: PublicKey, tx_hash: Hash, event: AuthorEvent) {
+        if self.api_state.author_watchers().is_watched(&author) {
+            self.api_state.notify_author(AuthorNotification {
+                author,
+                tx_hash,
+                event,
+            });
+        }
+    }
+
+    /// Notifies the watchers of the authors of the committed transactions.
+    fn notify_committed_authors(&self, tx_hashes: &[Hash]) {
+        if self.api_state.author_watchers().is_empty() {
+            return;
+        }
+        let snapshot = self.blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        for tx_hash in tx_hashes {
+            let author = match schema.transactions().get(tx_hash) {
+                Some(tx) => tx.author(),
+                None => continue,
+            };
+            let location = schema.transactions_locations().get(tx_hash);
+            let status = schema.transaction_results().get(tx_hash);
+            if let (Some(location), Some(status)) = (location, status) {
+                let event = AuthorEvent::Committed { location, status };
+                self.notify_author(author, *tx_hash, event);
+            }
         }
     }
 
@@ -626,6 +982,7 @@ impl NodeHandler {
 
         info!("Jump to a new round = {}", round);
         self.state.jump_round(round);
+        self.observe(ConsensusEvent::NewRound { height, round });
         self.add_round_timeout();
         self.process_new_round();
     }
@@ -664,6 +1021,10 @@ impl NodeHandler {
 
         // Update state to new round
         self.state.new_round();
+        self.observe(ConsensusEvent::NewRound {
+            height,
+            round: self.state.round(),
+        });
 
         // Add timeout for this round
         self.add_round_timeout();
@@ -690,18 +1051,52 @@ impl NodeHandler {
             }
             let round = self.state.round();
 
-            let txs = self.get_txs_for_propose();
+            // A node with a skewed clock yields the round to the leader of the next round.
+            if let Some(max_skew) = self.clock.max_propose_skew {
+                if let Some(skew) = self.state.clock_skew().estimate() {
+                    if skew.abs() as u64 > max_skew {
+                        warn!(
+                            "Skipping propose at height {}, round {}: local clock skew of {} ms \
+                             exceeds {} ms",
+                            height, round, skew, max_skew
+                        );
+                        return;
+                    }
+                }
+            }
 
-            let propose = self.sign_message(Propose::new(
-                validator_id,
-                self.state.height(),
-                round,
-                self.state.last_hash(),
-                &txs,
-            ));
+            let txs = self.get_txs_for_propose(round);
+            // The precommits for the previous block determine the consensus time of the block.
+            let precommits: Vec<_> = Schema::new(&self.blockchain.snapshot())
+                .precommits(self.state.last_hash())
+                .iter()
+                .collect();
+
+            let propose = if self.state.consensus_config().compact_proposals && !txs.is_empty() {
+                Propose::compact(
+                    validator_id,
+                    self.state.height(),
+                    round,
+                    self.state.last_hash(),
+                    rand::random(),
+                    &txs,
+                    &precommits,
+                )
+            } else {
+                Propose::new(
+                    validator_id,
+                    self.state.height(),
+                    round,
+                    self.state.last_hash(),
+                    &txs,
+                    &precommits,
+                )
+            };
+            let propose = self.sign_message(propose);
 
             // Put our propose to the consensus messages cache
             self.blockchain.save_message(round, propose.clone());
+            self.archive_message(&ConsensusMessage::Propose(propose.clone()));
 
             trace!("Broadcast propose: {:?}", propose);
             self.broadcast(propose.clone());
@@ -709,7 +1104,14 @@ impl NodeHandler {
             self.allow_expedited_propose = true;
 
             // Save our propose into state
-            let hash = self.state.add_self_propose(propose);
+            let hash = self.state.add_self_propose(propose, txs.clone());
+            self.observe(ConsensusEvent::ProposeReceived {
+                height,
+                round,
+                proposer: validator_id,
+                propose_hash: hash,
+                transactions: txs.len(),
+            });
 
             // Send prevote
             let has_majority_prevotes = self.broadcast_prevote(round, &hash);
@@ -719,7 +1121,7 @@ impl NodeHandler {
         }
     }
 
-    fn get_txs_for_propose(&self) -> Vec<Hash> {
+    fn get_txs_for_propose(&self, round: Round) -> Vec<Hash> {
         let txs_cache_len = self.state.tx_cache_len() as u64;
         let tx_block_limit = self.txs_block_limit();
 
@@ -728,22 +1130,114 @@ impl NodeHandler {
         let pool = schema.transactions_pool();
         let pool_len = schema.transactions_pool_len();
 
-        info!("LEADER: pool = {}, cache = {}", pool_len, txs_cache_len);
-
-        let remaining_tx_count = tx_block_limit.saturating_sub(txs_cache_len as u32);
-        let cache_max_count = ::std::cmp::min(u64::from(tx_block_limit), txs_cache_len);
+        info!(
+            "LEADER: pool = {}, cache = {}, priority = {}",
+            pool_len,
+            txs_cache_len,
+            self.state.priority_txs().len()
+        );
 
-        let mut cache_txs: Vec<Hash> = self
+        let priority_txs = self.state.priority_txs();
+        let conflicts = ConflictFilter::default();
+        let sizes = SizeFilter::new(self.state.consensus_config());
+        let quota = AuthorQuotaFilter::new(self.author_quota.max_block_txs);
+        let transactions = schema.transactions();
+        let conflict_keys = |hash: &Hash| match self.state.tx_conflict_keys(hash) {
+            Some(keys) => Cow::Borrowed(keys),
+            // Transactions received before a restart are parsed again.
+            None => Cow::Owned(
+                transactions
+                    .get(hash)
+                    .and_then(|tx| self.blockchain.tx_from_raw(tx.payload().clone()).ok())
+                    .map(|tx| tx.conflict_keys())
+                    .unwrap_or_default(),
+            ),
+        };
+        // The candidate is checked against all filters before any of them is updated,
+        // so a transaction left out of the propose takes neither the conflict keys,
+        // nor the block space, nor the quota of its author. `select_txs` pulls
+        // the candidates lazily, so each admitted transaction is included into the propose.
+        let admit = |hash: &Hash| {
+            let tx = get_tx(hash, &transactions, self.state.tx_cache());
+            let author = tx.as_ref().map(|tx| tx.author());
+            let size = tx.map_or(0, |tx| tx.signed_message().raw().len() as u64);
+            let keys = conflict_keys(hash);
+            if let Some(author) = author.filter(|author| !quota.admits(author)) {
+                quota.skip(&author);
+                return false;
+            }
+            if !conflicts.admits(&keys) {
+                conflicts.postpone();
+                return false;
+            }
+            if !sizes.admits(size) {
+                sizes.skip();
+                return false;
+            }
+            if let Some(author) = author {
+                quota.take(&author);
+            }
+            conflicts.take(&keys);
+            sizes.take(size);
+            true
+        };
+        // Spilled transactions are not in the pool yet, so they are selected along
+        // with the cached ones.
+        let cache_txs = self
             .state
             .tx_cache()
             .keys()
-            .take(cache_max_count as usize)
-            .cloned()
-            .collect();
-        let pool_txs: Vec<Hash> = pool.iter().take(remaining_tx_count as usize).collect();
+            .chain(self.state.spilled_txs())
+            .filter(|hash| !priority_txs.contains(hash))
+            .cloned();
+        let pool_txs = pool.iter().filter(|hash| !priority_txs.contains(hash));
+
+        let txs = self.priority_lane.select_txs(
+            tx_block_limit as usize,
+            priority_txs.iter().cloned().filter(&admit),
+            cache_txs.chain(pool_txs).filter(&admit),
+        );
 
-        cache_txs.extend(pool_txs);
-        cache_txs
+        let candidates =
+            pool_len as usize + txs_cache_len as usize + self.state.spilled_txs().len();
+        self.record_block_assembly(round, candidates, &txs, &conflicts, &sizes, &quota);
+        txs
+    }
+
+    /// Records statistics of the proposed block for metrics and the API.
+    fn record_block_assembly(
+        &self,
+        round: Round,
+        candidates: usize,
+        txs: &[Hash],
+        conflicts: &ConflictFilter,
+        sizes: &SizeFilter,
+        quota: &AuthorQuotaFilter,
+    ) {
+        let now = self.system_state.current_time();
+        let waits = txs.iter().filter_map(|hash| {
+            let received = self.state.tx_receive_time(hash)?;
+            now.duration_since(received).ok()
+        });
+        let stats = BlockAssemblyStats::new(
+            self.state.height(),
+            round,
+            &self.priority_lane,
+            candidates,
+            self.state.priority_txs().len(),
+            txs.len(),
+            waits,
+        )
+        .with_conflicts(conflicts.postponed())
+        .with_size_limit(sizes.skipped())
+        .with_author_quota(quota.skipped());
+
+        stats.report_metrics();
+        metric!(
+            "node.block_assembly.throttled_authors",
+            quota.throttled_authors()
+        );
+        self.api_state.block_assembly_log().push(stats);
     }
 
     /// Handles request timeout by sending the corresponding request message to a peer.
@@ -762,16 +1256,7 @@ impl NodeHandler {
                     ))
                     .into(),
                 RequestData::ProposeTransactions(ref propose_hash) => {
-                    let txs: Vec<_> = self
-                        .state
-                        .propose(propose_hash)
-                        .unwrap()
-                        .unknown_txs()
-                        .iter()
-                        .cloned()
-                        .collect();
-                    self.sign_message(TransactionsRequest::new(&peer, &txs))
-                        .into()
+                    self.propose_transactions_request(propose_hash, peer)
                 }
                 RequestData::PoolTransactions => {
                     self.sign_message(PoolTransactionsRequest::new(peer)).into()
@@ -804,19 +1289,52 @@ impl NodeHandler {
         }
     }
 
-    /// Creates block with given transaction and returns its hash and corresponding changes.
+    /// Creates a request for the unknown transactions of the propose.
+    fn propose_transactions_request(&self, propose_hash: &Hash, peer: PublicKey) -> SignedMessage {
+        let propose = self.state.propose(propose_hash).unwrap();
+        if propose.message().is_compact() {
+            let short_ids = propose.unknown_short_ids();
+            self.sign_message(ShortTransactionsRequest::new(
+                &peer,
+                propose_hash,
+                &short_ids,
+            ))
+            .into()
+        } else {
+            let txs: Vec<_> = propose.unknown_txs().iter().cloned().collect();
+            self.sign_message(TransactionsRequest::new(&peer, &txs))
+                .into()
+        }
+    }
+
+    /// Creates block with given transaction and consensus time and returns its hash,
+    /// corresponding changes and the statistics of the execution.
     pub fn create_block(
         &mut self,
         proposer_id: ValidatorId,
         height: Height,
+        time: Option<DateTime<Utc>>,
         tx_hashes: &[Hash],
-    ) -> (Hash, Patch) {
-        self.blockchain.create_patch(
+    ) -> (Hash, Patch, BlockExecution) {
+        let pool_len = Schema::new(&self.blockchain.snapshot()).transactions_pool_len();
+        let pool_size =
+            pool_len + self.state.tx_cache_len() as u64 + self.state.spilled_txs().len() as u64;
+
+        let start = Instant::now();
+        let (block_hash, patch) = self.blockchain.create_patch_with_time(
             proposer_id,
             height,
+            time,
             tx_hashes,
             &mut self.state.tx_cache_mut(),
-        )
+        );
+        let execution = BlockExecution {
+            time: start.elapsed(),
+            pool_size,
+        };
+        // Executed transactions are removed from the cache.
+        self.state.update_tx_cache_bytes();
+        (block_hash, patch, execution)
     }
 
     /// Calls `create_block` with transactions from the corresponding `Propose` and returns the
@@ -826,15 +1344,27 @@ impl NodeHandler {
         if let Some(hash) = self.state.propose_mut(propose_hash).unwrap().block_hash() {
             return hash;
         }
-        let propose = self.state.propose(propose_hash).unwrap().message().clone();
+        let propose_state = self.state.propose(propose_hash).unwrap();
+        let propose = propose_state.message().clone();
 
-        let tx_hashes = propose.transactions().to_vec();
-
-        let (block_hash, patch) =
-            self.create_block(propose.validator(), propose.height(), tx_hashes.as_slice());
+        let tx_hashes = propose_state.transactions().to_vec();
+        let precommits: Vec<_> = propose
+            .precommits()
+            .into_iter()
+            .map(Precommit::verify_precommit)
+            .collect::<Result<_, _>>()
+            .expect("Precommits of the propose are verified on its receipt");
+        let time = Schema::new(&self.blockchain.snapshot()).next_block_time(&precommits);
+
+        let (block_hash, patch, execution) = self.create_block(
+            propose.validator(),
+            propose.height(),
+            time,
+            tx_hashes.as_slice(),
+        );
         // Save patch
         self.state
-            .add_block(block_hash, patch, tx_hashes, propose.validator());
+            .add_block(block_hash, patch, tx_hashes, propose.validator(), execution);
         self.state
             .propose_mut(propose_hash)
             .unwrap()
@@ -914,6 +1444,7 @@ impl NodeHandler {
         // save outgoing Prevote to the consensus messages cache before broadcast
         self.check_propose_saved(round, propose_hash);
         self.blockchain.save_message(round, prevote.clone());
+        self.archive_message(&ConsensusMessage::Prevote(prevote.clone()));
 
         trace!("Broadcast prevote: {:?}", prevote);
         self.broadcast(prevote);
@@ -939,37 +1470,76 @@ impl NodeHandler {
 
         // Put our Precommit to the consensus cache before broadcast
         self.blockchain.save_message(round, precommit.clone());
+        self.archive_message(&ConsensusMessage::Precommit(precommit.clone()));
 
         trace!("Broadcast precommit: {:?}", precommit);
         self.broadcast(precommit);
     }
 
-    /// Checks that pre-commits count is correct and calls `verify_precommit` for each of them.
+    /// Checks that pre-commits have the weight sufficient for a quorum and calls
+    /// `verify_precommit` for each of them.
     fn verify_precommits(
         &self,
         precommits: &[Signed<Precommit>],
         block_hash: &Hash,
         block_height: Height,
     ) -> Result<(), failure::Error> {
-        if precommits.len() < self.state.majority_count() {
-            bail!("Received block without consensus");
-        } else if precommits.len() > self.state.validators().len() {
+        if precommits.len() > self.state.validators().len() {
             bail!("Wrong precommits count in block");
         }
 
         let mut validators = HashSet::with_capacity(precommits.len());
-        let round = precommits[0].round();
         for precommit in precommits {
             if !validators.insert(precommit.validator()) {
                 bail!("Several precommits from one validator in block")
             }
+        }
+        if !self.state.config().has_majority(validators) {
+            bail!("Received block without consensus");
+        }
 
+        let round = precommits[0].round();
+        for precommit in precommits {
             self.verify_precommit(block_hash, block_height, round, precommit)?;
         }
 
         Ok(())
     }
 
+    /// Checks that the `Propose` contains the precommits for the latest committed block
+    /// with the weight sufficient for a quorum. The `Propose` of the block following
+    /// the genesis block contains no precommits.
+    fn verify_propose_precommits(&self, propose: &Propose) -> Result<(), failure::Error> {
+        let precommits: Vec<_> = propose
+            .precommits()
+            .into_iter()
+            .map(Precommit::verify_precommit)
+            .collect::<Result<_, _>>()?;
+
+        let snapshot = self.blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let height = schema.height();
+        if height == Height::zero() {
+            ensure!(
+                precommits.is_empty(),
+                "Precommits for the genesis block are not allowed"
+            );
+            return Ok(());
+        }
+
+        if let Some(precommit) = precommits
+            .iter()
+            .find(|p| p.network_id() != self.network_id)
+        {
+            bail!(
+                "Received precommit signed for another network, precommit={:?}",
+                precommit
+            );
+        }
+        let config = schema.configuration_by_height(height);
+        verify_precommits(&config, &precommits, self.state.last_hash(), height)
+    }
+
     /// Verifies that `Precommit` contains correct block hash, height round and is signed by the
     /// right validator.
     fn verify_precommit(
@@ -1008,6 +1578,12 @@ impl NodeHandler {
                     precommit
                 )
             }
+            if precommit.network_id() != self.network_id {
+                bail!(
+                    "Received precommit signed for another network, precommit={:?}",
+                    precommit
+                )
+            }
         } else {
             bail!(
                 "Received precommit with wrong validator, precommit={:?}",
@@ -1028,3 +1604,28 @@ impl NodeHandler {
         }
     }
 }
+
+/// Adds the performance record of the committed block to the block patch.
+fn add_block_metrics(patch: Patch, execution: BlockExecution) -> Patch {
+    let fork = Fork::from(patch);
+    {
+        let schema = Schema::new(&fork);
+        let block = schema.last_block();
+        let transactions = schema.transactions();
+        let block_size = schema
+            .block_transactions(block.height())
+            .iter()
+            .filter_map(|tx_hash| transactions.get(&tx_hash))
+            .map(|tx| tx.signed_message().raw().len() as u64)
+            .sum();
+        let metrics = BlockMetrics {
+            height: block.height(),
+            tx_count: block.tx_count(),
+            block_size,
+            execution_time_us: execution.time.as_micros() as u64,
+            pool_size: execution.pool_size,
+        };
+        schema.block_metrics().put(&block.height().0, metrics);
+    }
+    fork.into_patch()
+}
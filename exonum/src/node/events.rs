@@ -14,6 +14,7 @@
 
 use super::{ConnectListConfig, ExternalMessage, NodeHandler, NodeTimeout};
 use crate::blockchain::{get_tx, Schema};
+use crate::crypto::Hash;
 use crate::events::{
     error::LogError, Event, EventHandler, InternalEvent, InternalRequest, NetworkEvent,
 };
@@ -57,6 +58,9 @@ impl NodeHandler {
             ExternalMessage::Transaction(tx) => {
                 self.handle_incoming_tx(tx);
             }
+            ExternalMessage::TaggedTransaction { transaction, tag } => {
+                self.handle_incoming_tagged_tx(transaction, tag);
+            }
             ExternalMessage::PeerAdd(info) => {
                 info!("Send Connect message to {}", info);
                 self.state.add_peer_to_connect_list(info.clone());
@@ -88,10 +92,24 @@ impl NodeHandler {
             }
             ExternalMessage::Shutdown => self.handle_shutdown(),
             ExternalMessage::Rebroadcast => self.handle_rebroadcast(),
+            ExternalMessage::RebroadcastTransaction { tx_hash, reply } => {
+                let peers = self.handle_rebroadcast_transaction(&tx_hash);
+                // The API request may have been cancelled.
+                let _ = reply.send(peers);
+            }
+            ExternalMessage::ConsensusState(reply) => {
+                let _ = reply.send(self.consensus_state_info());
+            }
+            ExternalMessage::ServiceMessage {
+                to,
+                service_id,
+                payload,
+            } => self.send_service_message(to, service_id, payload),
         }
     }
 
     fn handle_timeout(&mut self, timeout: NodeTimeout) {
+        self.timeouts.remove(&timeout);
         if !self.is_enabled {
             info!(
                 "Ignoring a timeout {:?} because the node is disabled",
@@ -116,19 +134,37 @@ impl NodeHandler {
 
     /// Broadcasts all transactions from the pool to other validators.
     pub(crate) fn handle_rebroadcast(&mut self) {
-        use exonum_crypto::Hash;
         let snapshot = self.blockchain.snapshot();
         let schema = Schema::new(&snapshot);
 
         let mut txs: Vec<Hash> = self.state.tx_cache().keys().cloned().collect();
+        txs.extend(self.state.spilled_txs().iter().cloned());
         txs.extend(schema.transactions_pool().iter());
 
         for tx_hash in txs {
             self.broadcast(
                 get_tx(&tx_hash, &schema.transactions(), &self.state.tx_cache())
                     .expect("Rebroadcast: invalid transaction hash"),
-            )
+            );
+        }
+    }
+
+    /// Broadcasts a transaction from the pool to other validators. Returns the number
+    /// of peers the transaction has been sent to, or `None` if the transaction is not
+    /// in the pool.
+    pub(crate) fn handle_rebroadcast_transaction(&mut self, tx_hash: &Hash) -> Option<usize> {
+        let snapshot = self.blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+
+        let in_pool = self.state.tx_cache().contains_key(tx_hash)
+            || self.state.spilled_txs().contains(tx_hash)
+            || schema.transactions_pool().contains(tx_hash);
+        if !in_pool {
+            return None;
         }
+        let tx = get_tx(tx_hash, &schema.transactions(), &self.state.tx_cache())
+            .expect("Rebroadcast: invalid transaction hash");
+        Some(self.broadcast(tx))
     }
 
     pub(crate) fn handle_shutdown(&mut self) {
@@ -0,0 +1,220 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Propagation of transactions between the peers.
+//!
+//! With the default `broadcast` strategy, the node sends the transactions submitted
+//! through its API to all the connected peers, and does not relay the transactions
+//! received from the network. In large networks this results in a lot of traffic
+//! from the nodes receiving the transactions of the clients.
+//!
+//! With the `epidemic` strategy, the node sends each transaction to `fanout` peers
+//! chosen at random, and the peers relay the transactions new to them in the same way
+//! with the `relay_probability`. The hashes of the recently sent transactions are kept
+//! in a window of `dedup_window` entries, so that a transaction is not sent twice
+//! if it reaches the node again, e.g., after being rebroadcast. Transactions missed
+//! by a validator are requested by it once they are included into a proposal.
+
+use failure::Error;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::crypto::{Hash, PublicKey};
+use crate::helpers::lru::LruMap;
+
+/// Default number of peers each transaction is sent to with the `epidemic` strategy.
+pub const DEFAULT_GOSSIP_FANOUT: usize = 4;
+/// Default number of the recently sent transactions remembered by the node.
+pub const DEFAULT_GOSSIP_DEDUP_WINDOW: usize = 10_000;
+
+/// Strategy of the transaction propagation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GossipStrategy {
+    /// Transactions submitted to the node are sent to all the peers; transactions
+    /// received from the peers are not relayed.
+    Broadcast,
+    /// Transactions are sent to a random subset of the peers, which relay them further.
+    Epidemic,
+}
+
+impl Default for GossipStrategy {
+    fn default() -> Self {
+        GossipStrategy::Broadcast
+    }
+}
+
+/// Configuration of the transaction propagation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GossipConfig {
+    /// Propagation strategy.
+    #[serde(default)]
+    pub strategy: GossipStrategy,
+    /// Number of peers each transaction is sent to with the `epidemic` strategy.
+    #[serde(default = "default_fanout")]
+    pub fanout: usize,
+    /// Probability of relaying a transaction received from a peer with the `epidemic`
+    /// strategy.
+    #[serde(default = "default_relay_probability")]
+    pub relay_probability: f64,
+    /// Number of the recently sent transactions which are not sent again.
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: usize,
+}
+
+fn default_fanout() -> usize {
+    DEFAULT_GOSSIP_FANOUT
+}
+
+fn default_relay_probability() -> f64 {
+    1.0
+}
+
+fn default_dedup_window() -> usize {
+    DEFAULT_GOSSIP_DEDUP_WINDOW
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            strategy: GossipStrategy::default(),
+            fanout: DEFAULT_GOSSIP_FANOUT,
+            relay_probability: default_relay_probability(),
+            dedup_window: DEFAULT_GOSSIP_DEDUP_WINDOW,
+        }
+    }
+}
+
+impl GossipConfig {
+    /// Checks that the parameters are within their bounds.
+    pub fn validate(&self) -> Result<(), Error> {
+        ensure!(self.fanout > 0, "Gossip fanout should be positive");
+        ensure!(
+            (0.0..=1.0).contains(&self.relay_probability),
+            "Gossip relay probability should be between 0 and 1, got {}",
+            self.relay_probability
+        );
+        ensure!(
+            self.dedup_window > 0,
+            "Gossip deduplication window should be positive"
+        );
+        Ok(())
+    }
+}
+
+/// Chooses the peers the transactions are sent to.
+#[derive(Debug)]
+pub(crate) struct Gossip {
+    config: GossipConfig,
+    recently_sent: LruMap<Hash, ()>,
+}
+
+impl Gossip {
+    pub(crate) fn new(config: GossipConfig) -> Self {
+        Self {
+            config,
+            recently_sent: LruMap::default(),
+        }
+    }
+
+    /// Returns the peers a transaction should be sent to. `relayed` is set for
+    /// the transactions received from other peers.
+    pub(crate) fn targets(
+        &mut self,
+        tx_hash: Hash,
+        mut peers: Vec<PublicKey>,
+        relayed: bool,
+    ) -> Vec<PublicKey> {
+        let mut rng = rand::thread_rng();
+        match self.config.strategy {
+            GossipStrategy::Broadcast if relayed => return Vec::new(),
+            GossipStrategy::Broadcast => {}
+            GossipStrategy::Epidemic => {
+                if relayed && !rng.gen_bool(self.config.relay_probability) {
+                    return Vec::new();
+                }
+                peers.shuffle(&mut rng);
+                peers.truncate(self.config.fanout);
+            }
+        }
+
+        if self.recently_sent.get(&tx_hash).is_some() {
+            return Vec::new();
+        }
+        self.recently_sent
+            .insert(tx_hash, (), self.config.dedup_window);
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{gen_keypair, hash};
+
+    fn peers(count: usize) -> Vec<PublicKey> {
+        (0..count).map(|_| gen_keypair().0).collect()
+    }
+
+    #[test]
+    fn broadcast_does_not_relay() {
+        let mut gossip = Gossip::new(GossipConfig::default());
+        let peers = peers(10);
+        assert_eq!(gossip.targets(hash(&[1]), peers.clone(), false), peers);
+        assert!(gossip.targets(hash(&[2]), peers, true).is_empty());
+    }
+
+    #[test]
+    fn epidemic_sends_to_fanout_peers_once() {
+        let mut gossip = Gossip::new(GossipConfig {
+            strategy: GossipStrategy::Epidemic,
+            fanout: 3,
+            ..GossipConfig::default()
+        });
+        let peers = peers(10);
+        let targets = gossip.targets(hash(&[1]), peers.clone(), true);
+        assert_eq!(targets.len(), 3);
+        assert!(targets.iter().all(|target| peers.contains(target)));
+        assert!(gossip.targets(hash(&[1]), peers.clone(), false).is_empty());
+        assert_eq!(
+            gossip.targets(hash(&[2]), peers[..2].to_vec(), false).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn epidemic_relay_probability() {
+        let mut gossip = Gossip::new(GossipConfig {
+            strategy: GossipStrategy::Epidemic,
+            relay_probability: 0.0,
+            ..GossipConfig::default()
+        });
+        assert!(gossip.targets(hash(&[1]), peers(5), true).is_empty());
+        assert_eq!(gossip.targets(hash(&[1]), peers(5), false).len(), 4);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config = GossipConfig {
+            relay_probability: 1.5,
+            ..GossipConfig::default()
+        };
+        assert!(config.validate().is_err());
+        let config = GossipConfig {
+            fanout: 0,
+            ..GossipConfig::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(GossipConfig::default().validate().is_ok());
+    }
+}
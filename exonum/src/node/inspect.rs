@@ -0,0 +1,196 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dump of the consensus state machine served by the private `v1/consensus_state`
+//! endpoint of the system API.
+//!
+//! The dump is assembled by the node thread on request, so it reflects the state
+//! at a single point in time rather than a periodically refreshed copy.
+
+use chrono::{DateTime, Utc};
+
+use std::{collections::BTreeMap, time::SystemTime};
+
+use super::{state::VoteMessage, NodeHandler, NodeTimeout};
+use crate::crypto::Hash;
+use crate::helpers::{Height, Round, ValidatorId};
+
+/// Internals of the consensus state machine of the node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsensusStateInfo {
+    /// Height of the block being agreed upon.
+    pub height: Height,
+    /// Current round.
+    pub round: Round,
+    /// Identifier of the node if it is a validator.
+    pub validator_id: Option<ValidatorId>,
+    /// Leader of the current round.
+    pub leader: ValidatorId,
+    /// Round in which the node has locked on a proposal; zero if there is no lock.
+    pub locked_round: Round,
+    /// Hash of the locked proposal.
+    pub locked_propose: Option<Hash>,
+    /// Proposals received at the current height.
+    pub proposes: Vec<ProposeInfo>,
+    /// Prevotes received at the current height, grouped by round and proposal.
+    pub prevotes: Vec<VotesInfo>,
+    /// Precommits received at the current height, grouped by round and block.
+    pub precommits: Vec<VotesInfo>,
+    /// Latest rounds the validators have been observed in.
+    pub validators_rounds: BTreeMap<ValidatorId, Round>,
+    /// Scheduled timeouts that have not fired yet.
+    pub timeouts: Vec<TimeoutInfo>,
+}
+
+/// Proposal received at the current height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProposeInfo {
+    /// Hash of the `Propose` message.
+    pub hash: Hash,
+    /// Round of the proposal.
+    pub round: Round,
+    /// Validator that has made the proposal.
+    pub validator: ValidatorId,
+    /// Number of the proposed transactions unknown to the node.
+    pub unknown_txs: usize,
+    /// Hash of the block resulting from the proposal, if it has been executed.
+    pub block_hash: Option<Hash>,
+}
+
+/// Votes of the validators for a proposal or a block in a round.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VotesInfo {
+    /// Round of the votes.
+    pub round: Round,
+    /// Hash of the proposal for prevotes or the block for precommits.
+    pub hash: Hash,
+    /// Validators that have voted, in the order of the receipt of their votes.
+    pub validators: Vec<ValidatorId>,
+}
+
+impl VotesInfo {
+    fn new<T: VoteMessage>(round: Round, hash: Hash, votes: &[T]) -> Self {
+        Self {
+            round,
+            hash,
+            validators: votes.iter().map(VoteMessage::validator).collect(),
+        }
+    }
+}
+
+/// Kind of a scheduled timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutKind {
+    /// End of the round.
+    Round,
+    /// Creation of a proposal by the leader.
+    Propose,
+    /// Broadcast of the `Status` message.
+    Status,
+    /// Retry of a data request.
+    Request,
+    /// Exchange of the peer lists.
+    PeerExchange,
+    /// Update of the state shared with the API.
+    UpdateApiState,
+}
+
+/// Timeout scheduled by the node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeoutInfo {
+    /// Kind of the timeout.
+    pub kind: TimeoutKind,
+    /// Height the timeout has been scheduled for, if applicable.
+    pub height: Option<Height>,
+    /// Round the timeout has been scheduled for, if applicable.
+    pub round: Option<Round>,
+    /// Time when the timeout fires.
+    pub deadline: DateTime<Utc>,
+}
+
+impl TimeoutInfo {
+    fn new(timeout: &NodeTimeout, deadline: SystemTime) -> Self {
+        let (kind, height, round) = match *timeout {
+            NodeTimeout::Round(height, round) => (TimeoutKind::Round, Some(height), Some(round)),
+            NodeTimeout::Propose(height, round) => {
+                (TimeoutKind::Propose, Some(height), Some(round))
+            }
+            NodeTimeout::Status(height) => (TimeoutKind::Status, Some(height), None),
+            NodeTimeout::Request(..) => (TimeoutKind::Request, None, None),
+            NodeTimeout::PeerExchange => (TimeoutKind::PeerExchange, None, None),
+            NodeTimeout::UpdateApiState => (TimeoutKind::UpdateApiState, None, None),
+        };
+        Self {
+            kind,
+            height,
+            round,
+            deadline: deadline.into(),
+        }
+    }
+}
+
+impl NodeHandler {
+    /// Returns the dump of the consensus state.
+    pub(crate) fn consensus_state_info(&self) -> ConsensusStateInfo {
+        let state = &self.state;
+
+        let mut proposes = state
+            .proposes()
+            .iter()
+            .map(|(hash, propose)| ProposeInfo {
+                hash: *hash,
+                round: propose.message().round(),
+                validator: propose.message().validator(),
+                unknown_txs: propose.unknown_txs().len() + propose.unknown_short_ids().len(),
+                block_hash: propose.block_hash(),
+            })
+            .collect::<Vec<_>>();
+        proposes.sort_by_key(|propose| (propose.round, propose.validator));
+
+        let mut prevotes = state
+            .prevote_sets()
+            .iter()
+            .map(|(&(round, hash), votes)| VotesInfo::new(round, hash, votes.messages()))
+            .collect::<Vec<_>>();
+        prevotes.sort_by_key(|votes| (votes.round, votes.hash));
+        let mut precommits = state
+            .precommit_sets()
+            .iter()
+            .map(|(&(round, hash), votes)| VotesInfo::new(round, hash, votes.messages()))
+            .collect::<Vec<_>>();
+        precommits.sort_by_key(|votes| (votes.round, votes.hash));
+
+        let mut timeouts = self
+            .timeouts
+            .iter()
+            .map(|(timeout, &deadline)| TimeoutInfo::new(timeout, deadline))
+            .collect::<Vec<_>>();
+        timeouts.sort_by_key(|timeout| timeout.deadline);
+
+        ConsensusStateInfo {
+            height: state.height(),
+            round: state.round(),
+            validator_id: state.validator_id(),
+            leader: state.leader(state.round()),
+            locked_round: state.locked_round(),
+            locked_propose: state.locked_propose(),
+            proposes,
+            prevotes,
+            precommits,
+            validators_rounds: state.validators_rounds().clone(),
+            timeouts,
+        }
+    }
+}
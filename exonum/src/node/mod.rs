@@ -17,22 +17,53 @@
 //! For details about consensus message handling see messages module documentation.
 // spell-checker:ignore cors
 
+#[cfg(unix)]
+pub use self::transport::UnixSocketTransport;
 pub use self::{
-    connect_list::{ConnectList, PeerAddress},
+    alerts::{
+        validate_alert_rules, AlertAction, AlertMetric, AlertNotification, AlertRule, AlertState,
+        FiringAlert, FiringAlerts, NodeMetrics,
+    },
+    archive::{
+        archived_messages, ArchivedMessage, ArchivedMessageKind, ConsensusArchiveConfig,
+        DEFAULT_ARCHIVE_MAX_HEIGHTS, DEFAULT_ARCHIVE_MAX_MESSAGES_PER_HEIGHT,
+    },
+    assembly::{
+        BlockAssemblyLog, BlockAssemblyStats, PoolWaitStats, SkippedTransactions,
+        BLOCK_ASSEMBLY_LOG_SIZE,
+    },
+    builder::{NodeBuildError, NodeBuilder, NodeHandle, NodeKeys},
+    clock::{ClockSanityConfig, ClockSkewInfo, DEFAULT_WARN_CLOCK_SKEW},
+    connect_list::{ConnectList, PeerAddress, ReconnectOrder},
+    gossip::{GossipConfig, GossipStrategy, DEFAULT_GOSSIP_DEDUP_WINDOW, DEFAULT_GOSSIP_FANOUT},
+    inspect::{ConsensusStateInfo, ProposeInfo, TimeoutInfo, TimeoutKind, VotesInfo},
+    observer::{ConsensusEvent, ObservedEvent},
+    preflight::PreflightError,
+    quorum::{simulate_change, QuorumImpact, QuorumInfo, ValidatorSetChange},
+    rejected::{
+        RejectedTransaction, RejectedTransactionsLog, RejectionReason, TransactionSource,
+        REJECTED_TRANSACTIONS_LOG_SIZE,
+    },
+    resources::{ResourceLimitsConfig, ResourceUsage, DEFAULT_SHED_THRESHOLD},
     state::{RequestData, State, ValidatorState},
+    transport::{RemoteTransport, TransportRequest, TransportResponse},
 };
 
 // TODO: Temporary solution to get access to WAIT constants. (ECR-167)
 pub mod state;
 
 use failure::Error;
-use futures::{sync::mpsc, Future, Sink};
+use futures::{
+    sync::{mpsc, oneshot},
+    Future, Sink,
+};
 use tokio_core::reactor::Core;
 use tokio_threadpool::Builder as ThreadPoolBuilder;
 use toml::Value;
 
 use std::{
-    collections::{BTreeMap, HashSet},
+    cmp,
+    collections::{BTreeMap, BTreeSet, HashSet},
     fmt,
     net::SocketAddr,
     path::{Path, PathBuf},
@@ -42,11 +73,17 @@ use std::{
 };
 
 use crate::api::{
-    backends::actix::{AllowOrigin, ApiRuntimeConfig, App, AppConfig, Cors, SystemRuntimeConfig},
+    access_log::AccessLogConfig,
+    backends::actix::{
+        AllowOrigin, ApiRuntimeConfig, App, AppConfig, Cors, SystemRuntimeConfig,
+        DEFAULT_DRAIN_TIMEOUT,
+    },
     ApiAccess, ApiAggregator,
 };
 use crate::blockchain::{
-    Blockchain, ConsensusConfig, GenesisConfig, Schema, Service, SharedNodeState, ValidatorKeys,
+    retention::Compactor, scheduler::JobScheduler, Blockchain, ConsensusConfig, GenesisConfig,
+    GenesisState, RetentionConfig, Schema, Service, SharedNodeState, TransactionArchive,
+    TransactionTag, ValidatorKeys,
 };
 use crate::crypto::{self, read_keys_from_file, CryptoHash, Hash, PublicKey, SecretKey};
 use crate::events::{
@@ -55,20 +92,39 @@ use crate::events::{
     HandlerPart, InternalEvent, InternalPart, InternalRequest, NetworkConfiguration, NetworkEvent,
     NetworkPart, NetworkRequest, SyncSender, TimeoutRequest,
 };
+use crate::explorer::{BlockHeaderCache, DEFAULT_BLOCK_HEADER_CACHE_SIZE};
 use crate::helpers::{
     config::ConfigManager,
     fabric::{NodePrivateConfig, NodePublicConfig},
     user_agent, Height, Milliseconds, Round, ValidatorId,
 };
 use crate::messages::{Connect, Message, ProtocolMessage, RawTransaction, Signed, SignedMessage};
-use crate::node::state::SharedConnectList;
+#[cfg(unix)]
+use crate::node::transport::TransportServer;
+use crate::node::{
+    alerts::AlertEngine, archive::ConsensusArchive, gossip::Gossip, observer::ConsensusObserver,
+    preflight::StartupChecks, resources::ResourceWatchdog, state::SharedConnectList,
+};
 use exonum_merkledb::{Database, DbOptions};
 
+mod alerts;
+mod archive;
+mod assembly;
 mod basic;
+mod builder;
+mod clock;
 mod connect_list;
 mod consensus;
 mod events;
+mod gossip;
+mod inspect;
+mod observer;
+mod preflight;
+mod quorum;
+mod rejected;
 mod requests;
+mod resources;
+mod transport;
 
 /// External messages.
 #[derive(Debug)]
@@ -77,16 +133,43 @@ pub enum ExternalMessage {
     PeerAdd(ConnectInfo),
     /// Transaction that implements the `Transaction` trait.
     Transaction(Signed<RawTransaction>),
+    /// Transaction submitted with a correlation tag, which is stored by the node
+    /// if the transaction is added to the pool.
+    TaggedTransaction {
+        /// Transaction message.
+        transaction: Signed<RawTransaction>,
+        /// Tag of the transaction.
+        tag: TransactionTag,
+    },
     /// Enable or disable the node.
     Enable(bool),
     /// Shutdown the node.
     Shutdown,
     /// Rebroadcast transactions from the pool.
     Rebroadcast,
+    /// Rebroadcast a single transaction from the pool.
+    RebroadcastTransaction {
+        /// Hash of the transaction.
+        tx_hash: Hash,
+        /// Receives the number of peers the transaction has been sent to, or `None`
+        /// if the transaction is not in the pool.
+        reply: oneshot::Sender<Option<usize>>,
+    },
+    /// Dump the state of the consensus state machine.
+    ConsensusState(oneshot::Sender<ConsensusStateInfo>),
+    /// Send a message from a service to another validator.
+    ServiceMessage {
+        /// Recipient of the message.
+        to: ValidatorId,
+        /// Identifier of the service.
+        service_id: u16,
+        /// Payload of the message.
+        payload: Vec<u8>,
+    },
 }
 
 /// Node timeout types.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum NodeTimeout {
     /// Status timeout with the current height.
     Status(Height),
@@ -112,8 +195,20 @@ pub trait SystemStateProvider: ::std::fmt::Debug + Send + 'static {
 }
 
 /// Transactions sender.
+///
+/// The sender either delivers messages to the node over an in-process channel,
+/// or sends transactions to a node running in another process over
+/// a [`RemoteTransport`].
+///
+/// [`RemoteTransport`]: trait.RemoteTransport.html
 #[derive(Clone)]
-pub struct ApiSender(pub mpsc::Sender<ExternalMessage>);
+pub struct ApiSender(ApiTransport);
+
+#[derive(Clone)]
+enum ApiTransport {
+    Channel(mpsc::Sender<ExternalMessage>),
+    Remote(Arc<dyn RemoteTransport>),
+}
 
 /// Handler that that performs consensus algorithm.
 pub struct NodeHandler {
@@ -137,6 +232,32 @@ pub struct NodeHandler {
     config_manager: Option<ConfigManager>,
     /// Can we speed up Propose with transaction pressure?
     allow_expedited_propose: bool,
+    /// Priority lane configuration.
+    priority_lane: PriorityLaneConfig,
+    /// Limits on the transactions of a single author.
+    author_quota: AuthorQuotaConfig,
+    /// Compactor enforcing the retention policies.
+    compactor: Option<Compactor>,
+    /// Maximum size of the transactions cache in bytes.
+    tx_cache_memory_limit: usize,
+    /// Server streaming consensus events to external observers.
+    observer: Option<ConsensusObserver>,
+    /// Clock sanity configuration.
+    clock: ClockSanityConfig,
+    /// Alert rules evaluated on the metrics of the node.
+    alerts: AlertEngine,
+    /// Watchdog of the memory and disk space used by the node.
+    resources: ResourceWatchdog,
+    /// Propagation of transactions to the peers.
+    gossip: Gossip,
+    /// Order in which the known peers are connected on start.
+    reconnect_order: ReconnectOrder,
+    /// Scheduled timeouts that have not fired yet, with their deadlines.
+    timeouts: BTreeMap<NodeTimeout, SystemTime>,
+    /// Identifier of the network the messages are signed for.
+    network_id: u32,
+    /// Archive of the observed consensus messages, if enabled.
+    consensus_archive: Option<ConsensusArchive>,
 }
 
 /// Service configuration.
@@ -180,6 +301,41 @@ pub struct NodeApiConfig {
     ///
     /// [cors]: https://developer.mozilla.org/en-US/docs/Web/HTTP/CORS
     pub private_allow_origin: Option<AllowOrigin>,
+    /// Memory limit of the block header cache used by the explorer API, in bytes.
+    /// If not specified, [`DEFAULT_BLOCK_HEADER_CACHE_SIZE`] is used.
+    ///
+    /// [`DEFAULT_BLOCK_HEADER_CACHE_SIZE`]: ../explorer/constant.DEFAULT_BLOCK_HEADER_CACHE_SIZE.html
+    #[serde(default)]
+    pub block_header_cache_size: Option<usize>,
+    /// Access log configuration of both public and private API. If not specified,
+    /// requests are not logged, but their latencies are still recorded.
+    #[serde(default)]
+    pub access_log: Option<AccessLogConfig>,
+    /// Time during which the health check reports the node as stopping before the API
+    /// stops accepting connections, in milliseconds. This allows load balancers to
+    /// stop routing requests to the node. If not specified, the API is stopped at once.
+    #[serde(default)]
+    pub shutdown_delay: Option<Milliseconds>,
+    /// Time given to the in-flight API requests to complete once the API stops
+    /// accepting connections, in milliseconds. If not specified,
+    /// [`DEFAULT_DRAIN_TIMEOUT`] is used.
+    ///
+    /// [`DEFAULT_DRAIN_TIMEOUT`]: ../api/backends/actix/constant.DEFAULT_DRAIN_TIMEOUT.html
+    #[serde(default)]
+    pub drain_timeout: Option<Milliseconds>,
+    /// Enables the private endpoints exposing the raw contents of the database,
+    /// see `v1/storage` in the private system API. The endpoints bypass the service
+    /// schemas and may be slow on large indexes, so they are intended for debugging
+    /// only. Disabled by default.
+    #[serde(default)]
+    pub storage_inspection: bool,
+    /// Path to the Unix domain socket on which the node accepts transactions from
+    /// other processes. See [`UnixSocketTransport`] for details. The socket is not
+    /// created if not specified.
+    ///
+    /// [`UnixSocketTransport`]: struct.UnixSocketTransport.html
+    #[serde(default)]
+    pub transaction_socket: Option<PathBuf>,
 }
 
 impl Default for NodeApiConfig {
@@ -190,6 +346,12 @@ impl Default for NodeApiConfig {
             private_api_address: None,
             public_allow_origin: None,
             private_allow_origin: None,
+            block_header_cache_size: None,
+            access_log: None,
+            shutdown_delay: None,
+            drain_timeout: None,
+            storage_inspection: false,
+            transaction_socket: None,
         }
     }
 }
@@ -218,22 +380,105 @@ impl Default for EventsPoolCapacity {
     }
 }
 
+/// Default limit of the memory occupied by the uncommitted transactions cache, in bytes.
+pub const DEFAULT_TX_CACHE_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
 /// Memory pool configuration parameters.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct MemoryPoolConfig {
     /// Sets the maximum number of messages that can be buffered on the event loop's
     /// notification channel before a send will fail.
     pub events_pool_capacity: EventsPoolCapacity,
+    /// Priority lane for transactions of the system services.
+    #[serde(default)]
+    pub priority_lane: PriorityLaneConfig,
+    /// Limits on the transactions of a single author.
+    #[serde(default)]
+    pub author_quota: AuthorQuotaConfig,
+    /// Maximum total size in bytes of the uncommitted transactions kept in memory.
+    /// Once the limit is exceeded, the cached transactions are moved to the storage and
+    /// are added to the persistent pool after the next commit. If the value is absent,
+    /// `DEFAULT_TX_CACHE_MEMORY_LIMIT` is used.
+    #[serde(default)]
+    pub tx_cache_memory_limit: Option<usize>,
 }
 
 impl Default for MemoryPoolConfig {
     fn default() -> Self {
         Self {
             events_pool_capacity: EventsPoolCapacity::default(),
+            priority_lane: PriorityLaneConfig::default(),
+            author_quota: AuthorQuotaConfig::default(),
+            tx_cache_memory_limit: None,
         }
     }
 }
 
+/// Priority lane configuration.
+///
+/// Transactions of the listed services (usually, governance services such as the
+/// configuration updater) are tracked separately from the rest of the pool. When this
+/// node proposes a block, up to `reserved_txs` slots of the block are filled with priority
+/// transactions first; the block space left unused by regular transactions is also
+/// given to the priority lane. This keeps the network administrable when the pool is
+/// flooded with user transactions.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct PriorityLaneConfig {
+    /// Identifiers of the services whose transactions go to the priority lane.
+    #[serde(default)]
+    pub services: BTreeSet<u16>,
+    /// Number of transactions in each proposed block reserved for the priority lane.
+    #[serde(default)]
+    pub reserved_txs: u32,
+}
+
+impl PriorityLaneConfig {
+    /// Returns `true` if transactions of the given service belong to the priority lane.
+    pub fn is_priority_service(&self, service_id: u16) -> bool {
+        self.services.contains(&service_id)
+    }
+
+    /// Selects at most `limit` transactions for a proposal. Priority transactions take
+    /// the reserved part of the block, regular transactions take the rest, and the space
+    /// not used by the regular transactions is filled with the remaining priority ones.
+    /// The transactions are pulled from the iterators only while there is room for them,
+    /// so each yielded transaction is selected.
+    pub(crate) fn select_txs<P, R>(&self, limit: usize, priority: P, regular: R) -> Vec<Hash>
+    where
+        P: IntoIterator<Item = Hash>,
+        R: IntoIterator<Item = Hash>,
+    {
+        let mut priority = priority.into_iter();
+        let reserved = cmp::min(self.reserved_txs as usize, limit);
+
+        let mut txs: Vec<Hash> = priority.by_ref().take(reserved).collect();
+        let remaining = limit - txs.len();
+        txs.extend(regular.into_iter().take(remaining));
+        let remaining = limit - txs.len();
+        txs.extend(priority.take(remaining));
+        txs
+    }
+}
+
+/// Limits on the transactions of a single author.
+///
+/// The limits keep a single key from flooding the pool and monopolizing the blocks.
+/// The number of the uncommitted transactions of an author is checked when the node
+/// receives a transaction; the transactions awaited to complete the proposals of the peers
+/// are accepted regardless of it. The number of the transactions of an author in a block
+/// is checked when this node proposes a block.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuthorQuotaConfig {
+    /// Maximum number of the uncommitted transactions of an author accepted into the pool.
+    /// Not limited if absent.
+    #[serde(default)]
+    pub max_pooled_txs: Option<u32>,
+    /// Maximum number of the transactions of an author included into a block proposed
+    /// by this node. Not limited if absent.
+    #[serde(default)]
+    pub max_block_txs: Option<u32>,
+}
+
 /// Configuration for the `Node`.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct NodeConfig<T = SecretKey> {
@@ -267,6 +512,67 @@ pub struct NodeConfig<T = SecretKey> {
     pub connect_list: ConnectListConfig,
     /// Transaction Verification Thread Pool size.
     pub thread_pool_size: Option<u8>,
+    /// Optional path to the genesis state dump, which is imported into the storage
+    /// before the genesis block is created. The dump is ignored if the storage already
+    /// contains the genesis block. See [`GenesisState`] for details.
+    ///
+    /// [`GenesisState`]: ../blockchain/struct.GenesisState.html
+    #[serde(default)]
+    pub genesis_state: Option<PathBuf>,
+    /// Retention windows of the prunable data. See [`RetentionConfig`] for details.
+    ///
+    /// [`RetentionConfig`]: ../blockchain/struct.RetentionConfig.html
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Optional address on which external observers can subscribe to the stream
+    /// of consensus events. See [`ConsensusEvent`] for details.
+    ///
+    /// [`ConsensusEvent`]: enum.ConsensusEvent.html
+    #[serde(default)]
+    pub observer_address: Option<SocketAddr>,
+    /// Number of the latest blocks verified by the storage integrity check before
+    /// the node start. The check is disabled if not set. See [`check_integrity`]
+    /// for details.
+    ///
+    /// [`check_integrity`]: ../blockchain/fn.check_integrity.html
+    #[serde(default)]
+    pub integrity_check_depth: Option<u64>,
+    /// Thresholds of the skew of the local clock relative to the validators.
+    /// See [`ClockSanityConfig`] for details.
+    ///
+    /// [`ClockSanityConfig`]: struct.ClockSanityConfig.html
+    #[serde(default)]
+    pub clock: ClockSanityConfig,
+    /// Names of the untrusted services executed in separate processes. The option is
+    /// experimental and is supported only by the nodes launched with `NodeBuilder`.
+    /// See [`IsolatedService`] for details.
+    ///
+    /// [`IsolatedService`]: ../blockchain/struct.IsolatedService.html
+    #[serde(default)]
+    pub isolated_services: Vec<String>,
+    /// Whether to record the hashes of the changes made by each block, which can
+    /// be compared among the nodes to find the nondeterministic services. The hashes
+    /// are available via the private API.
+    #[serde(default)]
+    pub record_patch_hashes: bool,
+    /// Alert rules checked by the node. See [`AlertRule`] for details.
+    ///
+    /// [`AlertRule`]: struct.AlertRule.html
+    #[serde(default)]
+    pub alerts: Vec<AlertRule>,
+    /// Limits of the memory and disk space used by the node. See [`ResourceLimitsConfig`]
+    /// for details.
+    ///
+    /// [`ResourceLimitsConfig`]: struct.ResourceLimitsConfig.html
+    #[serde(default)]
+    pub resources: ResourceLimitsConfig,
+    /// Retention limits of the archive of all the consensus messages observed by
+    /// the node. The archive is meant for the analysis of the round failures in staging
+    /// networks and is disabled if not set. See [`ConsensusArchiveConfig`] for details.
+    ///
+    /// [`ConsensusArchiveConfig`]: struct.ConsensusArchiveConfig.html
+    #[serde(default)]
+    pub consensus_archive: Option<ConsensusArchiveConfig>,
 }
 
 impl NodeConfig<PathBuf> {
@@ -310,39 +616,59 @@ impl NodeConfig<PathBuf> {
             database: self.database,
             connect_list: self.connect_list,
             thread_pool_size: self.thread_pool_size,
+            genesis_state: self.genesis_state,
+            retention: self.retention,
+            observer_address: self.observer_address,
+            integrity_check_depth: self.integrity_check_depth,
+            clock: self.clock,
+            isolated_services: self.isolated_services,
+            record_patch_hashes: self.record_patch_hashes,
+            alerts: self.alerts,
+            resources: self.resources,
+            consensus_archive: self.consensus_archive,
         }
     }
 }
 
 impl<T> NodeConfig<T> {
-    fn validate_or_panic(&self) {
+    /// Checks that the configuration is consistent, e.g., that the capacities of
+    /// the event pools are in the allowed ranges and the alert rules are valid.
+    pub fn validate(&self) -> Result<(), failure::Error> {
         let capacity = &self.mempool.events_pool_capacity;
-        if capacity.internal_events_capacity < 3 {
-            panic!(
-                "internal_events_capacity({}) must be strictly larger than 2",
-                capacity.internal_events_capacity
-            );
-        }
-        if capacity.network_requests_capacity == 0 {
-            panic!(
-                "network_requests_capacity({}) must be strictly larger than 0",
-                capacity.network_requests_capacity
-            );
-        }
+        ensure!(
+            capacity.internal_events_capacity >= 3,
+            "internal_events_capacity({}) must be strictly larger than 2",
+            capacity.internal_events_capacity
+        );
+        ensure!(
+            capacity.network_requests_capacity > 0,
+            "network_requests_capacity({}) must be strictly larger than 0",
+            capacity.network_requests_capacity
+        );
         // Sanity checks for cases of accidental negative overflows.
         let sanity_max = 2_usize.pow(16);
-        if capacity.internal_events_capacity >= sanity_max {
-            panic!(
-                "internal_events_capacity({}) must be smaller than {}",
-                capacity.internal_events_capacity, sanity_max,
-            );
-        }
-        if capacity.network_requests_capacity >= sanity_max {
-            panic!(
-                "network_requests_capacity({}) must be smaller than {}",
-                capacity.network_requests_capacity, sanity_max,
-            );
-        }
+        ensure!(
+            capacity.internal_events_capacity < sanity_max,
+            "internal_events_capacity({}) must be smaller than {}",
+            capacity.internal_events_capacity,
+            sanity_max
+        );
+        ensure!(
+            capacity.network_requests_capacity < sanity_max,
+            "network_requests_capacity({}) must be smaller than {}",
+            capacity.network_requests_capacity,
+            sanity_max
+        );
+        validate_alert_rules(&self.alerts)
+            .map_err(|e| format_err!("Invalid alert rules: {}", e))?;
+        self.network
+            .gossip
+            .validate()
+            .map_err(|e| format_err!("Invalid gossip configuration: {}", e))?;
+        self.resources
+            .validate()
+            .map_err(|e| format_err!("Invalid resource limits: {}", e))?;
+        Ok(())
     }
 }
 
@@ -359,6 +685,18 @@ pub struct Configuration {
     pub peer_discovery: Vec<String>,
     /// Memory pool configuration.
     pub mempool: MemoryPoolConfig,
+    /// Retention windows of the prunable data.
+    pub retention: RetentionConfig,
+    /// Address on which consensus events are streamed to external observers.
+    pub observer_address: Option<SocketAddr>,
+    /// Clock sanity configuration.
+    pub clock: ClockSanityConfig,
+    /// Alert rules.
+    pub alerts: Vec<AlertRule>,
+    /// Resource limits.
+    pub resources: ResourceLimitsConfig,
+    /// Retention limits of the consensus archive, if it is enabled.
+    pub consensus_archive: Option<ConsensusArchiveConfig>,
 }
 
 /// Channel for messages, timeouts and api requests.
@@ -482,6 +820,7 @@ impl NodeHandler {
 
         let stored = Schema::new(&snapshot).actual_configuration();
         info!("Creating a node with config: {:#?}", stored);
+        let network_id = Schema::new(&snapshot).network_id();
 
         let validator_id = stored
             .validator_keys
@@ -489,18 +828,19 @@ impl NodeHandler {
             .position(|pk| pk.consensus_key == config.listener.consensus_public_key)
             .map(|id| ValidatorId(id as u16));
         info!("Validator id = '{:?}'", validator_id);
-        let connect = Message::concrete(
+        let connect = Message::concrete_for_network(
             Connect::new(
                 external_address,
                 system_state.current_time().into(),
                 &user_agent::get(),
             ),
+            network_id,
             config.listener.consensus_public_key,
             &config.listener.consensus_secret_key,
         );
 
         let connect_list = config.listener.connect_list;
-        let state = State::new(
+        let mut state = State::new(
             validator_id,
             config.listener.consensus_public_key,
             config.listener.consensus_secret_key,
@@ -515,6 +855,38 @@ impl NodeHandler {
             system_state.current_time(),
         );
 
+        // Transactions spilled before a restart are added to the pool on the next commit.
+        state.add_spilled_txs(Schema::new(&snapshot).spilled_transactions().iter());
+        let tx_cache_memory_limit = config
+            .mempool
+            .tx_cache_memory_limit
+            .unwrap_or(DEFAULT_TX_CACHE_MEMORY_LIMIT);
+
+        let priority_lane = config.mempool.priority_lane;
+        if !priority_lane.services.is_empty() {
+            let schema = Schema::new(&snapshot);
+            let transactions = schema.transactions();
+            for hash in schema.transactions_pool().iter() {
+                let is_priority = transactions.get(&hash).map_or(false, |tx| {
+                    priority_lane.is_priority_service(tx.payload().service_id())
+                });
+                if is_priority {
+                    state.add_priority_tx(hash);
+                }
+            }
+        }
+
+        let author_quota = config.mempool.author_quota;
+        if author_quota.max_pooled_txs.is_some() {
+            let schema = Schema::new(&snapshot);
+            let transactions = schema.transactions();
+            for hash in schema.transactions_pool().iter() {
+                if let Some(tx) = transactions.get(&hash) {
+                    state.add_tx_author(hash, tx.author());
+                }
+            }
+        }
+
         let node_role = NodeRole::new(validator_id);
         let is_enabled = api_state.is_enabled();
         api_state.set_node_role(node_role);
@@ -524,6 +896,37 @@ impl NodeHandler {
             None => None,
         };
 
+        let compactor = Compactor::start(
+            blockchain.clone(),
+            config.retention.clone(),
+            api_state.block_proof_cache().clone(),
+        );
+
+        let observer = config.observer_address.and_then(|address| {
+            match ConsensusObserver::start(address) {
+                Ok(observer) => {
+                    info!(
+                        "Streaming consensus events to observers on {}",
+                        observer.local_addr()
+                    );
+                    Some(observer)
+                }
+                Err(e) => {
+                    error!("Unable to start consensus observer on {}: {}", address, e);
+                    None
+                }
+            }
+        });
+
+        let alerts = AlertEngine::new(config.alerts, api_state.firing_alerts().clone());
+        let gossip = Gossip::new(config.network.gossip);
+        let reconnect_order = config.network.reconnect_order;
+        let resources = ResourceWatchdog::new(config.resources);
+        let consensus_archive = config.consensus_archive.map(|config| {
+            warn!("Consensus archive is enabled, all consensus messages are stored");
+            ConsensusArchive::new(config)
+        });
+
         Self {
             blockchain,
             api_state,
@@ -535,12 +938,26 @@ impl NodeHandler {
             node_role,
             config_manager,
             allow_expedited_propose: true,
+            priority_lane,
+            author_quota,
+            compactor,
+            tx_cache_memory_limit,
+            observer,
+            clock: config.clock,
+            alerts,
+            resources,
+            gossip,
+            reconnect_order,
+            timeouts: BTreeMap::new(),
+            network_id,
+            consensus_archive,
         }
     }
 
     fn sign_message<T: ProtocolMessage>(&self, message: T) -> Signed<T> {
-        Message::concrete(
+        Message::concrete_for_network(
             message,
+            self.network_id,
             *self.state.consensus_public_key(),
             self.state.consensus_secret_key(),
         )
@@ -615,6 +1032,8 @@ impl NodeHandler {
             let it = it.filter(|address| address != &self.state.our_connect_message().author());
             it.collect()
         };
+        let mut peers: Vec<_> = peers.into_iter().collect();
+        self.reconnect_order.sort(&mut peers, self.state.validators());
 
         for key in peers {
             self.connect(key);
@@ -647,6 +1066,13 @@ impl NodeHandler {
         self.add_update_api_state_timeout();
     }
 
+    /// Reports the consensus event to the connected observers, if any.
+    fn observe(&self, event: ConsensusEvent) {
+        if let Some(ref observer) = self.observer {
+            observer.notify(self.system_state.current_time(), event);
+        }
+    }
+
     /// Sends the given message to a peer by its public key.
     pub fn send_to_peer<T: Into<SignedMessage>>(&mut self, public_key: PublicKey, message: T) {
         let message = message.into();
@@ -654,26 +1080,37 @@ impl NodeHandler {
         self.channel.network_requests.send(request).log_error();
     }
 
-    /// Broadcasts given message to all peers.
-    pub(crate) fn broadcast<M: Into<SignedMessage>>(&mut self, message: M) {
-        let peers: Vec<PublicKey> = self
-            .state
-            .peers()
-            .iter()
-            .filter_map(|(pubkey, _)| {
-                if self.state.connect_list().is_peer_allowed(pubkey) {
-                    Some(*pubkey)
-                } else {
-                    None
-                }
-            })
-            .collect();
+    /// Broadcasts given message to all peers. Returns the number of peers the message
+    /// has been sent to.
+    pub(crate) fn broadcast<M: Into<SignedMessage>>(&mut self, message: M) -> usize {
+        let peers = self.allowed_peers();
         let message = message.into();
-        for address in peers {
+        for address in &peers {
+            self.send_to_peer(*address, message.clone());
+        }
+        peers.len()
+    }
+
+    /// Sends the transaction to the peers chosen according to the gossip configuration.
+    /// `relayed` is set for the transactions received from other peers.
+    pub(crate) fn gossip_transaction(&mut self, tx: Signed<RawTransaction>, relayed: bool) {
+        let peers = self.allowed_peers();
+        let targets = self.gossip.targets(tx.hash(), peers, relayed);
+        let message = SignedMessage::from(tx);
+        for address in targets {
             self.send_to_peer(address, message.clone());
         }
     }
 
+    fn allowed_peers(&self) -> Vec<PublicKey> {
+        self.state
+            .peers()
+            .keys()
+            .filter(|pubkey| self.state.connect_list().is_peer_allowed(pubkey))
+            .cloned()
+            .collect()
+    }
+
     /// Performs connection to the specified network address.
     pub fn connect(&mut self, key: PublicKey) {
         let connect = self.state.our_connect_message().clone();
@@ -682,6 +1119,7 @@ impl NodeHandler {
 
     /// Add timeout request.
     pub fn add_timeout(&mut self, timeout: NodeTimeout, time: SystemTime) {
+        self.timeouts.insert(timeout.clone(), time);
         let request = TimeoutRequest(time, timeout);
         self.channel
             .internal_requests
@@ -699,7 +1137,15 @@ impl NodeHandler {
 
     /// Adds `NodeTimeout::Round` timeout to the channel.
     pub fn add_round_timeout(&mut self) {
-        let time = self.round_start_time(self.state.round().next());
+        let round = self.state.round();
+        let mut time = self.round_start_time(round.next());
+        // A leader lagging behind the network is unlikely to send a propose in time,
+        // so its round ends as soon as the propose timeout expires.
+        if self.state.is_leader_lagging(round) {
+            let propose_time =
+                self.round_start_time(round) + Duration::from_millis(self.max_propose_timeout());
+            time = cmp::min(time, propose_time);
+        }
         trace!(
             "ADD ROUND TIMEOUT: time={:?}, height={}, round={}",
             time,
@@ -740,8 +1186,8 @@ impl NodeHandler {
 
     fn need_faster_propose(&self) -> bool {
         let snapshot = self.blockchain.snapshot();
-        let pending_tx_count =
-            Schema::new(&snapshot).transactions_pool_len() + self.state.tx_cache_len() as u64;
+        let pending_tx_count = Schema::new(&snapshot).transactions_pool_len()
+            + (self.state.tx_cache_len() + self.state.spilled_txs().len()) as u64;
         pending_tx_count >= u64::from(self.propose_timeout_threshold())
     }
 
@@ -780,7 +1226,8 @@ impl NodeHandler {
 
     /// Returns the number of uncommitted transactions.
     pub fn uncommitted_txs_count(&self) -> u64 {
-        self.blockchain.pool_size() + self.state.tx_cache_len() as u64
+        self.blockchain.pool_size()
+            + (self.state.tx_cache_len() + self.state.spilled_txs().len()) as u64
     }
 
     /// Returns start time of the requested round.
@@ -811,7 +1258,22 @@ impl fmt::Debug for NodeHandler {
 impl ApiSender {
     /// Creates new `ApiSender` with given channel.
     pub fn new(inner: mpsc::Sender<ExternalMessage>) -> Self {
-        ApiSender(inner)
+        ApiSender(ApiTransport::Channel(inner))
+    }
+
+    /// Creates an `ApiSender` sending transactions over the given transport.
+    /// Only transactions can be sent; other messages result in an error.
+    pub fn with_transport<T: RemoteTransport>(transport: T) -> Self {
+        ApiSender(ApiTransport::Remote(Arc::new(transport)))
+    }
+
+    /// Creates an `ApiSender` connected to the transaction socket of the node
+    /// at the given path. See [`UnixSocketTransport`] for details.
+    ///
+    /// [`UnixSocketTransport`]: struct.UnixSocketTransport.html
+    #[cfg(unix)]
+    pub fn unix_socket<P: AsRef<Path>>(path: P) -> ::std::io::Result<Self> {
+        UnixSocketTransport::connect(path).map(Self::with_transport)
     }
 
     /// Add peer to peer list
@@ -822,12 +1284,27 @@ impl ApiSender {
 
     /// Sends an external message.
     pub fn send_external_message(&self, message: ExternalMessage) -> Result<(), Error> {
-        self.0
-            .clone()
-            .send(message)
-            .wait()
-            .map(drop)
-            .map_err(into_failure)
+        let transport = match self.0 {
+            ApiTransport::Channel(ref sender) => {
+                return sender
+                    .clone()
+                    .send(message)
+                    .wait()
+                    .map(drop)
+                    .map_err(into_failure);
+            }
+            ApiTransport::Remote(ref transport) => transport,
+        };
+        match message {
+            ExternalMessage::Transaction(tx) => transport.send_transaction(tx, None),
+            ExternalMessage::TaggedTransaction { transaction, tag } => {
+                transport.send_transaction(transaction, Some(tag))
+            }
+            other => bail!(
+                "Message {:?} cannot be sent to the node over a remote transport",
+                other
+            ),
+        }
     }
 
     /// Broadcast transaction to other node.
@@ -835,6 +1312,58 @@ impl ApiSender {
         let msg = ExternalMessage::Transaction(tx);
         self.send_external_message(msg)
     }
+
+    /// Broadcasts the transaction like `broadcast_transaction` does. If the tag is
+    /// specified, it is stored by the node together with the transaction.
+    pub fn broadcast_tagged_transaction(
+        &self,
+        tx: Signed<RawTransaction>,
+        tag: Option<TransactionTag>,
+    ) -> Result<(), Error> {
+        let msg = match tag {
+            Some(tag) => ExternalMessage::TaggedTransaction {
+                transaction: tx,
+                tag,
+            },
+            None => ExternalMessage::Transaction(tx),
+        };
+        self.send_external_message(msg)
+    }
+
+    /// Rebroadcasts a transaction from the pool of the node to the connected peers.
+    /// The returned receiver is resolved with the number of peers the transaction
+    /// has been sent to, or `None` if the transaction is not in the pool.
+    pub fn rebroadcast_transaction(
+        &self,
+        tx_hash: Hash,
+    ) -> Result<oneshot::Receiver<Option<usize>>, Error> {
+        let (reply, receiver) = oneshot::channel();
+        let msg = ExternalMessage::RebroadcastTransaction { tx_hash, reply };
+        self.send_external_message(msg).map(|()| receiver)
+    }
+
+    /// Requests the dump of the consensus state. The returned receiver is resolved
+    /// by the node thread once it processes the request.
+    pub fn consensus_state(&self) -> Result<oneshot::Receiver<ConsensusStateInfo>, Error> {
+        let (reply, receiver) = oneshot::channel();
+        let msg = ExternalMessage::ConsensusState(reply);
+        self.send_external_message(msg).map(|()| receiver)
+    }
+
+    /// Sends a service message to the validator with the given identifier.
+    pub fn send_to_validator(
+        &self,
+        to: ValidatorId,
+        service_id: u16,
+        payload: Vec<u8>,
+    ) -> Result<(), Error> {
+        let msg = ExternalMessage::ServiceMessage {
+            to,
+            service_id,
+            payload,
+        };
+        self.send_external_message(msg)
+    }
 }
 
 impl fmt::Debug for ApiSender {
@@ -928,15 +1457,57 @@ impl NodeChannel {
 
 impl Node {
     /// Creates node for the given services and node configuration.
+    ///
+    /// The node does not perform the startup checks; use [`try_new`](#method.try_new)
+    /// to verify the configuration against the storage before creating the node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration is invalid or the storage cannot be initialized
+    /// with the genesis configuration.
     pub fn new<D: Into<Arc<dyn Database>>>(
         db: D,
         services: Vec<Box<dyn Service>>,
         node_cfg: NodeConfig,
         config_file_path: Option<String>,
     ) -> Self {
+        Self::create(db, services, node_cfg, config_file_path, None)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates node for the given services and node configuration after checking
+    /// the configuration against the storage. The node keys, the genesis configuration,
+    /// the services and the listen addresses are checked before the storage is
+    /// initialized, and a [`PreflightError`] listing all detected problems is returned,
+    /// if any.
+    ///
+    /// [`PreflightError`]: struct.PreflightError.html
+    pub fn try_new<D: Into<Arc<dyn Database>>>(
+        db: D,
+        services: Vec<Box<dyn Service>>,
+        node_cfg: NodeConfig,
+        config_file_path: Option<String>,
+    ) -> Result<Self, failure::Error> {
+        let startup_checks = StartupChecks::new(&node_cfg);
+        Self::create(
+            db,
+            services,
+            node_cfg,
+            config_file_path,
+            Some(startup_checks),
+        )
+    }
+
+    fn create<D: Into<Arc<dyn Database>>>(
+        db: D,
+        services: Vec<Box<dyn Service>>,
+        node_cfg: NodeConfig,
+        config_file_path: Option<String>,
+        startup_checks: Option<StartupChecks>,
+    ) -> Result<Self, failure::Error> {
         crypto::init();
 
-        node_cfg.validate_or_panic();
+        node_cfg.validate()?;
 
         let channel = NodeChannel::new(&node_cfg.mempool.events_pool_capacity);
         let mut blockchain = Blockchain::new(
@@ -946,7 +1517,27 @@ impl Node {
             node_cfg.service_secret_key.clone(),
             ApiSender::new(channel.api_requests.0.clone()),
         );
-        blockchain.initialize(node_cfg.genesis.clone()).unwrap();
+        if let Some(startup_checks) = startup_checks {
+            startup_checks.run(&blockchain)?;
+        }
+        blockchain.set_record_patch_hashes(node_cfg.record_patch_hashes);
+        if let Some(ref archive) = node_cfg.retention.transactions {
+            let archive = TransactionArchive::from_config(archive)
+                .map_err(|e| format_err!("Unable to open the transaction archive: {}", e))?;
+            blockchain.set_transaction_archive(archive);
+        }
+        let has_genesis_block = !Schema::new(&blockchain.snapshot())
+            .block_hashes_by_height()
+            .is_empty();
+        // The genesis state is only needed to create the genesis block.
+        if !has_genesis_block {
+            let genesis_state = match node_cfg.genesis_state {
+                Some(ref path) => GenesisState::load(path)
+                    .map_err(|e| format_err!("Unable to load genesis state: {}", e))?,
+                None => GenesisState::default(),
+            };
+            blockchain.initialize_with_state(node_cfg.genesis.clone(), genesis_state)?;
+        }
 
         let peers = node_cfg.connect_list.addresses();
 
@@ -964,11 +1555,23 @@ impl Node {
             mempool: node_cfg.mempool,
             network: node_cfg.network,
             peer_discovery: peers,
+            retention: node_cfg.retention,
+            observer_address: node_cfg.observer_address,
+            clock: node_cfg.clock,
+            alerts: node_cfg.alerts,
+            resources: node_cfg.resources,
+            consensus_archive: node_cfg.consensus_archive,
         };
 
-        let api_state = SharedNodeState::new(node_cfg.api.state_update_timeout as u64);
+        let header_cache_size = node_cfg
+            .api
+            .block_header_cache_size
+            .unwrap_or(DEFAULT_BLOCK_HEADER_CACHE_SIZE);
+        let api_state = SharedNodeState::new(node_cfg.api.state_update_timeout as u64)
+            .with_block_header_cache(BlockHeaderCache::new(header_cache_size))
+            .with_storage_inspection(node_cfg.api.storage_inspection);
         let system_state = Box::new(DefaultSystemState(node_cfg.listen_address));
-        let network_config = config.network;
+        let network_config = config.network.clone();
         let handler = NodeHandler::new(
             blockchain,
             &node_cfg.external_address,
@@ -978,14 +1581,14 @@ impl Node {
             api_state,
             config_file_path,
         );
-        Self {
+        Ok(Self {
             api_options: node_cfg.api,
             handler,
             channel,
             network_config,
             max_message_len: node_cfg.genesis.consensus.max_message_len,
             thread_pool_size: node_cfg.thread_pool_size,
-        }
+        })
     }
 
     /// Launches only consensus messages handler.
@@ -994,6 +1597,7 @@ impl Node {
         self.handler.initialize();
 
         let pool_size = self.thread_pool_size;
+        let blockchain = self.handler.blockchain.clone();
         let (handler_part, network_part, internal_part) = self.into_reactor();
         let handshake_params = handshake_params.clone();
 
@@ -1017,8 +1621,12 @@ impl Node {
         });
 
         let mut core = Core::new().map_err(into_failure)?;
-        core.run(handler_part.run())
-            .map_err(|_| format_err!("An error in the `Handler` thread occurred"))?;
+        let scheduler = JobScheduler::start(blockchain);
+        let handler_result = core.run(handler_part.run());
+        if let Some(scheduler) = scheduler {
+            scheduler.stop();
+        }
+        handler_result.map_err(|_| format_err!("An error in the `Handler` thread occurred"))?;
         network_thread.join().unwrap()
     }
 
@@ -1030,6 +1638,10 @@ impl Node {
     pub fn run(self) -> Result<(), failure::Error> {
         trace!("Running node.");
         let api_state = self.handler.api_state.clone();
+        let drain_timeout = self
+            .api_options
+            .drain_timeout
+            .unwrap_or(DEFAULT_DRAIN_TIMEOUT);
         // Runs actix-web api.
         let actix_api_runtime = SystemRuntimeConfig {
             api_runtimes: {
@@ -1052,6 +1664,8 @@ impl Node {
                             .public_allow_origin
                             .clone()
                             .map(into_app_config),
+                        access_log: self.api_options.access_log.clone(),
+                        drain_timeout,
                     })
                     .into_iter();
                 let private_api_handler = self
@@ -1065,6 +1679,8 @@ impl Node {
                             .private_allow_origin
                             .clone()
                             .map(into_app_config),
+                        access_log: self.api_options.access_log.clone(),
+                        drain_timeout,
                     })
                     .into_iter();
                 // Collects API handlers.
@@ -1079,6 +1695,22 @@ impl Node {
         }
         .start()?;
 
+        // Accepts transactions from other processes.
+        #[cfg(unix)]
+        let transaction_socket = match self.api_options.transaction_socket {
+            Some(ref path) => {
+                info!("Accepting transactions on socket {}", path.display());
+                Some(TransportServer::start(path, self.channel())?)
+            }
+            None => None,
+        };
+        #[cfg(not(unix))]
+        {
+            if self.api_options.transaction_socket.is_some() {
+                warn!("Transaction socket is not supported on this platform");
+            }
+        }
+
         // Runs NodeHandler.
         let handshake_params = HandshakeParams::new(
             *self.state().consensus_public_key(),
@@ -1087,7 +1719,17 @@ impl Node {
             self.state().our_connect_message().clone(),
             self.max_message_len,
         );
+        let shutdown_delay = self.api_options.shutdown_delay;
         self.run_handler(&handshake_params)?;
+        #[cfg(unix)]
+        drop(transaction_socket);
+
+        // Let load balancers notice that the node is stopping before closing the API.
+        api_state.set_stopping();
+        if let Some(delay) = shutdown_delay {
+            info!("Waiting {} ms before stopping the API", delay);
+            thread::sleep(Duration::from_millis(delay));
+        }
 
         // Stop ws server.
         api_state.shutdown_broadcast_server();
@@ -1196,7 +1838,7 @@ mod tests {
             "test service"
         }
 
-        fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
             vec![]
         }
 
@@ -1228,6 +1870,13 @@ mod tests {
         assert_eq!(node.state().tx_cache_len(), 1);
         assert_eq!(schema.transactions_pool_len(), 0);
 
+        // The cached transaction is visible to the API once its state is updated.
+        let api_state = node.handler.api_state.clone();
+        assert!(!api_state.is_tx_cached(&tx.hash()));
+        api_state.update_node_state(node.state());
+        assert!(api_state.is_tx_cached(&tx.hash()));
+        assert_eq!(api_state.tx_cache_size(), 1);
+
         // Create duplicated transaction.
         let tx_copy = tx.clone();
         let event = ExternalMessage::Transaction(tx_copy);
@@ -1240,6 +1889,112 @@ mod tests {
         assert_eq!(schema.transactions_pool_len(), 0);
     }
 
+    #[test]
+    fn test_tx_cache_spilled_to_storage() {
+        let (p_key, s_key) = gen_keypair();
+
+        let db = Arc::from(Box::new(TemporaryDB::new()) as Box<dyn Database>) as Arc<dyn Database>;
+        let services = vec![Box::new(TestService) as Box<dyn Service>];
+        let mut node_cfg = helpers::generate_testnet_config(1, 16_500)[0].clone();
+        node_cfg.mempool.tx_cache_memory_limit = Some(1);
+
+        let mut node = Node::new(db, services, node_cfg, None);
+
+        let tx = create_simple_tx(p_key, &s_key);
+        let event = ExternalMessage::Transaction(tx.clone());
+        node.handler.handle_event(event.into());
+
+        // The transaction exceeds the limit, so it is moved to the storage.
+        assert_eq!(node.state().tx_cache_len(), 0);
+        assert_eq!(node.state().tx_cache_bytes(), 0);
+        assert!(node.state().spilled_txs().contains(&tx.hash()));
+        assert_eq!(node.handler.uncommitted_txs_count(), 1);
+        let snapshot = node.blockchain().snapshot();
+        let schema = Schema::new(&snapshot);
+        assert!(schema.transactions().contains(&tx.hash()));
+        assert!(schema.spilled_transactions().contains(&tx.hash()));
+        assert_eq!(schema.transactions_pool_len(), 0);
+
+        // The spilled transaction is still recognized as a duplicate.
+        let event = ExternalMessage::Transaction(tx.clone());
+        node.handler.handle_event(event.into());
+        assert_eq!(node.state().tx_cache_len(), 0);
+        assert_eq!(node.state().spilled_txs().len(), 1);
+
+        let fork = node.blockchain().fork();
+        assert_eq!(Schema::new(&fork).pool_spilled_transactions(), 1);
+        let schema = Schema::new(&fork);
+        assert!(schema.transactions_pool().contains(&tx.hash()));
+        assert_eq!(schema.transactions_pool_len(), 1);
+        assert!(schema.spilled_transactions().iter().next().is_none());
+    }
+
+    #[test]
+    fn test_author_quota_limits_pooled_txs() {
+        let (p_key, s_key) = gen_keypair();
+
+        let db = Arc::from(Box::new(TemporaryDB::new()) as Box<dyn Database>) as Arc<dyn Database>;
+        let services = vec![Box::new(TestService) as Box<dyn Service>];
+        let mut node_cfg = helpers::generate_testnet_config(1, 16_500)[0].clone();
+        node_cfg.mempool.author_quota.max_pooled_txs = Some(2);
+
+        let mut node = Node::new(db, services, node_cfg, None);
+
+        let create_tx = |p_key: PublicKey, s_key: &SecretKey, text: &str| {
+            let mut msg = TxSimple::new();
+            msg.set_public_key(p_key.to_pb());
+            msg.set_msg(text.to_owned());
+            Message::sign_transaction(msg, SERVICE_ID, p_key, s_key)
+        };
+        for text in &["first", "second", "third"] {
+            let event = ExternalMessage::Transaction(create_tx(p_key, &s_key, text));
+            node.handler.handle_event(event.into());
+        }
+
+        // The third transaction exceeds the quota of the author.
+        assert_eq!(node.state().tx_cache_len(), 2);
+        assert_eq!(node.state().author_pooled_txs(&p_key), 2);
+        let rejected = node.handler.api_state.rejected_transactions().records();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].reason, RejectionReason::AuthorQuota);
+
+        // Other authors are not affected.
+        let (other_key, other_secret) = gen_keypair();
+        let event = ExternalMessage::Transaction(create_tx(other_key, &other_secret, "first"));
+        node.handler.handle_event(event.into());
+        assert_eq!(node.state().tx_cache_len(), 3);
+    }
+
+    #[test]
+    fn test_transactions_of_other_networks_are_rejected() {
+        let (p_key, s_key) = gen_keypair();
+
+        let db = Arc::from(Box::new(TemporaryDB::new()) as Box<dyn Database>) as Arc<dyn Database>;
+        let services = vec![Box::new(TestService) as Box<dyn Service>];
+        let mut node_cfg = helpers::generate_testnet_config(1, 16_500)[0].clone();
+        node_cfg.genesis.network_id = 7;
+
+        let mut node = Node::new(db, services, node_cfg, None);
+        assert_eq!(Schema::new(&node.blockchain().snapshot()).network_id(), 7);
+
+        let create_tx = |network_id: u32| {
+            let mut msg = TxSimple::new();
+            msg.set_public_key(p_key.to_pb());
+            msg.set_msg("replayed".to_owned());
+            Message::sign_transaction_for_network(msg, SERVICE_ID, network_id, p_key, &s_key)
+        };
+        let event = ExternalMessage::Transaction(create_tx(0));
+        node.handler.handle_event(event.into());
+        assert_eq!(node.state().tx_cache_len(), 0);
+        let rejected = node.handler.api_state.rejected_transactions().records();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].reason, RejectionReason::WrongNetwork);
+
+        let event = ExternalMessage::Transaction(create_tx(7));
+        node.handler.handle_event(event.into());
+        assert_eq!(node.state().tx_cache_len(), 1);
+    }
+
     #[test]
     fn test_transaction_without_service() {
         let (p_key, s_key) = gen_keypair();
@@ -1315,4 +2070,71 @@ mod tests {
             .network_requests_capacity = accidental_large_value;
         let _ = Node::new(db, services, node_cfg, None);
     }
+
+    #[test]
+    fn test_startup_checks_precede_node_creation() {
+        let db = Arc::from(Box::new(TemporaryDB::new()) as Box<dyn Database>) as Arc<dyn Database>;
+        let busy_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut node_cfg = helpers::generate_testnet_config(1, 16_500)[0].clone();
+        node_cfg.listen_address = busy_listener.local_addr().unwrap();
+
+        let err = Node::try_new(Arc::clone(&db), vec![], node_cfg, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unable to bind the network listen address"));
+        // The storage is left intact.
+        let snapshot = db.snapshot();
+        assert!(Schema::new(&snapshot).block_hashes_by_height().is_empty());
+    }
+
+    #[test]
+    fn test_genesis_state_is_loaded_only_for_new_storage() {
+        let db = Arc::from(Box::new(TemporaryDB::new()) as Box<dyn Database>) as Arc<dyn Database>;
+        let node_cfg = helpers::generate_testnet_config(1, 16_500)[0].clone();
+        let mut missing_state_cfg = node_cfg.clone();
+        missing_state_cfg.genesis_state = Some("missing-genesis-state.json".into());
+
+        let err = Node::create(
+            Arc::clone(&db),
+            vec![],
+            missing_state_cfg.clone(),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Unable to load genesis state"));
+
+        let _ = Node::new(Arc::clone(&db), vec![], node_cfg, None);
+        let _ = Node::new(db, vec![], missing_state_cfg, None);
+    }
+
+    #[test]
+    fn test_priority_lane_txs_selection() {
+        let priority: Vec<_> = (0..4_u8).map(|i| crypto::hash(&[i])).collect();
+        let regular: Vec<_> = (4..10_u8).map(|i| crypto::hash(&[i])).collect();
+        let lane = PriorityLaneConfig {
+            services: vec![SERVICE_ID].into_iter().collect(),
+            reserved_txs: 2,
+        };
+
+        // Regular transactions cannot take the reserved space.
+        let txs = lane.select_txs(5, priority.clone(), regular.clone());
+        assert_eq!(txs, [&priority[..2], &regular[..3]].concat());
+
+        // Unused space is given to the remaining priority transactions.
+        let txs = lane.select_txs(8, priority.clone(), regular[..3].to_vec());
+        assert_eq!(txs, [&priority[..2], &regular[..3], &priority[2..]].concat());
+
+        // Without priority transactions the whole block is available to regular ones.
+        let txs = lane.select_txs(5, vec![], regular.clone());
+        assert_eq!(txs, regular[..5].to_vec());
+
+        // Reserved space is capped by the block limit.
+        let lane = PriorityLaneConfig {
+            reserved_txs: 10,
+            ..lane
+        };
+        let txs = lane.select_txs(3, priority.clone(), regular);
+        assert_eq!(txs, priority[..3].to_vec());
+    }
 }
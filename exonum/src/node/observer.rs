@@ -0,0 +1,367 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming of consensus events to external observers.
+//!
+//! If the observer address is set in the node configuration, the node listens on it
+//! for TCP connections. Each connected process receives a stream of [`ObservedEvent`]s
+//! serialized as JSON, one event per line. The stream is write-only: the node ignores
+//! anything sent by observers.
+//!
+//! Observers are not allowed to slow the node down. If an observer does not keep up
+//! with the events or does not accept the written data for a while, its connection
+//! is closed and the observer has to reconnect.
+//!
+//! [`ObservedEvent`]: struct.ObservedEvent.html
+
+use chrono::{DateTime, Utc};
+
+use std::{
+    io::{self, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::crypto::Hash;
+use crate::helpers::{Height, Round, ValidatorId};
+
+/// Maximum number of events queued for an observer before it is disconnected.
+const OBSERVER_QUEUE_SIZE: usize = 1024;
+/// Maximum time an event is written to an observer before it is disconnected.
+const OBSERVER_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Consensus event reported to observers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConsensusEvent {
+    /// The node has moved to a new round.
+    NewRound {
+        /// Current height.
+        height: Height,
+        /// New round.
+        round: Round,
+    },
+    /// The node has received a valid `Propose`, or has created one as the leader.
+    ProposeReceived {
+        /// Height of the proposed block.
+        height: Height,
+        /// Round of the propose.
+        round: Round,
+        /// Leader of the round.
+        proposer: ValidatorId,
+        /// Hash of the propose.
+        propose_hash: Hash,
+        /// Number of transactions in the propose.
+        transactions: usize,
+    },
+    /// The node has locked on a propose after collecting +2/3 prevotes.
+    Locked {
+        /// Current height.
+        height: Height,
+        /// Round of the lock.
+        round: Round,
+        /// Hash of the locked propose.
+        propose_hash: Hash,
+    },
+    /// The node has committed a block.
+    Committed {
+        /// Height of the committed block.
+        height: Height,
+        /// Round in which the block was accepted, if known. The round is unknown
+        /// if the block was received from another node.
+        round: Option<Round>,
+        /// Hash of the block.
+        block_hash: Hash,
+        /// Author of the block propose.
+        proposer: ValidatorId,
+        /// Number of transactions in the block.
+        transactions: usize,
+    },
+}
+
+/// Consensus event with the time it has been observed by the node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObservedEvent {
+    /// Node time of the event.
+    pub time: DateTime<Utc>,
+    /// The event itself.
+    pub event: ConsensusEvent,
+}
+
+/// Connected observer.
+#[derive(Debug)]
+struct Subscriber {
+    events: SyncSender<Arc<String>>,
+    // Handle to the connection used to close it without waiting for the writer thread.
+    stream: TcpStream,
+}
+
+/// Server streaming consensus events to the connected observers.
+#[derive(Debug)]
+pub(crate) struct ConsensusObserver {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    local_addr: SocketAddr,
+}
+
+impl ConsensusObserver {
+    /// Starts accepting observer connections on the given address.
+    pub(crate) fn start(address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let local_addr = listener.local_addr()?;
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+
+        let accepted = Arc::clone(&subscribers);
+        thread::Builder::new()
+            .name("consensus-observer".to_owned())
+            .spawn(move || accept_observers(&listener, &accepted))?;
+        Ok(Self {
+            subscribers,
+            local_addr,
+        })
+    }
+
+    /// Returns the address the observers connect to.
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the number of connected observers.
+    pub(crate) fn observers_count(&self) -> usize {
+        self.subscribers.lock().expect("Expected mutex lock").len()
+    }
+
+    /// Sends the event to all connected observers.
+    pub(crate) fn notify(&self, time: SystemTime, event: ConsensusEvent) {
+        let mut subscribers = self.subscribers.lock().expect("Expected mutex lock");
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let event = ObservedEvent {
+            time: time.into(),
+            event,
+        };
+        let line = match serde_json::to_string(&event) {
+            Ok(line) => Arc::new(line),
+            Err(e) => {
+                error!("Unable to serialize consensus event {:?}: {}", event, e);
+                return;
+            }
+        };
+
+        // Dropping the sender stops the writer thread, which closes the connection.
+        subscribers.retain(|subscriber| {
+            match subscriber.events.try_send(Arc::clone(&line)) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    warn!("Consensus observer does not keep up with the events, disconnecting it");
+                    // The writer thread may be blocked on a write, so the connection is
+                    // shut down to fail the write instead of waiting for its timeout.
+                    let _ = subscriber.stream.shutdown(Shutdown::Both);
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+}
+
+fn accept_observers(listener: &TcpListener, subscribers: &Mutex<Vec<Subscriber>>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Unable to accept consensus observer connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map_or_else(|_| "unknown".to_owned(), |addr| addr.to_string());
+        let control = stream
+            .set_write_timeout(Some(OBSERVER_WRITE_TIMEOUT))
+            .and_then(|()| stream.try_clone());
+        let control = match control {
+            Ok(control) => control,
+            Err(e) => {
+                error!("Unable to serve consensus observer {}: {}", peer, e);
+                continue;
+            }
+        };
+
+        let (sender, receiver) = mpsc::sync_channel(OBSERVER_QUEUE_SIZE);
+        let spawned = thread::Builder::new()
+            .name("consensus-observer-writer".to_owned())
+            .spawn(move || write_events(stream, &receiver));
+        match spawned {
+            Ok(_) => {
+                info!("Consensus observer {} connected", peer);
+                subscribers
+                    .lock()
+                    .expect("Expected mutex lock")
+                    .push(Subscriber {
+                        events: sender,
+                        stream: control,
+                    });
+            }
+            Err(e) => error!("Unable to serve consensus observer {}: {}", peer, e),
+        }
+    }
+}
+
+fn write_events(mut stream: TcpStream, events: &mpsc::Receiver<Arc<String>>) {
+    for line in events {
+        let written = stream
+            .write_all(line.as_bytes())
+            .and_then(|()| stream.write_all(b"\n"));
+        if let Err(e) = written {
+            info!("Consensus observer disconnected: {}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::crypto;
+
+    fn connect(observer: &ConsensusObserver, count: usize) -> BufReader<TcpStream> {
+        let stream = TcpStream::connect(observer.local_addr()).unwrap();
+        while observer.observers_count() < count {
+            thread::sleep(Duration::from_millis(10));
+        }
+        BufReader::new(stream)
+    }
+
+    fn read_event(reader: &mut BufReader<TcpStream>) -> ObservedEvent {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn events_are_streamed_to_observers() {
+        let observer = ConsensusObserver::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        let mut first = connect(&observer, 1);
+        let mut second = connect(&observer, 2);
+
+        let time = SystemTime::now();
+        let events = vec![
+            ConsensusEvent::NewRound {
+                height: Height(1),
+                round: Round(2),
+            },
+            ConsensusEvent::Locked {
+                height: Height(1),
+                round: Round(2),
+                propose_hash: crypto::hash(&[1]),
+            },
+            ConsensusEvent::Committed {
+                height: Height(1),
+                round: None,
+                block_hash: crypto::hash(&[2]),
+                proposer: ValidatorId(3),
+                transactions: 4,
+            },
+        ];
+        for event in &events {
+            observer.notify(time, event.clone());
+        }
+
+        for reader in &mut [&mut first, &mut second] {
+            for event in &events {
+                let observed = read_event(reader);
+                assert_eq!(observed.time, DateTime::<Utc>::from(time));
+                assert_eq!(&observed.event, event);
+            }
+        }
+    }
+
+    #[test]
+    fn disconnected_observers_are_dropped() {
+        let observer = ConsensusObserver::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        let reader = connect(&observer, 1);
+        drop(reader);
+
+        let event = ConsensusEvent::NewRound {
+            height: Height(1),
+            round: Round(1),
+        };
+        // The writer thread notices the closed connection only after a failed write.
+        for _ in 0..100 {
+            observer.notify(SystemTime::now(), event.clone());
+            if observer.observers_count() == 0 {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Disconnected observer has not been dropped");
+    }
+
+    #[test]
+    fn stalled_observers_are_disconnected() {
+        let observer = ConsensusObserver::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        let mut reader = connect(&observer, 1);
+
+        // The observer does not read the events, so the writer thread is blocked
+        // once the socket buffers are full, and the queue of the observer overflows.
+        let event = ConsensusEvent::NewRound {
+            height: Height(1),
+            round: Round(1),
+        };
+        for _ in 0..1_000_000 {
+            observer.notify(SystemTime::now(), event.clone());
+            if observer.observers_count() == 0 {
+                break;
+            }
+        }
+        assert_eq!(observer.observers_count(), 0);
+
+        // The connection is closed after the data written so far.
+        reader
+            .get_ref()
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 {
+            line.clear();
+        }
+    }
+
+    #[test]
+    fn event_format() {
+        let event = ConsensusEvent::ProposeReceived {
+            height: Height(5),
+            round: Round(1),
+            proposer: ValidatorId(0),
+            propose_hash: Hash::zero(),
+            transactions: 10,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "propose_received");
+        assert_eq!(json["height"], 5);
+        assert_eq!(json["transactions"], 10);
+    }
+}
@@ -0,0 +1,530 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consistency checks performed before the node start.
+//!
+//! The checks cover the node keys, the genesis configuration, the set of services,
+//! the data versions of the services and the availability of the listen addresses
+//! of the peer network, the public and the private API (which also serves the dashboard)
+//! and the consensus observer. If enabled in the node configuration, the integrity
+//! of the latest blocks is verified as well. All detected problems are collected
+//! and reported together.
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    net::{SocketAddr, TcpListener},
+};
+
+use crate::{
+    blockchain::{check_integrity, deployment, migration, Blockchain, GenesisConfig, Schema},
+    crypto::{self, PublicKey, SecretKey},
+    helpers::Height,
+};
+
+use super::NodeConfig;
+
+/// Problems detected by the startup checks.
+#[derive(Debug, Fail)]
+pub struct PreflightError {
+    problems: Vec<String>,
+}
+
+impl PreflightError {
+    /// Returns descriptions of the detected problems.
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+}
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Node startup checks failed:")?;
+        for problem in &self.problems {
+            write!(f, "\n- {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Node parameters verified by the startup checks.
+#[derive(Debug, Clone)]
+pub(crate) struct StartupChecks {
+    genesis: GenesisConfig,
+    consensus_keys: (PublicKey, SecretKey),
+    service_keys: (PublicKey, SecretKey),
+    listen_addresses: Vec<(&'static str, SocketAddr)>,
+    integrity_check_depth: Option<u64>,
+}
+
+impl StartupChecks {
+    pub(crate) fn new(node_cfg: &NodeConfig) -> Self {
+        let listen_addresses = Some(("network", node_cfg.listen_address))
+            .into_iter()
+            .chain(
+                node_cfg
+                    .api
+                    .public_api_address
+                    .map(|address| ("public API", address)),
+            )
+            .chain(
+                node_cfg
+                    .api
+                    .private_api_address
+                    .map(|address| ("private API", address)),
+            )
+            .chain(
+                node_cfg
+                    .observer_address
+                    .map(|address| ("consensus observer", address)),
+            )
+            .collect();
+
+        Self {
+            genesis: node_cfg.genesis.clone(),
+            consensus_keys: (
+                node_cfg.consensus_public_key,
+                node_cfg.consensus_secret_key.clone(),
+            ),
+            service_keys: (
+                node_cfg.service_public_key,
+                node_cfg.service_secret_key.clone(),
+            ),
+            listen_addresses,
+            integrity_check_depth: node_cfg.integrity_check_depth,
+        }
+    }
+
+    /// Runs all checks against the given blockchain.
+    pub(crate) fn run(&self, blockchain: &Blockchain) -> Result<(), PreflightError> {
+        let mut problems = Vec::new();
+        self.check_keys(blockchain, &mut problems);
+        self.check_storage(blockchain, &mut problems);
+        self.check_services(blockchain, &mut problems);
+        self.check_integrity(blockchain, &mut problems);
+        self.check_listen_addresses(&mut problems);
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(PreflightError { problems })
+        }
+    }
+
+    fn check_keys(&self, blockchain: &Blockchain, problems: &mut Vec<String>) {
+        let keys = [
+            ("consensus", &self.consensus_keys),
+            ("service", &self.service_keys),
+        ];
+        for (kind, (public_key, secret_key)) in keys.iter() {
+            if !keys_match(public_key, secret_key) {
+                problems.push(format!(
+                    "The {} secret key does not correspond to the public key {}",
+                    kind,
+                    public_key.to_hex()
+                ));
+            }
+        }
+
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let validator_keys = if schema.block_hashes_by_height().is_empty() {
+            self.genesis.validator_keys.clone()
+        } else {
+            schema.actual_configuration().validator_keys
+        };
+
+        let (consensus_key, service_key) = (self.consensus_keys.0, self.service_keys.0);
+        let entry = validator_keys.iter().enumerate().find(|(_, keys)| {
+            keys.consensus_key == consensus_key || keys.service_key == service_key
+        });
+        if let Some((id, expected)) = entry {
+            if expected.consensus_key != consensus_key || expected.service_key != service_key {
+                problems.push(format!(
+                    "Node keys do not match the validator entry #{}: expected consensus key {} \
+                     and service key {}, configured {} and {}",
+                    id,
+                    expected.consensus_key.to_hex(),
+                    expected.service_key.to_hex(),
+                    consensus_key.to_hex(),
+                    service_key.to_hex()
+                ));
+            }
+        }
+    }
+
+    fn check_storage(&self, blockchain: &Blockchain, problems: &mut Vec<String>) {
+        let snapshot = blockchain.snapshot();
+        // A service cannot read the data migrated by a newer release of the service.
+        for service in blockchain.service_map().values() {
+            let version = migration::data_version(&snapshot, service.service_id());
+            if version > service.data_version() {
+                problems.push(format!(
+                    "Service <{}> stores data of version {}, but the node supports \
+                     data versions up to {}",
+                    service.service_name(),
+                    version,
+                    service.data_version()
+                ));
+            }
+        }
+
+        let schema = Schema::new(&snapshot);
+        let genesis_hash = match schema.block_hash_by_height(Height::zero()) {
+            Some(hash) => hash,
+            // The genesis block will be created from the node configuration.
+            None => return,
+        };
+
+        let genesis = schema.configuration_by_height(Height::zero());
+        if genesis.validator_keys != self.genesis.validator_keys {
+            problems.push(format!(
+                "Validator keys in the genesis configuration differ from the ones \
+                 of the stored genesis block {}",
+                genesis_hash.to_hex()
+            ));
+        }
+        if genesis.consensus != self.genesis.consensus {
+            problems.push(format!(
+                "Consensus parameters in the genesis configuration differ from the ones \
+                 of the stored genesis block {}",
+                genesis_hash.to_hex()
+            ));
+        }
+    }
+
+    /// Compares the services of the node with the services of the actual configuration
+    /// of the blockchain. A node lacking a service cannot execute its transactions,
+    /// and a node with an extra service or a service with changed tables computes
+    /// a different state hash, so the node would fail at the first block
+    /// touching the service. Deployable services that are not deployed yet
+    /// are not expected in the configuration.
+    fn check_services(&self, blockchain: &Blockchain, problems: &mut Vec<String>) {
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        if schema.block_hashes_by_height().is_empty() {
+            // The genesis block will be created with the services of the node.
+            return;
+        }
+
+        let stored_services = schema
+            .actual_configuration()
+            .services
+            .keys()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let deployed_services = blockchain
+            .service_map()
+            .values()
+            .filter(|service| deployment::is_deployed(&snapshot, service.as_ref()))
+            .collect::<Vec<_>>();
+        let services = deployed_services
+            .iter()
+            .map(|service| service.service_name().to_owned())
+            .collect::<BTreeSet<_>>();
+        let missing = stored_services.difference(&services).collect::<Vec<_>>();
+        if !missing.is_empty() {
+            problems.push(format!(
+                "Services {:?} are present in the stored configuration, but not in the node; \
+                 the node cannot execute their transactions",
+                missing
+            ));
+        }
+        let unknown = services.difference(&stored_services).collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            problems.push(format!(
+                "Services {:?} are present in the node, but not in the stored configuration",
+                unknown
+            ));
+        }
+
+        // The tables of a service are aggregated under keys derived from their names,
+        // so the tables of a service with a changed layout are missing in the stored state.
+        let aggregator = schema.state_hash_aggregator();
+        let mut changed = deployed_services
+            .iter()
+            .filter(|service| stored_services.contains(service.service_name()))
+            .filter(|service| {
+                service
+                    .state_hash_tables(snapshot.as_ref())
+                    .iter()
+                    .any(|(name, _)| !aggregator.contains(&Blockchain::state_table_key(name)))
+            })
+            .map(|service| format!("{} (ID {})", service.service_name(), service.service_id()))
+            .collect::<Vec<_>>();
+        changed.sort();
+        if !changed.is_empty() {
+            problems.push(format!(
+                "Tables of services {:?} are not found in the stored state; the services \
+                 may differ from the ones the blockchain has been created with",
+                changed
+            ));
+        }
+    }
+
+    fn check_integrity(&self, blockchain: &Blockchain, problems: &mut Vec<String>) {
+        let depth = match self.integrity_check_depth {
+            Some(depth) => depth,
+            None => return,
+        };
+
+        let report = check_integrity(blockchain, depth);
+        info!(
+            "Storage integrity check has verified {} blocks",
+            report.checked_blocks
+        );
+        if let Some(hint) = report.repair_hint() {
+            let ranges = report
+                .corrupt_ranges()
+                .iter()
+                .map(|(from, to)| {
+                    if from == to {
+                        from.to_string()
+                    } else {
+                        format!("{}..={}", from, to)
+                    }
+                })
+                .collect::<Vec<_>>();
+            let details = report
+                .problems
+                .iter()
+                .map(|problem| format!("\n  - height {}: {}", problem.height, problem.description))
+                .collect::<String>();
+            problems.push(format!(
+                "Inconsistent blocks at heights {}:{}\n  {}",
+                ranges.join(", "),
+                details,
+                hint
+            ));
+        }
+    }
+
+    fn check_listen_addresses(&self, problems: &mut Vec<String>) {
+        for (purpose, address) in &self.listen_addresses {
+            if let Err(e) = TcpListener::bind(address) {
+                problems.push(format!(
+                    "Unable to bind the {} listen address {}: {}",
+                    purpose, address, e
+                ));
+            }
+        }
+    }
+}
+
+fn keys_match(public_key: &PublicKey, secret_key: &SecretKey) -> bool {
+    const PROBE: &[u8] = b"exonum-preflight";
+    crypto::verify(&crypto::sign(PROBE, secret_key), PROBE, public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{
+        tests::create_blockchain_in, ConsensusConfig, Service, Transaction, ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::messages::RawTransaction;
+    use exonum_merkledb::{Database, Snapshot, TemporaryDB};
+
+    use std::sync::Arc;
+
+    struct TestService {
+        id: u16,
+        name: &'static str,
+        data_version: u32,
+    }
+
+    impl Service for TestService {
+        fn service_id(&self) -> u16 {
+            self.id
+        }
+
+        fn service_name(&self) -> &str {
+            self.name
+        }
+
+        fn data_version(&self) -> u32 {
+            self.data_version
+        }
+
+        // The table name depends on the ID to emulate a change of the service layout.
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            vec![(format!("{}.table_{}", self.name, self.id), Hash::zero())]
+        }
+
+        fn tx_from_raw(&self, _: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            unimplemented!()
+        }
+    }
+
+    fn free_address() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    fn create_checks() -> StartupChecks {
+        let consensus_keys = gen_keypair();
+        let service_keys = gen_keypair();
+        let genesis = GenesisConfig::new(
+            vec![ValidatorKeys::new(consensus_keys.0, service_keys.0)].into_iter(),
+        );
+        StartupChecks {
+            genesis,
+            consensus_keys,
+            service_keys,
+            listen_addresses: vec![("network", free_address())],
+            integrity_check_depth: Some(10),
+        }
+    }
+
+    fn create_blockchain(genesis: GenesisConfig) -> Blockchain {
+        let mut blockchain = crate::blockchain::tests::create_blockchain(Vec::new());
+        blockchain.initialize(genesis).unwrap();
+        blockchain
+    }
+
+    #[test]
+    fn consistent_node_passes_checks() {
+        let checks = create_checks();
+        let blockchain = create_blockchain(checks.genesis.clone());
+        checks.run(&blockchain).unwrap();
+    }
+
+    #[test]
+    fn all_problems_are_reported() {
+        let checks = create_checks();
+        let blockchain = create_blockchain(checks.genesis.clone());
+
+        let mut genesis = checks.genesis.clone();
+        genesis.consensus = ConsensusConfig {
+            txs_block_limit: 1,
+            ..ConsensusConfig::default()
+        };
+        let busy_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let misconfigured = StartupChecks {
+            genesis,
+            service_keys: (gen_keypair().0, checks.service_keys.1.clone()),
+            listen_addresses: vec![("network", busy_listener.local_addr().unwrap())],
+            ..checks
+        };
+
+        let problems = misconfigured.run(&blockchain).unwrap_err().problems;
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems[0].contains("service secret key does not correspond"));
+        assert!(problems[1].contains("do not match the validator entry #0"));
+        assert!(problems[2].contains("Consensus parameters"));
+        assert!(problems[3].contains("Unable to bind the network listen address"));
+    }
+
+    #[test]
+    fn observer_address_is_checked() {
+        let busy_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut node_cfg = crate::helpers::generate_testnet_config(1, 0)[0].clone();
+        node_cfg.observer_address = Some(busy_listener.local_addr().unwrap());
+
+        let mut problems = Vec::new();
+        StartupChecks::new(&node_cfg).check_listen_addresses(&mut problems);
+        assert!(
+            problems
+                .iter()
+                .any(|problem| problem.contains("bind the consensus observer listen address")),
+            "{:?}",
+            problems
+        );
+    }
+
+    #[test]
+    fn integrity_problems_are_reported() {
+        let checks = create_checks();
+        let mut blockchain = create_blockchain(checks.genesis.clone());
+        let fork = blockchain.fork();
+        Schema::new(&fork)
+            .state_hash_aggregator()
+            .put(&crypto::Hash::zero(), crypto::Hash::zero());
+        blockchain.merge(fork.into_patch()).unwrap();
+
+        let problems = checks.run(&blockchain).unwrap_err().problems;
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].starts_with("Inconsistent blocks at heights 0:"));
+        assert!(problems[0].contains("Re-synchronize the node"));
+
+        let unchecked = StartupChecks {
+            integrity_check_depth: None,
+            ..checks
+        };
+        unchecked.run(&blockchain).unwrap();
+    }
+
+    #[test]
+    fn service_mismatch_is_reported() {
+        let checks = StartupChecks {
+            integrity_check_depth: None,
+            ..create_checks()
+        };
+        let db: Arc<dyn Database> = Arc::new(TemporaryDB::new());
+        let blockchain_with = |services: &[(u16, &'static str)]| {
+            let services = services
+                .iter()
+                .map(|&(id, name)| {
+                    let service = TestService {
+                        id,
+                        name,
+                        data_version: 0,
+                    };
+                    Box::new(service) as Box<dyn Service>
+                })
+                .collect();
+            create_blockchain_in(Arc::clone(&db), services)
+        };
+
+        let mut blockchain = blockchain_with(&[(1, "first"), (2, "second")]);
+        blockchain.initialize(checks.genesis.clone()).unwrap();
+        checks.run(&blockchain).unwrap();
+
+        let blockchain = blockchain_with(&[(3, "first"), (4, "third")]);
+        let problems = checks.run(&blockchain).unwrap_err().problems;
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems[0].contains("[\"second\"] are present in the stored configuration"));
+        assert!(problems[1].contains("[\"third\"] are present in the node"));
+        assert!(problems[2].contains("[\"first (ID 3)\"] are not found in the stored state"));
+    }
+
+    #[test]
+    fn newer_data_version_is_reported() {
+        let checks = StartupChecks {
+            integrity_check_depth: None,
+            ..create_checks()
+        };
+        let db: Arc<dyn Database> = Arc::new(TemporaryDB::new());
+        let blockchain_with = |data_version: u32| {
+            let service = TestService {
+                id: 1,
+                name: "versioned",
+                data_version,
+            };
+            create_blockchain_in(Arc::clone(&db), vec![Box::new(service)])
+        };
+
+        let mut blockchain = blockchain_with(2);
+        blockchain.initialize(checks.genesis.clone()).unwrap();
+        checks.run(&blockchain).unwrap();
+        checks.run(&blockchain_with(3)).unwrap();
+
+        let problems = checks.run(&blockchain_with(1)).unwrap_err().problems;
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].contains("Service <versioned> stores data of version 2"));
+    }
+}
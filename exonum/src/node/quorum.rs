@@ -0,0 +1,255 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulation of the validator set changes.
+//!
+//! Before voting for a configuration changing the validator set, the administrators
+//! can estimate how the change affects the consensus: the voting weight of `Precommit`s
+//! required to accept a block, the weight of the validators that may fail without
+//! stopping the network, and whether the validators currently offline would prevent
+//! the network from accepting blocks. A validator is considered online if the node
+//! is connected to it or if it is the node itself; the validators being added are
+//! therefore online only if they are already connected to the node.
+
+use std::collections::HashSet;
+
+use crate::blockchain::{StoredConfiguration, ValidatorKeys};
+use crate::crypto::PublicKey;
+use crate::helpers::ValidatorId;
+
+/// Hypothetical change of the validator set.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ValidatorSetChange {
+    /// Keys and voting weights of the validators to add.
+    #[serde(default)]
+    pub add: Vec<ValidatorKeys>,
+    /// Consensus keys of the validators to remove.
+    #[serde(default)]
+    pub remove: Vec<PublicKey>,
+}
+
+/// Consensus properties of a validator set.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct QuorumInfo {
+    /// Number of the validators.
+    pub validators: usize,
+    /// Voting weight of the `Precommit`s required to accept a block.
+    pub quorum: u64,
+    /// Voting weight of the validators that may fail without stopping the network.
+    pub tolerated_failures: u64,
+    /// Consensus keys of the validators which are currently offline.
+    pub offline: Vec<PublicKey>,
+    /// Whether the online validators are able to accept blocks.
+    pub is_live: bool,
+}
+
+impl QuorumInfo {
+    fn new(config: &StoredConfiguration, online: &HashSet<PublicKey>) -> Self {
+        let quorum = config.majority_weight();
+        let validators = &config.validator_keys;
+        let offline = validators
+            .iter()
+            .map(|keys| keys.consensus_key)
+            .filter(|key| !online.contains(key))
+            .collect::<Vec<_>>();
+        let online_weight = config.weight_of(
+            validators
+                .iter()
+                .enumerate()
+                .filter(|(_, keys)| online.contains(&keys.consensus_key))
+                .map(|(id, _)| ValidatorId(id as u16)),
+        );
+        Self {
+            validators: validators.len(),
+            quorum,
+            tolerated_failures: config.total_weight().saturating_sub(quorum),
+            is_live: online_weight >= quorum,
+            offline,
+        }
+    }
+}
+
+/// Impact of a validator set change on the consensus.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct QuorumImpact {
+    /// Properties of the current validator set.
+    pub current: QuorumInfo,
+    /// Properties of the validator set after the change.
+    pub proposed: QuorumInfo,
+}
+
+/// Computes the impact of the change on the validator set of the given configuration
+/// given the consensus keys of the online validators.
+pub fn simulate_change(
+    config: &StoredConfiguration,
+    change: &ValidatorSetChange,
+    online: &HashSet<PublicKey>,
+) -> Result<QuorumImpact, failure::Error> {
+    let mut proposed = config.clone();
+    for key in &change.remove {
+        let position = proposed
+            .validator_keys
+            .iter()
+            .position(|validator| validator.consensus_key == *key)
+            .ok_or_else(|| format_err!("Validator {:?} is not in the validator set", key))?;
+        proposed.validator_keys.remove(position);
+    }
+    for keys in &change.add {
+        ensure!(
+            !proposed
+                .validator_keys
+                .iter()
+                .any(|validator| validator.consensus_key == keys.consensus_key),
+            "Validator {:?} is already in the validator set",
+            keys.consensus_key
+        );
+        proposed.validator_keys.push(*keys);
+    }
+    ensure!(
+        !proposed.validator_keys.is_empty(),
+        "Validator set cannot be empty"
+    );
+
+    Ok(QuorumImpact {
+        current: QuorumInfo::new(config, online),
+        proposed: QuorumInfo::new(&proposed, online),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ConsensusConfig;
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::Height;
+
+    fn keys(weights: &[u64]) -> Vec<ValidatorKeys> {
+        weights
+            .iter()
+            .map(|&weight| ValidatorKeys::new(gen_keypair().0, gen_keypair().0).with_weight(weight))
+            .collect()
+    }
+
+    fn config(validator_keys: Vec<ValidatorKeys>) -> StoredConfiguration {
+        StoredConfiguration {
+            previous_cfg_hash: Hash::zero(),
+            actual_from: Height(0),
+            validator_keys,
+            consensus: ConsensusConfig::default(),
+            services: Default::default(),
+        }
+    }
+
+    fn consensus_keys(validators: &[ValidatorKeys]) -> Vec<PublicKey> {
+        validators.iter().map(|keys| keys.consensus_key).collect()
+    }
+
+    #[test]
+    fn removal_breaks_liveness() {
+        let config = config(keys(&[1; 4]));
+        let validators = consensus_keys(&config.validator_keys);
+        // The last validator is offline.
+        let online = validators[..3].iter().cloned().collect::<HashSet<_>>();
+        let change = ValidatorSetChange {
+            add: vec![],
+            remove: vec![validators[0]],
+        };
+
+        let impact = simulate_change(&config, &change, &online).unwrap();
+        assert_eq!(impact.current.quorum, 3);
+        assert_eq!(impact.current.tolerated_failures, 1);
+        assert_eq!(impact.current.offline, vec![validators[3]]);
+        assert!(impact.current.is_live);
+
+        assert_eq!(impact.proposed.validators, 3);
+        assert_eq!(impact.proposed.quorum, 3);
+        assert_eq!(impact.proposed.tolerated_failures, 0);
+        assert_eq!(impact.proposed.offline, vec![validators[3]]);
+        assert!(!impact.proposed.is_live);
+    }
+
+    #[test]
+    fn added_validators_count_as_offline_until_connected() {
+        let config = config(keys(&[1; 4]));
+        let online = consensus_keys(&config.validator_keys)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let added = keys(&[1; 3]);
+        let change = ValidatorSetChange {
+            add: added.clone(),
+            remove: vec![],
+        };
+
+        let impact = simulate_change(&config, &change, &online).unwrap();
+        assert_eq!(impact.proposed.validators, 7);
+        assert_eq!(impact.proposed.quorum, 5);
+        assert_eq!(impact.proposed.tolerated_failures, 2);
+        assert_eq!(impact.proposed.offline, consensus_keys(&added));
+        assert!(!impact.proposed.is_live);
+    }
+
+    #[test]
+    fn quorum_depends_on_weights() {
+        let config = config(keys(&[4, 1, 1, 1]));
+        let validators = consensus_keys(&config.validator_keys);
+        // Three of four validators are online, which is enough by count, but not by weight.
+        let online = validators[1..].iter().cloned().collect::<HashSet<_>>();
+        let change = ValidatorSetChange {
+            add: vec![],
+            remove: vec![validators[1]],
+        };
+
+        let impact = simulate_change(&config, &change, &online).unwrap();
+        assert_eq!(impact.current.validators, 4);
+        assert_eq!(impact.current.quorum, 5);
+        assert_eq!(impact.current.tolerated_failures, 2);
+        assert_eq!(impact.current.offline, vec![validators[0]]);
+        assert!(!impact.current.is_live);
+
+        assert_eq!(impact.proposed.quorum, 5);
+        assert_eq!(impact.proposed.tolerated_failures, 1);
+        assert!(!impact.proposed.is_live);
+
+        // Two of four validators are online, which is not enough by count, but enough by weight.
+        let online = validators[..2].iter().cloned().collect::<HashSet<_>>();
+        let impact = simulate_change(&config, &change, &online).unwrap();
+        assert!(impact.current.is_live);
+        assert!(!impact.proposed.is_live);
+    }
+
+    #[test]
+    fn invalid_changes() {
+        let config = config(keys(&[1; 2]));
+        let validators = consensus_keys(&config.validator_keys);
+        let online = HashSet::new();
+
+        let unknown = ValidatorSetChange {
+            add: vec![],
+            remove: consensus_keys(&keys(&[1])),
+        };
+        assert!(simulate_change(&config, &unknown, &online).is_err());
+
+        let duplicate = ValidatorSetChange {
+            add: vec![config.validator_keys[0]],
+            remove: vec![],
+        };
+        assert!(simulate_change(&config, &duplicate, &online).is_err());
+
+        let empty = ValidatorSetChange {
+            add: vec![],
+            remove: validators.clone(),
+        };
+        assert!(simulate_change(&config, &empty, &online).is_err());
+    }
+}
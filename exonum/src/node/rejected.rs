@@ -0,0 +1,203 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Log of the transactions rejected by the node.
+//!
+//! Transactions failing the checks of the explorer API or of the node are not added
+//! to the pool and are not stored anywhere. To let operators find out why a transaction
+//! has disappeared, the latest rejections are kept in memory together with their reasons
+//! and served by the private `v1/rejected_transactions` endpoint of the system API.
+
+use chrono::{DateTime, Utc};
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::crypto::Hash;
+
+/// Number of the latest rejected transactions kept for the API.
+pub const REJECTED_TRANSACTIONS_LOG_SIZE: usize = 1024;
+
+/// Reason of the transaction rejection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    /// The transaction exceeds `max_message_len` of the consensus configuration.
+    TooLarge,
+    /// The signature of the transaction is invalid.
+    InvalidSignature,
+    /// The message cannot be decoded as a transaction.
+    Malformed,
+    /// The transaction refers to a service which is not deployed on the node.
+    UnknownService,
+    /// The service of the transaction is stopped or frozen.
+    InactiveService,
+    /// The service has failed to parse the transaction payload.
+    InvalidPayload,
+    /// The node sheds load because its resource usage approaches the limits.
+    Overloaded,
+    /// The transaction is signed with the key reserved for the transactions generated
+    /// by the services.
+    ReservedAuthor,
+    /// The author of the transaction has reached the `max_pooled_txs` limit
+    /// of the node configuration.
+    AuthorQuota,
+    /// The transaction is signed for another network.
+    WrongNetwork,
+}
+
+/// Origin of the rejected transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionSource {
+    /// The transaction was submitted via the API of the node or broadcast by a local service.
+    Api,
+    /// The transaction was received from a peer.
+    Network,
+}
+
+/// Record of a rejected transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectedTransaction {
+    /// Hash of the transaction message.
+    pub tx_hash: Hash,
+    /// Reason of the rejection.
+    pub reason: RejectionReason,
+    /// Description of the error that caused the rejection.
+    pub details: String,
+    /// Origin of the transaction.
+    pub source: TransactionSource,
+    /// Time of the rejection.
+    pub time: DateTime<Utc>,
+}
+
+impl RejectedTransaction {
+    /// Creates a record of a transaction rejected at the given time.
+    pub fn new(
+        tx_hash: Hash,
+        reason: RejectionReason,
+        details: String,
+        source: TransactionSource,
+        time: SystemTime,
+    ) -> Self {
+        Self {
+            tx_hash,
+            reason,
+            details,
+            source,
+            time: time.into(),
+        }
+    }
+}
+
+/// Error returned by the node for the transactions that are not added to the pool
+/// because they are invalid.
+#[derive(Debug, Fail)]
+#[fail(display = "Received invalid transaction: {}", details)]
+pub(crate) struct TransactionRejected {
+    pub tx_hash: Hash,
+    pub reason: RejectionReason,
+    pub details: String,
+}
+
+/// Latest rejected transactions shared with the API.
+///
+/// The log is cheaply cloneable; all clones share the same records.
+#[derive(Debug, Clone, Default)]
+pub struct RejectedTransactionsLog {
+    records: Arc<Mutex<VecDeque<RejectedTransaction>>>,
+}
+
+impl RejectedTransactionsLog {
+    /// Returns the recorded rejections, oldest first.
+    pub fn records(&self) -> Vec<RejectedTransaction> {
+        let records = self.records.lock().expect("Expected mutex lock");
+        records.iter().cloned().collect()
+    }
+
+    /// Returns the recorded rejections of the transaction with the given hash, oldest first.
+    pub fn find(&self, tx_hash: &Hash) -> Vec<RejectedTransaction> {
+        let records = self.records.lock().expect("Expected mutex lock");
+        records
+            .iter()
+            .filter(|record| record.tx_hash == *tx_hash)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the number of the recorded rejections which have happened since the given time.
+    pub fn count_since(&self, time: SystemTime) -> usize {
+        let time = DateTime::<Utc>::from(time);
+        let records = self.records.lock().expect("Expected mutex lock");
+        records
+            .iter()
+            .rev()
+            .take_while(|record| record.time >= time)
+            .count()
+    }
+
+    pub(crate) fn push(&self, record: RejectedTransaction) {
+        let mut records = self.records.lock().expect("Expected mutex lock");
+        if records.len() == REJECTED_TRANSACTIONS_LOG_SIZE {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::crypto::hash;
+
+    fn record(value: u8) -> RejectedTransaction {
+        RejectedTransaction::new(
+            hash(&[value]),
+            RejectionReason::UnknownService,
+            "Service not found.".to_owned(),
+            TransactionSource::Network,
+            SystemTime::now(),
+        )
+    }
+
+    #[test]
+    fn log_keeps_latest_records() {
+        let log = RejectedTransactionsLog::default();
+        for i in 0..=REJECTED_TRANSACTIONS_LOG_SIZE {
+            log.push(record(i as u8));
+        }
+
+        let records = log.records();
+        assert_eq!(records.len(), REJECTED_TRANSACTIONS_LOG_SIZE);
+        assert_eq!(records[0].tx_hash, hash(&[1]));
+        assert_eq!(
+            records[REJECTED_TRANSACTIONS_LOG_SIZE - 1].tx_hash,
+            hash(&[REJECTED_TRANSACTIONS_LOG_SIZE as u8])
+        );
+        // Hashes repeat every 256 records.
+        assert_eq!(log.find(&hash(&[1])).len(), 4);
+
+        let now = SystemTime::now();
+        assert_eq!(
+            log.count_since(now - Duration::from_secs(60)),
+            REJECTED_TRANSACTIONS_LOG_SIZE
+        );
+        assert_eq!(log.count_since(now + Duration::from_secs(60)), 0);
+    }
+}
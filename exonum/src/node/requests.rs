@@ -14,11 +14,14 @@
 
 use exonum_crypto::{Hash, PublicKey};
 
+use std::collections::HashSet;
+
 use super::NodeHandler;
 use crate::blockchain::{get_tx, Schema};
 use crate::messages::{
-    BlockRequest, BlockResponse, PoolTransactionsRequest, PrevotesRequest, ProposeRequest,
-    Requests, Signed, TransactionsRequest, TransactionsResponse, RAW_TRANSACTION_HEADER,
+    short_tx_id, BlockRequest, BlockResponse, PoolTransactionsRequest, PrevotesRequest,
+    ProposeRequest, Requests, ShortTransactionsRequest, Signed, TransactionsRequest,
+    TransactionsResponse, NETWORK_ID_LENGTH, RAW_TRANSACTION_HEADER,
     TRANSACTION_RESPONSE_EMPTY_SIZE,
 };
 
@@ -49,6 +52,7 @@ impl NodeHandler {
             Requests::PeersRequest(ref msg) => self.handle_request_peers(msg),
             Requests::BlockRequest(ref msg) => self.handle_request_block(msg),
             Requests::PoolTransactionsRequest(ref msg) => self.handle_request_pool_txs(msg),
+            Requests::ShortTransactionsRequest(ref msg) => self.handle_request_short_txs(msg),
         }
     }
 
@@ -86,6 +90,27 @@ impl NodeHandler {
 
         let mut hashes: Vec<Hash> = schema.transactions_pool().iter().collect();
         hashes.extend(self.state.tx_cache().keys().cloned());
+        hashes.extend(self.state.spilled_txs().iter().cloned());
+
+        self.send_transactions_by_hash(&msg.author(), &hashes);
+    }
+
+    /// Handles `ShortTransactionsRequest` message. For details see the message documentation.
+    pub fn handle_request_short_txs(&mut self, msg: &Signed<ShortTransactionsRequest>) {
+        trace!("HANDLE SHORT TRANSACTIONS REQUEST");
+        let hashes: Vec<Hash> = match self.state.propose(&msg.propose_hash) {
+            Some(propose) => {
+                let salt = propose.message().short_id_salt();
+                let requested: HashSet<_> = msg.short_ids.iter().collect();
+                propose
+                    .transactions()
+                    .iter()
+                    .filter(|tx_hash| requested.contains(&short_tx_id(salt, tx_hash)))
+                    .cloned()
+                    .collect()
+            }
+            None => return,
+        };
 
         self.send_transactions_by_hash(&msg.author(), &hashes);
     }
@@ -96,8 +121,14 @@ impl NodeHandler {
         let schema = Schema::new(&snapshot);
         let mut txs = Vec::new();
         let mut txs_size = 0;
+        let network_id_size = if self.network_id == 0 {
+            0
+        } else {
+            NETWORK_ID_LENGTH
+        };
         let unoccupied_message_size = self.state.config().consensus.max_message_len as usize
-            - TRANSACTION_RESPONSE_EMPTY_SIZE;
+            - TRANSACTION_RESPONSE_EMPTY_SIZE
+            - network_id_size;
 
         for hash in hashes {
             if let Some(tx) = get_tx(&hash, &schema.transactions(), &self.state.tx_cache()) {
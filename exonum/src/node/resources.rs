@@ -0,0 +1,251 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Watchdog of the memory and disk space used by the node.
+//!
+//! The resident memory of the node process and the size of the database directory
+//! are measured every time the node updates the state shared with the API. When the
+//! usage of either resource reaches `shed_threshold` of its limit, the node becomes
+//! overloaded and sheds load instead of waiting for the operating system to kill it:
+//! new transactions from the API and the peers are rejected, except for those needed
+//! to complete the proposals and blocks of the current height, and the explorer API
+//! rejects the queries that traverse many blocks. The consensus messages are handled
+//! as usual, so that a validator keeps participating in the consensus.
+//!
+//! The state of the watchdog is reported by the `node.resources.*` metrics and
+//! the `overloaded` flag of the public health check.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Default fraction of a resource limit at which the node starts shedding load.
+pub const DEFAULT_SHED_THRESHOLD: f64 = 0.9;
+
+/// Limits of the resources used by the node.
+///
+/// The limits are not enforced if absent. Memory usage is measured only on Linux.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ResourceLimitsConfig {
+    /// Limit of the resident memory of the node process, in bytes.
+    #[serde(default)]
+    pub max_memory: Option<u64>,
+    /// Limit of the size of the database directory, in bytes.
+    #[serde(default)]
+    pub max_disk_usage: Option<u64>,
+    /// Database directory measured against `max_disk_usage`. The `run` command
+    /// sets it to the database path of the node if not specified.
+    #[serde(default)]
+    pub database_path: Option<PathBuf>,
+    /// Fraction of a limit, greater than 0 and at most 1, at which the node
+    /// starts shedding load.
+    #[serde(default = "default_shed_threshold")]
+    pub shed_threshold: f64,
+}
+
+fn default_shed_threshold() -> f64 {
+    DEFAULT_SHED_THRESHOLD
+}
+
+impl Default for ResourceLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_memory: None,
+            max_disk_usage: None,
+            database_path: None,
+            shed_threshold: DEFAULT_SHED_THRESHOLD,
+        }
+    }
+}
+
+impl ResourceLimitsConfig {
+    /// Checks that the threshold is within bounds and the disk limit has a directory
+    /// to measure.
+    pub fn validate(&self) -> Result<(), failure::Error> {
+        ensure!(
+            self.shed_threshold > 0.0 && self.shed_threshold <= 1.0,
+            "shed_threshold ({}) must be greater than 0 and at most 1",
+            self.shed_threshold
+        );
+        ensure!(
+            self.max_disk_usage.is_none() || self.database_path.is_some(),
+            "max_disk_usage requires database_path to be specified"
+        );
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.max_memory.is_none() && self.max_disk_usage.is_none()
+    }
+
+    fn exceeds(&self, usage: Option<u64>, limit: Option<u64>) -> bool {
+        match (usage, limit) {
+            (Some(usage), Some(limit)) => usage as f64 >= limit as f64 * self.shed_threshold,
+            _ => false,
+        }
+    }
+}
+
+/// Resources used by the node, reported by the public health check.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ResourceUsage {
+    /// Resident memory of the node process in bytes, if measured.
+    pub memory: Option<u64>,
+    /// Size of the database directory in bytes, if measured.
+    pub disk_usage: Option<u64>,
+    /// Whether the node sheds load because of the usage approaching the limits.
+    pub overloaded: bool,
+}
+
+/// Measures the resources used by the node and checks them against the limits.
+#[derive(Debug)]
+pub(crate) struct ResourceWatchdog {
+    config: ResourceLimitsConfig,
+    usage: ResourceUsage,
+}
+
+impl ResourceWatchdog {
+    pub fn new(config: ResourceLimitsConfig) -> Self {
+        if config.max_memory.is_some() && process_memory().is_none() {
+            warn!("Memory usage cannot be measured on this platform, max_memory is ignored");
+        }
+        Self {
+            config,
+            usage: ResourceUsage::default(),
+        }
+    }
+
+    pub fn is_overloaded(&self) -> bool {
+        self.usage.overloaded
+    }
+
+    /// Measures the resources and updates the state of the watchdog.
+    pub fn check(&mut self) -> ResourceUsage {
+        if self.config.is_empty() {
+            return self.usage;
+        }
+
+        let memory = self.config.max_memory.and_then(|_| process_memory());
+        let disk_usage = self.config.database_path.as_ref().and_then(|path| {
+            directory_size(path)
+                .map_err(|e| warn!("Unable to measure the size of {:?}: {}", path, e))
+                .ok()
+        });
+        let usage = self.update(memory, disk_usage);
+        if let Some(memory) = usage.memory {
+            metric!("node.resources.memory", memory);
+        }
+        if let Some(disk_usage) = usage.disk_usage {
+            metric!("node.resources.disk_usage", disk_usage);
+        }
+        metric!("node.resources.overloaded", usage.overloaded);
+        usage
+    }
+
+    fn update(&mut self, memory: Option<u64>, disk_usage: Option<u64>) -> ResourceUsage {
+        let overloaded = self.config.exceeds(memory, self.config.max_memory)
+            || self.config.exceeds(disk_usage, self.config.max_disk_usage);
+        if overloaded != self.usage.overloaded {
+            if overloaded {
+                warn!(
+                    "Node is approaching its resource limits, shedding load: \
+                     memory {:?} of {:?}, disk usage {:?} of {:?}",
+                    memory, self.config.max_memory, disk_usage, self.config.max_disk_usage
+                );
+            } else {
+                info!("Node resource usage is back within limits, accepting transactions");
+            }
+        }
+        self.usage = ResourceUsage {
+            memory,
+            disk_usage,
+            overloaded,
+        };
+        self.usage
+    }
+}
+
+/// Returns the resident memory of the current process in bytes, or `None` if it cannot
+/// be measured.
+fn process_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Returns the total size of the files in the directory and its subdirectories.
+fn directory_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_is_shed_at_threshold() {
+        let mut watchdog = ResourceWatchdog::new(ResourceLimitsConfig {
+            max_disk_usage: Some(1_000),
+            database_path: Some(PathBuf::from("db")),
+            shed_threshold: 0.8,
+            ..ResourceLimitsConfig::default()
+        });
+        assert!(!watchdog.update(None, Some(799)).overloaded);
+        assert!(watchdog.update(None, Some(800)).overloaded);
+        assert!(watchdog.is_overloaded());
+        // Unmeasured resources do not affect the state.
+        assert!(!watchdog.update(Some(u64::max_value()), None).overloaded);
+        assert!(!watchdog.is_overloaded());
+    }
+
+    #[test]
+    fn limits_are_validated() {
+        let mut config = ResourceLimitsConfig::default();
+        assert!(config.validate().is_ok());
+        config.shed_threshold = 1.5;
+        assert!(config.validate().is_err());
+        config.shed_threshold = 1.0;
+        config.max_disk_usage = Some(1_000);
+        assert!(config.validate().is_err());
+        config.database_path = Some(PathBuf::from("db"));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn directory_size_includes_subdirectories() {
+        let dir = tempdir::TempDir::new("exonum_resources").unwrap();
+        fs::write(dir.path().join("a"), vec![0; 10]).unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(dir.path().join("nested").join("b"), vec![0; 5]).unwrap();
+        assert_eq!(directory_size(dir.path()).unwrap(), 15);
+    }
+}
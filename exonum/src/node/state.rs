@@ -18,7 +18,8 @@ use bit_vec::BitVec;
 use serde_json::Value;
 
 use std::{
-    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet},
+    mem,
     ops::Deref,
     sync::{Arc, RwLock},
     time::{Duration, SystemTime},
@@ -29,10 +30,11 @@ use crate::crypto::{Hash, PublicKey, SecretKey};
 use crate::events::network::ConnectedPeerAddr;
 use crate::helpers::{Height, Milliseconds, Round, ValidatorId};
 use crate::messages::{
-    BlockResponse, Connect, Consensus as ConsensusMessage, Precommit, Prevote, Propose,
-    RawTransaction, Signed,
+    short_tx_id, BlockResponse, Connect, Consensus as ConsensusMessage, Precommit, Prevote,
+    Propose, RawTransaction, SignatureAlgorithm, Signed,
 };
 use crate::node::{
+    clock::ClockSkew,
     connect_list::{ConnectList, PeerAddress},
     ConnectInfo,
 };
@@ -89,6 +91,7 @@ pub struct State {
 
     // Maximum of node height in consensus messages.
     nodes_max_height: BTreeMap<PublicKey, Height>,
+    lagging_nodes: HashSet<PublicKey>,
 
     validators_rounds: BTreeMap<ValidatorId, Round>,
 
@@ -96,6 +99,30 @@ pub struct State {
 
     // Cache that stores transactions before adding to persistent pool.
     tx_cache: BTreeMap<Hash, Signed<RawTransaction>>,
+
+    // Total size of the transactions in `tx_cache` in bytes.
+    tx_cache_bytes: usize,
+
+    // Hashes of the uncommitted transactions moved from `tx_cache` to the storage.
+    spilled_txs: BTreeSet<Hash>,
+
+    // Hashes of uncommitted transactions that belong to the priority lane.
+    priority_txs: BTreeSet<Hash>,
+
+    // Time when the uncommitted transactions were received by the node.
+    tx_receive_times: HashMap<Hash, SystemTime>,
+
+    // Conflict keys of the uncommitted transactions received by the node.
+    tx_conflict_keys: HashMap<Hash, Vec<String>>,
+
+    // Authors of the uncommitted transactions received by the node.
+    tx_authors: HashMap<Hash, PublicKey>,
+
+    // Numbers of the transactions in `tx_authors` by their authors.
+    author_pooled_txs: HashMap<PublicKey, usize>,
+
+    // Differences between the local clock and the clocks of the validators.
+    clock_skew: ClockSkew,
 }
 
 /// State of a validator-node.
@@ -137,7 +164,12 @@ struct RequestState {
 /// transactions.
 pub struct ProposeState {
     propose: Signed<Propose>,
+    // Hashes of the proposed transactions. For compact proposes, the hashes of unresolved
+    // short identifiers are zero.
+    transactions: Vec<Hash>,
     unknown_txs: HashSet<Hash>,
+    // Unresolved short identifiers of a compact propose with their positions.
+    unknown_short_ids: HashMap<u64, usize>,
     block_hash: Option<Hash>,
     // Whether the message has been saved to the consensus messages' cache or not.
     is_saved: bool,
@@ -151,6 +183,16 @@ pub struct BlockState {
     patch: Option<Patch>,
     txs: Vec<Hash>,
     proposer_id: ValidatorId,
+    execution: BlockExecution,
+}
+
+/// Statistics of the block execution by the node.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BlockExecution {
+    /// Time spent executing the block.
+    pub time: Duration,
+    /// Number of uncommitted transactions known to the node when it executed the block.
+    pub pool_size: u64,
 }
 
 /// Incomplete block.
@@ -255,6 +297,12 @@ where
     pub fn messages(&self) -> &Vec<T> {
         &self.messages
     }
+
+    /// Returns `true` if the voters have the voting weight sufficient for a quorum
+    /// in the given configuration.
+    pub fn has_majority(&self, config: &StoredConfiguration) -> bool {
+        config.has_majority(self.messages.iter().map(VoteMessage::validator))
+    }
 }
 
 impl RequestData {
@@ -319,14 +367,50 @@ impl ProposeState {
         &self.propose
     }
 
+    /// Returns hashes of the proposed transactions. For compact proposes, the list
+    /// is complete only if there are no unknown transactions.
+    pub fn transactions(&self) -> &[Hash] {
+        &self.transactions
+    }
+
     /// Returns unknown transactions of the propose.
     pub fn unknown_txs(&self) -> &HashSet<Hash> {
         &self.unknown_txs
     }
 
+    /// Returns unresolved short identifiers of a compact propose.
+    pub fn unknown_short_ids(&self) -> Vec<u64> {
+        let mut short_ids: Vec<_> = self.unknown_short_ids.keys().cloned().collect();
+        short_ids.sort_unstable();
+        short_ids
+    }
+
     /// Returns `true` if there are unknown transactions in the propose.
     pub fn has_unknown_txs(&self) -> bool {
-        !self.unknown_txs.is_empty()
+        !self.unknown_txs.is_empty() || !self.unknown_short_ids.is_empty()
+    }
+
+    /// Returns `true` if the transaction is missing from the propose.
+    fn awaits(&self, tx_hash: &Hash) -> bool {
+        if !self.has_unknown_txs() {
+            return false;
+        }
+        self.unknown_txs.contains(tx_hash)
+            || (!self.unknown_short_ids.is_empty()
+                && self
+                    .unknown_short_ids
+                    .contains_key(&short_tx_id(self.propose.short_id_salt(), tx_hash)))
+    }
+
+    /// Fills in the transaction if it matches an unresolved short identifier.
+    fn resolve_short_id(&mut self, tx_hash: Hash) {
+        if self.unknown_short_ids.is_empty() {
+            return;
+        }
+        let short_id = short_tx_id(self.propose.short_id_salt(), &tx_hash);
+        if let Some(position) = self.unknown_short_ids.remove(&short_id) {
+            self.transactions[position] = tx_hash;
+        }
     }
 
     /// Indicates whether Propose has been saved to the consensus messages cache
@@ -342,12 +426,19 @@ impl ProposeState {
 
 impl BlockState {
     /// Creates a new `BlockState` instance with the given parameters.
-    pub fn new(hash: Hash, patch: Patch, txs: Vec<Hash>, proposer_id: ValidatorId) -> Self {
+    pub fn new(
+        hash: Hash,
+        patch: Patch,
+        txs: Vec<Hash>,
+        proposer_id: ValidatorId,
+        execution: BlockExecution,
+    ) -> Self {
         Self {
             hash,
             patch: Some(patch),
             txs,
             proposer_id,
+            execution,
         }
     }
 
@@ -370,6 +461,11 @@ impl BlockState {
     pub fn proposer_id(&self) -> ValidatorId {
         self.proposer_id
     }
+
+    /// Returns the statistics of the block execution.
+    pub fn execution(&self) -> BlockExecution {
+        self.execution
+    }
 }
 
 impl IncompleteBlock {
@@ -480,6 +576,7 @@ impl State {
             unknown_proposes_with_precommits: HashMap::new(),
 
             nodes_max_height: BTreeMap::new(),
+            lagging_nodes: HashSet::new(),
             validators_rounds: BTreeMap::new(),
 
             our_connect_message: connect,
@@ -491,6 +588,16 @@ impl State {
             incomplete_block: None,
 
             tx_cache: BTreeMap::new(),
+            tx_cache_bytes: 0,
+            spilled_txs: BTreeSet::new(),
+
+            priority_txs: BTreeSet::new(),
+            tx_receive_times: HashMap::new(),
+            tx_conflict_keys: HashMap::new(),
+            tx_authors: HashMap::new(),
+            author_pooled_txs: HashMap::new(),
+
+            clock_skew: ClockSkew::default(),
         }
     }
 
@@ -581,6 +688,10 @@ impl State {
         self.renew_validator_id(validator_id);
         trace!("Validator={:#?}", self.validator_state());
 
+        let validators = &config.validator_keys;
+        self.clock_skew
+            .retain(|key| validators.iter().any(|keys| keys.consensus_key == *key));
+
         self.config = config;
     }
 
@@ -634,6 +745,12 @@ impl State {
         self.validators().get(id).map(|x| x.consensus_key)
     }
 
+    /// Returns the signature scheme of the consensus key of a validator identified by id.
+    pub fn consensus_key_algorithm_of(&self, id: ValidatorId) -> Option<SignatureAlgorithm> {
+        let id: usize = id.into();
+        self.validators().get(id).map(|x| x.consensus_key_algorithm)
+    }
+
     /// Returns the consensus public key of the current node.
     pub fn consensus_public_key(&self) -> &PublicKey {
         &self.consensus_public_key
@@ -655,10 +772,28 @@ impl State {
     }
 
     /// Returns the leader id for the specified round and current height.
+    ///
+    /// The rounds are allotted to the validators in proportion to their weights: a validator
+    /// with weight `w` leads `w` consecutive rounds in each cycle over the validators.
     pub fn leader(&self, round: Round) -> ValidatorId {
         let height: u64 = self.height().into();
         let round: u64 = round.into();
-        ValidatorId(((height + round) % (self.validators().len() as u64)) as u16)
+        self.config.validator_by_slot(height + round)
+    }
+
+    /// Checks if the leader of the specified round has asked to be skipped in the proposer
+    /// rotation. Always returns `false` if `skip_lagging_proposers` is disabled in the
+    /// consensus configuration.
+    pub fn is_leader_lagging(&self, round: Round) -> bool {
+        if !self.consensus_config().skip_lagging_proposers {
+            return false;
+        }
+        let leader = self.leader(round);
+        self.validators()
+            .get(leader.0 as usize)
+            .map_or(false, |keys| {
+                self.lagging_nodes.contains(&keys.consensus_key)
+            })
     }
 
     /// Updates known round for a validator and returns
@@ -683,22 +818,35 @@ impl State {
         }
 
         // Find highest non-byzantine round.
-        // At max we can have (N - 1) / 3 byzantine nodes.
-        // It is calculated via rounded up integer division.
-        let max_byzantine_count = (self.validators().len() + 2) / 3 - 1;
-        if self.validators_rounds.len() <= max_byzantine_count {
-            trace!("Count of validators, lower then max byzantine count.");
-            return None;
-        }
-
-        let mut rounds: Vec<_> = self.validators_rounds.iter().map(|(_, v)| v).collect();
+        // At max the byzantine validators can have the weight that does not prevent
+        // the rest of validators from reaching a quorum. Hence, the highest round
+        // reached by the validators with a greater total weight has been reached
+        // by at least one honest validator.
+        let max_byzantine_weight = self
+            .config
+            .total_weight()
+            .saturating_sub(self.config.majority_weight());
+        let mut rounds: Vec<_> = self
+            .validators_rounds
+            .iter()
+            .map(|(&id, &round)| (round, self.config.weight_of(Some(id))))
+            .collect();
         rounds.sort_unstable_by(|a, b| b.cmp(a));
 
-        if *rounds[max_byzantine_count] > self.round {
-            Some(*rounds[max_byzantine_count])
-        } else {
-            None
+        let mut weight = 0_u64;
+        for (round, validator_weight) in rounds {
+            weight = weight.saturating_add(validator_weight);
+            if weight > max_byzantine_weight {
+                return Some(round).filter(|&round| round > self.round);
+            }
         }
+        trace!("Weight of validators is lower than max byzantine weight.");
+        None
+    }
+
+    /// Returns the latest known rounds of the validators at the current height.
+    pub fn validators_rounds(&self) -> &BTreeMap<ValidatorId, Round> {
+        &self.validators_rounds
     }
 
     /// Returns the height for a validator identified by the public key.
@@ -714,6 +862,26 @@ impl State {
             .or_insert_with(Height::zero) = height;
     }
 
+    /// Records whether the node identified by the public key has asked to be skipped
+    /// in the proposer rotation.
+    pub fn set_node_lagging(&mut self, key: PublicKey, is_lagging: bool) {
+        if is_lagging {
+            self.lagging_nodes.insert(key);
+        } else {
+            self.lagging_nodes.remove(&key);
+        }
+    }
+
+    /// Checks if the current node lags behind other nodes of the network.
+    pub fn is_lagging(&self) -> bool {
+        self.is_lagging_behind(self.height())
+    }
+
+    /// Returns `true` if some nodes have reported a height bigger than the given one.
+    pub fn is_lagging_behind(&self, height: Height) -> bool {
+        self.nodes_max_height.values().any(|&h| h > height)
+    }
+
     /// Returns a list of nodes whose height is bigger than one of the current node.
     pub fn nodes_with_bigger_height(&self) -> Vec<&PublicKey> {
         self.nodes_max_height
@@ -723,7 +891,8 @@ impl State {
             .collect()
     }
 
-    /// Returns sufficient number of votes for current validators number.
+    /// Returns sufficient number of votes for current validators number, assuming
+    /// that the validators have equal weights.
     pub fn majority_count(&self) -> usize {
         Self::byzantine_majority_count(self.validators().len())
     }
@@ -855,7 +1024,8 @@ impl State {
         let mut full_proposes = Vec::new();
         for (propose_hash, propose_state) in &mut self.proposes {
             propose_state.unknown_txs.remove(&tx_hash);
-            if propose_state.unknown_txs.is_empty() {
+            propose_state.resolve_short_id(tx_hash);
+            if !propose_state.has_unknown_txs() {
                 full_proposes.push((*propose_hash, propose_state.message().round()));
             }
         }
@@ -863,6 +1033,18 @@ impl State {
         full_proposes
     }
 
+    /// Returns `true` if a propose or an incomplete block of the current height waits
+    /// for the transaction.
+    pub fn is_awaited_tx(&self, tx_hash: &Hash) -> bool {
+        self.proposes
+            .values()
+            .any(|propose| propose.awaits(tx_hash))
+            || self
+                .incomplete_block
+                .as_ref()
+                .map_or(false, |block| block.unknown_txs.contains(tx_hash))
+    }
+
     /// Checks if there is an incomplete block that waits for this transaction.
     /// Returns a block that don't contain unknown transactions.
     ///
@@ -880,6 +1062,21 @@ impl State {
         None
     }
 
+    /// Returns the proposes known at the current height.
+    pub fn proposes(&self) -> &HashMap<Hash, ProposeState> {
+        &self.proposes
+    }
+
+    /// Returns pre-votes at the current height grouped by round and propose hash.
+    pub fn prevote_sets(&self) -> &HashMap<(Round, Hash), Votes<Signed<Prevote>>> {
+        &self.prevotes
+    }
+
+    /// Returns pre-commits at the current height grouped by round and block hash.
+    pub fn precommit_sets(&self) -> &HashMap<(Round, Hash), Votes<Signed<Precommit>>> {
+        &self.precommits
+    }
+
     /// Returns pre-votes for the specified round and propose hash.
     pub fn prevotes(&self, round: Round, propose_hash: Hash) -> &[Signed<Prevote>] {
         self.prevotes
@@ -909,14 +1106,16 @@ impl State {
 
     /// Adds propose from this node to the proposes list for the current height. Such propose
     /// cannot contain unknown transactions. Returns hash of the propose.
-    pub fn add_self_propose(&mut self, msg: Signed<Propose>) -> Hash {
+    pub fn add_self_propose(&mut self, msg: Signed<Propose>, transactions: Vec<Hash>) -> Hash {
         debug_assert!(self.validator_state().is_some());
         let propose_hash = msg.hash();
         self.proposes.insert(
             propose_hash,
             ProposeState {
                 propose: msg,
+                transactions,
                 unknown_txs: HashSet::new(),
+                unknown_short_ids: HashMap::new(),
                 block_hash: None,
                 // TODO: For the moment it's true because this code gets called immediately after
                 // saving a propose to the cache. Think about making this approach less error-prone.
@@ -936,12 +1135,24 @@ impl State {
         transaction_pool: &KeySetIndex<S, Hash>,
     ) -> Result<&ProposeState, failure::Error> {
         let propose_hash = msg.hash();
+        if msg.is_compact() {
+            ensure!(
+                msg.transactions().is_empty(),
+                "Received compact propose with transaction hashes"
+            );
+            if self.proposes.contains_key(&propose_hash) {
+                bail!("Propose already found");
+            }
+            let state = self.resolve_compact_propose(msg, transaction_pool)?;
+            return Ok(self.proposes.entry(propose_hash).or_insert(state));
+        }
+
         match self.proposes.entry(propose_hash) {
             Entry::Occupied(..) => bail!("Propose already found"),
             Entry::Vacant(e) => {
                 let mut unknown_txs = HashSet::new();
                 for hash in msg.transactions() {
-                    if self.tx_cache.contains_key(hash) {
+                    if self.tx_cache.contains_key(hash) || self.spilled_txs.contains(hash) {
                         //Tx with `hash` is  not committed yet.
                         continue;
                     }
@@ -965,9 +1176,12 @@ impl State {
                         .push(propose_hash);
                 }
 
+                let transactions = msg.transactions().to_vec();
                 Ok(e.insert(ProposeState {
                     propose: msg,
+                    transactions,
                     unknown_txs,
+                    unknown_short_ids: HashMap::new(),
                     block_hash: None,
                     is_saved: false,
                 }))
@@ -975,6 +1189,66 @@ impl State {
         }
     }
 
+    /// Resolves the short identifiers of a compact propose against the uncommitted
+    /// transactions. Ambiguous identifiers are left unresolved, so that the transactions
+    /// are requested from the proposer.
+    fn resolve_compact_propose<S: IndexAccess>(
+        &self,
+        msg: Signed<Propose>,
+        transaction_pool: &KeySetIndex<S, Hash>,
+    ) -> Result<ProposeState, failure::Error> {
+        let salt = msg.short_id_salt();
+        let mut positions = HashMap::with_capacity(msg.short_ids().len());
+        for (position, &short_id) in msg.short_ids().iter().enumerate() {
+            ensure!(
+                positions.insert(short_id, position).is_none(),
+                "Received compact propose with duplicate short ids"
+            );
+        }
+
+        let mut candidates: HashMap<u64, Option<Hash>> = HashMap::new();
+        let uncommitted = self
+            .tx_cache
+            .keys()
+            .cloned()
+            .chain(self.spilled_txs.iter().cloned())
+            .chain(transaction_pool.iter());
+        for tx_hash in uncommitted {
+            let short_id = short_tx_id(salt, &tx_hash);
+            if !positions.contains_key(&short_id) {
+                continue;
+            }
+            candidates
+                .entry(short_id)
+                .and_modify(|candidate| {
+                    if *candidate != Some(tx_hash) {
+                        *candidate = None;
+                    }
+                })
+                .or_insert(Some(tx_hash));
+        }
+
+        let mut transactions = vec![Hash::zero(); positions.len()];
+        let mut unknown_short_ids = HashMap::new();
+        for (short_id, position) in positions {
+            match candidates.get(&short_id) {
+                Some(Some(tx_hash)) => transactions[position] = *tx_hash,
+                _ => {
+                    unknown_short_ids.insert(short_id, position);
+                }
+            }
+        }
+
+        Ok(ProposeState {
+            propose: msg,
+            transactions,
+            unknown_txs: HashSet::new(),
+            unknown_short_ids,
+            block_hash: None,
+            is_saved: false,
+        })
+    }
+
     /// Adds block to the list of blocks for the current height. Returns `BlockState` if it is a
     /// new block.
     pub fn add_block(
@@ -983,15 +1257,17 @@ impl State {
         patch: Patch,
         txs: Vec<Hash>,
         proposer_id: ValidatorId,
+        execution: BlockExecution,
     ) -> Option<&BlockState> {
         match self.blocks.entry(block_hash) {
             Entry::Occupied(..) => None,
-            Entry::Vacant(e) => Some(e.insert(BlockState {
-                hash: block_hash,
-                patch: Some(patch),
+            Entry::Vacant(e) => Some(e.insert(BlockState::new(
+                block_hash,
+                patch,
                 txs,
                 proposer_id,
-            })),
+                execution,
+            ))),
         }
     }
 
@@ -1013,7 +1289,10 @@ impl State {
         let mut unknown_txs = HashSet::new();
         for hash in msg.transactions() {
             if check_tx(hash, &txs, &self.tx_cache) {
-                if !self.tx_cache.contains_key(hash) && !txs_pool.contains(hash) {
+                if !self.tx_cache.contains_key(hash)
+                    && !self.spilled_txs.contains(hash)
+                    && !txs_pool.contains(hash)
+                {
                     panic!(
                         "Received block with already \
                          committed transaction"
@@ -1038,7 +1317,6 @@ impl State {
     ///
     /// A node panics if it has already sent a different `Prevote` for the same round.
     pub fn add_prevote(&mut self, msg: Signed<Prevote>) -> bool {
-        let majority_count = self.majority_count();
         if let Some(ref mut validator_state) = self.validator_state {
             if validator_state.id == msg.validator() {
                 if let Some(other) = validator_state
@@ -1063,13 +1341,13 @@ impl State {
             .entry(key)
             .or_insert_with(|| Votes::new(validators_len));
         votes.insert(msg);
-        votes.count() >= majority_count
+        votes.has_majority(&self.config)
     }
 
     /// Returns `true` if there are +2/3 pre-votes for the specified round and hash.
     pub fn has_majority_prevotes(&self, round: Round, propose_hash: Hash) -> bool {
         match self.prevotes.get(&(round, propose_hash)) {
-            Some(votes) => votes.count() >= self.majority_count(),
+            Some(votes) => votes.has_majority(&self.config),
             None => false,
         }
     }
@@ -1096,7 +1374,6 @@ impl State {
     ///
     /// A node panics if it has already sent a different `Precommit` for the same round.
     pub fn add_precommit(&mut self, msg: Signed<Precommit>) -> bool {
-        let majority_count = self.majority_count();
         if let Some(ref mut validator_state) = self.validator_state {
             if validator_state.id == msg.validator() {
                 if let Some(other) = validator_state
@@ -1121,7 +1398,7 @@ impl State {
             .entry(key)
             .or_insert_with(|| Votes::new(validators_len));
         votes.insert(msg);
-        votes.count() >= majority_count
+        votes.has_majority(&self.config)
     }
 
     /// Adds unknown (for this node) propose.
@@ -1150,7 +1427,7 @@ impl State {
     /// Returns true if the node has +2/3 pre-commits for the specified round and block hash.
     pub fn has_majority_precommits(&self, round: Round, block_hash: Hash) -> bool {
         match self.precommits.get(&(round, block_hash)) {
-            Some(votes) => votes.count() >= self.majority_count(),
+            Some(votes) => votes.has_majority(&self.config),
             None => false,
         }
     }
@@ -1238,8 +1515,147 @@ impl State {
         &self.tx_cache
     }
 
-    /// Returns mutable reference to the transactions cache.
+    /// Returns mutable reference to the transactions cache. The size of the cache
+    /// must be recalculated with `update_tx_cache_bytes` after the modification.
     pub fn tx_cache_mut(&mut self) -> &mut BTreeMap<Hash, Signed<RawTransaction>> {
         &mut self.tx_cache
     }
+
+    /// Adds the transaction to the cache.
+    pub fn add_tx_to_cache(&mut self, hash: Hash, tx: Signed<RawTransaction>) {
+        self.tx_cache_bytes += tx_size(&tx);
+        if let Some(replaced) = self.tx_cache.insert(hash, tx) {
+            self.tx_cache_bytes -= tx_size(&replaced);
+        }
+    }
+
+    /// Returns the total size of the cached transactions in bytes.
+    pub fn tx_cache_bytes(&self) -> usize {
+        self.tx_cache_bytes
+    }
+
+    /// Recalculates the size of the transactions cache.
+    pub fn update_tx_cache_bytes(&mut self) {
+        self.tx_cache_bytes = self.tx_cache.values().map(tx_size).sum();
+    }
+
+    /// Returns hashes of the uncommitted transactions moved from the cache to the storage
+    /// during the current height.
+    pub fn spilled_txs(&self) -> &BTreeSet<Hash> {
+        &self.spilled_txs
+    }
+
+    /// Empties the transactions cache, marking the transactions as spilled. The caller
+    /// is responsible for storing the transactions.
+    pub(crate) fn spill_tx_cache(&mut self) -> usize {
+        let txs = mem::replace(&mut self.tx_cache, BTreeMap::new());
+        self.spilled_txs.extend(txs.keys().cloned());
+        self.tx_cache_bytes = 0;
+        txs.len()
+    }
+
+    /// Marks the given transactions as spilled, e.g., after a restart of the node.
+    pub(crate) fn add_spilled_txs<I: IntoIterator<Item = Hash>>(&mut self, hashes: I) {
+        self.spilled_txs.extend(hashes);
+    }
+
+    /// Forgets the spilled transactions after they have been added to the pool.
+    pub(crate) fn clear_spilled_txs(&mut self) {
+        self.spilled_txs.clear();
+    }
+
+    /// Returns hashes of the uncommitted transactions from the priority lane.
+    pub fn priority_txs(&self) -> &BTreeSet<Hash> {
+        &self.priority_txs
+    }
+
+    /// Adds an uncommitted transaction to the priority lane.
+    pub fn add_priority_tx(&mut self, hash: Hash) {
+        self.priority_txs.insert(hash);
+    }
+
+    /// Retains only the priority lane transactions for which the predicate returns `true`.
+    pub fn retain_priority_txs<F: FnMut(&Hash) -> bool>(&mut self, mut f: F) {
+        self.priority_txs = self.priority_txs.iter().cloned().filter(|h| f(h)).collect();
+    }
+
+    /// Returns the time when the uncommitted transaction was received by the node, if known.
+    pub fn tx_receive_time(&self, hash: &Hash) -> Option<SystemTime> {
+        self.tx_receive_times.get(hash).cloned()
+    }
+
+    /// Records the time when the transaction was received by the node.
+    pub fn add_tx_receive_time(&mut self, hash: Hash, time: SystemTime) {
+        self.tx_receive_times.entry(hash).or_insert(time);
+    }
+
+    /// Retains only the receive times of the transactions for which the predicate
+    /// returns `true`.
+    pub fn retain_tx_receive_times<F: FnMut(&Hash) -> bool>(&mut self, mut f: F) {
+        self.tx_receive_times.retain(|hash, _| f(hash));
+    }
+
+    /// Returns the conflict keys of the uncommitted transaction, if they are known.
+    pub fn tx_conflict_keys(&self, hash: &Hash) -> Option<&[String]> {
+        self.tx_conflict_keys.get(hash).map(Vec::as_slice)
+    }
+
+    /// Records the conflict keys of the transaction admitted into the pool.
+    pub fn add_tx_conflict_keys(&mut self, hash: Hash, keys: Vec<String>) {
+        self.tx_conflict_keys.insert(hash, keys);
+    }
+
+    /// Retains only the conflict keys of the transactions for which the predicate
+    /// returns `true`.
+    pub fn retain_tx_conflict_keys<F: FnMut(&Hash) -> bool>(&mut self, mut f: F) {
+        self.tx_conflict_keys.retain(|hash, _| f(hash));
+    }
+
+    /// Returns the number of the uncommitted transactions of the given author known
+    /// to the node.
+    pub fn author_pooled_txs(&self, author: &PublicKey) -> usize {
+        self.author_pooled_txs.get(author).cloned().unwrap_or(0)
+    }
+
+    /// Returns the numbers of the uncommitted transactions by their authors.
+    pub fn pooled_txs_by_author(&self) -> &HashMap<PublicKey, usize> {
+        &self.author_pooled_txs
+    }
+
+    /// Records the author of the transaction admitted into the pool.
+    pub fn add_tx_author(&mut self, hash: Hash, author: PublicKey) {
+        if self.tx_authors.insert(hash, author).is_none() {
+            *self.author_pooled_txs.entry(author).or_insert(0) += 1;
+        }
+    }
+
+    /// Retains only the authors of the transactions for which the predicate returns `true`.
+    pub fn retain_tx_authors<F: FnMut(&Hash) -> bool>(&mut self, mut f: F) {
+        let author_pooled_txs = &mut self.author_pooled_txs;
+        self.tx_authors.retain(|hash, author| {
+            if f(hash) {
+                return true;
+            }
+            if let Some(count) = author_pooled_txs.get_mut(author) {
+                *count -= 1;
+            }
+            false
+        });
+        author_pooled_txs.retain(|_, count| *count > 0);
+    }
+
+    /// Returns the differences between the local clock and the clocks of the validators.
+    pub(crate) fn clock_skew(&self) -> &ClockSkew {
+        &self.clock_skew
+    }
+
+    /// Returns the mutable differences between the local clock and the clocks
+    /// of the validators.
+    pub(crate) fn clock_skew_mut(&mut self) -> &mut ClockSkew {
+        &mut self.clock_skew
+    }
+}
+
+fn tx_size(tx: &Signed<RawTransaction>) -> usize {
+    tx.signed_message().raw().len()
 }
@@ -0,0 +1,409 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transports connecting `ApiSender` to a node running in another process.
+//!
+//! The `ApiSender` of the node delivers messages over an in-process channel. Tools
+//! and sidecars running outside of the node process can construct an `ApiSender`
+//! backed by a [`RemoteTransport`] instead. Remote transports carry only the
+//! transactions; the other external messages require the node process.
+//!
+//! The node accepts transactions on a Unix domain socket if `transaction_socket`
+//! is set in the API section of the node configuration, and [`UnixSocketTransport`]
+//! connects to it. The protocol is line-based: the client sends a
+//! [`TransportRequest`] serialized as JSON, one request per line, and the node
+//! replies with a [`TransportResponse`] line once it has queued the transaction.
+//! Only the user running the node can connect to the socket, and the number of
+//! simultaneously connected clients is limited. Other transports, such as gRPC, can be plugged in by implementing the
+//! [`RemoteTransport`] trait.
+//!
+//! [`RemoteTransport`]: trait.RemoteTransport.html
+//! [`UnixSocketTransport`]: struct.UnixSocketTransport.html
+//! [`TransportRequest`]: struct.TransportRequest.html
+//! [`TransportResponse`]: struct.TransportResponse.html
+
+use failure::Error;
+
+#[cfg(unix)]
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::Shutdown,
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+#[cfg(unix)]
+use super::ApiSender;
+use crate::blockchain::TransactionTag;
+use crate::messages::{RawTransaction, Signed};
+
+/// Maximum number of simultaneously connected clients of the transaction socket.
+/// Connections over the limit are closed at once.
+#[cfg(unix)]
+const MAX_TRANSPORT_CLIENTS: usize = 64;
+/// Permissions of the transaction socket, allowing only the user running the node
+/// to connect to it.
+#[cfg(unix)]
+const TRANSPORT_SOCKET_MODE: u32 = 0o600;
+
+/// Transport delivering transactions to a node running in another process.
+pub trait RemoteTransport: Send + Sync + 'static {
+    /// Sends the transaction to the node. The transaction is broadcast by the node
+    /// like the transactions received via the API.
+    fn send_transaction(
+        &self,
+        transaction: Signed<RawTransaction>,
+        tag: Option<TransactionTag>,
+    ) -> Result<(), Error>;
+}
+
+/// Request sent to the transaction socket of the node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransportRequest {
+    /// Hex-encoded signed transaction.
+    pub transaction: Signed<RawTransaction>,
+    /// Optional tag of the transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<TransactionTag>,
+}
+
+/// Reply of the node to a `TransportRequest`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransportResponse {
+    /// Description of the error if the node has not accepted the transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Client of the transaction socket of a node.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixSocketTransport {
+    stream: Mutex<BufReader<UnixStream>>,
+}
+
+#[cfg(unix)]
+impl UnixSocketTransport {
+    /// Connects to the transaction socket of the node at the given path.
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let stream = UnixStream::connect(path)?;
+        Ok(Self {
+            stream: Mutex::new(BufReader::new(stream)),
+        })
+    }
+}
+
+#[cfg(unix)]
+impl RemoteTransport for UnixSocketTransport {
+    fn send_transaction(
+        &self,
+        transaction: Signed<RawTransaction>,
+        tag: Option<TransactionTag>,
+    ) -> Result<(), Error> {
+        let mut line = serde_json::to_string(&TransportRequest { transaction, tag })?;
+        line.push('\n');
+
+        let mut stream = self.stream.lock().expect("Expected mutex lock");
+        stream.get_mut().write_all(line.as_bytes())?;
+        let mut reply = String::new();
+        if stream.read_line(&mut reply)? == 0 {
+            bail!("Transaction socket has been closed by the node");
+        }
+        match serde_json::from_str::<TransportResponse>(&reply)?.error {
+            None => Ok(()),
+            Some(e) => bail!("Transaction is not accepted by the node: {}", e),
+        }
+    }
+}
+
+/// Connected clients of the transaction socket, by connection identifiers.
+#[cfg(unix)]
+type TransportClients = Arc<Mutex<HashMap<u64, UnixStream>>>;
+
+/// Server accepting transactions on a Unix domain socket.
+///
+/// Dropping the server stops accepting connections, closes the connected clients
+/// and removes the socket.
+#[cfg(unix)]
+#[derive(Debug)]
+pub(crate) struct TransportServer {
+    path: PathBuf,
+    stopped: Arc<AtomicBool>,
+    clients: TransportClients,
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(unix)]
+impl TransportServer {
+    /// Starts accepting connections on the given path. A socket left at the path
+    /// by a previous run of the node is removed.
+    pub(crate) fn start(path: &Path, sender: ApiSender) -> io::Result<Self> {
+        if let Ok(metadata) = fs::metadata(path) {
+            if metadata.file_type().is_socket() {
+                fs::remove_file(path)?;
+            }
+        }
+        let listener = UnixListener::bind(path)?;
+        // The permissions are set explicitly rather than derived from the umask
+        // of the node process.
+        let permissions = fs::Permissions::from_mode(TRANSPORT_SOCKET_MODE);
+        if let Err(e) = fs::set_permissions(path, permissions) {
+            let _ = fs::remove_file(path);
+            return Err(e);
+        }
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let clients = TransportClients::default();
+        let accept_thread = {
+            let stopped = Arc::clone(&stopped);
+            let clients = Arc::clone(&clients);
+            thread::Builder::new()
+                .name("transaction-socket".to_owned())
+                .spawn(move || accept_clients(&listener, &sender, &stopped, &clients))?
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            stopped,
+            clients,
+            accept_thread: Some(accept_thread),
+        })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for TransportServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wakes up the accept thread waiting for the next connection.
+        match UnixStream::connect(&self.path) {
+            Ok(_) => {
+                if let Some(accept_thread) = self.accept_thread.take() {
+                    if accept_thread.join().is_err() {
+                        error!("Transaction socket thread has panicked");
+                    }
+                }
+            }
+            Err(e) => warn!(
+                "Unable to stop accepting connections on transaction socket {}: {}",
+                self.path.display(),
+                e
+            ),
+        }
+
+        let clients = self.clients.lock().expect("Expected mutex lock");
+        for stream in clients.values() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(
+                "Unable to remove transaction socket {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(unix)]
+fn accept_clients(
+    listener: &UnixListener,
+    sender: &ApiSender,
+    stopped: &AtomicBool,
+    clients: &TransportClients,
+) {
+    for (id, stream) in (0..).zip(listener.incoming()) {
+        if stopped.load(Ordering::SeqCst) {
+            return;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Unable to accept transaction socket connection: {}", e);
+                continue;
+            }
+        };
+        let control = match stream.try_clone() {
+            Ok(control) => control,
+            Err(e) => {
+                error!("Unable to serve transaction socket client: {}", e);
+                continue;
+            }
+        };
+        {
+            let mut clients = clients.lock().expect("Expected mutex lock");
+            if clients.len() >= MAX_TRANSPORT_CLIENTS {
+                warn!(
+                    "Transaction socket has {} connected clients, closing the new connection",
+                    clients.len()
+                );
+                continue;
+            }
+            clients.insert(id, control);
+        }
+
+        let sender = sender.clone();
+        let connected = Arc::clone(clients);
+        let spawned = thread::Builder::new()
+            .name("transaction-socket-client".to_owned())
+            .spawn(move || {
+                serve_client(stream, &sender);
+                connected.lock().expect("Expected mutex lock").remove(&id);
+            });
+        if let Err(e) = spawned {
+            error!("Unable to serve transaction socket client: {}", e);
+            clients.lock().expect("Expected mutex lock").remove(&id);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn serve_client(stream: UnixStream, sender: &ApiSender) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            error!("Unable to serve transaction socket client: {}", e);
+            return;
+        }
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                info!("Transaction socket client disconnected: {}", e);
+                return;
+            }
+        };
+        let result = serde_json::from_str::<TransportRequest>(&line)
+            .map_err(Error::from)
+            .and_then(|request| {
+                sender.broadcast_tagged_transaction(request.transaction, request.tag)
+            });
+        let response = TransportResponse {
+            error: result.err().map(|e| e.to_string()),
+        };
+        let mut reply = serde_json::to_string(&response).expect("Unable to serialize response");
+        reply.push('\n');
+        if let Err(e) = writer.write_all(reply.as_bytes()) {
+            info!("Transaction socket client disconnected: {}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use futures::{sync::mpsc, Future, Stream};
+    use tempfile::TempDir;
+
+    use std::time::Duration;
+
+    use super::*;
+    use crate::crypto::gen_keypair;
+    use crate::messages::{Message, ServiceTransaction};
+    use crate::node::ExternalMessage;
+
+    #[test]
+    fn transactions_are_sent_over_unix_socket() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("node.sock");
+        let (sender, receiver) = mpsc::channel(16);
+        let server = TransportServer::start(&path, ApiSender::new(sender)).unwrap();
+
+        let client = ApiSender::with_transport(UnixSocketTransport::connect(&path).unwrap());
+        let (public_key, secret_key) = gen_keypair();
+        let tx = Message::sign_transaction(
+            ServiceTransaction::from_raw_unchecked(0, vec![1; 16]),
+            0,
+            public_key,
+            &secret_key,
+        );
+        let tag = TransactionTag::new(vec![1, 2, 3]).unwrap();
+        client
+            .broadcast_tagged_transaction(tx.clone(), Some(tag.clone()))
+            .unwrap();
+        client.broadcast_transaction(tx.clone()).unwrap();
+        // Messages other than transactions are not supported by the transport.
+        assert!(client.consensus_state().is_err());
+
+        let messages = receiver.take(2).collect().wait().unwrap();
+        match messages[0] {
+            ExternalMessage::TaggedTransaction {
+                ref transaction,
+                tag: ref received_tag,
+            } => {
+                assert_eq!(*transaction, tx);
+                assert_eq!(*received_tag, tag);
+            }
+            ref other => panic!("Unexpected message: {:?}", other),
+        }
+        match messages[1] {
+            ExternalMessage::Transaction(ref transaction) => assert_eq!(*transaction, tx),
+            ref other => panic!("Unexpected message: {:?}", other),
+        }
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, TRANSPORT_SOCKET_MODE);
+
+        drop(server);
+        assert!(!path.exists());
+        // The connected clients are disconnected.
+        assert!(client.broadcast_transaction(tx).is_err());
+    }
+
+    #[test]
+    fn number_of_clients_is_limited() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("node.sock");
+        let (sender, _receiver) = mpsc::channel(16);
+        let server = TransportServer::start(&path, ApiSender::new(sender)).unwrap();
+
+        let clients: Vec<_> = (0..MAX_TRANSPORT_CLIENTS)
+            .map(|_| UnixStream::connect(&path).unwrap())
+            .collect();
+        while server.clients.lock().unwrap().len() < MAX_TRANSPORT_CLIENTS {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // The connection over the limit is closed by the node.
+        let mut rejected = BufReader::new(UnixStream::connect(&path).unwrap());
+        let mut line = String::new();
+        assert_eq!(rejected.read_line(&mut line).unwrap(), 0);
+        assert_eq!(server.clients.lock().unwrap().len(), MAX_TRANSPORT_CLIENTS);
+
+        // The slot of a disconnected client is freed.
+        drop(clients);
+        while !server.clients.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let client = UnixSocketTransport::connect(&path).unwrap();
+        let (public_key, secret_key) = gen_keypair();
+        let tx = Message::sign_transaction(
+            ServiceTransaction::from_raw_unchecked(0, vec![1; 16]),
+            0,
+            public_key,
+            &secret_key,
+        );
+        client.send_transaction(tx, None).unwrap();
+    }
+}
@@ -59,12 +59,15 @@
 //! }
 //! ```
 
-pub use self::schema::blockchain::{Block, ConfigReference, TransactionResult, TxLocation};
+pub use self::schema::blockchain::{
+    Block, BlockHeaderEntry, BlockMetrics, ConfigActivation, ConfigReference, OracleObservation,
+    OracleValue, TransactionEvent, TransactionResult, TxLocation,
+};
 pub use self::schema::helpers::{BitVec, Hash, PublicKey, Signature};
 pub use self::schema::protocol::{
     BlockRequest, BlockResponse, Connect, PeersRequest, PoolTransactionsRequest, Precommit,
-    Prevote, PrevotesRequest, Propose, ProposeRequest, Status, TransactionsRequest,
-    TransactionsResponse,
+    Prevote, PrevotesRequest, Propose, ProposeRequest, ServiceMessage, ShortTransactionsRequest,
+    Status, TransactionsRequest, TransactionsResponse,
 };
 
 pub mod schema;
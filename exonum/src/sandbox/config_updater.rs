@@ -76,7 +76,7 @@ impl Service for ConfigUpdateService {
         CONFIG_SERVICE
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         vec![]
     }
 
@@ -18,16 +18,16 @@
 
 use rand::{thread_rng, Rng};
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
 
-use crate::blockchain::{Blockchain, Schema, CORE_SERVICE};
+use crate::blockchain::{Blockchain, Schema};
 use crate::crypto::{gen_keypair_from_seed, CryptoHash, Hash, Seed, HASH_SIZE, SEED_LENGTH};
 use crate::helpers::{Height, Round, ValidatorId};
-use crate::messages::{Precommit, Signed};
+use crate::messages::{Message, Precommit, Signed, Status};
 use crate::sandbox::{
     self,
     sandbox_tests_helper::*,
-    timestamping::{TimestampingTxGenerator, DATA_SIZE, TIMESTAMPING_SERVICE},
+    timestamping::{TimestampingTxGenerator, DATA_SIZE},
     timestamping_sandbox, timestamping_sandbox_builder,
 };
 
@@ -87,6 +87,23 @@ fn test_reach_one_height() {
     sandbox.assert_state(Height(2), Round(1));
 }
 
+/// Checks that the node records the performance metrics of the committed blocks.
+#[test]
+fn test_block_metrics_recorded() {
+    let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+
+    let tx = gen_timestamping_tx();
+    add_one_height_with_transactions(&sandbox, &sandbox_state, &[tx.clone()]);
+
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let metrics = Schema::new(&snapshot).block_metrics().get(&1).unwrap();
+    assert_eq!(metrics.height, Height(1));
+    assert_eq!(metrics.tx_count, 1);
+    assert_eq!(metrics.block_size, tx.signed_message().raw().len() as u64);
+    assert_eq!(metrics.pool_size, 1);
+}
+
 /// The idea of the test is to reach one height in the network with single validator.
 #[test]
 fn test_one_validator() {
@@ -182,11 +199,11 @@ fn test_query_state_hash() {
     for _ in 0..2 {
         let state_hash = sandbox.last_state_hash();
         let configs_rh = sandbox.get_configs_merkle_root();
-        let configs_key = Blockchain::service_table_unique_key(CORE_SERVICE, 0);
-        let timestamp_t1_key = Blockchain::service_table_unique_key(TIMESTAMPING_SERVICE, 0);
-        let timestamp_t2_key = Blockchain::service_table_unique_key(TIMESTAMPING_SERVICE, 1);
+        let configs_key = Blockchain::state_table_key("core.configs");
+        let timestamp_t1_key = Blockchain::state_table_key("sandbox_timestamping.first");
+        let timestamp_t2_key = Blockchain::state_table_key("sandbox_timestamping.second");
 
-        let proof_configs = sandbox.get_proof_to_service_table(CORE_SERVICE, 0);
+        let proof_configs = sandbox.get_proof_to_service_table("core.configs");
         let proof = proof_configs.check().unwrap();
         assert_eq!(proof.root_hash(), state_hash);
         assert_ne!(configs_rh, Hash::zero());
@@ -195,7 +212,7 @@ fn test_query_state_hash() {
             vec![(&configs_key, &configs_rh)]
         );
 
-        let proof_configs = sandbox.get_proof_to_service_table(TIMESTAMPING_SERVICE, 0);
+        let proof_configs = sandbox.get_proof_to_service_table("sandbox_timestamping.first");
         let proof = proof_configs.check().unwrap();
         assert_eq!(proof.root_hash(), state_hash);
         assert_eq!(
@@ -203,7 +220,7 @@ fn test_query_state_hash() {
             vec![(&timestamp_t1_key, &Hash::new([127; HASH_SIZE]))]
         );
 
-        let proof_configs = sandbox.get_proof_to_service_table(TIMESTAMPING_SERVICE, 1);
+        let proof_configs = sandbox.get_proof_to_service_table("sandbox_timestamping.second");
         let proof = proof_configs.check().unwrap();
         assert_eq!(proof.root_hash(), state_hash);
         assert_eq!(
@@ -308,3 +325,42 @@ fn tx_cache_with_tx_block_limit() {
 
     //TODO: check pool after commit.
 }
+
+#[test]
+fn rounds_of_lagging_leaders_are_shortened() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_consensus(|config| {
+            config.skip_lagging_proposers = true;
+        })
+        .build();
+
+    let lagging = ValidatorId(2);
+    assert_eq!(sandbox.leader(Round(1)), lagging);
+    sandbox.recv(&Message::concrete(
+        Status::new(Height(1), &sandbox.last_hash(), 0).with_skip_propose(true),
+        sandbox.public_key(lagging),
+        sandbox.secret_key(lagging),
+    ));
+    // The leaders do not depend on the reports of the validators.
+    assert_eq!(sandbox.leader(Round(1)), lagging);
+
+    sandbox.add_time(Duration::from_millis(PROPOSE_TIMEOUT));
+    sandbox.assert_state(Height(1), Round(2));
+    assert_eq!(sandbox.leader(Round(2)), ValidatorId(3));
+}
+
+#[test]
+fn skip_propose_is_ignored_if_disabled() {
+    let sandbox = timestamping_sandbox();
+
+    let lagging = ValidatorId(2);
+    sandbox.recv(&Message::concrete(
+        Status::new(Height(1), &sandbox.last_hash(), 0).with_skip_propose(true),
+        sandbox.public_key(lagging),
+        sandbox.secret_key(lagging),
+    ));
+    assert_eq!(sandbox.leader(Round(1)), lagging);
+
+    sandbox.add_time(Duration::from_millis(PROPOSE_TIMEOUT));
+    sandbox.assert_state(Height(1), Round(1));
+}
@@ -53,6 +53,7 @@ fn test_ignore_message_with_incorrect_validator_id() {
             Round(1),
             &sandbox.last_hash(),
             &[],
+            &[],
         ),
         sandbox.public_key(ValidatorId(1)),
         sandbox.secret_key(ValidatorId(1)),
@@ -85,24 +86,35 @@ fn ignore_propose_from_non_leader() {
     sandbox.recv(&propose);
 }
 
-/// Propose with incorrect time should be handled as usual.
 #[test]
-fn handle_propose_with_incorrect_time() {
+fn ignore_propose_without_precommits() {
     let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
 
-    let propose = ProposeBuilder::new(&sandbox).build();
+    add_one_height(&sandbox, &sandbox_state);
+
+    let propose = ProposeBuilder::new(&sandbox)
+        // without this line Prevote would have been broadcast
+        .with_precommits(&[])
+        .build();
 
     sandbox.recv(&propose);
+}
 
-    sandbox.assert_lock(NOT_LOCKED, None);
-    sandbox.broadcast(&sandbox.create_prevote(
-        ValidatorId(0),
-        Height(1),
-        Round(1),
-        &propose.hash(),
-        NOT_LOCKED,
-        sandbox.secret_key(ValidatorId(0)),
-    ));
+#[test]
+fn ignore_propose_with_insufficient_precommits() {
+    let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+
+    add_one_height(&sandbox, &sandbox_state);
+
+    let precommits = sandbox.block_precommits(&sandbox.last_hash());
+    let propose = ProposeBuilder::new(&sandbox)
+        // without this line Prevote would have been broadcast
+        .with_precommits(&precommits[..1])
+        .build();
+
+    sandbox.recv(&propose);
 }
 
 #[test]
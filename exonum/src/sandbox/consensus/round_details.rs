@@ -29,6 +29,7 @@ use crate::messages::{PrevotesRequest, ProtocolMessage, TransactionsRequest};
 use crate::node::state::{
     PREVOTES_REQUEST_TIMEOUT, PROPOSE_REQUEST_TIMEOUT, TRANSACTIONS_REQUEST_TIMEOUT,
 };
+use crate::node::TimeoutKind;
 use crate::sandbox::{self, compute_tx_hash, sandbox_tests_helper::*, timestamping_sandbox};
 
 /// check scenario:
@@ -57,6 +58,40 @@ fn positive_get_propose_send_prevote() {
     info!("time: {:?}", sandbox.time());
 }
 
+#[test]
+fn consensus_state_dump() {
+    let sandbox = timestamping_sandbox();
+
+    let propose = ProposeBuilder::new(&sandbox).build();
+    sandbox.recv(&propose);
+    sandbox.broadcast(&sandbox.create_prevote(
+        ValidatorId(0),
+        Height(1),
+        Round(1),
+        &propose.hash(),
+        NOT_LOCKED,
+        sandbox.secret_key(ValidatorId(0)),
+    ));
+
+    let state = sandbox.consensus_state();
+    assert_eq!(state.height, Height(1));
+    assert_eq!(state.round, Round(1));
+    assert_eq!(state.validator_id, Some(ValidatorId(0)));
+    assert_eq!(state.locked_propose, None);
+    assert_eq!(state.proposes.len(), 1);
+    assert_eq!(state.proposes[0].hash, propose.hash());
+    assert_eq!(state.proposes[0].unknown_txs, 0);
+    assert_eq!(state.prevotes.len(), 1);
+    assert_eq!(state.prevotes[0].hash, propose.hash());
+    assert_eq!(state.prevotes[0].validators, vec![ValidatorId(0)]);
+    assert!(state.precommits.is_empty());
+    let round_timeout = state
+        .timeouts
+        .iter()
+        .find(|timeout| timeout.kind == TimeoutKind::Round);
+    assert_eq!(round_timeout.unwrap().round, Some(Round(1)));
+}
+
 // HANDLE FULL PROPOSE
 
 // - send prevote when lock=0 for known propose//covered in positive_get_propose_send_prevote()
@@ -1304,19 +1339,20 @@ fn handle_precommit_positive_scenario_commit_with_queued_precommit() {
         .with_state_hash(&sandbox.compute_state_hash(&[tx.clone()]))
         .build();
 
+    // The propose for the second height includes the precommits for the first block,
+    // so it is created by another sandbox which has already committed the same block.
+    let committed_sandbox = timestamping_sandbox();
+    add_one_height_with_transactions(&committed_sandbox, &SandboxState::new(), &[tx.clone()]);
+    assert_eq!(first_block.hash(), committed_sandbox.last_hash());
+
     // this propose will be used during second commit
-    let height_one_propose = ProposeBuilder::new(&sandbox)
+    let height_one_propose = ProposeBuilder::new(&committed_sandbox)
         .with_validator(ValidatorId(3))
-        .with_height(Height(2))
-        .with_prev_hash(&first_block.hash())
         .build();
 
     // this block will be created during second commit while manually creating precommits
-    let second_block = BlockBuilder::new(&sandbox)
+    let second_block = BlockBuilder::new(&committed_sandbox)
         .with_proposer_id(ValidatorId(3))
-        .with_height(Height(2))
-        .with_prev_hash(&first_block.hash())
-        .with_state_hash(&sandbox.compute_state_hash(&[tx.clone()]))
         .build();
 
     let precommit_1 = sandbox.create_precommit(
@@ -18,8 +18,10 @@ use std::time::Duration;
 
 use crate::crypto::CryptoHash;
 use crate::helpers::{Height, Round, ValidatorId};
-use crate::node::state::PROPOSE_REQUEST_TIMEOUT;
-use crate::sandbox::{compute_tx_hash, sandbox_tests_helper::*, timestamping_sandbox};
+use crate::node::{state::PROPOSE_REQUEST_TIMEOUT, ClockSanityConfig};
+use crate::sandbox::{
+    compute_tx_hash, sandbox_tests_helper::*, timestamping_sandbox, timestamping_sandbox_builder,
+};
 
 /// HANDLE ROUND TIMEOUT:
 /// - Ignore if height and round are not the same
@@ -156,6 +158,54 @@ fn test_send_propose_and_prevote_when_we_are_leader() {
     sandbox.add_time(Duration::from_millis(0));
 }
 
+/// idea of the scenario is to become leader with a skewed clock
+/// then:
+///  - precommit of another validator reveals the skew
+///  - when propose timeout is triggered - propose is not sent
+#[test]
+fn test_skip_propose_when_clock_is_skewed() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_clock(ClockSanityConfig {
+            max_propose_skew: Some(1_000),
+            ..ClockSanityConfig::default()
+        })
+        .build();
+
+    let propose = ProposeBuilder::new(&sandbox).build();
+    let block = BlockBuilder::new(&sandbox).build();
+    // The local clock is 10 seconds ahead of validator 1.
+    let precommit = sandbox.create_precommit(
+        ValidatorId(1),
+        Height(1),
+        Round(3),
+        &propose.hash(),
+        &block.hash(),
+        (sandbox.time() - Duration::from_secs(10)).into(),
+        sandbox.secret_key(ValidatorId(1)),
+    );
+    sandbox.recv(&precommit);
+    assert_eq!(sandbox.node_state().clock_skew().estimate(), Some(10_000));
+
+    // round happens
+    sandbox.add_time(Duration::from_millis(sandbox.current_round_timeout()));
+    sandbox.add_time(Duration::from_millis(
+        sandbox.current_round_timeout() + PROPOSE_TIMEOUT,
+    ));
+    sandbox.assert_state(Height(1), Round(3));
+    assert!(sandbox.is_leader());
+
+    // The queued precommit is handled in round 3, but our propose is not broadcast.
+    sandbox.send(
+        sandbox.public_key(ValidatorId(1)),
+        &make_request_propose_from_precommit(&sandbox, &precommit),
+    );
+    sandbox.send(
+        sandbox.public_key(ValidatorId(1)),
+        &make_request_prevote_from_precommit(&sandbox, &precommit),
+    );
+    sandbox.add_time(Duration::from_millis(0));
+}
+
 /// HANDLE ROUND TIMEOUT:
 /// - send prevote if locked to propose
 /// idea:
@@ -20,7 +20,7 @@ use std::time::Duration;
 
 use crate::crypto::{gen_keypair, CryptoHash, Hash};
 use crate::helpers::{Height, Milliseconds, Round, ValidatorId};
-use crate::messages::{RawTransaction, Signed};
+use crate::messages::{short_tx_id, Message, Propose, RawTransaction, Signed};
 use crate::node::state::TRANSACTIONS_REQUEST_TIMEOUT;
 use crate::sandbox::{
     compute_tx_hash,
@@ -96,6 +96,117 @@ fn response_to_request_txs() {
     );
 }
 
+/// idea of the test is to verify that the unknown transactions of a compact propose
+/// are requested by their short ids as soon as the propose is received
+#[test]
+fn request_txs_of_compact_propose() {
+    let mut tx_gen = TimestampingTxGenerator::new(DATA_SIZE);
+    let known_tx = tx_gen.next().unwrap();
+    let unknown_tx = tx_gen.next().unwrap();
+    let sandbox = timestamping_sandbox();
+
+    sandbox.recv(&known_tx);
+
+    let salt = 42;
+    let propose = Message::concrete(
+        Propose::compact(
+            ValidatorId(2),
+            Height(1),
+            Round(1),
+            &sandbox.last_hash(),
+            salt,
+            &[known_tx.hash(), unknown_tx.hash()],
+            &[],
+        ),
+        sandbox.public_key(ValidatorId(2)),
+        sandbox.secret_key(ValidatorId(2)),
+    );
+    sandbox.recv(&propose);
+
+    sandbox.send(
+        sandbox.public_key(ValidatorId(2)),
+        &sandbox.create_short_transactions_request(
+            &sandbox.public_key(ValidatorId(0)),
+            &sandbox.public_key(ValidatorId(2)),
+            &propose.hash(),
+            &[short_tx_id(salt, &unknown_tx.hash())],
+            sandbox.secret_key(ValidatorId(0)),
+        ),
+    );
+
+    sandbox.recv(&sandbox.create_transactions_response(
+        &sandbox.public_key(ValidatorId(2)),
+        &sandbox.public_key(ValidatorId(0)),
+        vec![unknown_tx.clone()],
+        sandbox.secret_key(ValidatorId(2)),
+    ));
+
+    sandbox.broadcast(&sandbox.create_prevote(
+        ValidatorId(0),
+        Height(1),
+        Round(1),
+        &propose.hash(),
+        NOT_LOCKED,
+        sandbox.secret_key(ValidatorId(0)),
+    ));
+}
+
+/// idea of the test is to verify that a compact propose is not voted for until all
+/// its short ids are resolved, even if some of the missing transactions arrive
+#[test]
+fn compact_propose_waits_for_all_short_ids() {
+    let mut tx_gen = TimestampingTxGenerator::new(DATA_SIZE);
+    let first_tx = tx_gen.next().unwrap();
+    let second_tx = tx_gen.next().unwrap();
+    let sandbox = timestamping_sandbox();
+
+    let salt = 42;
+    let propose = Message::concrete(
+        Propose::compact(
+            ValidatorId(2),
+            Height(1),
+            Round(1),
+            &sandbox.last_hash(),
+            salt,
+            &[first_tx.hash(), second_tx.hash()],
+            &[],
+        ),
+        sandbox.public_key(ValidatorId(2)),
+        sandbox.secret_key(ValidatorId(2)),
+    );
+    sandbox.recv(&propose);
+
+    let mut short_ids = vec![
+        short_tx_id(salt, &first_tx.hash()),
+        short_tx_id(salt, &second_tx.hash()),
+    ];
+    short_ids.sort_unstable();
+    sandbox.send(
+        sandbox.public_key(ValidatorId(2)),
+        &sandbox.create_short_transactions_request(
+            &sandbox.public_key(ValidatorId(0)),
+            &sandbox.public_key(ValidatorId(2)),
+            &propose.hash(),
+            &short_ids,
+            sandbox.secret_key(ValidatorId(0)),
+        ),
+    );
+
+    // The propose still has an unresolved short id, so the node does not prevote.
+    sandbox.recv(&first_tx);
+    sandbox.assert_state(Height(1), Round(1));
+
+    sandbox.recv(&second_tx);
+    sandbox.broadcast(&sandbox.create_prevote(
+        ValidatorId(0),
+        Height(1),
+        Round(1),
+        &propose.hash(),
+        NOT_LOCKED,
+        sandbox.secret_key(ValidatorId(0)),
+    ));
+}
+
 #[test]
 fn empty_tx_request() {
     let sandbox = timestamping_sandbox();
@@ -263,6 +374,26 @@ fn rebroadcast_transactions() {
     }
 }
 
+#[test]
+fn rebroadcast_single_transaction() {
+    let sandbox = timestamping_sandbox();
+
+    let transactions = send_txs_into_pool(
+        &sandbox,
+        TimestampingTxGenerator::new(DATA_SIZE).take(2).collect(),
+    );
+
+    let peers = sandbox.recv_rebroadcast_transaction(transactions[1].hash());
+    assert_eq!(peers, Some(sandbox.validators().len() - 1));
+    sandbox.broadcast(&transactions[1]);
+
+    let unknown_tx = TimestampingTxGenerator::new(DATA_SIZE).next().unwrap();
+    assert_eq!(
+        sandbox.recv_rebroadcast_transaction(unknown_tx.hash()),
+        None
+    );
+}
+
 // TODO: transaction verification logic is duplicated,
 // in sandbox so this test is testing sandbox
 #[test]
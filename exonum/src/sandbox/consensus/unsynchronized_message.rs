@@ -19,7 +19,7 @@ use std::time::Duration;
 
 use crate::crypto::CryptoHash;
 use crate::helpers::{Height, Round, ValidatorId};
-use crate::sandbox::{compute_tx_hash, sandbox_tests_helper::*, timestamping_sandbox};
+use crate::sandbox::{sandbox_tests_helper::*, timestamping_sandbox};
 
 #[test]
 fn test_queue_message_from_future_round() {
@@ -91,13 +91,13 @@ fn test_queue_propose_message_from_next_height() {
 
     let tx = gen_timestamping_tx();
 
-    let block_at_first_height = BlockBuilder::new(&sandbox)
-        .with_proposer_id(ValidatorId(0))
-        .with_tx_hash(&compute_tx_hash(&[tx.clone()]))
-        .with_state_hash(&sandbox.compute_state_hash(&[tx.clone()]))
-        .build();
+    // The future propose includes the precommits for the block at the first height,
+    // so it is created by another sandbox which has already committed the same block.
+    let committed_sandbox = timestamping_sandbox();
+    add_one_height_with_transactions(&committed_sandbox, &SandboxState::new(), &[tx.clone()]);
+    let block_at_first_height = committed_sandbox.last_block();
 
-    let future_propose = sandbox.create_propose(
+    let future_propose = committed_sandbox.create_propose(
         ValidatorId(0),
         Height(2),
         Round(2),
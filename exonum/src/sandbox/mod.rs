@@ -13,7 +13,10 @@
 // limitations under the License.
 
 use bit_vec::BitVec;
-use futures::{sync::mpsc, Async, Future, Sink, Stream};
+use futures::{
+    sync::{mpsc, oneshot},
+    Async, Future, Sink, Stream,
+};
 
 use std::{
     cell::{Ref, RefCell, RefMut},
@@ -42,13 +45,14 @@ use crate::{
     helpers::{user_agent, Height, Milliseconds, Round, ValidatorId},
     messages::{
         BlockRequest, BlockResponse, Connect, Message, PeersRequest, Precommit, Prevote,
-        PrevotesRequest, Propose, ProposeRequest, ProtocolMessage, RawTransaction, Signed,
-        SignedMessage, Status, TransactionsRequest, TransactionsResponse,
+        PrevotesRequest, Propose, ProposeRequest, ProtocolMessage, RawTransaction,
+        ShortTransactionsRequest, Signed, SignedMessage, Status, TransactionsRequest,
+        TransactionsResponse,
     },
     node::{
-        ApiSender, Configuration, ConnectInfo, ConnectList, ConnectListConfig, ExternalMessage,
-        ListenerConfig, NodeHandler, NodeSender, PeerAddress, ServiceConfig, State,
-        SystemStateProvider,
+        ApiSender, ClockSanityConfig, Configuration, ConnectInfo, ConnectList, ConnectListConfig,
+        ConsensusStateInfo, ExternalMessage, ListenerConfig, NodeHandler, NodeSender, PeerAddress,
+        ServiceConfig, State, SystemStateProvider,
     },
     sandbox::{
         config_updater::ConfigUpdateService, sandbox_tests_helper::PROPOSE_TIMEOUT,
@@ -304,7 +308,8 @@ impl Sandbox {
         Message::concrete(PoolTransactionsRequest::new(to), *public_key, secret_key)
     }
 
-    /// Creates a `Propose` message signed by this validator.
+    /// Creates a `Propose` message signed by this validator. The `Propose` includes
+    /// the precommits for the block with the given hash known to the node.
     pub fn create_propose(
         &self,
         validator_id: ValidatorId,
@@ -315,7 +320,14 @@ impl Sandbox {
         secret_key: &SecretKey,
     ) -> Signed<Propose> {
         Message::concrete(
-            Propose::new(validator_id, height, round, last_hash, tx_hashes),
+            Propose::new(
+                validator_id,
+                height,
+                round,
+                last_hash,
+                tx_hashes,
+                &self.block_precommits(last_hash),
+            ),
             self.public_key(validator_id),
             secret_key,
         )
@@ -416,6 +428,22 @@ impl Sandbox {
         Message::concrete(TransactionsRequest::new(to, txs), *author, secret_key)
     }
 
+    /// Creates a `ShortTransactionsRequest` message signed by this validator.
+    pub fn create_short_transactions_request(
+        &self,
+        author: &PublicKey,
+        to: &PublicKey,
+        propose_hash: &Hash,
+        short_ids: &[u64],
+        secret_key: &SecretKey,
+    ) -> Signed<ShortTransactionsRequest> {
+        Message::concrete(
+            ShortTransactionsRequest::new(to, propose_hash, short_ids),
+            *author,
+            secret_key,
+        )
+    }
+
     /// Creates a `TransactionsResponse` message signed by this validator.
     pub fn create_transactions_response<I>(
         &self,
@@ -490,6 +518,24 @@ impl Sandbox {
             .handle_event(ExternalMessage::Rebroadcast);
     }
 
+    pub fn recv_rebroadcast_transaction(&self, tx_hash: Hash) -> Option<usize> {
+        self.check_unexpected_message();
+        let (reply, receiver) = oneshot::channel();
+        self.inner
+            .borrow_mut()
+            .handle_event(ExternalMessage::RebroadcastTransaction { tx_hash, reply });
+        receiver.wait().expect("Rebroadcast reply is dropped")
+    }
+
+    pub fn consensus_state(&self) -> ConsensusStateInfo {
+        self.check_unexpected_message();
+        let (reply, receiver) = oneshot::channel();
+        self.inner
+            .borrow_mut()
+            .handle_event(ExternalMessage::ConsensusState(reply));
+        receiver.wait().expect("Consensus state reply is dropped")
+    }
+
     pub fn process_events(&self) {
         self.inner.borrow_mut().process_events();
     }
@@ -645,6 +691,22 @@ impl Sandbox {
         *self.last_block().state_hash()
     }
 
+    /// Returns the precommits for the block with the given hash stored by the node.
+    pub fn block_precommits(&self, block_hash: &Hash) -> Vec<Signed<Precommit>> {
+        let snapshot = self.blockchain_ref().snapshot();
+        let precommits = Schema::new(&snapshot).precommits(block_hash);
+        precommits.iter().collect()
+    }
+
+    /// Returns the consensus time of the block at the current height, provided that
+    /// its `Propose` includes the precommits for the latest block stored by the node.
+    pub fn next_block_time(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let precommits = self.block_precommits(&self.last_hash());
+        let snapshot = self.blockchain_ref().snapshot();
+        let schema = Schema::new(&snapshot);
+        schema.next_block_time(&precommits)
+    }
+
     pub fn filter_present_transactions<'a, I>(&self, txs: I) -> Vec<Signed<RawTransaction>>
     where
         I: IntoIterator<Item = &'a Signed<RawTransaction>>,
@@ -673,8 +735,21 @@ impl Sandbox {
             .collect()
     }
 
-    /// Extracts state_hash from the fake block.
+    /// Extracts state_hash from the fake block with the consensus time computed from
+    /// the precommits for the latest block.
     pub fn compute_state_hash<'a, I>(&self, txs: I) -> Hash
+    where
+        I: IntoIterator<Item = &'a Signed<RawTransaction>>,
+    {
+        self.compute_state_hash_at(self.next_block_time(), txs)
+    }
+
+    /// Extracts state_hash from the fake block with the given consensus time.
+    pub fn compute_state_hash_at<'a, I>(
+        &self,
+        time: Option<chrono::DateTime<chrono::Utc>>,
+        txs: I,
+    ) -> Hash
     where
         I: IntoIterator<Item = &'a Signed<RawTransaction>>,
     {
@@ -702,8 +777,13 @@ impl Sandbox {
 
         let fork = {
             let mut fork = blockchain.fork();
-            let (_, patch) =
-                blockchain.create_patch(ValidatorId(0), height, &hashes, &mut BTreeMap::new());
+            let (_, patch) = blockchain.create_patch_with_time(
+                ValidatorId(0),
+                height,
+                time,
+                &hashes,
+                &mut BTreeMap::new(),
+            );
             fork.merge(patch);
             fork
         };
@@ -722,14 +802,10 @@ impl Sandbox {
         *Schema::new(&fork).last_block().state_hash()
     }
 
-    pub fn get_proof_to_service_table(
-        &self,
-        service_id: u16,
-        table_idx: usize,
-    ) -> MapProof<Hash, Hash> {
+    pub fn get_proof_to_service_table(&self, name: &str) -> MapProof<Hash, Hash> {
         let snapshot = self.blockchain_ref().snapshot();
         let schema = Schema::new(&snapshot);
-        schema.get_proof_to_service_table(service_id, table_idx)
+        schema.get_proof_to_service_table(name)
     }
 
     pub fn get_configs_merkle_root(&self) -> Hash {
@@ -890,6 +966,12 @@ impl Sandbox {
             network: NetworkConfiguration::default(),
             peer_discovery: Vec::new(),
             mempool: Default::default(),
+            retention: Default::default(),
+            observer_address: None,
+            clock: Default::default(),
+            alerts: Vec::new(),
+            resources: Default::default(),
+            consensus_archive: None,
         };
 
         let system_state = SandboxSystemStateProvider {
@@ -989,6 +1071,7 @@ pub struct SandboxBuilder {
     services: Vec<Box<dyn Service>>,
     validators_count: u8,
     consensus_config: ConsensusConfig,
+    clock: ClockSanityConfig,
 }
 
 impl SandboxBuilder {
@@ -1006,7 +1089,14 @@ impl SandboxBuilder {
                 min_propose_timeout: PROPOSE_TIMEOUT,
                 max_propose_timeout: PROPOSE_TIMEOUT,
                 propose_timeout_threshold: std::u32::MAX,
+                skip_lagging_proposers: false,
+                compact_proposals: false,
+                parallel_execution: false,
+                transaction_gas_limit: 0,
+                max_block_size: 0,
+                max_block_gas: 0,
             },
+            clock: ClockSanityConfig::default(),
         }
     }
 
@@ -1030,6 +1120,11 @@ impl SandboxBuilder {
         self
     }
 
+    pub fn with_clock(mut self, clock: ClockSanityConfig) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn build(self) -> Sandbox {
         let _ = env_logger::Builder::from_default_env()
             .target(env_logger::Target::Stdout)
@@ -1039,6 +1134,7 @@ impl SandboxBuilder {
             self.services,
             self.consensus_config,
             self.validators_count,
+            self.clock,
         );
 
         sandbox.inner.borrow_mut().sent.clear(); // To clear initial connect messages.
@@ -1061,6 +1157,7 @@ fn sandbox_with_services_uninitialized(
     services: Vec<Box<dyn Service>>,
     consensus: ConsensusConfig,
     validators_count: u8,
+    clock: ClockSanityConfig,
 ) -> Sandbox {
     let validators = (0..validators_count)
         .map(|i| gen_keypair_from_seed(&Seed::new([i; SEED_LENGTH])))
@@ -1101,10 +1198,7 @@ fn sandbox_with_services_uninitialized(
         validators
             .iter()
             .zip(service_keys.iter())
-            .map(|x| ValidatorKeys {
-                consensus_key: (x.0).0,
-                service_key: (x.1).0,
-            }),
+            .map(|x| ValidatorKeys::new((x.0).0, (x.1).0)),
     );
 
     let connect_list_config =
@@ -1126,6 +1220,12 @@ fn sandbox_with_services_uninitialized(
         network: NetworkConfiguration::default(),
         peer_discovery: Vec::new(),
         mempool: Default::default(),
+        retention: Default::default(),
+        observer_address: None,
+        clock,
+        alerts: Vec::new(),
+        resources: Default::default(),
+        consensus_archive: None,
     };
 
     let system_state = SandboxSystemStateProvider {
@@ -1203,7 +1303,7 @@ mod tests {
     use super::*;
     use crate::blockchain::{ExecutionResult, ServiceContext, TransactionContext, TransactionSet};
     use crate::crypto::{gen_keypair_from_seed, Seed};
-    use crate::messages::RawTransaction;
+    use crate::messages::{RawTransaction, ServiceMessage};
     use crate::proto::schema::tests::TxAfterCommit;
     use crate::sandbox::sandbox_tests_helper::{add_one_height, SandboxState};
     use exonum_merkledb::{BinaryValue, Snapshot};
@@ -1244,7 +1344,7 @@ mod tests {
             "after_commit"
         }
 
-        fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
             Vec::new()
         }
 
@@ -1259,6 +1359,38 @@ mod tests {
         }
     }
 
+    const ECHO_SERVICE_ID: u16 = 2;
+
+    /// Sends validator messages back to their authors.
+    struct EchoService;
+
+    impl Service for EchoService {
+        fn service_id(&self) -> u16 {
+            ECHO_SERVICE_ID
+        }
+
+        fn service_name(&self) -> &str {
+            "echo"
+        }
+
+        fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+            Vec::new()
+        }
+
+        fn tx_from_raw(&self, _: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            bail!("Echo service has no transactions")
+        }
+
+        fn handle_validator_message(
+            &self,
+            context: &ServiceContext,
+            from: ValidatorId,
+            payload: &[u8],
+        ) {
+            context.send_to_validator(from, payload.to_vec());
+        }
+    }
+
     #[test]
     fn test_sandbox_init() {
         timestamping_sandbox();
@@ -1272,10 +1404,7 @@ mod tests {
         // keypair.
         let (public, secret) = gen_keypair();
         let (service, _) = gen_keypair();
-        let validator_keys = ValidatorKeys {
-            consensus_key: public,
-            service_key: service,
-        };
+        let validator_keys = ValidatorKeys::new(public, service);
 
         let new_peer_addr = gen_primitive_socket_addr(2);
         // We also need to add public key from this keypair to the ConnectList.
@@ -1334,10 +1463,7 @@ mod tests {
         // See comments to `test_sandbox_recv_and_send`.
         let (public, secret) = gen_keypair();
         let (service, _) = gen_keypair();
-        let validator_keys = ValidatorKeys {
-            consensus_key: public,
-            service_key: service,
-        };
+        let validator_keys = ValidatorKeys::new(public, service);
         s.add_peer_to_connect_list(gen_primitive_socket_addr(1), validator_keys);
         s.recv(&s.create_connect(
             &public,
@@ -1365,10 +1491,7 @@ mod tests {
         // See comments to `test_sandbox_recv_and_send`.
         let (public, secret) = gen_keypair();
         let (service, _) = gen_keypair();
-        let validator_keys = ValidatorKeys {
-            consensus_key: public,
-            service_key: service,
-        };
+        let validator_keys = ValidatorKeys::new(public, service);
         s.add_peer_to_connect_list(gen_primitive_socket_addr(1), validator_keys);
         s.recv(&s.create_connect(
             &public,
@@ -1386,10 +1509,7 @@ mod tests {
         // See comments to `test_sandbox_recv_and_send`.
         let (public, secret) = gen_keypair();
         let (service, _) = gen_keypair();
-        let validator_keys = ValidatorKeys {
-            consensus_key: public,
-            service_key: service,
-        };
+        let validator_keys = ValidatorKeys::new(public, service);
         s.add_peer_to_connect_list(gen_primitive_socket_addr(1), validator_keys);
         s.recv(&s.create_connect(
             &public,
@@ -1415,10 +1535,7 @@ mod tests {
         // See comments to `test_sandbox_recv_and_send`.
         let (public, secret) = gen_keypair();
         let (service, _) = gen_keypair();
-        let validator_keys = ValidatorKeys {
-            consensus_key: public,
-            service_key: service,
-        };
+        let validator_keys = ValidatorKeys::new(public, service);
         s.add_peer_to_connect_list(gen_primitive_socket_addr(1), validator_keys);
         s.recv(&s.create_connect(
             &public,
@@ -1444,4 +1561,33 @@ mod tests {
         let tx = TxAfterCommit::new_with_height(Height(1));
         sandbox.broadcast(&tx);
     }
+
+    #[test]
+    fn test_sandbox_service_messages() {
+        let sandbox = SandboxBuilder::new()
+            .with_services(vec![
+                Box::new(EchoService),
+                Box::new(TimestampingService::new()),
+            ])
+            .build();
+        let message = |from: u16, validator: u16, to: u16| {
+            Message::concrete(
+                ServiceMessage::new(
+                    &sandbox.public_key(ValidatorId(to)),
+                    ValidatorId(validator),
+                    ECHO_SERVICE_ID,
+                    vec![1, 2, 3],
+                ),
+                sandbox.public_key(ValidatorId(from)),
+                sandbox.secret_key(ValidatorId(from)),
+            )
+        };
+
+        sandbox.recv(&message(1, 1, 0));
+        sandbox.send(sandbox.public_key(ValidatorId(1)), &message(0, 0, 1));
+
+        // Messages with a forged sender or addressed to other nodes are ignored.
+        sandbox.recv(&message(1, 2, 0));
+        sandbox.recv(&message(1, 1, 2));
+    }
 }
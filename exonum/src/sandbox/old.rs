@@ -15,13 +15,11 @@
 use std::time::Duration;
 
 use super::{
-    sandbox_tests_helper::{gen_timestamping_tx, NOT_LOCKED},
+    sandbox_tests_helper::{gen_timestamping_tx, BlockBuilder, NOT_LOCKED},
     timestamping_sandbox,
 };
-use crate::blockchain::Block;
 use crate::crypto::CryptoHash;
 use crate::helpers::{Height, Round, ValidatorId};
-use exonum_merkledb::HashTag;
 
 #[test]
 fn test_send_propose_and_prevote() {
@@ -95,14 +93,10 @@ fn test_get_lock_and_send_precommit() {
         sandbox.secret_key(ValidatorId(2)),
     );
 
-    let block = Block::new(
-        ValidatorId(2),
-        Height(1),
-        0,
-        &sandbox.last_hash(),
-        &HashTag::empty_list_hash(),
-        &sandbox.last_state_hash(),
-    );
+    let block = BlockBuilder::new(&sandbox)
+        .with_proposer_id(ValidatorId(2))
+        .with_height(Height(1))
+        .build();
 
     sandbox.recv(&propose);
     sandbox.broadcast(&sandbox.create_prevote(
@@ -155,14 +149,10 @@ fn test_commit() {
         sandbox.secret_key(ValidatorId(2)),
     );
 
-    let block = Block::new(
-        ValidatorId(2),
-        Height(1),
-        0,
-        &sandbox.last_hash(),
-        &HashTag::empty_list_hash(),
-        &sandbox.last_state_hash(),
-    );
+    let block = BlockBuilder::new(&sandbox)
+        .with_proposer_id(ValidatorId(2))
+        .with_height(Height(1))
+        .build();
 
     sandbox.recv(&propose);
     sandbox.broadcast(&sandbox.create_prevote(
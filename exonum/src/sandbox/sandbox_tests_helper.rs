@@ -19,11 +19,11 @@ use std::{cell::RefCell, collections::BTreeMap, time::Duration};
 
 use super::timestamping::DATA_SIZE;
 use super::{timestamping::TimestampingTxGenerator, Sandbox};
-use crate::blockchain::Block;
+use crate::blockchain::{AdditionalHeaders, Block, BlockTime, TransactionResult, TxResultsHash};
 use crate::crypto::{CryptoHash, Hash, HASH_SIZE};
 use crate::helpers::{Height, Milliseconds, Round, ValidatorId};
 use crate::messages::{
-    Precommit, Prevote, PrevotesRequest, Propose, ProposeRequest, RawTransaction, Signed,
+    Message, Precommit, Prevote, PrevotesRequest, Propose, ProposeRequest, RawTransaction, Signed,
 };
 use exonum_merkledb::{Database, HashTag, ObjectHash, ProofListIndex, TemporaryDB};
 
@@ -94,17 +94,32 @@ impl<'a> BlockBuilder<'a> {
     }
 
     pub fn build(&self) -> Block {
-        Block::new(
+        let tx_count = self.tx_count.unwrap_or(0);
+        // The `Propose` of the block is assumed to include the precommits known to the node.
+        let time = self.sandbox.next_block_time();
+        let block = Block::new(
             self.proposer_id
                 .unwrap_or_else(|| self.sandbox.current_leader()),
             self.height.unwrap_or_else(|| self.sandbox.current_height()),
-            self.tx_count.unwrap_or(0),
+            tx_count,
             &self.prev_hash.unwrap_or_else(|| self.sandbox.last_hash()),
             &self.tx_hash.unwrap_or_else(HashTag::empty_list_hash),
             &self
                 .state_hash
-                .unwrap_or_else(|| self.sandbox.last_state_hash()),
-        )
+                .unwrap_or_else(|| self.sandbox.compute_state_hash_at(time, &[])),
+        );
+
+        let mut headers = AdditionalHeaders::new();
+        if tx_count > 0 {
+            // Sandbox transactions are always executed successfully.
+            let result_hash = TransactionResult(Ok(())).object_hash();
+            let results = vec![result_hash; tx_count as usize];
+            headers.insert::<TxResultsHash>(compute_txs_merkle_root(&results));
+        }
+        if let Some(time) = time {
+            headers.insert::<BlockTime>(time);
+        }
+        block.with_additional_headers(headers)
     }
 }
 
@@ -116,6 +131,7 @@ pub struct ProposeBuilder<'a> {
     round: Option<Round>,
     prev_hash: Option<&'a Hash>,
     tx_hashes: Option<&'a [Hash]>,
+    precommits: Option<&'a [Signed<Precommit>]>,
 
     sandbox: &'a TimestampingSandbox,
 }
@@ -128,6 +144,7 @@ impl<'a> ProposeBuilder<'a> {
             round: None,
             prev_hash: None,
             tx_hashes: None,
+            precommits: None,
             sandbox,
         }
     }
@@ -157,18 +174,33 @@ impl<'a> ProposeBuilder<'a> {
         self
     }
 
+    pub fn with_precommits(mut self, precommits: &'a [Signed<Precommit>]) -> Self {
+        self.precommits = Some(precommits);
+        self
+    }
+
     pub fn build(&self) -> Signed<Propose> {
-        self.sandbox.create_propose(
-            self.validator_id
-                .unwrap_or_else(|| self.sandbox.current_leader()),
-            self.height.unwrap_or_else(|| self.sandbox.current_height()),
-            self.round.unwrap_or_else(|| self.sandbox.current_round()),
-            self.prev_hash.unwrap_or(&self.sandbox.last_hash()),
-            self.tx_hashes.unwrap_or(&[]),
-            self.sandbox.secret_key(
-                self.validator_id
-                    .unwrap_or_else(|| self.sandbox.current_leader()),
+        let validator_id = self
+            .validator_id
+            .unwrap_or_else(|| self.sandbox.current_leader());
+        let prev_hash = self
+            .prev_hash
+            .cloned()
+            .unwrap_or_else(|| self.sandbox.last_hash());
+        let precommits = self
+            .precommits
+            .map_or_else(|| self.sandbox.block_precommits(&prev_hash), <[_]>::to_vec);
+        Message::concrete(
+            Propose::new(
+                validator_id,
+                self.height.unwrap_or_else(|| self.sandbox.current_height()),
+                self.round.unwrap_or_else(|| self.sandbox.current_round()),
+                &prev_hash,
+                self.tx_hashes.unwrap_or(&[]),
+                &precommits,
             ),
+            self.sandbox.public_key(validator_id),
+            self.sandbox.secret_key(validator_id),
         )
     }
 }
@@ -103,8 +103,17 @@ impl Service for TimestampingService {
         "sandbox_timestamping"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
-        vec![Hash::new([127; HASH_SIZE]), Hash::new([128; HASH_SIZE])]
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
+        vec![
+            (
+                "sandbox_timestamping.first".to_owned(),
+                Hash::new([127; HASH_SIZE]),
+            ),
+            (
+                "sandbox_timestamping.second".to_owned(),
+                Hash::new([128; HASH_SIZE]),
+            ),
+        ]
     }
 
     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
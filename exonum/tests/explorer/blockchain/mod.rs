@@ -104,7 +104,7 @@ impl Service for MyService {
         "my-service"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         vec![]
     }
 
@@ -122,6 +122,12 @@ pub fn consensus_keys() -> (PublicKey, SecretKey) {
 
 /// Creates a blockchain with no blocks.
 pub fn create_blockchain() -> Blockchain {
+    create_blockchain_with_weights(&[1])
+}
+
+/// Creates a blockchain with validators of the given voting weights. The first validator
+/// uses the keys from `consensus_keys`, the other validators have random keys.
+pub fn create_blockchain_with_weights(weights: &[u64]) -> Blockchain {
     use exonum::blockchain::{GenesisConfig, ValidatorKeys};
 
     let (consensus_key, _) = consensus_keys();
@@ -133,15 +139,19 @@ pub fn create_blockchain() -> Blockchain {
         vec![MyService.into()],
         service_keys.0,
         service_keys.1,
-        ApiSender(api_channel.0),
+        ApiSender::new(api_channel.0),
     );
 
-    let keys = ValidatorKeys {
-        consensus_key,
-        service_key: service_keys.0,
-    };
+    let validators = weights.iter().enumerate().map(|(i, &weight)| {
+        let keys = if i == 0 {
+            ValidatorKeys::new(consensus_key, service_keys.0)
+        } else {
+            ValidatorKeys::new(crypto::gen_keypair().0, crypto::gen_keypair().0)
+        };
+        keys.with_weight(weight)
+    });
     blockchain
-        .initialize(GenesisConfig::new(vec![keys].into_iter()))
+        .initialize(GenesisConfig::new(validators))
         .unwrap();
     blockchain
 }
@@ -165,10 +175,14 @@ pub fn create_block(blockchain: &mut Blockchain, transactions: Vec<Signed<RawTra
     }
     blockchain.merge(fork.into_patch()).unwrap();
 
-    let (block_hash, patch) =
-        blockchain.create_patch(ValidatorId(0), height, &tx_hashes, &mut BTreeMap::new());
+    let (last_precommits, time) = {
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let last_precommits: Vec<_> = schema.precommits(&blockchain.last_hash()).iter().collect();
+        let time = schema.next_block_time(&last_precommits);
+        (last_precommits, time)
+    };
     let (consensus_public_key, consensus_secret_key) = consensus_keys();
-
     let propose = Message::concrete(
         Propose::new(
             ValidatorId(0),
@@ -176,10 +190,19 @@ pub fn create_block(blockchain: &mut Blockchain, transactions: Vec<Signed<RawTra
             Round::first(),
             &blockchain.last_hash(),
             &tx_hashes,
+            &last_precommits,
         ),
         consensus_public_key,
         &consensus_secret_key,
     );
+    let (block_hash, patch) = blockchain.create_patch_with_time(
+        ValidatorId(0),
+        height,
+        time,
+        &tx_hashes,
+        &mut BTreeMap::new(),
+    );
+
     let precommit = Message::concrete(
         Precommit::new(
             ValidatorId(0),
@@ -31,9 +31,11 @@ use exonum::{
     helpers::Height,
     messages::{self, Message, RawTransaction, Signed},
 };
+use exonum_merkledb::ObjectHash;
 
 use crate::blockchain::{
-    create_block, create_blockchain, CreateWallet, ExplorerTransactions, Transfer, SERVICE_ID,
+    consensus_keys, create_block, create_blockchain, create_blockchain_with_weights, CreateWallet,
+    ExplorerTransactions, Transfer, SERVICE_ID,
 };
 
 mod blockchain;
@@ -62,6 +64,7 @@ fn test_explorer_basics() {
         assert_eq!(explorer.height(), Height(0));
         let block = explorer.block(Height(0)).unwrap();
         assert_eq!(block.len(), 0);
+        assert_eq!(block.time(), None);
         assert!(block.transaction(0).is_none());
         assert!(explorer.transaction(&tx_alice.hash()).is_none());
     }
@@ -80,6 +83,9 @@ fn test_explorer_basics() {
 
         let block = explorer.block(Height(1)).unwrap();
         assert_eq!(block.len(), 1);
+        // The block following the genesis block has no consensus time.
+        assert_eq!(block.time(), None);
+        assert_eq!(block.header().time(), None);
         let tx_info = block.transaction(0).unwrap();
         assert_eq!(*tx_info.location(), TxLocation::new(Height(1), 0));
         assert_eq!(tx_info.status(), Ok(()));
@@ -113,6 +119,7 @@ fn test_explorer_basics() {
                 },
                 "location_proof": tx_info.location_proof(), // too complicated to check
                 "status": { "type": "success" },
+                "status_proof": tx_info.status_proof(),
                 "time": tx_info.time(),
             })
         );
@@ -130,7 +137,15 @@ fn test_explorer_basics() {
     let block = explorer.block(Height(2)).unwrap();
     assert_eq!(block.len(), 2);
 
+    // The consensus time is the median of the precommit times for the previous block.
+    let previous_block = explorer.block(Height(1)).unwrap();
+    let time = previous_block.precommits()[0].time();
+    assert_eq!(block.time(), Some(time));
+    assert_eq!(block.header().time(), Some(time));
+    assert_eq!(schema.last_block_time(), Some(time));
+
     let tx_info = block.transaction(0).unwrap();
+    assert_eq!(*tx_info.time(), time);
     let err = tx_info.status().unwrap_err();
     assert_eq!(err.error_type(), TransactionErrorType::Code(1));
     assert_eq!(err.description(), Some("Not allowed"));
@@ -152,10 +167,25 @@ fn test_explorer_basics() {
                 "code": 1,
                 "description": "Not allowed",
             },
+            "status_proof": tx_info.status_proof(),
             "time": tx_info.time(),
         })
     );
 
+    // The status proof pairs the transaction with its execution result.
+    let results_hash = block.header().tx_results_hash().unwrap();
+    let result_hash = schema
+        .transaction_results()
+        .get(&tx_bob.hash())
+        .unwrap()
+        .object_hash();
+    let proven = tx_info
+        .status_proof()
+        .unwrap()
+        .validate(results_hash, 2)
+        .unwrap();
+    assert_eq!(proven, vec![(0, &result_hash)]);
+
     let tx_info = block.transaction(1).unwrap();
     let err = tx_info.status().unwrap_err();
     assert_eq!(err.error_type(), TransactionErrorType::Panic);
@@ -177,6 +207,7 @@ fn test_explorer_basics() {
                 "type": "panic",
                 "description": "oops",
             },
+            "status_proof": tx_info.status_proof(),
             "time": tx_info.time(),
         })
     );
@@ -339,6 +370,196 @@ fn test_explorer_block_iter() {
     assert_eq!(iter.nth(2).unwrap().height(), Height(6));
 }
 
+#[test]
+fn test_explorer_block_iter_read_ahead() {
+    let mut blockchain = create_blockchain();
+    let mut tx_gen = tx_generator();
+    for height in 1..100 {
+        let txs = if height % 7 == 0 {
+            tx_gen.by_ref().take(2).collect()
+        } else {
+            vec![]
+        };
+        create_block(&mut blockchain, txs);
+    }
+
+    let explorer = BlockchainExplorer::new(&blockchain);
+    let expected: Vec<_> = (0..100)
+        .map(|height| explorer.block(Height(height)).unwrap().into_header())
+        .collect();
+    let headers: Vec<_> = explorer.blocks(..).map(BlockInfo::into_header).collect();
+    assert_eq!(headers, expected);
+    let headers: Vec<_> = explorer
+        .blocks(..)
+        .rev()
+        .map(BlockInfo::into_header)
+        .collect();
+    assert_eq!(headers, expected.iter().cloned().rev().collect::<Vec<_>>());
+
+    // Iteration from both ends meets in the middle without skipping or repeating blocks.
+    let mut iter = explorer.blocks(Height(3)..Height(90));
+    let mut heights = Vec::new();
+    while let Some(block) = iter.next() {
+        heights.push(block.height().0);
+        if let Some(block) = iter.next_back() {
+            heights.push(block.height().0);
+        }
+    }
+    heights.sort();
+    assert_eq!(heights, (3..90).collect::<Vec<_>>());
+
+    let mut iter = explorer.blocks(..);
+    assert_eq!(iter.next_back().unwrap().height(), Height(99));
+    assert_eq!(iter.nth(50).unwrap().height(), Height(50));
+    assert_eq!(iter.size_hint(), (48, Some(48)));
+    assert_eq!(iter.nth(47).unwrap().height(), Height(98));
+    assert!(iter.next().is_none());
+
+    let tx_hashes: Vec<Hash> = explorer
+        .blocks(..)
+        .with_transaction_hashes()
+        .flat_map(|block| block.transaction_hashes().to_vec())
+        .collect();
+    assert_eq!(tx_hashes.len(), 28);
+    let block = explorer.block(Height(14)).unwrap();
+    assert_eq!(&tx_hashes[2..4], &*block.transaction_hashes());
+}
+
+#[test]
+fn test_explorer_with_header_cache() {
+    let mut blockchain = create_blockchain();
+    let mut tx_gen = tx_generator();
+    create_block(&mut blockchain, vec![]); // Height(1)
+    create_block(&mut blockchain, tx_gen.by_ref().take(2).collect()); // Height(2)
+
+    let cache = BlockHeaderCache::new(DEFAULT_BLOCK_HEADER_CACHE_SIZE);
+    // The limit is too small to hold a single header.
+    let small_cache = BlockHeaderCache::new(1);
+    assert_eq!(small_cache.memory_limit(), 1);
+
+    let explorer = BlockchainExplorer::new(&blockchain).with_header_cache(cache.clone());
+    let uncached = BlockchainExplorer::new(&blockchain);
+    let headers: Vec<_> = explorer.blocks(..).map(BlockInfo::into_header).collect();
+    let expected: Vec<_> = uncached.blocks(..).map(BlockInfo::into_header).collect();
+    assert_eq!(headers, expected);
+    assert_eq!(cache.len(), 3);
+    assert_eq!(cache.get(Height(2)).as_ref(), Some(&expected[2]));
+
+    let block = explorer.block_with_txs(Height(2)).unwrap();
+    assert_eq!(block.header, expected[2]);
+    assert_eq!(block.len(), 2);
+    assert!(explorer.block_with_txs(Height(3)).is_none());
+    assert!(cache.get(Height(3)).is_none());
+
+    let small_explorer =
+        BlockchainExplorer::new(&blockchain).with_header_cache(small_cache.clone());
+    assert_eq!(small_explorer.block(Height(1)).unwrap().header(), &expected[1]);
+    assert!(small_cache.is_empty());
+    assert_eq!(small_cache.memory_usage(), 0);
+}
+
+#[test]
+fn test_header_cache_accounts_for_additional_headers() {
+    let mut blockchain = create_blockchain();
+    let mut tx_gen = tx_generator();
+    create_block(&mut blockchain, vec![]); // Height(1)
+    create_block(&mut blockchain, tx_gen.by_ref().take(2).collect()); // Height(2)
+
+    let snapshot = blockchain.snapshot();
+    let usage = |height: Height| {
+        let cache = BlockHeaderCache::new(DEFAULT_BLOCK_HEADER_CACHE_SIZE);
+        cache.get_or_load(snapshot.as_ref(), height).unwrap();
+        cache.memory_usage()
+    };
+    // The block with transactions has the `tx_results_hash` additional header.
+    let (empty_block_usage, block_usage) = (usage(Height(1)), usage(Height(2)));
+    assert!(empty_block_usage > 0);
+    assert!(block_usage > empty_block_usage);
+
+    // The limit fits the headers of the last two blocks, so the genesis one is evicted.
+    let cache = BlockHeaderCache::new(empty_block_usage + block_usage);
+    let explorer = BlockchainExplorer::new(&blockchain).with_header_cache(cache.clone());
+    assert_eq!(explorer.blocks(..).count(), 3);
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(Height(0)).is_none());
+    assert_eq!(cache.memory_usage(), cache.memory_limit());
+
+    cache.clear();
+    assert!(cache.is_empty());
+    assert_eq!(cache.memory_usage(), 0);
+}
+
+#[test]
+fn test_block_finality() {
+    let mut blockchain = create_blockchain();
+    create_block(&mut blockchain, vec![]); // Height(1)
+    create_block(&mut blockchain, vec![]); // Height(2)
+
+    let (consensus_key, _) = consensus_keys();
+    let expected_set_hash = crypto::hash(consensus_key.as_ref());
+    let explorer = BlockchainExplorer::new(&blockchain);
+
+    let finality = explorer.block(Height(1)).unwrap().finality();
+    assert_eq!(
+        finality,
+        BlockFinality {
+            is_final: true,
+            confirmations: 1,
+            precommits: 1,
+            precommits_weight: 1,
+            quorum: 1,
+            validators: 1,
+            validator_set_hash: expected_set_hash,
+        }
+    );
+
+    let genesis_finality = explorer.block(Height(0)).unwrap().finality();
+    assert!(genesis_finality.is_final);
+    assert_eq!(genesis_finality.confirmations, 2);
+    assert_eq!(genesis_finality.precommits, 0);
+    assert_eq!(genesis_finality.validator_set_hash, expected_set_hash);
+}
+
+#[test]
+fn test_block_finality_with_weighted_validators() {
+    // The single precommit of the first validator is enough for the quorum by weight,
+    // although the byzantine majority of two validators is two precommits.
+    let mut blockchain = create_blockchain_with_weights(&[3, 1]);
+    create_block(&mut blockchain, vec![]);
+
+    let explorer = BlockchainExplorer::new(&blockchain);
+    let finality = explorer.block(Height(1)).unwrap().finality();
+    assert_eq!(finality.precommits, 1);
+    assert_eq!(finality.precommits_weight, 3);
+    assert_eq!(finality.quorum, 3);
+    assert_eq!(finality.validators, 2);
+    assert!(finality.precommits_weight >= finality.quorum);
+}
+
+#[test]
+fn test_block_at_time() {
+    let mut blockchain = create_blockchain();
+    for _ in 0..4 {
+        create_block(&mut blockchain, vec![]);
+    }
+
+    let explorer = BlockchainExplorer::new(&blockchain);
+    let times: Vec<_> = (2..=4)
+        .map(|height| explorer.block(Height(height)).unwrap().time().unwrap())
+        .collect();
+    for time in &times {
+        let expected_height = times.iter().position(|t| t >= time).unwrap() as u64 + 2;
+        let block = explorer.block_at_time(*time).unwrap();
+        assert_eq!(block.height(), Height(expected_height));
+    }
+
+    // The first two blocks have no time and are never found.
+    let earlier = times[0] - chrono::Duration::seconds(1);
+    assert_eq!(explorer.block_at_time(earlier).unwrap().height(), Height(2));
+    let later = times[2] + chrono::Duration::seconds(1);
+    assert!(explorer.block_at_time(later).is_none());
+}
+
 #[test]
 fn test_transaction_iterator() {
     let mut blockchain = create_blockchain();
@@ -439,6 +660,26 @@ fn test_block_with_transactions() {
     assert!(all_transactions_create_wallets);
 }
 
+#[test]
+fn test_block_transactions_page() {
+    let mut blockchain = create_blockchain();
+    let txs: Vec<_> = tx_generator().take(5).collect();
+    let hashes: Vec<_> = txs.iter().map(Signed::hash).collect();
+    create_block(&mut blockchain, txs);
+
+    let explorer = BlockchainExplorer::new(&blockchain);
+    let (block, page) = explorer.block_transactions_page(Height(1), 2, 2).unwrap();
+    assert_eq!(page.list_len, u64::from(block.tx_count()));
+    assert_eq!(page.next_offset, Some(4));
+    let entries = page.validate(*block.tx_hash()).unwrap();
+    assert_eq!(entries, vec![(2, &hashes[2]), (3, &hashes[3])]);
+
+    let (_, last_page) = explorer.block_transactions_page(Height(1), 4, 2).unwrap();
+    assert_eq!(last_page.next_offset, None);
+    assert_eq!(last_page.validate(*block.tx_hash()).unwrap().len(), 1);
+    assert!(explorer.block_transactions_page(Height(2), 0, 2).is_none());
+}
+
 #[test]
 #[should_panic(expected = "Index exceeds number of transactions")]
 fn test_block_with_transactions_index_overflow() {
@@ -32,7 +32,7 @@ use exonum::{
     crypto::Hash,
     helpers,
     messages::RawTransaction,
-    node::{ApiSender, ExternalMessage, Node},
+    node::{ApiSender, ExternalMessage, Node, NodeBuilder},
 };
 
 struct CommitWatcherService(pub Mutex<Option<oneshot::Sender<()>>>);
@@ -46,7 +46,7 @@ impl Service for CommitWatcherService {
         "commit_watcher"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         Vec::new()
     }
 
@@ -72,7 +72,7 @@ impl Service for InitializeCheckerService {
         "initialize_checker"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         Vec::new()
     }
 
@@ -157,3 +157,23 @@ fn test_node_restart_regression() {
     start_node(node_cfg, db, Arc::clone(&init_times));
     assert_eq!(*init_times.lock().unwrap(), 1);
 }
+
+#[test]
+fn test_node_builder_run() {
+    let (commit_tx, commit_rx) = oneshot::channel();
+    let service = Box::new(CommitWatcherService(Mutex::new(Some(commit_tx))));
+    let handle = NodeBuilder::new()
+        .with_database(TemporaryDB::new())
+        .with_config(helpers::generate_testnet_config(1, 3_700).remove(0))
+        .with_service(service)
+        .run()
+        .unwrap();
+
+    let mut core = Core::new().unwrap();
+    let future = commit_rx
+        .into_future()
+        .timeout(Duration::from_secs(60))
+        .map_err(drop);
+    core.run(future).expect("failed commit");
+    handle.shutdown().unwrap();
+}
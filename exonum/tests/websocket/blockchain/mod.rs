@@ -105,7 +105,7 @@ impl Service for MyService {
         "my-service"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         vec![]
     }
 
@@ -34,7 +34,12 @@ use std::{
     time::{Duration, Instant},
 };
 
-use exonum::{api::websocket::*, crypto::gen_keypair, messages::Message, node::ExternalMessage};
+use exonum::{
+    api::{node::public::AuthorEvent, websocket::*},
+    crypto::gen_keypair,
+    messages::Message,
+    node::ExternalMessage,
+};
 
 mod blockchain;
 
@@ -342,6 +347,63 @@ fn test_transactions_subscribe_with_bad_filter() {
     node_handler.node_thread.join().unwrap();
 }
 
+#[test]
+fn test_transactions_subscribe_with_field_filter() {
+    let node_handler = run_node(6339, 8088);
+
+    let mut client =
+        create_ws_client("ws://localhost:8088/api/explorer/v1/ws").expect("Cannot connect to node");
+    client
+        .stream_ref()
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    // Subscribe to the wallets named "Alice" only.
+    let filter = TransactionFilter::new(SERVICE_ID, Some(0)).with_field("name", json!("Alice"));
+    let filters = serde_json::to_string(&json!({
+        "type": "set-subscriptions",
+        "payload": [{ "type": "transactions", "filter": filter }]
+    }))
+    .unwrap();
+    client.send_message(&OwnedMessage::Text(filters)).unwrap();
+    let resp_text = recv_text_msg(&mut client).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&resp_text).unwrap(),
+        json!({"result": "success"})
+    );
+
+    let http_client = reqwest::Client::new();
+    let send_tx = |name| {
+        let (pk, sk) = gen_keypair();
+        let tx = Message::sign_transaction(CreateWallet::new(&pk, name), SERVICE_ID, pk, &sk);
+        let tx_hash = tx.hash();
+        let _res = http_client
+            .post("http://localhost:8088/api/explorer/v1/transactions")
+            .json(&json!({ "tx_body": tx }))
+            .send()
+            .unwrap();
+        tx_hash
+    };
+
+    // The transaction with another name is filtered out by the node.
+    send_tx("Bob");
+    assert!(recv_text_msg(&mut client).is_none());
+
+    let tx_hash = send_tx("Alice");
+    let resp_text = recv_text_msg(&mut client).unwrap();
+    let notification = serde_json::from_str::<serde_json::Value>(&resp_text).unwrap();
+    assert_eq!(notification["type"], json!("transaction"));
+    assert_eq!(notification["tx_hash"], json!(tx_hash));
+
+    // Shutdown node.
+    client.shutdown().unwrap();
+    node_handler
+        .api_tx
+        .send_external_message(ExternalMessage::Shutdown)
+        .unwrap();
+    node_handler.node_thread.join().unwrap();
+}
+
 #[test]
 fn test_subscribe() {
     let node_handler = run_node(6336, 8085);
@@ -389,6 +451,71 @@ fn test_subscribe() {
     node_handler.node_thread.join().unwrap();
 }
 
+#[test]
+fn test_author_subscribe() {
+    let node_handler = run_node(6338, 8087);
+
+    let (pk, sk) = gen_keypair();
+    let mut client = create_ws_client(&format!(
+        "ws://localhost:8087/api/explorer/v1/authors/subscribe?author={}",
+        hex::encode(pk.as_ref())
+    ))
+    .expect("Cannot connect to node");
+    client
+        .stream_ref()
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    let http_client = reqwest::Client::new();
+    let send_tx = |tx| {
+        let _res = http_client
+            .post("http://localhost:8087/api/explorer/v1/transactions")
+            .json(&json!({ "tx_body": tx }))
+            .send()
+            .unwrap();
+    };
+    let mut recv_event = || {
+        let resp_text = recv_text_msg(&mut client).unwrap();
+        match serde_json::from_str::<Notification>(&resp_text).unwrap() {
+            Notification::AuthorTransaction(notification) => {
+                assert_eq!(notification.author, pk);
+                notification.event
+            }
+            other => panic!(
+                "Incorrect notification type (expected AuthorTransaction): {:?}",
+                other
+            ),
+        }
+    };
+
+    let tx = Message::sign_transaction(CreateWallet::new(&pk, "Alice"), SERVICE_ID, pk, &sk);
+    send_tx(tx);
+    assert_eq!(recv_event(), AuthorEvent::Pooled);
+    match recv_event() {
+        AuthorEvent::Committed { status, .. } => assert!(status.0.is_ok()),
+        other => panic!("Incorrect event (expected Committed): {:?}", other),
+    }
+
+    // Transactions of other authors are not reported.
+    let (other_pk, other_sk) = gen_keypair();
+    let tx = Message::sign_transaction(
+        CreateWallet::new(&other_pk, "Bob"),
+        SERVICE_ID,
+        other_pk,
+        &other_sk,
+    );
+    send_tx(tx);
+    assert!(recv_text_msg(&mut client).is_none());
+
+    // Shutdown node.
+    client.shutdown().unwrap();
+    node_handler
+        .api_tx
+        .send_external_message(ExternalMessage::Shutdown)
+        .unwrap();
+    node_handler.node_thread.join().unwrap();
+}
+
 #[test]
 fn test_node_shutdown_with_active_ws_client_should_not_wait_for_timeout() {
     let node_handler = run_node(6337, 8086);
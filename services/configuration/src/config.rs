@@ -17,7 +17,8 @@
 pub struct ConfigurationServiceConfig {
     /// Number of votes required to commit the new configuration.
     /// This value should be greater than 2/3 and less or equal to the
-    /// validators count.
+    /// validators count. Regardless of this value, the voting validators
+    /// must have the voting weight sufficient for a quorum.
     pub majority_count: Option<u16>,
 }
 
@@ -46,6 +46,19 @@ pub enum ErrorCode {
     ///
     /// Can be emitted by `Propose` or `Vote`.
     ActivationInPast = 3,
+    /// The configuration activates service features at invalid heights.
+    ///
+    /// Can be emitted by `Propose` or `Vote`.
+    InvalidFeatureActivation = 4,
+    /// The configuration specifies an invalid lifecycle state of a service, or stops
+    /// or freezes the configuration service itself.
+    ///
+    /// Can be emitted by `Propose` or `Vote`.
+    InvalidServiceState = 5,
+    /// The configuration specifies an invalid data version of a service.
+    ///
+    /// Can be emitted by `Propose` or `Vote`.
+    InvalidDataVersion = 6,
 
     /// The same configuration is already proposed.
     ///
@@ -89,6 +102,15 @@ pub(crate) enum Error {
     )]
     ActivationInPast(Height),
 
+    #[fail(display = "Invalid feature activation: {}", _0)]
+    InvalidFeatureActivation(String),
+
+    #[fail(display = "Invalid service state: {}", _0)]
+    InvalidServiceState(String),
+
+    #[fail(display = "Invalid data version: {}", _0)]
+    InvalidDataVersion(String),
+
     #[fail(display = "Already proposed; old proposal: {:?}", _0)]
     AlreadyProposed(Propose),
 
@@ -121,6 +143,9 @@ impl Error {
             UnknownSender => ErrorCode::UnknownSender,
             InvalidConfigRef(..) => ErrorCode::InvalidConfigRef,
             ActivationInPast(..) => ErrorCode::ActivationInPast,
+            InvalidFeatureActivation(..) => ErrorCode::InvalidFeatureActivation,
+            InvalidServiceState(..) => ErrorCode::InvalidServiceState,
+            InvalidDataVersion(..) => ErrorCode::InvalidDataVersion,
             AlreadyProposed(..) => ErrorCode::AlreadyProposed,
             InvalidConfig(..) => ErrorCode::InvalidConfig,
             InvalidMajorityCount { .. } => ErrorCode::InvalidMajorityCount,
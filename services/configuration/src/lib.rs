@@ -92,7 +92,7 @@ use exonum_merkledb::{Fork, Snapshot};
 
 use exonum::{
     api::ServiceApiBuilder,
-    blockchain::{self, Transaction, TransactionSet},
+    blockchain::{self, Namespace, Transaction, TransactionSet},
     crypto::Hash,
     helpers::fabric::{self, keys, Command, CommandExtension, CommandName, Context},
     messages::RawTransaction,
@@ -134,15 +134,23 @@ impl blockchain::Service for Service {
         SERVICE_NAME
     }
 
-    fn state_hash(&self, snapshot: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
         let schema = Schema::new(snapshot);
-        schema.state_hash()
+        schema.state_hash_tables()
     }
 
     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
         ConfigurationTransactions::tx_from_raw(raw).map(Into::into)
     }
 
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    fn namespace(&self) -> Option<Namespace> {
+        Some(Namespace::new(SERVICE_NAME).grant_configuration_tables())
+    }
+
     fn initialize(&self, _fork: &Fork) -> Value {
         to_value(self.config.clone()).unwrap()
     }
@@ -257,4 +257,19 @@ where
             self.config_hash_by_ordinal().object_hash(),
         ]
     }
+
+    /// Returns state hash values used by the configuration service together with
+    /// the table names.
+    pub fn state_hash_tables(&self) -> Vec<(String, Hash)> {
+        vec![
+            (
+                PROPOSES.to_owned(),
+                self.propose_data_by_config_hash().object_hash(),
+            ),
+            (
+                PROPOSE_HASHES.to_owned(),
+                self.config_hash_by_ordinal().object_hash(),
+            ),
+        ]
+    }
 }
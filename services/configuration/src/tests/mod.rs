@@ -276,6 +276,54 @@ fn test_apply_with_increased_majority() {
     );
 }
 
+#[test]
+fn test_votes_are_counted_by_weight() {
+    let mut testkit = TestKit::configuration_default();
+
+    // The first validator outweighs the other three validators together.
+    let new_cfg = {
+        let mut cfg = testkit.configuration_change_proposal();
+        cfg.set_actual_from(Height(5));
+        let mut stored = cfg.stored_configuration().clone();
+        for (keys, &weight) in stored.validator_keys.iter_mut().zip(&[3, 1, 1, 1]) {
+            keys.weight = weight;
+        }
+        stored
+    };
+    testkit.apply_configuration(ValidatorId(0), new_cfg);
+
+    let cfg_change_height = Height(10);
+    let new_cfg = {
+        let mut cfg = testkit.configuration_change_proposal();
+        cfg.set_service_config("dummy", "Weighted cfg");
+        cfg.set_actual_from(cfg_change_height);
+        cfg.stored_configuration().clone()
+    };
+    let validators = testkit.network().validators().to_vec();
+    let tx_propose = new_tx_config_propose(&validators[1], new_cfg.clone());
+    testkit.create_block_with_transactions(txvec![tx_propose]);
+
+    // Three votes are the byzantine majority of four validators, but their weight
+    // is less than the quorum weight of `5`.
+    let tx_votes = validators[1..]
+        .iter()
+        .map(|validator| new_tx_config_vote(validator, new_cfg.hash()))
+        .collect::<Vec<_>>();
+    testkit.create_block_with_transactions(tx_votes);
+    assert_eq!(
+        Schema::new(&testkit.snapshot()).following_configuration(),
+        None
+    );
+
+    let tx_vote = new_tx_config_vote(&validators[0], new_cfg.hash());
+    testkit.create_block_with_transactions(txvec![tx_vote]);
+    testkit.create_blocks_until(cfg_change_height);
+    assert_eq!(
+        Schema::new(&testkit.snapshot()).actual_configuration(),
+        new_cfg
+    );
+}
+
 #[test]
 fn test_discard_proposes_with_too_big_majority_count() {
     let mut testkit = TestKitBuilder::validator()
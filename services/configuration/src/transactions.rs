@@ -17,9 +17,13 @@ use exonum_merkledb::{Fork, ObjectHash, Snapshot};
 
 use exonum::{
     blockchain::{
-        ExecutionResult, Schema as CoreSchema, StoredConfiguration, Transaction, TransactionContext,
+        features,
+        lifecycle::{self, ServiceState},
+        migration, ExecutionResult, Schema as CoreSchema, StoredConfiguration, Transaction,
+        TransactionContext,
     },
     crypto::{CryptoHash, Hash, PublicKey, SecretKey},
+    helpers::ValidatorId,
     messages::{Message, RawTransaction, Signed},
     node::State,
 };
@@ -153,21 +157,30 @@ fn validator_index(snapshot: &dyn Snapshot, key: &PublicKey) -> Option<usize> {
 }
 
 /// Checks if there is enough votes for a particular configuration hash.
+///
+/// The validators voting for the configuration must have the voting weight sufficient
+/// for a quorum. If the service configuration specifies `majority_count`, the number
+/// of votes must also be not less than it.
 fn enough_votes_to_commit(snapshot: &Fork, cfg_hash: &Hash) -> bool {
     let actual_config = CoreSchema::new(snapshot).actual_configuration();
 
     let schema = Schema::new(snapshot);
     let votes = schema.votes_by_config_hash(cfg_hash);
-    let votes_count = votes.iter().filter(MaybeVote::is_consent).count();
+    let voters = votes
+        .iter()
+        .enumerate()
+        .filter(|(_, vote)| vote.is_consent())
+        .map(|(index, _)| ValidatorId(index as u16))
+        .collect::<Vec<_>>();
 
     let config: ConfigurationServiceConfig = get_service_config(&actual_config);
+    if let Some(majority_count) = config.majority_count {
+        if voters.len() < majority_count as usize {
+            return false;
+        }
+    }
 
-    let majority_count = match config.majority_count {
-        Some(majority_count) => majority_count as usize,
-        _ => State::byzantine_majority_count(actual_config.validator_keys.len()),
-    };
-
-    votes_count >= majority_count
+    actual_config.has_majority(voters)
 }
 
 fn get_service_config(config: &StoredConfiguration) -> ConfigurationServiceConfig {
@@ -232,6 +245,21 @@ impl Propose {
             return Err(ActivationInPast(current_height));
         }
 
+        features::check_activations(&actual_config, candidate, snapshot)
+            .map_err(|e| InvalidFeatureActivation(e.to_string()))?;
+
+        lifecycle::check_states(candidate).map_err(|e| InvalidServiceState(e.to_string()))?;
+        let own_state = lifecycle::configured_state(candidate, SERVICE_NAME)
+            .map_err(|e| InvalidServiceState(e.to_string()))?;
+        if own_state != ServiceState::Active {
+            return Err(InvalidServiceState(format!(
+                "the configuration service cannot be {}",
+                own_state
+            )));
+        }
+
+        migration::check_versions(candidate).map_err(|e| InvalidDataVersion(e.to_string()))?;
+
         let config: ConfigurationServiceConfig = get_service_config(candidate);
 
         if let Some(proposed_majority_count) = config.majority_count.map(|count| count as usize) {
@@ -57,9 +57,12 @@ impl<T: IndexAccess> MarkerSchema<T> {
         ProofMapIndex::new(format!("{}.marks", SERVICE_NAME), self.access.clone())
     }
 
-    /// Returns hashes for stored table.
-    pub fn state_hash(&self) -> Vec<Hash> {
-        vec![self.marks().object_hash()]
+    /// Returns hashes for stored table together with the table name.
+    pub fn state_hash_tables(&self) -> Vec<(String, Hash)> {
+        vec![(
+            format!("{}.marks", SERVICE_NAME),
+            self.marks().object_hash(),
+        )]
     }
 }
 
@@ -110,9 +113,9 @@ impl Service for MarkerService {
         SERVICE_NAME
     }
 
-    fn state_hash(&self, snapshot: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
         let schema = MarkerSchema::new(snapshot);
-        schema.state_hash()
+        schema.state_hash_tables()
     }
 
     fn service_id(&self) -> u16 {
@@ -48,7 +48,7 @@ use exonum_merkledb::{Fork, Snapshot};
 
 use exonum::{
     api::ServiceApiBuilder,
-    blockchain::{Service, ServiceContext, Transaction, TransactionSet},
+    blockchain::{Namespace, Service, ServiceContext, Transaction, TransactionSet},
     crypto::Hash,
     helpers::fabric::{Context, ServiceFactory},
     messages::RawTransaction,
@@ -104,15 +104,23 @@ impl Service for TimeService {
         SERVICE_NAME
     }
 
-    fn state_hash(&self, snapshot: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, snapshot: &dyn Snapshot) -> Vec<(String, Hash)> {
         let schema = TimeSchema::new(snapshot);
-        schema.state_hash()
+        schema.state_hash_tables()
     }
 
     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
         TimeTransactions::tx_from_raw(raw).map(Into::into)
     }
 
+    fn is_builtin(&self) -> bool {
+        true
+    }
+
+    fn namespace(&self) -> Option<Namespace> {
+        Some(Namespace::new(SERVICE_NAME))
+    }
+
     fn initialize(&self, _fork: &Fork) -> Value {
         Value::Null
     }
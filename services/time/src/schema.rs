@@ -17,6 +17,9 @@ use chrono::{DateTime, Utc};
 use exonum::crypto::{Hash, PublicKey};
 use exonum_merkledb::{Entry, IndexAccess, ObjectHash, ProofMapIndex};
 
+const VALIDATORS_TIMES: &str = "exonum_time.validators_times";
+const TIME: &str = "exonum_time.time";
+
 /// `Exonum-time` service database schema.
 #[derive(Debug)]
 pub struct TimeSchema<T> {
@@ -31,16 +34,27 @@ impl<T: IndexAccess> TimeSchema<T> {
 
     /// Returns the table that stores `DateTime` for every validator.
     pub fn validators_times(&self) -> ProofMapIndex<T, PublicKey, DateTime<Utc>> {
-        ProofMapIndex::new("exonum_time.validators_times", self.access.clone())
+        ProofMapIndex::new(VALIDATORS_TIMES, self.access.clone())
     }
 
     /// Returns stored time.
     pub fn time(&self) -> Entry<T, DateTime<Utc>> {
-        Entry::new("exonum_time.time", self.access.clone())
+        Entry::new(TIME, self.access.clone())
     }
 
     /// Returns hashes for stored tables.
     pub fn state_hash(&self) -> Vec<Hash> {
         vec![self.validators_times().object_hash(), self.time().hash()]
     }
+
+    /// Returns hashes for stored tables together with the table names.
+    pub fn state_hash_tables(&self) -> Vec<(String, Hash)> {
+        vec![
+            (
+                VALIDATORS_TIMES.to_owned(),
+                self.validators_times().object_hash(),
+            ),
+            (TIME.to_owned(), self.time().hash()),
+        ]
+    }
 }
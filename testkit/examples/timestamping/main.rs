@@ -73,7 +73,7 @@ impl Service for TimestampingService {
         "timestamping"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         Vec::new()
     }
 
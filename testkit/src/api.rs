@@ -24,7 +24,6 @@ use std::fmt::{self, Display};
 
 use exonum::{
     api::{self, ApiAggregator, ServiceApiState},
-    blockchain::SharedNodeState,
     messages::{RawTransaction, Signed},
     node::ApiSender,
 };
@@ -74,7 +73,7 @@ impl TestKitApi {
     /// Creates a new instance of API.
     pub fn new(testkit: &TestKit) -> Self {
         Self::from_raw_parts(
-            ApiAggregator::new(testkit.blockchain().clone(), SharedNodeState::new(10_000)),
+            ApiAggregator::new(testkit.blockchain().clone(), testkit.api_state().clone()),
             testkit.api_sender.clone(),
         )
     }
@@ -293,6 +292,7 @@ where
             StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => Err(api::Error::Unauthorized),
             StatusCode::BAD_REQUEST => Err(api::Error::BadRequest(error(response))),
             StatusCode::NOT_FOUND => Err(api::Error::NotFound(error(response))),
+            StatusCode::SERVICE_UNAVAILABLE => Err(api::Error::ServiceUnavailable(error(response))),
             s if s.is_server_error() => Err(api::Error::InternalError(format_err!(
                 "{}",
                 error(response)
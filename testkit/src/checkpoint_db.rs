@@ -14,7 +14,10 @@
 
 use exonum_merkledb::{Database, Patch, Result as StorageResult, Snapshot, View};
 
-use std::sync::{Arc, RwLock};
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
 
 /// Implementation of a `Database`, which allows to rollback its state
 /// to the last made checkpoint.
@@ -61,6 +64,14 @@ impl<T: Database> Database for CheckpointDb<T> {
     fn merge_sync(&self, patch: Patch) -> StorageResult<()> {
         self.merge(patch)
     }
+
+    fn create_checkpoint(&self, path: &Path) -> StorageResult<()> {
+        self.inner
+            .read()
+            .expect("Cannot lock CheckpointDb for checkpoint")
+            .db
+            .create_checkpoint(path)
+    }
 }
 
 impl<T: Database> From<CheckpointDb<T>> for Arc<dyn Database> {
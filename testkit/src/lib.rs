@@ -74,7 +74,7 @@
 //!         "timestamping"
 //!     }
 //!
-//!     fn state_hash(&self, _: &Snapshot) -> Vec<Hash> {
+//!     fn state_hash_tables(&self, _: &Snapshot) -> Vec<(String, Hash)> {
 //!         Vec::new()
 //!     }
 //!
@@ -181,12 +181,15 @@ use exonum::{
         backends::actix::{ApiRuntimeConfig, SystemRuntimeConfig},
         ApiAccess,
     },
-    blockchain::{Blockchain, GenesisConfig, Schema as CoreSchema, Service, StoredConfiguration},
+    blockchain::{
+        Blockchain, GenesisConfig, Schema as CoreSchema, Service, SharedNodeState,
+        StoredConfiguration,
+    },
     crypto::{self, Hash},
     explorer::{BlockWithTransactions, BlockchainExplorer},
     helpers::{Height, ValidatorId},
     messages::{RawTransaction, Signed},
-    node::{ApiSender, ExternalMessage, State as NodeState},
+    node::{ApiSender, ExternalMessage},
 };
 
 use crate::checkpoint_db::{CheckpointDb, CheckpointDbHandler};
@@ -264,7 +267,7 @@ mod server;
 /// #    fn service_name(&self) -> &str {
 /// #        "documentation"
 /// #    }
-/// #    fn state_hash(&self, _: &exonum_merkledb::Snapshot) -> Vec<exonum::crypto::Hash> {
+/// #    fn state_hash_tables(&self, _: &exonum_merkledb::Snapshot) -> Vec<(String, exonum::crypto::Hash)> {
 /// #        Vec::new()
 /// #    }
 /// #    fn service_id(&self) -> u16 {
@@ -395,6 +398,7 @@ pub struct TestKit {
     processing_lock: Arc<Mutex<()>>,
     network: TestNetwork,
     api_sender: ApiSender,
+    api_state: SharedNodeState,
     cfg_proposal: Option<ConfigurationProposalState>,
 }
 
@@ -454,9 +458,23 @@ impl TestKit {
                             schema.add_transaction_into_pool(tx.clone());
                         }
                     }
+                    ExternalMessage::TaggedTransaction { transaction, tag } => {
+                        let hash = transaction.hash();
+                        if !schema.transactions().contains(&hash) {
+                            schema.add_transaction_into_pool(transaction);
+                            schema.transaction_tags().put(&hash, tag.into_bytes());
+                        }
+                    }
+                    ExternalMessage::RebroadcastTransaction { tx_hash, reply } => {
+                        // The testkit has no peers to send the transaction to.
+                        let in_pool = schema.transactions_pool().contains(&tx_hash);
+                        let _ = reply.send(if in_pool { Some(0) } else { None });
+                    }
                     ExternalMessage::PeerAdd(_)
                     | ExternalMessage::Enable(_)
                     | ExternalMessage::Rebroadcast
+                    | ExternalMessage::ServiceMessage { .. }
+                    | ExternalMessage::ConsensusState(_)
                     | ExternalMessage::Shutdown => { /* Ignored */ }
                 }
                 blockchain.merge(fork.into_patch()).unwrap();
@@ -469,6 +487,7 @@ impl TestKit {
             blockchain,
             db_handler,
             api_sender,
+            api_state: SharedNodeState::new(10_000).with_storage_inspection(true),
             events_stream,
             processing_lock,
             network,
@@ -497,6 +516,12 @@ impl TestKit {
         &self.blockchain
     }
 
+    /// Returns the state shared by the API handlers of the testkit, including
+    /// the caches of the explorer.
+    pub(crate) fn api_state(&self) -> &SharedNodeState {
+        &self.api_state
+    }
+
     /// Returns a blockchain instance for low level manipulations with storage.
     pub fn blockchain_mut(&mut self) -> &mut Blockchain {
         &mut self.blockchain
@@ -534,7 +559,7 @@ impl TestKit {
     /// #    fn service_name(&self) -> &str {
     /// #        "documentation"
     /// #    }
-    /// #    fn state_hash(&self, _: &exonum_merkledb::Snapshot) -> Vec<exonum::crypto::Hash> {
+    /// #    fn state_hash_tables(&self, _: &exonum_merkledb::Snapshot) -> Vec<(String, exonum::crypto::Hash)> {
     /// #        Vec::new()
     /// #    }
     /// #    fn service_id(&self) -> u16 {
@@ -592,7 +617,10 @@ impl TestKit {
     /// # }
     /// ```
     pub fn rollback(&mut self) {
-        self.db_handler.rollback()
+        self.db_handler.rollback();
+        // The explorer may have cached the headers and proofs of the dropped blocks.
+        self.api_state.block_header_cache().clear();
+        self.api_state.block_proof_cache().clear();
     }
 
     /// Executes a list of transactions given the current state of the blockchain, but does not
@@ -633,11 +661,22 @@ impl TestKit {
         let last_hash = self.last_block_hash();
 
         let config_patch = self.update_configuration(new_block_height);
+        let (last_precommits, time) = {
+            let snapshot = self.snapshot();
+            let schema = CoreSchema::new(&snapshot);
+            let last_precommits: Vec<_> = schema.precommits(&last_hash).iter().collect();
+            let time = schema.next_block_time(&last_precommits);
+            (last_precommits, time)
+        };
+        let propose =
+            self.leader()
+                .create_propose(new_block_height, &last_hash, tx_hashes, &last_precommits);
         let (block_hash, patch) = {
             let validator_id = self.leader().validator_id().unwrap();
-            self.blockchain.create_patch(
+            self.blockchain.create_patch_with_time(
                 validator_id,
                 new_block_height,
+                time,
                 tx_hashes,
                 &mut BTreeMap::new(),
             )
@@ -652,9 +691,6 @@ impl TestKit {
             patch
         };
 
-        let propose = self
-            .leader()
-            .create_propose(new_block_height, &last_hash, tx_hashes);
         let precommits: Vec<_> = self
             .network()
             .validators()
@@ -892,9 +928,15 @@ impl TestKit {
         &self.network.validators()[id.0 as usize]
     }
 
-    /// Returns sufficient number of validators for the Byzantine Fault Tolerance consensus.
+    /// Returns sufficient number of validators for the Byzantine Fault Tolerance consensus,
+    /// i.e., the number of the first validators having more than `2/3` of the total voting
+    /// weight. If all validators have equal weights, this is the byzantine majority of them.
     pub fn majority_count(&self) -> usize {
-        NodeState::byzantine_majority_count(self.network().validators().len())
+        let config = self.actual_configuration();
+        let validators_count = config.validator_keys.len();
+        (0..validators_count)
+            .find(|&count| config.has_majority((0..count).map(|id| ValidatorId(id as u16))))
+            .unwrap_or(validators_count)
     }
 
     /// Returns the leader on the current height. At the moment first validator.
@@ -1087,7 +1129,7 @@ impl TestKit {
 ///
 /// impl Service for AfterCommitService {
 /// #   fn service_name(&self) -> &str { "after_commit" }
-/// #   fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> { Vec::new() }
+/// #   fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> { Vec::new() }
 /// #   fn service_id(&self) -> u16 { 100 }
 /// #   fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
 /// #       unimplemented!()
@@ -163,12 +163,14 @@ impl TestNode {
         }
     }
 
-    /// Creates a `Propose` message signed by this validator.
+    /// Creates a `Propose` message signed by this validator. The `Propose` includes
+    /// the given precommits for the previous block.
     pub fn create_propose(
         &self,
         height: Height,
         last_hash: &crypto::Hash,
         tx_hashes: &[crypto::Hash],
+        precommits: &[Signed<Precommit>],
     ) -> Signed<Propose> {
         Message::concrete(
             Propose::new(
@@ -178,6 +180,7 @@ impl TestNode {
                 Round::first(),
                 last_hash,
                 tx_hashes,
+                precommits,
             ),
             self.consensus_public_key,
             &self.consensus_secret_key,
@@ -209,10 +212,7 @@ impl TestNode {
 
     /// Returns public keys of the node.
     pub fn public_keys(&self) -> ValidatorKeys {
-        ValidatorKeys {
-            consensus_key: self.consensus_public_key,
-            service_key: self.service_public_key,
-        }
+        ValidatorKeys::new(self.consensus_public_key, self.service_public_key)
     }
 
     /// Returns the current validator id of node if it is validator of the test network.
@@ -14,7 +14,6 @@
 
 use exonum::{
     api::{self, ApiAggregator, ServiceApiBuilder, ServiceApiScope, ServiceApiState},
-    blockchain::SharedNodeState,
     crypto::Hash,
     explorer::{BlockWithTransactions, BlockchainExplorer},
     helpers::Height,
@@ -146,10 +145,10 @@ pub fn create_testkit_handlers(inner: &Arc<RwLock<TestKit>>) -> ServiceApiBuilde
 
 /// Creates an ApiAggregator with the testkit server specific handlers.
 pub fn create_testkit_api_aggregator(testkit: &Arc<RwLock<TestKit>>) -> ApiAggregator {
-    let mut aggregator = ApiAggregator::new(
-        testkit.read().unwrap().blockchain().clone(),
-        SharedNodeState::new(10_000),
-    );
+    let mut aggregator = {
+        let testkit = testkit.read().unwrap();
+        ApiAggregator::new(testkit.blockchain().clone(), testkit.api_state().clone())
+    };
     aggregator.insert("testkit", create_testkit_handlers(testkit));
     aggregator
 }
@@ -215,7 +214,7 @@ mod tests {
                 "sample"
             }
 
-            fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+            fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
                 Vec::new()
             }
 
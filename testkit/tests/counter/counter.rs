@@ -231,7 +231,7 @@ impl Service for CounterService {
         "counter"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         Vec::new()
     }
 
@@ -485,6 +485,7 @@ fn test_explorer_blocks_basic() {
                 "prev_hash": blocks[1].block.hash(),
                 "tx_hash": HashTag::empty_list_hash(),
                 "state_hash": blocks[0].block.state_hash(),
+                "additional_headers": blocks[0].block.additional_headers(),
                 "precommits": [precommit],
             }],
         })
@@ -505,6 +506,7 @@ fn test_explorer_blocks_basic() {
                 "prev_hash": blocks[1].block.hash(),
                 "tx_hash": HashTag::empty_list_hash(),
                 "state_hash": blocks[0].block.state_hash(),
+                "additional_headers": blocks[0].block.additional_headers(),
                 "time": precommit.time(),
             }],
         })
@@ -744,6 +746,74 @@ fn test_explorer_single_block() {
     }
 }
 
+#[test]
+fn test_explorer_block_proof() {
+    use exonum::blockchain::{BlockProof, Schema};
+
+    let (mut testkit, api) = init_testkit();
+    testkit.create_block_with_transaction({
+        let (pubkey, key) = crypto::gen_keypair();
+        TxIncrement::sign(&pubkey, 5, &key)
+    });
+
+    let expected = Schema::new(&testkit.snapshot())
+        .block_and_precommits(Height(1))
+        .unwrap();
+    // The second request is served from the cache.
+    for _ in 0..2 {
+        let proof: BlockProof = api
+            .public(ApiKind::Explorer)
+            .get("v1/block/proof?height=1")
+            .unwrap();
+        assert_eq!(proof, expected);
+    }
+
+    let error = api
+        .public(ApiKind::Explorer)
+        .get::<BlockProof>("v1/block/proof?height=2")
+        .unwrap_err();
+    assert_matches!(error, ApiError::NotFound(_));
+}
+
+#[test]
+fn test_explorer_caches_are_cleared_on_rollback() {
+    use exonum::api::node::public::explorer::BlockInfo;
+    use exonum::blockchain::{BlockProof, Schema};
+
+    let (mut testkit, api) = init_testkit();
+    let (pubkey, key) = crypto::gen_keypair();
+    testkit.checkpoint();
+    testkit.create_block_with_transaction(TxIncrement::sign(&pubkey, 5, &key));
+    // Fill the caches of the explorer with the block to be rolled back.
+    let dropped: BlockProof = api
+        .public(ApiKind::Explorer)
+        .get("v1/block/proof?height=1")
+        .unwrap();
+    let dropped_info: BlockInfo = api
+        .public(ApiKind::Explorer)
+        .get("v1/block?height=1")
+        .unwrap();
+    assert_eq!(dropped_info.block, dropped.block);
+
+    testkit.rollback();
+    testkit.create_block_with_transaction(TxIncrement::sign(&pubkey, 3, &key));
+    let expected = Schema::new(&testkit.snapshot())
+        .block_and_precommits(Height(1))
+        .unwrap();
+    assert_ne!(expected.block, dropped.block);
+
+    let proof: BlockProof = api
+        .public(ApiKind::Explorer)
+        .get("v1/block/proof?height=1")
+        .unwrap();
+    assert_eq!(proof, expected);
+    let info: BlockInfo = api
+        .public(ApiKind::Explorer)
+        .get("v1/block?height=1")
+        .unwrap();
+    assert_eq!(info.block, expected.block);
+}
+
 #[test]
 fn test_explorer_transaction_info() {
     use exonum::explorer::{BlockchainExplorer, TransactionInfo};
@@ -869,6 +939,143 @@ fn test_explorer_transaction_statuses() {
     check_statuses(&statuses);
 }
 
+#[test]
+fn test_explorer_transaction_status() {
+    use exonum::api::node::public::explorer::TransactionStatus;
+
+    let (mut testkit, api) = init_testkit();
+    let get_status = |hash: &crypto::Hash, query: &str| -> Value {
+        api.public(ApiKind::Explorer)
+            .get(&format!("v1/transactions/{}/status{}", hash.to_hex(), query))
+            .unwrap()
+    };
+
+    let tx = {
+        let (pubkey, key) = crypto::gen_keypair();
+        TxIncrement::sign(&pubkey, 5, &key)
+    };
+    assert_eq!(get_status(&tx.hash(), ""), json!({ "type": "unknown" }));
+
+    testkit.add_tx(tx.clone());
+    assert_eq!(get_status(&tx.hash(), ""), json!({ "type": "in-pool" }));
+
+    testkit.create_block();
+    let status: TransactionStatus = serde_json::from_value(get_status(&tx.hash(), "")).unwrap();
+    assert_matches!(
+        status,
+        TransactionStatus::Committed { ref location, location_proof: None, .. }
+            if location.block_height() == Height(1)
+    );
+
+    let explorer = testkit.explorer();
+    let committed = explorer.transaction(&tx.hash()).unwrap();
+    let committed = committed.as_committed().unwrap();
+    assert_eq!(
+        get_status(&tx.hash(), "?with_proof=true"),
+        json!({
+            "type": "committed",
+            "location": committed.location(),
+            "status": { "type": "success" },
+            "location_proof": committed.location_proof(),
+        })
+    );
+
+    let error = api
+        .public(ApiKind::Explorer)
+        .get::<Value>("v1/transactions/not-a-hash/status")
+        .unwrap_err();
+    assert_matches!(error, ApiError::BadRequest(_));
+}
+
+#[test]
+fn test_explorer_transaction_hash() {
+    use exonum::api::node::public::explorer::{self, TransactionHex};
+
+    let (testkit, api) = init_testkit();
+    let tx = {
+        let (pubkey, key) = crypto::gen_keypair();
+        TxIncrement::sign(&pubkey, 5, &key)
+    };
+    let query = TransactionHex::new(hex::encode(tx.signed_message().raw()));
+    let response: explorer::TransactionResponse = api
+        .public(ApiKind::Explorer)
+        .query(&query)
+        .post("v1/transactions/hash")
+        .unwrap();
+    assert_eq!(response.tx_hash, tx.hash());
+    // The transaction is not submitted.
+    assert!(testkit.explorer().transaction(&tx.hash()).is_none());
+
+    let mut tampered = tx.signed_message().raw().to_vec();
+    *tampered.last_mut().unwrap() ^= 1;
+    let error = api
+        .public(ApiKind::Explorer)
+        .query(&TransactionHex::new(hex::encode(&tampered)))
+        .post::<Value>("v1/transactions/hash")
+        .unwrap_err();
+    assert_matches!(error, ApiError::BadRequest(_));
+}
+
+#[test]
+fn test_explorer_transaction_tag() {
+    use exonum::api::node::public::explorer::{TransactionHex, TransactionStatus};
+    use exonum::blockchain::{TransactionTag, MAX_TRANSACTION_TAG_SIZE};
+    use exonum::explorer::TransactionInfo;
+
+    let (mut testkit, api) = init_testkit();
+    let tx = {
+        let (pubkey, key) = crypto::gen_keypair();
+        TxIncrement::sign(&pubkey, 5, &key)
+    };
+    let tag = TransactionTag::new(b"invoice-42".to_vec()).unwrap();
+    let query = TransactionHex {
+        tag: Some(tag.clone()),
+        ..TransactionHex::new(hex::encode(tx.signed_message().raw()))
+    };
+    let _: Value = api
+        .public(ApiKind::Explorer)
+        .query(&query)
+        .post("v1/transactions")
+        .unwrap();
+    testkit.poll_events();
+
+    let info = testkit.explorer().transaction(&tx.hash()).unwrap();
+    assert_matches!(info, TransactionInfo::InPool { tag: Some(ref t), .. } if *t == tag);
+
+    testkit.create_block();
+    let info: TransactionInfo = api
+        .public(ApiKind::Explorer)
+        .query(&TransactionQuery::new(tx.hash()))
+        .get("v1/transactions")
+        .unwrap();
+    assert_eq!(info.as_committed().unwrap().tag(), Some(&tag));
+    let status: TransactionStatus = api
+        .public(ApiKind::Explorer)
+        .get(&format!("v1/transactions/{}/status", tx.hash().to_hex()))
+        .unwrap();
+    assert_matches!(status, TransactionStatus::Committed { tag: Some(ref t), .. } if *t == tag);
+
+    // Transactions submitted without a tag are not tagged.
+    let untagged = {
+        let (pubkey, key) = crypto::gen_keypair();
+        TxIncrement::sign(&pubkey, 6, &key)
+    };
+    testkit.create_block_with_transaction(untagged.clone());
+    let info = testkit.explorer().transaction(&untagged.hash()).unwrap();
+    assert_eq!(info.as_committed().unwrap().tag(), None);
+
+    let too_long = json!({
+        "tx_body": hex::encode(tx.signed_message().raw()),
+        "tag": "00".repeat(MAX_TRANSACTION_TAG_SIZE + 1),
+    });
+    let error = api
+        .public(ApiKind::Explorer)
+        .query(&too_long)
+        .post::<Value>("v1/transactions")
+        .unwrap_err();
+    assert_matches!(error, ApiError::BadRequest(_));
+}
+
 // Make sure that boxed transaction can be used in the `TestKitApi::send`.
 #[test]
 fn test_boxed_tx() {
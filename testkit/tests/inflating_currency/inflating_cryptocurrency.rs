@@ -228,7 +228,7 @@ impl Service for CurrencyService {
         "cryptocurrency"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         Vec::new()
     }
 
@@ -16,7 +16,8 @@
 use super::proto;
 use exonum::{
     blockchain::{
-        ExecutionResult, Service, ServiceContext, Transaction, TransactionContext, TransactionSet,
+        Block, BlockSummary, ExecutionResult, Schema, Service, ServiceContext, Transaction,
+        TransactionContext, TransactionSet,
     },
     crypto::Hash,
     helpers::Height,
@@ -26,7 +27,7 @@ use exonum_merkledb::Snapshot;
 
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 
 pub const SERVICE_ID: u16 = 512;
@@ -57,6 +58,9 @@ impl Transaction for TxAfterCommit {
 #[derive(Clone, Default)]
 pub struct AfterCommitService {
     counter: Arc<AtomicUsize>,
+    leader_only: bool,
+    committed_blocks: Arc<Mutex<Vec<(Block, Option<Hash>)>>>,
+    block_summaries: Arc<Mutex<Vec<BlockSummary>>>,
 }
 
 impl AfterCommitService {
@@ -64,9 +68,28 @@ impl AfterCommitService {
         Self::default()
     }
 
+    /// Creates a service broadcasting transactions only when the node is the leader.
+    pub fn leader_only() -> Self {
+        Self {
+            leader_only: true,
+            ..Self::default()
+        }
+    }
+
     pub fn counter(&self) -> usize {
         self.counter.load(Ordering::SeqCst)
     }
+
+    /// Returns the blocks passed to `after_commit` together with the hashes of the blocks
+    /// at the same height in the snapshot of the context.
+    pub fn committed_blocks(&self) -> Vec<(Block, Option<Hash>)> {
+        self.committed_blocks.lock().unwrap().clone()
+    }
+
+    /// Returns the summaries passed to `after_commit_block`.
+    pub fn block_summaries(&self) -> Vec<BlockSummary> {
+        self.block_summaries.lock().unwrap().clone()
+    }
 }
 
 impl Service for AfterCommitService {
@@ -78,7 +101,7 @@ impl Service for AfterCommitService {
         "after_commit"
     }
 
-    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+    fn state_hash_tables(&self, _: &dyn Snapshot) -> Vec<(String, Hash)> {
         Vec::new()
     }
 
@@ -89,7 +112,21 @@ impl Service for AfterCommitService {
 
     fn after_commit(&self, context: &ServiceContext) {
         self.counter.fetch_add(1, Ordering::SeqCst);
+        let block = context.last_block().clone();
+        let block_hash = Schema::new(context.snapshot()).block_hash_by_height(block.height());
+        self.committed_blocks
+            .lock()
+            .unwrap()
+            .push((block, block_hash));
         let tx = TxAfterCommit::new(context.height());
-        context.broadcast_transaction(tx);
+        if self.leader_only {
+            context.broadcast_if_leader(tx);
+        } else {
+            context.broadcast_transaction(tx);
+        }
+    }
+
+    fn after_commit_block(&self, _context: &ServiceContext, summary: &BlockSummary) {
+        self.block_summaries.lock().unwrap().push(summary.clone());
     }
 }
@@ -20,7 +20,7 @@ extern crate exonum_derive;
 // HACK: Silent "dead_code" warning.
 pub use crate::hooks::{AfterCommitService, HandleCommitTransactions, TxAfterCommit, SERVICE_ID};
 
-use exonum::{blockchain::TransactionSet, helpers::Height, messages::Message};
+use exonum::{blockchain::TransactionSet, crypto::CryptoHash, helpers::Height, messages::Message};
 use exonum_testkit::TestKitBuilder;
 
 mod hooks;
@@ -62,6 +62,89 @@ fn test_after_commit() {
     assert!(expected_block_sizes);
 }
 
+#[test]
+fn test_after_commit_last_block() {
+    let service = AfterCommitService::new();
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(service.clone())
+        .create();
+
+    let blocks = (0..3)
+        .map(|_| testkit.create_block().header)
+        .collect::<Vec<_>>();
+    let committed_blocks = service.committed_blocks();
+    assert_eq!(committed_blocks.len(), blocks.len());
+    for (block, (committed_block, snapshot_hash)) in blocks.iter().zip(committed_blocks) {
+        assert_eq!(*block, committed_block);
+        assert_eq!(snapshot_hash, Some(block.hash()));
+    }
+}
+
+#[test]
+fn test_after_commit_block_summary() {
+    let service = AfterCommitService::new();
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(service.clone())
+        .create();
+
+    let blocks = (0..3).map(|_| testkit.create_block()).collect::<Vec<_>>();
+    let summaries = service.block_summaries();
+    assert_eq!(summaries.len(), blocks.len());
+    for (block, summary) in blocks.iter().zip(&summaries) {
+        assert_eq!(*summary.block(), block.header);
+        let tx_hashes = summary
+            .transactions()
+            .iter()
+            .map(|tx| tx.tx_hash)
+            .collect::<Vec<_>>();
+        let expected_hashes = block
+            .transactions
+            .iter()
+            .map(|tx| tx.content().message().hash())
+            .collect::<Vec<_>>();
+        assert_eq!(tx_hashes, expected_hashes);
+        assert!(summary.transactions().iter().all(|tx| tx.result.0.is_ok()));
+        assert_eq!(
+            summary.transactions_of_type(0).count(),
+            summary.transactions().len()
+        );
+        assert_eq!(summary.transactions_of_type(1).count(), 0);
+    }
+    assert!(summaries[0].transactions().is_empty());
+    assert_eq!(summaries[1].transactions().len(), 1);
+}
+
+#[test]
+fn test_broadcast_if_leader() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(AfterCommitService::leader_only())
+        .create();
+    testkit.create_blocks_until(Height(8));
+
+    // Our node is the validator 0, so it is the leader at every fourth height.
+    for i in 1..8 {
+        let tx = Message::sign_transaction(
+            TxAfterCommit::new(Height(i)),
+            SERVICE_ID,
+            testkit.blockchain().service_keypair.0,
+            &testkit.blockchain().service_keypair.1,
+        );
+        let committed = testkit
+            .explorer()
+            .transaction_without_proof(&tx.hash())
+            .is_some();
+        assert_eq!(committed, i == 4, "height {}", i);
+    }
+    let tx = Message::sign_transaction(
+        TxAfterCommit::new(Height(8)),
+        SERVICE_ID,
+        testkit.blockchain().service_keypair.0,
+        &testkit.blockchain().service_keypair.1,
+    );
+    assert!(testkit.is_tx_in_pool(&tx.hash()));
+}
+
 #[test]
 fn restart_testkit() {
     let mut testkit = TestKitBuilder::validator()
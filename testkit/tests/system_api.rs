@@ -17,11 +17,23 @@ extern crate pretty_assertions;
 
 use exonum::{
     api::node::{
-        private::NodeInfo,
-        public::system::{ConsensusStatus, HealthCheckInfo, StatsInfo},
+        private::{
+            NodeInfo, RejectedTransactionsQuery, StorageEntriesQuery, StorageEntry,
+            StorageIndexInfo,
+        },
+        public::system::{ConsensusStatus, HealthCheckInfo, ServiceIdsResponse, StatsInfo},
+    },
+    api::node::public::explorer::{TransactionHex, TransactionResponse},
+    blockchain::RESERVED_SERVICE_IDS,
+    crypto,
+    helpers::{
+        log_filters::{LogFilters, LogFiltersUpdate, LogLevel},
+        user_agent,
+    },
+    messages::{SignatureCheck, TestVector, PROTOCOL_MAJOR_VERSION},
+    node::{
+        QuorumImpact, RejectedTransaction, RejectionReason, TransactionSource, ValidatorSetChange,
     },
-    helpers::user_agent,
-    messages::PROTOCOL_MAJOR_VERSION,
 };
 use exonum_testkit::{ApiKind, TestKitBuilder};
 
@@ -39,6 +51,9 @@ fn healthcheck() {
     let expected = HealthCheckInfo {
         consensus_status: ConsensusStatus::Enabled,
         connected_peers: 0,
+        degraded_by: Vec::new(),
+        stopping: false,
+        overloaded: false,
     };
     assert_eq!(info, expected);
 }
@@ -67,6 +82,16 @@ fn user_agent_info() {
     assert_eq!(info, expected);
 }
 
+#[test]
+fn service_ids() {
+    let testkit = TestKitBuilder::validator().create();
+    let api = testkit.api();
+
+    let info: ServiceIdsResponse = api.public(ApiKind::System).get("v1/services/ids").unwrap();
+    assert_eq!(info.reserved, RESERVED_SERVICE_IDS);
+    assert!(info.services.is_empty());
+}
+
 #[test]
 fn network() {
     let testkit = TestKitBuilder::validator().with_validators(2).create();
@@ -103,3 +128,185 @@ fn rebroadcast() {
         ()
     )
 }
+
+#[test]
+fn log_filters() {
+    let testkit = TestKitBuilder::validator().with_logger().create();
+    let api = testkit.api();
+
+    let mut update = LogFiltersUpdate::default();
+    update
+        .targets
+        .insert("exonum::node::consensus".to_owned(), Some(LogLevel::Debug));
+    let filters: LogFilters = api
+        .private(ApiKind::System)
+        .query(&update)
+        .post("v1/log_filters")
+        .unwrap();
+    assert_eq!(filters.level("exonum::node::consensus"), LogLevel::Debug);
+
+    let current: LogFilters = api.private(ApiKind::System).get("v1/log_filters").unwrap();
+    assert_eq!(current, filters);
+
+    update
+        .targets
+        .insert("exonum::node::consensus".to_owned(), None);
+    let filters: LogFilters = api
+        .private(ApiKind::System)
+        .query(&update)
+        .post("v1/log_filters")
+        .unwrap();
+    assert!(!filters.targets.contains_key("exonum::node::consensus"));
+
+    let update = LogFiltersUpdate {
+        spec: Some("exonum=verbose".to_owned()),
+        ..LogFiltersUpdate::default()
+    };
+    let err = api
+        .private(ApiKind::System)
+        .query(&update)
+        .post::<LogFilters>("v1/log_filters")
+        .unwrap_err();
+    assert!(err.to_string().contains("Unknown log level"), "{}", err);
+}
+
+#[test]
+fn test_vectors_and_signature_check() {
+    let testkit = TestKitBuilder::validator().create();
+    let api = testkit.api();
+
+    let vectors: Vec<TestVector> = api.public(ApiKind::System).get("v1/test-vectors").unwrap();
+    assert!(!vectors.is_empty());
+
+    let query = TransactionHex::new(hex::encode(&vectors[0].message));
+    let check: SignatureCheck = api
+        .public(ApiKind::System)
+        .query(&query)
+        .post("v1/verify-signature")
+        .unwrap();
+    assert!(check.signature_valid);
+    assert_eq!(check.hash, vectors[0].hash);
+    assert_eq!(check.parts.as_ref(), Some(&vectors[0].parts));
+    // The testkit has no service with the identifier used by the vector.
+    assert_eq!(check.errors.len(), 1);
+    assert!(check.errors[0].contains("Service not found"));
+}
+
+#[test]
+fn rejected_transactions() {
+    let testkit = TestKitBuilder::validator().create();
+    let api = testkit.api();
+
+    let vectors: Vec<TestVector> = api.public(ApiKind::System).get("v1/test-vectors").unwrap();
+    let mut message = vectors[0].message.clone();
+    *message.last_mut().unwrap() ^= 1;
+    let tx_hash = crypto::hash(&message);
+
+    let response = api
+        .public(ApiKind::Explorer)
+        .query(&TransactionHex::new(hex::encode(&message)))
+        .post::<TransactionResponse>("v1/transactions");
+    assert!(response.is_err());
+
+    let rejected: Vec<RejectedTransaction> = api
+        .private(ApiKind::System)
+        .get("v1/rejected_transactions")
+        .unwrap();
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0].tx_hash, tx_hash);
+    assert_eq!(rejected[0].reason, RejectionReason::InvalidSignature);
+    assert_eq!(rejected[0].source, TransactionSource::Api);
+
+    let rejected: Vec<RejectedTransaction> = api
+        .private(ApiKind::System)
+        .query(&RejectedTransactionsQuery {
+            hash: Some(crypto::hash(&[])),
+        })
+        .get("v1/rejected_transactions")
+        .unwrap();
+    assert!(rejected.is_empty());
+}
+
+#[test]
+fn quorum_simulation() {
+    let testkit = TestKitBuilder::validator().with_validators(4).create();
+    let api = testkit.api();
+    let removed = testkit.network().validators()[1]
+        .public_keys()
+        .consensus_key;
+
+    let impact: QuorumImpact = api
+        .private(ApiKind::System)
+        .query(&ValidatorSetChange {
+            add: vec![],
+            remove: vec![removed],
+        })
+        .post("v1/quorum/simulate")
+        .unwrap();
+    assert_eq!(impact.current.validators, 4);
+    assert_eq!(impact.current.quorum, 3);
+    assert_eq!(impact.current.tolerated_failures, 1);
+    assert_eq!(impact.proposed.validators, 3);
+    assert_eq!(impact.proposed.quorum, 3);
+    assert_eq!(impact.proposed.tolerated_failures, 0);
+
+    let response = api
+        .private(ApiKind::System)
+        .query(&ValidatorSetChange {
+            add: vec![],
+            remove: vec![crypto::gen_keypair().0],
+        })
+        .post::<QuorumImpact>("v1/quorum/simulate");
+    assert!(response.is_err());
+}
+
+#[test]
+fn storage_inspection() {
+    let testkit = TestKitBuilder::validator().create();
+    let api = testkit.api();
+
+    let indexes: Vec<StorageIndexInfo> = api
+        .private(ApiKind::System)
+        .get("v1/storage/indexes")
+        .unwrap();
+    let block_hashes = indexes
+        .iter()
+        .find(|index| index.name == "core.block_hashes_by_height")
+        .unwrap();
+    assert_eq!(block_hashes.family_id, None);
+    assert_eq!(block_hashes.entries, 1);
+
+    let genesis_entry = StorageEntry {
+        key: hex::encode(&[0_u8; 8]),
+        value: hex::encode(testkit.last_block_hash()),
+    };
+    let entries: Vec<StorageEntry> = api
+        .private(ApiKind::System)
+        .query(&StorageEntriesQuery {
+            index: "core.block_hashes_by_height".to_owned(),
+            ..Default::default()
+        })
+        .get("v1/storage/entries")
+        .unwrap();
+    assert_eq!(entries, vec![genesis_entry.clone()]);
+
+    let entries: Vec<StorageEntry> = api
+        .private(ApiKind::System)
+        .query(&StorageEntriesQuery {
+            index: "core.block_hashes_by_height".to_owned(),
+            key: Some(genesis_entry.key.clone()),
+            ..Default::default()
+        })
+        .get("v1/storage/entries")
+        .unwrap();
+    assert_eq!(entries, vec![genesis_entry]);
+
+    let response = api
+        .private(ApiKind::System)
+        .query(&StorageEntriesQuery {
+            index: "core.unknown".to_owned(),
+            ..Default::default()
+        })
+        .get::<Vec<StorageEntry>>("v1/storage/entries");
+    assert!(response.is_err());
+}